@@ -0,0 +1,187 @@
+//! Interactive tool-use approval for agent runs
+//!
+//! Tools listed in an agent type's `approval_tools` are gated on every run,
+//! whoever starts it; streamed runs may gate more (see
+//! `AgentExecutor::with_tool_approval`). The executor installs a
+//! `ToolApprovalGate` as the cc-sdk permission callback (other backends call
+//! `ToolApprovalGate::check` directly). Calls to a gated tool emit an
+//! `ApprovalRequired` stream event, with secrets redacted from the input, and
+//! block until `POST /api/agent-runs/:session_id/approve-tool` resolves the
+//! request. Runs nobody can approve from, such as pipeline automation, have
+//! gated calls denied.
+
+use async_trait::async_trait;
+use cc_sdk::{CanUseTool, PermissionResult, PermissionResultAllow, PermissionResultDeny, ToolPermissionContext};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::time::Duration;
+use tokio::sync::{mpsc, oneshot, Mutex};
+
+use super::backend::redact_json;
+use super::StreamEvent;
+
+/// How long a gated tool call waits for a decision before it is denied
+const APPROVAL_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ToolApprovalDecision {
+    pub approved: bool,
+    /// Optional explanation passed back to the agent on denial
+    pub message: Option<String>,
+}
+
+/// Pending approval requests keyed by session_id, then request_id
+pub struct PendingApprovals {
+    pending: Mutex<HashMap<String, HashMap<String, oneshot::Sender<ToolApprovalDecision>>>>,
+}
+
+impl PendingApprovals {
+    pub fn new() -> Self {
+        Self {
+            pending: Mutex::new(HashMap::new()),
+        }
+    }
+
+    async fn register(&self, session_id: &str, request_id: &str) -> oneshot::Receiver<ToolApprovalDecision> {
+        let (tx, rx) = oneshot::channel();
+        self.pending
+            .lock()
+            .await
+            .entry(session_id.to_string())
+            .or_default()
+            .insert(request_id.to_string(), tx);
+        rx
+    }
+
+    async fn remove(&self, session_id: &str, request_id: &str) -> Option<oneshot::Sender<ToolApprovalDecision>> {
+        let mut pending = self.pending.lock().await;
+        let requests = pending.get_mut(session_id)?;
+        let sender = requests.remove(request_id);
+        if requests.is_empty() {
+            pending.remove(session_id);
+        }
+        sender
+    }
+
+    /// Deliver a decision for a pending request. Returns false if no such request is waiting.
+    pub async fn resolve(&self, session_id: &str, request_id: &str, decision: ToolApprovalDecision) -> bool {
+        match self.remove(session_id, request_id).await {
+            Some(sender) => sender.send(decision).is_ok(),
+            None => false,
+        }
+    }
+}
+
+lazy_static::lazy_static! {
+    pub static ref PENDING_APPROVALS: PendingApprovals = PendingApprovals::new();
+}
+
+/// Approval settings for a single agent run
+#[derive(Debug, Clone)]
+pub struct ToolApprovalConfig {
+    /// Session the approval endpoint is addressed with; gated calls are
+    /// denied without one
+    pub session_id: Option<String>,
+    /// Tool names that require explicit approval (e.g. "Bash", "Write")
+    pub tools: Vec<String>,
+}
+
+/// cc-sdk permission callback that pauses on gated tools
+pub struct ToolApprovalGate {
+    config: ToolApprovalConfig,
+    event_tx: Option<mpsc::Sender<StreamEvent>>,
+    /// Organization secrets, redacted from the inputs shown for approval
+    env: HashMap<String, String>,
+}
+
+impl ToolApprovalGate {
+    pub fn new(
+        config: ToolApprovalConfig,
+        event_tx: Option<mpsc::Sender<StreamEvent>>,
+        env: &HashMap<String, String>,
+    ) -> Self {
+        Self { config, event_tx, env: env.clone() }
+    }
+}
+
+impl ToolApprovalGate {
+    /// Wait for a decision on a tool call if the tool is gated.
+    /// `Err` carries the denial message shown to the agent.
+    pub async fn check(&self, tool_name: &str, input: &serde_json::Value) -> Result<(), String> {
+        if !self.config.tools.iter().any(|t| t == tool_name) {
+            return Ok(());
+        }
+
+        // Without a stream and a session nobody can see or answer the request,
+        // so deny outright
+        let (Some(tx), Some(session_id)) = (&self.event_tx, &self.config.session_id) else {
+            return Err(format!("{} requires approval but no client is attached", tool_name));
+        };
+
+        let request_id = uuid::Uuid::new_v4().to_string();
+        let rx = PENDING_APPROVALS.register(session_id, &request_id).await;
+
+        tracing::info!(
+            "Tool {} awaiting approval (session={}, request={})",
+            tool_name, session_id, request_id
+        );
+        let _ = tx.send(StreamEvent::ApprovalRequired {
+            request_id: request_id.clone(),
+            tool_name: tool_name.to_string(),
+            input: redact_json(&self.env, input),
+        }).await;
+
+        let decision = match tokio::time::timeout(APPROVAL_TIMEOUT, rx).await {
+            Ok(Ok(decision)) => decision,
+            Ok(Err(_)) => ToolApprovalDecision {
+                approved: false,
+                message: Some("Approval request was dropped".to_string()),
+            },
+            Err(_) => {
+                PENDING_APPROVALS.remove(session_id, &request_id).await;
+                ToolApprovalDecision {
+                    approved: false,
+                    message: Some("Approval timed out".to_string()),
+                }
+            }
+        };
+
+        let _ = tx.send(StreamEvent::Status {
+            status: "running".to_string(),
+            message: Some(format!(
+                "{} {}",
+                tool_name,
+                if decision.approved { "approved" } else { "denied" }
+            )),
+        }).await;
+
+        if decision.approved {
+            Ok(())
+        } else {
+            Err(decision
+                .message
+                .unwrap_or_else(|| format!("User denied {}", tool_name)))
+        }
+    }
+}
+
+#[async_trait]
+impl CanUseTool for ToolApprovalGate {
+    async fn can_use_tool(
+        &self,
+        tool_name: &str,
+        input: &serde_json::Value,
+        _context: &ToolPermissionContext,
+    ) -> PermissionResult {
+        match self.check(tool_name, input).await {
+            Ok(()) => PermissionResult::Allow(PermissionResultAllow {
+                updated_input: None,
+                updated_permissions: None,
+            }),
+            Err(message) => PermissionResult::Deny(PermissionResultDeny {
+                message,
+                interrupt: false,
+            }),
+        }
+    }
+}
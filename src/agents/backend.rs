@@ -0,0 +1,90 @@
+//! Agent backends
+//!
+//! `AgentExecutor` builds the prompt and post-processes the run; a backend
+//! does the actual model conversation. Claude Code (via cc-sdk) is the
+//! default; agent types can be moved to an OpenAI-compatible backend, with an
+//! optional fallback, via `backend`/`fallback_backend` in agents.json.
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::path::Path;
+use tokio::sync::mpsc;
+
+use super::approvals::ToolApprovalConfig;
+use super::claude_code::ClaudeCodeBackend;
+use super::openai_compat::OpenAiCompatBackend;
+use super::{AgentRunStatus, AgentType, AgentsConfig, BackendConfig, RunUsage, StreamEvent, ToolCall, CLAUDE_CODE_BACKEND};
+
+/// Everything a backend needs for one run
+pub struct BackendRequest<'a> {
+    /// Session id assigned by the executor
+    pub session_id: &'a str,
+    pub agent_type: &'a AgentType,
+    pub system_prompt: &'a str,
+    pub prompt: &'a str,
+    pub working_dir: &'a Path,
+    /// Organization secrets, passed to the agent and redacted from its output
+    pub env: &'a HashMap<String, String>,
+    /// Non-secret variables the pipeline step sets on the agent process. Only
+    /// backends that run a process use them.
+    pub step_env: &'a HashMap<String, String>,
+    pub approval: Option<&'a ToolApprovalConfig>,
+}
+
+/// What a backend produced. `status` stays `Running` when the backend never
+/// reported an outcome; the executor then infers it from the output.
+pub struct BackendOutput {
+    /// Backend-side session id, if it has one
+    pub session_id: Option<String>,
+    pub output_parts: Vec<String>,
+    pub status: AgentRunStatus,
+    pub usage: RunUsage,
+    /// Why the backend stopped the run itself (e.g. the loop guard). Such
+    /// runs are not retried on the fallback backend.
+    pub error: Option<String>,
+    /// Every tool call made, see `agents::tool_audit`
+    pub tool_calls: Vec<ToolCall>,
+}
+
+#[async_trait]
+pub trait AgentBackend: Send + Sync {
+    fn name(&self) -> &str;
+
+    async fn run(
+        &self,
+        request: &BackendRequest<'_>,
+        event_tx: Option<mpsc::Sender<StreamEvent>>,
+    ) -> Result<BackendOutput>;
+}
+
+/// Instantiate a backend by its agents.json name
+pub fn backend_by_name(name: &str) -> Result<Box<dyn AgentBackend>> {
+    if name == CLAUDE_CODE_BACKEND {
+        return Ok(Box::new(ClaudeCodeBackend));
+    }
+    match AgentsConfig::get().backends.get(name) {
+        Some(BackendConfig::OpenaiCompatible { base_url, api_key_env, model, max_turns }) => {
+            let api_key = match api_key_env {
+                Some(var) => Some(std::env::var(var).map_err(|_| anyhow!("{} is not set for backend '{}'", var, name))?),
+                None => None,
+            };
+            Ok(Box::new(OpenAiCompatBackend::new(name, base_url, api_key, model, *max_turns)))
+        }
+        None => Err(anyhow!("Unknown agent backend '{}'", name)),
+    }
+}
+
+/// Replace any secret value in `text` with a placeholder.
+pub fn redact(env: &HashMap<String, String>, text: &str) -> String {
+    env.values()
+        .filter(|v| v.len() >= 4)
+        .fold(text.to_string(), |acc, secret| acc.replace(secret.as_str(), "[REDACTED]"))
+}
+
+pub fn redact_json(env: &HashMap<String, String>, value: &serde_json::Value) -> serde_json::Value {
+    if env.is_empty() {
+        return value.clone();
+    }
+    serde_json::from_str(&redact(env, &value.to_string())).unwrap_or_else(|_| value.clone())
+}
@@ -0,0 +1,369 @@
+//! Claude Code backend: runs the agent through the Claude Code CLI via cc-sdk
+
+use anyhow::Result;
+use async_trait::async_trait;
+use cc_sdk::{query, ClaudeCodeOptions, ContentBlock, Message, ToolsConfig};
+use futures::StreamExt;
+use std::sync::Arc;
+use tokio::sync::mpsc;
+
+use super::approvals::ToolApprovalGate;
+use super::deltas::TextDeltas;
+use super::backend::{redact, redact_json, AgentBackend, BackendOutput, BackendRequest};
+use super::loops::{Intervention, LoopDetector};
+use super::tool_audit::ToolCallLog;
+use super::{AgentRunStatus, RunUsage, StreamEvent, CLAUDE_CODE_BACKEND};
+
+pub struct ClaudeCodeBackend;
+
+#[async_trait]
+impl AgentBackend for ClaudeCodeBackend {
+    fn name(&self) -> &str {
+        CLAUDE_CODE_BACKEND
+    }
+
+    async fn run(
+        &self,
+        request: &BackendRequest<'_>,
+        event_tx: Option<mpsc::Sender<StreamEvent>>,
+    ) -> Result<BackendOutput> {
+        let agent_type = request.agent_type;
+
+        // Build cc-sdk options using builder pattern
+        let tools_list: Vec<String> = agent_type
+            .allowed_tools()
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+
+        tracing::info!("Working dir: {:?}", request.working_dir);
+        tracing::info!("Tools config: {:?}", tools_list);
+        tracing::info!("Max turns: {:?}", agent_type.max_turns());
+
+        // Gated tools stay available but are not auto-approved, so cc-sdk
+        // routes them through the permission callback
+        let auto_approved: Vec<String> = match request.approval {
+            Some(approval) => tools_list
+                .iter()
+                .filter(|t| !approval.tools.contains(t))
+                .cloned()
+                .collect(),
+            None => tools_list.clone(),
+        };
+
+        // Build options. A loop reminder resumes the session, so options are
+        // rebuilt per query.
+        let build_options = |resume: Option<String>| {
+            // Use ToolsConfig to actually restrict which tools are available (not just auto-approval)
+            let mut builder = ClaudeCodeOptions::builder()
+                .system_prompt(request.system_prompt)
+                .model(agent_type.model())
+                .tools(ToolsConfig::list(tools_list.clone()))
+                .allowed_tools(auto_approved.clone()) // Also auto-approve these tools
+                .cwd(request.working_dir);
+
+            // Only set max_turns if configured (otherwise unlimited)
+            if let Some(turns) = agent_type.max_turns() {
+                builder = builder.max_turns(turns);
+            }
+            if let Some(session_id) = resume {
+                builder = builder.resume(session_id);
+            }
+
+            let mut options = builder.build();
+            options.env.extend(request.env.clone());
+            options.env.extend(request.step_env.clone());
+            // Partial messages only matter when someone is watching the stream
+            options.include_partial_messages = event_tx.is_some();
+
+            if let Some(approval) = request.approval {
+                options.can_use_tool = Some(Arc::new(ToolApprovalGate::new(approval.clone(), event_tx.clone(), request.env)));
+            }
+            options
+        };
+        if let Some(approval) = request.approval {
+            tracing::info!("Tool approval required for: {:?}", approval.tools);
+        }
+
+        // Execute using query() - simple and reliable
+        let mut output_parts = Vec::new();
+        let mut status = AgentRunStatus::Running;
+        let mut actual_session_id = None;
+        let mut usage = RunUsage::default();
+        let mut loops = LoopDetector::new(agent_type.loop_guard());
+        let mut tool_log = ToolCallLog::new(request.env);
+        let mut error = None;
+        // Session id from the CLI's init message, needed to resume with a reminder
+        let mut live_session_id: Option<String> = None;
+        let mut prompt = request.prompt.to_string();
+        let mut resume = None;
+
+        loop {
+            let mut reminder = None;
+
+            tracing::info!("Calling cc-sdk query...");
+            let query_start = std::time::Instant::now();
+
+            match query(&prompt, Some(build_options(resume.take()))).await {
+                Ok(stream) => {
+                    tracing::info!("Query returned stream in {:?}", query_start.elapsed());
+
+                    let mut stream = Box::pin(stream);
+                    let mut message_count = 0u32;
+                    let mut deltas = TextDeltas::new(request.env);
+                    // A loop detected in a turn that called tools waits for their
+                    // results, so the session is never left with an unanswered tool_use
+                    let mut deferred_loop = None;
+
+                    while let Some(message_result) = stream.next().await {
+                        message_count += 1;
+                        match message_result {
+                            Ok(message) => {
+                                if let Message::StreamEvent { event, .. } = &message {
+                                    if let (Some(tx), Some(delta)) = (&event_tx, deltas.push(event)) {
+                                        if let Err(e) = tx.send(delta).await {
+                                            tracing::warn!("Failed to send text delta: {}", e);
+                                        }
+                                    }
+                                    continue;
+                                }
+
+                                // Log message type for debugging
+                                let msg_type = match &message {
+                                    Message::System { .. } => "System",
+                                    Message::Assistant { .. } => "Assistant",
+                                    Message::User { .. } => "User",
+                                    Message::Result { .. } => "Result",
+                                    Message::StreamEvent { .. } => "StreamEvent",
+                                };
+                                tracing::info!("Received message #{}: type={}", message_count, msg_type);
+                                let mut detected = None;
+
+                                if let Message::System { data, .. } = &message {
+                                    if let Some(id) = data.get("session_id").and_then(|v| v.as_str()) {
+                                        live_session_id = Some(id.to_string());
+                                    }
+                                }
+
+                                // Tool results come back in user messages, one per result
+                                // when tools ran in parallel; a call whose result is never
+                                // read stays unknown
+                                if let Message::User { .. } = &message {
+                                    match serde_json::to_value(&message) {
+                                        Ok(value) => tool_log.finish_from_message(&value),
+                                        Err(e) => tracing::debug!("Failed to read tool results: {}", e),
+                                    }
+                                    detected = deferred_loop.take();
+                                }
+
+                                // Track pending tool for synthetic result generation
+                                // The CLI doesn't emit tool results directly - we infer completion
+                                // when we see text output after a tool use
+
+                                // Extract content from assistant messages
+                                if let Message::Assistant { message: assistant_msg } = &message {
+                                    let mut looping = None;
+                                    let mut called_tools = false;
+                                    for block in &assistant_msg.content {
+                                        match block {
+                                            ContentBlock::Text(text_content) => {
+                                                tracing::debug!("Assistant text: {} chars", text_content.text.len());
+                                                loops.observe_text(&text_content.text);
+                                                output_parts.push(redact(request.env, &text_content.text));
+
+                                                // Forward structured event if provided
+                                                if let Some(ref tx) = event_tx {
+                                                    let event = StreamEvent::Text { content: redact(request.env, &text_content.text) };
+                                                    if let Err(e) = tx.send(event).await {
+                                                        tracing::warn!("Failed to send text event: {}", e);
+                                                    }
+                                                }
+                                            }
+                                            ContentBlock::ToolUse(tool_use) => {
+                                                tracing::info!("Tool use: {} ({})", tool_use.name, tool_use.id);
+                                                tool_log.start(&tool_use.id, &tool_use.name, &tool_use.input);
+                                                called_tools = true;
+                                                if let Some(kind) = loops.observe_tool_use(&tool_use.name, &tool_use.input) {
+                                                    looping.get_or_insert(kind);
+                                                }
+
+                                                if let Some(ref tx) = event_tx {
+                                                    let event = StreamEvent::ToolUse {
+                                                        id: tool_use.id.clone(),
+                                                        name: tool_use.name.clone(),
+                                                        input: redact_json(request.env, &tool_use.input),
+                                                    };
+                                                    if let Err(e) = tx.send(event).await {
+                                                        tracing::warn!("Failed to send tool_use event: {}", e);
+                                                    }
+                                                }
+                                            }
+                                            ContentBlock::ToolResult(tool_result) => {
+                                                // ToolResult blocks from the stream are rare - most tool results
+                                                // come via the PostToolUse hook configured above.
+                                                // This handles edge cases like transcript replay or resume scenarios.
+                                                tracing::debug!(
+                                                    "ToolResult block from stream: {} (hook handles most results)",
+                                                    tool_result.tool_use_id
+                                                );
+                                                tool_log.finish(&tool_result.tool_use_id, tool_result.is_error.unwrap_or(false));
+
+                                                // Only send if we don't have a hook (no event_tx means no hook configured)
+                                                if event_tx.is_none() {
+                                                    tracing::info!("Tool result for: {} (content: {})",
+                                                        tool_result.tool_use_id,
+                                                        tool_result.content.is_some());
+                                                }
+                                            }
+                                            ContentBlock::Thinking(thinking) => {
+                                                tracing::debug!("Thinking: {} chars", thinking.thinking.len());
+
+                                                if let Some(ref tx) = event_tx {
+                                                    let event = StreamEvent::Thinking { content: redact(request.env, &thinking.thinking) };
+                                                    if let Err(e) = tx.send(event).await {
+                                                        tracing::warn!("Failed to send thinking event: {}", e);
+                                                    }
+                                                }
+                                            }
+                                        }
+                                    }
+
+                                    let stalled = loops.end_turn();
+                                    if called_tools {
+                                        deferred_loop = deferred_loop.or(looping.or(stalled));
+                                    } else {
+                                        detected = looping.or(stalled);
+                                    }
+                                }
+
+                                if let Some(kind) = detected {
+                                    let intervention = loops.intervene(&kind);
+                                    tracing::warn!("{}: {} ({})", agent_type.as_str(), kind.describe(), intervention.action());
+                                    if let Some(ref tx) = event_tx {
+                                        let _ = tx.send(intervention.warning(&kind)).await;
+                                    }
+                                    match intervention {
+                                        // Stop this query and resume the session with the reminder.
+                                        // Without a session id the run continues and the next
+                                        // detection aborts it.
+                                        Intervention::Remind(message) if live_session_id.is_some() => {
+                                            reminder = Some(message);
+                                            break;
+                                        }
+                                        Intervention::Remind(_) => {
+                                            tracing::warn!("No session id to resume with a loop reminder");
+                                        }
+                                        Intervention::Abort(message) => {
+                                            status = AgentRunStatus::Failed;
+                                            actual_session_id = live_session_id.clone();
+                                            error = Some(message);
+                                            break;
+                                        }
+                                    }
+                                }
+
+                                // Check for result message to capture session info and status
+                                if let Message::Result {
+                                    subtype,
+                                    session_id: sess_id,
+                                    is_error,
+                                    result,
+                                    total_cost_usd,
+                                    usage: result_usage,
+                                    ..
+                                } = &message {
+                                    usage.add(parse_usage(result_usage.as_ref(), *total_cost_usd));
+                                    tracing::info!(
+                                        "Result message: subtype={}, is_error={}, session_id={}",
+                                        subtype, is_error, sess_id
+                                    );
+                                    if let Some(result_text) = result {
+                                        tracing::info!("Result text: {} chars", result_text.len());
+                                    }
+                                    actual_session_id = Some(sess_id.clone());
+                                    if *is_error {
+                                        tracing::error!("Agent returned error result");
+                                        status = AgentRunStatus::Failed;
+                                    } else if subtype == "success" {
+                                        tracing::info!("Agent completed successfully");
+                                        status = AgentRunStatus::Completed;
+                                    }
+
+                                    // Send result event
+                                    if let Some(ref tx) = event_tx {
+                                        let event = StreamEvent::Result {
+                                            session_id: sess_id.clone(),
+                                            status: subtype.clone(),
+                                            is_error: *is_error,
+                                        };
+                                        if let Err(e) = tx.send(event).await {
+                                            tracing::warn!("Failed to send result event: {}", e);
+                                        }
+                                    }
+
+                                    // Result message means we're done - break out of the loop
+                                    // The cc-sdk stream may not close automatically after Result
+                                    tracing::info!("Breaking out of stream loop after Result message");
+                                    break;
+                                }
+                            }
+                            Err(e) => {
+                                tracing::error!("Error receiving message #{}: {}", message_count, e);
+                                status = AgentRunStatus::Failed;
+                                break;
+                            }
+                        }
+                    }
+
+                    tracing::info!(
+                        "Stream ended after {} messages, total time: {:?}",
+                        message_count,
+                        query_start.elapsed()
+                    );
+                }
+                Err(e) => {
+                    tracing::error!("Query failed after {:?}: {}", query_start.elapsed(), e);
+                    status = AgentRunStatus::Failed;
+                }
+            }
+
+            match reminder {
+                Some(message) => {
+                    prompt = message;
+                    resume = live_session_id.clone();
+                }
+                None => break,
+            }
+        }
+
+        // An aborted run never gets a Result message from the CLI
+        if let (Some(_), Some(tx)) = (&error, &event_tx) {
+            let event = StreamEvent::Result {
+                session_id: actual_session_id.clone().unwrap_or_else(|| request.session_id.to_string()),
+                status: "error_looping".to_string(),
+                is_error: true,
+            };
+            let _ = tx.send(event).await;
+        }
+
+        Ok(BackendOutput {
+            session_id: actual_session_id,
+            output_parts,
+            status,
+            usage,
+            error,
+            tool_calls: tool_log.into_calls(),
+        })
+    }
+}
+
+/// Token counts from a Claude Code result message. Cache reads and writes
+/// are input tokens too.
+pub fn parse_usage(usage: Option<&serde_json::Value>, cost_usd: Option<f64>) -> RunUsage {
+    let count = |key: &str| usage.and_then(|u| u.get(key)).and_then(|v| v.as_u64()).unwrap_or(0);
+    RunUsage {
+        input_tokens: count("input_tokens") + count("cache_creation_input_tokens") + count("cache_read_input_tokens"),
+        output_tokens: count("output_tokens"),
+        cost_usd: cost_usd.unwrap_or(0.0),
+    }
+}
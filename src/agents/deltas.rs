@@ -0,0 +1,129 @@
+//! Partial text streaming
+//!
+//! With partial messages enabled, Claude Code emits raw API stream events
+//! (`content_block_delta` with a `text_delta`) ahead of each complete
+//! assistant message. `TextDeltas` turns those into numbered
+//! `StreamEvent::TextDelta`s so the UI can render text as it is generated.
+//! The complete `Text` event for the block still follows and supersedes the
+//! deltas, so storage and replay only ever deal with whole blocks.
+//!
+//! Secrets are redacted from deltas too. Because a secret can be split
+//! across deltas, the tail that could still be the start of one is held back
+//! until the next delta or the end of the block.
+
+use std::collections::HashMap;
+
+use serde_json::Value;
+
+use super::backend::redact;
+use super::StreamEvent;
+
+pub struct TextDeltas<'a> {
+    env: &'a HashMap<String, String>,
+    /// Longest secret minus one: how much of the tail might be a partial secret
+    holdback: usize,
+    pending: String,
+    seq: u64,
+}
+
+impl<'a> TextDeltas<'a> {
+    pub fn new(env: &'a HashMap<String, String>) -> Self {
+        let holdback = env
+            .values()
+            .filter(|v| v.len() >= 4)
+            .map(|v| v.len() - 1)
+            .max()
+            .unwrap_or(0);
+        Self { env, holdback, pending: String::new(), seq: 0 }
+    }
+
+    /// Feed a raw stream event. Returns the delta to forward, if any.
+    pub fn push(&mut self, event: &Value) -> Option<StreamEvent> {
+        match event.get("type").and_then(|t| t.as_str()) {
+            Some("content_block_delta") => {
+                let text = event
+                    .get("delta")
+                    .filter(|d| d.get("type").and_then(|t| t.as_str()) == Some("text_delta"))
+                    .and_then(|d| d.get("text"))
+                    .and_then(|t| t.as_str())?;
+                self.pending.push_str(text);
+                let cut = self.safe_cut();
+                self.emit(cut)
+            }
+            Some("content_block_stop") | Some("message_stop") => self.flush(),
+            _ => None,
+        }
+    }
+
+    /// Emit whatever is still held back (end of a block)
+    pub fn flush(&mut self) -> Option<StreamEvent> {
+        self.emit(self.pending.len())
+    }
+
+    /// Largest prefix of `pending` that can't end inside a secret
+    fn safe_cut(&self) -> usize {
+        let mut cut = self.pending.len().saturating_sub(self.holdback);
+        while !self.pending.is_char_boundary(cut) {
+            cut -= 1;
+        }
+        // A complete secret straddling the cut is redacted whole next time
+        for secret in self.env.values().filter(|v| v.len() >= 4) {
+            for (start, _) in self.pending.match_indices(secret.as_str()) {
+                if start < cut && start + secret.len() > cut {
+                    cut = start;
+                }
+            }
+        }
+        cut
+    }
+
+    fn emit(&mut self, cut: usize) -> Option<StreamEvent> {
+        if cut == 0 {
+            return None;
+        }
+        let rest = self.pending.split_off(cut);
+        let content = redact(self.env, &std::mem::replace(&mut self.pending, rest));
+        let seq = self.seq;
+        self.seq += 1;
+        Some(StreamEvent::TextDelta { seq, content })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn delta(text: &str) -> Value {
+        json!({ "type": "content_block_delta", "index": 0, "delta": { "type": "text_delta", "text": text } })
+    }
+
+    fn content(event: Option<StreamEvent>) -> Option<(u64, String)> {
+        match event {
+            Some(StreamEvent::TextDelta { seq, content }) => Some((seq, content)),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn numbers_deltas_and_redacts_split_secrets() {
+        let no_secrets = HashMap::new();
+        let mut deltas = TextDeltas::new(&no_secrets);
+        assert_eq!(content(deltas.push(&delta("Hel"))), Some((0, "Hel".to_string())));
+        assert_eq!(content(deltas.push(&delta("lo"))), Some((1, "lo".to_string())));
+        assert_eq!(content(deltas.push(&json!({ "type": "message_start" }))), None);
+
+        let env = HashMap::from([("TOKEN".to_string(), "s3cret-value".to_string())]);
+        let mut deltas = TextDeltas::new(&env);
+        let mut streamed = String::new();
+        for part in ["The token is s3c", "ret-val", "ue, done."] {
+            if let Some((_, text)) = content(deltas.push(&delta(part))) {
+                streamed.push_str(&text);
+            }
+        }
+        if let Some((_, text)) = content(deltas.push(&json!({ "type": "content_block_stop", "index": 0 }))) {
+            streamed.push_str(&text);
+        }
+        assert_eq!(streamed, "The token is [REDACTED], done.");
+    }
+}
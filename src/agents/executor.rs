@@ -1,21 +1,103 @@
-use cc_sdk::{query, ClaudeCodeOptions, Message, ContentBlock, ToolsConfig};
+use cc_sdk::{query, ClaudeCodeOptions, Message, ContentBlock};
 use futures::StreamExt;
 use tokio::sync::mpsc;
 use anyhow::{Result, Context};
 use std::collections::HashMap;
 use std::path::PathBuf;
 
-use super::{AgentType, AgentRun, AgentRunStatus, TicketContext, StreamEvent, EmailOutput};
+use super::{AgentType, AgentRun, AgentRunStatus, RunUsage, TicketContext, StreamEvent, EmailOutput, CLAUDE_CODE_BACKEND};
+use super::approvals::ToolApprovalConfig;
+use super::backend::{backend_by_name, redact, redact_json, BackendRequest};
 use super::prompts::load_prompt;
-
-/// Executes agents using the Claude Code CLI via cc-sdk.
+use super::deltas::TextDeltas;
+use super::failure;
+use super::snapshot::{summarize_sections, ContextSnapshot};
+use crate::redaction::RedactionAudit;
+use crate::secrets::StepEnv;
+
+/// Executes agents on the backend configured for their agent type
+/// (Claude Code via cc-sdk by default). Resuming sessions is Claude Code only.
 pub struct AgentExecutor {
     working_dir: PathBuf,
+    /// Session the approval endpoint answers gated tool calls on
+    approval_session: Option<String>,
+    /// Tools gated on top of the agent type's `approval_tools`
+    extra_approval_tools: Vec<String>,
+    env: HashMap<String, String>,
+    step_env: HashMap<String, String>,
+    extra_vars: HashMap<String, String>,
+    redactions: RedactionAudit,
 }
 
 impl AgentExecutor {
     pub fn new(working_dir: PathBuf) -> Self {
-        Self { working_dir }
+        Self {
+            working_dir,
+            approval_session: None,
+            extra_approval_tools: Vec::new(),
+            env: HashMap::new(),
+            step_env: HashMap::new(),
+            extra_vars: HashMap::new(),
+            redactions: RedactionAudit::default(),
+        }
+    }
+
+    /// Extra environment variables for the agent process (organization secrets).
+    /// Their values are redacted from streamed events and the run output.
+    pub fn with_env(mut self, env: HashMap<String, String>) -> Self {
+        self.env = env;
+        self
+    }
+
+    /// Environment declared by the pipeline step being run. Values taken from
+    /// secrets are redacted like the organization's; literal values are not.
+    /// Call after `with_env`.
+    pub fn with_step_env(mut self, step_env: StepEnv) -> Self {
+        self.env.extend(step_env.secret);
+        self.step_env = step_env.plain;
+        self
+    }
+
+    /// Additional prompt template variables, applied after the built-in ones.
+    pub fn with_prompt_vars(mut self, vars: HashMap<String, String>) -> Self {
+        self.extra_vars = vars;
+        self
+    }
+
+    /// PII already redacted from the injected context, recorded in the run's snapshot.
+    pub fn with_redactions(mut self, redactions: RedactionAudit) -> Self {
+        self.redactions = redactions;
+        self
+    }
+
+    /// Replace any secret value in `text` with a placeholder.
+    fn redact(&self, text: &str) -> String {
+        redact(&self.env, text)
+    }
+
+    fn redact_json(&self, value: &serde_json::Value) -> serde_json::Value {
+        redact_json(&self.env, value)
+    }
+
+    /// Let a client approve gated tool calls through `session_id`, gating
+    /// `extra_tools` as well as the agent type's configured `approval_tools`.
+    /// Without it, gated tools are denied.
+    pub fn with_tool_approval(mut self, session_id: String, extra_tools: Vec<String>) -> Self {
+        self.approval_session = Some(session_id);
+        self.extra_approval_tools = extra_tools;
+        self
+    }
+
+    /// Tools gated for a run of `agent_type`, `None` when there are none.
+    /// The configured ones always apply; callers can only add to them.
+    fn approval_for(&self, agent_type: &AgentType) -> Option<ToolApprovalConfig> {
+        let mut tools = agent_type.approval_tools();
+        for tool in &self.extra_approval_tools {
+            if !tools.contains(tool) {
+                tools.push(tool.clone());
+            }
+        }
+        (!tools.is_empty()).then(|| ToolApprovalConfig { session_id: self.approval_session.clone(), tools })
     }
 
     /// Execute an agent for a specific ticket.
@@ -44,6 +126,24 @@ impl AgentExecutor {
         vars.insert("ticket_id".to_string(), ticket_context.ticket_id.clone());
         vars.insert("ticket_title".to_string(), ticket_context.title.clone());
         vars.insert("ticket_intent".to_string(), ticket_context.intent.clone());
+        if let Some(checklist) = &ticket_context.checklist {
+            vars.insert("checklist".to_string(), checklist.clone());
+        }
+        if let Some(research) = &ticket_context.research {
+            vars.insert("research_corpus".to_string(), research.clone());
+        }
+        if let Some(step_inputs) = &ticket_context.step_inputs {
+            vars.insert("step_inputs".to_string(), step_inputs.clone());
+        }
+        if let Some(estimation) = &ticket_context.estimation {
+            vars.insert("estimation_context".to_string(), estimation.clone());
+        }
+        if let Some(email_templates) = &ticket_context.email_templates {
+            vars.insert("email_templates".to_string(), email_templates.clone());
+        }
+        if let Some(pinned) = &ticket_context.pinned_outputs {
+            vars.insert("pinned_outputs".to_string(), pinned.clone());
+        }
 
         // Add previous output for chaining
         if let Some(prev) = &previous_output {
@@ -89,18 +189,14 @@ impl AgentExecutor {
             vars.insert("sender_info".to_string(), "(No sender information available - please add your contact details)".to_string());
         }
 
+        vars.extend(self.extra_vars.clone());
+        let snapshot_vars: std::collections::BTreeMap<String, String> =
+            vars.iter().map(|(k, v)| (k.clone(), self.redact(v))).collect();
+
         // Load system prompt for this agent type
         let system_prompt = load_prompt(agent_type.as_str(), vars)
             .context("Failed to load agent prompt")?;
 
-        // Build cc-sdk options using builder pattern
-        let tools_list: Vec<String> = agent_type
-            .allowed_tools()
-            .iter()
-            .map(|s| s.to_string())
-            .collect();
-
-        // Log what we're about to do
         tracing::info!(
             "Starting agent execution: type={}, ticket={}, model={}",
             agent_type.as_str(),
@@ -108,184 +204,78 @@ impl AgentExecutor {
             agent_type.model()
         );
         tracing::info!("System prompt length: {} chars", system_prompt.len());
-        tracing::info!("Working dir: {:?}", self.working_dir);
-        tracing::info!("Tools config: {:?}", tools_list);
-        tracing::info!("Max turns: {:?}", agent_type.max_turns());
-
-        // Build options
-        // Use ToolsConfig to actually restrict which tools are available (not just auto-approval)
-        let mut builder = ClaudeCodeOptions::builder()
-            .system_prompt(&system_prompt)
-            .model(agent_type.model())
-            .tools(ToolsConfig::list(tools_list.clone()))
-            .allowed_tools(tools_list) // Also auto-approve these tools
-            .cwd(&self.working_dir);
-
-        // Only set max_turns if configured (otherwise unlimited)
-        if let Some(turns) = agent_type.max_turns() {
-            builder = builder.max_turns(turns);
-        }
 
-        let options = builder.build();
-
-        // The initial prompt is the ticket intent
-        let prompt = format!(
+        // The initial prompt is the ticket intent, followed by any pinned outputs
+        let mut prompt = format!(
             "Work on this ticket:\n\nTitle: {}\nIntent: {}",
             ticket_context.title,
             ticket_context.intent
         );
+        if let Some(pinned) = &ticket_context.pinned_outputs {
+            prompt.push_str("\n\n");
+            prompt.push_str(pinned);
+        }
 
-        // Execute using query() - simple and reliable
+        let approval = self.approval_for(&agent_type);
+        let request = BackendRequest {
+            session_id: &session_id,
+            agent_type: &agent_type,
+            system_prompt: &system_prompt,
+            prompt: &prompt,
+            working_dir: &self.working_dir,
+            env: &self.env,
+            step_env: &self.step_env,
+            approval: approval.as_ref(),
+        };
+
+        // Try the configured backend, then its fallback if it fails outright
         let mut output_parts = Vec::new();
-        let mut status = AgentRunStatus::Running;
+        let mut status = AgentRunStatus::Failed;
         let mut actual_session_id = session_id.clone();
-
-        tracing::info!("Calling cc-sdk query...");
-        let query_start = std::time::Instant::now();
-
-        match query(prompt.as_str(), Some(options)).await {
-            Ok(stream) => {
-                tracing::info!("Query returned stream in {:?}", query_start.elapsed());
-
-                let mut stream = Box::pin(stream);
-                let mut message_count = 0u32;
-
-                while let Some(message_result) = stream.next().await {
-                    message_count += 1;
-                    match message_result {
-                        Ok(message) => {
-                            // Log message type for debugging
-                            let msg_type = match &message {
-                                Message::System { .. } => "System",
-                                Message::Assistant { .. } => "Assistant",
-                                Message::User { .. } => "User",
-                                Message::Result { .. } => "Result",
-                            };
-                            tracing::info!("Received message #{}: type={}", message_count, msg_type);
-
-                            // Track pending tool for synthetic result generation
-                            // The CLI doesn't emit tool results directly - we infer completion
-                            // when we see text output after a tool use
-
-                            // Extract content from assistant messages
-                            if let Message::Assistant { message: assistant_msg } = &message {
-                                for block in &assistant_msg.content {
-                                    match block {
-                                        ContentBlock::Text(text_content) => {
-                                            tracing::debug!("Assistant text: {} chars", text_content.text.len());
-                                            output_parts.push(text_content.text.clone());
-
-                                            // Forward structured event if provided
-                                            if let Some(ref tx) = event_tx {
-                                                let event = StreamEvent::Text { content: text_content.text.clone() };
-                                                if let Err(e) = tx.send(event).await {
-                                                    tracing::warn!("Failed to send text event: {}", e);
-                                                }
-                                            }
-                                        }
-                                        ContentBlock::ToolUse(tool_use) => {
-                                            tracing::info!("Tool use: {} ({})", tool_use.name, tool_use.id);
-
-                                            if let Some(ref tx) = event_tx {
-                                                let event = StreamEvent::ToolUse {
-                                                    id: tool_use.id.clone(),
-                                                    name: tool_use.name.clone(),
-                                                    input: tool_use.input.clone(),
-                                                };
-                                                if let Err(e) = tx.send(event).await {
-                                                    tracing::warn!("Failed to send tool_use event: {}", e);
-                                                }
-                                            }
-                                        }
-                                        ContentBlock::ToolResult(tool_result) => {
-                                            // ToolResult blocks from the stream are rare - most tool results
-                                            // come via the PostToolUse hook configured above.
-                                            // This handles edge cases like transcript replay or resume scenarios.
-                                            tracing::debug!(
-                                                "ToolResult block from stream: {} (hook handles most results)",
-                                                tool_result.tool_use_id
-                                            );
-
-                                            // Only send if we don't have a hook (no event_tx means no hook configured)
-                                            if event_tx.is_none() {
-                                                tracing::info!("Tool result for: {} (content: {})",
-                                                    tool_result.tool_use_id,
-                                                    tool_result.content.is_some());
-                                            }
-                                        }
-                                        ContentBlock::Thinking(thinking) => {
-                                            tracing::debug!("Thinking: {} chars", thinking.thinking.len());
-
-                                            if let Some(ref tx) = event_tx {
-                                                let event = StreamEvent::Thinking { content: thinking.thinking.clone() };
-                                                if let Err(e) = tx.send(event).await {
-                                                    tracing::warn!("Failed to send thinking event: {}", e);
-                                                }
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-
-                            // Check for result message to capture session info and status
-                            if let Message::Result {
-                                subtype,
-                                session_id: sess_id,
-                                is_error,
-                                result,
-                                ..
-                            } = &message {
-                                tracing::info!(
-                                    "Result message: subtype={}, is_error={}, session_id={}",
-                                    subtype, is_error, sess_id
-                                );
-                                if let Some(result_text) = result {
-                                    tracing::info!("Result text: {} chars", result_text.len());
-                                }
-                                actual_session_id = sess_id.clone();
-                                if *is_error {
-                                    tracing::error!("Agent returned error result");
-                                    status = AgentRunStatus::Failed;
-                                } else if subtype == "success" {
-                                    tracing::info!("Agent completed successfully");
-                                    status = AgentRunStatus::Completed;
-                                }
-
-                                // Send result event
-                                if let Some(ref tx) = event_tx {
-                                    let event = StreamEvent::Result {
-                                        session_id: sess_id.clone(),
-                                        status: subtype.clone(),
-                                        is_error: *is_error,
-                                    };
-                                    if let Err(e) = tx.send(event).await {
-                                        tracing::warn!("Failed to send result event: {}", e);
-                                    }
-                                }
-
-                                // Result message means we're done - break out of the loop
-                                // The cc-sdk stream may not close automatically after Result
-                                tracing::info!("Breaking out of stream loop after Result message");
-                                break;
-                            }
-                        }
-                        Err(e) => {
-                            tracing::error!("Error receiving message #{}: {}", message_count, e);
-                            status = AgentRunStatus::Failed;
-                            break;
-                        }
+        let mut backend_used = CLAUDE_CODE_BACKEND;
+        // Failed attempts still count: the tokens were spent
+        let mut usage = RunUsage::default();
+        // Kept for classifying the failure if no backend succeeds
+        let mut last_error = None;
+        // Tool calls made by failed attempts are audited too
+        let mut tool_calls = Vec::new();
+        for name in agent_type.backends() {
+            backend_used = name;
+            let mut result = match backend_by_name(name) {
+                Ok(backend) => backend.run(&request, event_tx.clone()).await,
+                Err(e) => Err(e),
+            };
+            if let Ok(output) = &mut result {
+                usage.add(output.usage);
+                tool_calls.append(&mut output.tool_calls);
+                if output.error.is_some() {
+                    last_error = output.error.clone();
+                }
+            }
+            match result {
+                Ok(output)
+                    if output.status == AgentRunStatus::Completed
+                        || !output.output_parts.is_empty()
+                        || output.error.is_some() =>
+                {
+                    output_parts = output.output_parts;
+                    status = output.status;
+                    if let Some(id) = output.session_id {
+                        actual_session_id = id;
                     }
+                    break;
+                }
+                Ok(_) => tracing::error!("Backend {} failed without output for {}", name, agent_type.as_str()),
+                Err(e) => {
+                    tracing::error!("Backend {} failed for {}: {:#}", name, agent_type.as_str(), e);
+                    last_error = Some(format!("{:#}", e));
                 }
-
-                tracing::info!(
-                    "Stream ended after {} messages, total time: {:?}",
-                    message_count,
-                    query_start.elapsed()
-                );
             }
-            Err(e) => {
-                tracing::error!("Query failed after {:?}: {}", query_start.elapsed(), e);
-                status = AgentRunStatus::Failed;
+            if let Some(ref tx) = event_tx {
+                let _ = tx.send(StreamEvent::Status {
+                    status: "running".to_string(),
+                    message: Some(format!("Backend {} failed", name)),
+                }).await;
             }
         }
 
@@ -331,6 +321,25 @@ impl AgentExecutor {
             None
         };
 
+        let failure_reason = (status == AgentRunStatus::Failed).then(|| {
+            failure::classify(last_error.as_deref().or(output_summary.as_deref()).unwrap_or_default())
+        });
+
+        let context_snapshot = ContextSnapshot {
+            agent_type: agent_type.as_str().to_string(),
+            model: agent_type.model().to_string(),
+            max_turns: agent_type.max_turns(),
+            backend: backend_used.to_string(),
+            allowed_tools: agent_type.allowed_tools().into_iter().map(|t| t.to_string()).collect(),
+            working_dir: self.working_dir.display().to_string(),
+            system_prompt: self.redact(&system_prompt),
+            prompt: self.redact(&prompt),
+            sections: summarize_sections(&snapshot_vars),
+            prompt_vars: snapshot_vars,
+            redactions: self.redactions.clone(),
+            captured_at: started_at.clone(),
+        };
+
         Ok(AgentRun {
             session_id: actual_session_id,
             ticket_id: ticket_context.ticket_id,
@@ -343,6 +352,10 @@ impl AgentExecutor {
             input_message: ticket_context.intent,
             output_summary,
             email_output,
+            failure_reason,
+            context_snapshot: Some(context_snapshot),
+            usage,
+            tool_calls,
         })
     }
 
@@ -354,12 +367,15 @@ impl AgentExecutor {
         message: &str,
         event_tx: Option<mpsc::Sender<StreamEvent>>,
     ) -> Result<Vec<String>> {
-        let options = ClaudeCodeOptions::builder()
+        let mut options = ClaudeCodeOptions::builder()
             .resume(session_id.to_string())
             .cwd(&self.working_dir)
             .build();
+        options.env.extend(self.env.clone());
+        options.include_partial_messages = event_tx.is_some();
 
         let mut output_parts = Vec::new();
+        let mut deltas = TextDeltas::new(&self.env);
 
         tracing::info!("Resuming session {} with message: {}...", session_id, &message[..message.len().min(100)]);
 
@@ -370,14 +386,21 @@ impl AgentExecutor {
                 while let Some(message_result) = stream.next().await {
                     match message_result {
                         Ok(message) => {
+                            if let Message::StreamEvent { event, .. } = &message {
+                                if let (Some(tx), Some(delta)) = (&event_tx, deltas.push(event)) {
+                                    let _ = tx.send(delta).await;
+                                }
+                                continue;
+                            }
+
                             if let Message::Assistant { message: assistant_msg } = &message {
                                 for block in &assistant_msg.content {
                                     match block {
                                         ContentBlock::Text(text_content) => {
-                                            output_parts.push(text_content.text.clone());
+                                            output_parts.push(self.redact(&text_content.text));
 
                                             if let Some(ref tx) = event_tx {
-                                                let event = StreamEvent::Text { content: text_content.text.clone() };
+                                                let event = StreamEvent::Text { content: self.redact(&text_content.text) };
                                                 let _ = tx.send(event).await;
                                             }
                                         }
@@ -386,7 +409,7 @@ impl AgentExecutor {
                                                 let event = StreamEvent::ToolUse {
                                                     id: tool_use.id.clone(),
                                                     name: tool_use.name.clone(),
-                                                    input: tool_use.input.clone(),
+                                                    input: self.redact_json(&tool_use.input),
                                                 };
                                                 let _ = tx.send(event).await;
                                             }
@@ -401,7 +424,7 @@ impl AgentExecutor {
                                         }
                                         ContentBlock::Thinking(thinking) => {
                                             if let Some(ref tx) = event_tx {
-                                                let event = StreamEvent::Thinking { content: thinking.thinking.clone() };
+                                                let event = StreamEvent::Thinking { content: self.redact(&thinking.thinking) };
                                                 let _ = tx.send(event).await;
                                             }
                                         }
@@ -0,0 +1,177 @@
+//! Failure classification
+//!
+//! When a run fails, its error text is matched against known failure shapes
+//! and the resulting reason is stored with the run. Automated pipeline steps
+//! use it to decide whether a retry can help: rate limits are retried even
+//! without a retry policy, while permission errors, impossible tasks and
+//! loops are never retried.
+
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FailureReason {
+    /// The model provider throttled or was overloaded
+    RateLimit,
+    /// A tool call was denied, by approval or by the filesystem
+    PermissionDenied,
+    /// The prompt or conversation no longer fits the model's context
+    ContextOverflow,
+    /// The agent CLI exited or its stream broke before a result
+    CliCrash,
+    /// The agent finished but reported the task cannot be done
+    Impossible,
+    /// The loop guard stopped the agent repeating the same steps
+    Looping,
+    Unknown,
+}
+
+impl FailureReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FailureReason::RateLimit => "rate_limit",
+            FailureReason::PermissionDenied => "permission_denied",
+            FailureReason::ContextOverflow => "context_overflow",
+            FailureReason::CliCrash => "cli_crash",
+            FailureReason::Impossible => "impossible",
+            FailureReason::Looping => "looping",
+            FailureReason::Unknown => "unknown",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        serde_json::from_value(serde_json::Value::String(s.to_string())).ok()
+    }
+
+    /// Whether running the same step again could succeed. Rate limits and
+    /// crashes are transient; the rest fail the same way on every attempt.
+    pub fn is_transient(&self) -> bool {
+        matches!(self, FailureReason::RateLimit | FailureReason::CliCrash | FailureReason::Unknown)
+    }
+}
+
+/// Checked in order; the first matching group wins, so a rate-limit message
+/// that also mentions "exited" is still a rate limit
+const PATTERNS: &[(FailureReason, &[&str])] = &[
+    (FailureReason::Looping, &["loop detected"]),
+    (
+        FailureReason::RateLimit,
+        &[
+            "rate limit",
+            "rate_limit",
+            "ratelimit",
+            "too many requests",
+            "status 429",
+            "status: 429",
+            "error 429",
+            "http 429",
+            "overloaded",
+            "usage limit",
+        ],
+    ),
+    (
+        FailureReason::ContextOverflow,
+        &[
+            "context window",
+            "context length",
+            "context_length_exceeded",
+            "prompt is too long",
+            "maximum context",
+            "too many tokens",
+        ],
+    ),
+    (
+        FailureReason::PermissionDenied,
+        &[
+            "permission denied",
+            "permission to use",
+            "not permitted",
+            "denied by user",
+            "tool use was denied",
+            "eacces",
+            "operation not permitted",
+        ],
+    ),
+    (
+        FailureReason::Impossible,
+        &["cannot be completed", "unable to complete", "not possible to", "impossible to", "cannot complete this task"],
+    ),
+    (
+        FailureReason::CliCrash,
+        &[
+            "exited with",
+            "exit code",
+            "exit status",
+            "broken pipe",
+            "failed to spawn",
+            "failed to connect",
+            "failed to resume session",
+            "by signal",
+            "signal: ",
+            "sigkill",
+            "sigterm",
+            "sigsegv",
+            "panicked",
+            "unexpected eof",
+            "error receiving message",
+        ],
+    ),
+];
+
+/// Classify a failed run from its error message or output
+pub fn classify(text: &str) -> FailureReason {
+    let text = text.to_lowercase();
+    PATTERNS
+        .iter()
+        .find(|(_, needles)| needles.iter().any(|needle| text.contains(needle)))
+        .map(|(reason, _)| *reason)
+        .unwrap_or(FailureReason::Unknown)
+}
+
+/// Classify a failure and store the reason on the run. Storage failures are
+/// logged; the reason is returned either way for retry decisions.
+pub async fn record_failure(db: &SqlitePool, session_id: &str, text: &str) -> FailureReason {
+    let reason = classify(text);
+    if let Err(e) = ticketing_system::agent_runs::set_failure_reason(db, session_id, reason.as_str()).await {
+        tracing::warn!("Failed to store failure reason for {}: {}", session_id, e);
+    }
+    reason
+}
+
+/// Stored failure reason of a run, if it failed and was classified
+pub async fn failure_reason(db: &SqlitePool, session_id: &str) -> Option<FailureReason> {
+    match ticketing_system::agent_runs::get_failure_reason(db, session_id).await {
+        Ok(reason) => reason.as_deref().and_then(FailureReason::parse),
+        Err(e) => {
+            tracing::warn!("Failed to load failure reason for {}: {}", session_id, e);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classifies_common_failures() {
+        assert_eq!(classify("API Error: 429 Too Many Requests"), FailureReason::RateLimit);
+        assert_eq!(classify("Claude CLI exited with status 1: overloaded_error"), FailureReason::RateLimit);
+        assert_eq!(classify("prompt is too long: 210000 tokens > 200000 maximum"), FailureReason::ContextOverflow);
+        assert_eq!(classify("Tool use was denied by user: Bash"), FailureReason::PermissionDenied);
+        assert_eq!(classify("Failed to resume session: broken pipe"), FailureReason::CliCrash);
+        assert_eq!(classify("This task cannot be completed without repo access"), FailureReason::Impossible);
+        assert_eq!(classify("Loop detected: the agent called Grep with the same input 4 times"), FailureReason::Looping);
+        assert_eq!(classify("something odd"), FailureReason::Unknown);
+        assert_eq!(classify("Claude CLI terminated by signal: 9 (SIGKILL)"), FailureReason::CliCrash);
+        assert_eq!(classify("HTTP 429 from the provider"), FailureReason::RateLimit);
+
+        // Numbers and words in ordinary output don't count
+        assert_eq!(classify("Fixed the signal handler; see issue 4291"), FailureReason::Unknown);
+
+        assert_eq!(FailureReason::parse("permission_denied"), Some(FailureReason::PermissionDenied));
+        assert!(!FailureReason::PermissionDenied.is_transient());
+        assert!(FailureReason::RateLimit.is_transient());
+    }
+}
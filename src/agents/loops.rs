@@ -0,0 +1,237 @@
+//! Loop detection
+//!
+//! Backends feed every assistant turn through a `LoopDetector`. A run is
+//! looping when it calls the same tool with the same input `repeat_limit`
+//! times in a row, or goes `stall_turns` turns without producing any new text
+//! or calling any tool. The first detection sends a corrective reminder to the
+//! agent (unless the agent is configured to abort straight away); a second one
+//! fails the run with a `looping` failure reason. Backends act on a detection
+//! once the turn's tool calls have returned, never in the middle of one.
+
+use serde::Deserialize;
+use std::collections::HashSet;
+
+use super::StreamEvent;
+
+fn default_repeat_limit() -> usize {
+    4
+}
+
+fn default_stall_turns() -> usize {
+    12
+}
+
+/// What to do the first time a loop is detected
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LoopAction {
+    /// Tell the agent it is repeating itself and let it continue once
+    #[default]
+    Remind,
+    /// Stop the run immediately
+    Abort,
+}
+
+/// Per-agent loop guard settings, `loop_guard` in agents.json
+#[derive(Debug, Clone, Deserialize)]
+pub struct LoopGuardConfig {
+    /// Identical tool calls (same tool and input) in a row that count as a loop
+    #[serde(default = "default_repeat_limit")]
+    pub repeat_limit: usize,
+    /// Consecutive turns without new text or tool calls that count as a loop
+    #[serde(default = "default_stall_turns")]
+    pub stall_turns: usize,
+    #[serde(default)]
+    pub action: LoopAction,
+}
+
+impl Default for LoopGuardConfig {
+    fn default() -> Self {
+        Self {
+            repeat_limit: default_repeat_limit(),
+            stall_turns: default_stall_turns(),
+            action: LoopAction::default(),
+        }
+    }
+}
+
+/// Prefix of the abort error; `failure::classify` maps it to `looping`
+pub const LOOP_ABORT_PREFIX: &str = "Loop detected";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LoopKind {
+    RepeatedTool { tool: String, count: usize },
+    NoProgress { turns: usize },
+}
+
+impl LoopKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LoopKind::RepeatedTool { .. } => "repeated_tool",
+            LoopKind::NoProgress { .. } => "no_progress",
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        match self {
+            LoopKind::RepeatedTool { tool, count } => {
+                format!("called {} with the same input {} times", tool, count)
+            }
+            LoopKind::NoProgress { turns } => format!("went {} turns without producing new output", turns),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Intervention {
+    /// Send this message to the agent and keep going
+    Remind(String),
+    /// Stop the run with this error
+    Abort(String),
+}
+
+impl Intervention {
+    pub fn action(&self) -> &'static str {
+        match self {
+            Intervention::Remind(_) => "remind",
+            Intervention::Abort(_) => "abort",
+        }
+    }
+
+    /// Stream event telling watchers what was detected and what happens next
+    pub fn warning(&self, kind: &LoopKind) -> StreamEvent {
+        StreamEvent::LoopWarning {
+            kind: kind.as_str().to_string(),
+            message: format!("Agent {}", kind.describe()),
+            action: self.action().to_string(),
+        }
+    }
+}
+
+pub struct LoopDetector {
+    config: LoopGuardConfig,
+    /// The last tool call (tool and input) and how many times in a row it was made
+    last_call: Option<(String, usize)>,
+    seen_text: HashSet<String>,
+    turn_has_progress: bool,
+    stalled_turns: usize,
+    reminded: bool,
+}
+
+impl LoopDetector {
+    pub fn new(config: LoopGuardConfig) -> Self {
+        Self {
+            config,
+            last_call: None,
+            seen_text: HashSet::new(),
+            turn_has_progress: false,
+            stalled_turns: 0,
+            reminded: false,
+        }
+    }
+
+    /// Record assistant text for the current turn
+    pub fn observe_text(&mut self, text: &str) {
+        let normalized = text.split_whitespace().collect::<Vec<_>>().join(" ");
+        if !normalized.is_empty() && self.seen_text.insert(normalized) {
+            self.turn_has_progress = true;
+        }
+    }
+
+    /// Record a tool call; returns the loop if this call completes one
+    pub fn observe_tool_use(&mut self, tool: &str, input: &serde_json::Value) -> Option<LoopKind> {
+        self.turn_has_progress = true;
+        let key = format!("{}:{}", tool, input);
+        let count = match &mut self.last_call {
+            Some((last, count)) if *last == key => {
+                *count += 1;
+                *count
+            }
+            _ => {
+                self.last_call = Some((key, 1));
+                1
+            }
+        };
+        (count >= self.config.repeat_limit).then(|| LoopKind::RepeatedTool { tool: tool.to_string(), count })
+    }
+
+    /// Close the current turn; returns the loop if the run has stalled
+    pub fn end_turn(&mut self) -> Option<LoopKind> {
+        if std::mem::take(&mut self.turn_has_progress) {
+            self.stalled_turns = 0;
+        } else {
+            self.stalled_turns += 1;
+        }
+        (self.stalled_turns >= self.config.stall_turns).then_some(LoopKind::NoProgress { turns: self.stalled_turns })
+    }
+
+    /// Decide what to do about a detected loop. After a reminder the counters
+    /// start over, so the agent gets a fair second chance before an abort.
+    pub fn intervene(&mut self, kind: &LoopKind) -> Intervention {
+        if self.reminded || self.config.action == LoopAction::Abort {
+            return Intervention::Abort(format!("{}: the agent {}", LOOP_ABORT_PREFIX, kind.describe()));
+        }
+        self.reminded = true;
+        self.last_call = None;
+        self.stalled_turns = 0;
+        Intervention::Remind(format!(
+            "<system-reminder>You have {}. Repeating the same step will not produce a different result. \
+             Use what you have already found, try a different approach, or finish and explain what is blocking you. \
+             If this happens again the run will be stopped.</system-reminder>",
+            kind.describe()
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn reminds_then_aborts_on_repeated_tool_calls() {
+        let mut detector = LoopDetector::new(LoopGuardConfig { repeat_limit: 3, ..Default::default() });
+        let grep = json!({"pattern": "fn main"});
+
+        // Only calls in a row count
+        assert_eq!(detector.observe_tool_use("Grep", &grep), None);
+        assert_eq!(detector.observe_tool_use("Grep", &grep), None);
+        assert_eq!(detector.observe_tool_use("Grep", &json!({"pattern": "fn run"})), None);
+        assert_eq!(detector.observe_tool_use("Grep", &grep), None);
+        assert_eq!(detector.observe_tool_use("Grep", &grep), None);
+        let kind = detector.observe_tool_use("Grep", &grep).unwrap();
+        assert_eq!(kind, LoopKind::RepeatedTool { tool: "Grep".to_string(), count: 3 });
+        assert_eq!(detector.intervene(&kind).action(), "remind");
+
+        // Counters restart after the reminder
+        assert_eq!(detector.observe_tool_use("Grep", &grep), None);
+        assert_eq!(detector.observe_tool_use("Grep", &grep), None);
+        let kind = detector.observe_tool_use("Grep", &grep).unwrap();
+        match detector.intervene(&kind) {
+            Intervention::Abort(error) => assert!(error.starts_with(LOOP_ABORT_PREFIX)),
+            other => panic!("expected abort, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn detects_turns_without_new_text() {
+        let mut detector = LoopDetector::new(LoopGuardConfig { stall_turns: 2, ..Default::default() });
+
+        detector.observe_text("Looking at the handler");
+        assert_eq!(detector.end_turn(), None);
+        detector.observe_text("Looking  at the handler\n");
+        assert_eq!(detector.end_turn(), None);
+        assert_eq!(detector.end_turn(), Some(LoopKind::NoProgress { turns: 2 }));
+
+        // Turns that only call tools are progress
+        let mut working = LoopDetector::new(LoopGuardConfig { stall_turns: 2, ..Default::default() });
+        for i in 0..5 {
+            working.observe_tool_use("Read", &json!({ "file_path": format!("src/{}.rs", i) }));
+            assert_eq!(working.end_turn(), None);
+        }
+
+        let mut strict = LoopDetector::new(LoopGuardConfig { stall_turns: 1, action: LoopAction::Abort, ..Default::default() });
+        let kind = strict.end_turn().unwrap();
+        assert_eq!(strict.intervene(&kind).action(), "abort");
+    }
+}
@@ -2,7 +2,20 @@ pub mod types;
 pub mod prompts;
 pub mod executor;
 pub mod working_dir;
+pub mod approvals;
+pub mod backend;
+pub mod claude_code;
+pub mod openai_compat;
+pub mod snapshot;
+pub mod deltas;
+pub mod failure;
+pub mod loops;
+pub mod tool_audit;
 
 pub use types::*;
 pub use executor::*;
 pub use working_dir::resolve_working_dir;
+pub use snapshot::ContextSnapshot;
+pub use failure::FailureReason;
+pub use tool_audit::{ToolCall, ToolCallLog};
+pub use approvals::{ToolApprovalConfig, ToolApprovalDecision, PENDING_APPROVALS};
@@ -0,0 +1,500 @@
+//! OpenAI-compatible backend: drives a chat-completions API with its own
+//! function-calling loop. File tools run inside the agent's working directory
+//! and `mcp__agentic-mcp__*` tools go through the in-process MCP handler.
+//! Tools this backend can't provide (e.g. Bash) are left out of the run.
+
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use regex::Regex;
+use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
+use tokio::sync::mpsc;
+
+use super::approvals::ToolApprovalGate;
+use super::backend::{redact, redact_json, AgentBackend, BackendOutput, BackendRequest};
+use super::loops::{Intervention, LoopDetector};
+use super::tool_audit::ToolCallLog;
+use super::{AgentRunStatus, RunUsage, StreamEvent};
+
+/// Turn limit when neither the agent nor the backend sets one
+const DEFAULT_MAX_TURNS: i32 = 25;
+
+/// Tool results are cut to this many characters before going back to the model
+const MAX_TOOL_RESULT_CHARS: usize = 20_000;
+
+const MAX_READ_LINES: usize = 2000;
+const MAX_SEARCH_RESULTS: usize = 200;
+
+/// Prefix of tools served by the in-process MCP handler
+const MCP_TOOL_PREFIX: &str = "mcp__agentic-mcp__";
+
+const LOCAL_TOOLS: &[&str] = &["Read", "Write", "Edit", "Glob", "Grep"];
+
+pub struct OpenAiCompatBackend {
+    name: String,
+    base_url: String,
+    api_key: Option<String>,
+    model: String,
+    max_turns: Option<i32>,
+    client: reqwest::Client,
+}
+
+impl OpenAiCompatBackend {
+    pub fn new(name: &str, base_url: &str, api_key: Option<String>, model: &str, max_turns: Option<i32>) -> Self {
+        Self {
+            name: name.to_string(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            api_key,
+            model: model.to_string(),
+            max_turns,
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// One chat completion: the assistant message and the tokens it used
+    async fn complete(&self, messages: &[Value], tools: &[Value]) -> Result<(Value, RunUsage)> {
+        let mut body = json!({ "model": self.model, "messages": messages });
+        if !tools.is_empty() {
+            body["tools"] = json!(tools);
+        }
+        let mut req = self.client.post(format!("{}/chat/completions", self.base_url)).json(&body);
+        if let Some(key) = &self.api_key {
+            req = req.bearer_auth(key);
+        }
+        let response = req.send().await.with_context(|| format!("{} request failed", self.name))?;
+        if !response.status().is_success() {
+            let status = response.status();
+            return Err(anyhow!("{} returned {}: {}", self.name, status, response.text().await.unwrap_or_default()));
+        }
+        let body: Value = response.json().await.context("Failed to parse chat completion")?;
+        let count = |key: &str| body.pointer(&format!("/usage/{}", key)).and_then(|v| v.as_u64()).unwrap_or(0);
+        let usage = RunUsage {
+            input_tokens: count("prompt_tokens"),
+            output_tokens: count("completion_tokens"),
+            cost_usd: 0.0,
+        };
+        let message = body
+            .pointer("/choices/0/message")
+            .cloned()
+            .ok_or_else(|| anyhow!("{} returned no message", self.name))?;
+        Ok((message, usage))
+    }
+}
+
+fn tool_schema(name: &str) -> Option<Value> {
+    let (description, parameters) = match name {
+        "Read" => (
+            "Read a file in the working directory. Returns numbered lines.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "file_path": { "type": "string" },
+                    "offset": { "type": "integer", "description": "1-based line to start from" },
+                    "limit": { "type": "integer" }
+                },
+                "required": ["file_path"]
+            }),
+        ),
+        "Write" => (
+            "Create or overwrite a file in the working directory.",
+            json!({
+                "type": "object",
+                "properties": { "file_path": { "type": "string" }, "content": { "type": "string" } },
+                "required": ["file_path", "content"]
+            }),
+        ),
+        "Edit" => (
+            "Replace an exact string in a file. old_string must be unique unless replace_all is true.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "file_path": { "type": "string" },
+                    "old_string": { "type": "string" },
+                    "new_string": { "type": "string" },
+                    "replace_all": { "type": "boolean" }
+                },
+                "required": ["file_path", "old_string", "new_string"]
+            }),
+        ),
+        "Glob" => (
+            "List files matching a glob pattern such as src/**/*.rs.",
+            json!({
+                "type": "object",
+                "properties": { "pattern": { "type": "string" }, "path": { "type": "string" } },
+                "required": ["pattern"]
+            }),
+        ),
+        "Grep" => (
+            "Search file contents with a regular expression. Returns path:line:text matches.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "pattern": { "type": "string" },
+                    "path": { "type": "string" },
+                    "glob": { "type": "string", "description": "Only search files matching this glob" }
+                },
+                "required": ["pattern"]
+            }),
+        ),
+        _ if name.starts_with(MCP_TOOL_PREFIX) => (
+            "Agentic Flowstate MCP tool. Pass the tool's arguments as a JSON object.",
+            json!({ "type": "object", "additionalProperties": true }),
+        ),
+        _ => return None,
+    };
+    Some(json!({
+        "type": "function",
+        "function": { "name": name, "description": description, "parameters": parameters }
+    }))
+}
+
+/// Resolve a tool path inside `root`, rejecting anything that escapes it
+fn resolve_path(root: &Path, path: &str) -> Result<PathBuf> {
+    let root = root.canonicalize().context("Working directory is not accessible")?;
+    let candidate = if Path::new(path).is_absolute() { PathBuf::from(path) } else { root.join(path) };
+
+    // Canonicalize the deepest existing ancestor so new files can be written
+    let mut existing = candidate.as_path();
+    let mut rest = Vec::new();
+    while !existing.exists() {
+        rest.push(existing.file_name().ok_or_else(|| anyhow!("Invalid path '{}'", path))?);
+        existing = existing.parent().ok_or_else(|| anyhow!("Invalid path '{}'", path))?;
+    }
+    let mut resolved = existing.canonicalize()?;
+    for part in rest.iter().rev() {
+        if *part == ".." {
+            return Err(anyhow!("Path '{}' is outside the working directory", path));
+        }
+        resolved.push(part);
+    }
+    if !resolved.starts_with(&root) {
+        return Err(anyhow!("Path '{}' is outside the working directory", path));
+    }
+    Ok(resolved)
+}
+
+/// Translate a glob (`*`, `**`, `?`) into an anchored regex over `/`-separated paths
+fn glob_to_regex(glob: &str) -> Result<Regex> {
+    let mut pattern = String::from("^");
+    let mut chars = glob.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                if chars.peek() == Some(&'/') {
+                    chars.next();
+                    pattern.push_str("(?:.*/)?");
+                } else {
+                    pattern.push_str(".*");
+                }
+            }
+            '*' => pattern.push_str("[^/]*"),
+            '?' => pattern.push_str("[^/]"),
+            c => pattern.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    pattern.push('$');
+    Ok(Regex::new(&pattern)?)
+}
+
+/// Files under `dir` (relative paths), skipping hidden entries and build output
+fn walk_files(dir: &Path) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    let mut stack = vec![dir.to_path_buf()];
+    while let Some(current) = stack.pop() {
+        let Ok(entries) = std::fs::read_dir(&current) else { continue };
+        for entry in entries.flatten() {
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            if name.starts_with('.') || name == "target" || name == "node_modules" {
+                continue;
+            }
+            let path = entry.path();
+            if path.is_dir() {
+                stack.push(path);
+            } else if let Ok(relative) = path.strip_prefix(dir) {
+                files.push(relative.to_path_buf());
+            }
+        }
+    }
+    files.sort();
+    files
+}
+
+fn str_arg<'a>(input: &'a Value, key: &str) -> Result<&'a str> {
+    input.get(key).and_then(|v| v.as_str()).ok_or_else(|| anyhow!("Missing '{}'", key))
+}
+
+/// Run one of the local file tools
+fn run_local_tool(root: &Path, name: &str, input: &Value) -> Result<String> {
+    match name {
+        "Read" => {
+            let content = std::fs::read_to_string(resolve_path(root, str_arg(input, "file_path")?)?)?;
+            let offset = input.get("offset").and_then(|v| v.as_u64()).unwrap_or(1).max(1) as usize;
+            let limit = input.get("limit").and_then(|v| v.as_u64()).map(|l| l as usize).unwrap_or(MAX_READ_LINES);
+            Ok(content
+                .lines()
+                .enumerate()
+                .skip(offset - 1)
+                .take(limit.min(MAX_READ_LINES))
+                .map(|(i, line)| format!("{:>6}\t{}", i + 1, line))
+                .collect::<Vec<_>>()
+                .join("\n"))
+        }
+        "Write" => {
+            let path = resolve_path(root, str_arg(input, "file_path")?)?;
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            std::fs::write(&path, str_arg(input, "content")?)?;
+            Ok(format!("Wrote {}", path.display()))
+        }
+        "Edit" => {
+            let path = resolve_path(root, str_arg(input, "file_path")?)?;
+            let content = std::fs::read_to_string(&path)?;
+            let old = str_arg(input, "old_string")?;
+            let new = str_arg(input, "new_string")?;
+            let replace_all = input.get("replace_all").and_then(|v| v.as_bool()).unwrap_or(false);
+            let updated = match content.matches(old).count() {
+                0 => return Err(anyhow!("old_string not found in {}", path.display())),
+                1 => content.replacen(old, new, 1),
+                _ if replace_all => content.replace(old, new),
+                n => return Err(anyhow!("old_string matches {} times; make it unique or set replace_all", n)),
+            };
+            std::fs::write(&path, updated)?;
+            Ok(format!("Edited {}", path.display()))
+        }
+        "Glob" => {
+            let base = resolve_path(root, input.get("path").and_then(|v| v.as_str()).unwrap_or("."))?;
+            let matcher = glob_to_regex(str_arg(input, "pattern")?)?;
+            let matches: Vec<String> = walk_files(&base)
+                .into_iter()
+                .map(|p| p.to_string_lossy().replace('\\', "/"))
+                .filter(|p| matcher.is_match(p))
+                .take(MAX_SEARCH_RESULTS)
+                .collect();
+            Ok(if matches.is_empty() { "No files found".to_string() } else { matches.join("\n") })
+        }
+        "Grep" => {
+            let base = resolve_path(root, input.get("path").and_then(|v| v.as_str()).unwrap_or("."))?;
+            let pattern = Regex::new(str_arg(input, "pattern")?)?;
+            let file_filter = input.get("glob").and_then(|v| v.as_str()).map(glob_to_regex).transpose()?;
+            let mut matches = Vec::new();
+            'files: for relative in walk_files(&base) {
+                let display = relative.to_string_lossy().replace('\\', "/");
+                if file_filter.as_ref().is_some_and(|f| !f.is_match(&display)) {
+                    continue;
+                }
+                let Ok(content) = std::fs::read_to_string(base.join(&relative)) else { continue };
+                for (i, line) in content.lines().enumerate() {
+                    if pattern.is_match(line) {
+                        matches.push(format!("{}:{}:{}", display, i + 1, line));
+                        if matches.len() >= MAX_SEARCH_RESULTS {
+                            break 'files;
+                        }
+                    }
+                }
+            }
+            Ok(if matches.is_empty() { "No matches found".to_string() } else { matches.join("\n") })
+        }
+        other => Err(anyhow!("Unsupported tool '{}'", other)),
+    }
+}
+
+async fn run_tool(root: &Path, name: &str, input: &Value) -> Result<String> {
+    if let Some(tool) = name.strip_prefix(MCP_TOOL_PREFIX) {
+        let result = crate::mcp_wrapper::call_mcp_tool(tool, Some(input.clone())).await?;
+        return Ok(serde_json::to_string_pretty(&result)?);
+    }
+    let root = root.to_path_buf();
+    let name = name.to_string();
+    let input = input.clone();
+    tokio::task::spawn_blocking(move || run_local_tool(&root, &name, &input)).await?
+}
+
+fn truncate(text: String) -> String {
+    if text.len() <= MAX_TOOL_RESULT_CHARS {
+        return text;
+    }
+    let mut end = MAX_TOOL_RESULT_CHARS;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    format!("{}\n[truncated]", &text[..end])
+}
+
+async fn send(event_tx: &Option<mpsc::Sender<StreamEvent>>, event: StreamEvent) {
+    if let Some(tx) = event_tx {
+        if let Err(e) = tx.send(event).await {
+            tracing::warn!("Failed to send stream event: {}", e);
+        }
+    }
+}
+
+#[async_trait]
+impl AgentBackend for OpenAiCompatBackend {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn run(
+        &self,
+        request: &BackendRequest<'_>,
+        event_tx: Option<mpsc::Sender<StreamEvent>>,
+    ) -> Result<BackendOutput> {
+        let agent_type = request.agent_type;
+        let mut tools = Vec::new();
+        for tool in agent_type.allowed_tools() {
+            match tool_schema(tool) {
+                Some(schema) => tools.push(schema),
+                None => tracing::warn!("Backend {} can't provide tool {}; leaving it out", self.name, tool),
+            }
+        }
+        let gate = request
+            .approval
+            .map(|approval| ToolApprovalGate::new(approval.clone(), event_tx.clone(), request.env));
+        let max_turns = agent_type.max_turns().or(self.max_turns).unwrap_or(DEFAULT_MAX_TURNS).max(1);
+
+        tracing::info!(
+            "Running {} on backend {} (model={}, tools={})",
+            agent_type.as_str(), self.name, self.model, tools.len()
+        );
+
+        let mut messages = vec![
+            json!({ "role": "system", "content": request.system_prompt }),
+            json!({ "role": "user", "content": request.prompt }),
+        ];
+        let mut output_parts = Vec::new();
+        let mut usage = RunUsage::default();
+        let mut loops = LoopDetector::new(agent_type.loop_guard());
+        let mut tool_log = ToolCallLog::new(request.env);
+
+        for turn in 1..=max_turns {
+            let (message, turn_usage) = self.complete(&messages, &tools).await?;
+            usage.add(turn_usage);
+            messages.push(message.clone());
+
+            if let Some(text) = message.get("content").and_then(|c| c.as_str()).filter(|t| !t.trim().is_empty()) {
+                loops.observe_text(text);
+                let text = redact(request.env, text);
+                output_parts.push(text.clone());
+                send(&event_tx, StreamEvent::Text { content: text }).await;
+            }
+
+            let calls = message.get("tool_calls").and_then(|c| c.as_array()).cloned().unwrap_or_default();
+            if calls.is_empty() {
+                send(&event_tx, StreamEvent::Result {
+                    session_id: request.session_id.to_string(),
+                    status: "success".to_string(),
+                    is_error: false,
+                }).await;
+                return Ok(BackendOutput {
+                    session_id: None,
+                    output_parts,
+                    status: AgentRunStatus::Completed,
+                    usage,
+                    error: None,
+                    tool_calls: tool_log.into_calls(),
+                });
+            }
+
+            let mut looping = None;
+            for call in calls {
+                let id = call.get("id").and_then(|v| v.as_str()).unwrap_or_default().to_string();
+                let name = call.pointer("/function/name").and_then(|v| v.as_str()).unwrap_or_default().to_string();
+                let input: Value = call
+                    .pointer("/function/arguments")
+                    .and_then(|v| v.as_str())
+                    .and_then(|args| serde_json::from_str(args).ok())
+                    .unwrap_or_else(|| json!({}));
+                tracing::info!("Tool use (turn {}): {} ({})", turn, name, id);
+                send(&event_tx, StreamEvent::ToolUse {
+                    id: id.clone(),
+                    name: name.clone(),
+                    input: redact_json(request.env, &input),
+                }).await;
+                if let Some(kind) = loops.observe_tool_use(&name, &input) {
+                    looping.get_or_insert(kind);
+                }
+
+                tool_log.start(&id, &name, &input);
+                let allowed = agent_type.allowed_tools().contains(&name.as_str());
+                let result = match &gate {
+                    _ if !allowed => Err(anyhow!("Tool '{}' is not available to this agent", name)),
+                    Some(gate) => match gate.check(&name, &input).await {
+                        Ok(()) => run_tool(request.working_dir, &name, &input).await,
+                        Err(message) => Err(anyhow!(message)),
+                    },
+                    None => run_tool(request.working_dir, &name, &input).await,
+                };
+                let (content, is_error) = match result {
+                    Ok(content) => (truncate(redact(request.env, &content)), false),
+                    Err(e) => (format!("Error: {}", e), true),
+                };
+                tool_log.finish(&id, is_error);
+                send(&event_tx, StreamEvent::ToolResult {
+                    tool_use_id: id.clone(),
+                    content: content.clone(),
+                    is_error,
+                }).await;
+                messages.push(json!({ "role": "tool", "tool_call_id": id, "content": content }));
+            }
+
+            // Every call still gets its result; the intervention follows them
+            let stalled = loops.end_turn();
+            if let Some(kind) = looping.or(stalled) {
+                let intervention = loops.intervene(&kind);
+                tracing::warn!("{} on backend {}: {} ({})", agent_type.as_str(), self.name, kind.describe(), intervention.action());
+                send(&event_tx, intervention.warning(&kind)).await;
+                match intervention {
+                    Intervention::Remind(reminder) => messages.push(json!({ "role": "user", "content": reminder })),
+                    Intervention::Abort(error) => {
+                        send(&event_tx, StreamEvent::Result {
+                            session_id: request.session_id.to_string(),
+                            status: "error_looping".to_string(),
+                            is_error: true,
+                        }).await;
+                        return Ok(BackendOutput {
+                            session_id: None,
+                            output_parts,
+                            status: AgentRunStatus::Failed,
+                            usage,
+                            error: Some(error),
+                            tool_calls: tool_log.into_calls(),
+                        });
+                    }
+                }
+            }
+        }
+
+        tracing::warn!("{} hit the {}-turn limit on backend {}", agent_type.as_str(), max_turns, self.name);
+        send(&event_tx, StreamEvent::Result {
+            session_id: request.session_id.to_string(),
+            status: "error_max_turns".to_string(),
+            is_error: true,
+        }).await;
+        Ok(BackendOutput {
+            session_id: None,
+            output_parts,
+            status: AgentRunStatus::Failed,
+            usage,
+            error: None,
+            tool_calls: tool_log.into_calls(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn glob_patterns_match_nested_paths() {
+        let rust = glob_to_regex("src/**/*.rs").unwrap();
+        assert!(rust.is_match("src/main.rs"));
+        assert!(rust.is_match("src/agents/executor.rs"));
+        assert!(!rust.is_match("tests/main.rs"));
+        assert!(glob_to_regex("*.md").unwrap().is_match("README.md"));
+        assert!(!glob_to_regex("*.md").unwrap().is_match("docs/guide.md"));
+    }
+}
@@ -26,6 +26,21 @@ pub fn load_prompt(agent_type: &str, vars: HashMap<String, String>) -> Result<St
     Ok(result)
 }
 
+/// Short content hash of a prompt template, used to tell prompt revisions apart
+/// when comparing agent run quality. Returns `None` if the template is missing.
+pub fn prompt_version(agent_type: &str) -> Option<String> {
+    let prompt_file = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("_prompts")
+        .join(format!("{}.txt", agent_type));
+    let template = fs::read(&prompt_file).ok()?;
+
+    // FNV-1a: stable across builds, unlike std's DefaultHasher
+    let hash = template.iter().fold(0xcbf29ce484222325u64, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+    });
+    Some(format!("{:016x}", hash)[..12].to_string())
+}
+
 fn process_conditionals(template: &str, vars: &HashMap<String, String>) -> String {
     let mut result = template.to_string();
 
@@ -37,8 +52,8 @@ fn process_conditionals(template: &str, vars: &HashMap<String, String>) -> Strin
             let var_name = caps.get(1).map(|m| m.as_str()).unwrap_or("");
             let content = caps.get(2).map(|m| m.as_str()).unwrap_or("");
 
-            // Check if variable exists and is non-empty
-            if vars.get(&var_name.to_uppercase()).map(|v| !v.is_empty()).unwrap_or(false) {
+            // Check if variable exists and is non-empty (vars are keyed lowercase)
+            if vars.get(&var_name.to_lowercase()).map(|v| !v.is_empty()).unwrap_or(false) {
                 content.to_string()
             } else {
                 String::new()
@@ -63,4 +78,14 @@ mod tests {
         // Note: load_prompt reads from file, so this test would need the file to exist
         // This is a basic test of the substitution logic
     }
+
+    #[test]
+    fn conditionals_match_vars_case_insensitively() {
+        let vars = HashMap::from([
+            ("variant_guidance".to_string(), "Be brief.".to_string()),
+            ("checklist".to_string(), String::new()),
+        ]);
+        let template = "Draft.{{#if VARIANT_GUIDANCE}} Style: Be brief.{{/if}}{{#if CHECKLIST}} Checklist{{/if}}{{#if MISSING}} x{{/if}}";
+        assert_eq!(process_conditionals(template, &vars), "Draft. Style: Be brief.");
+    }
 }
@@ -0,0 +1,127 @@
+//! Context snapshots
+//!
+//! Every run records exactly what the agent was given: the resolved system
+//! prompt, the prompt variables, which context sections were included, and the
+//! model settings. Snapshots are stored gzip-compressed alongside the run so
+//! an unexpected output can be traced back to its inputs.
+
+use std::collections::BTreeMap;
+use std::io::{Read, Write};
+
+use anyhow::{Context, Result};
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+use crate::redaction::RedactionAudit;
+
+/// One block of context injected into the prompt
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ContextSection {
+    /// Prompt variable the section was passed as
+    pub name: String,
+    pub chars: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContextSnapshot {
+    pub agent_type: String,
+    pub model: String,
+    pub max_turns: Option<i32>,
+    /// Backend that produced the output (or the last one tried)
+    pub backend: String,
+    pub allowed_tools: Vec<String>,
+    pub working_dir: String,
+    pub system_prompt: String,
+    pub prompt: String,
+    /// Sorted so snapshots of similar runs diff cleanly
+    pub prompt_vars: BTreeMap<String, String>,
+    pub sections: Vec<ContextSection>,
+    /// PII redacted from email and transcript context, counted per rule
+    #[serde(default)]
+    pub redactions: RedactionAudit,
+    pub captured_at: String,
+}
+
+/// Non-empty prompt variables, largest first
+pub fn summarize_sections(vars: &BTreeMap<String, String>) -> Vec<ContextSection> {
+    let mut sections: Vec<ContextSection> = vars
+        .iter()
+        .filter(|(_, value)| !value.trim().is_empty())
+        .map(|(name, value)| ContextSection { name: name.clone(), chars: value.chars().count() })
+        .collect();
+    sections.sort_by(|a, b| b.chars.cmp(&a.chars).then_with(|| a.name.cmp(&b.name)));
+    sections
+}
+
+pub fn compress(snapshot: &ContextSnapshot) -> Result<Vec<u8>> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(&serde_json::to_vec(snapshot)?)?;
+    Ok(encoder.finish()?)
+}
+
+pub fn decompress(bytes: &[u8]) -> Result<ContextSnapshot> {
+    let mut json = Vec::new();
+    GzDecoder::new(bytes)
+        .read_to_end(&mut json)
+        .context("Context snapshot is not valid gzip")?;
+    serde_json::from_slice(&json).context("Context snapshot is not valid JSON")
+}
+
+/// Store a run's snapshot. Failures are logged; they never fail the run.
+pub async fn store_snapshot(db: &SqlitePool, session_id: &str, snapshot: &ContextSnapshot) {
+    let bytes = match compress(snapshot) {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            tracing::warn!("Failed to compress context snapshot for {}: {}", session_id, e);
+            return;
+        }
+    };
+    if let Err(e) = ticketing_system::agent_runs::store_context_snapshot(db, session_id, &bytes).await {
+        tracing::warn!("Failed to store context snapshot for {}: {}", session_id, e);
+    }
+}
+
+pub async fn load_snapshot(db: &SqlitePool, session_id: &str) -> Result<Option<ContextSnapshot>> {
+    match ticketing_system::agent_runs::get_context_snapshot(db, session_id).await? {
+        Some(bytes) => decompress(&bytes).map(Some),
+        None => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_and_summarizes() {
+        let mut vars = BTreeMap::new();
+        vars.insert("ticket_intent".to_string(), "Fix the login page".to_string());
+        vars.insert("research_corpus".to_string(), "x".repeat(500));
+        vars.insert("checklist".to_string(), "  ".to_string());
+
+        let sections = summarize_sections(&vars);
+        assert_eq!(sections.len(), 2);
+        assert_eq!(sections[0], ContextSection { name: "research_corpus".to_string(), chars: 500 });
+
+        let snapshot = ContextSnapshot {
+            agent_type: "execution".to_string(),
+            model: "claude-sonnet".to_string(),
+            max_turns: Some(40),
+            backend: "claude-code".to_string(),
+            allowed_tools: vec!["Read".to_string()],
+            working_dir: "/tmp".to_string(),
+            system_prompt: "You are an execution agent.".repeat(100),
+            prompt: "Work on this ticket".to_string(),
+            prompt_vars: vars,
+            sections,
+            redactions: RedactionAudit::default(),
+            captured_at: "2026-01-01T00:00:00Z".to_string(),
+        };
+        let bytes = compress(&snapshot).unwrap();
+        assert!(bytes.len() < snapshot.system_prompt.len());
+        let restored = decompress(&bytes).unwrap();
+        assert_eq!(restored.system_prompt, snapshot.system_prompt);
+        assert_eq!(restored.prompt_vars, snapshot.prompt_vars);
+    }
+}
@@ -0,0 +1,237 @@
+//! Tool audit trail
+//!
+//! Backends log every tool call an agent makes in a `ToolCallLog`: the tool,
+//! a short summary of its input, how long it took and whether it failed. The
+//! calls are stored per run in their own table (see
+//! `ticketing_system::tool_audit`), so they can be queried and aggregated
+//! without replaying the run's stream events. Input summaries are redacted of
+//! secrets and PII and truncated; full inputs stay in the event stream only.
+//!
+//! Agents run outside the executor (chats, email and meeting agents) log their
+//! calls with `ToolCallLog::observe` and store them with their organization and
+//! agent type, since they have no agent run whose ticket says whose they are.
+//! Shadow steps do the same.
+
+use cc_sdk::{ContentBlock, Message};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::time::Instant;
+use ticketing_system::{tool_audit, CreateToolAuditRequest, SqlitePool};
+
+use super::backend::redact;
+use super::AgentType;
+
+/// Longest input summary stored, in characters
+const MAX_SUMMARY_CHARS: usize = 200;
+
+/// Input fields that say what a call did, in the order they're shown
+const SUMMARY_FIELDS: &[&str] = &[
+    "command", "file_path", "notebook_path", "path", "pattern", "glob", "url", "query", "ticket_id", "description",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ToolCallStatus {
+    Ok,
+    Error,
+    /// No result was seen, e.g. the run ended or was aborted mid-call
+    Unknown,
+}
+
+impl ToolCallStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ToolCallStatus::Ok => "ok",
+            ToolCallStatus::Error => "error",
+            ToolCallStatus::Unknown => "unknown",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolCall {
+    pub tool_use_id: String,
+    pub tool_name: String,
+    pub input_summary: String,
+    pub started_at: String,
+    /// Until the result arrived. `None` when the run ended first.
+    pub duration_ms: Option<u64>,
+    pub status: ToolCallStatus,
+}
+
+/// One-line summary of a tool's input: the fields that say what it did, or
+/// the whole input when it has none of them
+fn summarize(input: &Value) -> String {
+    let fields: Vec<String> = SUMMARY_FIELDS
+        .iter()
+        .filter_map(|key| {
+            let value = input.get(key)?;
+            let value = value.as_str().map(str::to_string).unwrap_or_else(|| value.to_string());
+            Some(format!("{}: {}", key, value))
+        })
+        .collect();
+    let summary = if fields.is_empty() { input.to_string() } else { fields.join(", ") };
+    summary.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn truncate(text: String) -> String {
+    match text.char_indices().nth(MAX_SUMMARY_CHARS) {
+        Some((end, _)) => format!("{}…", &text[..end]),
+        None => text,
+    }
+}
+
+/// Tool results (`tool_use_id`, `is_error`) anywhere in a message's JSON
+fn tool_results(value: &Value, results: &mut Vec<(String, bool)>) {
+    match value {
+        Value::Object(map) => {
+            if let Some(id) = map.get("tool_use_id").and_then(|v| v.as_str()) {
+                let is_error = map.get("is_error").and_then(|v| v.as_bool()).unwrap_or(false);
+                results.push((id.to_string(), is_error));
+                return;
+            }
+            map.values().for_each(|v| tool_results(v, results));
+        }
+        Value::Array(items) => items.iter().for_each(|v| tool_results(v, results)),
+        _ => {}
+    }
+}
+
+/// The tool calls of one backend run, timed from call to result
+pub struct ToolCallLog<'a> {
+    /// Organization secrets, redacted from input summaries
+    env: &'a HashMap<String, String>,
+    calls: Vec<ToolCall>,
+    /// Index into `calls` and start time of calls without a result yet
+    pending: HashMap<String, (usize, Instant)>,
+}
+
+impl<'a> ToolCallLog<'a> {
+    pub fn new(env: &'a HashMap<String, String>) -> Self {
+        Self { env, calls: Vec::new(), pending: HashMap::new() }
+    }
+
+    pub fn start(&mut self, tool_use_id: &str, tool_name: &str, input: &Value) {
+        let (summary, _) = crate::redaction::redact(&redact(self.env, &summarize(input)));
+        self.pending.insert(tool_use_id.to_string(), (self.calls.len(), Instant::now()));
+        self.calls.push(ToolCall {
+            tool_use_id: tool_use_id.to_string(),
+            tool_name: tool_name.to_string(),
+            input_summary: truncate(summary),
+            started_at: chrono::Utc::now().to_rfc3339(),
+            duration_ms: None,
+            status: ToolCallStatus::Unknown,
+        });
+    }
+
+    pub fn finish(&mut self, tool_use_id: &str, is_error: bool) {
+        if let Some((idx, started)) = self.pending.remove(tool_use_id) {
+            let call = &mut self.calls[idx];
+            call.duration_ms = Some(started.elapsed().as_millis() as u64);
+            call.status = if is_error { ToolCallStatus::Error } else { ToolCallStatus::Ok };
+        }
+    }
+
+    /// Finish the calls whose results are in a message from the CLI
+    pub fn finish_from_message(&mut self, message: &Value) {
+        let mut results = Vec::new();
+        tool_results(message, &mut results);
+        for (id, is_error) in results {
+            self.finish(&id, is_error);
+        }
+    }
+
+    /// Log the tool calls and results in a message from the CLI
+    pub fn observe(&mut self, message: &Message) {
+        match message {
+            Message::Assistant { message } => {
+                for block in &message.content {
+                    match block {
+                        ContentBlock::ToolUse(tool_use) => self.start(&tool_use.id, &tool_use.name, &tool_use.input),
+                        ContentBlock::ToolResult(result) => {
+                            self.finish(&result.tool_use_id, result.is_error.unwrap_or(false))
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            Message::User { .. } => match serde_json::to_value(message) {
+                Ok(value) => self.finish_from_message(&value),
+                Err(e) => tracing::debug!("Failed to read tool results: {}", e),
+            },
+            _ => {}
+        }
+    }
+
+    pub fn into_calls(self) -> Vec<ToolCall> {
+        self.calls
+    }
+}
+
+/// Store an agent run's tool calls. Failures are logged; they never fail the run.
+pub async fn store_tool_calls(db: &SqlitePool, session_id: &str, calls: &[ToolCall]) {
+    store(db, session_id, None, calls).await
+}
+
+/// Store the tool calls of a run without an agent run record, for
+/// `organization` (if any) and `agent_type`. `session_id` is the CLI's when
+/// the run got far enough to report one.
+pub async fn store_standalone_tool_calls(
+    db: &SqlitePool,
+    session_id: Option<&str>,
+    organization: Option<&str>,
+    agent_type: &AgentType,
+    calls: &[ToolCall],
+) {
+    let session_id = session_id.map(str::to_string).unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+    store(db, &session_id, Some((organization, agent_type)), calls).await
+}
+
+async fn store(db: &SqlitePool, session_id: &str, owner: Option<(Option<&str>, &AgentType)>, calls: &[ToolCall]) {
+    if calls.is_empty() {
+        return;
+    }
+    let requests: Vec<CreateToolAuditRequest> = calls
+        .iter()
+        .map(|call| CreateToolAuditRequest {
+            session_id: session_id.to_string(),
+            tool_use_id: call.tool_use_id.clone(),
+            tool_name: call.tool_name.clone(),
+            input_summary: call.input_summary.clone(),
+            started_at: call.started_at.clone(),
+            duration_ms: call.duration_ms.map(|ms| ms as i64),
+            status: call.status.as_str().to_string(),
+            organization: owner.and_then(|(organization, _)| organization.map(str::to_string)),
+            agent_type: owner.map(|(_, agent_type)| agent_type.as_str().to_string()),
+        })
+        .collect();
+    if let Err(e) = tool_audit::record_calls(db, &requests).await {
+        tracing::warn!("Failed to store {} tool calls for {}: {}", calls.len(), session_id, e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn summarizes_what_a_call_did() {
+        assert_eq!(summarize(&json!({"command": "cargo  test\n-q", "timeout": 60})), "command: cargo test -q");
+        assert_eq!(
+            summarize(&json!({"pattern": "fn main", "path": "src"})),
+            "path: src, pattern: fn main"
+        );
+        assert_eq!(summarize(&json!({"limit": 5})), "{\"limit\":5}");
+        assert_eq!(truncate("é".repeat(300)).chars().count(), MAX_SUMMARY_CHARS + 1);
+
+        let mut results = Vec::new();
+        let message = json!({"type": "user", "message": {"content": [
+            {"type": "tool_result", "tool_use_id": "a", "content": "ok"},
+            {"type": "tool_result", "tool_use_id": "b", "is_error": true},
+        ]}});
+        tool_results(&message, &mut results);
+        assert_eq!(results, vec![("a".to_string(), false), ("b".to_string(), true)]);
+    }
+}
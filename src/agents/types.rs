@@ -1,7 +1,13 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::sync::RwLock;
+use anyhow::Context;
 use once_cell::sync::Lazy;
 
+use super::snapshot::ContextSnapshot;
+use super::failure::FailureReason;
+use super::loops::LoopGuardConfig;
+
 /// Agent configuration loaded from agents.json
 #[derive(Debug, Clone, Deserialize)]
 pub struct AgentConfig {
@@ -15,27 +21,91 @@ pub struct AgentConfig {
     /// If not set, defaults to the base projects directory.
     #[serde(default)]
     pub working_dir: Option<String>,
+    /// Tools that pause for user approval during streamed runs (e.g. "Bash", "Write")
+    #[serde(default)]
+    pub approval_tools: Vec<String>,
+    /// Name of an entry in `backends`; unset runs on Claude Code
+    #[serde(default)]
+    pub backend: Option<String>,
+    /// Backend to retry on when the primary one fails outright
+    #[serde(default)]
+    pub fallback_backend: Option<String>,
+    /// Repetition limits and what to do when the agent loops; defaults apply when unset
+    #[serde(default)]
+    pub loop_guard: Option<LoopGuardConfig>,
 }
 
+/// An agent backend other than Claude Code, from the `backends` section of agents.json
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum BackendConfig {
+    /// Any chat-completions API with function calling (OpenAI, vLLM, Ollama, ...)
+    OpenaiCompatible {
+        base_url: String,
+        /// Environment variable holding the API key, if the endpoint needs one
+        #[serde(default)]
+        api_key_env: Option<String>,
+        /// Model used for every agent on this backend
+        model: String,
+        /// Turn limit when the agent config has none
+        #[serde(default)]
+        max_turns: Option<i32>,
+    },
+}
+
+/// Backend name that always refers to the built-in Claude Code backend
+pub const CLAUDE_CODE_BACKEND: &str = "claude-code";
+
 /// Root config structure from agents.json
 #[derive(Debug, Clone, Deserialize)]
 pub struct AgentsConfig {
     pub models: HashMap<String, String>,
     pub agents: HashMap<String, AgentConfig>,
+    #[serde(default)]
+    pub backends: HashMap<String, BackendConfig>,
 }
 
-/// Global config loaded once at startup
-static CONFIG: Lazy<AgentsConfig> = Lazy::new(|| {
-    let config_path = std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("agents.json");
-    let config_str = std::fs::read_to_string(&config_path)
-        .unwrap_or_else(|e| panic!("Failed to read agents.json at {:?}: {}", config_path, e));
-    serde_json::from_str(&config_str)
-        .unwrap_or_else(|e| panic!("Failed to parse agents.json: {}", e))
+/// Global config, loaded at startup and swapped on reload.
+///
+/// Each reload leaks the previous config so `&'static` borrows held by running
+/// agents stay valid. Reloads are manual and rare, so the leak is bounded.
+static CONFIG: Lazy<RwLock<&'static AgentsConfig>> = Lazy::new(|| {
+    let config = AgentsConfig::load().unwrap_or_else(|e| panic!("{:#}", e));
+    RwLock::new(Box::leak(Box::new(config)))
 });
 
 impl AgentsConfig {
     pub fn get() -> &'static AgentsConfig {
-        &CONFIG
+        *CONFIG.read().unwrap()
+    }
+
+    fn load() -> anyhow::Result<AgentsConfig> {
+        let config_path = std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("agents.json");
+        let config_str = std::fs::read_to_string(&config_path)
+            .with_context(|| format!("Failed to read agents.json at {:?}", config_path))?;
+        let config: AgentsConfig = serde_json::from_str(&config_str).context("Failed to parse agents.json")?;
+
+        // Every agent type must keep a config, or `AgentType::config` would panic mid-run
+        if let Some(missing) = AgentType::all().iter().find(|t| !config.agents.contains_key(t.as_str())) {
+            anyhow::bail!("agents.json has no config for agent type '{}'", missing.as_str());
+        }
+        for (name, agent) in &config.agents {
+            for backend in agent.backend.iter().chain(agent.fallback_backend.iter()) {
+                if backend != CLAUDE_CODE_BACKEND && !config.backends.contains_key(backend) {
+                    anyhow::bail!("Agent '{}' uses unknown backend '{}'", name, backend);
+                }
+            }
+        }
+        Ok(config)
+    }
+
+    /// Re-read agents.json. The current config is kept if the new one is invalid.
+    /// Returns the number of configured agents.
+    pub fn reload() -> anyhow::Result<usize> {
+        let config = AgentsConfig::load()?;
+        let count = config.agents.len();
+        *CONFIG.write().unwrap() = Box::leak(Box::new(config));
+        Ok(count)
     }
 
     /// Resolve model alias to full model ID
@@ -68,9 +138,43 @@ pub enum AgentType {
     LifePlanner,
     /// Selects the best next ticket to work on for a given organization
     PullTicket,
+    /// Estimates effort and flags risks from the ticket description, artifacts and history
+    Estimator,
+    /// Summarizes the day's email: new threads, threads awaiting a reply, tickets filed from email
+    EmailDigest,
+    /// Suggests a few short replies to an email: acknowledge, ask for details, decline
+    EmailReplies,
+    /// Folds old conversation messages into a compact memory of the thread
+    ConversationMemory,
+    /// Proposes a ticket's title, description, place and labels from an email
+    EmailTicket,
 }
 
 impl AgentType {
+    pub fn all() -> &'static [AgentType] {
+        &[
+            AgentType::Planning,
+            AgentType::Execution,
+            AgentType::Evaluation,
+            AgentType::Email,
+            AgentType::WorkspaceManager,
+            AgentType::MeetingNotes,
+            AgentType::TicketAssistant,
+            AgentType::ExaResearch,
+            AgentType::ResearchSynthesis,
+            AgentType::TicketPlanner,
+            AgentType::TicketCreator,
+            AgentType::DocDrafter,
+            AgentType::LifePlanner,
+            AgentType::PullTicket,
+            AgentType::Estimator,
+            AgentType::EmailDigest,
+            AgentType::EmailReplies,
+            AgentType::ConversationMemory,
+            AgentType::EmailTicket,
+        ]
+    }
+
     pub fn as_str(&self) -> &'static str {
         match self {
             AgentType::Planning => "planning",
@@ -87,6 +191,11 @@ impl AgentType {
             AgentType::DocDrafter => "doc-drafter",
             AgentType::LifePlanner => "life-planner",
             AgentType::PullTicket => "pull-ticket",
+            AgentType::Estimator => "estimator",
+            AgentType::EmailDigest => "email-digest",
+            AgentType::EmailReplies => "email-replies",
+            AgentType::ConversationMemory => "conversation-memory",
+            AgentType::EmailTicket => "email-ticket",
         }
     }
 
@@ -113,6 +222,25 @@ impl AgentType {
     pub fn max_turns(&self) -> Option<i32> {
         self.config().max_turns
     }
+
+    pub fn approval_tools(&self) -> Vec<String> {
+        self.config().approval_tools.clone()
+    }
+
+    pub fn loop_guard(&self) -> LoopGuardConfig {
+        self.config().loop_guard.clone().unwrap_or_default()
+    }
+
+    /// Backend names to try in order: the configured one, then its fallback
+    pub fn backends(&self) -> Vec<&str> {
+        let config = self.config();
+        let primary = config.backend.as_deref().unwrap_or(CLAUDE_CODE_BACKEND);
+        let mut backends = vec![primary];
+        if let Some(fallback) = config.fallback_backend.as_deref().filter(|f| *f != primary) {
+            backends.push(fallback);
+        }
+        backends
+    }
 }
 
 /// Structured email output parsed from agent response
@@ -208,6 +336,38 @@ pub struct AgentRun {
     /// Structured email output (only for email agent type)
     #[serde(skip_serializing_if = "Option::is_none")]
     pub email_output: Option<EmailOutput>,
+    /// Why a failed run failed, see `agents::failure`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub failure_reason: Option<FailureReason>,
+    /// What the agent was given; stored separately, see `GET /api/agent-runs/:session_id/context`
+    #[serde(skip)]
+    pub context_snapshot: Option<ContextSnapshot>,
+    /// Tokens and cost, counted against the organization's quota
+    #[serde(skip)]
+    pub usage: RunUsage,
+    /// Every tool call the agent made; stored separately, see `GET /api/agent-runs/:session_id/tools`
+    #[serde(skip)]
+    pub tool_calls: Vec<super::ToolCall>,
+}
+
+/// Tokens and cost a run consumed, as reported by its backend(s)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct RunUsage {
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub cost_usd: f64,
+}
+
+impl RunUsage {
+    pub fn tokens(&self) -> u64 {
+        self.input_tokens + self.output_tokens
+    }
+
+    pub fn add(&mut self, other: RunUsage) {
+        self.input_tokens += other.input_tokens;
+        self.output_tokens += other.output_tokens;
+        self.cost_usd += other.cost_usd;
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -230,13 +390,25 @@ impl AgentRunStatus {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TicketContext {
     pub epic_id: String,
     pub slice_id: String,
     pub ticket_id: String,
     pub title: String,
     pub intent: String,
+    /// Rendered checklist items (`- [ ] (id) text`), if the ticket has any
+    pub checklist: Option<String>,
+    /// Research corpus documents from the ticket's epic, for agents that use them
+    pub research: Option<String>,
+    /// Resolved pipeline step inputs (`- key: value`), when run as a step that has any
+    pub step_inputs: Option<String>,
+    /// Earlier agent output and ticket history, for the estimator
+    pub estimation: Option<String>,
+    /// The organization's email templates, for the email agent
+    pub email_templates: Option<String>,
+    /// Outputs pinned to the ticket as its canonical plan or spec
+    pub pinned_outputs: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -255,6 +427,17 @@ pub struct RunAgentRequest {
     /// (transition through Running → Completed/Failed) and advances the pipeline.
     #[serde(default)]
     pub step_id: Option<String>,
+    /// Tool names that must be approved before use, on top of the agent's
+    /// configured `approval_tools`, which always apply.
+    #[serde(default)]
+    pub approval_tools: Option<Vec<String>>,
+    /// Context pack to inject. Defaults to the bound pipeline step's pack.
+    #[serde(default)]
+    pub context_pack_id: Option<String>,
+    /// For email agent: `tone`, `length` and `language` of the email.
+    /// Settings left unset fall back to the bound step's inputs.
+    #[serde(default, flatten)]
+    pub style: crate::email_style::EmailStyle,
 }
 
 #[derive(Debug, Serialize)]
@@ -280,6 +463,9 @@ pub struct AgentRunsResponse {
 pub enum StreamEvent {
     /// Text content from the assistant
     Text { content: String },
+    /// Partial text as it is generated, numbered from 0 per stream. The `Text`
+    /// event for the block follows and supersedes its deltas; deltas are not stored.
+    TextDelta { seq: u64, content: String },
     /// Tool use request
     ToolUse {
         id: String,
@@ -302,6 +488,18 @@ pub enum StreamEvent {
         status: String,
         is_error: bool,
     },
+    /// A gated tool call is waiting for POST /api/agent-runs/:session_id/approve-tool
+    ApprovalRequired {
+        request_id: String,
+        tool_name: String,
+        input: serde_json::Value,
+    },
+    /// The agent is repeating itself; `action` is "remind" or "abort"
+    LoopWarning {
+        kind: String,
+        message: String,
+        action: String,
+    },
     /// Sent after all historical events have been replayed during reconnection
     ReplayComplete {
         total_events: usize,
@@ -0,0 +1,181 @@
+//! Pipeline analytics rollups
+//!
+//! A background job folds raw agent runs and pipeline step events into daily
+//! rollup rows (one per organization and agent type, one per organization and
+//! step) so `/api/analytics/pipelines` can answer date-range queries without
+//! scanning run history. Runs and events take the organization of their ticket.
+//! Each refresh recomputes whole days, so it is safe to re-run for any range.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use chrono::{Duration, NaiveDate, Utc};
+use ticketing_system::{
+    agent_runs, analytics, ticket_history, tickets, AgentRun, AgentTypeRollup, SqlitePool, StepEvent, StepRollup,
+};
+
+/// How often rollups for recent days are recomputed
+const REFRESH_INTERVAL_SECS: u64 = 60 * 60;
+
+/// Days recomputed on every refresh (today and yesterday catch late-finishing runs)
+const REFRESH_RECENT_DAYS: i64 = 2;
+
+/// Days backfilled at startup
+const BACKFILL_DAYS: i64 = 30;
+
+/// Step event kinds recorded via `ticket_history::log_step_event`
+pub const STEP_EVENT_FAILED: &str = "failed";
+pub const STEP_EVENT_REJECTED: &str = "rejected";
+pub const STEP_EVENT_RETRIED: &str = "retried";
+
+/// Record a pipeline step event for analytics, logging instead of failing
+pub async fn record_step_event(db: &SqlitePool, ticket_id: &str, step_id: &str, agent_type: &str, event: &str) {
+    if let Err(e) = ticket_history::log_step_event(db, ticket_id, step_id, agent_type, event).await {
+        tracing::warn!("Failed to record {} event for step {} on {}: {}", event, step_id, ticket_id, e);
+    }
+}
+
+/// Duration of a finished run in seconds
+fn run_duration_secs(run: &AgentRun) -> Option<f64> {
+    let started = chrono::DateTime::parse_from_rfc3339(&run.started_at).ok()?;
+    let completed = chrono::DateTime::parse_from_rfc3339(run.completed_at.as_deref()?).ok()?;
+    let secs = completed.signed_duration_since(started).num_milliseconds() as f64 / 1000.0;
+    (secs >= 0.0).then_some(secs)
+}
+
+/// Build one day's rollups from that day's runs and step events, given the
+/// organization of each ticket. Runs and events of unknown tickets are left out.
+pub fn compute_day_rollups(
+    day: NaiveDate,
+    runs: &[AgentRun],
+    events: &[StepEvent],
+    organizations: &HashMap<String, String>,
+) -> (Vec<AgentTypeRollup>, Vec<StepRollup>) {
+    let day_str = day.format("%Y-%m-%d").to_string();
+
+    let mut by_agent: HashMap<(&str, &str), AgentTypeRollup> = HashMap::new();
+    for run in runs {
+        let Some(organization) = organizations.get(&run.ticket_id) else {
+            continue;
+        };
+        let rollup = by_agent.entry((organization.as_str(), run.agent_type.as_str())).or_insert_with(|| AgentTypeRollup {
+            day: day_str.clone(),
+            organization: organization.clone(),
+            agent_type: run.agent_type.clone(),
+            total: 0,
+            completed: 0,
+            failed: 0,
+            durations_secs: Vec::new(),
+        });
+        rollup.total += 1;
+        match run.status.as_str() {
+            "completed" => rollup.completed += 1,
+            "failed" => rollup.failed += 1,
+            _ => {}
+        }
+        if let Some(secs) = run_duration_secs(run) {
+            rollup.durations_secs.push(secs);
+        }
+    }
+
+    let mut by_step: HashMap<(&str, &str, &str), StepRollup> = HashMap::new();
+    for event in events {
+        let Some(organization) = organizations.get(&event.ticket_id) else {
+            continue;
+        };
+        let rollup = by_step
+            .entry((organization.as_str(), event.step_id.as_str(), event.agent_type.as_str()))
+            .or_insert_with(|| StepRollup {
+                day: day_str.clone(),
+                organization: organization.clone(),
+                step_id: event.step_id.clone(),
+                agent_type: event.agent_type.clone(),
+                failures: 0,
+                rejections: 0,
+                retries: 0,
+            });
+        match event.event.as_str() {
+            STEP_EVENT_FAILED => rollup.failures += 1,
+            STEP_EVENT_REJECTED => rollup.rejections += 1,
+            STEP_EVENT_RETRIED => rollup.retries += 1,
+            _ => {}
+        }
+    }
+
+    (by_agent.into_values().collect(), by_step.into_values().collect())
+}
+
+/// Recompute and store rollups for every day in `[from, to]`
+pub async fn refresh_rollups(db: &SqlitePool, from: NaiveDate, to: NaiveDate) -> anyhow::Result<()> {
+    let mut organizations: HashMap<String, String> = HashMap::new();
+    let mut day = from;
+    while day <= to {
+        let start = format!("{}T00:00:00Z", day.format("%Y-%m-%d"));
+        let end = format!("{}T00:00:00Z", (day + Duration::days(1)).format("%Y-%m-%d"));
+
+        let runs = agent_runs::list_runs_started_between(db, &start, &end).await?;
+        let events = ticket_history::list_step_events(db, &start, &end).await?;
+        let ticket_ids = runs.iter().map(|r| &r.ticket_id).chain(events.iter().map(|e| &e.ticket_id));
+        for ticket_id in ticket_ids {
+            if !organizations.contains_key(ticket_id) {
+                if let Some(ticket) = tickets::get_ticket_by_id(db, ticket_id).await? {
+                    organizations.insert(ticket_id.clone(), ticket.organization);
+                }
+            }
+        }
+        let (agent_rollups, step_rollups) = compute_day_rollups(day, &runs, &events, &organizations);
+        analytics::replace_rollups(db, &day.format("%Y-%m-%d").to_string(), &agent_rollups, &step_rollups).await?;
+
+        day += Duration::days(1);
+    }
+    Ok(())
+}
+
+/// Backfill recent history, then keep the last couple of days fresh
+pub fn start_analytics_rollup_job(db: Arc<SqlitePool>) {
+    tokio::spawn(async move {
+        let today = Utc::now().date_naive();
+        if let Err(e) = refresh_rollups(&db, today - Duration::days(BACKFILL_DAYS), today).await {
+            tracing::error!("Analytics backfill failed: {:?}", e);
+        }
+
+        let mut interval = tokio::time::interval(std::time::Duration::from_secs(REFRESH_INTERVAL_SECS));
+        interval.tick().await;
+        loop {
+            interval.tick().await;
+            if crate::maintenance::is_enabled() {
+                continue;
+            }
+            let today = Utc::now().date_naive();
+            if let Err(e) = refresh_rollups(&db, today - Duration::days(REFRESH_RECENT_DAYS - 1), today).await {
+                tracing::error!("Analytics rollup refresh failed: {:?}", e);
+            }
+        }
+    });
+}
+
+/// Median of a set of samples (sorts in place)
+pub fn median(samples: &mut [f64]) -> Option<f64> {
+    if samples.is_empty() {
+        return None;
+    }
+    samples.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+    let mid = samples.len() / 2;
+    Some(if samples.len() % 2 == 0 {
+        (samples[mid - 1] + samples[mid]) / 2.0
+    } else {
+        samples[mid]
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn median_handles_odd_and_even_lengths() {
+        assert_eq!(median(&mut []), None);
+        assert_eq!(median(&mut [3.0, 1.0, 2.0]), Some(2.0));
+        assert_eq!(median(&mut [4.0, 1.0, 3.0, 2.0]), Some(2.5));
+    }
+}
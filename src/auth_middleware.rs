@@ -17,10 +17,12 @@ const SESSION_COOKIE: &str = "session";
 
 /// Middleware that requires a valid session cookie.
 /// Returns 401 if no cookie or session is invalid/expired.
+/// On success the session's `User` is added to request extensions, along with
+/// its `GuestAccess` for guests, whose requests must pass `guest_access::permits` (403 otherwise).
 pub async fn require_auth(
     State(pool): State<Arc<SqlitePool>>,
     cookies: Cookies,
-    request: Request,
+    mut request: Request,
     next: Next,
 ) -> Response {
     let session_id = match cookies.get(SESSION_COOKIE) {
@@ -35,7 +37,31 @@ pub async fn require_auth(
     };
 
     match ticketing_system::auth::validate_session(&pool, &session_id).await {
-        Ok(Some(_user)) => next.run(request).await,
+        Ok(Some(user)) => {
+            match ticketing_system::guests::get_guest(&pool, &user.user_id).await {
+                Ok(Some(access)) => {
+                    if !crate::guest_access::permits(&access, request.method(), request.uri().path()) {
+                        return (
+                            StatusCode::FORBIDDEN,
+                            Json(json!({"error": "Guests can only access tickets shared with them"})),
+                        )
+                            .into_response();
+                    }
+                    request.extensions_mut().insert(access);
+                }
+                Ok(None) => {}
+                Err(e) => {
+                    tracing::error!("Auth middleware error: {:?}", e);
+                    return (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        Json(json!({"error": "Authentication check failed"})),
+                    )
+                        .into_response();
+                }
+            }
+            request.extensions_mut().insert(user);
+            next.run(request).await
+        }
         Ok(None) => (
             StatusCode::UNAUTHORIZED,
             Json(json!({"error": "Session expired or invalid"})),
@@ -0,0 +1,581 @@
+//! `flowstate` - command line client for the Agentic Flowstate API
+//!
+//! Talks to a running API server over HTTP, so scripts and terminal users can
+//! work with tickets and pipelines without the web UI. `flowstate login`
+//! stores the session in `~/.agentic-flowstate/cli.json`; the server URL and
+//! organization come from `--url`/`--org`, then `FLOWSTATE_URL`/`FLOWSTATE_ORG`,
+//! then that file.
+
+use anyhow::{bail, Context, Result};
+use reqwest::{header, Method, StatusCode};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::io::{BufRead, Write};
+use std::path::PathBuf;
+
+const DEFAULT_URL: &str = "http://localhost:8001";
+const SESSION_COOKIE: &str = "session";
+const ORGANIZATION_HEADER: &str = "X-Organization";
+
+const USAGE: &str = "\
+Usage: flowstate [--url URL] [--org ORG] [--json] <command>
+
+Commands:
+  login <user_id>                       Sign in (password from FLOWSTATE_PASSWORD or stdin)
+  logout                                Sign out and forget the session
+  tickets list [--status STATUS]        List the organization's tickets
+  tickets show <ticket_id>              Show a ticket and its pipeline
+  tickets create <epic_id> <slice_id> <title> [--due YYYY-MM-DD]
+  pipeline attach <ticket_id> <template_id> [--run] [--follow]
+  pipeline run <ticket_id> [--follow]   Start the ticket's pipeline
+  pipeline show <ticket_id>             Show pipeline step statuses
+  steps approve <ticket_id> <step_id> [--follow]
+  steps reject <ticket_id> <step_id> [--feedback TEXT]
+  runs tail <session_id>                Stream an agent run's output
+  admin rotate-storage-key              Re-encrypt stored data under the current key
+
+--follow tails the agent run a command starts.";
+
+/// What `login` saves between invocations
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CliConfig {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    organization: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    session: Option<String>,
+}
+
+fn config_path() -> Result<PathBuf> {
+    Ok(dirs::home_dir()
+        .context("Could not determine home directory")?
+        .join(".agentic-flowstate")
+        .join("cli.json"))
+}
+
+fn load_config() -> Result<CliConfig> {
+    let path = config_path()?;
+    if !path.exists() {
+        return Ok(CliConfig::default());
+    }
+    let content = std::fs::read_to_string(&path).context("Failed to read CLI config")?;
+    serde_json::from_str(&content).context("Failed to parse CLI config")
+}
+
+fn save_config(config: &CliConfig) -> Result<()> {
+    let path = config_path()?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(&path, serde_json::to_string_pretty(config)?).context("Failed to write CLI config")?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600))?;
+    }
+    Ok(())
+}
+
+/// Command line split into global options, positional words and command flags
+#[derive(Debug, Default)]
+struct Args {
+    url: Option<String>,
+    org: Option<String>,
+    json: bool,
+    words: Vec<String>,
+    flags: Vec<(String, Option<String>)>,
+}
+
+/// Command flags that take a value
+const VALUE_FLAGS: [&str; 3] = ["--status", "--due", "--feedback"];
+
+fn parse_args(raw: impl IntoIterator<Item = String>) -> Result<Args> {
+    let mut args = Args::default();
+    let mut raw = raw.into_iter();
+    while let Some(arg) = raw.next() {
+        match arg.as_str() {
+            "--url" => args.url = Some(raw.next().context("--url needs a value")?),
+            "--org" => args.org = Some(raw.next().context("--org needs a value")?),
+            "--json" => args.json = true,
+            flag if VALUE_FLAGS.contains(&flag) => {
+                let value = raw.next().with_context(|| format!("{} needs a value", flag))?;
+                args.flags.push((arg, Some(value)));
+            }
+            flag if flag.starts_with("--") => args.flags.push((arg, None)),
+            _ => args.words.push(arg),
+        }
+    }
+    Ok(args)
+}
+
+impl Args {
+    fn flag(&self, name: &str) -> bool {
+        self.flags.iter().any(|(f, _)| f == name)
+    }
+
+    fn value(&self, name: &str) -> Option<&str> {
+        self.flags.iter().find(|(f, _)| f == name).and_then(|(_, v)| v.as_deref())
+    }
+
+    /// The positional words after the command, exactly `N` of them
+    fn operands<const N: usize>(&self, skip: usize) -> Result<[&str; N]> {
+        let operands: Vec<&str> = self.words.iter().skip(skip).map(String::as_str).collect();
+        operands
+            .try_into()
+            .map_err(|_| anyhow::anyhow!("Wrong number of arguments for '{}'\n\n{}", self.words.join(" "), USAGE))
+    }
+}
+
+struct Client {
+    http: reqwest::Client,
+    url: String,
+    organization: Option<String>,
+    session: Option<String>,
+}
+
+impl Client {
+    fn request(&self, method: Method, path: &str) -> reqwest::RequestBuilder {
+        let mut request = self.http.request(method, format!("{}{}", self.url, path));
+        if let Some(session) = &self.session {
+            request = request.header(header::COOKIE, format!("{}={}", SESSION_COOKIE, session));
+        }
+        if let Some(org) = &self.organization {
+            request = request.header(ORGANIZATION_HEADER, org);
+        }
+        request
+    }
+
+    async fn send(&self, method: Method, path: &str, body: Option<Value>) -> Result<Value> {
+        let mut request = self.request(method, path);
+        if let Some(body) = body {
+            request = request.json(&body);
+        }
+        let response = request.send().await.with_context(|| format!("Could not reach {}", self.url))?;
+        let status = response.status();
+        let text = response.text().await?;
+        if status == StatusCode::UNAUTHORIZED {
+            bail!("Not signed in (or the session expired) - run `flowstate login <user_id>`");
+        }
+        if !status.is_success() {
+            bail!("{} {}: {}", status.as_u16(), path, error_message(&text));
+        }
+        if text.is_empty() {
+            return Ok(Value::Null);
+        }
+        Ok(serde_json::from_str(&text).unwrap_or(Value::String(text)))
+    }
+
+    async fn get(&self, path: &str) -> Result<Value> {
+        self.send(Method::GET, path, None).await
+    }
+
+    async fn post(&self, path: &str, body: Value) -> Result<Value> {
+        self.send(Method::POST, path, Some(body)).await
+    }
+}
+
+/// The API's error text, whether it came as `{"error": ..}` or plain text
+fn error_message(body: &str) -> String {
+    serde_json::from_str::<Value>(body)
+        .ok()
+        .and_then(|v| v.get("error").and_then(|e| e.as_str()).map(str::to_string))
+        .unwrap_or_else(|| body.trim().to_string())
+}
+
+fn str_field<'a>(value: &'a Value, key: &str) -> &'a str {
+    value.get(key).and_then(|v| v.as_str()).unwrap_or("-")
+}
+
+fn print_json(value: &Value) -> Result<()> {
+    println!("{}", serde_json::to_string_pretty(value)?);
+    Ok(())
+}
+
+fn print_pipeline(pipeline: &Value) {
+    let Some(steps) = pipeline.get("steps").and_then(|s| s.as_array()) else {
+        println!("No pipeline");
+        return;
+    };
+    for step in steps {
+        println!(
+            "  {:<24} {:<12} {:<8} {}",
+            str_field(step, "step_id"),
+            str_field(step, "agent_type"),
+            str_field(step, "execution_type"),
+            str_field(step, "status"),
+        );
+    }
+}
+
+/// Print the response's message and tail the run it started, if asked to
+async fn report_started(client: &Client, args: &Args, response: &Value) -> Result<()> {
+    if args.json {
+        print_json(response)?;
+    } else if let Some(message) = response.get("message").and_then(|m| m.as_str()) {
+        println!("{}", message);
+    }
+    match response.get("session_id").and_then(|s| s.as_str()) {
+        Some(session_id) if args.flag("--follow") => tail_run(client, session_id).await,
+        Some(session_id) if !args.json => {
+            println!("Agent run {} started - `flowstate runs tail {}` to follow it", session_id, session_id);
+            Ok(())
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Seconds to wait for an approved step's agent run to start
+const STEP_START_WAIT_SECS: u64 = 15;
+
+/// The agent run an approved step starts; approval returns before the run exists
+async fn wait_for_step_run(client: &Client, ticket_id: &str, step_id: &str) -> Result<Option<String>> {
+    let path = format!("/api/tickets/{}/pipeline", ticket_id);
+    for _ in 0..STEP_START_WAIT_SECS {
+        let response = client.get(&path).await?;
+        let step = response
+            .pointer("/pipeline/steps")
+            .and_then(|s| s.as_array())
+            .and_then(|steps| steps.iter().find(|s| str_field(s, "step_id") == step_id));
+        let Some(step) = step else { return Ok(None) };
+        let status = str_field(step, "status").to_lowercase().replace('_', "");
+        if status != "awaitingapproval" && status != "queued" {
+            return Ok(step.get("agent_run_id").and_then(|id| id.as_str()).map(str::to_string));
+        }
+        tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+    }
+    Ok(None)
+}
+
+/// Remove the complete SSE frames from `buffer`, returning each frame's data
+fn take_events(buffer: &mut String) -> Vec<String> {
+    let mut events = Vec::new();
+    while let Some(end) = buffer.find("\n\n") {
+        let frame: String = buffer.drain(..end + 2).collect();
+        let data: Vec<&str> = frame
+            .lines()
+            .filter_map(|line| line.strip_prefix("data:"))
+            .map(|d| d.strip_prefix(' ').unwrap_or(d))
+            .collect();
+        if !data.is_empty() {
+            events.push(data.join("\n"));
+        }
+    }
+    events
+}
+
+/// Print one stream event. Returns true once the run has finished.
+fn print_event(event: &Value, in_delta: &mut bool) -> bool {
+    let kind = str_field(event, "type");
+    if *in_delta && kind != "text_delta" {
+        *in_delta = false;
+        if kind == "text" {
+            // The full text repeats what the deltas already printed
+            println!();
+            return false;
+        }
+        println!();
+    }
+    match kind {
+        "text_delta" => {
+            *in_delta = true;
+            print!("{}", str_field(event, "content"));
+            let _ = std::io::stdout().flush();
+        }
+        "text" => println!("{}", str_field(event, "content")),
+        "thinking" => eprintln!("(thinking) {}", str_field(event, "content")),
+        "tool_use" => eprintln!("→ {}", str_field(event, "name")),
+        "tool_result" if event.get("is_error").and_then(|e| e.as_bool()) == Some(true) => {
+            eprintln!("✗ tool error: {}", str_field(event, "content"))
+        }
+        "status" => match event.get("message").and_then(|m| m.as_str()) {
+            Some(message) => eprintln!("[{}] {}", str_field(event, "status"), message),
+            None => eprintln!("[{}]", str_field(event, "status")),
+        },
+        "approval_required" => eprintln!(
+            "! {} is waiting for approval (request {})",
+            str_field(event, "tool_name"),
+            str_field(event, "request_id")
+        ),
+        "loop_warning" => eprintln!("! {} ({})", str_field(event, "message"), str_field(event, "action")),
+        "result" => {
+            eprintln!("Run {} finished: {}", str_field(event, "session_id"), str_field(event, "status"));
+            return true;
+        }
+        "replay_complete" => {
+            return matches!(str_field(event, "agent_status"), "completed" | "failed" | "cancelled");
+        }
+        _ => {}
+    }
+    false
+}
+
+/// Follow an agent run's event stream until it finishes
+async fn tail_run(client: &Client, session_id: &str) -> Result<()> {
+    let path = format!("/api/agent-runs/{}/stream", session_id);
+    let mut response = client
+        .request(Method::GET, &path)
+        .header(header::ACCEPT, "text/event-stream")
+        .send()
+        .await
+        .with_context(|| format!("Could not reach {}", client.url))?;
+    if !response.status().is_success() {
+        let status = response.status();
+        bail!("{} {}: {}", status.as_u16(), path, error_message(&response.text().await?));
+    }
+
+    let mut buffer = String::new();
+    let mut in_delta = false;
+    while let Some(chunk) = response.chunk().await? {
+        buffer.push_str(&String::from_utf8_lossy(&chunk).replace("\r\n", "\n"));
+        for data in take_events(&mut buffer) {
+            let Ok(event) = serde_json::from_str::<Value>(&data) else { continue };
+            if print_event(&event, &mut in_delta) {
+                return Ok(());
+            }
+        }
+    }
+    if in_delta {
+        println!();
+    }
+    Ok(())
+}
+
+fn read_password() -> Result<String> {
+    if let Ok(password) = std::env::var("FLOWSTATE_PASSWORD") {
+        return Ok(password);
+    }
+    eprint!("Password: ");
+    std::io::stderr().flush()?;
+    let mut password = String::new();
+    std::io::stdin().lock().read_line(&mut password)?;
+    Ok(password.trim_end_matches(['\r', '\n']).to_string())
+}
+
+async fn login(client: &Client, mut config: CliConfig, user_id: &str) -> Result<()> {
+    let password = read_password()?;
+    let response = client
+        .request(Method::POST, "/api/auth/login")
+        .json(&json!({ "user_id": user_id, "password": password }))
+        .send()
+        .await
+        .with_context(|| format!("Could not reach {}", client.url))?;
+    if !response.status().is_success() {
+        let status = response.status();
+        bail!("Login failed ({}): {}", status.as_u16(), error_message(&response.text().await?));
+    }
+
+    let prefix = format!("{}=", SESSION_COOKIE);
+    let session = response
+        .headers()
+        .get_all(header::SET_COOKIE)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .find_map(|cookie| cookie.split(';').next()?.trim().strip_prefix(prefix.as_str()).map(str::to_string))
+        .context("Login succeeded but the server sent no session cookie")?;
+
+    config.url = Some(client.url.clone());
+    config.session = Some(session);
+    if client.organization.is_some() {
+        config.organization = client.organization.clone();
+    }
+    save_config(&config)?;
+    println!("Signed in as {} on {}", user_id, client.url);
+    Ok(())
+}
+
+async fn run(args: Args) -> Result<()> {
+    let config = load_config()?;
+    let client = Client {
+        http: reqwest::Client::new(),
+        url: args
+            .url
+            .clone()
+            .or_else(|| std::env::var("FLOWSTATE_URL").ok())
+            .or_else(|| config.url.clone())
+            .unwrap_or_else(|| DEFAULT_URL.to_string())
+            .trim_end_matches('/')
+            .to_string(),
+        organization: args
+            .org
+            .clone()
+            .or_else(|| std::env::var("FLOWSTATE_ORG").ok())
+            .or_else(|| config.organization.clone()),
+        session: config.session.clone(),
+    };
+
+    let command: Vec<&str> = args.words.iter().take(2).map(String::as_str).collect();
+    match command.as_slice() {
+        ["login", ..] => {
+            let [user_id] = args.operands(1)?;
+            login(&client, config, user_id).await
+        }
+        ["logout", ..] => {
+            if client.session.is_some() {
+                client.post("/api/auth/logout", json!({})).await?;
+            }
+            save_config(&CliConfig { session: None, ..config })?;
+            println!("Signed out");
+            Ok(())
+        }
+        ["tickets", "list"] => {
+            let tickets = client.get("/api/tickets").await?;
+            let status = args.value("--status");
+            let tickets: Vec<&Value> = tickets
+                .as_array()
+                .map(|t| t.iter().filter(|t| status.is_none_or(|s| str_field(t, "status") == s)).collect())
+                .unwrap_or_default();
+            if args.json {
+                return print_json(&json!(tickets));
+            }
+            for ticket in tickets {
+                println!("{:<28} {:<12} {}", str_field(ticket, "ticket_id"), str_field(ticket, "status"), str_field(ticket, "title"));
+            }
+            Ok(())
+        }
+        ["tickets", "show"] => {
+            let [ticket_id] = args.operands(2)?;
+            let ticket = client.get(&format!("/api/tickets/{}", ticket_id)).await?;
+            if args.json {
+                return print_json(&ticket);
+            }
+            println!("{}  {}", str_field(&ticket, "ticket_id"), str_field(&ticket, "title"));
+            println!("Status: {}", str_field(&ticket, "status"));
+            println!("Epic/slice: {}/{}", str_field(&ticket, "epic_id"), str_field(&ticket, "slice_id"));
+            print_pipeline(ticket.get("pipeline").unwrap_or(&Value::Null));
+            Ok(())
+        }
+        ["tickets", "create"] => {
+            let [epic_id, slice_id, title] = args.operands(2)?;
+            let path = format!("/api/epics/{}/slices/{}/tickets", epic_id, slice_id);
+            let ticket = client.post(&path, json!({ "title": title, "due_date": args.value("--due") })).await?;
+            if args.json {
+                return print_json(&ticket);
+            }
+            println!("Created {}", str_field(&ticket, "ticket_id"));
+            Ok(())
+        }
+        ["pipeline", "attach"] => {
+            let [ticket_id, template_id] = args.operands(2)?;
+            if args.flag("--run") {
+                let path = format!("/api/tickets/{}/pipeline/attach-and-run", ticket_id);
+                let response = client.post(&path, json!({ "template_id": template_id })).await?;
+                return report_started(&client, &args, &response).await;
+            }
+            let path = format!("/api/tickets/{}/pipeline", ticket_id);
+            let response = client.post(&path, json!({ "template_id": template_id })).await?;
+            if args.json {
+                return print_json(&response);
+            }
+            println!("Attached {} to {}", template_id, ticket_id);
+            print_pipeline(response.get("pipeline").unwrap_or(&response));
+            Ok(())
+        }
+        ["pipeline", "run"] => {
+            let [ticket_id] = args.operands(2)?;
+            let response = client.post(&format!("/api/tickets/{}/pipeline/run", ticket_id), json!({})).await?;
+            report_started(&client, &args, &response).await
+        }
+        ["pipeline", "show"] => {
+            let [ticket_id] = args.operands(2)?;
+            let response = client.get(&format!("/api/tickets/{}/pipeline", ticket_id)).await?;
+            if args.json {
+                return print_json(&response);
+            }
+            print_pipeline(response.get("pipeline").unwrap_or(&response));
+            Ok(())
+        }
+        ["steps", "approve"] => {
+            let [ticket_id, step_id] = args.operands(2)?;
+            let path = format!("/api/tickets/{}/pipeline/steps/{}/approve", ticket_id, step_id);
+            let response = client.post(&path, json!({})).await?;
+            if args.json {
+                print_json(&response)?;
+            } else {
+                println!("Approved {} on {}", step_id, ticket_id);
+            }
+            if args.flag("--follow") {
+                match wait_for_step_run(&client, ticket_id, step_id).await? {
+                    Some(session_id) => tail_run(&client, &session_id).await?,
+                    None => eprintln!("Step {} did not start an agent run", step_id),
+                }
+            }
+            Ok(())
+        }
+        ["steps", "reject"] => {
+            let [ticket_id, step_id] = args.operands(2)?;
+            let path = format!("/api/tickets/{}/pipeline/steps/{}/reject", ticket_id, step_id);
+            let response = client.post(&path, json!({ "feedback": args.value("--feedback") })).await?;
+            if args.json {
+                return print_json(&response);
+            }
+            println!("Rejected {} on {}", step_id, ticket_id);
+            Ok(())
+        }
+        ["runs", "tail"] => {
+            let [session_id] = args.operands(2)?;
+            tail_run(&client, session_id).await
+        }
+        ["admin", "rotate-storage-key"] => {
+            let response = client.post("/api/admin/storage-encryption/rotate", json!({})).await?;
+            if args.json {
+                return print_json(&response);
+            }
+            println!("Re-encrypted under key {}", str_field(&response, "key_id"));
+            for column in response.get("columns").and_then(|c| c.as_array()).into_iter().flatten() {
+                println!(
+                    "  {}.{}: {} rewritten, {} failed",
+                    str_field(column, "table"),
+                    str_field(column, "column"),
+                    column.get("rewritten").and_then(|n| n.as_u64()).unwrap_or(0),
+                    column.get("failed").and_then(|n| n.as_u64()).unwrap_or(0)
+                );
+            }
+            Ok(())
+        }
+        [] | ["help", ..] => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+        _ => bail!("Unknown command '{}'\n\n{}", args.words.join(" "), USAGE),
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    let result = match parse_args(std::env::args().skip(1)) {
+        Ok(args) => run(args).await,
+        Err(e) => Err(e),
+    };
+    if let Err(e) = result {
+        eprintln!("flowstate: {:#}", e);
+        std::process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_sse_frames_and_keeps_partial_ones() {
+        let mut buffer = "data: {\"type\":\"text\"}\n\n: keep-alive\n\ndata: {\"type\":".to_string();
+        assert_eq!(take_events(&mut buffer), vec!["{\"type\":\"text\"}".to_string()]);
+        assert_eq!(buffer, "data: {\"type\":");
+
+        buffer.push_str("\"result\"}\n\n");
+        assert_eq!(take_events(&mut buffer), vec!["{\"type\":\"result\"}".to_string()]);
+        assert!(buffer.is_empty());
+    }
+
+    #[test]
+    fn parses_global_options_and_flags_anywhere() {
+        let raw = ["steps", "reject", "--org", "acme", "T-1", "review", "--feedback", "needs work", "--json"];
+        let args = parse_args(raw.map(String::from)).unwrap();
+        assert_eq!(args.org.as_deref(), Some("acme"));
+        assert!(args.json);
+        assert_eq!(args.value("--feedback"), Some("needs work"));
+        assert_eq!(args.operands::<2>(2).unwrap(), ["T-1", "review"]);
+        assert!(args.operands::<1>(2).is_err());
+    }
+}
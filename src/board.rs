@@ -0,0 +1,268 @@
+//! Kanban board for a slice
+//!
+//! Slice settings can cap how many tickets sit in each status (WIP limits)
+//! and pick a ticket field to split the board into swimlanes. Limits are
+//! checked when a ticket's status is changed through the API: with `warn`
+//! enforcement the change goes through and the response carries a warning,
+//! with `block` it is refused. Status moves made by pipelines are never
+//! blocked. The board endpoint reports each column's count against its limit.
+
+use std::collections::{BTreeMap, HashMap};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use ticketing_system::SliceSettings;
+
+/// Column order for the statuses tickets normally move through; any other
+/// status follows these alphabetically
+const STATUS_ORDER: &[&str] = &["todo", "in_progress", "in_review", "blocked", "completed"];
+
+/// Lane for tickets with no value in the swimlane field
+pub const NO_LANE: &str = "none";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WipEnforcement {
+    /// Allow the change and report that the limit is exceeded
+    #[default]
+    Warn,
+    /// Refuse changes that would exceed the limit
+    Block,
+}
+
+impl WipEnforcement {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value {
+            "warn" => Ok(WipEnforcement::Warn),
+            "block" => Ok(WipEnforcement::Block),
+            other => Err(format!("Unknown WIP enforcement '{}', expected warn or block", other)),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WipEnforcement::Warn => "warn",
+            WipEnforcement::Block => "block",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct BoardSettings {
+    /// Most tickets allowed per status; statuses left out are unlimited
+    pub wip_limits: HashMap<String, u32>,
+    pub wip_enforcement: WipEnforcement,
+    /// Ticket field the board is split by, e.g. `assignee`, `labels` or
+    /// `custom_fields.team`
+    pub swimlane_by: Option<String>,
+}
+
+impl BoardSettings {
+    pub fn from_slice_settings(settings: Option<&SliceSettings>) -> Self {
+        let Some(settings) = settings else {
+            return Self::default();
+        };
+        Self {
+            wip_limits: settings.wip_limits.clone().unwrap_or_default(),
+            wip_enforcement: settings
+                .wip_enforcement
+                .as_deref()
+                .and_then(|e| WipEnforcement::parse(e).ok())
+                .unwrap_or_default(),
+            swimlane_by: settings.swimlane_by.clone(),
+        }
+    }
+}
+
+/// Problems with WIP limits and a swimlane field before they are saved
+pub fn validate_board_settings(wip_limits: &HashMap<String, u32>, swimlane_by: Option<&str>) -> Vec<String> {
+    let mut problems: Vec<String> = wip_limits
+        .iter()
+        .filter(|(status, limit)| status.trim().is_empty() || **limit == 0)
+        .map(|(status, _)| format!("wip_limits: '{}' needs a non-empty status and a limit of at least 1", status))
+        .collect();
+    if let Some(field) = swimlane_by {
+        if field.is_empty() || field.split('.').any(str::is_empty) {
+            problems.push(format!("swimlane_by: '{}' is not a ticket field", field));
+        }
+    }
+    problems.sort();
+    problems
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WipViolation {
+    pub status: String,
+    pub limit: u32,
+    /// Tickets in the status once the change is made
+    pub count: usize,
+    pub enforcement: WipEnforcement,
+}
+
+impl WipViolation {
+    pub fn message(&self) -> String {
+        format!(
+            "WIP limit for '{}' is {}; this change would make it {}",
+            self.status, self.limit, self.count
+        )
+    }
+}
+
+fn field_str<'a>(ticket: &'a Value, field: &str) -> Option<&'a str> {
+    ticket.get(field).and_then(|v| v.as_str())
+}
+
+/// The limit the slice's tickets would break if `ticket_id` moved to `new_status`
+pub fn check_transition(
+    settings: &BoardSettings,
+    tickets: &[Value],
+    ticket_id: &str,
+    new_status: &str,
+) -> Option<WipViolation> {
+    let limit = *settings.wip_limits.get(new_status)?;
+    let current = tickets.iter().find(|t| field_str(t, "ticket_id") == Some(ticket_id));
+    if current.and_then(|t| field_str(t, "status")) == Some(new_status) {
+        return None;
+    }
+    let others = tickets
+        .iter()
+        .filter(|t| field_str(t, "status") == Some(new_status) && field_str(t, "ticket_id") != Some(ticket_id))
+        .count();
+    (others + 1 > limit as usize).then(|| WipViolation {
+        status: new_status.to_string(),
+        limit,
+        count: others + 1,
+        enforcement: settings.wip_enforcement,
+    })
+}
+
+#[derive(Debug, Serialize)]
+pub struct BoardColumn {
+    pub status: String,
+    pub count: usize,
+    pub wip_limit: Option<u32>,
+    pub over_limit: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Swimlane {
+    pub key: String,
+    /// Tickets in this lane by status
+    pub columns: BTreeMap<String, Vec<Value>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Board {
+    pub settings: BoardSettings,
+    pub columns: Vec<BoardColumn>,
+    /// One lane holding every ticket when no swimlane field is set
+    pub swimlanes: Vec<Swimlane>,
+}
+
+/// Lane of a ticket: the field's text, the first entry of a list field, or `NO_LANE`
+fn lane_key(ticket: &Value, field: &str) -> String {
+    let path: Vec<&str> = field.split('.').collect();
+    let value = path.iter().try_fold(ticket, |value, segment| value.get(segment));
+    let value = match value {
+        Some(Value::Array(items)) => items.first(),
+        other => other,
+    };
+    match value {
+        None | Some(Value::Null) => NO_LANE.to_string(),
+        Some(Value::String(s)) if s.trim().is_empty() => NO_LANE.to_string(),
+        Some(Value::String(s)) => s.clone(),
+        Some(other) => other.to_string(),
+    }
+}
+
+fn status_rank(status: &str) -> (usize, String) {
+    let known = STATUS_ORDER.iter().position(|s| *s == status).unwrap_or(STATUS_ORDER.len());
+    (known, status.to_string())
+}
+
+pub fn build_board(settings: BoardSettings, tickets: Vec<Value>) -> Board {
+    let mut counts: HashMap<String, usize> = settings.wip_limits.keys().map(|s| (s.clone(), 0)).collect();
+    let mut lanes: BTreeMap<String, BTreeMap<String, Vec<Value>>> = BTreeMap::new();
+    for ticket in tickets {
+        let status = field_str(&ticket, "status").unwrap_or_default().to_string();
+        *counts.entry(status.clone()).or_default() += 1;
+        let lane = match &settings.swimlane_by {
+            Some(field) => lane_key(&ticket, field),
+            None => "all".to_string(),
+        };
+        lanes.entry(lane).or_default().entry(status).or_default().push(ticket);
+    }
+
+    let mut columns: Vec<BoardColumn> = counts
+        .into_iter()
+        .map(|(status, count)| {
+            let wip_limit = settings.wip_limits.get(&status).copied();
+            BoardColumn {
+                over_limit: wip_limit.is_some_and(|limit| count > limit as usize),
+                status,
+                count,
+                wip_limit,
+            }
+        })
+        .collect();
+    columns.sort_by_key(|c| status_rank(&c.status));
+
+    // Tickets without a lane value go last
+    let no_lane = lanes.remove(NO_LANE);
+    let mut swimlanes: Vec<Swimlane> = lanes.into_iter().map(|(key, columns)| Swimlane { key, columns }).collect();
+    swimlanes.extend(no_lane.map(|columns| Swimlane { key: NO_LANE.to_string(), columns }));
+
+    Board { settings, columns, swimlanes }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn settings(enforcement: WipEnforcement) -> BoardSettings {
+        BoardSettings {
+            wip_limits: HashMap::from([("in_progress".to_string(), 2)]),
+            wip_enforcement: enforcement,
+            swimlane_by: Some("assignee".to_string()),
+        }
+    }
+
+    fn tickets() -> Vec<Value> {
+        vec![
+            json!({"ticket_id": "T-1", "status": "in_progress", "assignee": "ana"}),
+            json!({"ticket_id": "T-2", "status": "in_progress", "assignee": "ben"}),
+            json!({"ticket_id": "T-3", "status": "todo", "assignee": null}),
+            json!({"ticket_id": "T-4", "status": "todo", "assignee": "ana"}),
+        ]
+    }
+
+    #[test]
+    fn checks_limits_on_status_changes() {
+        let violation = check_transition(&settings(WipEnforcement::Block), &tickets(), "T-3", "in_progress").unwrap();
+        assert_eq!((violation.limit, violation.count), (2, 3));
+        assert_eq!(violation.enforcement, WipEnforcement::Block);
+        // Already counted, unlimited status, or moving out of the column
+        assert!(check_transition(&settings(WipEnforcement::Block), &tickets(), "T-1", "in_progress").is_none());
+        assert!(check_transition(&settings(WipEnforcement::Block), &tickets(), "T-1", "todo").is_none());
+    }
+
+    #[test]
+    fn builds_columns_and_swimlanes() {
+        let mut tickets = tickets();
+        tickets.push(json!({"ticket_id": "T-5", "status": "in_progress", "assignee": "ana"}));
+        let board = build_board(settings(WipEnforcement::Warn), tickets);
+
+        let columns: Vec<(&str, usize, bool)> =
+            board.columns.iter().map(|c| (c.status.as_str(), c.count, c.over_limit)).collect();
+        assert_eq!(columns, vec![("todo", 2, false), ("in_progress", 3, true)]);
+
+        let lanes: Vec<&str> = board.swimlanes.iter().map(|l| l.key.as_str()).collect();
+        assert_eq!(lanes, vec!["ana", "ben", NO_LANE]);
+        assert_eq!(board.swimlanes[0].columns["in_progress"].len(), 2);
+
+        assert_eq!(lane_key(&json!({"labels": ["ui", "bug"]}), "labels"), "ui");
+        assert_eq!(lane_key(&json!({"custom_fields": {"team": "core"}}), "custom_fields.team"), "core");
+        assert_eq!(validate_board_settings(&HashMap::from([("todo".to_string(), 0)]), Some("a..b")).len(), 2);
+    }
+}
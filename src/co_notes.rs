@@ -0,0 +1,184 @@
+//! Meeting co-notes: a notepad participants edit together during a meeting
+//!
+//! The notes are a list of blocks (a paragraph or bullet each) with ids the
+//! clients generate. A patch is a list of operations on blocks, applied in
+//! order to the latest revision, so concurrent edits to different blocks both
+//! land and, on the same block, the last patch applied wins. Operations on a
+//! block someone else deleted are dropped rather than failing the patch.
+//! Each applied patch bumps the revision and is broadcast to the meeting's
+//! subscribers (`handlers::meeting_co_notes`). When notes are extracted the
+//! co-notes are merged in by the notes prompt.
+
+use serde::{Deserialize, Serialize};
+
+const MAX_BLOCKS: usize = 500;
+const MAX_BLOCK_CHARS: usize = 4_000;
+const MAX_BLOCK_ID_CHARS: usize = 64;
+pub const MAX_OPS: usize = 100;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NoteBlock {
+    pub block_id: String,
+    pub text: String,
+    pub updated_by: String,
+    pub updated_at: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CoNotes {
+    pub revision: u64,
+    pub blocks: Vec<NoteBlock>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum NotesOp {
+    /// Set a block's text; a new block goes after `after`, or at the end
+    Upsert {
+        block_id: String,
+        text: String,
+        #[serde(default)]
+        after: Option<String>,
+    },
+    /// Move a block after `after`, or to the top without it
+    Move {
+        block_id: String,
+        #[serde(default)]
+        after: Option<String>,
+    },
+    Delete { block_id: String },
+}
+
+impl NotesOp {
+    fn block_id(&self) -> &str {
+        match self {
+            NotesOp::Upsert { block_id, .. } | NotesOp::Move { block_id, .. } | NotesOp::Delete { block_id } => block_id,
+        }
+    }
+}
+
+impl CoNotes {
+    fn position(&self, block_id: &str) -> Option<usize> {
+        self.blocks.iter().position(|b| b.block_id == block_id)
+    }
+
+    /// Index just after `after`; blocks deleted meanwhile count as missing
+    fn insert_at(&self, after: Option<&str>, missing: usize) -> usize {
+        match after {
+            Some(after) => self.position(after).map_or(missing, |idx| idx + 1),
+            None => missing,
+        }
+    }
+
+    /// Apply a patch by `author`. Nothing changes if any operation is invalid.
+    pub fn apply(&mut self, ops: &[NotesOp], author: &str, now: &str) -> Result<(), Vec<String>> {
+        let mut problems = Vec::new();
+        if ops.is_empty() {
+            problems.push("ops must not be empty".to_string());
+        }
+        if ops.len() > MAX_OPS {
+            problems.push(format!("a patch holds at most {} ops", MAX_OPS));
+        }
+        for op in ops {
+            let block_id = op.block_id();
+            if block_id.trim().is_empty() || block_id.chars().count() > MAX_BLOCK_ID_CHARS {
+                problems.push(format!("block ids must be 1 to {} characters", MAX_BLOCK_ID_CHARS));
+            }
+            if let NotesOp::Upsert { text, .. } = op {
+                if text.chars().count() > MAX_BLOCK_CHARS {
+                    problems.push(format!("block '{}' is longer than {} characters", block_id, MAX_BLOCK_CHARS));
+                }
+            }
+        }
+        if !problems.is_empty() {
+            return Err(problems);
+        }
+
+        let mut next = self.clone();
+        for op in ops {
+            match op {
+                NotesOp::Upsert { block_id, text, after } => {
+                    let block = NoteBlock {
+                        block_id: block_id.clone(),
+                        text: text.clone(),
+                        updated_by: author.to_string(),
+                        updated_at: now.to_string(),
+                    };
+                    match next.position(block_id) {
+                        Some(idx) => next.blocks[idx] = block,
+                        None => {
+                            let idx = next.insert_at(after.as_deref(), next.blocks.len());
+                            next.blocks.insert(idx, block);
+                        }
+                    }
+                }
+                NotesOp::Move { block_id, after } => {
+                    let Some(idx) = next.position(block_id) else { continue };
+                    let block = next.blocks.remove(idx);
+                    let idx = next.insert_at(after.as_deref(), 0);
+                    next.blocks.insert(idx, block);
+                }
+                NotesOp::Delete { block_id } => next.blocks.retain(|b| &b.block_id != block_id),
+            }
+        }
+        if next.blocks.len() > MAX_BLOCKS {
+            return Err(vec![format!("the notes hold at most {} blocks", MAX_BLOCKS)]);
+        }
+        next.revision += 1;
+        *self = next;
+        Ok(())
+    }
+
+    /// The notes as plain text, one block per line
+    pub fn text(&self) -> String {
+        self.blocks
+            .iter()
+            .map(|b| b.text.trim())
+            .filter(|t| !t.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn upsert(block_id: &str, text: &str, after: Option<&str>) -> NotesOp {
+        NotesOp::Upsert { block_id: block_id.to_string(), text: text.to_string(), after: after.map(str::to_string) }
+    }
+
+    #[test]
+    fn merges_concurrent_block_patches() {
+        let mut notes = CoNotes::default();
+        notes.apply(&[upsert("a", "Agenda", None), upsert("c", "- ship Friday", None)], "ana", "t1").unwrap();
+
+        // Two participants patch from revision 1: different blocks both land,
+        // the same block keeps the later text
+        notes.apply(&[upsert("b", "- budget approved", Some("a")), upsert("c", "- ship Monday", None)], "ben", "t2").unwrap();
+        notes.apply(&[upsert("c", "- ship Tuesday", None)], "ana", "t3").unwrap();
+        assert_eq!(notes.revision, 3);
+        assert_eq!(notes.text(), "Agenda\n- budget approved\n- ship Tuesday");
+        assert_eq!(notes.blocks[2].updated_by, "ana");
+
+        // Ops on a block deleted meanwhile are dropped, inserts after it go last
+        notes.apply(&[NotesOp::Delete { block_id: "b".to_string() }], "ben", "t4").unwrap();
+        notes
+            .apply(
+                &[
+                    NotesOp::Move { block_id: "b".to_string(), after: None },
+                    upsert("d", "- follow up with legal", Some("b")),
+                    NotesOp::Move { block_id: "c".to_string(), after: None },
+                ],
+                "ana",
+                "t5",
+            )
+            .unwrap();
+        assert_eq!(notes.text(), "- ship Tuesday\nAgenda\n- follow up with legal");
+
+        let before = notes.clone();
+        assert!(notes.apply(&[upsert("e", "ok", None), upsert("", "no id", None)], "ana", "t6").is_err());
+        assert_eq!(notes.revision, before.revision);
+        assert_eq!(notes.blocks, before.blocks);
+    }
+}
@@ -0,0 +1,137 @@
+//! Context packs: named, reusable bundles of research docs, ticket links and
+//! notes an organization curates once and attaches to agent runs or pipeline
+//! steps by id, instead of re-selecting the same context for every run.
+//!
+//! A pack is rendered in its curated order within a character budget. An item
+//! that doesn't fit is cut short and the rest of the pack is left out.
+
+use ticketing_system::{context_packs, research_docs, tickets, ContextPack, ContextPackItem, SqlitePool, Ticket};
+
+/// Max characters of pack content injected into a prompt
+const CONTEXT_BUDGET_CHARS: usize = 16_000;
+
+/// Max characters of a linked ticket's description
+const TICKET_DESCRIPTION_CHARS: usize = 2_000;
+
+/// Smallest truncated item worth including
+const MIN_TRUNCATED_CHARS: usize = 200;
+
+/// Cut `text` to at most `max` bytes on a char boundary
+fn truncate(text: &str, max: usize) -> &str {
+    let mut end = max.min(text.len());
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    &text[..end]
+}
+
+/// Join rendered items under the pack heading, within `budget` characters
+pub fn render_sections(pack_name: &str, sections: Vec<String>, budget: usize) -> Option<String> {
+    let header = format!("## Context Pack: {}", pack_name);
+    let mut remaining = budget.saturating_sub(header.len());
+    let mut parts = vec![header];
+    for section in sections {
+        if section.len() <= remaining {
+            remaining -= section.len();
+            parts.push(section);
+            continue;
+        }
+        if remaining >= MIN_TRUNCATED_CHARS {
+            parts.push(format!("{}\n…(truncated)", truncate(&section, remaining)));
+        }
+        tracing::info!("Context pack '{}' exceeded its budget; remaining items left out", pack_name);
+        break;
+    }
+    if parts.len() == 1 {
+        None
+    } else {
+        Some(parts.join("\n\n"))
+    }
+}
+
+async fn render_item(db: &SqlitePool, pack: &ContextPack, item: &ContextPackItem) -> Option<String> {
+    match item {
+        ContextPackItem::Note { title, text } => {
+            Some(format!("### {}\n{}", title.as_deref().unwrap_or("Note"), text))
+        }
+        ContextPackItem::Document { doc_id } => {
+            let doc = research_docs::get_doc(db, doc_id).await.ok().flatten()?;
+            (doc.organization == pack.organization)
+                .then(|| format!("### {} ({})\n{}", doc.title, doc.doc_id, doc.content))
+        }
+        ContextPackItem::Ticket { ticket_id } => {
+            let ticket = tickets::get_ticket_by_id(db, ticket_id).await.ok().flatten()?;
+            (ticket.organization == pack.organization).then(|| {
+                let description = ticket.description.as_deref().unwrap_or_default();
+                format!(
+                    "### Ticket {}: {} [{}]\n{}",
+                    ticket.ticket_id,
+                    ticket.title,
+                    ticket.status,
+                    truncate(description, TICKET_DESCRIPTION_CHARS)
+                )
+            })
+        }
+    }
+}
+
+/// Render a pack for an agent prompt. `None` if the pack is missing, belongs
+/// to another organization, or has nothing left to show.
+pub async fn pack_context(db: &SqlitePool, organization: &str, pack_id: &str) -> Option<String> {
+    let pack = match context_packs::get_pack(db, pack_id).await {
+        Ok(Some(pack)) if pack.organization == organization => pack,
+        Ok(Some(_)) => {
+            tracing::warn!("Context pack {} doesn't belong to {}; ignoring it", pack_id, organization);
+            return None;
+        }
+        Ok(None) => {
+            tracing::warn!("Context pack {} not found; ignoring it", pack_id);
+            return None;
+        }
+        Err(e) => {
+            tracing::warn!("Failed to load context pack {}: {}", pack_id, e);
+            return None;
+        }
+    };
+
+    let mut sections = Vec::new();
+    for item in &pack.items {
+        match render_item(db, &pack, item).await {
+            Some(section) => sections.push(section),
+            None => tracing::debug!("Skipping unavailable item {:?} in context pack {}", item, pack_id),
+        }
+    }
+    render_sections(&pack.name, sections, CONTEXT_BUDGET_CHARS)
+}
+
+/// The context pack configured on a ticket's pipeline step, if any
+pub fn step_context_pack(ticket: &Ticket, step_id: &str) -> Option<String> {
+    ticket
+        .pipeline
+        .as_ref()?
+        .steps
+        .iter()
+        .find(|s| s.step_id == step_id)?
+        .context_pack_id
+        .clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_within_budget() {
+        let sections = vec!["### Note\nUse the staging bucket".to_string(), format!("### Doc\n{}", "x".repeat(1_000))];
+        let full = render_sections("Onboarding", sections.clone(), 10_000).unwrap();
+        assert!(full.starts_with("## Context Pack: Onboarding\n\n### Note"));
+        assert!(full.ends_with(&"x".repeat(1_000)));
+
+        let cut = render_sections("Onboarding", sections.clone(), 400).unwrap();
+        assert!(cut.ends_with("…(truncated)"));
+        assert!(cut.len() < 450);
+
+        // Not even the first item's minimum fits
+        assert_eq!(render_sections("Onboarding", sections, 40), None);
+    }
+}
@@ -0,0 +1,405 @@
+//! Conversation memory rollups
+//!
+//! Long chat threads (workspace manager, life planner) degrade once the
+//! resumed session carries hundreds of turns. An hourly job folds a thread's
+//! older messages into a compact memory stored on the conversation, keeping
+//! the latest `keep_recent_messages` out of it. When a resume would carry
+//! more than `history_token_budget` tokens of raw history, the thread moves to
+//! a fresh session whose first message is the memory, the messages since,
+//! and the new message. That session is recorded on the memory and resumed
+//! as usual until it carries more than the budget in turn.
+//!
+//! `~/.agentic-flowstate/conversation-memory.json` tunes it, e.g.
+//! `{"history_token_budget": 40000, "keep_recent_messages": 10}`;
+//! `"enabled": false` turns it off.
+
+use std::ops::Range;
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use cc_sdk::{query, ClaudeCodeOptions, ContentBlock, Message, ToolsConfig};
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use ticketing_system::{conversations, Conversation, ConversationMessage, SqlitePool};
+
+use crate::agents::prompts::load_prompt;
+use crate::agents::AgentType;
+use crate::handlers::chat_stream::transcript;
+
+/// How often conversations are checked for messages to roll up
+const RUN_INTERVAL_SECS: u64 = 60 * 60;
+
+/// Rough chars-per-token ratio used for budgeting
+const CHARS_PER_TOKEN: usize = 4;
+
+/// Characters of messages folded into the memory per agent call
+const MAX_ROLLUP_CHARS: usize = 120_000;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct MemoryConfig {
+    pub enabled: bool,
+    /// Raw history a resumed session may carry before moving to a fresh one
+    pub history_token_budget: usize,
+    /// Latest messages always kept out of the memory
+    pub keep_recent_messages: usize,
+    /// Older messages are only rolled up once there are at least this many tokens of them
+    pub min_rollup_tokens: usize,
+}
+
+impl Default for MemoryConfig {
+    fn default() -> Self {
+        Self { enabled: true, history_token_budget: 60_000, keep_recent_messages: 20, min_rollup_tokens: 8_000 }
+    }
+}
+
+/// Load the memory config, falling back to defaults when none is configured
+pub fn load_config() -> Result<MemoryConfig> {
+    let path = dirs::home_dir()
+        .context("Could not determine home directory")?
+        .join(".agentic-flowstate")
+        .join("conversation-memory.json");
+    if !path.exists() {
+        return Ok(MemoryConfig::default());
+    }
+    let content = std::fs::read_to_string(&path).context("Failed to read conversation memory config")?;
+    serde_json::from_str(&content).context("Failed to parse conversation memory config")
+}
+
+/// A thread's rolled-up memory, stored on the conversation as JSON
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConversationMemory {
+    pub summary: String,
+    /// Last message folded in; later messages are not in the summary
+    pub through_message_id: String,
+    pub messages_covered: usize,
+    pub updated_at: String,
+    /// The fresh session the thread last moved to, if any
+    #[serde(default)]
+    pub fresh_session: Option<FreshSession>,
+}
+
+/// Where the session a thread moved to starts: its first message stood in
+/// for every message up to `after_message_id`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FreshSession {
+    pub after_message_id: String,
+    /// Tokens of the first message, besides the new message itself
+    pub first_message_tokens: usize,
+}
+
+/// The conversation's memory, stored sealed (see `crate::storage_crypto`)
+pub fn stored_memory(conversation: &Conversation) -> Option<ConversationMemory> {
+    let json = match crate::storage_crypto::open(conversation.memory.as_deref()?) {
+        Ok(json) => json,
+        Err(e) => {
+            tracing::warn!("Ignoring unreadable memory on conversation {}: {}", conversation.id, e);
+            return None;
+        }
+    };
+    match serde_json::from_str(&json) {
+        Ok(memory) => Some(memory),
+        Err(e) => {
+            tracing::warn!("Ignoring unreadable memory on conversation {}: {}", conversation.id, e);
+            None
+        }
+    }
+}
+
+fn tokens(chars: usize) -> usize {
+    chars.div_ceil(CHARS_PER_TOKEN)
+}
+
+/// Index of the first message the memory doesn't cover. `None` when the
+/// messages it covers were removed (a message was edited and regenerated).
+fn uncovered_from(messages: &[ConversationMessage], memory: Option<&ConversationMemory>) -> Option<usize> {
+    match memory {
+        Some(memory) => messages.iter().position(|m| m.id == memory.through_message_id).map(|idx| idx + 1),
+        None => Some(0),
+    }
+}
+
+/// The messages, by length, to fold into the memory next: from `start`,
+/// leaving the latest `keep_recent_messages` out, once there are enough of
+/// them, at most `MAX_ROLLUP_CHARS` at a time
+pub fn rollup_batch(lengths: &[usize], start: usize, config: &MemoryConfig) -> Option<Range<usize>> {
+    let end = lengths.len().saturating_sub(config.keep_recent_messages);
+    if start >= end || tokens(lengths[start..end].iter().sum()) < config.min_rollup_tokens {
+        return None;
+    }
+    let mut batch_end = start;
+    let mut chars = 0;
+    while batch_end < end && (batch_end == start || chars + lengths[batch_end] <= MAX_ROLLUP_CHARS) {
+        chars += lengths[batch_end];
+        batch_end += 1;
+    }
+    Some(start..batch_end)
+}
+
+/// Tokens of history a resumed session carries: every message, or for a
+/// thread that moved to a fresh session after message `after`, its first
+/// message and the messages since
+pub fn session_tokens(lengths: &[usize], fresh: Option<(usize, usize)>) -> usize {
+    match fresh {
+        Some((after, first_message_tokens)) => first_message_tokens + tokens(lengths[after + 1..].iter().sum()),
+        None => tokens(lengths.iter().sum()),
+    }
+}
+
+/// Where to start replaying messages from `start` so that at most `budget`
+/// tokens of them are replayed; the latest message always is
+pub fn replay_from(lengths: &[usize], start: usize, budget: usize) -> usize {
+    let mut chars = 0;
+    let mut from = lengths.len();
+    while from > start {
+        chars += lengths[from - 1];
+        if tokens(chars) > budget && from < lengths.len() {
+            break;
+        }
+        from -= 1;
+    }
+    from
+}
+
+async fn run_memory_agent(
+    db: &SqlitePool,
+    conversation: &Conversation,
+    previous: Option<&str>,
+    messages: &str,
+) -> Result<String> {
+    let organization = conversation.organization.as_deref();
+    let (messages, redactions) = crate::redaction::redact(messages);
+    let subject = format!("conversation:{}", conversation.id);
+    crate::redaction::store_audit(db, organization, &AgentType::ConversationMemory, &subject, &redactions).await;
+    let mut vars = std::collections::HashMap::new();
+    vars.insert("transcript".to_string(), messages);
+    if let Some(previous) = previous {
+        vars.insert("previous_memory".to_string(), previous.to_string());
+    }
+    let system_prompt = load_prompt("conversation-memory", vars).context("Failed to load conversation-memory prompt")?;
+    let agent_type = AgentType::ConversationMemory;
+    let mut options = ClaudeCodeOptions::builder()
+        .system_prompt(&system_prompt)
+        .model(agent_type.model())
+        .tools(ToolsConfig::none())
+        .max_turns(agent_type.max_turns().unwrap_or(1))
+        .cwd(std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR")))
+        .build();
+    if let Some(organization) = organization {
+        options.env.extend(crate::llm_keys::anthropic_env(db, organization).await);
+    }
+
+    let prompt = "Write the updated memory of the thread provided in the system prompt.";
+    let mut stream =
+        Box::pin(query(prompt, Some(options)).await.context("Failed to run conversation-memory agent")?);
+    let mut output_parts = Vec::new();
+    while let Some(message) = stream.next().await {
+        match message {
+            Ok(Message::Assistant { message }) => {
+                for block in &message.content {
+                    if let ContentBlock::Text(text) = block {
+                        output_parts.push(text.text.clone());
+                    }
+                }
+            }
+            Ok(Message::Result { .. }) => break,
+            Ok(_) => {}
+            Err(e) => {
+                tracing::error!("Error receiving message from conversation-memory agent: {}", e);
+                break;
+            }
+        }
+    }
+    let summary = output_parts.join("\n").trim().to_string();
+    if summary.is_empty() {
+        anyhow::bail!("No output from conversation-memory agent");
+    }
+    Ok(summary)
+}
+
+/// Fold the conversation's older messages into its memory. Returns whether
+/// the memory changed.
+pub async fn rollup_conversation(db: &SqlitePool, conversation_id: &str, config: &MemoryConfig) -> Result<bool> {
+    let conversation = conversations::get_conversation(db, conversation_id, false)
+        .await?
+        .context("Conversation not found")?;
+    let messages = conversations::list_messages(db, conversation_id).await?;
+    let lengths: Vec<usize> = messages.iter().map(|m| m.content.len()).collect();
+
+    let mut memory = stored_memory(&conversation);
+    let (mut start, stale) = match uncovered_from(&messages, memory.as_ref()) {
+        Some(start) => (start, false),
+        None => {
+            memory = None;
+            (0, true)
+        }
+    };
+
+    let mut rolled_up = false;
+    while let Some(batch) = rollup_batch(&lengths, start, config) {
+        let previous = memory.as_ref().map(|m| m.summary.as_str());
+        let summary =
+            run_memory_agent(db, &conversation, previous, &transcript(&messages[batch.clone()])).await?;
+        memory = Some(ConversationMemory {
+            summary,
+            through_message_id: messages[batch.end - 1].id.clone(),
+            messages_covered: batch.end,
+            updated_at: chrono::Utc::now().to_rfc3339(),
+            fresh_session: memory.as_ref().and_then(|m| m.fresh_session.clone()),
+        });
+        start = batch.end;
+        rolled_up = true;
+    }
+
+    if rolled_up || stale {
+        save_memory(db, conversation_id, memory.as_ref()).await?;
+    }
+    Ok(rolled_up || stale)
+}
+
+async fn save_memory(db: &SqlitePool, conversation_id: &str, memory: Option<&ConversationMemory>) -> Result<()> {
+    let json = memory.map(serde_json::to_string).transpose()?;
+    let sealed = json.as_deref().map(crate::storage_crypto::seal).transpose()?;
+    conversations::set_memory(db, conversation_id, sealed.as_deref()).await?;
+    Ok(())
+}
+
+/// First message of a fresh session for a thread whose session would carry
+/// more history than the budget: its memory, the messages since and
+/// `message`. The fresh session is recorded so later resumes only count what
+/// it carries. `None` when the session can simply be resumed.
+pub async fn compacted_resume(db: &SqlitePool, conversation_id: &str, message: &str) -> Option<String> {
+    let config = match load_config() {
+        Ok(config) if config.enabled => config,
+        Ok(_) => return None,
+        Err(e) => {
+            tracing::warn!("Failed to load conversation memory config: {:#}", e);
+            return None;
+        }
+    };
+    let messages = match conversations::list_messages(db, conversation_id).await {
+        Ok(messages) => messages,
+        Err(e) => {
+            tracing::warn!("Failed to load messages of conversation {}: {}", conversation_id, e);
+            return None;
+        }
+    };
+    let lengths: Vec<usize> = messages.iter().map(|m| m.content.len()).collect();
+    let fresh = match conversations::get_conversation(db, conversation_id, false).await {
+        Ok(Some(conversation)) => stored_memory(&conversation).and_then(|memory| {
+            let fresh = memory.fresh_session?;
+            let after = messages.iter().position(|m| m.id == fresh.after_message_id)?;
+            Some((after, fresh.first_message_tokens))
+        }),
+        Ok(None) => return None,
+        Err(e) => {
+            tracing::warn!("Failed to load conversation {}: {}", conversation_id, e);
+            return None;
+        }
+    };
+    if session_tokens(&lengths, fresh) <= config.history_token_budget {
+        return None;
+    }
+
+    // Catch up on anything the job hasn't rolled up yet
+    if let Err(e) = rollup_conversation(db, conversation_id, &config).await {
+        tracing::warn!("Failed to roll up conversation {}, resuming as is: {:#}", conversation_id, e);
+        return None;
+    }
+    let conversation = conversations::get_conversation(db, conversation_id, false).await.ok().flatten()?;
+    let mut memory = stored_memory(&conversation)?;
+    let start = uncovered_from(&messages, Some(&memory))?;
+
+    let budget = config.history_token_budget.saturating_sub(tokens(memory.summary.len()));
+    let from = replay_from(&lengths, start, budget);
+    tracing::info!(
+        "Conversation {} is over its history budget; starting a fresh session from its memory and {} recent messages",
+        conversation_id,
+        messages.len() - from
+    );
+    let history = format!(
+        "[Conversation memory]\n{}\n\n[Recent messages]\n\n{}\n---\n\n",
+        memory.summary,
+        transcript(&messages[from..])
+    );
+
+    memory.fresh_session = messages.last().map(|last| FreshSession {
+        after_message_id: last.id.clone(),
+        first_message_tokens: tokens(history.len()),
+    });
+    if let Err(e) = save_memory(db, conversation_id, Some(&memory)).await {
+        tracing::warn!("Failed to record the fresh session of conversation {}: {:#}", conversation_id, e);
+    }
+    Some(history + message)
+}
+
+/// Roll up every active conversation once
+pub async fn run_rollups(db: &SqlitePool) -> Result<usize> {
+    let config = load_config()?;
+    if !config.enabled {
+        return Ok(0);
+    }
+    let mut updated = 0;
+    for conversation in conversations::list_conversations(db, None, false).await? {
+        match rollup_conversation(db, &conversation.id, &config).await {
+            Ok(true) => updated += 1,
+            Ok(false) => {}
+            Err(e) => tracing::warn!("Failed to roll up conversation {}: {:#}", conversation.id, e),
+        }
+    }
+    Ok(updated)
+}
+
+/// Roll up conversation memories at startup and then hourly
+pub fn start_memory_rollup_job(db: Arc<SqlitePool>) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(std::time::Duration::from_secs(RUN_INTERVAL_SECS));
+        loop {
+            interval.tick().await;
+            if crate::maintenance::is_enabled() {
+                continue;
+            }
+            match run_rollups(&db).await {
+                Ok(updated) if updated > 0 => tracing::info!("Rolled up memory of {} conversation(s)", updated),
+                Ok(_) => {}
+                Err(e) => tracing::error!("Conversation memory rollup failed: {:?}", e),
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn picks_messages_to_roll_up_and_replay() {
+        let config = MemoryConfig { keep_recent_messages: 2, min_rollup_tokens: 100, ..Default::default() };
+        // 400 chars = 100 tokens per message
+        let lengths = vec![400; 6];
+        assert_eq!(rollup_batch(&lengths, 0, &config), Some(0..4));
+        assert_eq!(rollup_batch(&lengths, 3, &config), Some(3..4));
+        assert_eq!(rollup_batch(&lengths, 4, &config), None);
+        assert_eq!(rollup_batch(&[50, 50, 400, 400], 0, &config), None);
+
+        // Batches stop at MAX_ROLLUP_CHARS, but always take a message
+        let long = vec![MAX_ROLLUP_CHARS / 2 + 1, MAX_ROLLUP_CHARS / 2, 10, 10, 10];
+        assert_eq!(rollup_batch(&long, 0, &config), Some(0..1));
+        assert_eq!(rollup_batch(&long, 1, &config), Some(1..3));
+        assert_eq!(rollup_batch(&[MAX_ROLLUP_CHARS * 2, 10, 10], 0, &config), Some(0..1));
+
+        assert_eq!(replay_from(&lengths, 2, 250), 4);
+        assert_eq!(replay_from(&lengths, 2, 10_000), 2);
+        assert_eq!(replay_from(&lengths, 2, 10), 5);
+        assert_eq!(replay_from(&lengths, 6, 10), 6);
+    }
+
+    #[test]
+    fn counts_only_what_the_fresh_session_carries() {
+        let lengths = vec![400; 6];
+        assert_eq!(session_tokens(&lengths, None), 600);
+        // Moved after the fourth message with a 150-token first message
+        assert_eq!(session_tokens(&lengths, Some((3, 150))), 350);
+        assert_eq!(session_tokens(&lengths, Some((5, 150))), 150);
+    }
+}
@@ -0,0 +1,162 @@
+//! Conversation retention
+//!
+//! A daily job purges conversations (and their messages and stream events)
+//! that haven't been updated in a configured number of days, optionally
+//! exporting each one to a JSON file first. Policy comes from
+//! `~/.agentic-flowstate/conversation-retention.json` and is re-read on every
+//! run; without that file nothing is purged.
+
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use chrono::{Duration, Utc};
+use serde::{Deserialize, Serialize};
+use ticketing_system::{conversations, SqlitePool};
+
+/// How often the retention policy is applied
+const RUN_INTERVAL_SECS: u64 = 24 * 60 * 60;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RetentionMode {
+    /// Delete without keeping a copy
+    Delete,
+    /// Write the conversation and its messages to `export_dir`, then delete
+    Export,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RetentionPolicy {
+    /// Conversations not updated for this many days are purged
+    pub days: i64,
+    pub mode: RetentionMode,
+    /// Only purge conversations that have been archived (default true)
+    #[serde(default = "default_archived_only")]
+    pub archived_only: bool,
+    /// Export destination; defaults to `~/.agentic-flowstate/conversation-exports`
+    pub export_dir: Option<PathBuf>,
+}
+
+fn default_archived_only() -> bool {
+    true
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct RetentionReport {
+    pub purged: usize,
+    pub exported: usize,
+    pub errors: Vec<String>,
+}
+
+fn config_dir() -> Result<PathBuf> {
+    Ok(dirs::home_dir()
+        .context("Could not determine home directory")?
+        .join(".agentic-flowstate"))
+}
+
+/// Load the retention policy, `None` if none is configured
+pub fn load_policy() -> Result<Option<RetentionPolicy>> {
+    let path = config_dir()?.join("conversation-retention.json");
+    if !path.exists() {
+        return Ok(None);
+    }
+    let content = std::fs::read_to_string(&path).context("Failed to read conversation retention config")?;
+    let policy: RetentionPolicy =
+        serde_json::from_str(&content).context("Failed to parse conversation retention config")?;
+    if policy.days < 1 {
+        anyhow::bail!("Conversation retention days must be at least 1");
+    }
+    Ok(Some(policy))
+}
+
+/// Write a conversation with its messages to `<dir>/<id>.json`
+async fn export_conversation(db: &SqlitePool, dir: &std::path::Path, conversation_id: &str) -> Result<()> {
+    let conversation = conversations::get_conversation(db, conversation_id, true)
+        .await?
+        .context("Conversation disappeared before export")?;
+    let json = serde_json::to_vec_pretty(&conversation)?;
+    tokio::fs::write(dir.join(format!("{}.json", conversation_id)), json)
+        .await
+        .context("Failed to write conversation export")?;
+    Ok(())
+}
+
+/// Apply a retention policy once
+pub async fn apply_policy(db: &SqlitePool, policy: &RetentionPolicy) -> Result<RetentionReport> {
+    let cutoff = (Utc::now() - Duration::days(policy.days)).to_rfc3339();
+    let stale = conversations::list_stale_conversations(db, &cutoff, policy.archived_only).await?;
+
+    let export_dir = match policy.mode {
+        RetentionMode::Export => {
+            let dir = match &policy.export_dir {
+                Some(dir) => dir.clone(),
+                None => config_dir()?.join("conversation-exports"),
+            };
+            tokio::fs::create_dir_all(&dir)
+                .await
+                .context("Failed to create conversation export directory")?;
+            Some(dir)
+        }
+        RetentionMode::Delete => None,
+    };
+
+    let mut report = RetentionReport::default();
+    for conversation in stale {
+        if let Some(dir) = &export_dir {
+            // Never delete what couldn't be exported
+            if let Err(e) = export_conversation(db, dir, &conversation.id).await {
+                report.errors.push(format!("{}: {}", conversation.id, e));
+                continue;
+            }
+            report.exported += 1;
+        }
+        if let Err(e) = conversations::clear_events(db, &conversation.id).await {
+            tracing::warn!("Failed to clear stream events for {}: {}", conversation.id, e);
+        }
+        match conversations::delete_conversation(db, &conversation.id).await {
+            Ok(_) => {
+                crate::handlers::remove_conversation_attachments(&conversation.id).await;
+                report.purged += 1;
+            }
+            Err(e) => report.errors.push(format!("{}: {}", conversation.id, e)),
+        }
+    }
+    Ok(report)
+}
+
+/// Load and apply the configured policy. Empty report when none is configured.
+pub async fn run_retention(db: &SqlitePool) -> Result<RetentionReport> {
+    match load_policy()? {
+        Some(policy) => apply_policy(db, &policy).await,
+        None => Ok(RetentionReport::default()),
+    }
+}
+
+/// Apply the retention policy at startup and then daily
+pub fn start_conversation_retention_job(db: Arc<SqlitePool>) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(std::time::Duration::from_secs(RUN_INTERVAL_SECS));
+        loop {
+            interval.tick().await;
+            if crate::maintenance::is_enabled() {
+                continue;
+            }
+            match run_retention(&db).await {
+                Ok(report) if report.purged > 0 || !report.errors.is_empty() => {
+                    tracing::info!(
+                        "Conversation retention purged {} ({} exported, {} errors)",
+                        report.purged,
+                        report.exported,
+                        report.errors.len()
+                    );
+                    for error in &report.errors {
+                        tracing::warn!("Conversation retention: {}", error);
+                    }
+                }
+                Ok(_) => {}
+                Err(e) => tracing::error!("Conversation retention failed: {:?}", e),
+            }
+        }
+    });
+}
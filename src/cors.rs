@@ -0,0 +1,57 @@
+//! CORS origin allowlist
+//!
+//! Origins come from `~/.agentic-flowstate/cors-origins.json` (a JSON array of
+//! origin strings) when present, otherwise the built-in defaults. The list is
+//! checked per request so it can be reloaded without rebuilding the router.
+
+use std::sync::RwLock;
+
+use anyhow::{Context, Result};
+use http::HeaderValue;
+
+const DEFAULT_ORIGINS: &[&str] = &[
+    "http://localhost:3000",
+    "http://100.119.87.128:3000",
+    "https://jarviss-mac-mini-1.tail3da916.ts.net",
+];
+
+lazy_static::lazy_static! {
+    static ref ALLOWED_ORIGINS: RwLock<Vec<HeaderValue>> = RwLock::new(parse_origins(DEFAULT_ORIGINS.iter().copied()));
+}
+
+fn parse_origins<'a>(origins: impl Iterator<Item = &'a str>) -> Vec<HeaderValue> {
+    origins
+        .filter_map(|o| match HeaderValue::from_str(o.trim_end_matches('/')) {
+            Ok(v) => Some(v),
+            Err(_) => {
+                tracing::warn!("Ignoring invalid CORS origin '{}'", o);
+                None
+            }
+        })
+        .collect()
+}
+
+/// Load the allowlist from disk (or defaults) and install it. Returns the origin count.
+pub fn load_allowed_origins() -> Result<usize> {
+    let config_path = dirs::home_dir()
+        .context("Could not determine home directory")?
+        .join(".agentic-flowstate")
+        .join("cors-origins.json");
+
+    let origins = if config_path.exists() {
+        let content = std::fs::read_to_string(&config_path).context("Failed to read CORS origins config")?;
+        let configured: Vec<String> = serde_json::from_str(&content).context("Failed to parse CORS origins config")?;
+        parse_origins(configured.iter().map(String::as_str))
+    } else {
+        parse_origins(DEFAULT_ORIGINS.iter().copied())
+    };
+
+    let count = origins.len();
+    *ALLOWED_ORIGINS.write().unwrap() = origins;
+    Ok(count)
+}
+
+/// Predicate for `AllowOrigin::predicate`
+pub fn is_allowed_origin(origin: &HeaderValue) -> bool {
+    ALLOWED_ORIGINS.read().unwrap().iter().any(|o| o == origin)
+}
@@ -0,0 +1,233 @@
+//! Critical path through an epic's blocking relationships
+//!
+//! Each ticket is weighted by its remaining work: the estimator's
+//! `estimate_hours`, or hours for its `estimate_size`, or a default for
+//! unestimated tickets; closed tickets weigh nothing. `A blocks B` means B
+//! starts when A finishes, so the critical path is the heaviest blocking chain
+//! and sets how long the epic takes. Tickets off the path have slack: hours
+//! they can slip without delaying it. Bottlenecks are open tickets with the
+//! most remaining work waiting behind them.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use serde::Serialize;
+use serde_json::Value;
+
+/// Hours assumed for a ticket with no estimate
+pub const UNESTIMATED_HOURS: f64 = 8.0;
+
+/// Bottlenecks listed, worst first
+const MAX_BOTTLENECKS: usize = 5;
+
+/// Hours for each estimate size
+const SIZE_HOURS: &[(&str, f64)] = &[("XS", 2.0), ("S", 4.0), ("M", 8.0), ("L", 16.0), ("XL", 32.0)];
+
+/// Floating point slack below this counts as none
+const EPSILON: f64 = 1e-6;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct WorkItem {
+    pub ticket_id: String,
+    /// Remaining hours
+    pub hours: f64,
+    /// Whether `hours` came from an estimate rather than the default
+    pub estimated: bool,
+}
+
+impl WorkItem {
+    /// Weigh a ticket from its custom fields
+    pub fn new(ticket_id: &str, open: bool, custom_fields: Option<&Value>) -> Self {
+        let field = |name: &str| custom_fields.and_then(|f| f.get(name));
+        let hours = field("estimate_hours").and_then(|h| h.as_f64()).filter(|h| *h >= 0.0).or_else(|| {
+            let size = field("estimate_size")?.as_str()?.to_uppercase();
+            SIZE_HOURS.iter().find(|(s, _)| *s == size).map(|(_, hours)| *hours)
+        });
+        Self {
+            ticket_id: ticket_id.to_string(),
+            hours: if open { hours.unwrap_or(UNESTIMATED_HOURS) } else { 0.0 },
+            estimated: hours.is_some(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Schedule {
+    pub ticket_id: String,
+    pub hours: f64,
+    pub estimated: bool,
+    /// Hours from the start of the epic until the ticket can start
+    pub earliest_start: f64,
+    pub earliest_finish: f64,
+    pub slack: f64,
+    pub on_critical_path: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Bottleneck {
+    pub ticket_id: String,
+    /// Tickets that can't start until this one finishes, directly or not
+    pub blocked_tickets: usize,
+    /// Their remaining hours
+    pub blocked_hours: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CriticalPath {
+    /// Remaining hours along the critical path
+    pub total_hours: f64,
+    /// Ticket ids in the order they must finish
+    pub path: Vec<String>,
+    pub schedule: Vec<Schedule>,
+    pub bottlenecks: Vec<Bottleneck>,
+    /// Tickets caught in a blocking cycle, left out of the schedule
+    pub cyclic: Vec<String>,
+}
+
+/// Schedule `items` given `blocks` edges (`(blocker, blocked)`); edges to
+/// tickets outside `items` are ignored
+pub fn critical_path(items: &[WorkItem], blocks: &[(String, String)]) -> CriticalPath {
+    let index: HashMap<&str, usize> = items.iter().enumerate().map(|(i, item)| (item.ticket_id.as_str(), i)).collect();
+    let mut successors: Vec<Vec<usize>> = vec![Vec::new(); items.len()];
+    let mut predecessors: Vec<Vec<usize>> = vec![Vec::new(); items.len()];
+    let mut seen_edges = HashSet::new();
+    for (blocker, blocked) in blocks {
+        let (Some(&from), Some(&to)) = (index.get(blocker.as_str()), index.get(blocked.as_str())) else {
+            continue;
+        };
+        if from != to && seen_edges.insert((from, to)) {
+            successors[from].push(to);
+            predecessors[to].push(from);
+        }
+    }
+
+    // Kahn's algorithm, taking ready tickets in input order
+    let mut in_degree: Vec<usize> = predecessors.iter().map(Vec::len).collect();
+    let mut ready: VecDeque<usize> = (0..items.len()).filter(|&i| in_degree[i] == 0).collect();
+    let mut order = Vec::with_capacity(items.len());
+    while let Some(i) = ready.pop_front() {
+        order.push(i);
+        for &next in &successors[i] {
+            in_degree[next] -= 1;
+            if in_degree[next] == 0 {
+                ready.push_back(next);
+            }
+        }
+    }
+    let ordered: HashSet<usize> = order.iter().copied().collect();
+    let cyclic = (0..items.len()).filter(|i| !ordered.contains(i)).map(|i| items[i].ticket_id.clone()).collect();
+
+    let mut start = vec![0.0f64; items.len()];
+    let mut finish = vec![0.0f64; items.len()];
+    for &i in &order {
+        start[i] = predecessors[i].iter().filter(|p| ordered.contains(p)).map(|&p| finish[p]).fold(0.0, f64::max);
+        finish[i] = start[i] + items[i].hours;
+    }
+    let total_hours = order.iter().map(|&i| finish[i]).fold(0.0, f64::max);
+
+    let mut latest_finish = vec![total_hours; items.len()];
+    for &i in order.iter().rev() {
+        for &next in &successors[i] {
+            latest_finish[i] = latest_finish[i].min(latest_finish[next] - items[next].hours);
+        }
+    }
+
+    // Walk back from the last ticket to finish through the blocker that held it up
+    let mut path = Vec::new();
+    let mut current = order.iter().copied().filter(|&i| finish[i] >= total_hours - EPSILON).min_by_key(|&i| i);
+    while let Some(i) = current {
+        path.push(i);
+        current = predecessors[i].iter().copied().filter(|&p| (finish[p] - start[i]).abs() < EPSILON).min();
+    }
+    path.reverse();
+    let on_path: HashSet<usize> = path.iter().copied().collect();
+
+    let schedule = order
+        .iter()
+        .map(|&i| Schedule {
+            ticket_id: items[i].ticket_id.clone(),
+            hours: items[i].hours,
+            estimated: items[i].estimated,
+            earliest_start: start[i],
+            earliest_finish: finish[i],
+            slack: (latest_finish[i] - finish[i]).max(0.0),
+            on_critical_path: on_path.contains(&i),
+        })
+        .collect();
+
+    let mut bottlenecks: Vec<Bottleneck> = order
+        .iter()
+        .filter(|&&i| items[i].hours > 0.0)
+        .filter_map(|&i| {
+            let mut blocked = HashSet::new();
+            let mut stack = successors[i].clone();
+            while let Some(next) = stack.pop() {
+                if blocked.insert(next) {
+                    stack.extend(&successors[next]);
+                }
+            }
+            let blocked_hours: f64 = blocked.iter().map(|&b| items[b].hours).sum();
+            (blocked_hours > 0.0).then(|| Bottleneck {
+                ticket_id: items[i].ticket_id.clone(),
+                blocked_tickets: blocked.len(),
+                blocked_hours,
+            })
+        })
+        .collect();
+    bottlenecks.sort_by(|a, b| b.blocked_hours.total_cmp(&a.blocked_hours).then_with(|| a.ticket_id.cmp(&b.ticket_id)));
+    bottlenecks.truncate(MAX_BOTTLENECKS);
+
+    CriticalPath {
+        total_hours,
+        path: path.into_iter().map(|i| items[i].ticket_id.clone()).collect(),
+        schedule,
+        bottlenecks,
+        cyclic,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn edge(a: &str, b: &str) -> (String, String) {
+        (a.to_string(), b.to_string())
+    }
+
+    #[test]
+    fn finds_longest_blocking_chain() {
+        let items = vec![
+            WorkItem::new("design", true, Some(&json!({"estimate_hours": 4.0}))),
+            WorkItem::new("api", true, Some(&json!({"estimate_size": "l"}))),
+            WorkItem::new("ui", true, Some(&json!({"estimate_size": "S"}))),
+            WorkItem::new("docs", true, None),
+            WorkItem::new("launch", true, Some(&json!({"estimate_hours": 1.0}))),
+            WorkItem::new("spike", false, Some(&json!({"estimate_hours": 40.0}))),
+        ];
+        assert_eq!(items[1].hours, 16.0);
+        assert!(!items[3].estimated);
+        assert_eq!(items[5].hours, 0.0);
+
+        let edges = vec![
+            edge("design", "api"),
+            edge("design", "ui"),
+            edge("api", "launch"),
+            edge("ui", "launch"),
+            edge("docs", "launch"),
+            edge("spike", "design"),
+            edge("elsewhere", "docs"),
+        ];
+        let result = critical_path(&items, &edges);
+        assert_eq!(result.total_hours, 21.0);
+        assert_eq!(result.path, vec!["spike", "design", "api", "launch"]);
+        let ui = result.schedule.iter().find(|s| s.ticket_id == "ui").unwrap();
+        assert_eq!((ui.earliest_start, ui.slack, ui.on_critical_path), (4.0, 12.0, false));
+        assert_eq!(result.bottlenecks[0].ticket_id, "design");
+        assert_eq!(result.bottlenecks[0].blocked_tickets, 3);
+        assert!(result.cyclic.is_empty());
+
+        let looped = critical_path(&items[..2], &[edge("design", "api"), edge("api", "design")]);
+        assert_eq!(looped.cyclic, vec!["design", "api"]);
+        assert_eq!(looped.total_hours, 0.0);
+    }
+}
@@ -0,0 +1,196 @@
+//! SQLite connection settings and WAL maintenance
+//!
+//! Pool size, busy timeout, synchronous mode and checkpointing come from
+//! `~/.agentic-flowstate/database.json` (all optional). Concurrent pipelines
+//! write agent events heavily, so a busy timeout lets writers wait for the
+//! lock instead of failing with `database is locked`, and a periodic
+//! `wal_checkpoint` keeps the WAL file from growing without bound.
+
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use sqlx::sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePoolOptions, SqliteSynchronous};
+use sqlx::SqlitePool;
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckpointMode {
+    Passive,
+    Full,
+    Restart,
+    Truncate,
+}
+
+impl CheckpointMode {
+    fn pragma(&self) -> &'static str {
+        match self {
+            CheckpointMode::Passive => "PRAGMA wal_checkpoint(PASSIVE)",
+            CheckpointMode::Full => "PRAGMA wal_checkpoint(FULL)",
+            CheckpointMode::Restart => "PRAGMA wal_checkpoint(RESTART)",
+            CheckpointMode::Truncate => "PRAGMA wal_checkpoint(TRUNCATE)",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IntegrityCheck {
+    Off,
+    /// `PRAGMA quick_check`: skips index consistency, fast on large files
+    Quick,
+    Full,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct DatabaseConfig {
+    pub max_connections: u32,
+    pub min_connections: u32,
+    /// How long a connection waits on a locked database before erroring
+    pub busy_timeout_ms: u64,
+    /// How long a request waits for a free pooled connection
+    pub acquire_timeout_ms: u64,
+    /// off, normal, full or extra
+    pub synchronous: String,
+    /// Pages after which SQLite checkpoints on its own (0 disables)
+    pub wal_autocheckpoint: u32,
+    pub checkpoint_mode: CheckpointMode,
+    /// Seconds between checkpoint passes (0 disables the job)
+    pub checkpoint_interval_secs: u64,
+    pub integrity_check: IntegrityCheck,
+}
+
+impl Default for DatabaseConfig {
+    fn default() -> Self {
+        Self {
+            max_connections: 10,
+            min_connections: 1,
+            busy_timeout_ms: 10_000,
+            acquire_timeout_ms: 30_000,
+            synchronous: "normal".to_string(),
+            wal_autocheckpoint: 1000,
+            checkpoint_mode: CheckpointMode::Truncate,
+            checkpoint_interval_secs: 300,
+            integrity_check: IntegrityCheck::Quick,
+        }
+    }
+}
+
+impl DatabaseConfig {
+    fn validate(&self) -> Result<SqliteSynchronous> {
+        if self.max_connections == 0 || self.min_connections > self.max_connections {
+            anyhow::bail!("max_connections must be at least 1 and not below min_connections");
+        }
+        SqliteSynchronous::from_str(&self.synchronous)
+            .map_err(|_| anyhow::anyhow!("Unknown synchronous mode '{}'", self.synchronous))
+    }
+}
+
+/// Load the database config, falling back to defaults when none is configured
+pub fn load_config() -> Result<DatabaseConfig> {
+    let path = dirs::home_dir()
+        .context("Could not determine home directory")?
+        .join(".agentic-flowstate")
+        .join("database.json");
+    if !path.exists() {
+        return Ok(DatabaseConfig::default());
+    }
+    let content = std::fs::read_to_string(&path).context("Failed to read database config")?;
+    let config: DatabaseConfig = serde_json::from_str(&content).context("Failed to parse database config")?;
+    config.validate()?;
+    Ok(config)
+}
+
+/// Open the ticketing database with the configured pool and pragmas, then migrate it
+pub async fn connect(config: &DatabaseConfig) -> Result<SqlitePool> {
+    let synchronous = config.validate()?;
+    let connect_options = SqliteConnectOptions::new()
+        .filename(ticketing_system::db_path()?)
+        .create_if_missing(true)
+        .journal_mode(SqliteJournalMode::Wal)
+        .synchronous(synchronous)
+        .busy_timeout(Duration::from_millis(config.busy_timeout_ms))
+        .pragma("wal_autocheckpoint", config.wal_autocheckpoint.to_string());
+    let pool_options = SqlitePoolOptions::new()
+        .max_connections(config.max_connections)
+        .min_connections(config.min_connections)
+        .acquire_timeout(Duration::from_millis(config.acquire_timeout_ms));
+
+    let pool = ticketing_system::init_db_with_options(connect_options, pool_options).await?;
+    tracing::info!(
+        "SQLite pool: {} connection(s), busy_timeout {}ms, synchronous {}",
+        config.max_connections,
+        config.busy_timeout_ms,
+        config.synchronous
+    );
+    Ok(pool)
+}
+
+/// Run the configured integrity check. Returns the problems found, if any.
+pub async fn check_integrity(pool: &SqlitePool, check: IntegrityCheck) -> Result<Vec<String>> {
+    let pragma = match check {
+        IntegrityCheck::Off => return Ok(Vec::new()),
+        IntegrityCheck::Quick => "PRAGMA quick_check",
+        IntegrityCheck::Full => "PRAGMA integrity_check",
+    };
+    let rows: Vec<String> = sqlx::query_scalar(pragma).fetch_all(pool).await?;
+    Ok(rows.into_iter().filter(|r| r != "ok").collect())
+}
+
+/// Checkpoint the WAL once. Returns `(busy, wal_pages, checkpointed_pages)`.
+pub async fn checkpoint(pool: &SqlitePool, mode: CheckpointMode) -> Result<(i64, i64, i64)> {
+    Ok(sqlx::query_as(mode.pragma()).fetch_one(pool).await?)
+}
+
+/// Start the periodic WAL checkpoint job. It keeps running in maintenance
+/// mode, where a truncated WAL makes file-level backups consistent.
+pub fn start_wal_checkpoint_job(db: Arc<SqlitePool>, config: &DatabaseConfig) {
+    if config.checkpoint_interval_secs == 0 {
+        tracing::info!("WAL checkpoint job disabled");
+        return;
+    }
+    let mode = config.checkpoint_mode;
+    let period = Duration::from_secs(config.checkpoint_interval_secs);
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(period);
+        loop {
+            interval.tick().await;
+            match checkpoint(&db, mode).await {
+                Ok((0, wal_pages, checkpointed)) => {
+                    tracing::debug!("WAL checkpoint: {}/{} page(s)", checkpointed, wal_pages);
+                }
+                Ok((_, wal_pages, checkpointed)) => {
+                    tracing::warn!(
+                        "WAL checkpoint blocked by active readers/writers ({}/{} page(s))",
+                        checkpointed,
+                        wal_pages
+                    );
+                }
+                Err(e) => tracing::error!("WAL checkpoint failed: {:?}", e),
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fills_defaults_and_validates() {
+        let config: DatabaseConfig =
+            serde_json::from_str(r#"{"max_connections": 4, "checkpoint_mode": "passive"}"#).unwrap();
+        assert_eq!(config.max_connections, 4);
+        assert_eq!(config.busy_timeout_ms, 10_000);
+        assert_eq!(config.checkpoint_mode, CheckpointMode::Passive);
+        assert!(config.validate().is_ok());
+
+        let bad = DatabaseConfig { synchronous: "sometimes".to_string(), ..Default::default() };
+        assert!(bad.validate().is_err());
+        let bad = DatabaseConfig { max_connections: 0, ..Default::default() };
+        assert!(bad.validate().is_err());
+    }
+}
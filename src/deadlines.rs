@@ -0,0 +1,303 @@
+//! Ticket deadlines and reminders
+//!
+//! Tickets carry an optional `due_date` (YYYY-MM-DD). A background job raises a
+//! "due soon" notification the day before and an "overdue" one once the date
+//! has passed, each once per due date, and publishes them to data subscribers.
+//! Upcoming deadlines also feed the daily plan and workspace manager context.
+
+use std::sync::Arc;
+
+use chrono::{Duration, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use tokio::sync::broadcast;
+use ticketing_system::{tickets, CreateNotificationRequest, SqlitePool, Ticket};
+
+use crate::notifications::notify;
+use crate::user_events::{self, Subject, UserEvent};
+
+/// How often the reminder job runs
+const CHECK_INTERVAL_SECS: u64 = 15 * 60;
+
+/// Days before the due date that count as "due soon"
+const DUE_SOON_DAYS: i64 = 1;
+
+/// Days ahead shown in the daily plan and workspace manager context
+pub const UPCOMING_DAYS: i64 = 7;
+
+/// Custom field recording the last reminder sent (`<state>:<due_date>`)
+const REMINDER_FIELD: &str = "due_reminder";
+
+const CLOSED_STATUSES: &[&str] = &["completed", "cancelled"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeadlineState {
+    DueSoon,
+    Overdue,
+}
+
+impl DeadlineState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DeadlineState::DueSoon => "due_soon",
+            DeadlineState::Overdue => "overdue",
+        }
+    }
+}
+
+/// Where a due date stands relative to `today`; `None` if it's further out
+pub fn classify(due: NaiveDate, today: NaiveDate) -> Option<DeadlineState> {
+    if due < today {
+        Some(DeadlineState::Overdue)
+    } else if due <= today + Duration::days(DUE_SOON_DAYS) {
+        Some(DeadlineState::DueSoon)
+    } else {
+        None
+    }
+}
+
+pub fn is_open(ticket: &Ticket) -> bool {
+    !CLOSED_STATUSES.contains(&ticket.status.as_str())
+}
+
+fn due_date_of(ticket: &Ticket) -> Option<NaiveDate> {
+    ticket.due_date.as_deref().and_then(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d").ok())
+}
+
+/// Due-date filters shared by the ticket list endpoints
+#[derive(Debug, Default, Deserialize)]
+pub struct DueDateFilter {
+    /// Due on or before this date
+    pub due_before: Option<NaiveDate>,
+    /// Due on or after this date
+    pub due_after: Option<NaiveDate>,
+    /// Only open tickets past their due date
+    pub overdue: Option<bool>,
+}
+
+impl DueDateFilter {
+    pub fn is_empty(&self) -> bool {
+        self.due_before.is_none() && self.due_after.is_none() && self.overdue.is_none()
+    }
+
+    fn matches(&self, ticket: &Value, today: NaiveDate) -> bool {
+        let due = ticket
+            .get("due_date")
+            .and_then(|d| d.as_str())
+            .and_then(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d").ok());
+        let Some(due) = due else {
+            return false;
+        };
+        if self.due_before.is_some_and(|before| due > before) || self.due_after.is_some_and(|after| due < after) {
+            return false;
+        }
+        match self.overdue {
+            Some(overdue) => {
+                let open = ticket
+                    .get("status")
+                    .and_then(|s| s.as_str())
+                    .is_none_or(|s| !CLOSED_STATUSES.contains(&s));
+                overdue == (open && due < today)
+            }
+            None => true,
+        }
+    }
+
+    /// Drop tickets that don't match from a list response (array or `{"tickets": [...]}`)
+    pub fn apply(&self, response: &mut Value, today: NaiveDate) {
+        if self.is_empty() {
+            return;
+        }
+        let tickets = match response {
+            Value::Array(items) => items,
+            Value::Object(obj) => match obj.get_mut("tickets").and_then(|t| t.as_array_mut()) {
+                Some(items) => items,
+                None => return,
+            },
+            _ => return,
+        };
+        tickets.retain(|t| self.matches(t, today));
+    }
+}
+
+// ============================================================================
+// Reminder job
+// ============================================================================
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DeadlineEvent {
+    pub organization: String,
+    pub ticket_id: String,
+    pub epic_id: String,
+    pub slice_id: String,
+    pub title: String,
+    pub due_date: String,
+    pub state: DeadlineState,
+}
+
+lazy_static::lazy_static! {
+    static ref DEADLINE_EVENTS: broadcast::Sender<DeadlineEvent> = broadcast::channel(64).0;
+}
+
+/// Receive deadline reminders as they fire (used by the data event stream)
+pub fn subscribe() -> broadcast::Receiver<DeadlineEvent> {
+    DEADLINE_EVENTS.subscribe()
+}
+
+fn last_reminder(ticket: &Ticket) -> Option<String> {
+    serde_json::to_value(ticket)
+        .ok()?
+        .get("custom_fields")?
+        .get(REMINDER_FIELD)?
+        .as_str()
+        .map(str::to_string)
+}
+
+/// Send any due-soon/overdue reminders not yet sent. Returns how many fired.
+pub async fn send_reminders(db: &SqlitePool, today: NaiveDate) -> anyhow::Result<usize> {
+    let mut sent = 0;
+    for ticket in tickets::list_tickets_with_due_date(db, None).await? {
+        let Some(due) = due_date_of(&ticket).filter(|_| is_open(&ticket)) else {
+            continue;
+        };
+        let Some(state) = classify(due, today) else {
+            continue;
+        };
+        let marker = format!("{}:{}", state.as_str(), due);
+        if last_reminder(&ticket).as_deref() == Some(marker.as_str()) {
+            continue;
+        }
+
+        let title = match state {
+            DeadlineState::DueSoon => format!("Due soon: {}", ticket.title),
+            DeadlineState::Overdue => format!("Overdue: {}", ticket.title),
+        };
+        notify(
+            db,
+            CreateNotificationRequest {
+                kind: format!("ticket_{}", state.as_str()),
+                title,
+                body: Some(format!("{} is due {}", ticket.ticket_id, due.format("%a %b %-d"))),
+                ticket_id: Some(ticket.ticket_id.clone()),
+                organization: Some(ticket.organization.clone()),
+            },
+        )
+        .await;
+        let deadline = DeadlineEvent {
+            organization: ticket.organization.clone(),
+            ticket_id: ticket.ticket_id.clone(),
+            epic_id: ticket.epic_id.clone(),
+            slice_id: ticket.slice_id.clone(),
+            title: ticket.title.clone(),
+            due_date: due.to_string(),
+            state,
+        };
+        let _ = DEADLINE_EVENTS.send(deadline.clone());
+        user_events::publish(db, Subject::Ticket, &ticket.ticket_id, None, UserEvent::Deadline { deadline }).await;
+
+        let mut fields = Map::new();
+        fields.insert(REMINDER_FIELD.to_string(), json!(marker));
+        if let Err(e) = tickets::update_custom_fields(db, &ticket.ticket_id, &fields).await {
+            tracing::warn!("Failed to record deadline reminder for {}: {}", ticket.ticket_id, e);
+        }
+        sent += 1;
+    }
+    Ok(sent)
+}
+
+/// Start the periodic deadline reminder job
+pub fn start_deadline_reminder_job(db: Arc<SqlitePool>) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(std::time::Duration::from_secs(CHECK_INTERVAL_SECS));
+        loop {
+            interval.tick().await;
+            if crate::maintenance::is_enabled() {
+                continue;
+            }
+            match send_reminders(&db, Utc::now().date_naive()).await {
+                Ok(0) => {}
+                Ok(n) => tracing::info!("Sent {} deadline reminder(s)", n),
+                Err(e) => tracing::error!("Deadline reminder pass failed: {:?}", e),
+            }
+        }
+    });
+}
+
+// ============================================================================
+// Context
+// ============================================================================
+
+/// Open tickets due on or before `through` (overdue included), soonest first
+pub async fn upcoming_deadlines(
+    db: &SqlitePool,
+    organization: Option<&str>,
+    through: NaiveDate,
+) -> anyhow::Result<Vec<Ticket>> {
+    let mut upcoming: Vec<(NaiveDate, Ticket)> = tickets::list_tickets_with_due_date(db, organization)
+        .await?
+        .into_iter()
+        .filter(is_open)
+        .filter_map(|t| due_date_of(&t).filter(|due| *due <= through).map(|due| (due, t)))
+        .collect();
+    upcoming.sort_by(|a, b| a.0.cmp(&b.0));
+    Ok(upcoming.into_iter().map(|(_, t)| t).collect())
+}
+
+/// Upcoming and overdue deadlines rendered for the workspace manager.
+/// `None` when there are none.
+pub async fn deadlines_context(db: &SqlitePool, organization: &str) -> Option<String> {
+    let today = Utc::now().date_naive();
+    let tickets = match upcoming_deadlines(db, Some(organization), today + Duration::days(UPCOMING_DAYS)).await {
+        Ok(tickets) if !tickets.is_empty() => tickets,
+        Ok(_) => return None,
+        Err(e) => {
+            tracing::warn!("Failed to load deadlines for {}: {}", organization, e);
+            return None;
+        }
+    };
+    let lines: Vec<String> = tickets
+        .iter()
+        .filter_map(|t| {
+            let due = due_date_of(t)?;
+            let flag = if due < today { " (OVERDUE)" } else { "" };
+            Some(format!("- {}{}: {} — {} [{}]", due, flag, t.ticket_id, t.title, t.status))
+        })
+        .collect();
+    Some(format!("[Upcoming Deadlines]\n{}", lines.join("\n")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(s: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn classifies_and_filters_due_dates() {
+        let today = date("2026-03-10");
+        assert_eq!(classify(date("2026-03-09"), today), Some(DeadlineState::Overdue));
+        assert_eq!(classify(date("2026-03-10"), today), Some(DeadlineState::DueSoon));
+        assert_eq!(classify(date("2026-03-11"), today), Some(DeadlineState::DueSoon));
+        assert_eq!(classify(date("2026-03-12"), today), None);
+
+        let mut response = json!({"tickets": [
+            {"ticket_id": "a", "status": "open", "due_date": "2026-03-01"},
+            {"ticket_id": "b", "status": "completed", "due_date": "2026-03-01"},
+            {"ticket_id": "c", "status": "open", "due_date": "2026-03-20"},
+            {"ticket_id": "d", "status": "open"},
+        ]});
+        DueDateFilter { overdue: Some(true), ..Default::default() }.apply(&mut response, today);
+        assert_eq!(response["tickets"].as_array().unwrap().len(), 1);
+        assert_eq!(response["tickets"][0]["ticket_id"], "a");
+
+        let mut response = json!([
+            {"ticket_id": "a", "due_date": "2026-03-01"},
+            {"ticket_id": "c", "due_date": "2026-03-20"},
+        ]);
+        DueDateFilter { due_after: Some(date("2026-03-05")), ..Default::default() }.apply(&mut response, today);
+        assert_eq!(response[0]["ticket_id"], "c");
+    }
+}
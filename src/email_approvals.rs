@@ -0,0 +1,253 @@
+//! Approving pipeline steps by email
+//!
+//! A manual step can list `approval_emails` in its template. When the step
+//! starts waiting for approval, each address gets an email with approve and
+//! reject links, so approvers can decide from their phone without logging in.
+//! Each link carries a signed token, `<link_id>.<signature>`, an HMAC-SHA256
+//! over the link's id, ticket, step, approver and expiry keyed by
+//! `APPROVAL_SIGNING_KEY` (base64, at least 32 bytes, like the invitation
+//! key). Links open a confirmation page rather than deciding on GET, since
+//! mail scanners follow links. A token stops working once used, once it
+//! expires, or once the step is no longer waiting for approval.
+//!
+//! `~/.agentic-flowstate/email-approvals.json` configures the email:
+//! `{"email_from": "...", "base_url": "https://api.example.com"}`. No emails
+//! are sent without it. Send-email steps can't be approved by email, since
+//! approving one sends mail on behalf of a mailbox.
+
+use anyhow::{anyhow, Context, Result};
+use serde::Deserialize;
+use tracing::{error, info, warn};
+
+use ticketing_system::{
+    approval_links,
+    models::{PipelineStep, PipelineStepStatus, StepComment},
+    pipelines, tickets, ApprovalLink, CreateApprovalLinkRequest, SqlitePool,
+};
+
+use crate::handlers::PRESENCE;
+use crate::invitations::{sign_fields, signing_key_from, verify_fields};
+use crate::pipeline_status::save_pipeline;
+
+const KEY_ENV: &str = "APPROVAL_SIGNING_KEY";
+
+/// Hours an approval link stays valid
+pub const LINK_VALID_HOURS: i64 = 72;
+
+pub const DECISION_APPROVE: &str = "approve";
+pub const DECISION_REJECT: &str = "reject";
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct EmailApprovalConfig {
+    /// Sender of approval emails; none are sent without it
+    pub email_from: Option<String>,
+    /// Public address of this API, which the links point at
+    pub base_url: Option<String>,
+}
+
+/// Load the email approval config, falling back to defaults when none is configured
+pub fn load_config() -> Result<EmailApprovalConfig> {
+    let path = dirs::home_dir()
+        .context("Could not determine home directory")?
+        .join(".agentic-flowstate")
+        .join("email-approvals.json");
+    if !path.exists() {
+        return Ok(EmailApprovalConfig::default());
+    }
+    let content = std::fs::read_to_string(&path).context("Failed to read email approvals config")?;
+    serde_json::from_str(&content).context("Failed to parse email approvals config")
+}
+
+/// Whether an address could be emailed; checked when templates are saved
+pub fn is_valid_email(email: &str) -> bool {
+    match email.split_once('@') {
+        Some((local, domain)) => {
+            !local.is_empty() && domain.contains('.') && !email.chars().any(|c| c.is_whitespace() || c == ',')
+        }
+        None => false,
+    }
+}
+
+fn signed_fields(link: &ApprovalLink) -> [&str; 5] {
+    [&link.link_id, &link.ticket_id, &link.step_id, &link.approver_email, &link.expires_at]
+}
+
+fn sign(link: &ApprovalLink) -> Result<String> {
+    Ok(sign_fields(&signing_key_from(KEY_ENV)?, &signed_fields(link)))
+}
+
+/// Link to the confirmation page for a decision
+fn decision_link(base_url: &str, token: &str, decision: &str) -> String {
+    format!("{}/api/email-approvals/{}?decision={}", base_url.trim_end_matches('/'), token, decision)
+}
+
+/// Why a link can no longer be used, if it can't
+fn unusable_reason(link: &ApprovalLink) -> Option<&'static str> {
+    if link.used_at.is_some() {
+        return Some("This link was already used");
+    }
+    let expired = chrono::DateTime::parse_from_rfc3339(&link.expires_at)
+        .map(|t| t < chrono::Utc::now())
+        .unwrap_or(true);
+    expired.then_some("This link has expired")
+}
+
+/// The link a token was signed for, if it is valid and still usable
+pub async fn resolve_token(db: &SqlitePool, token: &str) -> Result<ApprovalLink> {
+    let link_id = token
+        .split_once('.')
+        .map(|(id, _)| id)
+        .filter(|id| !id.is_empty())
+        .ok_or_else(|| anyhow!("This link is not valid"))?;
+    let link = approval_links::get_link(db, link_id)
+        .await?
+        .ok_or_else(|| anyhow!("This link is not valid"))?;
+    if !verify_fields(&signing_key_from(KEY_ENV)?, token, &signed_fields(&link)) {
+        return Err(anyhow!("This link is not valid"));
+    }
+    if let Some(reason) = unusable_reason(&link) {
+        return Err(anyhow!(reason));
+    }
+    Ok(link)
+}
+
+/// Email a step's approvers their links. Called when the step starts waiting
+/// for approval; failures are logged.
+pub async fn send_approval_emails(db: &SqlitePool, ticket_id: &str, step: &PipelineStep) {
+    let approvers = step.approval_emails.clone().unwrap_or_default();
+    if approvers.is_empty() || crate::email_steps::is_send_step(&step.agent_type) {
+        return;
+    }
+    let config = match load_config() {
+        Ok(config) => config,
+        Err(e) => {
+            warn!("Failed to load email approvals config: {:#}", e);
+            return;
+        }
+    };
+    let (Some(from), Some(base_url)) = (&config.email_from, &config.base_url) else {
+        warn!("Step {} on ticket {} has approvers but email approvals aren't configured", step.step_id, ticket_id);
+        return;
+    };
+    let ticket = match tickets::get_ticket_by_id(db, ticket_id).await {
+        Ok(Some(ticket)) => ticket,
+        Ok(None) => return,
+        Err(e) => {
+            error!("Failed to load ticket {} for approval emails: {:?}", ticket_id, e);
+            return;
+        }
+    };
+    let step_name = &step.agent_type;
+    let expires_at = (chrono::Utc::now() + chrono::Duration::hours(LINK_VALID_HOURS)).to_rfc3339();
+
+    for approver in approvers {
+        let request = CreateApprovalLinkRequest {
+            ticket_id: ticket_id.to_string(),
+            step_id: step.step_id.clone(),
+            approver_email: approver.clone(),
+            expires_at: expires_at.clone(),
+        };
+        let link = approval_links::create_link(db, &request).await.map_err(anyhow::Error::from);
+        let token = match link.and_then(|link| sign(&link)) {
+            Ok(token) => token,
+            Err(e) => {
+                error!("Failed to create approval link for {} on ticket {}: {:#}", approver, ticket_id, e);
+                continue;
+            }
+        };
+        let body = format!(
+            "\"{}\" is waiting for your approval on ticket \"{}\".\n\nApprove:\n{}\n\nReject:\n{}\n\nThese links expire on {}.",
+            step_name,
+            ticket.title,
+            decision_link(base_url, &token, DECISION_APPROVE),
+            decision_link(base_url, &token, DECISION_REJECT),
+            expires_at
+        );
+        let req = crate::handlers::SendEmailRequest {
+            to: vec![approver.clone()],
+            cc: Vec::new(),
+            bcc: Vec::new(),
+            subject: format!("Approval needed: {} on {}", step_name, ticket.title),
+            body_text: Some(body),
+            body_html: None,
+            from: from.clone(),
+            reply_to: None,
+        };
+        match crate::handlers::deliver_email(db, &ticket.organization, &req).await {
+            Ok(_) => info!("Sent approval email for step {} on ticket {} to {}", step.step_id, ticket_id, approver),
+            Err((_, e)) => error!("Failed to send approval email to {}: {}", approver, e),
+        }
+    }
+}
+
+/// Approve or reject the step a link is for, recording the approver's address
+pub async fn decide(db: &SqlitePool, link: &ApprovalLink, decision: &str, feedback: Option<String>) -> Result<()> {
+    let _decision = PRESENCE
+        .lock_step_decision(&link.ticket_id, &link.step_id)
+        .ok_or_else(|| anyhow!("Another decision on this step is in progress"))?;
+    let mut ticket = tickets::get_ticket_by_id(db, &link.ticket_id)
+        .await?
+        .ok_or_else(|| anyhow!("The ticket no longer exists"))?;
+    let pipeline = ticket.pipeline.as_mut().ok_or_else(|| anyhow!("The ticket no longer has a pipeline"))?;
+    let step_idx = pipeline
+        .steps
+        .iter()
+        .position(|s| s.step_id == link.step_id && s.status == PipelineStepStatus::AwaitingApproval)
+        .ok_or_else(|| anyhow!("This step is no longer waiting for approval"))?;
+
+    // Claim the link first, so it can't decide twice
+    if !approval_links::mark_used(db, &link.link_id, decision).await? {
+        return Err(anyhow!("This link was already used"));
+    }
+
+    let author = format!("{} (by email)", link.approver_email);
+    if decision == DECISION_APPROVE {
+        pipelines::approve_step(pipeline, &link.step_id);
+    } else {
+        let feedback = feedback.map(|f| f.trim().to_string()).filter(|f| !f.is_empty());
+        let error = match &feedback {
+            Some(f) => serde_json::json!({ "rejected": true, "feedback": f }),
+            None => serde_json::json!({ "rejected": true }),
+        };
+        pipelines::fail_step(pipeline, &link.step_id, Some(error));
+        if let Some(feedback) = feedback {
+            pipeline.steps[step_idx].comments.push(StepComment {
+                comment_id: uuid::Uuid::new_v4().to_string(),
+                kind: crate::pipeline_automation::STEP_COMMENT_REJECTION.to_string(),
+                author_id: format!("email:{}", link.approver_email),
+                author_name: author.clone(),
+                body: feedback,
+                created_at: chrono::Utc::now().to_rfc3339(),
+            });
+        }
+    }
+    save_pipeline(db, &link.ticket_id, pipeline).await?;
+
+    if decision == DECISION_REJECT {
+        let agent_type = &pipeline.steps[step_idx].agent_type;
+        crate::analytics::record_step_event(
+            db, &link.ticket_id, &link.step_id, agent_type, crate::analytics::STEP_EVENT_REJECTED,
+        ).await;
+    }
+    info!("{} step {} on ticket {} by {}", decision, link.step_id, link.ticket_id, author);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checks_approver_addresses_and_builds_links() {
+        assert!(is_valid_email("dana@example.com"));
+        assert!(!is_valid_email("dana"));
+        assert!(!is_valid_email("@example.com"));
+        assert!(!is_valid_email("dana@localhost"));
+        assert!(!is_valid_email("dana@example.com, lee@example.com"));
+
+        assert_eq!(
+            decision_link("https://api.example.com/", "l-1.sig", DECISION_APPROVE),
+            "https://api.example.com/api/email-approvals/l-1.sig?decision=approve"
+        );
+    }
+}
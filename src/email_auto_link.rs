@@ -0,0 +1,302 @@
+//! Linking incoming email threads to the tickets they mention
+//!
+//! When the fetcher stores a new message in a thread, the thread is linked to
+//! the tickets the message references: links to a ticket in the app, and
+//! ticket ids in the subject or body (candidates are only linked if the
+//! ticket exists in the organization owning the mailbox). Failing that, a
+//! thread with no tickets yet is linked to that organization's indexed ticket
+//! most similar to the message, if it scores at least `min_similarity`.
+//! Mailboxes without an organization are never auto-linked. Every automatic link is recorded with why it was made
+//! (`GET /api/email-threads/:thread_id/auto-links`) and can be undone, which
+//! also stops later messages in the thread from linking that ticket again.
+//!
+//! `~/.agentic-flowstate/email-auto-link.json` tunes it, e.g.
+//! `{"ticket_id_pattern": "\\bPROJ-\\d+\\b", "app_url": "https://flowstate.example.com", "min_similarity": 0.9}`.
+//! `"min_similarity": null` turns similarity matching off, `"enabled": false`
+//! auto-linking altogether.
+
+use std::collections::HashSet;
+
+use anyhow::{Context, Result};
+use regex::Regex;
+use serde::Deserialize;
+use tracing::{info, warn};
+
+use ticketing_system::{
+    email_auto_links, email_thread_tickets, embeddings, tickets, CreateEmailAutoLinkRequest, LinkThreadTicketRequest,
+    SqlitePool, Ticket,
+};
+
+use crate::embeddings::{cosine_similarity, SOURCE_TICKET};
+
+/// Ticket ids like `T-123` or `tkt_9f2a`: a word, `-` or `_`, then a part with a digit
+const DEFAULT_TICKET_ID_PATTERN: &str = r"\b[A-Za-z]+[-_][A-Za-z0-9]*[0-9][A-Za-z0-9]*\b";
+
+const DEFAULT_MIN_SIMILARITY: f32 = 0.85;
+
+/// Candidate tickets looked up per message
+const MAX_CANDIDATES: usize = 20;
+
+/// Characters of the message embedded for similarity matching
+const MAX_EMBED_CHARS: usize = 4_000;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct AutoLinkConfig {
+    pub enabled: bool,
+    pub ticket_id_pattern: String,
+    /// Address of the web app; links to `<app_url>/.../tickets/<id>` match
+    pub app_url: Option<String>,
+    pub min_similarity: Option<f32>,
+}
+
+impl Default for AutoLinkConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            ticket_id_pattern: DEFAULT_TICKET_ID_PATTERN.to_string(),
+            app_url: None,
+            min_similarity: Some(DEFAULT_MIN_SIMILARITY),
+        }
+    }
+}
+
+/// Load the auto-link config, falling back to defaults when none is configured
+pub fn load_config() -> Result<AutoLinkConfig> {
+    let path = dirs::home_dir()
+        .context("Could not determine home directory")?
+        .join(".agentic-flowstate")
+        .join("email-auto-link.json");
+    if !path.exists() {
+        return Ok(AutoLinkConfig::default());
+    }
+    let content = std::fs::read_to_string(&path).context("Failed to read email auto-link config")?;
+    serde_json::from_str(&content).context("Failed to parse email auto-link config")
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MatchReason {
+    AppLink,
+    TicketId,
+    Similarity,
+}
+
+impl MatchReason {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            MatchReason::AppLink => "app_link",
+            MatchReason::TicketId => "ticket_id",
+            MatchReason::Similarity => "similarity",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Candidate {
+    pub ticket_id: String,
+    pub reason: MatchReason,
+    /// The text that matched
+    pub matched: String,
+}
+
+/// Compiled matchers for a config
+pub struct Matchers {
+    ticket_id: Regex,
+    app_link: Option<Regex>,
+}
+
+impl Matchers {
+    pub fn new(config: &AutoLinkConfig) -> Result<Self> {
+        let ticket_id = Regex::new(&config.ticket_id_pattern).context("Invalid ticket_id_pattern")?;
+        let app_link = match config.app_url.as_deref().map(|u| u.trim_end_matches('/')).filter(|u| !u.is_empty()) {
+            Some(url) => Some(Regex::new(&format!(r#"{}/(?:[^\s"'<>]*/)?tickets/([A-Za-z0-9_.\-]+)"#, regex::escape(url)))?),
+            None => None,
+        };
+        Ok(Self { ticket_id, app_link })
+    }
+
+    /// Tickets a message references, app links first, each ticket once
+    pub fn candidates(&self, text: &str) -> Vec<Candidate> {
+        let links = self.app_link.iter().flat_map(|re| re.captures_iter(text)).map(|caps| Candidate {
+            ticket_id: caps[1].to_string(),
+            reason: MatchReason::AppLink,
+            matched: caps[0].to_string(),
+        });
+        let ids = self.ticket_id.find_iter(text).map(|m| Candidate {
+            ticket_id: m.as_str().to_string(),
+            reason: MatchReason::TicketId,
+            matched: m.as_str().to_string(),
+        });
+
+        let mut seen = HashSet::new();
+        links
+            .chain(ids)
+            .filter(|c| seen.insert(c.ticket_id.clone()))
+            .take(MAX_CANDIDATES)
+            .collect()
+    }
+}
+
+/// The organization's indexed ticket most similar to `text`, if any scores at
+/// least `min_score`
+async fn most_similar_ticket(
+    db: &SqlitePool,
+    organization: &str,
+    text: &str,
+    min_score: f32,
+) -> Result<Option<(String, f32)>> {
+    let Some(client) = crate::embeddings::client() else {
+        return Ok(None);
+    };
+    let text: String = text.chars().take(MAX_EMBED_CHARS).collect();
+    let Some(vector) = client.embed(&[text]).await?.pop() else {
+        return Ok(None);
+    };
+    let best = embeddings::list_chunks(db, Some(organization), &[SOURCE_TICKET])
+        .await?
+        .into_iter()
+        .map(|chunk| {
+            let score = cosine_similarity(&vector, &chunk.vector);
+            (chunk.source_id, score)
+        })
+        .filter(|(_, score)| *score >= min_score)
+        .max_by(|a, b| a.1.total_cmp(&b.1));
+    Ok(best)
+}
+
+/// The ticket, if it exists and belongs to `organization`
+async fn organization_ticket(db: &SqlitePool, organization: &str, ticket_id: &str) -> Result<Option<Ticket>> {
+    Ok(tickets::get_ticket_by_id(db, ticket_id).await?.filter(|t| t.organization == organization))
+}
+
+/// Link the thread of a newly stored message to the tickets it references,
+/// recording each link for the audit trail. Only tickets of `organization`,
+/// the mailbox's owner, are linked. Failures are logged, never returned, so
+/// fetching carries on.
+pub async fn link_new_email(
+    db: &SqlitePool,
+    organization: Option<&str>,
+    message_id: &str,
+    thread_id: &str,
+    subject: Option<&str>,
+    body_text: Option<&str>,
+    body_html: Option<&str>,
+) {
+    let Some(organization) = organization else {
+        return;
+    };
+    if let Err(e) = try_link(db, organization, message_id, thread_id, subject, body_text, body_html).await {
+        warn!("Failed to auto-link email {} to tickets: {:#}", message_id, e);
+    }
+}
+
+async fn try_link(
+    db: &SqlitePool,
+    organization: &str,
+    message_id: &str,
+    thread_id: &str,
+    subject: Option<&str>,
+    body_text: Option<&str>,
+    body_html: Option<&str>,
+) -> Result<()> {
+    let config = load_config()?;
+    if !config.enabled {
+        return Ok(());
+    }
+    let matchers = Matchers::new(&config)?;
+
+    let linked: HashSet<String> = email_thread_tickets::get_tickets_for_thread(db, thread_id)
+        .await?
+        .into_iter()
+        .map(|link| link.ticket_id)
+        .collect();
+    // Tickets someone unlinked from this thread stay unlinked
+    let undone: HashSet<String> = email_auto_links::list_for_thread(db, thread_id)
+        .await?
+        .into_iter()
+        .filter(|link| link.undone_at.is_some())
+        .map(|link| link.ticket_id)
+        .collect();
+
+    let text = [subject, body_text, body_html].into_iter().flatten().collect::<Vec<_>>().join("\n");
+    let mut matches: Vec<(Candidate, Ticket, Option<f32>)> = Vec::new();
+    for candidate in matchers.candidates(&text) {
+        if linked.contains(&candidate.ticket_id) || undone.contains(&candidate.ticket_id) {
+            continue;
+        }
+        if let Some(ticket) = organization_ticket(db, organization, &candidate.ticket_id).await? {
+            matches.push((candidate, ticket, None));
+        }
+    }
+
+    // Similarity only places threads nothing else has
+    if let (true, true, Some(min_score)) = (matches.is_empty(), linked.is_empty(), config.min_similarity) {
+        let text = [subject, body_text].into_iter().flatten().collect::<Vec<_>>().join("\n\n");
+        if let Some((ticket_id, score)) = most_similar_ticket(db, organization, &text, min_score).await? {
+            if !undone.contains(&ticket_id) {
+                if let Some(ticket) = organization_ticket(db, organization, &ticket_id).await? {
+                    let matched = subject.unwrap_or_default().to_string();
+                    matches.push((Candidate { ticket_id, reason: MatchReason::Similarity, matched }, ticket, Some(score)));
+                }
+            }
+        }
+    }
+
+    for (candidate, ticket, score) in matches {
+        email_thread_tickets::link_thread_to_ticket(
+            db,
+            &LinkThreadTicketRequest {
+                thread_id: thread_id.to_string(),
+                ticket_id: ticket.ticket_id.clone(),
+                epic_id: Some(ticket.epic_id.clone()),
+                slice_id: Some(ticket.slice_id.clone()),
+            },
+        )
+        .await?;
+        email_auto_links::record_link(
+            db,
+            &CreateEmailAutoLinkRequest {
+                thread_id: thread_id.to_string(),
+                ticket_id: ticket.ticket_id.clone(),
+                message_id: message_id.to_string(),
+                reason: candidate.reason.as_str().to_string(),
+                matched: candidate.matched,
+                score: score.map(f64::from),
+            },
+        )
+        .await?;
+        info!(
+            "Auto-linked email thread {} to ticket {} ({})",
+            thread_id,
+            ticket.ticket_id,
+            candidate.reason.as_str()
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_ticket_references() {
+        let config = AutoLinkConfig { app_url: Some("https://flow.example.com/".to_string()), ..Default::default() };
+        let matchers = Matchers::new(&config).unwrap();
+        let text = "Re: [T-123] login bug\n\
+                    See https://flow.example.com/epics/e1/tickets/tkt_9f2a for details, also T-123 \
+                    and the follow-up. Not https://other.example.com/tickets/T-77";
+        let candidates = matchers.candidates(text);
+        let found: Vec<(&str, MatchReason)> = candidates.iter().map(|c| (c.ticket_id.as_str(), c.reason)).collect();
+        assert_eq!(
+            found,
+            vec![("tkt_9f2a", MatchReason::AppLink), ("T-123", MatchReason::TicketId), ("T-77", MatchReason::TicketId)]
+        );
+        assert_eq!(candidates[0].matched, "https://flow.example.com/epics/e1/tickets/tkt_9f2a");
+
+        let custom = AutoLinkConfig { ticket_id_pattern: r"\bPROJ-\d+\b".to_string(), ..Default::default() };
+        let matchers = Matchers::new(&custom).unwrap();
+        assert_eq!(matchers.candidates("PROJ-4 and T-123").len(), 1);
+        assert!(Matchers::new(&AutoLinkConfig { ticket_id_pattern: "(".to_string(), ..Default::default() }).is_err());
+    }
+}
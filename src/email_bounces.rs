@@ -0,0 +1,260 @@
+//! Bounce / DSN handling for outbound email
+//!
+//! Delivery status notifications arrive like any other message, in the INBOX
+//! or wherever the provider files them (spam, a Gmail label, ...). The fetcher
+//! hands each new message outside the Sent folder to `handle_possible_bounce`,
+//! which matches it back to the sent email (by the SES message ID of the
+//! original embedded in the report) and marks it bounced.
+//! Recipients of permanent (5.x.x) bounces of emails we sent go on the
+//! suppression list of the organization that sent them.
+
+use regex::Regex;
+use ticketing_system::{drafts, email_thread_tickets, emails, tickets, CreateNotificationRequest, Email, SqlitePool};
+
+use crate::email_fetcher::find_account;
+use crate::{email_suppressions, notifications};
+
+/// Details extracted from a bounce message
+#[derive(Debug, Clone, PartialEq)]
+pub struct BounceInfo {
+    /// SES message ID of the original outbound email
+    pub original_message_id: String,
+    pub recipient: Option<String>,
+    pub reason: String,
+    /// A 5.x.x failure; temporary (4.x.x) and unclassified bounces are false
+    pub permanent: bool,
+}
+
+lazy_static::lazy_static! {
+    static ref SES_MESSAGE_ID: Regex =
+        Regex::new(r"(?im)^Message-ID:\s*<([^@>\s]+)@[^>]*amazonses\.com>").unwrap();
+    /// Start of the report part holding the original message or its headers
+    static ref EMBEDDED_ORIGINAL: Regex =
+        Regex::new(r"(?i)Content-Type:\s*(?:message/rfc822|text/rfc822-headers)").unwrap();
+    static ref FINAL_RECIPIENT: Regex =
+        Regex::new(r"(?im)^Final-Recipient:\s*(?:rfc822;)?\s*(\S+)").unwrap();
+    static ref DIAGNOSTIC_CODE: Regex =
+        Regex::new(r"(?im)^Diagnostic-Code:\s*(?:smtp;)?\s*(.+)$").unwrap();
+    static ref DSN_STATUS: Regex =
+        Regex::new(r"(?im)^Status:\s*([245]\.\d+\.\d+)").unwrap();
+}
+
+/// Whether a message looks like a delivery status notification
+fn is_bounce(raw: &str, from_address: &str, subject: Option<&str>) -> bool {
+    let from = from_address.to_lowercase();
+    if from.starts_with("mailer-daemon@") || from.starts_with("postmaster@") {
+        return true;
+    }
+
+    if raw.to_lowercase().contains("report-type=delivery-status") {
+        return true;
+    }
+
+    let subject = subject.unwrap_or_default().to_lowercase();
+    subject.contains("undeliverable")
+        || subject.contains("delivery status notification (failure)")
+        || subject.contains("returned to sender")
+        || subject.contains("delivery failure")
+}
+
+/// The part of a bounce holding the original message: the embedded
+/// `message/rfc822` (or headers) part, otherwise everything after the bounce's
+/// own headers, so the bounce's own Message-ID is never mistaken for it
+fn embedded_original(raw: &str) -> &str {
+    if let Some(m) = EMBEDDED_ORIGINAL.find(raw) {
+        return &raw[m.end()..];
+    }
+    raw.split_once("\r\n\r\n")
+        .or_else(|| raw.split_once("\n\n"))
+        .map_or("", |(_, body)| body)
+}
+
+/// Parse a raw RFC822 message into bounce details, if it is a bounce for an
+/// email we sent through SES
+pub fn parse_bounce(raw: &str, from_address: &str, subject: Option<&str>) -> Option<BounceInfo> {
+    if !is_bounce(raw, from_address, subject) {
+        return None;
+    }
+
+    let original_message_id = SES_MESSAGE_ID.captures(embedded_original(raw))?.get(1)?.as_str().to_string();
+
+    let recipient = FINAL_RECIPIENT
+        .captures(raw)
+        .and_then(|c| c.get(1))
+        .map(|m| m.as_str().trim().to_string());
+
+    let reason = DIAGNOSTIC_CODE
+        .captures(raw)
+        .and_then(|c| c.get(1))
+        .map(|m| m.as_str().trim().to_string())
+        .or_else(|| {
+            DSN_STATUS
+                .captures(raw)
+                .and_then(|c| c.get(1))
+                .map(|m| format!("Status {}", m.as_str()))
+        })
+        .unwrap_or_else(|| "Message could not be delivered".to_string());
+
+    let permanent = match DSN_STATUS.captures(raw).and_then(|c| c.get(1)) {
+        Some(status) => status.as_str().starts_with('5'),
+        None => reason.starts_with('5'),
+    };
+
+    Some(BounceInfo {
+        original_message_id,
+        recipient,
+        reason,
+        permanent,
+    })
+}
+
+/// Organization a permanent bounce of `original` to `recipient` suppresses the
+/// address for: the one owning the mailbox it was sent from, or every one for
+/// the instance's own mailboxes. `None` if `recipient` wasn't a recipient or
+/// the mailbox isn't configured.
+async fn suppression_owner(original: &Email, recipient: &str) -> Option<String> {
+    let recipient = email_suppressions::normalize_address(recipient)?;
+    let was_recipient = original
+        .to_addresses
+        .iter()
+        .chain(original.cc_addresses.iter().flatten())
+        .any(|address| email_suppressions::normalize_address(address).as_deref() == Some(recipient.as_str()));
+    if !was_recipient {
+        return None;
+    }
+    let account = find_account(&original.mailbox).await?;
+    Some(account.organization.unwrap_or_else(|| email_suppressions::ALL_ORGANIZATIONS.to_string()))
+}
+
+/// Mark the original sent email (and its draft) as bounced and notify on
+/// ticket-linked threads. Returns true if the message was a recognised bounce.
+pub async fn handle_possible_bounce(
+    db: &SqlitePool,
+    raw: &str,
+    from_address: &str,
+    subject: Option<&str>,
+) -> bool {
+    let bounce = match parse_bounce(raw, from_address, subject) {
+        Some(b) => b,
+        None => return false,
+    };
+
+    tracing::warn!(
+        "Bounce detected for message {} ({}): {}",
+        bounce.original_message_id,
+        bounce.recipient.as_deref().unwrap_or("unknown recipient"),
+        bounce.reason
+    );
+
+    if let Err(e) = emails::update_delivery_status(
+        db,
+        &bounce.original_message_id,
+        "bounced",
+        Some(&bounce.reason),
+    )
+    .await
+    {
+        tracing::warn!("Failed to mark email {} as bounced: {}", bounce.original_message_id, e);
+    }
+
+    if let Ok(Some(draft)) = drafts::get_draft_by_message_id(db, &bounce.original_message_id).await {
+        if let Err(e) = drafts::update_draft_status(db, draft.id, "bounced").await {
+            tracing::warn!("Failed to mark draft {} as bounced: {}", draft.id, e);
+        }
+    }
+
+    let original = match emails::get_email_by_message_id(db, &bounce.original_message_id).await {
+        Ok(Some(email)) => email,
+        _ => return true,
+    };
+
+    // Only a bounce of an email we sent, for one of its recipients, suppresses
+    // anything, and only for the organization owning the mailbox it was sent from
+    if let (true, Some(recipient)) = (bounce.permanent, &bounce.recipient) {
+        match suppression_owner(&original, recipient).await {
+            Some(organization) => {
+                email_suppressions::suppress_hard_bounce(db, &organization, recipient, &bounce.reason).await
+            }
+            None => tracing::warn!(
+                "Not suppressing {}: not a recipient of {} or its mailbox isn't configured",
+                recipient,
+                bounce.original_message_id
+            ),
+        }
+    }
+
+    let thread_id = match &original.thread_id {
+        Some(t) => t.clone(),
+        None => return true,
+    };
+
+    if let Ok(linked) = email_thread_tickets::get_tickets_for_thread(db, &thread_id).await {
+        for link in linked {
+            let ticket = match tickets::get_ticket_by_id(db, &link.ticket_id).await {
+                Ok(Some(ticket)) => ticket,
+                _ => continue,
+            };
+            notifications::notify(
+                db,
+                CreateNotificationRequest {
+                    kind: "email_bounced".to_string(),
+                    title: format!(
+                        "Email bounced: {}",
+                        original.subject.as_deref().unwrap_or("(no subject)")
+                    ),
+                    body: Some(format!(
+                        "Delivery to {} failed: {}",
+                        bounce.recipient.as_deref().unwrap_or("recipient"),
+                        bounce.reason
+                    )),
+                    ticket_id: Some(link.ticket_id.clone()),
+                    organization: Some(ticket.organization),
+                },
+            )
+            .await;
+        }
+    }
+
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DSN: &str = "Message-ID: <0100018fff-9999-0000@us-east-1.amazonses.com>\r\n\
+        Content-Type: multipart/report; report-type=delivery-status;\r\n\
+        \r\n\
+        Final-Recipient: rfc822; nobody@example.com\r\n\
+        Action: failed\r\n\
+        Status: 5.1.1\r\n\
+        Diagnostic-Code: smtp; 550 5.1.1 user unknown\r\n\
+        \r\n\
+        Content-Type: message/rfc822\r\n\
+        \r\n\
+        Message-ID: <0100018abc-1234-5678@email.amazonses.com>\r\n";
+
+    #[test]
+    fn parses_ses_bounce() {
+        let bounce = parse_bounce(DSN, "MAILER-DAEMON@amazonses.com", Some("Delivery Status Notification (Failure)"))
+            .expect("bounce");
+        assert_eq!(bounce.original_message_id, "0100018abc-1234-5678");
+        assert_eq!(bounce.recipient.as_deref(), Some("nobody@example.com"));
+        assert_eq!(bounce.reason, "550 5.1.1 user unknown");
+        assert!(bounce.permanent);
+
+        let deferred = DSN.replace("Status: 5.1.1", "Status: 4.2.2");
+        assert!(!parse_bounce(&deferred, "MAILER-DAEMON@amazonses.com", None).unwrap().permanent);
+
+        // Without an embedded message part, only the report body is searched
+        let inline = DSN.replace("Content-Type: message/rfc822\r\n", "");
+        let bounce = parse_bounce(&inline, "MAILER-DAEMON@amazonses.com", None).unwrap();
+        assert_eq!(bounce.original_message_id, "0100018abc-1234-5678");
+    }
+
+    #[test]
+    fn ignores_regular_mail() {
+        let raw = "Subject: Lunch?\r\nMessage-ID: <abc@email.amazonses.com>\r\n\r\nAre you free?";
+        assert!(parse_bounce(raw, "friend@example.com", Some("Lunch?")).is_none());
+    }
+}
@@ -0,0 +1,509 @@
+//! Email digest
+//!
+//! Once a day (or on demand via `POST /api/emails/digest`) the last day's
+//! email is boiled down by the email-digest agent: new threads, threads whose
+//! latest message still needs a reply, and the organization's tickets linked
+//! from those threads. A digest only covers mailboxes its user can read, so it
+//! is stored for that user alone (see `ticketing_system::email_digests`) and can
+//! also be emailed out. The agent run counts against the organization's quota.
+//! The daily job reads `~/.agentic-flowstate/email-digest.json`; without that
+//! file it does nothing.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Duration, NaiveDate, Timelike, Utc};
+use cc_sdk::{query, ClaudeCodeOptions, ContentBlock, Message, ToolsConfig};
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use ticketing_system::{
+    email_digests, email_thread_tickets, emails, tickets, users, CreateEmailDigestRequest, Email, SqlitePool, User,
+};
+
+use crate::agents::claude_code::parse_usage;
+use crate::agents::prompts::load_prompt;
+use crate::agents::tool_audit::{store_standalone_tool_calls, ToolCallLog};
+use crate::agents::{AgentType, RunUsage};
+
+/// How often the daily job checks whether today's digest is due
+const CHECK_INTERVAL_SECS: u64 = 15 * 60;
+
+/// Emails fetched per page while walking back through the inbox
+const EMAIL_PAGE_SIZE: i64 = 200;
+
+/// How far back unanswered threads are looked for
+const AWAITING_REPLY_DAYS: i64 = 7;
+
+/// Threads listed per section; the rest are counted
+const MAX_THREADS_PER_SECTION: usize = 40;
+
+/// Characters of the latest message shown for threads awaiting a reply
+const SNIPPET_CHARS: usize = 300;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct DigestConfig {
+    /// Organization whose quota and tickets the digest uses
+    pub organization: String,
+    /// Name of the user the daily digest is for; it only covers mailboxes
+    /// they can read
+    pub user: String,
+    /// Hour of the day (UTC) from which the daily digest is generated
+    #[serde(default = "default_hour")]
+    pub hour: u32,
+    /// Mailboxes covered; all synced mailboxes the user can read when empty
+    #[serde(default)]
+    pub mailboxes: Vec<String>,
+    /// Also email the digest to this address, sent from `email_from`
+    pub email_to: Option<String>,
+    pub email_from: Option<String>,
+}
+
+fn default_hour() -> u32 {
+    7
+}
+
+/// Load the digest config, `None` if the daily digest isn't configured
+pub fn load_config() -> Result<Option<DigestConfig>> {
+    let path = dirs::home_dir()
+        .context("Could not determine home directory")?
+        .join(".agentic-flowstate")
+        .join("email-digest.json");
+    if !path.exists() {
+        return Ok(None);
+    }
+    let content = std::fs::read_to_string(&path).context("Failed to read email digest config")?;
+    let config: DigestConfig = serde_json::from_str(&content).context("Failed to parse email digest config")?;
+    if config.hour > 23 {
+        anyhow::bail!("Email digest hour must be between 0 and 23");
+    }
+    if config.email_to.is_some() && config.email_from.is_none() {
+        anyhow::bail!("Email digest email_to requires email_from");
+    }
+    Ok(Some(config))
+}
+
+// ============================================================================
+// Threads
+// ============================================================================
+
+/// The parts of an email the digest looks at
+#[derive(Debug, Clone)]
+struct DigestEmail {
+    thread: String,
+    subject: String,
+    from: String,
+    received_at: i64,
+    inbound: bool,
+    is_reply: bool,
+    body: String,
+}
+
+impl DigestEmail {
+    fn from_email(email: Email) -> Self {
+        let inbound = !email.folder.eq_ignore_ascii_case("Sent") && !email.from_address.eq_ignore_ascii_case(&email.mailbox);
+        DigestEmail {
+            thread: email.thread_id.clone().unwrap_or_else(|| email.message_id.clone()),
+            subject: email.subject.unwrap_or_else(|| "(no subject)".to_string()),
+            from: match email.from_name {
+                Some(name) if !name.trim().is_empty() => format!("{} <{}>", name.trim(), email.from_address),
+                _ => email.from_address,
+            },
+            received_at: email.received_at,
+            inbound,
+            is_reply: email.in_reply_to.is_some(),
+            body: email.body_text.unwrap_or_default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Thread {
+    id: String,
+    subject: String,
+    /// Sender of the latest message
+    from: String,
+    messages: usize,
+    last_at: i64,
+    /// Whether the thread began in the window (first message isn't a reply)
+    new: bool,
+    /// Whether the latest message is inbound, i.e. the ball is in our court
+    awaiting_reply: bool,
+    snippet: String,
+}
+
+/// Group emails into threads, most recently active first. `new_since` is the
+/// start of the digest window.
+fn group_threads(emails: Vec<DigestEmail>, new_since: i64) -> Vec<Thread> {
+    let mut by_thread: HashMap<String, Vec<DigestEmail>> = HashMap::new();
+    for email in emails {
+        by_thread.entry(email.thread.clone()).or_default().push(email);
+    }
+    let mut threads: Vec<Thread> = by_thread
+        .into_iter()
+        .map(|(id, mut messages)| {
+            messages.sort_by_key(|m| m.received_at);
+            let first = &messages[0];
+            let last = &messages[messages.len() - 1];
+            Thread {
+                subject: first.subject.clone(),
+                from: last.from.clone(),
+                messages: messages.len(),
+                last_at: last.received_at,
+                new: first.received_at >= new_since && !first.is_reply,
+                awaiting_reply: last.inbound,
+                snippet: snippet(&last.body),
+                id,
+            }
+        })
+        .collect();
+    threads.sort_by(|a, b| b.last_at.cmp(&a.last_at).then_with(|| a.id.cmp(&b.id)));
+    threads
+}
+
+fn snippet(body: &str) -> String {
+    let flat = body.split_whitespace().collect::<Vec<_>>().join(" ");
+    if flat.chars().count() <= SNIPPET_CHARS {
+        return flat;
+    }
+    format!("{}…", flat.chars().take(SNIPPET_CHARS).collect::<String>())
+}
+
+fn timestamp(ts: i64) -> String {
+    DateTime::from_timestamp(ts, 0)
+        .map(|t| t.format("%a %H:%M UTC").to_string())
+        .unwrap_or_default()
+}
+
+/// One line per thread, capped at `MAX_THREADS_PER_SECTION`
+fn render_threads<'a>(threads: impl Iterator<Item = &'a Thread>, with_snippet: bool) -> String {
+    let threads: Vec<&Thread> = threads.collect();
+    let mut lines: Vec<String> = threads
+        .iter()
+        .take(MAX_THREADS_PER_SECTION)
+        .map(|t| {
+            let mut line = format!(
+                "- {} — {} ({} message{}, last {})",
+                t.from,
+                t.subject,
+                t.messages,
+                if t.messages == 1 { "" } else { "s" },
+                timestamp(t.last_at)
+            );
+            if with_snippet && !t.snippet.is_empty() {
+                line.push_str(&format!("\n  > {}", t.snippet));
+            }
+            line
+        })
+        .collect();
+    if threads.len() > MAX_THREADS_PER_SECTION {
+        lines.push(format!("- … and {} more", threads.len() - MAX_THREADS_PER_SECTION));
+    }
+    lines.join("\n")
+}
+
+// ============================================================================
+// Generation
+// ============================================================================
+
+#[derive(Debug, Serialize)]
+pub struct Digest {
+    pub date: String,
+    pub digest_id: String,
+    pub new_threads: usize,
+    pub awaiting_reply: usize,
+    pub email_tickets: usize,
+    pub content: String,
+    /// SES message id when the digest was also emailed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub emailed_message_id: Option<String>,
+}
+
+/// Emails received since `since` in the given mailboxes (all when empty),
+/// walking back from the newest
+async fn recent_emails(db: &SqlitePool, mailboxes: &[String], since: i64) -> Result<Vec<Email>> {
+    let mut recent = Vec::new();
+    let mut offset = 0;
+    loop {
+        let page = emails::list_all_emails(db, EMAIL_PAGE_SIZE, offset).await?;
+        let page_len = page.len() as i64;
+        let reached_end = page.iter().any(|e| e.received_at < since);
+        recent.extend(page.into_iter().filter(|e| {
+            e.received_at >= since && (mailboxes.is_empty() || mailboxes.iter().any(|m| m.eq_ignore_ascii_case(&e.mailbox)))
+        }));
+        if reached_end || page_len < EMAIL_PAGE_SIZE {
+            break;
+        }
+        offset += page_len;
+    }
+    Ok(recent)
+}
+
+/// The organization's tickets linked to threads active in the window and
+/// created in it
+async fn email_tickets(db: &SqlitePool, organization: &str, threads: &[&Thread], since: DateTime<Utc>) -> Vec<String> {
+    let mut lines = Vec::new();
+    for thread in threads {
+        let links = match email_thread_tickets::get_tickets_for_thread(db, &thread.id).await {
+            Ok(links) => links,
+            Err(e) => {
+                tracing::warn!("Failed to load tickets for thread {}: {}", thread.id, e);
+                continue;
+            }
+        };
+        for link in links {
+            if let Ok(Some(ticket)) = tickets::get_ticket_by_id(db, &link.ticket_id).await {
+                if ticket.organization != organization {
+                    continue;
+                }
+                let created = DateTime::parse_from_rfc3339(&ticket.created_at).ok();
+                if created.is_some_and(|created| created >= since) {
+                    lines.push(format!("- {} {} [{}] (from: {})", ticket.ticket_id, ticket.title, ticket.status, thread.subject));
+                }
+            }
+        }
+    }
+    lines
+}
+
+async fn run_digest_agent(db: &SqlitePool, organization: &str, vars: HashMap<String, String>) -> Result<(String, RunUsage)> {
+    let system_prompt = load_prompt("email-digest", vars).context("Failed to load email-digest prompt")?;
+    let agent_type = AgentType::EmailDigest;
+    let mut options = ClaudeCodeOptions::builder()
+        .system_prompt(&system_prompt)
+        .model(agent_type.model())
+        .tools(ToolsConfig::none())
+        .max_turns(1)
+        .cwd(PathBuf::from(env!("CARGO_MANIFEST_DIR")))
+        .build();
+    options.env.extend(crate::llm_keys::anthropic_env(db, organization).await);
+    let env = options.env.clone();
+
+    let prompt = "Write the email digest from the activity provided in the system prompt.";
+    let mut stream = Box::pin(query(prompt, Some(options)).await.context("Failed to run email-digest agent")?);
+    let mut output_parts = Vec::new();
+    let mut usage = RunUsage::default();
+    let mut tool_log = ToolCallLog::new(&env);
+    let mut session_id = None;
+    while let Some(message) = stream.next().await {
+        if let Ok(message) = &message {
+            tool_log.observe(message);
+        }
+        match message {
+            Ok(Message::Assistant { message }) => {
+                for block in &message.content {
+                    if let ContentBlock::Text(text) = block {
+                        output_parts.push(text.text.clone());
+                    }
+                }
+            }
+            Ok(Message::Result { usage: result_usage, total_cost_usd, session_id: sess_id, .. }) => {
+                usage = parse_usage(result_usage.as_ref(), total_cost_usd);
+                session_id = Some(sess_id);
+                break;
+            }
+            Ok(_) => {}
+            Err(e) => {
+                tracing::error!("Error receiving message from email-digest agent: {}", e);
+                break;
+            }
+        }
+    }
+    store_standalone_tool_calls(db, session_id.as_deref(), Some(organization), &agent_type, &tool_log.into_calls()).await;
+    if output_parts.is_empty() {
+        anyhow::bail!("No output from email-digest agent");
+    }
+    Ok((output_parts.join("\n\n"), usage))
+}
+
+/// Build `user`'s digest for the `hours` before `now` from `mailboxes` (which
+/// the caller has checked they can read), store it for them and return it.
+/// Emailing is up to the caller.
+pub async fn generate_digest(
+    db: &SqlitePool,
+    organization: &str,
+    user: &User,
+    mailboxes: &[String],
+    hours: i64,
+    now: DateTime<Utc>,
+) -> Result<Digest> {
+    let since = now - Duration::hours(hours);
+    let lookback = now - Duration::days(AWAITING_REPLY_DAYS).max(Duration::hours(hours));
+    let emails = recent_emails(db, mailboxes, lookback.timestamp()).await?;
+    let threads = group_threads(emails.into_iter().map(DigestEmail::from_email).collect(), since.timestamp());
+
+    let new: Vec<&Thread> = threads.iter().filter(|t| t.new).collect();
+    let awaiting: Vec<&Thread> = threads.iter().filter(|t| t.awaiting_reply).collect();
+    let active: Vec<&Thread> = threads.iter().filter(|t| t.last_at >= since.timestamp()).collect();
+    let ticket_lines = email_tickets(db, organization, &active, since).await;
+
+    let date = now.date_naive();
+    let (awaiting_text, mut redactions) = crate::redaction::redact(&render_threads(awaiting.iter().copied(), true));
+    let (new_text, new_redactions) = crate::redaction::redact(&render_threads(new.iter().copied(), false));
+    redactions.merge(new_redactions);
+    let mut vars = HashMap::new();
+    vars.insert("date".to_string(), date.format("%A %B %-d, %Y").to_string());
+    vars.insert("awaiting_reply".to_string(), awaiting_text);
+    vars.insert("new_threads".to_string(), new_text);
+    vars.insert("email_tickets".to_string(), ticket_lines.join("\n"));
+
+    let content = if new.is_empty() && awaiting.is_empty() && ticket_lines.is_empty() {
+        "No new email, nothing awaiting a reply and no tickets filed from email.".to_string()
+    } else {
+        crate::quotas::check_agent_run(db, organization)
+            .await
+            .map_err(|exceeded| anyhow::anyhow!(exceeded.message()))?;
+        let subject = format!("digest:{}:{}", user.user_id, date);
+        crate::redaction::store_audit(db, Some(organization), &AgentType::EmailDigest, &subject, &redactions).await;
+        let (content, usage) = run_digest_agent(db, organization, vars).await?;
+        crate::quotas::record_agent_run(db, organization, &usage).await;
+        content
+    };
+
+    let stored = email_digests::create_digest(
+        db,
+        &CreateEmailDigestRequest {
+            user_id: user.user_id.clone(),
+            organization: organization.to_string(),
+            date: date.to_string(),
+            content: content.clone(),
+        },
+    )
+    .await?;
+
+    Ok(Digest {
+        date: date.to_string(),
+        digest_id: stored.digest_id,
+        new_threads: new.len(),
+        awaiting_reply: awaiting.len(),
+        email_tickets: ticket_lines.len(),
+        content,
+        emailed_message_id: None,
+    })
+}
+
+/// Email a generated digest, returning the SES message id
+pub async fn email_digest(
+    db: &SqlitePool,
+    organization: &str,
+    digest: &Digest,
+    from: &str,
+    to: &str,
+) -> std::result::Result<String, (axum::http::StatusCode, String)> {
+    let req = crate::handlers::SendEmailRequest {
+        to: vec![to.to_string()],
+        cc: Vec::new(),
+        bcc: Vec::new(),
+        subject: format!("Email digest for {}", digest.date),
+        body_text: Some(digest.content.clone()),
+        body_html: None,
+        from: from.to_string(),
+        reply_to: None,
+    };
+    crate::handlers::deliver_email(db, organization, &req).await
+}
+
+/// Whether the user already has a digest for the date
+async fn has_digest(db: &SqlitePool, user: &User, organization: &str, date: NaiveDate) -> Result<bool> {
+    Ok(email_digests::get_for_user(db, &user.user_id, organization, &date.to_string()).await?.is_some())
+}
+
+/// Generate (and email, if configured) today's digest if it's due and missing
+pub async fn run_daily_digest(db: &SqlitePool, now: DateTime<Utc>) -> Result<Option<Digest>> {
+    let Some(config) = load_config()? else {
+        return Ok(None);
+    };
+    if now.hour() < config.hour {
+        return Ok(None);
+    }
+    let user = users::get_user_by_name(db, &config.user)
+        .await?
+        .with_context(|| format!("Email digest user {} not found", config.user))?;
+    if !crate::tenancy::has_standing(db, &user.user_id, Some(&config.organization)).await? {
+        anyhow::bail!("Email digest user {} is not a member of {}", config.user, config.organization);
+    }
+    if has_digest(db, &user, &config.organization, now.date_naive()).await? {
+        return Ok(None);
+    }
+
+    let mailboxes = if config.mailboxes.is_empty() {
+        crate::email_fetcher::mailbox_addresses().await
+    } else {
+        config.mailboxes.clone()
+    };
+    let mailboxes = crate::handlers::filter_readable(db, &user, mailboxes, |m| m.as_str())
+        .await
+        .map_err(|(_, e)| anyhow::anyhow!(e))?;
+    if mailboxes.is_empty() {
+        anyhow::bail!("Email digest user {} cannot read any of the configured mailboxes", config.user);
+    }
+    let mut digest = generate_digest(db, &config.organization, &user, &mailboxes, 24, now).await?;
+    if let (Some(to), Some(from)) = (&config.email_to, &config.email_from) {
+        match email_digest(db, &config.organization, &digest, from, to).await {
+            Ok(message_id) => digest.emailed_message_id = Some(message_id),
+            Err((_, e)) => tracing::warn!("Failed to email digest to {}: {}", to, e),
+        }
+    }
+    Ok(Some(digest))
+}
+
+/// Start the daily digest job
+pub fn start_email_digest_job(db: Arc<SqlitePool>) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(std::time::Duration::from_secs(CHECK_INTERVAL_SECS));
+        loop {
+            interval.tick().await;
+            if crate::maintenance::is_enabled() {
+                continue;
+            }
+            match run_daily_digest(&db, Utc::now()).await {
+                Ok(Some(digest)) => tracing::info!(
+                    "Generated email digest for {} ({} new, {} awaiting reply)",
+                    digest.date,
+                    digest.new_threads,
+                    digest.awaiting_reply
+                ),
+                Ok(None) => {}
+                Err(e) => tracing::error!("Email digest failed: {:?}", e),
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn email(thread: &str, from: &str, at: i64, inbound: bool, is_reply: bool) -> DigestEmail {
+        DigestEmail {
+            thread: thread.to_string(),
+            subject: format!("{} subject", thread),
+            from: from.to_string(),
+            received_at: at,
+            inbound,
+            is_reply,
+            body: "Can you   send\nthe quote?".to_string(),
+        }
+    }
+
+    #[test]
+    fn groups_threads_and_flags_new_and_unanswered() {
+        let since = 1_000;
+        let threads = group_threads(
+            vec![
+                email("a", "dana@example.com", 1_100, true, false),
+                email("b", "me@example.com", 900, false, false),
+                email("b", "sam@example.com", 1_200, true, true),
+                email("c", "lee@example.com", 500, true, false),
+                email("c", "me@example.com", 1_300, false, true),
+            ],
+            since,
+        );
+        let summary: Vec<(&str, usize, bool, bool)> =
+            threads.iter().map(|t| (t.id.as_str(), t.messages, t.new, t.awaiting_reply)).collect();
+        assert_eq!(summary, vec![("c", 2, false, false), ("b", 2, false, true), ("a", 1, true, true)]);
+        assert_eq!(threads[1].from, "sam@example.com");
+        assert_eq!(threads[1].snippet, "Can you send the quote?");
+    }
+}
@@ -4,8 +4,13 @@ use async_std::net::TcpStream;
 use mail_parser::MessageParser;
 use std::sync::Arc;
 use std::time::Duration;
+use tokio::sync::RwLock;
 use ticketing_system::{emails, CreateEmailRequest, SqlitePool};
 
+use crate::email_health;
+
+type ImapSession = async_imap::Session<async_native_tls::TlsStream<TcpStream>>;
+
 /// Email account configuration
 #[derive(Debug, Clone)]
 pub struct EmailAccount {
@@ -13,21 +18,123 @@ pub struct EmailAccount {
     pub password: String,
     pub imap_host: String,
     pub imap_port: u16,
+    /// Folders (or Gmail labels) synced for this account
+    pub folders: Vec<SyncedFolder>,
+    /// Organization owning the account; instance admins own accounts without one
+    pub organization: Option<String>,
+}
+
+/// An IMAP folder and the name its emails are stored under
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyncedFolder {
+    pub imap: String,
+    pub name: String,
+}
+
+impl EmailAccount {
+    /// The synced folder stored under `name`
+    pub fn folder(&self, name: &str) -> Option<&SyncedFolder> {
+        self.folders.iter().find(|f| f.name.eq_ignore_ascii_case(name))
+    }
+}
+
+fn default_folders() -> Vec<SyncedFolder> {
+    vec![
+        SyncedFolder { imap: "INBOX".to_string(), name: "INBOX".to_string() },
+        // WorkMail uses "Sent Items"
+        SyncedFolder { imap: "Sent Items".to_string(), name: "Sent".to_string() },
+    ]
+}
+
+/// Stored `message_id` for a message: `<mailbox>:<folder>:<uid>`
+fn stored_message_id(mailbox: &str, folder: &str, uid: u32) -> String {
+    format!("{}:{}:{}", mailbox, folder, uid)
+}
+
+/// Stored `message_id` of a message moved to `folder` whose new UID isn't
+/// known yet. It has no UID, so the message can't be acted on until the next
+/// sync of the folder finds it by its Message-ID header and records the UID.
+fn unsynced_message_id(mailbox: &str, folder: &str, old_uid: u32) -> String {
+    format!("{}:{}:moved-{}", mailbox, folder, old_uid)
+}
+
+fn uid_from_message_id(message_id: &str) -> Option<u32> {
+    message_id.rsplit(':').next()?.parse().ok()
+}
+
+/// UID of a stored message on the server, or why it can't be acted on
+pub fn server_uid(message_id: &str) -> Result<u32> {
+    match uid_from_message_id(message_id) {
+        Some(uid) => Ok(uid),
+        None if message_id.contains(":moved-") => {
+            anyhow::bail!("The message was moved and hasn't been synced from its new folder yet; try again shortly")
+        }
+        None => anyhow::bail!("Stored message id has no IMAP UID"),
+    }
+}
+
+/// IMAP `STORE` changes setting `\Seen` and `\Flagged` to match the read
+/// and starred state
+fn flag_changes(is_read: Option<bool>, is_starred: Option<bool>) -> Vec<String> {
+    [(is_read, "\\Seen"), (is_starred, "\\Flagged")]
+        .into_iter()
+        .filter_map(|(set, flag)| set.map(|set| format!("{}FLAGS ({})", if set { "+" } else { "-" }, flag)))
+        .collect()
 }
 
-/// Start the background email fetcher task
-pub fn start_email_fetcher(db_pool: Arc<SqlitePool>, accounts: Vec<EmailAccount>) {
+lazy_static::lazy_static! {
+    /// Accounts polled by the fetcher; replaced on config reload
+    static ref ACCOUNTS: RwLock<Vec<EmailAccount>> = RwLock::new(Vec::new());
+}
+
+/// Replace the set of polled accounts. Takes effect on the next poll.
+pub async fn set_email_accounts(accounts: Vec<EmailAccount>) {
+    *ACCOUNTS.write().await = accounts;
+}
+
+/// The configured account for a mailbox address, if it is synced
+pub async fn find_account(mailbox: &str) -> Option<EmailAccount> {
+    ACCOUNTS
+        .read()
+        .await
+        .iter()
+        .find(|a| a.email.eq_ignore_ascii_case(mailbox))
+        .cloned()
+}
+
+/// Addresses of all synced mailboxes
+pub async fn mailbox_addresses() -> Vec<String> {
+    ACCOUNTS.read().await.iter().map(|a| a.email.clone()).collect()
+}
+
+/// Start the background email fetcher task. Polls whatever accounts are
+/// currently configured, so it idles until `set_email_accounts` provides some.
+pub fn start_email_fetcher(db_pool: Arc<SqlitePool>) {
     tokio::spawn(async move {
         let poll_interval = Duration::from_secs(60); // Check every minute
 
         loop {
+            if crate::maintenance::is_enabled() {
+                tokio::time::sleep(poll_interval).await;
+                continue;
+            }
+            let accounts = ACCOUNTS.read().await.clone();
             for account in &accounts {
-                if let Err(e) = fetch_emails_for_account(&db_pool, account).await {
-                    tracing::error!(
-                        "Failed to fetch emails for {}: {:?}",
-                        account.email,
-                        e
-                    );
+                // Broken accounts are polled less often, see `email_health`
+                if !email_health::is_due(&account.email).await {
+                    continue;
+                }
+                match fetch_emails_for_account(&db_pool, account).await {
+                    Ok(()) => email_health::record_success(&db_pool, &account.email).await,
+                    Err(e) => {
+                        tracing::error!(
+                            "Failed to fetch emails for {}: {:?}",
+                            account.email,
+                            e
+                        );
+                        email_health::record_failure(&db_pool, &account.email, &format!("{:#}", e), poll_interval)
+                            .await;
+                    }
                 }
             }
 
@@ -36,10 +143,8 @@ pub fn start_email_fetcher(db_pool: Arc<SqlitePool>, accounts: Vec<EmailAccount>
     });
 }
 
-/// Fetch emails for a single account (both INBOX and Sent folders)
-async fn fetch_emails_for_account(db_pool: &SqlitePool, account: &EmailAccount) -> Result<()> {
-    tracing::debug!("Fetching emails for {}", account.email);
-
+/// Connect and log in to the account's IMAP server
+async fn connect(account: &EmailAccount) -> Result<ImapSession> {
     // Connect to IMAP server using async-std TcpStream
     let tcp_stream = TcpStream::connect(format!("{}:{}", account.imap_host, account.imap_port))
         .await
@@ -54,20 +159,21 @@ async fn fetch_emails_for_account(db_pool: &SqlitePool, account: &EmailAccount)
     let client = async_imap::Client::new(tls_stream);
 
     // Login
-    let mut session = client
+    client
         .login(&account.email, &account.password)
         .await
-        .map_err(|e| anyhow::anyhow!("IMAP login failed: {:?}", e.0))?;
+        .map_err(|e| anyhow::anyhow!("IMAP login failed: {:?}", e.0))
+}
 
-    // Fetch from both INBOX and Sent folders
-    let folders = vec![
-        ("INBOX", "INBOX"),
-        ("Sent Items", "Sent"),  // WorkMail uses "Sent Items"
-    ];
+/// Fetch emails for a single account from each of its synced folders
+async fn fetch_emails_for_account(db_pool: &SqlitePool, account: &EmailAccount) -> Result<()> {
+    tracing::debug!("Fetching emails for {}", account.email);
+
+    let mut session = connect(account).await?;
 
-    for (imap_folder, db_folder) in folders {
-        if let Err(e) = fetch_folder(&mut session, db_pool, account, imap_folder, db_folder).await {
-            tracing::warn!("Failed to fetch {} for {}: {:?}", imap_folder, account.email, e);
+    for folder in &account.folders {
+        if let Err(e) = fetch_folder(&mut session, db_pool, account, &folder.imap, &folder.name).await {
+            tracing::warn!("Failed to fetch {} for {}: {:?}", folder.imap, account.email, e);
         }
     }
 
@@ -79,7 +185,7 @@ async fn fetch_emails_for_account(db_pool: &SqlitePool, account: &EmailAccount)
 
 /// Fetch emails from a specific IMAP folder
 async fn fetch_folder(
-    session: &mut async_imap::Session<async_native_tls::TlsStream<TcpStream>>,
+    session: &mut ImapSession,
     db_pool: &SqlitePool,
     account: &EmailAccount,
     imap_folder: &str,
@@ -132,8 +238,10 @@ async fn fetch_folder(
             }
         };
 
-        let uid = message.uid.unwrap_or(0);
-        let message_id = format!("{}:{}:{}", account.email, db_folder, uid);
+        let Some(uid) = message.uid else {
+            continue;
+        };
+        let message_id = stored_message_id(&account.email, db_folder, uid);
 
         // Check if we already have this email
         if emails::email_exists(db_pool, &message_id).await? {
@@ -188,6 +296,34 @@ async fn fetch_folder(
                     .map(|s| s.to_string())
                     .or_else(|| in_reply_to.clone());
 
+                // A message we already have under another UID: moved here by
+                // `move_message` (record its UID) or, with Gmail, the same
+                // message under another label (keep the one copy)
+                let header_message_id = parsed.message_id().map(|s| s.to_string());
+                if let Some(header_id) = &header_message_id {
+                    if let Some(existing) =
+                        emails::find_by_header_message_id(db_pool, &account.email, header_id).await?
+                    {
+                        if existing.folder.eq_ignore_ascii_case(db_folder) {
+                            emails::move_email(db_pool, existing.id, db_folder, &message_id).await?;
+                            tracing::info!("Recorded UID {} of email {} in {}", uid, existing.id, db_folder);
+                        }
+                        continue;
+                    }
+                }
+
+                // Our own sent messages are never bounces
+                if !db_folder.eq_ignore_ascii_case("Sent") {
+                    let raw = String::from_utf8_lossy(body);
+                    crate::email_bounces::handle_possible_bounce(
+                        db_pool,
+                        &raw,
+                        &from_addr,
+                        subject.as_deref(),
+                    )
+                    .await;
+                }
+
                 let req = CreateEmailRequest {
                     message_id,
                     mailbox: account.email.clone(),
@@ -202,12 +338,25 @@ async fn fetch_folder(
                     received_at,
                     thread_id,
                     in_reply_to,
+                    header_message_id,
                 };
 
                 if let Err(e) = emails::create_email(db_pool, &req).await {
                     tracing::warn!("Failed to store email: {:?}", e);
                 } else {
                     tracing::info!("Stored new email in {} from {}", db_folder, req.from_address);
+                    if let Some(thread_id) = &req.thread_id {
+                        crate::email_auto_link::link_new_email(
+                            db_pool,
+                            account.organization.as_deref(),
+                            &req.message_id,
+                            thread_id,
+                            req.subject.as_deref(),
+                            req.body_text.as_deref(),
+                            req.body_html.as_deref(),
+                        )
+                        .await;
+                    }
                 }
             }
         }
@@ -216,6 +365,101 @@ async fn fetch_folder(
     Ok(())
 }
 
+/// Move a stored message to another synced folder on the IMAP server.
+///
+/// Returns the message's stored id in the target folder. The new UID is found
+/// by the message's Message-ID header; when it can't be, the next sync of the
+/// target folder finds the message by that header and records its UID (see
+/// `unsynced_message_id`) rather than storing a duplicate.
+pub async fn move_message(
+    account: &EmailAccount,
+    message_id: &str,
+    from: &SyncedFolder,
+    to: &SyncedFolder,
+) -> Result<String> {
+    let uid = server_uid(message_id)?;
+    let mut session = connect(account).await?;
+
+    session.select(&from.imap).await.context("Failed to select source folder")?;
+
+    let header_id = {
+        use futures::StreamExt;
+        let fetches: Vec<_> = session
+            .uid_fetch(uid.to_string(), "RFC822.HEADER")
+            .await
+            .context("Failed to fetch message header")?
+            .collect()
+            .await;
+        fetches
+            .into_iter()
+            .filter_map(|f| f.ok())
+            .find_map(|f| {
+                MessageParser::default()
+                    .parse(f.header()?)
+                    .and_then(|m| m.message_id().map(|id| id.to_string()))
+            })
+    };
+
+    session
+        .uid_mv(uid.to_string(), &to.imap)
+        .await
+        .context("IMAP move failed")?;
+
+    let new_uid = match &header_id {
+        Some(header_id) => {
+            session.select(&to.imap).await.context("Failed to select target folder")?;
+            session
+                .uid_search(format!("HEADER Message-ID \"{}\"", header_id.replace('"', "")))
+                .await
+                .context("Failed to locate moved message")?
+                .into_iter()
+                .max()
+        }
+        None => None,
+    };
+    session.logout().await.ok();
+
+    match new_uid {
+        Some(new_uid) => Ok(stored_message_id(&account.email, &to.name, new_uid)),
+        None => {
+            tracing::warn!("Moved {} to {} but could not find its new UID yet", message_id, to.imap);
+            Ok(unsynced_message_id(&account.email, &to.name, uid))
+        }
+    }
+}
+
+/// Set a stored message's `\Seen` and `\Flagged` flags on the IMAP server to
+/// match its read and starred state; `None` leaves a flag as it is
+pub async fn set_flags(
+    account: &EmailAccount,
+    message_id: &str,
+    folder: &SyncedFolder,
+    is_read: Option<bool>,
+    is_starred: Option<bool>,
+) -> Result<()> {
+    let changes = flag_changes(is_read, is_starred);
+    if changes.is_empty() {
+        return Ok(());
+    }
+    let uid = server_uid(message_id)?;
+    let mut session = connect(account).await?;
+    session.select(&folder.imap).await.context("Failed to select folder")?;
+    for change in &changes {
+        use futures::StreamExt;
+        let updates: Vec<_> = session
+            .uid_store(uid.to_string(), change)
+            .await
+            .with_context(|| format!("IMAP STORE {} failed", change))?
+            .collect()
+            .await;
+        if let Some(Err(e)) = updates.into_iter().find(|u| u.is_err()) {
+            anyhow::bail!("IMAP STORE {} failed: {:?}", change, e);
+        }
+    }
+    session.logout().await.ok();
+    Ok(())
+}
+
 /// Load email accounts from config file
 pub fn load_email_accounts() -> Result<Vec<EmailAccount>> {
     let config_path = dirs::home_dir()
@@ -244,6 +488,11 @@ pub fn load_email_accounts() -> Result<Vec<EmailAccount>> {
             password: a.password,
             imap_host: a.imap_host.unwrap_or_else(|| "imap.mail.us-east-1.awsapps.com".to_string()),
             imap_port: a.imap_port.unwrap_or(993),
+            folders: match a.folders {
+                Some(folders) if !folders.is_empty() => folders.into_iter().map(FolderConfig::into_synced).collect(),
+                _ => default_folders(),
+            },
+            organization: a.organization,
         })
         .collect())
 }
@@ -254,4 +503,58 @@ struct EmailAccountConfig {
     password: String,
     imap_host: Option<String>,
     imap_port: Option<u16>,
+    /// Folders/labels to sync; defaults to INBOX and Sent
+    folders: Option<Vec<FolderConfig>>,
+    organization: Option<String>,
+}
+
+/// `"Archive"` or `{"imap": "[Gmail]/Sent Mail", "name": "Sent"}`
+#[derive(serde::Deserialize)]
+#[serde(untagged)]
+enum FolderConfig {
+    Name(String),
+    Mapped { imap: String, name: Option<String> },
+}
+
+impl FolderConfig {
+    fn into_synced(self) -> SyncedFolder {
+        match self {
+            FolderConfig::Name(imap) => SyncedFolder { name: imap.clone(), imap },
+            FolderConfig::Mapped { imap, name } => SyncedFolder { name: name.unwrap_or_else(|| imap.clone()), imap },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_uids_from_stored_ids() {
+        let stored = stored_message_id("ops@example.com", "Archive", 42);
+        assert_eq!(stored, "ops@example.com:Archive:42");
+        assert_eq!(server_uid(&stored).unwrap(), 42);
+
+        let unsynced = unsynced_message_id("ops@example.com", "Archive", 42);
+        assert!(server_uid(&unsynced).unwrap_err().to_string().contains("hasn't been synced"));
+        assert!(server_uid("ses-0100018abc").is_err());
+    }
+
+    #[test]
+    fn maps_read_and_starred_to_imap_flags() {
+        assert_eq!(flag_changes(Some(true), None), vec!["+FLAGS (\\Seen)"]);
+        assert_eq!(flag_changes(Some(false), Some(true)), vec!["-FLAGS (\\Seen)", "+FLAGS (\\Flagged)"]);
+        assert!(flag_changes(None, None).is_empty());
+    }
+
+    #[test]
+    fn parses_folder_config() {
+        let folders: Vec<FolderConfig> =
+            serde_json::from_str(r#"["Archive", {"imap": "[Gmail]/Sent Mail", "name": "Sent"}, {"imap": "Work"}]"#)
+                .unwrap();
+        let synced: Vec<SyncedFolder> = folders.into_iter().map(FolderConfig::into_synced).collect();
+        assert_eq!(synced[0], SyncedFolder { imap: "Archive".to_string(), name: "Archive".to_string() });
+        assert_eq!(synced[1], SyncedFolder { imap: "[Gmail]/Sent Mail".to_string(), name: "Sent".to_string() });
+        assert_eq!(synced[2].name, "Work");
+    }
 }
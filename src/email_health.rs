@@ -0,0 +1,193 @@
+//! Email account health
+//!
+//! The fetcher records the outcome of every poll here. An account that keeps
+//! failing is polled less often (see `backoff_cycles`), and once it has failed
+//! `NOTIFY_AFTER_FAILURES` polls in a row a notification is raised, with
+//! another when it recovers. Health is kept in memory and starts fresh on
+//! restart.
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::time::Duration;
+use ticketing_system::{CreateNotificationRequest, SqlitePool};
+use tokio::sync::RwLock;
+
+/// Consecutive failed polls before the account is reported as broken
+pub const NOTIFY_AFTER_FAILURES: u32 = 5;
+
+/// Most polls skipped between attempts on a failing account
+const MAX_BACKOFF_CYCLES: u32 = 60;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HealthStatus {
+    /// Not polled since startup
+    Unknown,
+    Healthy,
+    Failing,
+    /// The server rejected the credentials
+    AuthError,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AccountHealth {
+    pub mailbox: String,
+    pub status: HealthStatus,
+    pub consecutive_failures: u32,
+    pub consecutive_successes: u32,
+    pub last_attempt_at: Option<DateTime<Utc>>,
+    pub last_success_at: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+    /// When a backed-off account is polled next
+    pub next_attempt_at: Option<DateTime<Utc>>,
+    #[serde(skip)]
+    notified: bool,
+}
+
+impl AccountHealth {
+    fn new(mailbox: &str) -> Self {
+        Self {
+            mailbox: mailbox.to_string(),
+            status: HealthStatus::Unknown,
+            consecutive_failures: 0,
+            consecutive_successes: 0,
+            last_attempt_at: None,
+            last_success_at: None,
+            last_error: None,
+            next_attempt_at: None,
+            notified: false,
+        }
+    }
+}
+
+lazy_static::lazy_static! {
+    static ref HEALTH: RwLock<HashMap<String, AccountHealth>> = RwLock::new(HashMap::new());
+}
+
+/// Polls to skip after `failures` consecutive failures: none for the first
+/// two, then doubling up to `MAX_BACKOFF_CYCLES`
+fn backoff_cycles(failures: u32) -> u32 {
+    match failures {
+        0..=2 => 0,
+        n => 2u32.saturating_pow(n - 2).min(MAX_BACKOFF_CYCLES),
+    }
+}
+
+/// Whether a fetch error means the credentials were rejected
+fn is_auth_error(error: &str) -> bool {
+    let error = error.to_lowercase();
+    ["login failed", "authenticationfailed", "invalid credentials", "authentication failed"]
+        .iter()
+        .any(|marker| error.contains(marker))
+}
+
+/// Health of a mailbox, `Unknown` until its first poll
+pub async fn account_health(mailbox: &str) -> AccountHealth {
+    let key = mailbox.to_lowercase();
+    HEALTH.read().await.get(&key).cloned().unwrap_or_else(|| AccountHealth::new(mailbox))
+}
+
+/// Whether the fetcher should poll the mailbox now, or skip it while backing off
+pub async fn is_due(mailbox: &str) -> bool {
+    let key = mailbox.to_lowercase();
+    HEALTH
+        .read()
+        .await
+        .get(&key)
+        .and_then(|h| h.next_attempt_at)
+        .is_none_or(|next| next <= Utc::now())
+}
+
+/// Record a successful poll, clearing any backoff
+pub async fn record_success(db: &SqlitePool, mailbox: &str) {
+    let now = Utc::now();
+    let recovered = {
+        let mut health = HEALTH.write().await;
+        let entry = health.entry(mailbox.to_lowercase()).or_insert_with(|| AccountHealth::new(mailbox));
+        let recovered = entry.notified;
+        entry.status = HealthStatus::Healthy;
+        entry.consecutive_failures = 0;
+        entry.consecutive_successes += 1;
+        entry.last_attempt_at = Some(now);
+        entry.last_success_at = Some(now);
+        entry.last_error = None;
+        entry.next_attempt_at = None;
+        entry.notified = false;
+        recovered
+    };
+
+    if recovered {
+        tracing::info!("Email account {} recovered", mailbox);
+        crate::notifications::notify(
+            db,
+            CreateNotificationRequest {
+                kind: "email_account_recovered".to_string(),
+                title: format!("Email account {} is syncing again", mailbox),
+                body: None,
+                ticket_id: None,
+                organization: crate::email_fetcher::find_account(mailbox).await.and_then(|a| a.organization),
+            },
+        )
+        .await;
+    }
+}
+
+/// Record a failed poll and schedule the next attempt, `poll_interval` per skipped poll
+pub async fn record_failure(db: &SqlitePool, mailbox: &str, error: &str, poll_interval: Duration) {
+    let now = Utc::now();
+    let (failures, status, notify) = {
+        let mut health = HEALTH.write().await;
+        let entry = health.entry(mailbox.to_lowercase()).or_insert_with(|| AccountHealth::new(mailbox));
+        entry.consecutive_failures += 1;
+        entry.consecutive_successes = 0;
+        entry.status = if is_auth_error(error) { HealthStatus::AuthError } else { HealthStatus::Failing };
+        entry.last_attempt_at = Some(now);
+        entry.last_error = Some(error.to_string());
+
+        // Half an interval of slack so exactly `skipped` polls pass first
+        let skipped = backoff_cycles(entry.consecutive_failures);
+        entry.next_attempt_at = (skipped > 0).then(|| {
+            now + chrono::Duration::from_std(poll_interval * skipped + poll_interval / 2).unwrap_or_default()
+        });
+
+        let notify = !entry.notified && entry.consecutive_failures >= NOTIFY_AFTER_FAILURES;
+        entry.notified |= notify;
+        (entry.consecutive_failures, entry.status, notify)
+    };
+
+    if notify {
+        let reason = match status {
+            HealthStatus::AuthError => "The server rejected its credentials",
+            _ => "Syncing failed",
+        };
+        crate::notifications::notify(
+            db,
+            CreateNotificationRequest {
+                kind: "email_account_failing".to_string(),
+                title: format!("Email account {} has stopped syncing", mailbox),
+                body: Some(format!("{} on the last {} attempts: {}", reason, failures, error)),
+                ticket_id: None,
+                organization: crate::email_fetcher::find_account(mailbox).await.and_then(|a| a.organization),
+            },
+        )
+        .await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backs_off_failing_accounts() {
+        assert_eq!(backoff_cycles(1), 0);
+        assert_eq!(backoff_cycles(2), 0);
+        assert_eq!(backoff_cycles(3), 2);
+        assert_eq!(backoff_cycles(5), 8);
+        assert_eq!(backoff_cycles(40), MAX_BACKOFF_CYCLES);
+
+        assert!(is_auth_error("IMAP login failed: No(\"[AUTHENTICATIONFAILED] Invalid credentials\")"));
+        assert!(!is_auth_error("Failed to connect to IMAP server"));
+    }
+}
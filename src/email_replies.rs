@@ -0,0 +1,198 @@
+//! Suggested replies
+//!
+//! `GET /api/emails/:id/suggested-replies` has the email-replies agent write
+//! two or three short replies to an email: acknowledge, ask for details and
+//! decline. They're cached on the email row as JSON, so the agent only runs
+//! once per email unless a refresh or a different style (see
+//! `crate::email_style`) is asked for, and any of them can be turned into a
+//! draft reply with one call.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use cc_sdk::{query, ClaudeCodeOptions, ContentBlock, Message, ToolsConfig};
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use ticketing_system::{emails, Email, SqlitePool};
+
+use crate::agents::claude_code::parse_usage;
+use crate::agents::prompts::load_prompt;
+use crate::agents::tool_audit::{store_standalone_tool_calls, ToolCallLog};
+use crate::agents::{AgentType, RunUsage};
+use crate::email_style::EmailStyle;
+
+pub const KIND_ACKNOWLEDGE: &str = "acknowledge";
+pub const KIND_ASK_DETAILS: &str = "ask_details";
+pub const KIND_DECLINE: &str = "decline";
+
+const KINDS: &[&str] = &[KIND_ACKNOWLEDGE, KIND_ASK_DETAILS, KIND_DECLINE];
+
+/// Characters of the email body given to the agent
+const MAX_BODY_CHARS: usize = 6000;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SuggestedReply {
+    /// `acknowledge`, `ask_details` or `decline`
+    pub kind: String,
+    /// A few words on what the reply says
+    pub summary: String,
+    pub body: String,
+    /// The style the reply was asked for in
+    #[serde(default, skip_serializing_if = "EmailStyle::is_empty")]
+    pub style: EmailStyle,
+}
+
+/// Pull the suggestions out of the agent's output: the JSON array in it,
+/// keeping one reply of each known kind
+fn parse_suggestions(output: &str) -> Result<Vec<SuggestedReply>> {
+    let start = output.find('[').context("No JSON array in email-replies output")?;
+    let end = output.rfind(']').filter(|end| *end > start).context("No JSON array in email-replies output")?;
+    let parsed: Vec<SuggestedReply> =
+        serde_json::from_str(&output[start..=end]).context("Failed to parse email-replies output")?;
+
+    let mut suggestions: Vec<SuggestedReply> = Vec::new();
+    for reply in parsed {
+        let body = reply.body.trim();
+        if !KINDS.contains(&reply.kind.as_str()) || body.is_empty() || suggestions.iter().any(|s| s.kind == reply.kind) {
+            continue;
+        }
+        suggestions.push(SuggestedReply { body: body.to_string(), summary: reply.summary.trim().to_string(), ..reply });
+    }
+    if suggestions.is_empty() {
+        anyhow::bail!("No usable replies in email-replies output");
+    }
+    Ok(suggestions)
+}
+
+/// Subject of a reply to `subject`
+pub fn reply_subject(subject: Option<&str>) -> String {
+    let subject = subject.unwrap_or_default().trim();
+    if subject.to_lowercase().starts_with("re:") {
+        subject.to_string()
+    } else {
+        format!("Re: {}", subject)
+    }
+}
+
+/// The suggestions cached on an email, if any, in whatever style they were asked for
+pub fn cached_suggestions(email: &Email) -> Option<Vec<SuggestedReply>> {
+    let cached = email.suggested_replies.as_deref()?;
+    match serde_json::from_str(cached) {
+        Ok(suggestions) => Some(suggestions),
+        Err(e) => {
+            tracing::warn!("Ignoring unreadable suggested replies on email {}: {}", email.id, e);
+            None
+        }
+    }
+}
+
+async fn run_replies_agent(db: &SqlitePool, organization: &str, vars: HashMap<String, String>) -> Result<(String, RunUsage)> {
+    let system_prompt = load_prompt("email-replies", vars).context("Failed to load email-replies prompt")?;
+    let agent_type = AgentType::EmailReplies;
+    let mut options = ClaudeCodeOptions::builder()
+        .system_prompt(&system_prompt)
+        .model(agent_type.model())
+        .tools(ToolsConfig::none())
+        .max_turns(agent_type.max_turns().unwrap_or(1))
+        .cwd(PathBuf::from(env!("CARGO_MANIFEST_DIR")))
+        .build();
+    options.env.extend(crate::llm_keys::anthropic_env(db, organization).await);
+    let env = options.env.clone();
+
+    let prompt = "Suggest replies to the email provided in the system prompt.";
+    let mut stream = Box::pin(query(prompt, Some(options)).await.context("Failed to run email-replies agent")?);
+    let mut output_parts = Vec::new();
+    let mut usage = RunUsage::default();
+    let mut tool_log = ToolCallLog::new(&env);
+    let mut session_id = None;
+    while let Some(message) = stream.next().await {
+        if let Ok(message) = &message {
+            tool_log.observe(message);
+        }
+        match message {
+            Ok(Message::Assistant { message }) => {
+                for block in &message.content {
+                    if let ContentBlock::Text(text) = block {
+                        output_parts.push(text.text.clone());
+                    }
+                }
+            }
+            Ok(Message::Result { usage: result_usage, total_cost_usd, session_id: sess_id, .. }) => {
+                usage = parse_usage(result_usage.as_ref(), total_cost_usd);
+                session_id = Some(sess_id);
+                break;
+            }
+            Ok(_) => {}
+            Err(e) => {
+                tracing::error!("Error receiving message from email-replies agent: {}", e);
+                break;
+            }
+        }
+    }
+    store_standalone_tool_calls(db, session_id.as_deref(), Some(organization), &agent_type, &tool_log.into_calls()).await;
+    if output_parts.is_empty() {
+        anyhow::bail!("No output from email-replies agent");
+    }
+    Ok((output_parts.join("\n"), usage))
+}
+
+/// Suggest replies to an email in a style and cache them on its row. The run
+/// is billed to `organization`, which the caller has checked has quota left.
+pub async fn suggest_replies(
+    db: &SqlitePool,
+    organization: &str,
+    email: &Email,
+    style: &EmailStyle,
+) -> Result<Vec<SuggestedReply>> {
+    let from = match &email.from_name {
+        Some(name) if !name.trim().is_empty() => format!("{} <{}>", name.trim(), email.from_address),
+        _ => email.from_address.clone(),
+    };
+    let body: String = email.body_text.as_deref().unwrap_or_default().chars().take(MAX_BODY_CHARS).collect();
+    let (body, redactions) = crate::redaction::redact(&body);
+    let subject = format!("email:{}", email.id);
+    crate::redaction::store_audit(db, Some(organization), &AgentType::EmailReplies, &subject, &redactions).await;
+    let mut vars = HashMap::new();
+    vars.insert("from".to_string(), from);
+    vars.insert("subject".to_string(), email.subject.clone().unwrap_or_else(|| "(no subject)".to_string()));
+    vars.insert("body".to_string(), body);
+    vars.extend(style.prompt_vars());
+
+    let (output, usage) = run_replies_agent(db, organization, vars).await?;
+    crate::quotas::record_agent_run(db, organization, &usage).await;
+    let mut suggestions = parse_suggestions(&output)?;
+    for reply in &mut suggestions {
+        reply.style = style.clone();
+    }
+    let cached = serde_json::to_string(&suggestions)?;
+    if let Err(e) = emails::set_suggested_replies(db, email.id, Some(&cached)).await {
+        tracing::warn!("Failed to cache suggested replies on email {}: {}", email.id, e);
+    }
+    Ok(suggestions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_suggestions_from_agent_output() {
+        let output = r#"Here you go:
+[
+  {"kind": "acknowledge", "summary": "Got it", "body": " Thanks, will review by Friday. "},
+  {"kind": "acknowledge", "summary": "Again", "body": "Duplicate"},
+  {"kind": "haiku", "summary": "?", "body": "Autumn inbox"},
+  {"kind": "decline", "summary": "Not now", "body": ""},
+  {"kind": "ask_details", "summary": "Scope", "body": "Which sites are included?"}
+]"#;
+        let suggestions = parse_suggestions(output).unwrap();
+        let kinds: Vec<&str> = suggestions.iter().map(|s| s.kind.as_str()).collect();
+        assert_eq!(kinds, vec![KIND_ACKNOWLEDGE, KIND_ASK_DETAILS]);
+        assert_eq!(suggestions[0].body, "Thanks, will review by Friday.");
+        assert!(parse_suggestions("I can't help with that").is_err());
+
+        assert_eq!(reply_subject(Some("Renewal")), "Re: Renewal");
+        assert_eq!(reply_subject(Some("RE: Renewal")), "RE: Renewal");
+    }
+}
@@ -0,0 +1,216 @@
+//! Send-email pipeline steps
+//!
+//! A `send_email` step sends the email written by an earlier `email` agent
+//! step, closing the research → draft → approve → send loop. When an email
+//! step completes and a later send step will consume it, its parsed email is
+//! saved as a draft from the send step's `from_address` input, and the draft
+//! id goes into the email step's outputs. Send steps are always manual: the
+//! draft can be reviewed and edited while the step awaits approval, and
+//! approving it sends the draft and records `draft_id` and `message_id` in
+//! the send step's outputs.
+//!
+//! By default a send step takes the nearest earlier email step; its
+//! `draft_step` input can name another one.
+
+use serde_json::{json, Value};
+use ticketing_system::{drafts, models::Pipeline, tickets, CreateDraftRequest, EmailDraft, SqlitePool, Ticket};
+use tracing::{error, info, warn};
+
+use crate::agents::{AgentType, EmailOutput};
+use crate::email_style::EmailStyle;
+use crate::pipeline_automation::{advance_pipeline_after_step, resolve_step_inputs};
+
+pub const SEND_EMAIL_STEP: &str = "send_email";
+
+/// Send step input naming the email step to take the draft from
+pub const DRAFT_STEP_INPUT: &str = "draft_step";
+
+/// Send step input with the address to send from
+pub const FROM_ADDRESS_INPUT: &str = "from_address";
+
+pub fn is_send_step(agent_type: &str) -> bool {
+    agent_type == SEND_EMAIL_STEP
+}
+
+/// Index of the email step a send step at `send_idx` takes its draft from,
+/// given each step's `(step_id, agent_type)`
+pub fn source_step_idx(steps: &[(&str, &str)], send_idx: usize, draft_step: Option<&str>) -> Option<usize> {
+    steps[..send_idx.min(steps.len())]
+        .iter()
+        .rposition(|(step_id, agent_type)| {
+            *agent_type == AgentType::Email.as_str() && draft_step.is_none_or(|wanted| wanted == *step_id)
+        })
+}
+
+fn step_pairs(pipeline: &Pipeline) -> Vec<(&str, &str)> {
+    pipeline.steps.iter().map(|s| (s.step_id.as_str(), s.agent_type.as_str())).collect()
+}
+
+fn input_str(ticket: &Ticket, pipeline: &Pipeline, step_idx: usize, key: &str) -> Option<String> {
+    resolve_step_inputs(ticket, pipeline, step_idx)
+        .get(key)
+        .and_then(|v| v.as_str())
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+}
+
+/// Save a completed email step's output as a draft when a later send step
+/// will send it, adding `draft_id` to the step's outputs
+pub async fn capture_draft(pool: &SqlitePool, ticket: &Ticket, step_id: &str, outputs: &mut Option<Value>) {
+    let Some(pipeline) = &ticket.pipeline else { return };
+    let Some(step_idx) = pipeline.steps.iter().position(|s| s.step_id == step_id) else { return };
+    if pipeline.steps[step_idx].agent_type != AgentType::Email.as_str() {
+        return;
+    }
+
+    let pairs = step_pairs(pipeline);
+    let Some(send_idx) = (step_idx + 1..pipeline.steps.len()).find(|&idx| {
+        is_send_step(&pipeline.steps[idx].agent_type)
+            && source_step_idx(&pairs, idx, input_str(ticket, pipeline, idx, DRAFT_STEP_INPUT).as_deref())
+                == Some(step_idx)
+    }) else {
+        return;
+    };
+
+    let summary = outputs.as_ref().and_then(|o| o.get("summary")).and_then(|s| s.as_str());
+    let Some(email) = summary.and_then(EmailOutput::parse) else {
+        warn!("Email step {} on ticket {} produced no parseable email to send", step_id, ticket.ticket_id);
+        return;
+    };
+    let Some(from_address) = input_str(ticket, pipeline, send_idx, FROM_ADDRESS_INPUT) else {
+        warn!(
+            "Send step {} on ticket {} has no {} input; not drafting",
+            pipeline.steps[send_idx].step_id, ticket.ticket_id, FROM_ADDRESS_INPUT
+        );
+        return;
+    };
+
+    let style = EmailStyle::from_inputs(&resolve_step_inputs(ticket, pipeline, step_idx));
+    let draft = match drafts::create_draft(
+        pool,
+        &CreateDraftRequest {
+            from_address,
+            to_address: email.to,
+            cc_address: email.cc,
+            subject: email.subject,
+            body: email.body,
+            ticket_id: Some(ticket.ticket_id.clone()),
+            epic_id: Some(ticket.epic_id.clone()),
+            slice_id: Some(ticket.slice_id.clone()),
+            variant_group: None,
+            variant_label: None,
+            tone: style.tone,
+            length: style.length,
+            language: style.language,
+        },
+    )
+    .await
+    {
+        Ok(draft) => draft,
+        Err(e) => {
+            warn!("Failed to save draft from step {} on ticket {}: {}", step_id, ticket.ticket_id, e);
+            return;
+        }
+    };
+    if let Err(e) = ticketing_system::ticket_history::log_draft_created(
+        pool,
+        &ticket.ticket_id,
+        draft.id,
+        &draft.to_address,
+        &draft.subject,
+    )
+    .await
+    {
+        warn!("Failed to log draft creation to ticket history: {}", e);
+    }
+
+    info!("Saved draft {} from email step {} on ticket {}", draft.id, step_id, ticket.ticket_id);
+    if let Some(map) = outputs.get_or_insert_with(|| json!({})).as_object_mut() {
+        map.insert("draft_id".to_string(), json!(draft.id));
+    }
+}
+
+/// The unsent draft a send step will send
+pub async fn draft_for_send_step(pool: &SqlitePool, ticket: &Ticket, step_id: &str) -> Result<EmailDraft, String> {
+    let pipeline = ticket.pipeline.as_ref().ok_or("Ticket has no pipeline")?;
+    let send_idx = pipeline
+        .steps
+        .iter()
+        .position(|s| s.step_id == step_id)
+        .ok_or_else(|| format!("Step not found: {}", step_id))?;
+    let draft_step = input_str(ticket, pipeline, send_idx, DRAFT_STEP_INPUT);
+    let source_idx = source_step_idx(&step_pairs(pipeline), send_idx, draft_step.as_deref())
+        .ok_or_else(|| format!("Send step {} has no earlier email step", step_id))?;
+    let source = &pipeline.steps[source_idx];
+    let draft_id = source
+        .outputs
+        .as_ref()
+        .and_then(|o| o.get("draft_id"))
+        .and_then(|v| v.as_i64())
+        .ok_or_else(|| format!("Email step {} produced no draft", source.step_id))?;
+
+    let draft = drafts::get_draft_by_id(pool, draft_id).await.map_err(|e| e.to_string())?;
+    if draft.status != "draft" {
+        return Err(format!("Draft {} has already been sent or discarded", draft_id));
+    }
+    Ok(draft)
+}
+
+/// Send an approved send step's draft, then complete (or fail) the step and advance the pipeline
+pub async fn run_send_step(pool: &SqlitePool, ticket_id: &str, step_id: &str) {
+    let result = async {
+        let ticket = tickets::get_ticket_by_id(pool, ticket_id)
+            .await
+            .map_err(|e| e.to_string())?
+            .ok_or_else(|| format!("Ticket not found: {}", ticket_id))?;
+        let draft = draft_for_send_step(pool, &ticket, step_id).await?;
+        let draft_id = draft.id;
+
+        if let Some(mut pipeline) = ticket.pipeline.clone() {
+            ticketing_system::pipelines::start_step(&mut pipeline, step_id, &uuid::Uuid::new_v4().to_string());
+            crate::pipeline_status::save_pipeline(pool, ticket_id, &pipeline)
+                .await
+                .map_err(|e| e.to_string())?;
+        }
+
+        let message_id = crate::handlers::deliver_draft(pool, &ticket.organization, draft)
+            .await
+            .map_err(|(_, message)| message)?;
+        Ok::<_, String>(json!({ "draft_id": draft_id, "message_id": message_id }))
+    }
+    .await;
+
+    let (success, outputs) = match result {
+        Ok(outputs) => {
+            info!("Send step {} on ticket {} sent its draft", step_id, ticket_id);
+            (true, outputs)
+        }
+        Err(e) => {
+            warn!("Send step {} on ticket {} failed: {}", step_id, ticket_id, e);
+            (false, json!({ "error": e }))
+        }
+    };
+    if let Err(e) = advance_pipeline_after_step(pool, ticket_id, step_id, success, Some(outputs)).await {
+        error!("Failed to advance pipeline after send step {} on ticket {}: {}", step_id, ticket_id, e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn picks_nearest_or_named_email_step() {
+        let steps = [
+            ("research", "research"),
+            ("intro", "email"),
+            ("followup", "email"),
+            ("review", "human"),
+            ("send", SEND_EMAIL_STEP),
+        ];
+        assert_eq!(source_step_idx(&steps, 4, None), Some(2));
+        assert_eq!(source_step_idx(&steps, 4, Some("intro")), Some(1));
+        assert_eq!(source_step_idx(&steps, 4, Some("research")), None);
+        assert_eq!(source_step_idx(&steps, 1, None), None);
+    }
+}
@@ -0,0 +1,103 @@
+//! Email suppression list
+//!
+//! Addresses an organization must not email: people who opted out, addresses
+//! added by hand, and recipients of its emails that bounced permanently.
+//! Entries of `ALL_ORGANIZATIONS` apply everywhere; bounces of the instance's
+//! own mailboxes (those without an organization) land there, and only
+//! instance admins see or remove them. Every outbound send (`deliver_email`,
+//! `deliver_draft`) checks all of its recipients first and refuses the whole
+//! message if any are suppressed.
+
+use axum::http::StatusCode;
+use ticketing_system::{email_suppressions, CreateEmailSuppressionRequest, EmailSuppression, SqlitePool};
+
+/// Organization of entries that apply to every organization
+pub const ALL_ORGANIZATIONS: &str = "*";
+
+pub const REASON_HARD_BOUNCE: &str = "hard_bounce";
+pub const REASON_UNSUBSCRIBED: &str = "unsubscribed";
+pub const REASON_MANUAL: &str = "manual";
+
+/// Reasons that can be given when adding an address by hand
+pub const USER_REASONS: &[&str] = &[REASON_UNSUBSCRIBED, REASON_MANUAL];
+
+/// Bare lowercase address from `addr@example.com` or `Name <addr@example.com>`
+pub fn normalize_address(address: &str) -> Option<String> {
+    let address = address.trim();
+    let address = match (address.rfind('<'), address.rfind('>')) {
+        (Some(start), Some(end)) if start < end => &address[start + 1..end],
+        _ => address,
+    };
+    let address = address.trim().to_lowercase();
+    let (local, domain) = address.split_once('@')?;
+    let valid = !local.is_empty() && domain.contains('.') && !address.chars().any(|c| c.is_whitespace() || c == ',');
+    valid.then_some(address)
+}
+
+/// Entries that block sending to any of `recipients` from `organization`
+pub async fn suppressed_recipients(
+    db: &SqlitePool,
+    organization: &str,
+    recipients: &[&str],
+) -> anyhow::Result<Vec<EmailSuppression>> {
+    let addresses: Vec<String> = recipients.iter().filter_map(|r| normalize_address(r)).collect();
+    if addresses.is_empty() {
+        return Ok(Vec::new());
+    }
+    let mut found = email_suppressions::find_suppressions(db, organization, &addresses).await?;
+    found.extend(email_suppressions::find_suppressions(db, ALL_ORGANIZATIONS, &addresses).await?);
+    found.sort_by(|a, b| a.address.cmp(&b.address));
+    found.dedup_by(|a, b| a.address == b.address);
+    Ok(found)
+}
+
+/// Refuse a send when any recipient is suppressed, naming each one and why
+pub async fn check_recipients(
+    db: &SqlitePool,
+    organization: &str,
+    recipients: &[&str],
+) -> Result<(), (StatusCode, String)> {
+    let suppressed = suppressed_recipients(db, organization, recipients)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to check suppression list: {}", e)))?;
+    if suppressed.is_empty() {
+        return Ok(());
+    }
+    let listed: Vec<String> = suppressed.iter().map(|s| format!("{} ({})", s.address, s.reason)).collect();
+    Err((
+        StatusCode::UNPROCESSABLE_ENTITY,
+        format!("Not sent: recipients on the suppression list: {}", listed.join(", ")),
+    ))
+}
+
+/// Suppress an address for `organization` after a permanent bounce of one of
+/// its emails
+pub async fn suppress_hard_bounce(db: &SqlitePool, organization: &str, address: &str, reason: &str) {
+    let Some(address) = normalize_address(address) else {
+        return;
+    };
+    let request = CreateEmailSuppressionRequest {
+        organization: organization.to_string(),
+        address: address.clone(),
+        reason: REASON_HARD_BOUNCE.to_string(),
+        note: Some(reason.to_string()),
+    };
+    match email_suppressions::add_suppression(db, request).await {
+        Ok(_) => tracing::info!("Suppressed {} for {} after a permanent bounce", address, organization),
+        Err(e) => tracing::warn!("Failed to suppress bounced address {}: {}", address, e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalizes_addresses() {
+        assert_eq!(normalize_address(" Dana <Dana@Example.com> ").as_deref(), Some("dana@example.com"));
+        assert_eq!(normalize_address("ops@example.org").as_deref(), Some("ops@example.org"));
+        assert_eq!(normalize_address("not an address"), None);
+        assert_eq!(normalize_address("a@localhost"), None);
+        assert_eq!(normalize_address("a@x.com, b@y.com"), None);
+    }
+}
@@ -0,0 +1,338 @@
+//! Filing a ticket straight from an email
+//!
+//! `POST /api/emails/:id/create-ticket` has the email-ticket agent propose a
+//! title, description, epic/slice, priority and labels from the email. The
+//! proposal comes back for the reader to confirm or edit, then is sent back
+//! with `confirm: false` to create the ticket (or created right away when the
+//! first call says so). The new ticket is linked to the email's thread and
+//! records where it came from: the message id as its `email` external id, so
+//! an email is only filed once, and a `source_email` custom field.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use cc_sdk::{query, ClaudeCodeOptions, ContentBlock, Message, ToolsConfig};
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use ticketing_system::{email_thread_tickets, epics, imports, slices, Email, LinkThreadTicketRequest, SqlitePool};
+
+use crate::agents::prompts::load_prompt;
+use crate::agents::tool_audit::{store_standalone_tool_calls, ToolCallLog};
+use crate::agents::AgentType;
+use crate::mcp_wrapper::call_mcp_tool;
+use crate::saved_views::PRIORITY_ORDER;
+use crate::user_events::{self, Subject};
+
+/// External id source recording the message a ticket was filed from
+pub const SOURCE_EMAIL: &str = "email";
+
+/// Characters of the email body given to the agent
+const MAX_BODY_CHARS: usize = 8000;
+const MAX_TITLE_CHARS: usize = 200;
+const MAX_LABELS: usize = 10;
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TicketProposal {
+    pub title: String,
+    #[serde(default)]
+    pub description: String,
+    pub epic_id: Option<String>,
+    pub slice_id: Option<String>,
+    /// `urgent`, `high`, `medium` or `low`
+    pub priority: Option<String>,
+    #[serde(default)]
+    pub labels: Vec<String>,
+}
+
+/// An epic/slice pair a ticket can be filed in
+#[derive(Debug, Clone, PartialEq)]
+pub struct Place {
+    pub epic_id: String,
+    pub epic_title: String,
+    pub slice_id: String,
+    pub slice_title: String,
+}
+
+impl TicketProposal {
+    /// Trim the fields and lowercase priority and labels, dropping blank and repeated labels
+    fn normalized(self) -> Self {
+        let mut labels: Vec<String> = Vec::new();
+        for label in self.labels {
+            let label = label.trim().to_lowercase();
+            if !label.is_empty() && !labels.contains(&label) {
+                labels.push(label);
+            }
+        }
+        let non_empty = |v: Option<String>| v.map(|v| v.trim().to_string()).filter(|v| !v.is_empty());
+        Self {
+            title: self.title.trim().to_string(),
+            description: self.description.trim().to_string(),
+            epic_id: non_empty(self.epic_id),
+            slice_id: non_empty(self.slice_id),
+            priority: non_empty(self.priority).map(|p| p.to_lowercase()),
+            labels,
+        }
+    }
+
+    fn place_is_known(&self, places: &[Place]) -> bool {
+        places
+            .iter()
+            .any(|p| Some(&p.epic_id) == self.epic_id.as_ref() && Some(&p.slice_id) == self.slice_id.as_ref())
+    }
+}
+
+/// Pull the proposal out of the agent's output: the JSON object in it. A
+/// place or priority the agent made up is dropped for the reader to fill in.
+fn parse_proposal(output: &str, places: &[Place]) -> Result<TicketProposal> {
+    let start = output.find('{').context("No JSON object in email-ticket output")?;
+    let end = output.rfind('}').filter(|end| *end > start).context("No JSON object in email-ticket output")?;
+    let parsed: TicketProposal =
+        serde_json::from_str(&output[start..=end]).context("Failed to parse email-ticket output")?;
+
+    let mut proposal = parsed.normalized();
+    if proposal.title.is_empty() {
+        anyhow::bail!("No title in email-ticket output");
+    }
+    proposal.title = proposal.title.chars().take(MAX_TITLE_CHARS).collect();
+    if !proposal.place_is_known(places) {
+        proposal.epic_id = None;
+        proposal.slice_id = None;
+    }
+    if proposal.priority.as_deref().is_some_and(|p| !PRIORITY_ORDER.contains(&p)) {
+        proposal.priority = None;
+    }
+    proposal.labels.truncate(MAX_LABELS);
+    Ok(proposal)
+}
+
+/// Normalize a proposal to file, or say what's wrong with it
+pub fn check_proposal(proposal: TicketProposal, places: &[Place]) -> Result<TicketProposal, String> {
+    let proposal = proposal.normalized();
+    if proposal.title.is_empty() {
+        return Err("title is required".to_string());
+    }
+    if proposal.title.chars().count() > MAX_TITLE_CHARS {
+        return Err(format!("title is longer than {} characters", MAX_TITLE_CHARS));
+    }
+    if proposal.epic_id.is_none() || proposal.slice_id.is_none() {
+        return Err("epic_id and slice_id are required".to_string());
+    }
+    if !proposal.place_is_known(places) {
+        return Err("Unknown epic or slice".to_string());
+    }
+    if let Some(priority) = &proposal.priority {
+        if !PRIORITY_ORDER.contains(&priority.as_str()) {
+            return Err(format!("priority must be one of {}", PRIORITY_ORDER.join(", ")));
+        }
+    }
+    if proposal.labels.len() > MAX_LABELS {
+        return Err(format!("At most {} labels", MAX_LABELS));
+    }
+    Ok(proposal)
+}
+
+/// The epics and slices of an organization
+pub async fn list_places(db: &SqlitePool, organization: &str) -> Result<Vec<Place>> {
+    let mut places = Vec::new();
+    for epic in epics::list_epics(db, Some(organization)).await? {
+        for slice in slices::list_slices(db, organization, &epic.epic_id).await? {
+            places.push(Place {
+                epic_id: epic.epic_id.clone(),
+                epic_title: epic.title.clone(),
+                slice_id: slice.slice_id,
+                slice_title: slice.title,
+            });
+        }
+    }
+    Ok(places)
+}
+
+async fn run_ticket_agent(db: &SqlitePool, organization: &str, vars: HashMap<String, String>) -> Result<String> {
+    let system_prompt = load_prompt("email-ticket", vars).context("Failed to load email-ticket prompt")?;
+    let agent_type = AgentType::EmailTicket;
+    let mut options = ClaudeCodeOptions::builder()
+        .system_prompt(&system_prompt)
+        .model(agent_type.model())
+        .tools(ToolsConfig::none())
+        .max_turns(agent_type.max_turns().unwrap_or(1))
+        .cwd(PathBuf::from(env!("CARGO_MANIFEST_DIR")))
+        .build();
+    options.env.extend(crate::llm_keys::anthropic_env(db, organization).await);
+    let env = options.env.clone();
+
+    let prompt = "Propose a ticket for the email provided in the system prompt.";
+    let mut stream = Box::pin(query(prompt, Some(options)).await.context("Failed to run email-ticket agent")?);
+    let mut output_parts = Vec::new();
+    let mut tool_log = ToolCallLog::new(&env);
+    let mut session_id = None;
+    while let Some(message) = stream.next().await {
+        if let Ok(message) = &message {
+            tool_log.observe(message);
+        }
+        match message {
+            Ok(Message::Assistant { message }) => {
+                for block in &message.content {
+                    if let ContentBlock::Text(text) = block {
+                        output_parts.push(text.text.clone());
+                    }
+                }
+            }
+            Ok(Message::Result { session_id: sess_id, .. }) => {
+                session_id = Some(sess_id);
+                break;
+            }
+            Ok(_) => {}
+            Err(e) => {
+                tracing::error!("Error receiving message from email-ticket agent: {}", e);
+                break;
+            }
+        }
+    }
+    store_standalone_tool_calls(db, session_id.as_deref(), Some(organization), &agent_type, &tool_log.into_calls()).await;
+    if output_parts.is_empty() {
+        anyhow::bail!("No output from email-ticket agent");
+    }
+    Ok(output_parts.join("\n"))
+}
+
+fn sender(email: &Email) -> String {
+    match &email.from_name {
+        Some(name) if !name.trim().is_empty() => format!("{} <{}>", name.trim(), email.from_address),
+        _ => email.from_address.clone(),
+    }
+}
+
+/// Have the agent propose a ticket for an email, in one of `places`
+pub async fn propose_ticket(
+    db: &SqlitePool,
+    organization: &str,
+    email: &Email,
+    places: &[Place],
+) -> Result<TicketProposal> {
+    let body: String = email.body_text.as_deref().unwrap_or_default().chars().take(MAX_BODY_CHARS).collect();
+    let (body, redactions) = crate::redaction::redact(&body);
+    let subject = format!("email:{}", email.id);
+    crate::redaction::store_audit(db, Some(organization), &AgentType::EmailTicket, &subject, &redactions).await;
+    let place_list = if places.is_empty() {
+        "(none)".to_string()
+    } else {
+        places
+            .iter()
+            .map(|p| format!("- epic_id {} ({}), slice_id {} ({})", p.epic_id, p.epic_title, p.slice_id, p.slice_title))
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+    let mut vars = HashMap::new();
+    vars.insert("from".to_string(), sender(email));
+    vars.insert("subject".to_string(), email.subject.clone().unwrap_or_else(|| "(no subject)".to_string()));
+    vars.insert("body".to_string(), body);
+    vars.insert("places".to_string(), place_list);
+
+    let output = run_ticket_agent(db, organization, vars).await?;
+    parse_proposal(&output, places)
+}
+
+/// The ticket already filed from an email in the organization, if any
+pub async fn existing_ticket(db: &SqlitePool, organization: &str, email: &Email) -> Result<Option<String>> {
+    Ok(imports::find_ticket_by_external_id(db, organization, SOURCE_EMAIL, &email.message_id).await?)
+}
+
+/// File a checked proposal as a ticket, link it to the email's thread and
+/// record where it came from. Returns the created ticket.
+pub async fn file_email_ticket(
+    db: &SqlitePool,
+    organization: &str,
+    user_id: &str,
+    email: &Email,
+    proposal: &TicketProposal,
+) -> Result<Value> {
+    let (Some(epic_id), Some(slice_id)) = (&proposal.epic_id, &proposal.slice_id) else {
+        anyhow::bail!("Proposal has no epic and slice");
+    };
+    let defaults = crate::handlers::slices::slice_pipeline_defaults(db, organization, epic_id, slice_id).await;
+    let args = json!({
+        "organization": organization,
+        "epic_id": epic_id,
+        "slice_id": slice_id,
+        "tickets": [{
+            "ref": "email",
+            "title": proposal.title,
+            "ticket_type": "milestone",
+            "pipeline_template_id": defaults.template_id,
+            "description": proposal.description,
+            "labels": proposal.labels,
+            "priority": proposal.priority,
+        }]
+    });
+    let result = call_mcp_tool("create_slice_tickets", Some(args)).await.context("Failed to create ticket")?;
+    let ticket = result.pointer("/tickets/0/ticket").cloned().context("No ticket returned")?;
+    let ticket_id = ticket.get("ticket_id").and_then(|id| id.as_str()).context("Created ticket has no ticket_id")?;
+    tracing::info!("Created ticket {} from email {}", ticket_id, email.id);
+
+    // The ticket exists now; the rest is logged rather than failing the request
+    if let Err(e) = imports::record_external_id(db, organization, SOURCE_EMAIL, &email.message_id, ticket_id).await {
+        tracing::warn!("Failed to record email {} as source of ticket {}: {}", email.id, ticket_id, e);
+    }
+    let mut fields = serde_json::Map::new();
+    fields.insert(
+        "source_email".to_string(),
+        json!({
+            "email_id": email.id,
+            "message_id": email.message_id,
+            "thread_id": email.thread_id,
+            "mailbox": email.mailbox,
+            "from": sender(email),
+            "subject": email.subject,
+        }),
+    );
+    if let Err(e) = ticketing_system::tickets::update_custom_fields(db, ticket_id, &fields).await {
+        tracing::warn!("Failed to set source_email on ticket {}: {}", ticket_id, e);
+    }
+    if let Some(thread_id) = &email.thread_id {
+        let link = LinkThreadTicketRequest {
+            thread_id: thread_id.clone(),
+            ticket_id: ticket_id.to_string(),
+            epic_id: Some(epic_id.clone()),
+            slice_id: Some(slice_id.clone()),
+        };
+        if let Err(e) = email_thread_tickets::link_thread_to_ticket(db, &link).await {
+            tracing::warn!("Failed to link thread {} to ticket {}: {}", thread_id, ticket_id, e);
+        }
+    }
+    user_events::follow(db, user_id, Subject::Ticket, ticket_id).await;
+    if defaults.auto_run {
+        crate::handlers::tickets::auto_run_pipeline(db, ticket_id).await;
+    }
+    Ok(ticket)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_and_checks_proposals() {
+        let places = vec![Place {
+            epic_id: "billing".to_string(),
+            epic_title: "Billing".to_string(),
+            slice_id: "invoices".to_string(),
+            slice_title: "Invoices".to_string(),
+        }];
+        let output = r#"Proposed ticket:
+{"title": " Fix invoice totals ", "description": "Totals skip tax.", "epic_id": "billing",
+ "slice_id": "invoices", "priority": "High", "labels": ["Bug", "bug", " customer ", ""]}"#;
+        let proposal = parse_proposal(output, &places).unwrap();
+        assert_eq!(proposal.title, "Fix invoice totals");
+        assert_eq!(proposal.priority.as_deref(), Some("high"));
+        assert_eq!(proposal.labels, vec!["bug", "customer"]);
+        assert_eq!(check_proposal(proposal.clone(), &places), Ok(proposal));
+
+        let made_up = r#"{"title": "Refund", "epic_id": "billing", "slice_id": "refunds", "priority": "asap"}"#;
+        let proposal = parse_proposal(made_up, &places).unwrap();
+        assert_eq!((proposal.epic_id.as_deref(), proposal.priority.as_deref()), (None, None));
+        assert!(check_proposal(proposal, &places).is_err());
+        assert!(parse_proposal("Nothing to file here", &places).is_err());
+    }
+}
@@ -0,0 +1,646 @@
+//! Vector embeddings and semantic search
+//!
+//! Tickets, emails, meeting transcripts, agent artifacts and research docs are
+//! chunked, embedded and stored in SQLite (`ticketing_system::embeddings`).
+//! Search embeds the query and ranks stored chunks by cosine similarity in
+//! memory, which is plenty for a single workspace's corpus.
+//! Every source is indexed under the organization owning it (the ticket's, the
+//! mailbox's, the meeting's) and only ever searched within that organization;
+//! emails and transcripts with no owning organization are not indexed.
+//! Ticket vectors are also compared with each other to find likely duplicate
+//! and related tickets.
+//!
+//! Any OpenAI-compatible `/embeddings` endpoint works:
+//! - `EMBEDDINGS_PROVIDER=openai` (default when `OPENAI_KEY` is set)
+//! - `EMBEDDINGS_PROVIDER=local` with `EMBEDDINGS_URL` (default: Ollama on localhost)
+//!
+//! `EMBEDDINGS_MODEL` overrides the model. With no provider configured, indexing
+//! and semantic search are disabled.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use anyhow::{anyhow, Context, Result};
+use chrono::{Duration, Utc};
+use serde::{Deserialize, Serialize};
+use ticketing_system::{embeddings, EmbeddingChunk, SqlitePool, UpsertEmbeddingsRequest};
+
+use crate::email_fetcher::find_account;
+use crate::redaction::{self, RedactionAudit};
+use crate::research_corpus::content_hash;
+
+/// How often the indexer looks for new or changed content
+const INDEX_INTERVAL_SECS: u64 = 10 * 60;
+
+/// Agent runs older than this are not (re)considered on each pass
+const ARTIFACT_LOOKBACK_DAYS: i64 = 7;
+
+/// Characters per chunk, and overlap between consecutive chunks
+const CHUNK_CHARS: usize = 2000;
+const CHUNK_OVERLAP_CHARS: usize = 200;
+
+/// Inputs per embeddings API request
+const EMBED_BATCH_SIZE: usize = 64;
+
+const EMAIL_PAGE_SIZE: i64 = 500;
+
+/// Minimum similarity for a hit to be injected into an agent prompt
+const MIN_CONTEXT_SCORE: f32 = 0.35;
+const CONTEXT_MAX_HITS: usize = 5;
+
+pub const SOURCE_TICKET: &str = "ticket";
+pub const SOURCE_EMAIL: &str = "email";
+pub const SOURCE_TRANSCRIPT: &str = "transcript";
+pub const SOURCE_ARTIFACT: &str = "artifact";
+pub const SOURCE_RESEARCH_DOC: &str = "research_doc";
+
+/// Transcript sessions of meetings are `mtg-<room_id>`
+const MEETING_SESSION_PREFIX: &str = "mtg-";
+
+pub const ALL_SOURCES: &[&str] = &[
+    SOURCE_TICKET,
+    SOURCE_EMAIL,
+    SOURCE_TRANSCRIPT,
+    SOURCE_ARTIFACT,
+    SOURCE_RESEARCH_DOC,
+];
+
+// ============================================================================
+// Embedding Client
+// ============================================================================
+
+pub struct EmbeddingClient {
+    http: reqwest::Client,
+    base_url: String,
+    api_key: Option<String>,
+    model: String,
+}
+
+#[derive(Deserialize)]
+struct EmbeddingsResponse {
+    data: Vec<EmbeddingData>,
+}
+
+#[derive(Deserialize)]
+struct EmbeddingData {
+    index: usize,
+    embedding: Vec<f32>,
+}
+
+impl EmbeddingClient {
+    fn from_env() -> Option<Self> {
+        let provider = std::env::var("EMBEDDINGS_PROVIDER").ok();
+        let openai_key = std::env::var("OPENAI_KEY").ok();
+        let (base_url, api_key, default_model) = match provider.as_deref() {
+            Some("local") => (
+                std::env::var("EMBEDDINGS_URL").unwrap_or_else(|_| "http://localhost:11434/v1".to_string()),
+                std::env::var("EMBEDDINGS_API_KEY").ok(),
+                "nomic-embed-text",
+            ),
+            Some("openai") | None => ("https://api.openai.com/v1".to_string(), Some(openai_key?), "text-embedding-3-small"),
+            Some(other) => {
+                tracing::warn!("Unknown EMBEDDINGS_PROVIDER '{}', semantic search disabled", other);
+                return None;
+            }
+        };
+        Some(Self {
+            http: reqwest::Client::new(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            api_key,
+            model: std::env::var("EMBEDDINGS_MODEL").unwrap_or_else(|_| default_model.to_string()),
+        })
+    }
+
+    pub fn model(&self) -> &str {
+        &self.model
+    }
+
+    /// Embed a batch of inputs, returning one vector per input in order
+    pub async fn embed(&self, inputs: &[String]) -> Result<Vec<Vec<f32>>> {
+        let mut request = self
+            .http
+            .post(format!("{}/embeddings", self.base_url))
+            .json(&serde_json::json!({ "model": self.model, "input": inputs }));
+        if let Some(key) = &self.api_key {
+            request = request.header("Authorization", format!("Bearer {}", key));
+        }
+
+        let response = request.send().await.context("Embeddings request failed")?;
+        if !response.status().is_success() {
+            let error_text = response.text().await.unwrap_or_default();
+            return Err(anyhow!("Embeddings API error: {}", error_text));
+        }
+        let mut body: EmbeddingsResponse = response.json().await.context("Failed to parse embeddings response")?;
+        if body.data.len() != inputs.len() {
+            return Err(anyhow!("Expected {} embeddings, got {}", inputs.len(), body.data.len()));
+        }
+        body.data.sort_by_key(|d| d.index);
+        Ok(body.data.into_iter().map(|d| d.embedding).collect())
+    }
+}
+
+lazy_static::lazy_static! {
+    static ref CLIENT: Option<EmbeddingClient> = EmbeddingClient::from_env();
+}
+
+/// The configured embedding client, or `None` if semantic search is disabled
+pub fn client() -> Option<&'static EmbeddingClient> {
+    CLIENT.as_ref()
+}
+
+// ============================================================================
+// Vector Math and Chunking
+// ============================================================================
+
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() || a.is_empty() {
+        return 0.0;
+    }
+    let (mut dot, mut norm_a, mut norm_b) = (0.0f32, 0.0f32, 0.0f32);
+    for (x, y) in a.iter().zip(b) {
+        dot += x * y;
+        norm_a += x * x;
+        norm_b += y * y;
+    }
+    if norm_a == 0.0 || norm_b == 0.0 {
+        return 0.0;
+    }
+    dot / (norm_a.sqrt() * norm_b.sqrt())
+}
+
+/// Split text into overlapping chunks of at most `CHUNK_CHARS` characters,
+/// preferring to break on whitespace
+pub fn chunk_text(text: &str) -> Vec<String> {
+    let chars: Vec<char> = text.trim().chars().collect();
+    if chars.is_empty() {
+        return Vec::new();
+    }
+
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    while start < chars.len() {
+        let mut end = (start + CHUNK_CHARS).min(chars.len());
+        if end < chars.len() {
+            if let Some(space) = chars[start + CHUNK_CHARS / 2..end].iter().rposition(|c| c.is_whitespace()) {
+                end = start + CHUNK_CHARS / 2 + space;
+            }
+        }
+        chunks.push(chars[start..end].iter().collect::<String>().trim().to_string());
+        if end == chars.len() {
+            break;
+        }
+        start = end.saturating_sub(CHUNK_OVERLAP_CHARS).max(start + 1);
+    }
+    chunks
+}
+
+// ============================================================================
+// Indexing
+// ============================================================================
+
+/// A piece of content to index
+struct IndexDocument {
+    source_type: &'static str,
+    source_id: String,
+    organization: String,
+    title: String,
+    content: String,
+}
+
+async fn collect_documents(db: &SqlitePool) -> Result<Vec<IndexDocument>> {
+    let mut docs = Vec::new();
+
+    let tickets = ticketing_system::tickets::list_all_tickets(db).await?;
+    let ticket_organizations: HashMap<String, String> =
+        tickets.iter().map(|t| (t.ticket_id.clone(), t.organization.clone())).collect();
+    for ticket in tickets {
+        docs.push(IndexDocument {
+            source_type: SOURCE_TICKET,
+            source_id: ticket.ticket_id.clone(),
+            organization: ticket.organization.clone(),
+            content: format!("{}\n\n{}", ticket.title, ticket.description.clone().unwrap_or_default()),
+            title: ticket.title,
+        });
+    }
+
+    let mut mailbox_organizations: HashMap<String, Option<String>> = HashMap::new();
+    let mut offset = 0;
+    loop {
+        let page = ticketing_system::emails::list_all_emails(db, EMAIL_PAGE_SIZE, offset).await?;
+        let page_len = page.len() as i64;
+        for email in page {
+            let organization = match mailbox_organizations.get(&email.mailbox) {
+                Some(organization) => organization.clone(),
+                None => {
+                    let organization = find_account(&email.mailbox).await.and_then(|a| a.organization);
+                    mailbox_organizations.insert(email.mailbox.clone(), organization.clone());
+                    organization
+                }
+            };
+            let Some(organization) = organization else {
+                continue;
+            };
+            let subject = email.subject.unwrap_or_default();
+            docs.push(IndexDocument {
+                source_type: SOURCE_EMAIL,
+                source_id: email.id.to_string(),
+                organization,
+                content: format!(
+                    "From: {}\nSubject: {}\n\n{}",
+                    email.from_address,
+                    subject,
+                    email.body_text.unwrap_or_default()
+                ),
+                title: subject,
+            });
+        }
+        if page_len < EMAIL_PAGE_SIZE {
+            break;
+        }
+        offset += page_len;
+    }
+
+    for session in ticketing_system::transcripts::list_sessions(db, false).await? {
+        let Some(room_id) = session.session_id.strip_prefix(MEETING_SESSION_PREFIX) else {
+            continue;
+        };
+        let Some(organization) = ticketing_system::meetings::get_meeting(db, room_id).await?.and_then(|m| m.organization)
+        else {
+            continue;
+        };
+        let entries = ticketing_system::transcripts::get_entries(db, &session.session_id).await?;
+        let content: Vec<String> = entries.iter().map(|e| format!("{}: {}", e.username, e.text)).collect();
+        docs.push(IndexDocument {
+            source_type: SOURCE_TRANSCRIPT,
+            source_id: session.session_id.clone(),
+            organization,
+            title: session.channel_name.unwrap_or_else(|| session.session_id.clone()),
+            content: content.join("\n"),
+        });
+    }
+
+    let now = Utc::now();
+    let since = (now - Duration::days(ARTIFACT_LOOKBACK_DAYS)).to_rfc3339();
+    for run in ticketing_system::agent_runs::list_runs_started_between(db, &since, &now.to_rfc3339()).await? {
+        let Some(output) = run.output_summary.filter(|_| run.status == "completed") else {
+            continue;
+        };
+        let Some(organization) = ticket_organizations.get(&run.ticket_id).cloned() else {
+            continue;
+        };
+        docs.push(IndexDocument {
+            source_type: SOURCE_ARTIFACT,
+            source_id: run.session_id,
+            organization,
+            title: format!("{} output for {}", run.agent_type, run.ticket_id),
+            content: output,
+        });
+    }
+
+    let research_query = ticketing_system::ResearchDocQuery::default();
+    for doc in ticketing_system::research_docs::search_docs(db, &research_query).await? {
+        docs.push(IndexDocument {
+            source_type: SOURCE_RESEARCH_DOC,
+            source_id: doc.doc_id,
+            organization: doc.organization,
+            title: doc.title,
+            content: doc.content,
+        });
+    }
+
+    Ok(docs)
+}
+
+/// Embed every document whose content changed since it was last indexed.
+/// Returns the number of documents (re)indexed.
+pub async fn index_changed(db: &SqlitePool, client: &EmbeddingClient) -> Result<usize> {
+    let docs = collect_documents(db).await?;
+
+    let mut known: HashMap<&str, HashMap<String, String>> = HashMap::new();
+    for source_type in ALL_SOURCES.iter().copied() {
+        known.insert(source_type, embeddings::get_source_hashes(db, source_type).await?);
+    }
+
+    let mut indexed = 0;
+    for doc in docs {
+        // The owner is part of the hash, so a source that changes hands is re-indexed
+        let hash = content_hash(&format!("{}\n{}", doc.organization, doc.content));
+        if known.get(doc.source_type).and_then(|h| h.get(&doc.source_id)) == Some(&hash) {
+            continue;
+        }
+
+        let chunks = chunk_text(&doc.content);
+        let mut vectors = Vec::with_capacity(chunks.len());
+        for batch in chunks.chunks(EMBED_BATCH_SIZE) {
+            vectors.extend(client.embed(batch).await?);
+        }
+
+        embeddings::replace_source(
+            db,
+            &UpsertEmbeddingsRequest {
+                source_type: doc.source_type.to_string(),
+                source_id: doc.source_id,
+                organization: Some(doc.organization),
+                title: doc.title,
+                content_hash: hash,
+                model: client.model().to_string(),
+                // Embedded from the plaintext; only the sealed text is stored
+                chunks: chunks
+                    .iter()
+                    .map(|chunk| crate::storage_crypto::seal(chunk))
+                    .collect::<Result<Vec<_>>>()?
+                    .into_iter()
+                    .zip(vectors)
+                    .collect(),
+            },
+        )
+        .await?;
+        indexed += 1;
+    }
+    Ok(indexed)
+}
+
+/// Index everything on startup, then pick up changes periodically
+pub fn start_embedding_indexer(db: Arc<SqlitePool>) {
+    let Some(client) = client() else {
+        tracing::info!("No embeddings provider configured, semantic search disabled");
+        return;
+    };
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(std::time::Duration::from_secs(INDEX_INTERVAL_SECS));
+        loop {
+            interval.tick().await;
+            if crate::maintenance::is_enabled() {
+                continue;
+            }
+            match index_changed(&db, client).await {
+                Ok(0) => {}
+                Ok(n) => tracing::info!("Embedded {} new or changed documents", n),
+                Err(e) => tracing::error!("Embedding index pass failed: {:?}", e),
+            }
+        }
+    });
+}
+
+// ============================================================================
+// Search
+// ============================================================================
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SemanticHit {
+    pub source_type: String,
+    pub source_id: String,
+    pub title: String,
+    /// Best-matching chunk of the source
+    pub snippet: String,
+    pub score: f32,
+}
+
+/// Rank indexed sources of one organization against a query. Each source
+/// appears once, represented by its best chunk.
+pub async fn search(
+    db: &SqlitePool,
+    client: &EmbeddingClient,
+    query: &str,
+    organization: &str,
+    source_types: &[&str],
+    limit: usize,
+) -> Result<Vec<SemanticHit>> {
+    let query_vector = client
+        .embed(&[query.to_string()])
+        .await?
+        .pop()
+        .ok_or_else(|| anyhow!("No embedding returned for query"))?;
+
+    let chunks = embeddings::list_chunks(db, Some(organization), source_types).await?;
+    let mut hits = rank_chunks(&query_vector, chunks, organization, limit);
+    for hit in &mut hits {
+        hit.snippet = crate::storage_crypto::open(&hit.snippet)?;
+    }
+    Ok(hits)
+}
+
+/// Best chunk per source of `organization`, ranked against `query_vector`.
+/// Chunks of other organizations, or of none, never match.
+fn rank_chunks(query_vector: &[f32], chunks: Vec<EmbeddingChunk>, organization: &str, limit: usize) -> Vec<SemanticHit> {
+    let mut best: HashMap<(String, String), SemanticHit> = HashMap::new();
+    for chunk in chunks {
+        if chunk.organization.as_deref() != Some(organization) {
+            continue;
+        }
+        let score = cosine_similarity(query_vector, &chunk.vector);
+        let key = (chunk.source_type.clone(), chunk.source_id.clone());
+        if best.get(&key).map(|h| h.score >= score).unwrap_or(false) {
+            continue;
+        }
+        best.insert(
+            key,
+            SemanticHit {
+                source_type: chunk.source_type,
+                source_id: chunk.source_id,
+                title: chunk.title,
+                snippet: chunk.content,
+                score,
+            },
+        );
+    }
+
+    let mut hits: Vec<SemanticHit> = best.into_values().collect();
+    hits.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal));
+    hits.truncate(limit);
+    hits
+}
+
+/// Related prior work for a ticket's agent prompt, or `None` if semantic search
+/// is disabled or nothing is similar enough
+pub async fn related_work_context(db: &SqlitePool, ticket_id: &str) -> Option<(String, RedactionAudit)> {
+    let client = client()?;
+    let ticket = ticketing_system::tickets::get_ticket_by_id(db, ticket_id).await.ok().flatten()?;
+    let query = format!("{}\n\n{}", ticket.title, ticket.description.unwrap_or_default());
+
+    let hits = match search(db, client, &query, &ticket.organization, ALL_SOURCES, CONTEXT_MAX_HITS + 1).await {
+        Ok(hits) => hits,
+        Err(e) => {
+            tracing::warn!("Semantic context lookup failed for {}: {}", ticket_id, e);
+            return None;
+        }
+    };
+
+    // Email and transcript excerpts are scrubbed of PII before reaching the agent
+    let mut audit = RedactionAudit::default();
+    let sections: Vec<String> = hits
+        .into_iter()
+        .filter(|h| !(h.source_type == SOURCE_TICKET && h.source_id == ticket_id))
+        .filter(|h| h.score >= MIN_CONTEXT_SCORE)
+        .take(CONTEXT_MAX_HITS)
+        .map(|h| {
+            let snippet = if h.source_type == SOURCE_EMAIL || h.source_type == SOURCE_TRANSCRIPT {
+                let (snippet, found) = redaction::redact(&h.snippet);
+                audit.merge(found);
+                snippet
+            } else {
+                h.snippet
+            };
+            format!("### {} {} — {} (similarity {:.2})\n{}", h.source_type, h.source_id, h.title, h.score, snippet)
+        })
+        .collect();
+
+    if sections.is_empty() {
+        None
+    } else {
+        Some((format!("## Related Prior Work\n\n{}", sections.join("\n\n")), audit))
+    }
+}
+
+// ============================================================================
+// Similar Tickets
+// ============================================================================
+
+/// Similarity from which two tickets are reported as likely duplicates
+pub const DUPLICATE_SCORE: f32 = 0.9;
+
+/// Similarity from which two tickets are reported as related
+pub const RELATED_SCORE: f32 = 0.75;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Similarity {
+    Duplicate,
+    Related,
+}
+
+impl Similarity {
+    pub fn of(score: f32) -> Option<Self> {
+        if score >= DUPLICATE_SCORE {
+            Some(Similarity::Duplicate)
+        } else if score >= RELATED_SCORE {
+            Some(Similarity::Related)
+        } else {
+            None
+        }
+    }
+}
+
+/// Similarity of two sources: their closest pair of chunks
+pub fn source_similarity(a: &[Vec<f32>], b: &[Vec<f32>]) -> f32 {
+    a.iter()
+        .flat_map(|x| b.iter().map(move |y| cosine_similarity(x, y)))
+        .fold(0.0, f32::max)
+}
+
+/// Chunk vectors of every indexed ticket in an organization, by ticket id
+pub async fn ticket_vectors(db: &SqlitePool, organization: &str) -> Result<HashMap<String, Vec<Vec<f32>>>> {
+    let mut vectors: HashMap<String, Vec<Vec<f32>>> = HashMap::new();
+    for chunk in embeddings::list_chunks(db, Some(organization), &[SOURCE_TICKET]).await? {
+        vectors.entry(chunk.source_id).or_default().push(chunk.vector);
+    }
+    Ok(vectors)
+}
+
+/// Tickets most similar to `ticket_id` scoring at least `min_score`, best
+/// first. Uses the ticket's indexed vectors, so a ticket the indexer hasn't
+/// reached yet has no matches.
+pub fn similar_to(
+    vectors: &HashMap<String, Vec<Vec<f32>>>,
+    ticket_id: &str,
+    min_score: f32,
+    limit: usize,
+) -> Vec<(String, f32)> {
+    let Some(own) = vectors.get(ticket_id) else {
+        return Vec::new();
+    };
+    let mut hits: Vec<(String, f32)> = vectors
+        .iter()
+        .filter(|(id, _)| id.as_str() != ticket_id)
+        .map(|(id, other)| (id.clone(), source_similarity(own, other)))
+        .filter(|(_, score)| *score >= min_score)
+        .collect();
+    hits.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal).then_with(|| a.0.cmp(&b.0)));
+    hits.truncate(limit);
+    hits
+}
+
+/// Pairs of tickets scoring at least `min_score` where the first is one of
+/// `ticket_ids`, best first. Each pair appears once.
+pub fn similar_pairs(
+    vectors: &HashMap<String, Vec<Vec<f32>>>,
+    ticket_ids: &[String],
+    min_score: f32,
+) -> Vec<(String, String, f32)> {
+    let mut pairs: Vec<(String, String, f32)> = Vec::new();
+    for ticket_id in ticket_ids {
+        for (other, score) in similar_to(vectors, ticket_id, min_score, usize::MAX) {
+            let seen = pairs.iter().any(|(a, b, _)| a == &other && b == ticket_id);
+            if !seen {
+                pairs.push((ticket_id.clone(), other, score));
+            }
+        }
+    }
+    pairs.sort_by(|a, b| b.2.partial_cmp(&a.2).unwrap_or(std::cmp::Ordering::Equal));
+    pairs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cosine_similarity_basics() {
+        assert!((cosine_similarity(&[1.0, 0.0], &[2.0, 0.0]) - 1.0).abs() < 1e-6);
+        assert_eq!(cosine_similarity(&[1.0, 0.0], &[0.0, 1.0]), 0.0);
+        assert_eq!(cosine_similarity(&[1.0], &[1.0, 2.0]), 0.0);
+    }
+
+    #[test]
+    fn chunk_text_overlaps_and_covers_input() {
+        assert!(chunk_text("   ").is_empty());
+        assert_eq!(chunk_text("short note"), vec!["short note".to_string()]);
+
+        let text = "word ".repeat(1000);
+        let chunks = chunk_text(&text);
+        assert!(chunks.len() > 2);
+        assert!(chunks.iter().all(|c| c.chars().count() <= CHUNK_CHARS));
+        assert!(chunks.last().unwrap().ends_with("word"));
+    }
+
+    #[test]
+    fn finds_similar_tickets_and_pairs() {
+        let vectors: HashMap<String, Vec<Vec<f32>>> = [
+            ("T-1", vec![vec![1.0, 0.0, 0.0]]),
+            ("T-2", vec![vec![0.0, 1.0, 0.0], vec![0.99, 0.1, 0.0]]),
+            ("T-3", vec![vec![0.8, 0.6, 0.0]]),
+            ("T-4", vec![vec![0.0, 0.0, 1.0]]),
+        ]
+        .into_iter()
+        .map(|(id, v)| (id.to_string(), v))
+        .collect();
+
+        let similar = similar_to(&vectors, "T-1", RELATED_SCORE, 10);
+        let ids: Vec<&str> = similar.iter().map(|(id, _)| id.as_str()).collect();
+        assert_eq!(ids, vec!["T-2", "T-3"]);
+        assert_eq!(Similarity::of(similar[0].1), Some(Similarity::Duplicate));
+        assert_eq!(Similarity::of(similar[1].1), Some(Similarity::Related));
+        assert!(similar_to(&vectors, "T-9", 0.0, 10).is_empty());
+
+        let pairs = similar_pairs(&vectors, &["T-1".to_string(), "T-2".to_string()], DUPLICATE_SCORE);
+        assert_eq!(pairs.len(), 1);
+        assert_eq!((pairs[0].0.as_str(), pairs[0].1.as_str()), ("T-1", "T-2"));
+    }
+
+    #[test]
+    fn search_only_ranks_the_organizations_own_chunks() {
+        let chunk = |source_id: &str, organization: Option<&str>| EmbeddingChunk {
+            source_type: SOURCE_EMAIL.to_string(),
+            source_id: source_id.to_string(),
+            organization: organization.map(str::to_string),
+            title: source_id.to_string(),
+            content: String::new(),
+            vector: vec![1.0, 0.0],
+        };
+        let chunks = vec![chunk("acme-mail", Some("acme")), chunk("globex-mail", Some("globex")), chunk("orphan", None)];
+
+        let hits = rank_chunks(&[1.0, 0.0], chunks, "acme", 10);
+        let ids: Vec<&str> = hits.iter().map(|h| h.source_id.as_str()).collect();
+        assert_eq!(ids, vec!["acme-mail"]);
+    }
+}
@@ -0,0 +1,379 @@
+//! Epic pipelines - orchestrating pipelines across an epic's slices
+//!
+//! An epic pipeline is a list of steps, each "run pipeline template X on every
+//! ticket in slice Y". Steps run one after another: a step attaches its
+//! template to the slice's tickets and starts them through
+//! `pipeline_automation`, and the next step starts once the step's completion
+//! condition holds (see `evaluate`). Tickets whose own pipeline is still
+//! running are started once it finishes, and tickets added to the slice while
+//! the step runs are picked up too.
+//!
+//! A step with no tickets waits until its slice has some. A ticket that fails
+//! to start is retried on later checks, up to `MAX_START_ATTEMPTS` times, after
+//! which the step and the epic pipeline fail.
+//!
+//! A background job re-checks running epic pipelines every
+//! `CHECK_INTERVAL_SECS`; creating one checks it right away.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+use tracing::{error, info, warn};
+
+use ticketing_system::{
+    epic_pipelines, models::Ticket, tickets, CreateNotificationRequest, EpicPipeline, EpicPipelineStep, SqlitePool,
+};
+
+use crate::pipeline_status::{phase, PipelinePhase};
+
+pub const EPIC_PIPELINE_RUNNING: &str = "running";
+pub const EPIC_PIPELINE_COMPLETED: &str = "completed";
+pub const EPIC_PIPELINE_FAILED: &str = "failed";
+pub const EPIC_PIPELINE_CANCELLED: &str = "cancelled";
+
+pub const STEP_PENDING: &str = "pending";
+pub const STEP_RUNNING: &str = "running";
+pub const STEP_COMPLETED: &str = "completed";
+pub const STEP_FAILED: &str = "failed";
+
+/// Every ticket's pipeline completed; the step fails as soon as one fails
+pub const COMPLETION_ALL_COMPLETED: &str = "all_completed";
+/// Every ticket's pipeline finished, whether it completed or failed
+pub const COMPLETION_ALL_FINISHED: &str = "all_finished";
+/// At least `min_completed_percent` of the tickets' pipelines completed
+pub const COMPLETION_MIN_PERCENT: &str = "min_completed_percent";
+
+pub const COMPLETIONS: &[&str] = &[COMPLETION_ALL_COMPLETED, COMPLETION_ALL_FINISHED, COMPLETION_MIN_PERCENT];
+
+/// How often running epic pipelines are checked
+const CHECK_INTERVAL_SECS: u64 = 30;
+
+/// Times a ticket may fail to start before its step fails
+const MAX_START_ATTEMPTS: u32 = 3;
+
+lazy_static::lazy_static! {
+    /// One check at a time, so a ticket is never started twice
+    static ref ADVANCING: tokio::sync::Mutex<()> = tokio::sync::Mutex::new(());
+    /// Failed starts by (epic pipeline, step, ticket). Kept in memory, so a
+    /// restart gives every ticket its attempts back.
+    static ref START_FAILURES: std::sync::Mutex<HashMap<(String, String, String), u32>> =
+        std::sync::Mutex::new(HashMap::new());
+}
+
+/// Where a step's tickets are
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct StepProgress {
+    pub total: usize,
+    /// Waiting for the ticket's previous pipeline to finish
+    pub waiting: usize,
+    pub running: usize,
+    pub awaiting_approval: usize,
+    pub completed: usize,
+    pub failed: usize,
+}
+
+impl StepProgress {
+    fn finished(&self) -> usize {
+        self.completed + self.failed
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum StepOutcome {
+    InProgress,
+    Completed,
+    Failed(String),
+}
+
+/// Whether a step's completion condition holds, can no longer hold, or neither yet
+fn evaluate(completion: &str, min_percent: Option<u32>, progress: &StepProgress) -> StepOutcome {
+    // An empty slice has nothing to wait for yet, not nothing left to do
+    if progress.total == 0 {
+        return StepOutcome::InProgress;
+    }
+    match completion {
+        COMPLETION_ALL_FINISHED if progress.finished() == progress.total => StepOutcome::Completed,
+        COMPLETION_MIN_PERCENT => {
+            let percent = min_percent.unwrap_or(100).min(100) as usize;
+            // Smallest count that is at least `percent` of the tickets
+            let needed = (progress.total * percent).div_ceil(100);
+            if progress.completed >= needed {
+                StepOutcome::Completed
+            } else if progress.total - progress.failed < needed {
+                StepOutcome::Failed(format!(
+                    "{} of {} pipelines failed, so {}% can no longer complete",
+                    progress.failed, progress.total, percent
+                ))
+            } else {
+                StepOutcome::InProgress
+            }
+        }
+        COMPLETION_ALL_FINISHED => StepOutcome::InProgress,
+        _ if progress.failed > 0 => {
+            StepOutcome::Failed(format!("{} of {} pipelines failed", progress.failed, progress.total))
+        }
+        _ if progress.completed == progress.total => StepOutcome::Completed,
+        _ => StepOutcome::InProgress,
+    }
+}
+
+fn is_busy(ticket: &Ticket) -> bool {
+    ticket.pipeline.as_ref().and_then(|p| p.status.as_deref()) == Some("running")
+}
+
+async fn slice_tickets(pool: &SqlitePool, epic_pipeline: &EpicPipeline, step: &EpicPipelineStep) -> Result<Vec<Ticket>> {
+    tickets::list_tickets(pool, &epic_pipeline.organization, &epic_pipeline.epic_id, &step.slice_id).await
+}
+
+/// Progress of a step over the slice's tickets as they are now
+pub async fn step_progress(pool: &SqlitePool, epic_pipeline: &EpicPipeline, step: &EpicPipelineStep) -> Result<StepProgress> {
+    let started: HashSet<&str> = step.started_tickets.iter().map(String::as_str).collect();
+    let mut progress = StepProgress::default();
+    for ticket in slice_tickets(pool, epic_pipeline, step).await? {
+        progress.total += 1;
+        if !started.contains(ticket.ticket_id.as_str()) {
+            progress.waiting += 1;
+            continue;
+        }
+        match ticket.pipeline.as_ref().and_then(phase) {
+            Some(PipelinePhase::Completed) => progress.completed += 1,
+            Some(PipelinePhase::Failed) => progress.failed += 1,
+            Some(PipelinePhase::AwaitingApproval) => progress.awaiting_approval += 1,
+            Some(PipelinePhase::Started) | None => progress.running += 1,
+        }
+    }
+    Ok(progress)
+}
+
+/// Attach the step's template to a ticket and start its first step
+async fn start_ticket(pool: &SqlitePool, ticket_id: &str, organization: &str, template_id: &str) -> Result<()> {
+    let pipeline = crate::template_sharing::attach_template(pool, ticket_id, organization, template_id, None).await?;
+    crate::pipeline_read_model::record(ticket_id, Some(&pipeline));
+    let first_step_id = pipeline
+        .steps
+        .first()
+        .map(|s| s.step_id.clone())
+        .ok_or_else(|| anyhow!("Template {} has no steps", template_id))?;
+    crate::pipeline_automation::start_step_execution(pool, ticket_id, &first_step_id).await?;
+    crate::shadow_pipelines::start_shadow_runs(pool, ticket_id).await;
+    Ok(())
+}
+
+/// Count a failed start, returning the attempts so far
+fn record_start_failure(epic_pipeline_id: &str, step_id: &str, ticket_id: &str) -> u32 {
+    let mut failures = START_FAILURES.lock().unwrap();
+    let attempts = failures
+        .entry((epic_pipeline_id.to_string(), step_id.to_string(), ticket_id.to_string()))
+        .or_insert(0);
+    *attempts += 1;
+    *attempts
+}
+
+/// Forget an epic pipeline's failed starts once it has finished
+fn clear_start_failures(epic_pipeline_id: &str) {
+    START_FAILURES.lock().unwrap().retain(|(id, _, _), _| id != epic_pipeline_id);
+}
+
+/// Start the step on the slice's tickets it hasn't started yet and that are
+/// free. Stops when the organization's quota runs out; the rest are started on
+/// a later check. Returns why the step must fail once a ticket has failed to
+/// start `MAX_START_ATTEMPTS` times.
+async fn start_waiting_tickets(
+    pool: &SqlitePool,
+    epic_pipeline: &EpicPipeline,
+    step: &mut EpicPipelineStep,
+) -> Result<Option<String>> {
+    for ticket in slice_tickets(pool, epic_pipeline, step).await? {
+        if step.started_tickets.contains(&ticket.ticket_id) || is_busy(&ticket) {
+            continue;
+        }
+        if let Err(exceeded) = crate::quotas::check_agent_run(pool, &epic_pipeline.organization).await {
+            warn!("Epic pipeline {} waiting to start tickets: {}", epic_pipeline.epic_pipeline_id, exceeded.message());
+            break;
+        }
+        // Same lock as attaching a pipeline by hand
+        let Some(_guard) = crate::template_sharing::lock_attach(&ticket.ticket_id) else {
+            continue;
+        };
+        match start_ticket(pool, &ticket.ticket_id, &epic_pipeline.organization, &step.template_id).await {
+            Ok(()) => {
+                info!(
+                    "Epic pipeline {} started template {} on ticket {}",
+                    epic_pipeline.epic_pipeline_id, step.template_id, ticket.ticket_id
+                );
+                step.started_tickets.push(ticket.ticket_id);
+            }
+            Err(e) => {
+                let attempts = record_start_failure(&epic_pipeline.epic_pipeline_id, &step.step_id, &ticket.ticket_id);
+                error!(
+                    "Epic pipeline {} failed to start ticket {} (attempt {} of {}): {:#}",
+                    epic_pipeline.epic_pipeline_id, ticket.ticket_id, attempts, MAX_START_ATTEMPTS, e
+                );
+                if attempts >= MAX_START_ATTEMPTS {
+                    return Ok(Some(format!(
+                        "ticket {} failed to start {} times: {:#}",
+                        ticket.ticket_id, attempts, e
+                    )));
+                }
+            }
+        }
+    }
+    Ok(None)
+}
+
+async fn notify_finished(pool: &SqlitePool, epic_pipeline: &EpicPipeline) {
+    let (kind, title) = match epic_pipeline.status.as_str() {
+        EPIC_PIPELINE_COMPLETED => ("epic_pipeline_completed", format!("Epic pipeline on {} completed", epic_pipeline.epic_id)),
+        _ => ("epic_pipeline_failed", format!("Epic pipeline on {} failed", epic_pipeline.epic_id)),
+    };
+    crate::notifications::notify(
+        pool,
+        CreateNotificationRequest {
+            kind: kind.to_string(),
+            title,
+            body: epic_pipeline.error.clone(),
+            ticket_id: None,
+            organization: Some(epic_pipeline.organization.clone()),
+        },
+    )
+    .await;
+}
+
+/// Move a running epic pipeline forward: start tickets for the current step,
+/// and go on to the next step once the current one's condition holds
+async fn advance(pool: &SqlitePool, mut epic_pipeline: EpicPipeline) -> Result<()> {
+    let now = || chrono::Utc::now().to_rfc3339();
+    let mut steps = std::mem::take(&mut epic_pipeline.steps);
+
+    for step in steps.iter_mut().filter(|s| s.status == STEP_PENDING || s.status == STEP_RUNNING) {
+        if step.status == STEP_PENDING {
+            info!("Epic pipeline {} starting step {} on slice {}", epic_pipeline.epic_pipeline_id, step.step_id, step.slice_id);
+            step.status = STEP_RUNNING.to_string();
+            step.started_at = Some(now());
+        }
+        let outcome = match start_waiting_tickets(pool, &epic_pipeline, step).await? {
+            Some(reason) => StepOutcome::Failed(reason),
+            None => {
+                let progress = step_progress(pool, &epic_pipeline, step).await?;
+                evaluate(&step.completion, step.min_completed_percent, &progress)
+            }
+        };
+        match outcome {
+            StepOutcome::InProgress => break,
+            StepOutcome::Completed => {
+                step.status = STEP_COMPLETED.to_string();
+                step.completed_at = Some(now());
+            }
+            StepOutcome::Failed(reason) => {
+                step.status = STEP_FAILED.to_string();
+                step.completed_at = Some(now());
+                epic_pipeline.status = EPIC_PIPELINE_FAILED.to_string();
+                epic_pipeline.error = Some(format!("Step {} on slice {}: {}", step.step_id, step.slice_id, reason));
+                break;
+            }
+        }
+    }
+    if steps.iter().all(|s| s.status == STEP_COMPLETED) {
+        epic_pipeline.status = EPIC_PIPELINE_COMPLETED.to_string();
+    }
+    epic_pipeline.steps = steps;
+
+    let finished = epic_pipeline.status != EPIC_PIPELINE_RUNNING;
+    if finished {
+        epic_pipeline.completed_at = Some(now());
+        clear_start_failures(&epic_pipeline.epic_pipeline_id);
+    }
+    epic_pipeline.updated_at = now();
+    epic_pipelines::update_epic_pipeline(pool, &epic_pipeline).await?;
+    if finished {
+        info!("Epic pipeline {} {}", epic_pipeline.epic_pipeline_id, epic_pipeline.status);
+        notify_finished(pool, &epic_pipeline).await;
+    }
+    Ok(())
+}
+
+/// Check one epic pipeline now, e.g. right after it was created
+pub async fn check_epic_pipeline(pool: &SqlitePool, epic_pipeline_id: &str) {
+    let _lock = ADVANCING.lock().await;
+    let epic_pipeline = match epic_pipelines::get_epic_pipeline(pool, epic_pipeline_id).await {
+        Ok(Some(p)) if p.status == EPIC_PIPELINE_RUNNING => p,
+        Ok(_) => return,
+        Err(e) => {
+            error!("Failed to load epic pipeline {}: {:?}", epic_pipeline_id, e);
+            return;
+        }
+    };
+    if let Err(e) = advance(pool, epic_pipeline).await {
+        error!("Failed to advance epic pipeline {}: {:#}", epic_pipeline_id, e);
+    }
+}
+
+/// Cancel a running epic pipeline between checks. Ticket pipelines already
+/// started keep running. `None` when it isn't running.
+pub async fn cancel_epic_pipeline(pool: &SqlitePool, epic_pipeline_id: &str) -> Result<Option<EpicPipeline>> {
+    let _lock = ADVANCING.lock().await;
+    let Some(mut epic_pipeline) = epic_pipelines::get_epic_pipeline(pool, epic_pipeline_id).await? else {
+        return Ok(None);
+    };
+    if epic_pipeline.status != EPIC_PIPELINE_RUNNING {
+        return Ok(None);
+    }
+    let now = chrono::Utc::now().to_rfc3339();
+    epic_pipeline.status = EPIC_PIPELINE_CANCELLED.to_string();
+    epic_pipeline.completed_at = Some(now.clone());
+    epic_pipeline.updated_at = now;
+    epic_pipelines::update_epic_pipeline(pool, &epic_pipeline).await?;
+    clear_start_failures(epic_pipeline_id);
+    info!("Cancelled epic pipeline {}", epic_pipeline_id);
+    Ok(Some(epic_pipeline))
+}
+
+/// Start the job advancing running epic pipelines
+pub fn start_epic_pipeline_job(db: Arc<SqlitePool>) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(std::time::Duration::from_secs(CHECK_INTERVAL_SECS));
+        loop {
+            interval.tick().await;
+            if crate::maintenance::is_enabled() {
+                continue;
+            }
+            let running = match epic_pipelines::list_by_status(&db, EPIC_PIPELINE_RUNNING).await {
+                Ok(running) => running,
+                Err(e) => {
+                    error!("Failed to list running epic pipelines: {:?}", e);
+                    continue;
+                }
+            };
+            for epic_pipeline in running {
+                check_epic_pipeline(&db, &epic_pipeline.epic_pipeline_id).await;
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn progress(total: usize, completed: usize, failed: usize) -> StepProgress {
+        StepProgress { total, completed, failed, running: total - completed - failed, ..Default::default() }
+    }
+
+    #[test]
+    fn evaluates_completion_conditions() {
+        assert_eq!(evaluate(COMPLETION_ALL_COMPLETED, None, &progress(3, 2, 0)), StepOutcome::InProgress);
+        assert_eq!(evaluate(COMPLETION_ALL_COMPLETED, None, &progress(3, 3, 0)), StepOutcome::Completed);
+        assert!(matches!(evaluate(COMPLETION_ALL_COMPLETED, None, &progress(3, 1, 1)), StepOutcome::Failed(_)));
+        assert_eq!(evaluate(COMPLETION_ALL_COMPLETED, None, &progress(0, 0, 0)), StepOutcome::InProgress);
+        assert_eq!(evaluate(COMPLETION_ALL_FINISHED, None, &progress(0, 0, 0)), StepOutcome::InProgress);
+
+        assert_eq!(evaluate(COMPLETION_ALL_FINISHED, None, &progress(3, 1, 1)), StepOutcome::InProgress);
+        assert_eq!(evaluate(COMPLETION_ALL_FINISHED, None, &progress(3, 1, 2)), StepOutcome::Completed);
+
+        assert_eq!(evaluate(COMPLETION_MIN_PERCENT, Some(50), &progress(3, 1, 0)), StepOutcome::InProgress);
+        assert_eq!(evaluate(COMPLETION_MIN_PERCENT, Some(50), &progress(3, 2, 1)), StepOutcome::Completed);
+        assert!(matches!(evaluate(COMPLETION_MIN_PERCENT, Some(50), &progress(3, 0, 2)), StepOutcome::Failed(_)));
+    }
+}
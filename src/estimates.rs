@@ -0,0 +1,174 @@
+//! Ticket estimates
+//!
+//! The estimator agent reads a ticket's description, earlier agent output and
+//! history, and ends with an `<estimate>` block. The parsed estimate and risk
+//! notes are written into the ticket's custom fields so later pipeline steps
+//! (via `{{custom_field.estimate_size}}` etc.) and the UI can use them.
+
+use anyhow::Result;
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+use ticketing_system::SqlitePool;
+
+use crate::agents::AgentType;
+
+/// Max characters of history and earlier output injected into the prompt
+const CONTEXT_BUDGET_CHARS: usize = 30_000;
+
+/// Max characters kept from any single earlier agent run
+const MAX_RUN_OUTPUT_CHARS: usize = 4_000;
+
+/// History events considered, newest first
+const HISTORY_LIMIT: i64 = 50;
+
+pub const ESTIMATOR_AGENT: &str = "estimator";
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Estimate {
+    /// XS, S, M, L or XL
+    pub size: String,
+    pub hours: Option<f64>,
+    /// low, medium or high
+    pub confidence: Option<String>,
+    pub risks: Vec<String>,
+}
+
+fn tag<'a>(text: &'a str, name: &str) -> Option<&'a str> {
+    let open = format!("<{}>", name);
+    let close = format!("</{}>", name);
+    let start = text.rfind(&open)? + open.len();
+    let end = start + text[start..].find(&close)?;
+    Some(text[start..end].trim())
+}
+
+impl Estimate {
+    /// Parse the last `<estimate>` block in agent output
+    pub fn parse(output: &str) -> Option<Self> {
+        let block = tag(output, "estimate")?;
+        let size = tag(block, "size")?.to_uppercase();
+        if !matches!(size.as_str(), "XS" | "S" | "M" | "L" | "XL") {
+            return None;
+        }
+        let hours = tag(block, "hours").and_then(|h| h.trim_end_matches('h').trim().parse().ok());
+        let confidence = tag(block, "confidence")
+            .map(|c| c.to_lowercase())
+            .filter(|c| matches!(c.as_str(), "low" | "medium" | "high"));
+        let risks = tag(block, "risks")
+            .map(|risks| {
+                risks
+                    .lines()
+                    .map(|line| line.trim().trim_start_matches(['-', '*']).trim())
+                    .filter(|line| !line.is_empty())
+                    .map(str::to_string)
+                    .collect()
+            })
+            .unwrap_or_default();
+        Some(Estimate { size, hours, confidence, risks })
+    }
+
+    /// Custom fields written to the ticket
+    pub fn custom_fields(&self, session_id: &str) -> Map<String, Value> {
+        let mut fields = Map::new();
+        fields.insert("estimate_size".to_string(), json!(self.size));
+        fields.insert("estimate_hours".to_string(), json!(self.hours));
+        fields.insert("estimate_confidence".to_string(), json!(self.confidence));
+        fields.insert("estimate_risks".to_string(), json!(self.risks));
+        fields.insert("estimate_session_id".to_string(), json!(session_id));
+        fields.insert("estimated_at".to_string(), json!(chrono::Utc::now().to_rfc3339()));
+        fields
+    }
+}
+
+/// Store an estimate on the ticket's custom fields
+pub async fn store_estimate(db: &SqlitePool, ticket_id: &str, session_id: &str, estimate: &Estimate) -> Result<()> {
+    ticketing_system::tickets::update_custom_fields(db, ticket_id, &estimate.custom_fields(session_id)).await?;
+    Ok(())
+}
+
+/// Capture a completed estimator run onto its ticket. No-op for other agents.
+pub async fn capture_agent_output(db: &SqlitePool, ticket_id: &str, agent_type: &str, session_id: &str, output: &str) {
+    if agent_type != ESTIMATOR_AGENT {
+        return;
+    }
+    let Some(estimate) = Estimate::parse(output) else {
+        tracing::warn!("Estimator run {} produced no parseable estimate", session_id);
+        return;
+    };
+    match store_estimate(db, ticket_id, session_id, &estimate).await {
+        Ok(()) => tracing::info!("Stored {} estimate for ticket {} from session {}", estimate.size, ticket_id, session_id),
+        Err(e) => tracing::warn!("Failed to store estimate for ticket {}: {}", ticket_id, e),
+    }
+}
+
+/// Render the ticket's earlier agent output and history for the estimator
+/// prompt, within the context budget. `None` for other agents or when empty.
+pub async fn estimation_context(db: &SqlitePool, agent_type: &AgentType, ticket_id: &str) -> Option<String> {
+    if *agent_type != AgentType::Estimator {
+        return None;
+    }
+    let mut remaining = CONTEXT_BUDGET_CHARS;
+    let mut sections = Vec::new();
+
+    let runs = ticketing_system::agent_runs::list_runs_by_ticket(db, ticket_id).await.unwrap_or_default();
+    let mut outputs = Vec::new();
+    for run in runs.iter().filter(|r| r.status == "completed" && r.agent_type != ESTIMATOR_AGENT) {
+        let Some(output) = run.output_summary.as_deref().filter(|o| !o.trim().is_empty()) else {
+            continue;
+        };
+        let output: String = output.chars().take(MAX_RUN_OUTPUT_CHARS).collect();
+        let entry = format!("### {} ({})\n{}", run.agent_type, run.started_at, output);
+        if entry.len() > remaining {
+            break;
+        }
+        remaining -= entry.len();
+        outputs.push(entry);
+    }
+    if !outputs.is_empty() {
+        sections.push(format!("## Related Artifacts\n\n{}", outputs.join("\n\n")));
+    }
+
+    let events = ticketing_system::ticket_history::get_ticket_history_limited(db, ticket_id, HISTORY_LIMIT)
+        .await
+        .unwrap_or_default();
+    let mut history = Vec::new();
+    for event in events {
+        let line = format!("- {} {}: {}", event.created_at, event.event_type, event.summary);
+        if line.len() > remaining {
+            break;
+        }
+        remaining -= line.len();
+        history.push(line);
+    }
+    if !history.is_empty() {
+        sections.push(format!("## Ticket History (newest first)\n\n{}", history.join("\n")));
+    }
+
+    if sections.is_empty() {
+        None
+    } else {
+        Some(sections.join("\n\n"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_estimate_block() {
+        let output = "Looks contained.\n<estimate>\n<size>m</size>\n<hours>12h</hours>\n\
+                      <confidence>Medium</confidence>\n<risks>\n- Vendor API is undocumented\n\
+                      - Needs a migration\n</risks>\n</estimate>";
+        assert_eq!(
+            Estimate::parse(output),
+            Some(Estimate {
+                size: "M".to_string(),
+                hours: Some(12.0),
+                confidence: Some("medium".to_string()),
+                risks: vec!["Vendor API is undocumented".to_string(), "Needs a migration".to_string()],
+            })
+        );
+        assert_eq!(Estimate::parse("<estimate><size>huge</size></estimate>"), None);
+        assert_eq!(Estimate::parse("no estimate here"), None);
+    }
+}
@@ -0,0 +1,124 @@
+//! Conditional GETs with ETags
+//!
+//! Mobile clients polling over Tailscale re-fetch the same tickets, epics,
+//! pipelines and conversations over and over. JSON responses from those GET
+//! endpoints carry a weak ETag hashed from the body, which changes whenever a
+//! record's `updated_at` (or any other field) does. A request whose
+//! `If-None-Match` still matches gets 304 with no body. The handler still
+//! runs; what is saved is the transfer.
+
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+use axum::{
+    body::{Body, HttpBody},
+    extract::Request,
+    http::{header, HeaderValue, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+
+/// Larger responses are passed through untagged rather than buffered
+const MAX_BODY_BYTES: u64 = 8 * 1024 * 1024;
+
+/// Whether GETs of this path are tagged
+fn is_cacheable(path: &str) -> bool {
+    let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+    matches!(
+        segments.as_slice(),
+        ["api", "tickets"]
+            | ["api", "tickets", _]
+            | ["api", "tickets", _, "pipeline"]
+            | ["api", "epics"]
+            | ["api", "epics", _]
+            | ["api", "epics", _, "tickets"]
+            | ["api", "epics", _, "slices", _, "tickets"]
+            | ["api", "epics", _, "slices", _, "tickets", _]
+            | ["api", "conversations"]
+            | ["api", "conversations", _]
+            | ["api", "conversations", _, "messages"]
+    )
+}
+
+fn etag_for(body: &[u8]) -> String {
+    let mut hasher = DefaultHasher::new();
+    body.hash(&mut hasher);
+    format!("W/\"{:016x}\"", hasher.finish())
+}
+
+/// Whether an `If-None-Match` value matches the ETag, using weak comparison
+fn none_match(if_none_match: &str, etag: &str) -> bool {
+    let opaque = |tag: &str| tag.trim().trim_start_matches("W/").to_string();
+    let etag = opaque(etag);
+    if_none_match.split(',').any(|tag| tag.trim() == "*" || opaque(tag) == etag)
+}
+
+fn is_json(response: &Response) -> bool {
+    response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("application/json"))
+}
+
+/// Middleware tagging cacheable GET responses and answering 304 when the
+/// client's copy is current
+pub async fn conditional_get(request: Request, next: Next) -> Response {
+    if request.method() != Method::GET || !is_cacheable(request.uri().path()) {
+        return next.run(request).await;
+    }
+    let if_none_match = request
+        .headers()
+        .get(header::IF_NONE_MATCH)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string);
+
+    let response = next.run(request).await;
+    if response.status() != StatusCode::OK || !is_json(&response) {
+        return response;
+    }
+    let (mut parts, body) = response.into_parts();
+    if body.size_hint().upper().is_none_or(|len| len > MAX_BODY_BYTES) {
+        return Response::from_parts(parts, body);
+    }
+    let bytes = match axum::body::to_bytes(body, MAX_BODY_BYTES as usize).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            tracing::error!("Failed to buffer response for ETag: {}", e);
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+
+    let etag = etag_for(&bytes);
+    parts.headers.insert(header::CACHE_CONTROL, HeaderValue::from_static("private, no-cache"));
+    if let Ok(value) = HeaderValue::from_str(&etag) {
+        parts.headers.insert(header::ETAG, value);
+    }
+    if if_none_match.is_some_and(|value| none_match(&value, &etag)) {
+        parts.status = StatusCode::NOT_MODIFIED;
+        parts.headers.remove(header::CONTENT_LENGTH);
+        return Response::from_parts(parts, Body::empty());
+    }
+    Response::from_parts(parts, Body::from(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_weakly_against_any_listed_tag() {
+        let etag = etag_for(br#"{"ticket_id":"T-1","updated_at":"2026-01-01T00:00:00Z"}"#);
+        assert_ne!(etag, etag_for(br#"{"ticket_id":"T-1","updated_at":"2026-01-02T00:00:00Z"}"#));
+
+        assert!(none_match(&etag, &etag));
+        assert!(none_match(&etag.replace("W/", ""), &etag));
+        assert!(none_match(&format!("W/\"stale\", {}", etag), &etag));
+        assert!(none_match("*", &etag));
+        assert!(!none_match("W/\"stale\"", &etag));
+
+        assert!(is_cacheable("/api/tickets/T-1/pipeline"));
+        assert!(is_cacheable("/api/conversations/c1/messages"));
+        assert!(!is_cacheable("/api/tickets/T-1/history"));
+        assert!(!is_cacheable("/api/agent-runs/s1/stream"));
+    }
+}
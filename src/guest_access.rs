@@ -0,0 +1,65 @@
+//! Guest access - restricted accounts for external collaborators
+//!
+//! Guests join through an invitation and belong to no organization. They may
+//! only read the tickets shared with them, comment on them, and approve,
+//! reject or comment on the pipeline steps the share assigns to them. `require_auth` checks
+//! every guest request against `permits` and rejects anything else with 403,
+//! so new routes stay closed to guests unless they are added here.
+
+use axum::http::Method;
+use ticketing_system::{GuestAccess, GuestShare};
+
+fn share<'a>(access: &'a GuestAccess, ticket_id: &str) -> Option<&'a GuestShare> {
+    access.shares.iter().find(|s| s.ticket_id == ticket_id)
+}
+
+/// Whether the ticket is shared with the guest
+pub fn can_view(access: &GuestAccess, ticket_id: &str) -> bool {
+    share(access, ticket_id).is_some()
+}
+
+/// Whether the guest was assigned to decide on this step
+pub fn can_approve(access: &GuestAccess, ticket_id: &str, step_id: &str) -> bool {
+    share(access, ticket_id).is_some_and(|s| s.step_ids.iter().any(|id| id == step_id))
+}
+
+/// Whether a guest may make this request
+pub fn permits(access: &GuestAccess, method: &Method, path: &str) -> bool {
+    let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+    match (method, segments.as_slice()) {
+        (&Method::GET, ["api", "guest", "tickets"]) => true,
+        (&Method::GET, ["api", "tickets", ticket_id])
+        | (&Method::GET, ["api", "tickets", ticket_id, "history" | "pipeline"])
+        | (&Method::GET | &Method::POST, ["api", "tickets", ticket_id, "comments"]) => can_view(access, ticket_id),
+        (&Method::POST, ["api", "tickets", ticket_id, "pipeline", "steps", step_id, "approve" | "reject" | "comments"]) => {
+            can_approve(access, ticket_id, step_id)
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn permits_only_shared_tickets_and_assigned_steps() {
+        let access = GuestAccess {
+            user_id: "client".to_string(),
+            organization: "acme".to_string(),
+            shares: vec![GuestShare { ticket_id: "T-1".to_string(), step_ids: vec!["review".to_string()] }],
+        };
+        assert!(permits(&access, &Method::GET, "/api/tickets/T-1"));
+        assert!(permits(&access, &Method::POST, "/api/tickets/T-1/comments"));
+        assert!(permits(&access, &Method::POST, "/api/tickets/T-1/pipeline/steps/review/approve"));
+        assert!(permits(&access, &Method::POST, "/api/tickets/T-1/pipeline/steps/review/comments"));
+        assert!(permits(&access, &Method::GET, "/api/guest/tickets"));
+
+        assert!(!permits(&access, &Method::GET, "/api/tickets/T-2"));
+        assert!(!permits(&access, &Method::POST, "/api/tickets/T-1/pipeline/steps/build/approve"));
+        assert!(!permits(&access, &Method::POST, "/api/tickets/T-1/pipeline/run"));
+        assert!(!permits(&access, &Method::DELETE, "/api/tickets/T-1/comments"));
+        assert!(!permits(&access, &Method::GET, "/api/tickets"));
+        assert!(!permits(&access, &Method::GET, "/api/emails"));
+    }
+}
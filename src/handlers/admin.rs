@@ -0,0 +1,106 @@
+use axum::{
+    extract::{Extension, State},
+    http::StatusCode,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use ticketing_system::{SqlitePool, User};
+
+use crate::conversation_retention::{run_retention, RetentionReport};
+use crate::maintenance::{self, MaintenanceStatus};
+use crate::quotas::{transcription_stats, TranscriptionStats};
+use crate::reload::{reload_all, ReloadReport};
+use crate::storage_crypto::{self, RotationReport};
+use crate::tenancy::Organization;
+
+/// Reload prompts, CORS allowlist, email accounts and agent config (POST /api/admin/reload)
+pub async fn reload_config(
+    Extension(org): Extension<Organization>,
+) -> Result<Json<ReloadReport>, (StatusCode, String)> {
+    org.require_instance_admin()?;
+    Ok(Json(reload_all().await))
+}
+
+/// Apply the conversation retention policy now (POST /api/admin/conversation-retention)
+pub async fn run_conversation_retention(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(org): Extension<Organization>,
+) -> Result<Json<RetentionReport>, (StatusCode, String)> {
+    org.require_instance_admin()?;
+    run_retention(&pool)
+        .await
+        .map(Json)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
+
+/// Re-encrypt stored email bodies, transcripts and messages under the
+/// current storage key (POST /api/admin/storage-encryption/rotate)
+pub async fn rotate_storage_key(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(org): Extension<Organization>,
+) -> Result<Json<RotationReport>, (StatusCode, String)> {
+    org.require_instance_admin()?;
+    storage_crypto::rotate(&pool)
+        .await
+        .map(Json)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("{:#}", e)))
+}
+
+#[derive(Debug, Serialize)]
+pub struct MaintenanceResponse {
+    #[serde(flatten)]
+    pub status: MaintenanceStatus,
+    /// Agent runs still in flight; wait for zero before touching the database file
+    pub running_agents: i64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SetMaintenanceRequest {
+    pub enabled: bool,
+    /// Shown to clients whose mutations are rejected
+    pub message: Option<String>,
+}
+
+async fn maintenance_response(pool: &SqlitePool, status: MaintenanceStatus) -> Result<Json<MaintenanceResponse>, (StatusCode, String)> {
+    let running_agents = ticketing_system::agent_runs::count_runs_by_status(pool, "running")
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Ok(Json(MaintenanceResponse { status, running_agents }))
+}
+
+/// Current maintenance mode (GET /api/admin/maintenance), readable by everyone
+pub async fn get_maintenance(
+    State(pool): State<Arc<SqlitePool>>,
+) -> Result<Json<MaintenanceResponse>, (StatusCode, String)> {
+    maintenance_response(&pool, maintenance::status()).await
+}
+
+/// Turn read-only maintenance mode on or off (PUT /api/admin/maintenance)
+pub async fn set_maintenance(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(user): Extension<User>,
+    Extension(org): Extension<Organization>,
+    Json(req): Json<SetMaintenanceRequest>,
+) -> Result<Json<MaintenanceResponse>, (StatusCode, String)> {
+    org.require_instance_admin()?;
+    let status = maintenance::set(req.enabled, req.message, &user.name);
+    maintenance_response(&pool, status).await
+}
+
+#[derive(Debug, Serialize)]
+pub struct AdminStats {
+    pub transcription: TranscriptionStats,
+}
+
+/// Instance-wide usage figures across every organization (GET /api/admin/stats)
+pub async fn get_admin_stats(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(org): Extension<Organization>,
+) -> Result<Json<AdminStats>, (StatusCode, String)> {
+    org.require_instance_admin()?;
+    let transcription = transcription_stats(&pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("{:#}", e)))?;
+    Ok(Json(AdminStats { transcription }))
+}
@@ -1,13 +1,166 @@
 use sqlx::SqlitePool;
 use std::path::PathBuf;
 use tokio::fs;
-use chrono::Utc;
+use chrono::{NaiveDate, Utc};
+use ticketing_system::OrgSettings;
 
-/// Write agent output to repository as a markdown artifact
+pub const ARTIFACT_FORMAT_MARKDOWN: &str = "md";
+pub const ARTIFACT_FORMAT_JSON: &str = "json";
+
+/// Filename used when the organization hasn't set a template
+const DEFAULT_FILENAME_TEMPLATE: &str = "{ticket_id}-{agent_type}";
+
+/// Variables a filename template may use
+const FILENAME_VARIABLES: &[&str] = &["ticket_id", "agent_type", "date"];
+
+/// Directory for an agent type's artifacts when no base directory is set
+fn default_artifact_dir(agent_type: &str) -> &'static str {
+    match agent_type {
+        "research" | "exa-research" | "research-synthesis" | "competitive-research" | "vendor-research" | "technical-research" => {
+            "docs/research"
+        }
+        "planning" => "docs/planning",
+        "evaluation" => "docs/evaluation",
+        _ => "docs/agent-output",
+    }
+}
+
+/// A path inside the repo: relative, without `..` or empty segments
+fn is_repo_relative(path: &str) -> bool {
+    !path.starts_with('/') && !path.contains('\\') && path.split('/').all(|part| !part.is_empty() && part != "..")
+}
+
+/// Fill in a filename template's `{variables}`
+fn render_filename(template: &str, ticket_id: &str, agent_type: &str, date: NaiveDate) -> Result<String, String> {
+    let mut filename = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        filename.push_str(&rest[..start]);
+        let end = rest[start..]
+            .find('}')
+            .ok_or_else(|| format!("Unclosed '{{' in filename template '{}'", template))?;
+        let value = match &rest[start + 1..start + end] {
+            "ticket_id" => ticket_id.to_string(),
+            "agent_type" => agent_type.to_string(),
+            "date" => date.format("%Y-%m-%d").to_string(),
+            other => {
+                return Err(format!(
+                    "Unknown variable '{{{}}}' in filename template, expected one of: {}",
+                    other,
+                    FILENAME_VARIABLES.join(", ")
+                ))
+            }
+        };
+        filename.push_str(&value);
+        rest = &rest[start + end + 1..];
+    }
+    filename.push_str(rest);
+    Ok(filename)
+}
+
+/// Path of an artifact relative to its repo, or `None` when the organization
+/// disabled artifacts for the agent type
+fn artifact_relative_path(
+    settings: &OrgSettings,
+    ticket_id: &str,
+    agent_type: &str,
+    date: NaiveDate,
+) -> Result<Option<String>, String> {
+    if settings.artifact_disabled_agent_types.iter().any(|t| t == agent_type) {
+        return Ok(None);
+    }
+    let dir = settings
+        .artifact_base_dir
+        .as_deref()
+        .map(|dir| dir.trim_matches('/'))
+        .unwrap_or_else(|| default_artifact_dir(agent_type));
+    let template = settings.artifact_filename_template.as_deref().unwrap_or(DEFAULT_FILENAME_TEMPLATE);
+    let filename = render_filename(template, ticket_id, agent_type, date)?;
+    let path = format!("{}/{}.{}", dir, filename, settings.artifact_format);
+    if !is_repo_relative(&path) {
+        return Err(format!("Artifact path '{}' is outside the repository", path));
+    }
+    Ok(Some(path))
+}
+
+/// Check artifact settings before they're saved
+pub(crate) fn validate_artifact_settings(settings: &OrgSettings) -> Result<(), String> {
+    if ![ARTIFACT_FORMAT_MARKDOWN, ARTIFACT_FORMAT_JSON].contains(&settings.artifact_format.as_str()) {
+        return Err(format!(
+            "Unknown artifact format '{}', expected '{}' or '{}'",
+            settings.artifact_format, ARTIFACT_FORMAT_MARKDOWN, ARTIFACT_FORMAT_JSON
+        ));
+    }
+    if let Some(dir) = settings.artifact_base_dir.as_deref() {
+        if !is_repo_relative(dir.trim_matches('/')) {
+            return Err(format!("Artifact directory '{}' must be a path inside the repository", dir));
+        }
+    }
+    if let Some(template) = settings.artifact_filename_template.as_deref() {
+        let filename = render_filename(template, "ticket", "agent", Utc::now().date_naive())?;
+        if !is_repo_relative(&filename) {
+            return Err(format!("Filename template '{}' must give a path inside the repository", template));
+        }
+    }
+    Ok(())
+}
+
+/// Where an organization's artifacts for an agent type go on a given day: the
+/// repo's local path and the artifact's path inside it. The repo is the one
+/// of the configured type, else the documentation repo, falling back to the
+/// research repo.
+pub async fn artifact_location(
+    db: &SqlitePool,
+    organization: &str,
+    ticket_id: &str,
+    agent_type: &str,
+    date: NaiveDate,
+) -> Option<(PathBuf, String)> {
+    let settings = crate::handlers::load_org_settings(db, organization).await;
+    let relative_path = match artifact_relative_path(&settings, ticket_id, agent_type, date) {
+        Ok(Some(path)) => path,
+        Ok(None) => return None,
+        Err(e) => {
+            tracing::warn!("Invalid artifact settings for org '{}': {}", organization, e);
+            return None;
+        }
+    };
+
+    let repo_types = match settings.artifact_repo_type.as_deref() {
+        Some(repo_type) => vec![repo_type],
+        None => vec!["documentation", "research"],
+    };
+    let mut repo = None;
+    for repo_type in &repo_types {
+        match ticketing_system::repositories::get_repository_by_org_and_type(db, organization, repo_type).await {
+            Ok(Some(r)) => {
+                repo = Some(r);
+                break;
+            }
+            Ok(None) => {}
+            Err(e) => {
+                tracing::error!("Failed to lookup repo: {}", e);
+                return None;
+            }
+        }
+    }
+    let Some(repo) = repo else {
+        tracing::warn!(
+            "No {} repo found for org '{}' to keep artifacts in",
+            repo_types.join(" or "),
+            organization
+        );
+        return None;
+    };
+
+    Some((PathBuf::from(repo.local_path?), relative_path))
+}
+
+/// Write agent output to repository as an artifact
 /// Returns the relative artifact path if successful
 ///
-/// Artifacts go to the org's documentation repo (repo_type='documentation')
-/// Falls back to 'research' repo if no documentation repo exists
+/// Path, repo and format follow the organization's artifact settings (see
+/// `artifact_location`); agent types it disabled artifacts for are skipped
 pub async fn write_artifact(
     db: &SqlitePool,
     ticket_id: &str,
@@ -20,71 +173,33 @@ pub async fn write_artifact(
         .ok()
         .flatten()?;
 
-    // Try documentation repo first, fall back to research repo
-    let repo = match ticketing_system::repositories::get_repository_by_org_and_type(
-        db,
-        &ticket.organization,
-        "documentation",
-    )
-    .await
-    {
-        Ok(Some(r)) => r,
-        _ => {
-            // Fallback to research repo
-            match ticketing_system::repositories::get_repository_by_org_and_type(
-                db,
-                &ticket.organization,
-                "research",
-            )
-            .await
-            {
-                Ok(Some(r)) => r,
-                Ok(None) => {
-                    tracing::warn!(
-                        "No documentation or research repo found for org '{}', skipping artifact write",
-                        ticket.organization
-                    );
-                    return None;
-                }
-                Err(e) => {
-                    tracing::error!("Failed to lookup repo: {}", e);
-                    return None;
-                }
-            }
-        }
-    };
-
-    let local_path = repo.local_path.as_ref()?;
-
-    // Determine artifact directory based on agent type
-    let artifact_dir = match agent_type {
-        "research" | "exa-research" | "research-synthesis" | "competitive-research" | "vendor-research" | "technical-research" => {
-            "docs/research"
-        }
-        "planning" => "docs/planning",
-        "evaluation" => "docs/evaluation",
-        _ => "docs/agent-output",
-    };
-
-    // Build full path
-    let repo_path = PathBuf::from(local_path);
-    let output_dir = repo_path.join(artifact_dir);
+    let now = Utc::now();
+    let (repo_path, relative_path) =
+        artifact_location(db, &ticket.organization, ticket_id, agent_type, now.date_naive()).await?;
+    let file_path = repo_path.join(&relative_path);
 
     // Create directory if it doesn't exist
-    if let Err(e) = fs::create_dir_all(&output_dir).await {
-        tracing::error!("Failed to create artifact directory {:?}: {}", output_dir, e);
-        return None;
+    if let Some(output_dir) = file_path.parent() {
+        if let Err(e) = fs::create_dir_all(output_dir).await {
+            tracing::error!("Failed to create artifact directory {:?}: {}", output_dir, e);
+            return None;
+        }
     }
 
-    // Generate filename: {ticket_id}-{agent_type}.md to prevent overwrites
-    let filename = format!("{}-{}.md", ticket_id, agent_type);
-    let file_path = output_dir.join(&filename);
-    let relative_path = format!("{}/{}", artifact_dir, filename);
-
-    // Build markdown content with frontmatter
-    let now = Utc::now().to_rfc3339();
-    let content = format!(
-        r#"---
+    let now = now.to_rfc3339();
+    let content = if relative_path.ends_with(&format!(".{}", ARTIFACT_FORMAT_JSON)) {
+        let artifact = serde_json::json!({
+            "ticket_id": ticket_id,
+            "agent_type": agent_type,
+            "generated_at": now,
+            "title": ticket.title,
+            "output": output_summary,
+        });
+        serde_json::to_string_pretty(&artifact).unwrap_or_default()
+    } else {
+        // Markdown with frontmatter
+        format!(
+            r#"---
 ticket_id: {}
 agent_type: {}
 generated_at: {}
@@ -95,13 +210,14 @@ title: {}
 
 {}
 "#,
-        ticket_id,
-        agent_type,
-        now,
-        ticket.title,
-        ticket.title,
-        output_summary
-    );
+            ticket_id,
+            agent_type,
+            now,
+            ticket.title,
+            ticket.title,
+            output_summary
+        )
+    };
 
     // Write the file
     if let Err(e) = fs::write(&file_path, &content).await {
@@ -125,3 +241,25 @@ title: {}
 
     Some(relative_path)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_filename_templates() {
+        let date = NaiveDate::from_ymd_opt(2026, 3, 9).unwrap();
+        assert_eq!(
+            render_filename("{date}/{ticket_id}-{agent_type}", "t-1", "research", date).unwrap(),
+            "2026-03-09/t-1-research"
+        );
+        assert_eq!(render_filename("notes", "t-1", "research", date).unwrap(), "notes");
+        assert!(render_filename("{title}", "t-1", "research", date).is_err());
+        assert!(render_filename("{ticket_id", "t-1", "research", date).is_err());
+
+        assert!(is_repo_relative("docs/research/t-1.md"));
+        assert!(!is_repo_relative("/etc/t-1.md"));
+        assert!(!is_repo_relative("docs/../../t-1.md"));
+        assert!(!is_repo_relative("docs//t-1.md"));
+    }
+}
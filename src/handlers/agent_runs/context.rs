@@ -1,5 +1,6 @@
 use sqlx::SqlitePool;
 use crate::agents::{AgentType, TicketContext};
+use crate::redaction::RedactionAudit;
 
 /// Build ticket context for agent execution
 pub fn build_ticket_context(
@@ -15,6 +16,12 @@ pub fn build_ticket_context(
         ticket_id: ticket_id.to_string(),
         title,
         intent,
+        checklist: None,
+        research: None,
+        step_inputs: None,
+        estimation: None,
+        email_templates: None,
+        pinned_outputs: None,
     }
 }
 
@@ -78,42 +85,23 @@ pub async fn build_blocked_by_context(db: &SqlitePool, ticket_id: &str) -> Optio
                     .collect();
 
                 for run in completed_runs {
-                    // Build the artifact path based on our naming convention
-                    // Look up the repo to get the local path
-                    // Try documentation repo first, fall back to research
-                    let repo = if let Ok(Some(r)) =
-                        ticketing_system::repositories::get_repository_by_org_and_type(
-                            db,
-                            &blocker_ticket.organization,
-                            "documentation",
-                        ).await
-                    {
-                        Some(r)
-                    } else if let Ok(Some(r)) =
-                        ticketing_system::repositories::get_repository_by_org_and_type(
-                            db,
-                            &blocker_ticket.organization,
-                            "research",
-                        ).await
-                    {
-                        Some(r)
-                    } else {
-                        None
-                    };
-
-                    let artifact_path = repo.and_then(|r| {
-                        r.local_path.map(|local_path| {
-                            // Determine subdirectory based on agent type
-                            let subdir = match run.agent_type.as_str() {
-                                "research" | "exa-research" | "research-synthesis"
-                                | "competitive-research" | "vendor-research" | "technical-research" => "docs/research",
-                                "planning" => "docs/planning",
-                                "evaluation" => "docs/evaluation",
-                                _ => "docs/agent-output",
-                            };
-                            format!("{}/{}/{}-{}.md", local_path, subdir, blocker_id, run.agent_type)
-                        })
-                    });
+                    // Artifacts are written when the run completes, so its
+                    // completion date fills in any {date} in the path
+                    let completed_on = run
+                        .completed_at
+                        .as_deref()
+                        .and_then(|at| chrono::DateTime::parse_from_rfc3339(at).ok())
+                        .map(|at| at.with_timezone(&chrono::Utc).date_naive())
+                        .unwrap_or_else(|| chrono::Utc::now().date_naive());
+                    let artifact_path = super::artifacts::artifact_location(
+                        db,
+                        &blocker_ticket.organization,
+                        blocker_id,
+                        &run.agent_type,
+                        completed_on,
+                    )
+                    .await
+                    .map(|(repo_path, relative_path)| repo_path.join(relative_path).display().to_string());
 
                     if let Some(path) = artifact_path {
                         artifact_entries.push(format!(
@@ -168,15 +156,27 @@ pub async fn get_sender_info(db: &SqlitePool, assignee: Option<&str>) -> Option<
 }
 
 /// Get all context for agent execution
-/// Returns: (previous_output, selected_context, sender_info, blocked_by_context)
+/// Returns: (previous_output, selected_context, sender_info, blocked_by_context, related_work_context, context_pack)
+///
+/// The related-work context comes with the audit of PII redacted from it.
+#[allow(clippy::too_many_arguments)]
 pub async fn gather_agent_context(
     db: &SqlitePool,
     agent_type: &AgentType,
     ticket_id: &str,
+    organization: &str,
     previous_session_id: Option<&str>,
     selected_session_ids: &[String],
     assignee: Option<&str>,
-) -> (Option<String>, Option<String>, Option<String>, Option<String>) {
+    context_pack_id: Option<&str>,
+) -> (
+    Option<String>,
+    Option<String>,
+    Option<String>,
+    Option<String>,
+    Option<(String, RedactionAudit)>,
+    Option<String>,
+) {
     let previous_output = if let Some(prev_id) = previous_session_id {
         get_previous_output(db, prev_id).await
     } else {
@@ -194,5 +194,14 @@ pub async fn gather_agent_context(
     // Auto-fetch context from blocked_by tickets
     let blocked_by_context = build_blocked_by_context(db, ticket_id).await;
 
-    (previous_output, selected_context, sender_info, blocked_by_context)
+    // Semantically similar tickets, emails, transcripts and artifacts
+    let related_work_context = crate::embeddings::related_work_context(db, ticket_id).await;
+
+    // Curated context the run or its pipeline step asked for
+    let context_pack = match context_pack_id {
+        Some(pack_id) => crate::context_packs::pack_context(db, organization, pack_id).await,
+        None => None,
+    };
+
+    (previous_output, selected_context, sender_info, blocked_by_context, related_work_context, context_pack)
 }
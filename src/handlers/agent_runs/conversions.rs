@@ -16,7 +16,25 @@ pub async fn store_agent_run(db: &SqlitePool, run: &AgentRun) -> anyhow::Result<
         output_summary: run.output_summary.clone(),
     };
 
-    ticketing_system::agent_runs::update_agent_run(db, &db_run).await
+    ticketing_system::agent_runs::update_agent_run(db, &db_run).await?;
+    if run.usage != Default::default() {
+        ticketing_system::agent_runs::set_usage(
+            db,
+            &run.session_id,
+            run.usage.input_tokens,
+            run.usage.output_tokens,
+            run.usage.cost_usd,
+        )
+        .await?;
+    }
+    if let Some(snapshot) = &run.context_snapshot {
+        crate::agents::snapshot::store_snapshot(db, &run.session_id, snapshot).await;
+    }
+    crate::agents::tool_audit::store_tool_calls(db, &run.session_id, &run.tool_calls).await;
+    if let Some(reason) = run.failure_reason {
+        ticketing_system::agent_runs::set_failure_reason(db, &run.session_id, reason.as_str()).await?;
+    }
+    Ok(())
 }
 
 /// Convert a database agent run to API agent run
@@ -39,7 +57,20 @@ pub fn db_run_to_api_run(db_run: ticketing_system::AgentRun) -> AgentRun {
         input_message: db_run.input_message,
         output_summary: db_run.output_summary,
         email_output,
+        failure_reason: None,
+        context_snapshot: None,
+        usage: Default::default(),
+        tool_calls: Vec::new(),
+    }
+}
+
+/// Convert a database agent run, attaching its stored failure reason if it failed
+pub async fn load_api_run(db: &SqlitePool, db_run: ticketing_system::AgentRun) -> AgentRun {
+    let mut run = db_run_to_api_run(db_run);
+    if run.status == AgentRunStatus::Failed {
+        run.failure_reason = crate::agents::failure::failure_reason(db, &run.session_id).await;
     }
+    run
 }
 
 /// Parse agent status string to enum
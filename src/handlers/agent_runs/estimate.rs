@@ -0,0 +1,69 @@
+//! On-demand ticket estimation: run the estimator agent against a ticket and
+//! write the parsed estimate and risk notes into its custom fields.
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use serde::Serialize;
+use sqlx::SqlitePool;
+use std::sync::Arc;
+
+use crate::agents::{resolve_working_dir, AgentExecutor, AgentRunStatus, AgentType};
+use crate::estimates::{estimation_context, store_estimate, Estimate};
+use super::{context::build_ticket_context, conversions::store_agent_run};
+
+#[derive(Debug, Serialize)]
+pub struct EstimateTicketResponse {
+    pub session_id: String,
+    pub estimate: Estimate,
+}
+
+/// POST /api/tickets/:ticket_id/estimate
+pub async fn estimate_ticket(
+    Path(ticket_id): Path<String>,
+    State(db): State<Arc<SqlitePool>>,
+) -> Result<Json<EstimateTicketResponse>, (StatusCode, String)> {
+    let ticket = ticketing_system::tickets::get_ticket_by_id(&db, &ticket_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?
+        .ok_or_else(|| (StatusCode::NOT_FOUND, "Ticket not found".to_string()))?;
+    crate::quotas::check_agent_run(&db, &ticket.organization).await?;
+
+    let agent_type = AgentType::Estimator;
+    let mut context = build_ticket_context(
+        &ticket.epic_id,
+        &ticket.slice_id,
+        &ticket_id,
+        ticket.title,
+        ticket.description.unwrap_or_default(),
+    );
+    context.checklist = crate::handlers::checklist_context(&db, &ticket_id).await;
+    context.estimation = estimation_context(&db, &agent_type, &ticket_id).await;
+
+    let working_dir = resolve_working_dir(&db, &agent_type, &ticket.organization)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to resolve working dir: {}", e)))?;
+    let env = crate::secrets::agent_env(&db, &ticket.organization, &agent_type).await;
+
+    let run = AgentExecutor::new(working_dir)
+        .with_env(env)
+        .execute(agent_type, context, None, None, None, None)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Agent execution failed: {}", e)))?;
+    crate::quotas::record_agent_run(&db, &ticket.organization, &run.usage).await;
+    if let Err(e) = store_agent_run(&db, &run).await {
+        tracing::warn!("Failed to store estimator run {}: {}", run.session_id, e);
+    }
+
+    let estimate = match (&run.status, run.output_summary.as_deref().and_then(Estimate::parse)) {
+        (AgentRunStatus::Completed, Some(estimate)) => estimate,
+        _ => return Err((StatusCode::BAD_GATEWAY, "Estimator produced no parseable estimate".to_string())),
+    };
+    store_estimate(&db, &ticket_id, &run.session_id, &estimate)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(Json(EstimateTicketResponse { session_id: run.session_id, estimate }))
+}
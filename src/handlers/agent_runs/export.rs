@@ -0,0 +1,314 @@
+//! Agent run transcript export: stored events rendered as a readable Markdown
+//! or HTML document, for attaching to tickets or sharing outside the app, or
+//! streamed raw as NDJSON for processing elsewhere.
+
+use axum::{
+    extract::{Path, Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+};
+use serde::Deserialize;
+use serde_json::Value;
+use sqlx::SqlitePool;
+use std::sync::Arc;
+
+/// Tool results longer than this are cut short in the transcript
+const MAX_TOOL_RESULT_CHARS: usize = 4000;
+
+#[derive(Debug, Deserialize)]
+pub struct ExportTranscriptQuery {
+    /// "md" (default), "html" or "ndjson"
+    pub format: Option<String>,
+    /// Leave out extended thinking
+    #[serde(default)]
+    pub exclude_thinking: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum TranscriptFormat {
+    Markdown,
+    Html,
+    /// The stored events, one per line
+    Ndjson,
+}
+
+impl TranscriptFormat {
+    fn parse(format: Option<&str>) -> Result<Self, String> {
+        match format.map(|f| f.to_ascii_lowercase()).as_deref() {
+            None | Some("md") | Some("markdown") => Ok(TranscriptFormat::Markdown),
+            Some("html") => Ok(TranscriptFormat::Html),
+            Some("ndjson") => Ok(TranscriptFormat::Ndjson),
+            Some(other) => Err(format!("Unsupported format '{}', expected md, html or ndjson", other)),
+        }
+    }
+}
+
+/// One piece of the transcript, in the order it happened
+#[derive(Debug, PartialEq)]
+enum Block {
+    Text(String),
+    Thinking(String),
+    Tool {
+        name: String,
+        input: Value,
+        result: Option<String>,
+        is_error: bool,
+    },
+    ApprovalRequired(String),
+}
+
+fn str_field(event: &Value, key: &str) -> String {
+    event.get(key).and_then(|v| v.as_str()).unwrap_or_default().to_string()
+}
+
+/// Build the transcript from stored event payloads. Tool results are attached
+/// to their call; deltas, status and result events are left out.
+fn transcript_blocks(events: &[Value], include_thinking: bool) -> Vec<Block> {
+    let mut blocks = Vec::new();
+    let mut tool_ids: Vec<(String, usize)> = Vec::new();
+    for event in events {
+        match event.get("type").and_then(|t| t.as_str()) {
+            Some("text") => {
+                let content = str_field(event, "content");
+                if !content.trim().is_empty() {
+                    blocks.push(Block::Text(content));
+                }
+            }
+            Some("thinking") if include_thinking => blocks.push(Block::Thinking(str_field(event, "content"))),
+            Some("tool_use") => {
+                tool_ids.push((str_field(event, "id"), blocks.len()));
+                blocks.push(Block::Tool {
+                    name: str_field(event, "name"),
+                    input: event.get("input").cloned().unwrap_or(Value::Null),
+                    result: None,
+                    is_error: false,
+                });
+            }
+            Some("tool_result") => {
+                let tool_use_id = str_field(event, "tool_use_id");
+                let idx = tool_ids.iter().find(|(id, _)| *id == tool_use_id).map(|(_, idx)| *idx);
+                if let Some(Block::Tool { result, is_error, .. }) = idx.and_then(|idx| blocks.get_mut(idx)) {
+                    *result = Some(str_field(event, "content"));
+                    *is_error = event.get("is_error").and_then(|e| e.as_bool()).unwrap_or(false);
+                }
+            }
+            Some("approval_required") => blocks.push(Block::ApprovalRequired(str_field(event, "tool_name"))),
+            _ => {}
+        }
+    }
+    blocks
+}
+
+fn truncate_result(result: &str) -> String {
+    let total = result.chars().count();
+    if total <= MAX_TOOL_RESULT_CHARS {
+        return result.to_string();
+    }
+    let kept: String = result.chars().take(MAX_TOOL_RESULT_CHARS).collect();
+    format!("{}\n… ({} more characters)", kept, total - MAX_TOOL_RESULT_CHARS)
+}
+
+fn pretty_input(input: &Value) -> String {
+    serde_json::to_string_pretty(input).unwrap_or_default()
+}
+
+/// A fenced code block whose fence is longer than any backtick run in `content`
+fn fence(content: &str, lang: &str) -> String {
+    let longest = content
+        .split(|c| c != '`')
+        .map(str::len)
+        .max()
+        .unwrap_or(0);
+    let ticks = "`".repeat(longest.max(2) + 1);
+    format!("{}{}\n{}\n{}", ticks, lang, content.trim_end(), ticks)
+}
+
+pub(crate) fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn run_details(run: &ticketing_system::AgentRun) -> Vec<(&'static str, String)> {
+    let mut details = vec![
+        ("Ticket", run.ticket_id.clone()),
+        ("Status", run.status.clone()),
+        ("Started", run.started_at.clone()),
+    ];
+    if let Some(completed_at) = &run.completed_at {
+        details.push(("Completed", completed_at.clone()));
+    }
+    details
+}
+
+fn render_markdown(run: &ticketing_system::AgentRun, blocks: &[Block]) -> String {
+    let mut out = vec![format!("# {} run {}", run.agent_type, run.session_id), String::new()];
+    out.extend(run_details(run).into_iter().map(|(label, value)| format!("- **{}:** {}", label, value)));
+    out.push(String::new());
+    out.push("<details>\n<summary>Prompt</summary>\n".to_string());
+    out.push(fence(&run.input_message, ""));
+    out.push("\n</details>\n".to_string());
+    out.push("## Transcript\n".to_string());
+
+    for block in blocks {
+        match block {
+            Block::Text(text) => out.push(format!("{}\n", text.trim())),
+            Block::Thinking(text) => {
+                out.push(format!("<details>\n<summary>Thinking</summary>\n\n{}\n\n</details>\n", text.trim()))
+            }
+            Block::Tool { name, input, result, is_error } => {
+                let summary = if *is_error { format!("{} (error)", name) } else { name.clone() };
+                out.push(format!("<details>\n<summary>Tool: {}</summary>\n", summary));
+                out.push(format!("**Input**\n\n{}\n", fence(&pretty_input(input), "json")));
+                if let Some(result) = result {
+                    out.push(format!("**Result**\n\n{}\n", fence(&truncate_result(result), "")));
+                }
+                out.push("</details>\n".to_string());
+            }
+            Block::ApprovalRequired(tool) => out.push(format!("> Waited for approval to use {}\n", tool)),
+        }
+    }
+    out.join("\n")
+}
+
+const HTML_STYLE: &str = "body{font-family:system-ui,sans-serif;max-width:50rem;margin:2rem auto;padding:0 1rem;line-height:1.5;color:#1f2328}\
+.text{white-space:pre-wrap;margin:1rem 0}\
+details{border:1px solid #d0d7de;border-radius:6px;padding:.5rem .75rem;margin:.75rem 0}\
+summary{cursor:pointer;font-weight:600}\
+pre{background:#f6f8fa;padding:.75rem;overflow-x:auto;white-space:pre-wrap}\
+.error summary{color:#cf222e}.thinking{color:#57606a}.note{color:#57606a;font-style:italic}";
+
+fn render_html(run: &ticketing_system::AgentRun, blocks: &[Block]) -> String {
+    let title = escape_html(&format!("{} run {}", run.agent_type, run.session_id));
+    let mut out = vec![
+        "<!DOCTYPE html>".to_string(),
+        format!("<html><head><meta charset=\"utf-8\"><title>{}</title><style>{}</style></head><body>", title, HTML_STYLE),
+        format!("<h1>{}</h1><ul>", title),
+    ];
+    out.extend(
+        run_details(run)
+            .into_iter()
+            .map(|(label, value)| format!("<li><strong>{}:</strong> {}</li>", label, escape_html(&value))),
+    );
+    out.push("</ul>".to_string());
+    out.push(format!(
+        "<details><summary>Prompt</summary><pre>{}</pre></details>",
+        escape_html(&run.input_message)
+    ));
+    out.push("<h2>Transcript</h2>".to_string());
+
+    for block in blocks {
+        match block {
+            Block::Text(text) => out.push(format!("<div class=\"text\">{}</div>", escape_html(text.trim()))),
+            Block::Thinking(text) => out.push(format!(
+                "<details class=\"thinking\"><summary>Thinking</summary><div class=\"text\">{}</div></details>",
+                escape_html(text.trim())
+            )),
+            Block::Tool { name, input, result, is_error } => {
+                let class = if *is_error { " class=\"error\"" } else { "" };
+                let suffix = if *is_error { " (error)" } else { "" };
+                let mut tool = format!(
+                    "<details{}><summary>Tool: {}{}</summary><p>Input</p><pre>{}</pre>",
+                    class,
+                    escape_html(name),
+                    suffix,
+                    escape_html(&pretty_input(input))
+                );
+                if let Some(result) = result {
+                    tool.push_str(&format!("<p>Result</p><pre>{}</pre>", escape_html(&truncate_result(result))));
+                }
+                tool.push_str("</details>");
+                out.push(tool);
+            }
+            Block::ApprovalRequired(tool) => out.push(format!(
+                "<p class=\"note\">Waited for approval to use {}</p>",
+                escape_html(tool)
+            )),
+        }
+    }
+    out.push("</body></html>".to_string());
+    out.join("\n")
+}
+
+/// GET /api/agent-runs/:session_id/export?format=md|html|ndjson&exclude_thinking=true
+/// `Accept: application/x-ndjson` selects ndjson when no format is given
+pub async fn export_agent_run(
+    Path(session_id): Path<String>,
+    State(db): State<Arc<SqlitePool>>,
+    Query(params): Query<ExportTranscriptQuery>,
+    headers: HeaderMap,
+) -> Result<Response, (StatusCode, String)> {
+    let format = match params.format.as_deref() {
+        None if crate::ndjson::wants_ndjson(&headers) => TranscriptFormat::Ndjson,
+        format => TranscriptFormat::parse(format).map_err(|e| (StatusCode::BAD_REQUEST, e))?,
+    };
+    let run = ticketing_system::agent_runs::get_agent_run(&db, &session_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?
+        .ok_or_else(|| (StatusCode::NOT_FOUND, "Agent run not found".to_string()))?;
+    let events: Vec<Value> = ticketing_system::agent_runs::get_events(&db, &session_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?
+        .into_iter()
+        .filter_map(|e| serde_json::from_str(&e.event_data).ok())
+        .collect();
+    if format == TranscriptFormat::Ndjson {
+        let events = events
+            .into_iter()
+            .filter(|e| !params.exclude_thinking || e.get("type").and_then(|t| t.as_str()) != Some("thinking"))
+            .collect();
+        return Ok(crate::ndjson::rows::<Value>(events));
+    }
+
+    let blocks = transcript_blocks(&events, !params.exclude_thinking);
+    let (body, content_type, extension) = match format {
+        TranscriptFormat::Markdown => (render_markdown(&run, &blocks), "text/markdown; charset=utf-8", "md"),
+        TranscriptFormat::Html => (render_html(&run, &blocks), "text/html; charset=utf-8", "html"),
+        TranscriptFormat::Ndjson => unreachable!("streamed above"),
+    };
+    let disposition = format!("inline; filename=\"agent-run-{}.{}\"", session_id, extension);
+    Ok((
+        [(header::CONTENT_TYPE, content_type.to_string()), (header::CONTENT_DISPOSITION, disposition)],
+        body,
+    )
+        .into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn attaches_results_to_tool_calls_and_drops_thinking_on_request() {
+        let events = [
+            json!({"type": "status", "status": "running"}),
+            json!({"type": "thinking", "content": "Look at the file first"}),
+            json!({"type": "tool_use", "id": "t1", "name": "Read", "input": {"path": "src/main.rs"}}),
+            json!({"type": "text_delta", "seq": 0, "content": "Do"}),
+            json!({"type": "tool_result", "tool_use_id": "t1", "content": "fn main() {}", "is_error": false}),
+            json!({"type": "text", "content": "Done."}),
+        ];
+        let blocks = transcript_blocks(&events, false);
+        assert_eq!(
+            blocks,
+            vec![
+                Block::Tool {
+                    name: "Read".to_string(),
+                    input: json!({"path": "src/main.rs"}),
+                    result: Some("fn main() {}".to_string()),
+                    is_error: false,
+                },
+                Block::Text("Done.".to_string()),
+            ]
+        );
+        assert_eq!(transcript_blocks(&events, true).len(), 3);
+    }
+
+    #[test]
+    fn fences_outlast_backticks_in_content() {
+        assert_eq!(fence("x", "json"), "```json\nx\n```");
+        assert_eq!(fence("a ```rust``` b", ""), "````\na ```rust``` b\n````");
+    }
+}
@@ -0,0 +1,164 @@
+//! Agent run feedback: per-run ratings and tags, aggregated per agent type and
+//! prompt version so prompt changes can be compared. The prompt version is the
+//! one recorded on the run when it started.
+
+use axum::{
+    extract::{Extension, Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+use ticketing_system::{AgentFeedback, CreateAgentFeedbackRequest, User};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FeedbackTag {
+    Hallucination,
+    Incomplete,
+    Great,
+}
+
+impl FeedbackTag {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FeedbackTag::Hallucination => "hallucination",
+            FeedbackTag::Incomplete => "incomplete",
+            FeedbackTag::Great => "great",
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AgentFeedbackRequest {
+    /// 1 (poor) to 5 (excellent)
+    pub rating: u8,
+    #[serde(default)]
+    pub tags: Vec<FeedbackTag>,
+    pub notes: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FeedbackStatsQuery {
+    pub agent_type: Option<String>,
+}
+
+/// Aggregate quality for one agent type at one prompt version
+#[derive(Debug, Serialize)]
+pub struct FeedbackStats {
+    pub agent_type: String,
+    pub prompt_version: Option<String>,
+    pub count: usize,
+    pub average_rating: f64,
+    /// Rating (1-5) → number of runs
+    pub ratings: BTreeMap<u8, usize>,
+    pub tags: BTreeMap<String, usize>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FeedbackStatsResponse {
+    pub stats: Vec<FeedbackStats>,
+}
+
+/// Group feedback by (agent_type, prompt_version), sorted by agent type then version.
+fn aggregate_feedback(feedback: &[AgentFeedback]) -> Vec<FeedbackStats> {
+    let mut groups: HashMap<(String, Option<String>), Vec<&AgentFeedback>> = HashMap::new();
+    for entry in feedback {
+        groups
+            .entry((entry.agent_type.clone(), entry.prompt_version.clone()))
+            .or_default()
+            .push(entry);
+    }
+
+    let mut stats: Vec<FeedbackStats> = groups
+        .into_iter()
+        .map(|((agent_type, prompt_version), entries)| {
+            let mut ratings = BTreeMap::new();
+            let mut tags = BTreeMap::new();
+            for entry in &entries {
+                *ratings.entry(entry.rating).or_insert(0) += 1;
+                for tag in &entry.tags {
+                    *tags.entry(tag.clone()).or_insert(0) += 1;
+                }
+            }
+            let total: u32 = entries.iter().map(|e| e.rating as u32).sum();
+            FeedbackStats {
+                agent_type,
+                prompt_version,
+                count: entries.len(),
+                average_rating: total as f64 / entries.len() as f64,
+                ratings,
+                tags,
+            }
+        })
+        .collect();
+
+    stats.sort_by(|a, b| (&a.agent_type, &a.prompt_version).cmp(&(&b.agent_type, &b.prompt_version)));
+    stats
+}
+
+/// POST /api/agent-runs/:session_id/feedback
+pub async fn submit_agent_feedback(
+    Path(session_id): Path<String>,
+    State(db): State<Arc<SqlitePool>>,
+    Extension(user): Extension<User>,
+    Json(req): Json<AgentFeedbackRequest>,
+) -> Result<(StatusCode, Json<AgentFeedback>), (StatusCode, String)> {
+    if !(1..=5).contains(&req.rating) {
+        return Err((StatusCode::BAD_REQUEST, "Rating must be between 1 and 5".to_string()));
+    }
+
+    let run = ticketing_system::agent_runs::get_agent_run(&db, &session_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?
+        .ok_or_else(|| (StatusCode::NOT_FOUND, "Agent run not found".to_string()))?;
+
+    let mut tags: Vec<String> = req.tags.iter().map(|t| t.as_str().to_string()).collect();
+    tags.sort();
+    tags.dedup();
+
+    let feedback = ticketing_system::agent_feedback::create_feedback(
+        &db,
+        CreateAgentFeedbackRequest {
+            session_id: session_id.clone(),
+            // The prompt the run executed with, not the current one
+            prompt_version: run.prompt_version,
+            agent_type: run.agent_type,
+            rating: req.rating,
+            tags,
+            notes: req.notes,
+            user_id: Some(user.user_id),
+        },
+    )
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to save feedback: {}", e)))?;
+
+    tracing::info!("Recorded feedback for agent run {}: rating {}", session_id, feedback.rating);
+    Ok((StatusCode::CREATED, Json(feedback)))
+}
+
+/// GET /api/agent-runs/:session_id/feedback
+pub async fn list_agent_feedback(
+    Path(session_id): Path<String>,
+    State(db): State<Arc<SqlitePool>>,
+) -> Result<Json<Vec<AgentFeedback>>, (StatusCode, String)> {
+    let feedback = ticketing_system::agent_feedback::list_feedback_for_run(&db, &session_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?;
+    Ok(Json(feedback))
+}
+
+/// GET /api/agent-feedback/stats
+pub async fn get_agent_feedback_stats(
+    State(db): State<Arc<SqlitePool>>,
+    Query(query): Query<FeedbackStatsQuery>,
+) -> Result<Json<FeedbackStatsResponse>, (StatusCode, String)> {
+    let feedback = ticketing_system::agent_feedback::list_feedback(&db, query.agent_type.as_deref())
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?;
+
+    Ok(Json(FeedbackStatsResponse { stats: aggregate_feedback(&feedback) }))
+}
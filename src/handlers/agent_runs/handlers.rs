@@ -12,15 +12,18 @@ use tokio::sync::mpsc;
 use sqlx::SqlitePool;
 
 use crate::agents::{
-    AgentExecutor, AgentRun, AgentRunsResponse, StreamEvent,
+    AgentExecutor, AgentRun, AgentRunsResponse, ContextSnapshot, StreamEvent,
     RunAgentRequest, RunAgentResponse, SendMessageRequest,
-    resolve_working_dir,
+    ToolApprovalDecision, PENDING_APPROVALS,
+    prompts::prompt_version, resolve_working_dir,
 };
+use crate::email_style::EmailStyle;
 use crate::pipeline_automation;
 use super::{
     artifacts::write_artifact,
     context::{build_ticket_context, gather_agent_context},
-    conversions::{db_run_to_api_run, store_agent_run},
+    conversions::{load_api_run, store_agent_run},
+    pins::pinned_outputs_context,
     sse_helpers::{create_sse_stream, create_reconnect_stream, create_error_stream},
 };
 
@@ -34,36 +37,53 @@ pub async fn run_agent(
         .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?
         .ok_or_else(|| (StatusCode::NOT_FOUND, "Ticket not found".to_string()))?;
+    crate::quotas::check_agent_run(&db, &ticket.organization).await?;
+    if let Some(pack_id) = &req.context_pack_id {
+        crate::handlers::require_context_pack(&db, &ticket.organization, pack_id).await?;
+    }
+    req.style.check()?;
 
-    let context = build_ticket_context(&epic_id, &slice_id, &ticket_id, ticket.title, ticket.description.clone().unwrap_or_default());
+    let research = crate::research_corpus::research_context(&db, &req.agent_type, &ticket).await;
+    let mut context = build_ticket_context(&epic_id, &slice_id, &ticket_id, ticket.title, ticket.description.clone().unwrap_or_default());
+    context.checklist = crate::handlers::checklist_context(&db, &ticket_id).await;
+    context.research = research;
+    context.estimation = crate::estimates::estimation_context(&db, &req.agent_type, &ticket_id).await;
+    context.email_templates = crate::handlers::email_templates_context(&db, &req.agent_type, &ticket.organization).await;
+    context.pinned_outputs = pinned_outputs_context(&db, &ticket_id).await;
 
-    let (previous_output, selected_context, sender_info, blocked_by_context) = gather_agent_context(
+    let (previous_output, selected_context, sender_info, blocked_by_context, related_work_context, context_pack) = gather_agent_context(
         &db,
         &req.agent_type,
         &ticket_id,
+        &ticket.organization,
         req.previous_session_id.as_deref(),
         &req.selected_session_ids,
         ticket.assignee.as_deref(),
+        req.context_pack_id.as_deref(),
     ).await;
 
-    // Combine blocked_by context with previous output if both exist
-    let combined_previous = match (blocked_by_context, previous_output) {
-        (Some(blocked), Some(prev)) => Some(format!("{}\n\n{}", blocked, prev)),
-        (Some(blocked), None) => Some(blocked),
-        (None, Some(prev)) => Some(prev),
-        (None, None) => None,
-    };
+    // Combine the context pack, blocked_by and related-work context with previous output
+    let (related_work_context, redactions) = related_work_context.unzip();
+    let combined_previous = [context_pack, blocked_by_context, related_work_context, previous_output]
+        .into_iter()
+        .flatten()
+        .reduce(|acc, part| format!("{}\n\n{}", acc, part));
 
     let working_dir = resolve_working_dir(&db, &req.agent_type, &ticket.organization)
         .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to resolve working dir: {}", e)))?;
-    let executor = AgentExecutor::new(working_dir);
+    let env = crate::secrets::agent_env(&db, &ticket.organization, &req.agent_type).await;
+    let executor = AgentExecutor::new(working_dir)
+        .with_env(env)
+        .with_prompt_vars(req.style.prompt_vars())
+        .with_redactions(redactions.unwrap_or_default());
 
     let agent_run = executor
         .execute(req.agent_type, context, combined_previous, selected_context, sender_info, None)
         .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Agent execution failed: {}", e)))?;
 
+    crate::quotas::record_agent_run(&db, &ticket.organization, &agent_run.usage).await;
     store_agent_run(&db, &agent_run)
         .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to store agent run: {}", e)))?;
@@ -79,6 +99,12 @@ pub async fn run_agent(
             ).await {
                 tracing::info!("Artifact written to {}", artifact_path);
             }
+            crate::research_corpus::capture_agent_output(
+                &db, &ticket_id, &agent_run.agent_type, &agent_run.session_id, output,
+            ).await;
+            crate::estimates::capture_agent_output(
+                &db, &ticket_id, &agent_run.agent_type, &agent_run.session_id, output,
+            ).await;
         }
     }
 
@@ -97,7 +123,10 @@ pub async fn list_agent_runs(
         .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to query agent runs: {}", e)))?;
 
-    let runs: Vec<AgentRun> = db_runs.into_iter().map(db_run_to_api_run).collect();
+    let mut runs: Vec<AgentRun> = Vec::with_capacity(db_runs.len());
+    for db_run in db_runs {
+        runs.push(load_api_run(&db, db_run).await);
+    }
     Ok(Json(AgentRunsResponse { runs }))
 }
 
@@ -111,7 +140,19 @@ pub async fn get_agent_run(
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?
         .ok_or_else(|| (StatusCode::NOT_FOUND, "Agent run not found".to_string()))?;
 
-    Ok(Json(db_run_to_api_run(db_run)))
+    Ok(Json(load_api_run(&db, db_run).await))
+}
+
+/// GET /api/agent-runs/:session_id/context
+pub async fn get_agent_run_context(
+    Path(session_id): Path<String>,
+    State(db): State<Arc<SqlitePool>>,
+) -> Result<Json<ContextSnapshot>, (StatusCode, String)> {
+    crate::agents::snapshot::load_snapshot(&db, &session_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to load context snapshot: {}", e)))?
+        .map(Json)
+        .ok_or_else(|| (StatusCode::NOT_FOUND, "No context snapshot for this run".to_string()))
 }
 
 /// POST /api/epics/:epic_id/slices/:slice_id/tickets/:ticket_id/agent-runs/stream
@@ -119,12 +160,19 @@ pub async fn stream_agent_run(
     Path((epic_id, slice_id, ticket_id)): Path<(String, String, String)>,
     State(db): State<Arc<SqlitePool>>,
     Json(req): Json<RunAgentRequest>,
-) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, (StatusCode, String)> {
     tracing::info!("=== STREAM_AGENT_RUN START ===");
     tracing::info!("Ticket: {}/{}/{}", epic_id, slice_id, ticket_id);
+    req.style.check()?;
 
     let (tx, rx) = mpsc::channel::<StreamEvent>(100);
     let ticket_result = ticketing_system::tickets::get_ticket_by_id(&db, &ticket_id).await;
+    if let Ok(Some(ref ticket)) = ticket_result {
+        crate::quotas::check_agent_run(&db, &ticket.organization).await?;
+        if let Some(pack_id) = &req.context_pack_id {
+            crate::handlers::require_context_pack(&db, &ticket.organization, pack_id).await?;
+        }
+    }
     let db_clone = db.clone();
 
     // Generate session_id upfront
@@ -140,6 +188,7 @@ pub async fn stream_agent_run(
             ticket_id: ticket_id.clone(),
             agent_type: req.agent_type.as_str().to_string(),
             input_message: ticket.description.clone().unwrap_or_default(),
+            prompt_version: prompt_version(req.agent_type.as_str()),
         };
         if let Err(e) = ticketing_system::agent_runs::create_agent_run(&db, create_req).await {
             tracing::error!("Failed to store running agent state: {}", e);
@@ -151,7 +200,8 @@ pub async fn stream_agent_run(
     // Spawn agent execution in background
     let custom_input_message = req.custom_input_message.clone();
     let step_id = req.step_id.clone();
-    tokio::spawn(async move {
+    let span = crate::logging::agent_run_span(&session_id, Some(&ticket_id));
+    crate::logging::spawn_in_span(span, async move {
         match ticket_result {
             Ok(Some(ticket)) => {
                 // If step_id is provided, transition the pipeline step to Running
@@ -165,7 +215,7 @@ pub async fn stream_agent_run(
                                         // Approve + start in one shot
                                         ticketing_system::pipelines::approve_step(&mut pipeline, sid);
                                         ticketing_system::pipelines::start_step(&mut pipeline, sid, &session_id_clone);
-                                        if let Err(e) = ticketing_system::tickets::update_ticket_pipeline(&db_clone, &ticket_id, Some(&pipeline)).await {
+                                        if let Err(e) = crate::pipeline_status::save_pipeline(&db_clone, &ticket_id, &pipeline).await {
                                             tracing::error!("Failed to transition step {} to running: {}", sid, e);
                                         } else {
                                             tracing::info!("Pipeline step {} transitioned AwaitingApproval → Running for ticket {}", sid, ticket_id);
@@ -173,7 +223,7 @@ pub async fn stream_agent_run(
                                     }
                                     ticketing_system::models::PipelineStepStatus::Queued => {
                                         ticketing_system::pipelines::start_step(&mut pipeline, sid, &session_id_clone);
-                                        if let Err(e) = ticketing_system::tickets::update_ticket_pipeline(&db_clone, &ticket_id, Some(&pipeline)).await {
+                                        if let Err(e) = crate::pipeline_status::save_pipeline(&db_clone, &ticket_id, &pipeline).await {
                                             tracing::error!("Failed to transition step {} to running: {}", sid, e);
                                         } else {
                                             tracing::info!("Pipeline step {} transitioned Queued → Running for ticket {}", sid, ticket_id);
@@ -215,9 +265,29 @@ pub async fn stream_agent_run(
                     ticket.description.clone().unwrap_or_default()
                 };
 
-                let context = build_ticket_context(
+                let research = crate::research_corpus::research_context(&db_clone, &req.agent_type, &ticket).await;
+                let step_inputs = step_id
+                    .as_deref()
+                    .map(|sid| crate::pipeline_automation::step_inputs_for(&ticket, sid))
+                    .unwrap_or_default();
+                let review_comments = step_id
+                    .as_deref()
+                    .and_then(|sid| ticket.pipeline.as_ref()?.steps.iter().find(|s| s.step_id == sid))
+                    .and_then(crate::pipeline_automation::step_comments_context);
+                // Outputs of the steps a pipeline step fans in from, as automation gives it
+                let fan_in = step_id
+                    .as_deref()
+                    .and_then(|sid| crate::pipeline_automation::step_fan_in(&ticket, sid));
+                let mut context = build_ticket_context(
                     &epic_id, &slice_id, &ticket_id, ticket.title, intent
                 );
+                context.checklist = crate::handlers::checklist_context(&db_clone, &ticket_id).await;
+                context.research = research;
+                context.step_inputs = crate::pipeline_automation::render_step_inputs(&step_inputs);
+                context.estimation = crate::estimates::estimation_context(&db_clone, &req.agent_type, &ticket_id).await;
+                context.email_templates =
+                    crate::handlers::email_templates_context(&db_clone, &req.agent_type, &ticket.organization).await;
+                context.pinned_outputs = pinned_outputs_context(&db_clone, &ticket_id).await;
 
                 let working_dir = match resolve_working_dir(&db_clone, &req.agent_type, &ticket.organization).await {
                     Ok(wd) => wd,
@@ -229,35 +299,66 @@ pub async fn stream_agent_run(
                         return;
                     }
                 };
-                let executor = AgentExecutor::new(working_dir);
+                let approval_tools = req.approval_tools.clone().unwrap_or_default();
+                let env = crate::secrets::agent_env(&db_clone, &ticket.organization, &req.agent_type).await;
+                let step_env = match &step_id {
+                    Some(sid) => crate::secrets::step_env(&db_clone, &ticket, sid).await,
+                    None => Ok(Default::default()),
+                };
+                let step_env = match step_env {
+                    Ok(step_env) => step_env,
+                    Err(e) => {
+                        let _ = tx.send(StreamEvent::Status {
+                            status: "failed".to_string(),
+                            message: Some(e),
+                        }).await;
+                        return;
+                    }
+                };
+                let mut prompt_vars = crate::pipeline_automation::step_input_vars(&step_inputs);
+                prompt_vars.extend(req.style.clone().or(EmailStyle::from_inputs(&step_inputs)).prompt_vars());
+                let executor = AgentExecutor::new(working_dir)
+                    .with_tool_approval(session_id_clone.clone(), approval_tools)
+                    .with_env(env)
+                    .with_step_env(step_env)
+                    .with_prompt_vars(prompt_vars);
 
                 let _ = tx.send(StreamEvent::Status {
                     status: "running".to_string(),
                     message: Some(format!("Agent started (session: {})", session_id_clone)),
                 }).await;
 
-                let (previous_output, selected_context, sender_info, blocked_by_context) = gather_agent_context(
+                let context_pack_id = req
+                    .context_pack_id
+                    .clone()
+                    .or_else(|| step_id.as_deref().and_then(|sid| crate::context_packs::step_context_pack(&ticket, sid)));
+                let (previous_output, selected_context, sender_info, blocked_by_context, related_work_context, context_pack) = gather_agent_context(
                     &db_clone,
                     &req.agent_type,
                     &ticket_id,
+                    &ticket.organization,
                     req.previous_session_id.as_deref(),
                     &req.selected_session_ids,
                     ticket.assignee.as_deref(),
+                    context_pack_id.as_deref(),
                 ).await;
 
-                // Combine blocked_by context with previous output if both exist
-                let combined_previous = match (blocked_by_context, previous_output) {
-                    (Some(blocked), Some(prev)) => Some(format!("{}\n\n{}", blocked, prev)),
-                    (Some(blocked), None) => Some(blocked),
-                    (None, Some(prev)) => Some(prev),
-                    (None, None) => None,
-                };
+                // Combine the context pack, review comments, blocked_by and related-work context with the
+                // fanned-in or previous output
+                let (related_work_context, redactions) = related_work_context.unzip();
+                let combined_previous =
+                    [context_pack, review_comments, blocked_by_context, related_work_context, fan_in.or(previous_output)]
+                        .into_iter()
+                        .flatten()
+                        .reduce(|acc, part| format!("{}\n\n{}", acc, part));
+                let executor = executor.with_redactions(redactions.unwrap_or_default());
 
                 let agent_type_for_error = req.agent_type.clone();
 
                 match executor.execute(req.agent_type, context, combined_previous, selected_context, sender_info, Some(tx.clone())).await {
                     Ok(mut agent_run) => {
                         agent_run.session_id = session_id_clone.clone();
+                        crate::quotas::record_agent_run(&db_clone, &ticket.organization, &agent_run.usage).await;
 
                         if let Err(e) = store_agent_run(&db_clone, &agent_run).await {
                             tracing::error!("Failed to store completed agent run: {}", e);
@@ -297,6 +398,12 @@ pub async fn stream_agent_run(
                                 ).await {
                                     tracing::info!("Artifact written to {}", artifact_path);
                                 }
+                                crate::research_corpus::capture_agent_output(
+                                    &db_clone, &ticket_id, &agent_run.agent_type, &agent_run.session_id, output,
+                                ).await;
+                                crate::estimates::capture_agent_output(
+                                    &db_clone, &ticket_id, &agent_run.agent_type, &agent_run.session_id, output,
+                                ).await;
                             }
                         }
 
@@ -320,6 +427,7 @@ pub async fn stream_agent_run(
                         };
 
                         let _ = ticketing_system::agent_runs::update_agent_run(&db_clone, &failed_run).await;
+                        crate::agents::failure::record_failure(&db_clone, &session_id_clone, &format!("{:#}", e)).await;
                         let _ = ticketing_system::ticket_history::log_agent_run_completed(
                             &db_clone, &ticket_id, &session_id_clone, agent_type_for_error.as_str(), "failed",
                         ).await;
@@ -363,7 +471,7 @@ pub async fn stream_agent_run(
     });
 
     let stream = create_sse_stream((*db).clone(), session_id, rx, 0);
-    Sse::new(stream).keep_alive(KeepAlive::default())
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
 }
 
 /// GET /api/epics/:epic_id/slices/:slice_id/tickets/:ticket_id/agent-runs/active
@@ -376,7 +484,7 @@ pub async fn get_active_agent_run(
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to query agent runs: {}", e)))?;
 
     match db_run {
-        Some(run) => Ok(Json(db_run_to_api_run(run))),
+        Some(run) => Ok(Json(load_api_run(&db, run).await)),
         None => Err((StatusCode::NOT_FOUND, "No active agent run".to_string())),
     }
 }
@@ -413,20 +521,25 @@ pub async fn send_message_to_agent(
     let session_id_clone = session_id.clone();
     let db_clone = db.clone();
 
-    tokio::spawn(async move {
+    let span = crate::logging::agent_run_span(&session_id, None);
+    crate::logging::spawn_in_span(span, async move {
         match ticketing_system::agent_runs::get_agent_run(&db_clone, &session_id_clone).await {
             Ok(Some(run)) => {
+                tracing::Span::current().record("ticket_id", run.ticket_id.as_str());
                 // Resolve working dir from the original agent run's context
-                let working_dir = if let Ok(Some(ticket)) = ticketing_system::tickets::get_ticket_by_id(&db_clone, &run.ticket_id).await {
+                let (working_dir, env) = if let Ok(Some(ticket)) = ticketing_system::tickets::get_ticket_by_id(&db_clone, &run.ticket_id).await {
                     if let Ok(agent_type) = serde_json::from_str::<crate::agents::AgentType>(&format!("\"{}\"", run.agent_type)) {
-                        resolve_working_dir(&db_clone, &agent_type, &ticket.organization).await.unwrap_or_else(|_| PathBuf::from("/Users/jarvisgpt/projects"))
+                        (
+                            resolve_working_dir(&db_clone, &agent_type, &ticket.organization).await.unwrap_or_else(|_| PathBuf::from("/Users/jarvisgpt/projects")),
+                            crate::secrets::agent_env(&db_clone, &ticket.organization, &agent_type).await,
+                        )
                     } else {
-                        PathBuf::from("/Users/jarvisgpt/projects")
+                        (PathBuf::from("/Users/jarvisgpt/projects"), Default::default())
                     }
                 } else {
-                    PathBuf::from("/Users/jarvisgpt/projects")
+                    (PathBuf::from("/Users/jarvisgpt/projects"), Default::default())
                 };
-                let executor = AgentExecutor::new(working_dir);
+                let executor = AgentExecutor::new(working_dir).with_env(env);
 
                 let _ = tx.send(StreamEvent::Status {
                     status: "running".to_string(),
@@ -472,3 +585,29 @@ pub async fn send_message_to_agent(
     let stream = create_sse_stream((*db).clone(), session_id, rx, initial_index);
     Sse::new(stream).keep_alive(KeepAlive::default())
 }
+
+#[derive(Debug, serde::Deserialize)]
+pub struct ApproveToolRequest {
+    pub request_id: String,
+    #[serde(flatten)]
+    pub decision: ToolApprovalDecision,
+}
+
+/// POST /api/agent-runs/:session_id/approve-tool
+pub async fn approve_tool_use(
+    Path(session_id): Path<String>,
+    Json(req): Json<ApproveToolRequest>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let approved = req.decision.approved;
+    if !PENDING_APPROVALS.resolve(&session_id, &req.request_id, req.decision).await {
+        return Err((StatusCode::NOT_FOUND, "No pending approval request".to_string()));
+    }
+
+    tracing::info!(
+        "Tool approval {} for session {} (request {})",
+        if approved { "granted" } else { "denied" },
+        session_id,
+        req.request_id
+    );
+    Ok(StatusCode::NO_CONTENT)
+}
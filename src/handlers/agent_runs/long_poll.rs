@@ -0,0 +1,134 @@
+//! Long-poll access to stored agent run events, for clients behind proxies
+//! that buffer SSE. Each request returns the events after `after`, waiting up
+//! to `wait` for new ones when there are none yet.
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::Instant;
+
+use super::sse_helpers::EVENTS_STORED;
+
+const DEFAULT_WAIT: Duration = Duration::from_secs(30);
+const MAX_WAIT: Duration = Duration::from_secs(60);
+
+/// Re-check the database at least this often, in case a wakeup was missed
+const RECHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Deserialize)]
+pub struct PollEventsQuery {
+    /// Return events with a higher index than this; omit to start from the beginning
+    pub after: Option<i32>,
+    /// How long to wait for new events, e.g. `30s`, `500ms` or `30` (seconds)
+    pub wait: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PolledEvent {
+    pub index: i32,
+    pub event_type: String,
+    pub data: serde_json::Value,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PollEventsResponse {
+    pub session_id: String,
+    pub events: Vec<PolledEvent>,
+    /// Pass as `after` on the next request
+    pub next_after: Option<i32>,
+    pub status: String,
+    /// True once the run has finished; `events` then holds everything left
+    pub done: bool,
+}
+
+/// Parse a wait duration: `30s`, `500ms` or bare seconds, capped at `MAX_WAIT`
+fn parse_wait(wait: &str) -> Option<Duration> {
+    let wait = wait.trim();
+    let duration = if let Some(ms) = wait.strip_suffix("ms") {
+        Duration::from_millis(ms.trim().parse().ok()?)
+    } else {
+        Duration::from_secs(wait.strip_suffix('s').unwrap_or(wait).trim().parse().ok()?)
+    };
+    Some(duration.min(MAX_WAIT))
+}
+
+/// GET /api/agent-runs/:session_id/events?after=<index>&wait=30s
+pub async fn poll_agent_run_events(
+    Path(session_id): Path<String>,
+    State(db): State<Arc<SqlitePool>>,
+    Query(params): Query<PollEventsQuery>,
+) -> Result<Json<PollEventsResponse>, (StatusCode, String)> {
+    let wait = match params.wait.as_deref() {
+        Some(wait) => parse_wait(wait)
+            .ok_or_else(|| (StatusCode::BAD_REQUEST, format!("Invalid wait '{}'", wait)))?,
+        None => DEFAULT_WAIT,
+    };
+    let after = params.after.unwrap_or(-1);
+    let deadline = Instant::now() + wait;
+
+    // Subscribe before the first read so an event stored in between still wakes us
+    let mut stored = EVENTS_STORED.subscribe();
+    loop {
+        let run = ticketing_system::agent_runs::get_agent_run(&db, &session_id)
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?
+            .ok_or_else(|| (StatusCode::NOT_FOUND, "Agent run not found".to_string()))?;
+        let events = ticketing_system::agent_runs::get_events(&db, &session_id)
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?;
+
+        // Events are stored with contiguous indexes starting at 0
+        let events: Vec<PolledEvent> = events
+            .into_iter()
+            .enumerate()
+            .map(|(i, e)| (i as i32, e))
+            .filter(|(index, _)| *index > after)
+            .map(|(index, e)| PolledEvent {
+                index,
+                event_type: e.event_type,
+                data: serde_json::from_str(&e.event_data).unwrap_or(serde_json::Value::String(e.event_data)),
+            })
+            .collect();
+
+        let finished = run.status != "running";
+        if !events.is_empty() || finished || Instant::now() >= deadline {
+            let next_after = events.last().map(|e| e.index).or(params.after);
+            return Ok(Json(PollEventsResponse {
+                session_id,
+                done: finished,
+                events,
+                next_after,
+                status: run.status,
+            }));
+        }
+
+        let until = deadline.min(Instant::now() + RECHECK_INTERVAL);
+        loop {
+            match tokio::time::timeout_at(until, stored.recv()).await {
+                Ok(Ok(id)) if id != session_id => continue,
+                // Our session, a lagged receiver, or the recheck interval: read again
+                _ => break,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_wait_durations() {
+        assert_eq!(parse_wait("30s"), Some(Duration::from_secs(30)));
+        assert_eq!(parse_wait("15"), Some(Duration::from_secs(15)));
+        assert_eq!(parse_wait("250ms"), Some(Duration::from_millis(250)));
+        assert_eq!(parse_wait("10m"), None);
+        assert_eq!(parse_wait("600s"), Some(MAX_WAIT));
+    }
+}
@@ -1,7 +1,23 @@
 mod artifacts;
 mod context;
 mod conversions;
+mod estimate;
+mod export;
+mod feedback;
 mod handlers;
+mod long_poll;
+mod pins;
 mod sse_helpers;
+mod variants;
 
 pub use handlers::*;
+pub use estimate::*;
+pub use export::*;
+pub use feedback::*;
+pub use long_poll::*;
+pub use pins::*;
+pub use variants::*;
+pub use sse_helpers::get_event_type;
+pub(crate) use sse_helpers::EVENTS_STORED;
+pub(crate) use artifacts::{validate_artifact_settings, ARTIFACT_FORMAT_MARKDOWN};
+pub(crate) use export::escape_html;
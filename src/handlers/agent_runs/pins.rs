@@ -0,0 +1,159 @@
+//! Pinned outputs: an agent run's output, or one section of it, marked as the
+//! ticket's canonical plan or spec. Pins are shown on the ticket and given to
+//! every later agent run on it, so runs don't need to select session ids.
+
+use axum::{
+    extract::{Extension, Path, State},
+    http::StatusCode,
+    Json,
+};
+use serde::Deserialize;
+use sqlx::SqlitePool;
+use std::sync::Arc;
+
+use ticketing_system::{CreateTicketPinRequest, TicketPin, User};
+
+#[derive(Debug, Deserialize)]
+pub struct PinAgentOutputRequest {
+    /// Heading of the section to pin; the whole output when absent
+    pub section: Option<String>,
+    /// What the pin is, e.g. `plan` or `spec`. Defaults to the agent type.
+    /// Pinning replaces the ticket's pin with the same label.
+    pub label: Option<String>,
+}
+
+/// The markdown section under `heading`, up to the next heading of the same or
+/// a higher level. Headings match case-insensitively, ignoring `#`s.
+fn extract_section(markdown: &str, heading: &str) -> Option<String> {
+    let heading_level = |line: &str| {
+        let hashes = line.chars().take_while(|c| *c == '#').count();
+        (hashes > 0 && line[hashes..].starts_with(' ')).then_some(hashes)
+    };
+    let wanted = heading.trim().trim_start_matches('#').trim();
+
+    let mut lines = markdown.lines();
+    let level = lines.by_ref().find_map(|line| {
+        let level = heading_level(line)?;
+        line[level..].trim().eq_ignore_ascii_case(wanted).then_some(level)
+    })?;
+    let section: Vec<&str> = lines
+        .take_while(|line| heading_level(line).is_none_or(|l| l > level))
+        .collect();
+    let section = section.join("\n").trim().to_string();
+    (!section.is_empty()).then_some(section)
+}
+
+/// POST /api/agent-runs/:session_id/pin
+pub async fn pin_agent_output(
+    Path(session_id): Path<String>,
+    State(db): State<Arc<SqlitePool>>,
+    Extension(user): Extension<User>,
+    Json(req): Json<PinAgentOutputRequest>,
+) -> Result<(StatusCode, Json<TicketPin>), (StatusCode, String)> {
+    let run = ticketing_system::agent_runs::get_agent_run(&db, &session_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?
+        .ok_or_else(|| (StatusCode::NOT_FOUND, "Agent run not found".to_string()))?;
+    let output = run
+        .output_summary
+        .filter(|o| !o.trim().is_empty())
+        .ok_or_else(|| (StatusCode::BAD_REQUEST, "Agent run has no output to pin".to_string()))?;
+    let content = match &req.section {
+        Some(section) => extract_section(&output, section)
+            .ok_or_else(|| (StatusCode::BAD_REQUEST, format!("No section '{}' in the output", section)))?,
+        None => output,
+    };
+    let label = req
+        .label
+        .map(|l| l.trim().to_string())
+        .filter(|l| !l.is_empty())
+        .unwrap_or_else(|| run.agent_type.clone());
+
+    let existing = ticketing_system::ticket_pins::list_pins(&db, &run.ticket_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?;
+    for pin in existing.iter().filter(|p| p.label == label) {
+        ticketing_system::ticket_pins::delete_pin(&db, &pin.pin_id)
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?;
+    }
+
+    let pin = ticketing_system::ticket_pins::create_pin(
+        &db,
+        CreateTicketPinRequest {
+            ticket_id: run.ticket_id,
+            session_id: session_id.clone(),
+            agent_type: run.agent_type,
+            label,
+            section: req.section,
+            content,
+            pinned_by: user.user_id,
+        },
+    )
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to pin output: {}", e)))?;
+
+    tracing::info!("Pinned output of agent run {} to ticket {} as {}", session_id, pin.ticket_id, pin.label);
+    Ok((StatusCode::CREATED, Json(pin)))
+}
+
+/// DELETE /api/agent-runs/:session_id/pin
+pub async fn unpin_agent_output(
+    Path(session_id): Path<String>,
+    State(db): State<Arc<SqlitePool>>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let removed = ticketing_system::ticket_pins::delete_pins_for_run(&db, &session_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?;
+    if removed == 0 {
+        return Err((StatusCode::NOT_FOUND, "Agent run output is not pinned".to_string()));
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// GET /api/tickets/:ticket_id/pins
+pub async fn list_ticket_pins(
+    Path(ticket_id): Path<String>,
+    State(db): State<Arc<SqlitePool>>,
+) -> Result<Json<Vec<TicketPin>>, (StatusCode, String)> {
+    let pins = ticketing_system::ticket_pins::list_pins(&db, &ticket_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?;
+    Ok(Json(pins))
+}
+
+/// The ticket's pinned outputs, for the context of every agent run on it
+pub async fn pinned_outputs_context(db: &SqlitePool, ticket_id: &str) -> Option<String> {
+    let pins = ticketing_system::ticket_pins::list_pins(db, ticket_id)
+        .await
+        .unwrap_or_else(|e| {
+            tracing::warn!("Failed to load pinned outputs for ticket {}: {}", ticket_id, e);
+            Vec::new()
+        });
+    if pins.is_empty() {
+        return None;
+    }
+    let sections: Vec<String> = pins
+        .iter()
+        .map(|p| format!("## {} (from the {} agent, session {})\n{}", p.label, p.agent_type, p.session_id, p.content))
+        .collect();
+    Some(format!(
+        "# Pinned Outputs\n\nThese were pinned to the ticket as its canonical plan or spec. Follow them.\n\n{}",
+        sections.join("\n\n---\n\n")
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extracts_a_section_until_the_next_peer_heading() {
+        let output = "# Plan\nintro\n## Steps\n1. a\n### Detail\nmore\n## Risks\nnone";
+        assert_eq!(extract_section(output, "steps").as_deref(), Some("1. a\n### Detail\nmore"));
+        assert_eq!(extract_section(output, "## Risks").as_deref(), Some("none"));
+        assert_eq!(extract_section(output, "Plan").as_deref(), Some(output.split_once('\n').unwrap().1));
+        assert_eq!(extract_section(output, "Missing"), None);
+        assert_eq!(extract_section("#hashtag\ntext", "hashtag"), None);
+    }
+}
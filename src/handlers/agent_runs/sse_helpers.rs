@@ -2,12 +2,17 @@ use axum::response::sse::Event;
 use futures::stream::Stream;
 use std::convert::Infallible;
 use sqlx::SqlitePool;
-use tokio::sync::mpsc;
+use tokio::sync::{broadcast, mpsc};
 use tokio_stream::wrappers::ReceiverStream;
 use async_stream::stream;
 
 use crate::agents::StreamEvent;
 
+lazy_static::lazy_static! {
+    /// Session ids whose stored events just changed; wakes long-poll waiters
+    pub static ref EVENTS_STORED: broadcast::Sender<String> = broadcast::channel(256).0;
+}
+
 /// Create an SSE stream from a channel receiver, storing events to database
 pub fn create_sse_stream(
     db: SqlitePool,
@@ -25,6 +30,10 @@ pub fn create_sse_stream(
             tracing::debug!("[STREAM] Received event #{}: {}", event_index, event_type);
 
             match serde_json::to_string(&event) {
+                // Deltas go to the live client only; the full text block is stored
+                Ok(json) if matches!(event, StreamEvent::TextDelta { .. }) => {
+                    yield Ok(Event::default().data(json));
+                }
                 Ok(json) => {
                     if let Err(e) = ticketing_system::agent_runs::store_event(
                         &db,
@@ -34,6 +43,11 @@ pub fn create_sse_stream(
                         &json,
                     ).await {
                         tracing::warn!("[STREAM] Failed to store event #{}: {}", event_index, e);
+                    } else {
+                        let _ = EVENTS_STORED.send(session_id.clone());
+                    }
+                    if let StreamEvent::ApprovalRequired { request_id, tool_name, .. } = &event {
+                        crate::user_events::tool_approval_required(&db, &session_id, request_id, tool_name).await;
                     }
                     event_index += 1;
                     yield Ok(Event::default().data(json));
@@ -127,11 +141,14 @@ pub fn create_error_stream(message: String) -> impl Stream<Item = Result<Event,
 pub fn get_event_type(event: &StreamEvent) -> &'static str {
     match event {
         StreamEvent::Text { .. } => "text",
+        StreamEvent::TextDelta { .. } => "text_delta",
         StreamEvent::ToolUse { .. } => "tool_use",
         StreamEvent::ToolResult { .. } => "tool_result",
         StreamEvent::Thinking { .. } => "thinking",
         StreamEvent::Status { .. } => "status",
         StreamEvent::Result { .. } => "result",
+        StreamEvent::ApprovalRequired { .. } => "approval_required",
+        StreamEvent::LoopWarning { .. } => "loop_warning",
         StreamEvent::ReplayComplete { .. } => "replay_complete",
     }
 }
@@ -0,0 +1,260 @@
+//! Email draft A/B generation: run the email agent several times against the
+//! same ticket context with different tone/length guidance and store each
+//! result as a sibling draft in one variant group.
+
+use axum::{
+    extract::{Extension, State},
+    http::StatusCode,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use ticketing_system::{drafts, CreateDraftRequest, EmailDraft, User};
+
+use crate::agents::{resolve_working_dir, AgentExecutor, AgentRunStatus, AgentType};
+use crate::email_style::EmailStyle;
+use crate::tenancy::Organization;
+use super::{
+    context::{build_ticket_context, gather_agent_context},
+    conversions::store_agent_run,
+};
+
+const DEFAULT_VARIANT_COUNT: usize = 3;
+const MAX_VARIANT_COUNT: usize = 5;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VariantStyle {
+    /// e.g. "formal", "friendly", "direct"
+    pub tone: String,
+    /// e.g. "short", "medium", "detailed"
+    pub length: String,
+}
+
+impl VariantStyle {
+    fn new(tone: &str, length: &str) -> Self {
+        Self { tone: tone.to_string(), length: length.to_string() }
+    }
+
+    fn email_style(&self, language: Option<&str>) -> EmailStyle {
+        EmailStyle::new(Some(&self.tone), Some(&self.length), language)
+    }
+
+    fn label(&self) -> String {
+        format!("{} / {}", self.tone, self.length)
+    }
+
+    fn guidance(&self) -> String {
+        format!(
+            "Write this version in a {} tone and keep it {}. Other versions of this email are being drafted \
+             with different styles, so commit fully to this one.",
+            self.tone, self.length
+        )
+    }
+}
+
+/// Styles used when the request doesn't specify any, in order
+fn default_styles() -> Vec<VariantStyle> {
+    vec![
+        VariantStyle::new("formal", "short"),
+        VariantStyle::new("friendly", "medium"),
+        VariantStyle::new("direct", "very short"),
+        VariantStyle::new("warm", "detailed"),
+        VariantStyle::new("neutral", "medium"),
+    ]
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GenerateVariantsRequest {
+    pub epic_id: String,
+    pub slice_id: String,
+    pub ticket_id: String,
+    /// Defaults to the requesting user's email
+    pub from_address: Option<String>,
+    #[serde(default)]
+    pub selected_session_ids: Vec<String>,
+    pub previous_session_id: Option<String>,
+    /// Number of variants when `styles` is omitted (default 3, max 5)
+    pub count: Option<usize>,
+    pub styles: Option<Vec<VariantStyle>>,
+    /// Language every variant is written in
+    pub language: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct GenerateVariantsResponse {
+    pub variant_group: String,
+    pub drafts: Vec<EmailDraft>,
+    /// Styles whose run failed or produced no parseable email
+    pub failed: Vec<VariantStyle>,
+}
+
+/// POST /api/drafts/generate-variants
+pub async fn generate_draft_variants(
+    State(db): State<Arc<SqlitePool>>,
+    Extension(user): Extension<User>,
+    Extension(org): Extension<Organization>,
+    Json(req): Json<GenerateVariantsRequest>,
+) -> Result<(StatusCode, Json<GenerateVariantsResponse>), (StatusCode, String)> {
+    let styles = match req.styles {
+        Some(styles) if styles.is_empty() || styles.len() > MAX_VARIANT_COUNT => {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                format!("styles must contain between 1 and {} entries", MAX_VARIANT_COUNT),
+            ));
+        }
+        Some(styles) => styles,
+        None => {
+            let count = req.count.unwrap_or(DEFAULT_VARIANT_COUNT);
+            if count == 0 || count > MAX_VARIANT_COUNT {
+                return Err((
+                    StatusCode::BAD_REQUEST,
+                    format!("count must be between 1 and {}", MAX_VARIANT_COUNT),
+                ));
+            }
+            default_styles().into_iter().take(count).collect()
+        }
+    };
+    let language = req.language.as_deref();
+    for style in &styles {
+        style.email_style(language).check()?;
+    }
+
+    let from_address = req
+        .from_address
+        .or(user.email.clone())
+        .ok_or_else(|| (StatusCode::BAD_REQUEST, "from_address is required".to_string()))?;
+    crate::handlers::require_email_permission(&db, &user, &from_address, crate::handlers::EmailPermission::Read).await?;
+
+    let ticket = ticketing_system::tickets::get_ticket_by_id(&db, &req.ticket_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?
+        .ok_or_else(|| (StatusCode::NOT_FOUND, "Ticket not found".to_string()))?;
+    org.authorize(&ticket.organization)?;
+    if ticket.epic_id != req.epic_id || ticket.slice_id != req.slice_id {
+        return Err((StatusCode::BAD_REQUEST, "epic_id and slice_id must be the ticket's own".to_string()));
+    }
+    crate::quotas::check_agent_run(&db, &ticket.organization).await?;
+
+    let mut context = build_ticket_context(
+        &req.epic_id,
+        &req.slice_id,
+        &req.ticket_id,
+        ticket.title,
+        ticket.description.clone().unwrap_or_default(),
+    );
+    context.email_templates =
+        crate::handlers::email_templates_context(&db, &AgentType::Email, &ticket.organization).await;
+    context.pinned_outputs = super::pins::pinned_outputs_context(&db, &req.ticket_id).await;
+    let (previous_output, selected_context, sender_info, _, _, _) = gather_agent_context(
+        &db,
+        &AgentType::Email,
+        &req.ticket_id,
+        &ticket.organization,
+        req.previous_session_id.as_deref(),
+        &req.selected_session_ids,
+        ticket.assignee.as_deref(),
+        None,
+    )
+    .await;
+
+    let working_dir = resolve_working_dir(&db, &AgentType::Email, &ticket.organization)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to resolve working dir: {}", e)))?;
+    let env = crate::secrets::agent_env(&db, &ticket.organization, &AgentType::Email).await;
+
+    // All variants see the same context; only the style guidance differs
+    let runs = futures::future::join_all(styles.iter().map(|style| {
+        let executor = AgentExecutor::new(working_dir.clone())
+            .with_env(env.clone())
+            .with_prompt_vars(
+                HashMap::from([("variant_guidance".to_string(), style.guidance())])
+                    .into_iter()
+                    .chain(EmailStyle::new(None, None, language).prompt_vars())
+                    .collect(),
+            );
+        let context = context.clone();
+        let previous_output = previous_output.clone();
+        let selected_context = selected_context.clone();
+        let sender_info = sender_info.clone();
+        async move {
+            executor
+                .execute(AgentType::Email, context, previous_output, selected_context, sender_info, None)
+                .await
+        }
+    }))
+    .await;
+
+    let variant_group = uuid::Uuid::new_v4().to_string();
+    let mut created = Vec::new();
+    let mut failed = Vec::new();
+
+    for (style, result) in styles.into_iter().zip(runs) {
+        let run = match result {
+            Ok(run) => run,
+            Err(e) => {
+                tracing::warn!("Email variant '{}' failed: {}", style.label(), e);
+                failed.push(style);
+                continue;
+            }
+        };
+        crate::quotas::record_agent_run(&db, &ticket.organization, &run.usage).await;
+        if let Err(e) = store_agent_run(&db, &run).await {
+            tracing::warn!("Failed to store email variant run {}: {}", run.session_id, e);
+        }
+
+        let email = match (&run.status, run.email_output) {
+            (AgentRunStatus::Completed, Some(email)) => email,
+            _ => {
+                tracing::warn!("Email variant '{}' produced no parseable email", style.label());
+                failed.push(style);
+                continue;
+            }
+        };
+
+        let draft = drafts::create_draft(
+            &db,
+            &CreateDraftRequest {
+                from_address: from_address.clone(),
+                to_address: email.to,
+                cc_address: email.cc,
+                subject: email.subject,
+                body: email.body,
+                ticket_id: Some(req.ticket_id.clone()),
+                epic_id: Some(req.epic_id.clone()),
+                slice_id: Some(req.slice_id.clone()),
+                variant_group: Some(variant_group.clone()),
+                variant_label: Some(style.label()),
+                tone: Some(style.tone.clone()),
+                length: Some(style.length.clone()),
+                language: req.language.clone(),
+            },
+        )
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        created.push(draft);
+    }
+
+    if created.is_empty() {
+        return Err((StatusCode::BAD_GATEWAY, "No email variants could be generated".to_string()));
+    }
+
+    if let Err(e) = ticketing_system::ticket_history::log_draft_created(
+        &db,
+        &req.ticket_id,
+        created[0].id,
+        &created[0].to_address,
+        &format!("{} ({} variants)", created[0].subject, created.len()),
+    )
+    .await
+    {
+        tracing::warn!("Failed to log draft variants to ticket history: {}", e);
+    }
+
+    Ok((
+        StatusCode::CREATED,
+        Json(GenerateVariantsResponse { variant_group, drafts: created, failed }),
+    ))
+}
@@ -0,0 +1,179 @@
+use axum::{
+    extract::{Extension, Query, State},
+    http::StatusCode,
+    Json,
+};
+use chrono::{Duration, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use ticketing_system::{analytics, SqlitePool};
+
+use crate::analytics::{median, refresh_rollups};
+use crate::tenancy::Organization;
+
+/// Default window when no range is given
+const DEFAULT_RANGE_DAYS: i64 = 30;
+
+/// Most days a refresh recomputes; earlier `from` days are clamped
+const MAX_REFRESH_DAYS: i64 = 90;
+
+#[derive(Debug, Deserialize)]
+pub struct AnalyticsRangeQuery {
+    /// Inclusive start day (YYYY-MM-DD)
+    pub from: Option<String>,
+    /// Inclusive end day (YYYY-MM-DD)
+    pub to: Option<String>,
+    /// Defaults to the request's organization; ignored by refresh
+    pub organization: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AgentTypeStats {
+    pub agent_type: String,
+    pub total: i64,
+    pub completed: i64,
+    pub failed: i64,
+    pub success_rate: f64,
+    pub failure_rate: f64,
+    pub median_duration_secs: Option<f64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct StepBottleneck {
+    pub step_id: String,
+    pub agent_type: String,
+    pub failures: i64,
+    pub rejections: i64,
+    pub retries: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PipelineAnalyticsResponse {
+    pub from: String,
+    pub to: String,
+    pub agents: Vec<AgentTypeStats>,
+    /// Steps ordered by failures + rejections + retries, worst first
+    pub steps: Vec<StepBottleneck>,
+}
+
+fn parse_range(query: &AnalyticsRangeQuery) -> Result<(NaiveDate, NaiveDate), (StatusCode, String)> {
+    let parse = |s: &str| {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d")
+            .map_err(|_| (StatusCode::BAD_REQUEST, format!("Invalid date '{}', expected YYYY-MM-DD", s)))
+    };
+    let to = match &query.to {
+        Some(s) => parse(s)?,
+        None => Utc::now().date_naive(),
+    };
+    let from = match &query.from {
+        Some(s) => parse(s)?,
+        None => to - Duration::days(DEFAULT_RANGE_DAYS - 1),
+    };
+    if from > to {
+        return Err((StatusCode::BAD_REQUEST, "from must not be after to".to_string()));
+    }
+    Ok((from, to))
+}
+
+/// Keep a refresh within `MAX_REFRESH_DAYS` ending no later than today
+fn clamp_refresh_range(from: NaiveDate, to: NaiveDate, today: NaiveDate) -> (NaiveDate, NaiveDate) {
+    let to = to.min(today);
+    (from.max(to - Duration::days(MAX_REFRESH_DAYS - 1)), to)
+}
+
+/// Agent success rates and step bottlenecks of an organization over a date
+/// range (GET /api/analytics/pipelines)
+pub async fn get_pipeline_analytics(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(org): Extension<Organization>,
+    Query(query): Query<AnalyticsRangeQuery>,
+) -> Result<Json<PipelineAnalyticsResponse>, (StatusCode, String)> {
+    let (from, to) = parse_range(&query)?;
+    let organization = org.resolve(query.organization)?;
+    let from_str = from.format("%Y-%m-%d").to_string();
+    let to_str = to.format("%Y-%m-%d").to_string();
+
+    let agent_rollups = analytics::list_agent_rollups(&pool, &organization, &from_str, &to_str)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let step_rollups = analytics::list_step_rollups(&pool, &organization, &from_str, &to_str)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    // (total, completed, failed, durations) per agent type
+    let mut by_agent: HashMap<String, (i64, i64, i64, Vec<f64>)> = HashMap::new();
+    for rollup in agent_rollups {
+        let entry = by_agent.entry(rollup.agent_type).or_default();
+        entry.0 += rollup.total;
+        entry.1 += rollup.completed;
+        entry.2 += rollup.failed;
+        entry.3.extend(rollup.durations_secs);
+    }
+    let mut agents: Vec<AgentTypeStats> = by_agent
+        .into_iter()
+        .map(|(agent_type, (total, completed, failed, mut durations))| {
+            let rate = |n: i64| if total > 0 { n as f64 / total as f64 } else { 0.0 };
+            AgentTypeStats {
+                agent_type,
+                total,
+                completed,
+                failed,
+                success_rate: rate(completed),
+                failure_rate: rate(failed),
+                median_duration_secs: median(&mut durations),
+            }
+        })
+        .collect();
+    agents.sort_by(|a, b| a.agent_type.cmp(&b.agent_type));
+
+    let mut by_step: HashMap<(String, String), StepBottleneck> = HashMap::new();
+    for rollup in step_rollups {
+        let entry = by_step
+            .entry((rollup.step_id.clone(), rollup.agent_type.clone()))
+            .or_insert_with(|| StepBottleneck {
+                step_id: rollup.step_id,
+                agent_type: rollup.agent_type,
+                failures: 0,
+                rejections: 0,
+                retries: 0,
+            });
+        entry.failures += rollup.failures;
+        entry.rejections += rollup.rejections;
+        entry.retries += rollup.retries;
+    }
+    let mut steps: Vec<StepBottleneck> = by_step.into_values().collect();
+    steps.sort_by_key(|s| std::cmp::Reverse(s.failures + s.rejections + s.retries));
+
+    Ok(Json(PipelineAnalyticsResponse { from: from_str, to: to_str, agents, steps }))
+}
+
+/// Recompute every organization's rollups for a date range, at most
+/// `MAX_REFRESH_DAYS` of it (POST /api/analytics/refresh). Instance admins only.
+pub async fn refresh_pipeline_analytics(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(org): Extension<Organization>,
+    Query(query): Query<AnalyticsRangeQuery>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    org.require_instance_admin()?;
+    let (from, to) = parse_range(&query)?;
+    let (from, to) = clamp_refresh_range(from, to, Utc::now().date_naive());
+    refresh_rollups(&pool, from, to)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clamps_refresh_ranges() {
+        let day = |s: &str| NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap();
+        let today = day("2026-06-30");
+        assert_eq!(clamp_refresh_range(day("2026-06-01"), day("2026-06-10"), today), (day("2026-06-01"), day("2026-06-10")));
+        assert_eq!(clamp_refresh_range(day("2000-01-01"), day("2026-06-30"), today), (day("2026-04-02"), today));
+        assert_eq!(clamp_refresh_range(day("2026-06-01"), day("2027-01-01"), today), (day("2026-06-01"), today));
+    }
+}
@@ -1,11 +1,20 @@
-//! Authentication handlers - register, login, logout, session check
+//! Authentication handlers - register, login, logout, session check, guest and organization invitations
 
+use std::net::SocketAddr;
 use std::sync::Arc;
-use axum::{extract::State, http::StatusCode, Json};
+use axum::{
+    extract::{ConnectInfo, Extension, Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    Json,
+};
+use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 use tower_cookies::{Cookie, Cookies};
 
-use ticketing_system::{LoginRequest, RegisterUserRequest, SqlitePool};
+use ticketing_system::{guests, login_attempts, LoginAttempt, LoginRequest, RegisterUserRequest, SqlitePool, User};
+
+use crate::login_security::{self, ClientInfo};
+use crate::tenancy::bootstrap_memberships;
 
 const SESSION_COOKIE: &str = "session";
 const MAX_AGE_SECS: i64 = 30 * 24 * 60 * 60; // 30 days
@@ -56,6 +65,12 @@ pub async fn register(
         }
     })?;
 
+    // The first account of a fresh instance administers its first organization;
+    // everyone after that joins an organization by invitation
+    if let Err(e) = bootstrap_memberships(&pool).await {
+        tracing::error!("Membership bootstrap error: {:?}", e);
+    }
+
     let session_id = ticketing_system::auth::create_session(&pool, &user.user_id)
         .await
         .map_err(|e| {
@@ -73,11 +88,38 @@ pub async fn register(
 }
 
 /// POST /api/auth/login
+///
+/// Records the attempt (see `crate::login_security`); a locked account
+/// answers 429 with `retry_after_secs` without checking the password.
 pub async fn login(
     State(pool): State<Arc<SqlitePool>>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
     cookies: Cookies,
     Json(req): Json<LoginRequest>,
 ) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    let security = login_security::load_config().unwrap_or_else(|e| {
+        tracing::warn!("Using default login security policy: {:#}", e);
+        Default::default()
+    });
+    let client = ClientInfo::from_request(&headers, peer, &security);
+
+    match login_security::check_lockout(&pool, &req.user_id, &security).await {
+        Ok(Some(until)) => {
+            login_security::record_locked(&pool, &req.user_id, &client).await;
+            let retry_after_secs = (until - chrono::Utc::now()).num_seconds().max(1);
+            return Err((
+                StatusCode::TOO_MANY_REQUESTS,
+                Json(json!({
+                    "error": "Too many failed sign-ins, try again later",
+                    "retry_after_secs": retry_after_secs,
+                })),
+            ));
+        }
+        Ok(None) => {}
+        Err(e) => tracing::warn!("Failed to check lockout for {}: {:#}", req.user_id, e),
+    }
+
     let user = ticketing_system::auth::authenticate(&pool, &req.user_id, &req.password)
         .await
         .map_err(|e| {
@@ -86,8 +128,10 @@ pub async fn login(
         })?;
 
     let Some(user) = user else {
+        login_security::record_failure(&pool, &req.user_id, &client, &security).await;
         return Err((StatusCode::UNAUTHORIZED, Json(json!({"error": "Invalid user_id or password"}))));
     };
+    login_security::record_success(&pool, &user.user_id, &client, &security).await;
 
     let session_id = ticketing_system::auth::create_session(&pool, &user.user_id)
         .await
@@ -139,9 +183,270 @@ pub async fn me(
         return Err((StatusCode::UNAUTHORIZED, Json(json!({"error": "Session expired or invalid"}))));
     };
 
+    let guest = guests::get_guest(&pool, &user.user_id)
+        .await
+        .map_err(|e| {
+            tracing::error!("Guest lookup error: {:?}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "Session validation failed"})))
+        })?
+        .is_some();
+
     Ok(Json(json!({
         "user_id": user.user_id,
         "name": user.name,
         "email": user.email,
+        "guest": guest,
+    })))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AuthActivityQuery {
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AuthActivityResponse {
+    /// Newest first
+    pub attempts: Vec<LoginAttempt>,
+    /// Set while failed sign-ins have the account locked
+    pub locked_until: Option<String>,
+}
+
+/// GET /api/auth/activity
+///
+/// The signed-in user's recent login attempts.
+pub async fn activity(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(user): Extension<User>,
+    Query(query): Query<AuthActivityQuery>,
+) -> Result<Json<AuthActivityResponse>, (StatusCode, Json<Value>)> {
+    let limit = query.limit.unwrap_or(50).clamp(1, 200);
+    let attempts = login_attempts::list_attempts(&pool, &user.user_id, limit)
+        .await
+        .map_err(|e| {
+            tracing::error!("Login activity lookup error: {:?}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "Failed to load login activity"})))
+        })?;
+    let security = login_security::load_config().unwrap_or_default();
+    let locked_until = login_security::check_lockout(&pool, &user.user_id, &security)
+        .await
+        .ok()
+        .flatten()
+        .map(|t| t.to_rfc3339());
+    Ok(Json(AuthActivityResponse { attempts, locked_until }))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AcceptGuestInvitationRequest {
+    pub user_id: String,
+    pub name: String,
+    pub password: String,
+}
+
+/// POST /api/auth/guest-invitations/:token/accept
+///
+/// Creates the guest's restricted account and signs them in.
+pub async fn accept_guest_invitation(
+    State(pool): State<Arc<SqlitePool>>,
+    cookies: Cookies,
+    Path(token): Path<String>,
+    Json(req): Json<AcceptGuestInvitationRequest>,
+) -> Result<(StatusCode, Json<Value>), (StatusCode, Json<Value>)> {
+    if req.user_id.trim().is_empty() || req.password.trim().is_empty() || req.name.trim().is_empty() {
+        return Err((StatusCode::BAD_REQUEST, Json(json!({"error": "user_id, name, and password are required"}))));
+    }
+
+    let invitation = guests::get_invitation(&pool, &token)
+        .await
+        .map_err(|e| {
+            tracing::error!("Invitation lookup error: {:?}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "Failed to load invitation"})))
+        })?
+        .ok_or_else(|| (StatusCode::NOT_FOUND, Json(json!({"error": "Invitation not found"}))))?;
+
+    let expired = chrono::DateTime::parse_from_rfc3339(&invitation.expires_at)
+        .map(|t| t < chrono::Utc::now())
+        .unwrap_or(true);
+    if invitation.accepted_by.is_some() || expired {
+        return Err((StatusCode::GONE, Json(json!({"error": "Invitation has expired or was already used"}))));
+    }
+
+    let user = ticketing_system::auth::register_user(
+        &pool,
+        &req.user_id,
+        &req.name,
+        &req.password,
+        Some(&invitation.email),
+    )
+    .await
+    .map_err(|e| {
+        let msg = e.to_string();
+        if msg.contains("already has an account") {
+            (StatusCode::CONFLICT, Json(json!({"error": msg})))
+        } else {
+            tracing::error!("Guest registration error: {:?}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "Registration failed"})))
+        }
+    })?;
+
+    guests::accept_invitation(&pool, &token, &user.user_id)
+        .await
+        .map_err(|e| {
+            tracing::error!("Invitation acceptance error: {:?}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "Failed to accept invitation"})))
+        })?;
+
+    let session_id = ticketing_system::auth::create_session(&pool, &user.user_id)
+        .await
+        .map_err(|e| {
+            tracing::error!("Session creation error: {:?}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "Failed to create session"})))
+        })?;
+
+    cookies.add(make_session_cookie(&session_id));
+
+    Ok((StatusCode::CREATED, Json(json!({
+        "user_id": user.user_id,
+        "name": user.name,
+        "email": user.email,
+        "guest": true,
+    }))))
+}
+
+fn invitation_error((status, message): (StatusCode, String)) -> (StatusCode, Json<Value>) {
+    (status, Json(json!({"error": message})))
+}
+
+/// GET /api/auth/invitations/:token
+///
+/// What the invitee is joining, for the onboarding page. `signed_in` tells it
+/// whether accepting will link the current account or needs a new one.
+pub async fn get_org_invitation(
+    State(pool): State<Arc<SqlitePool>>,
+    cookies: Cookies,
+    Path(token): Path<String>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    let invitation = super::org_invitations::load_invitation_for_token(&pool, &token)
+        .await
+        .map_err(invitation_error)?;
+    let signed_in = session_user(&pool, &cookies).await?.is_some();
+    Ok(Json(json!({
+        "organization": invitation.organization,
+        "email": invitation.email,
+        "role": invitation.role,
+        "invited_by": invitation.invited_by,
+        "expires_at": invitation.expires_at,
+        "signed_in": signed_in,
     })))
 }
+
+#[derive(Debug, Default, Deserialize)]
+pub struct AcceptOrgInvitationRequest {
+    /// Account to create; not needed when already signed in
+    pub user_id: Option<String>,
+    pub name: Option<String>,
+    pub password: Option<String>,
+}
+
+/// The user of the request's session cookie, if it has a valid one
+async fn session_user(
+    pool: &SqlitePool,
+    cookies: &Cookies,
+) -> Result<Option<ticketing_system::User>, (StatusCode, Json<Value>)> {
+    let Some(session_id) = cookies.get(SESSION_COOKIE).map(|c| c.value().to_string()) else {
+        return Ok(None);
+    };
+    ticketing_system::auth::validate_session(pool, &session_id)
+        .await
+        .map_err(|e| {
+            tracing::error!("Session validation error: {:?}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "Session validation failed"})))
+        })
+}
+
+/// Whether an account's email is the one an invitation was sent to
+fn invited_email_matches(account_email: Option<&str>, invited: &str) -> bool {
+    account_email.is_some_and(|email| email.trim().eq_ignore_ascii_case(invited.trim()))
+}
+
+/// POST /api/auth/invitations/:token/accept
+///
+/// Adds the signed-in user to the organization, or creates an account from
+/// `user_id`, `name` and `password` and signs it in. A signed-in user must
+/// have the email the invitation was sent to. The invitation is claimed and
+/// the membership added in one transaction, so a token is only honored once.
+pub async fn accept_org_invitation(
+    State(pool): State<Arc<SqlitePool>>,
+    cookies: Cookies,
+    Path(token): Path<String>,
+    body: Option<Json<AcceptOrgInvitationRequest>>,
+) -> Result<(StatusCode, Json<Value>), (StatusCode, Json<Value>)> {
+    let req = body.map(|Json(req)| req).unwrap_or_default();
+    let invitation = super::org_invitations::load_invitation_for_token(&pool, &token)
+        .await
+        .map_err(invitation_error)?;
+
+    let user = match session_user(&pool, &cookies).await? {
+        Some(user) => {
+            if !invited_email_matches(user.email.as_deref(), &invitation.email) {
+                return Err((
+                    StatusCode::FORBIDDEN,
+                    Json(json!({"error": "This invitation was sent to a different email address"})),
+                ));
+            }
+            user
+        }
+        None => {
+            let (Some(user_id), Some(name), Some(password)) = (&req.user_id, &req.name, &req.password) else {
+                return Err((StatusCode::BAD_REQUEST, Json(json!({"error": "user_id, name, and password are required"}))));
+            };
+            if user_id.trim().is_empty() || password.trim().is_empty() || name.trim().is_empty() {
+                return Err((StatusCode::BAD_REQUEST, Json(json!({"error": "user_id, name, and password are required"}))));
+            }
+            let user = ticketing_system::auth::register_user(&pool, user_id, name, password, Some(&invitation.email))
+                .await
+                .map_err(|e| {
+                    let msg = e.to_string();
+                    if msg.contains("already has an account") {
+                        (StatusCode::CONFLICT, Json(json!({"error": msg})))
+                    } else {
+                        tracing::error!("Invited user registration error: {:?}", e);
+                        (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "Registration failed"})))
+                    }
+                })?;
+            let session_id = ticketing_system::auth::create_session(&pool, &user.user_id)
+                .await
+                .map_err(|e| {
+                    tracing::error!("Session creation error: {:?}", e);
+                    (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "Failed to create session"})))
+                })?;
+            cookies.add(make_session_cookie(&session_id));
+            user
+        }
+    };
+
+    if guests::get_guest(&pool, &user.user_id).await.ok().flatten().is_some() {
+        return Err((StatusCode::CONFLICT, Json(json!({"error": "Guest accounts can't join an organization"}))));
+    }
+
+    // Claims the invitation only while it's still unused and unrevoked, and
+    // adds the membership in the same transaction
+    let claimed = ticketing_system::org_invitations::claim_invitation(&pool, &invitation, &user.user_id)
+        .await
+        .map_err(|e| {
+            tracing::error!("Invitation acceptance error: {:?}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "Failed to accept invitation"})))
+        })?;
+    if !claimed {
+        return Err((StatusCode::CONFLICT, Json(json!({"error": "Invitation was already used"}))));
+    }
+    tracing::info!("{} joined {} as {}", user.user_id, invitation.organization, invitation.role);
+
+    Ok((StatusCode::CREATED, Json(json!({
+        "user_id": user.user_id,
+        "name": user.name,
+        "email": user.email,
+        "organization": invitation.organization,
+        "role": invitation.role,
+    }))))
+}
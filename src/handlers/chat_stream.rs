@@ -6,28 +6,73 @@ use std::collections::HashMap;
 use std::path::PathBuf;
 use std::pin::Pin;
 use std::time::{Duration, Instant};
-use tokio::sync::mpsc;
-use tokio_stream::wrappers::ReceiverStream;
+use tokio::sync::{broadcast, mpsc, RwLock};
+use tokio_stream::wrappers::UnboundedReceiverStream;
 use async_stream::stream;
 use sqlx::SqlitePool;
 use cc_sdk::{query, ClaudeCodeOptions, Message, ContentBlock, ToolsConfig};
 use futures::StreamExt;
-use ticketing_system::{conversations, checkpoints, AddMessageRequest, ToolUse, UpdateConversationRequest};
+use ticketing_system::{conversations, checkpoints, AddMessageRequest, ConversationMessage, ToolUse, UpdateConversationRequest};
 
-use crate::agents::{AgentType, StreamEvent};
+use crate::agents::{deltas::TextDeltas, AgentType, StreamEvent, ToolCall};
+use crate::agents::tool_audit::{store_standalone_tool_calls, ToolCallLog};
 use crate::agents::prompts::load_prompt;
+use super::agent_runs::get_event_type;
+use crate::user_events::{self, Subject, UserEvent};
 
 /// How often to flush accumulated content to the database (ms)
 const DB_FLUSH_INTERVAL_MS: u64 = 2000;
 
 pub type SseStream = Sse<Pin<Box<dyn Stream<Item = Result<Event, Infallible>> + Send>>>;
 
+/// Live event fan-out for generations that are still running, keyed by
+/// conversation_id. Each event carries its persisted index so reconnecting
+/// clients can de-duplicate against the replayed history.
+pub struct ChatStreams {
+    pub channels: RwLock<HashMap<String, broadcast::Sender<(i32, StreamEvent)>>>,
+}
+
+lazy_static::lazy_static! {
+    pub static ref CHAT_STREAMS: ChatStreams = ChatStreams {
+        channels: RwLock::new(HashMap::new()),
+    };
+}
+
+/// Buffered live events per conversation before slow subscribers lag
+const LIVE_CHANNEL_CAPACITY: usize = 256;
+
+impl ChatStreams {
+    /// Register a live channel for a conversation, unless a generation already
+    /// has one. Checking and registering under one lock keeps two requests from
+    /// both starting a generation.
+    pub async fn claim(&self, conversation_id: &str) -> Option<broadcast::Sender<(i32, StreamEvent)>> {
+        let mut channels = self.channels.write().await;
+        if channels.contains_key(conversation_id) {
+            return None;
+        }
+        let (live_tx, _) = broadcast::channel(LIVE_CHANNEL_CAPACITY);
+        channels.insert(conversation_id.to_string(), live_tx.clone());
+        Some(live_tx)
+    }
+
+    /// Drop a claimed channel whose generation never started
+    pub async fn release(&self, conversation_id: &str) {
+        self.channels.write().await.remove(conversation_id);
+    }
+}
+
 /// Configuration for a chat SSE endpoint
 pub struct ChatConfig {
     pub agent_type: AgentType,
     pub prompt_name: &'static str,
     pub working_dir: PathBuf,
     pub prompt_vars: HashMap<String, String>,
+    /// Extra environment for the agent process, inherited by its MCP servers
+    pub env: HashMap<String, String>,
+    /// Directories outside `working_dir` the agent may read, e.g. conversation attachments
+    pub add_dirs: Vec<PathBuf>,
+    /// Organization the chat's tool calls are audited under; None for personal chats
+    pub organization: Option<String>,
 }
 
 /// Start a new chat session via SSE
@@ -37,14 +82,45 @@ pub fn chat(
     session_id: Option<String>,
     conversation_id: Option<String>,
     config: ChatConfig,
+) -> SseStream {
+    start_chat(db, message, session_id, conversation_id, config, None)
+}
+
+/// Start a new session for a conversation whose live channel the caller
+/// already claimed (see `ChatStreams::claim`)
+pub fn chat_claimed(
+    db: Arc<SqlitePool>,
+    message: String,
+    conversation_id: String,
+    config: ChatConfig,
+    live_tx: broadcast::Sender<(i32, StreamEvent)>,
+) -> SseStream {
+    start_chat(db, message, None, Some(conversation_id), config, Some(live_tx))
+}
+
+fn start_chat(
+    db: Arc<SqlitePool>,
+    message: String,
+    session_id: Option<String>,
+    conversation_id: Option<String>,
+    config: ChatConfig,
+    claimed: Option<broadcast::Sender<(i32, StreamEvent)>>,
 ) -> SseStream {
     let (tx, rx) = mpsc::channel::<StreamEvent>(100);
+    let db_for_events = db.clone();
+    let conversation_id_for_events = conversation_id.clone();
 
     let session_id = session_id.unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
     let session_id_clone = session_id.clone();
 
     tokio::spawn(async move {
         tracing::info!("[CHAT] Background task started for {} session: {}", config.prompt_name, session_id_clone);
+        // Recorded so edits and recovery continue with the same agent
+        if let Some(conv_id) = &conversation_id {
+            if let Err(e) = conversations::set_agent(&db, conv_id, config.prompt_name).await {
+                tracing::warn!("[CHAT] Failed to record agent for conversation {}: {}", conv_id, e);
+            }
+        }
 
         let system_prompt = match load_prompt(config.prompt_name, config.prompt_vars) {
             Ok(prompt) => prompt,
@@ -64,26 +140,29 @@ pub fn chat(
             .map(|s| s.to_string())
             .collect();
 
-        let options = ClaudeCodeOptions::builder()
+        let mut options = ClaudeCodeOptions::builder()
             .system_prompt(&system_prompt)
             .model(config.agent_type.model())
             .tools(ToolsConfig::list(tools_list.clone()))
             .allowed_tools(tools_list)
             .cwd(&config.working_dir)
             .build();
+        options.env.extend(config.env);
+        options.add_dirs.extend(config.add_dirs);
 
         let _ = tx.send(StreamEvent::Status {
             status: "running".to_string(),
             message: Some(format!("Session: {}", session_id_clone)),
         }).await;
 
-        run_stream(
+        let (session_id, calls) = run_stream(
             &db, tx, &message, options,
             conversation_id.as_deref(), None,
         ).await;
+        store_standalone_tool_calls(&db, session_id.as_deref(), config.organization.as_deref(), &config.agent_type, &calls).await;
     });
 
-    create_sse_stream(rx)
+    create_sse_stream(detach(db_for_events, conversation_id_for_events, rx, None, claimed))
 }
 
 /// Resume an existing chat session via SSE
@@ -95,6 +174,8 @@ pub fn resume(
     config: ChatConfig,
 ) -> SseStream {
     let (tx, rx) = mpsc::channel::<StreamEvent>(100);
+    let db_for_events = db.clone();
+    let conversation_id_for_events = conversation_id.clone();
 
     let session_id_clone = session_id.clone();
 
@@ -107,36 +188,141 @@ pub fn resume(
             .map(|s| s.to_string())
             .collect();
 
-        let options = ClaudeCodeOptions::builder()
+        let mut options = ClaudeCodeOptions::builder()
             .resume(session_id_clone.clone())
             .tools(ToolsConfig::list(tools_list.clone()))
             .allowed_tools(tools_list)
             .cwd(&config.working_dir)
             .build();
+        options.env.extend(config.env);
+        options.add_dirs.extend(config.add_dirs);
 
         let _ = tx.send(StreamEvent::Status {
             status: "running".to_string(),
             message: Some("Resuming conversation...".to_string()),
         }).await;
 
-        run_stream(
+        let (session_id, calls) = run_stream(
             &db, tx, &message, options,
             conversation_id.as_deref(), Some(&session_id_clone),
         ).await;
+        store_standalone_tool_calls(&db, session_id.as_deref(), config.organization.as_deref(), &config.agent_type, &calls).await;
     });
 
-    create_sse_stream(rx)
+    create_sse_stream(detach(db_for_events, conversation_id_for_events, rx, None, None))
 }
 
-/// Core streaming logic shared between chat and resume
+/// Continue a generation interrupted by a server stop by resuming its session.
+///
+/// The client first gets `replayed` (the interrupted generation's stored
+/// events) and a `replay_complete` marker, then the continuation live. New
+/// events are stored after the replayed ones, so a later reconnect sees both.
+/// `live_tx` is the conversation's channel, claimed with `ChatStreams::claim`.
+#[allow(clippy::too_many_arguments)]
+pub fn recover(
+    db: Arc<SqlitePool>,
+    message: String,
+    session_id: String,
+    conversation_id: String,
+    replayed: Vec<String>,
+    next_event_index: i32,
+    config: ChatConfig,
+    live_tx: broadcast::Sender<(i32, StreamEvent)>,
+) -> SseStream {
+    let (tx, rx) = mpsc::channel::<StreamEvent>(100);
+    let db_for_events = db.clone();
+    let conversation_id_for_events = conversation_id.clone();
+
+    tokio::spawn(async move {
+        tracing::info!("[RECOVER] Resuming {} session {} for conversation {}", config.prompt_name, session_id, conversation_id);
+
+        let tools_list: Vec<String> = config.agent_type
+            .allowed_tools()
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+
+        let mut options = ClaudeCodeOptions::builder()
+            .resume(session_id.clone())
+            .tools(ToolsConfig::list(tools_list.clone()))
+            .allowed_tools(tools_list)
+            .cwd(&config.working_dir)
+            .build();
+        options.env.extend(config.env);
+        options.add_dirs.extend(config.add_dirs);
+
+        let _ = tx.send(StreamEvent::Status {
+            status: "running".to_string(),
+            message: Some("Recovering interrupted reply...".to_string()),
+        }).await;
+
+        let (session_id, calls) = run_stream(
+            &db, tx, &message, options,
+            Some(&conversation_id), Some(&session_id),
+        ).await;
+        store_standalone_tool_calls(&db, session_id.as_deref(), config.organization.as_deref(), &config.agent_type, &calls).await;
+    });
+
+    let live = detach(db_for_events, Some(conversation_id_for_events), rx, Some(next_event_index), Some(live_tx));
+    let stream = stream! {
+        let total_events = replayed.len();
+        for json in replayed {
+            yield Ok(Event::default().data(json));
+        }
+        let replay_complete = StreamEvent::ReplayComplete { total_events, agent_status: "running".to_string() };
+        if let Ok(json) = serde_json::to_string(&replay_complete) {
+            yield Ok(Event::default().data(json));
+        }
+        let mut live = UnboundedReceiverStream::new(live);
+        while let Some(event) = futures::StreamExt::next(&mut live).await {
+            if let Ok(json) = serde_json::to_string(&event) {
+                yield Ok(Event::default().data(json));
+            }
+        }
+    };
+    Sse::new(Box::pin(stream) as Pin<Box<dyn Stream<Item = Result<Event, Infallible>> + Send>>)
+        .keep_alive(KeepAlive::default())
+}
+
+/// First message of a session regenerating from an edited message. The old
+/// session can't be resumed part way, so the conversation up to the edit is
+/// replayed as a transcript ahead of the edited message.
+pub fn replay_prompt(history: &[ConversationMessage], message: &str) -> String {
+    let turns = transcript(history);
+    if turns.is_empty() {
+        return message.to_string();
+    }
+    format!("[Conversation so far]\n\n{}\n---\n\n{}", turns, message)
+}
+
+/// Messages as `User: ...` / `Assistant: ...` turns, skipping empty ones
+pub fn transcript(messages: &[ConversationMessage]) -> String {
+    messages
+        .iter()
+        .filter(|m| !m.content.trim().is_empty())
+        .map(|m| {
+            let speaker = if m.role == "user" { "User" } else { "Assistant" };
+            format!("{}: {}", speaker, m.content.trim())
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+/// Core streaming logic shared between chat and resume. Returns the session's
+/// id, if known, and the tool calls the agent made, for the caller to audit.
 async fn run_stream(
     db: &SqlitePool,
     tx: mpsc::Sender<StreamEvent>,
     message: &str,
-    options: ClaudeCodeOptions,
+    mut options: ClaudeCodeOptions,
     conversation_id: Option<&str>,
     known_session_id: Option<&str>,
-) {
+) -> (Option<String>, Vec<ToolCall>) {
+    options.include_partial_messages = true;
+    let env = options.env.clone();
+    let mut tool_log = ToolCallLog::new(&env);
+    let mut captured_session_id: Option<String> = known_session_id.map(|s| s.to_string());
+
     // Create initial checkpoint
     if let Some(conv_id) = conversation_id {
         let checkpoint_session = known_session_id.unwrap_or("pending");
@@ -176,12 +362,34 @@ async fn run_stream(
             let mut last_flush = Instant::now();
             let flush_interval = Duration::from_millis(DB_FLUSH_INTERVAL_MS);
             let mut tool_call_count: i32 = 0;
-            let captured_session_id: Option<String> = known_session_id.map(|s| s.to_string());
+            let no_secrets = HashMap::new();
+            let mut deltas = TextDeltas::new(&no_secrets);
 
             while let Some(message_result) = stream.next().await {
                 message_count += 1;
                 match message_result {
                     Ok(message) => {
+                        tool_log.observe(&message);
+
+                        // A new session's id arrives with its init message; checkpoint it
+                        // right away so the session can be resumed if the server stops
+                        if let Message::System { data, .. } = &message {
+                            let sess_id = data.get("session_id").and_then(|v| v.as_str());
+                            if let (None, Some(sess_id), Some(conv_id)) = (&captured_session_id, sess_id, conversation_id) {
+                                captured_session_id = Some(sess_id.to_string());
+                                if let Err(e) = checkpoints::upsert_checkpoint(db, conv_id, sess_id, tool_call_count).await {
+                                    tracing::warn!("[STREAM] Failed to checkpoint session id: {}", e);
+                                }
+                            }
+                        }
+
+                        if let Message::StreamEvent { event, .. } = &message {
+                            if let Some(delta) = deltas.push(event) {
+                                let _ = tx.send(delta).await;
+                            }
+                            continue;
+                        }
+
                         if let Message::Assistant { message: assistant_msg } = &message {
                             for block in &assistant_msg.content {
                                 match block {
@@ -250,6 +458,7 @@ async fn run_stream(
                         // Check for result message
                         if let Message::Result { session_id: sess_id, is_error, subtype, .. } = &message {
                             tracing::info!("[STREAM] Result: subtype={}, is_error={}", subtype, is_error);
+                            captured_session_id = Some(sess_id.clone());
 
                             let _ = tx.send(StreamEvent::Result {
                                 session_id: sess_id.clone(),
@@ -291,6 +500,14 @@ async fn run_stream(
 
             // Final flush to DB
             flush_to_db(db, assistant_message_id.as_deref(), &accumulated_text, &accumulated_tool_uses).await;
+            if let (Some(conv_id), Some(message_id)) = (conversation_id, &assistant_message_id) {
+                let event = UserEvent::ConversationMessage {
+                    conversation_id: conv_id.to_string(),
+                    message_id: message_id.clone(),
+                    role: "assistant".to_string(),
+                };
+                user_events::publish(db, Subject::Conversation, conv_id, None, event).await;
+            }
 
             tracing::info!("[STREAM] Stream ended after {} messages", message_count);
             let _ = tx.send(StreamEvent::Status {
@@ -306,6 +523,7 @@ async fn run_stream(
             }).await;
         }
     }
+    (captured_session_id, tool_log.into_calls())
 }
 
 /// Flush accumulated content to the database
@@ -332,10 +550,91 @@ async fn flush_to_db(
     }
 }
 
+/// Decouple generation from the HTTP connection.
+///
+/// Drains the generator's channel in its own task, persisting every event for
+/// the conversation and publishing it to `CHAT_STREAMS`, then forwards to the
+/// original client over an unbounded channel. If the client disconnects the
+/// generator keeps running and can be re-attached via
+/// `GET /api/conversations/:id/stream`.
+///
+/// `continue_from` appends to the stored events from that index instead of
+/// replacing them, for a generation that carries on an interrupted one.
+/// `claimed` is a live channel the caller already registered.
+fn detach(
+    db: Arc<SqlitePool>,
+    conversation_id: Option<String>,
+    mut rx: mpsc::Receiver<StreamEvent>,
+    continue_from: Option<i32>,
+    claimed: Option<broadcast::Sender<(i32, StreamEvent)>>,
+) -> mpsc::UnboundedReceiver<StreamEvent> {
+    let (client_tx, client_rx) = mpsc::unbounded_channel::<StreamEvent>();
+
+    tokio::spawn(async move {
+        let live_tx = match &conversation_id {
+            Some(conv_id) => {
+                // Events from the previous generation are superseded
+                if continue_from.is_none() {
+                    if let Err(e) = conversations::clear_events(&db, conv_id).await {
+                        tracing::warn!("[STREAM] Failed to clear stream events for {}: {}", conv_id, e);
+                    }
+                }
+                let live_tx = match claimed {
+                    Some(live_tx) => live_tx,
+                    None => {
+                        let (live_tx, _) = broadcast::channel(LIVE_CHANNEL_CAPACITY);
+                        CHAT_STREAMS.channels.write().await.insert(conv_id.clone(), live_tx.clone());
+                        live_tx
+                    }
+                };
+                Some(live_tx)
+            }
+            None => None,
+        };
+
+        let mut event_index: i32 = continue_from.unwrap_or(0);
+        let mut client_attached = true;
+
+        while let Some(event) = rx.recv().await {
+            // Deltas aren't stored and share the index of the next stored event,
+            // so a replaying subscriber only gets the ones after what it replayed
+            let is_delta = matches!(event, StreamEvent::TextDelta { .. });
+            if let Some(conv_id) = conversation_id.as_ref().filter(|_| !is_delta) {
+                match serde_json::to_string(&event) {
+                    Ok(json) => {
+                        if let Err(e) = conversations::store_event(&db, conv_id, event_index, get_event_type(&event), &json).await {
+                            tracing::warn!("[STREAM] Failed to store event #{} for {}: {}", event_index, conv_id, e);
+                        }
+                    }
+                    Err(e) => tracing::error!("[STREAM] Failed to serialize event: {}", e),
+                }
+            }
+
+            if let Some(live_tx) = &live_tx {
+                let _ = live_tx.send((event_index, event.clone()));
+            }
+            if !is_delta {
+                event_index += 1;
+            }
+
+            if client_attached && client_tx.send(event).is_err() {
+                tracing::info!("[STREAM] Client detached, continuing generation in background");
+                client_attached = false;
+            }
+        }
+
+        if let Some(conv_id) = &conversation_id {
+            CHAT_STREAMS.channels.write().await.remove(conv_id);
+        }
+    });
+
+    client_rx
+}
+
 /// Create SSE stream from receiver
-pub fn create_sse_stream(rx: mpsc::Receiver<StreamEvent>) -> SseStream {
+pub fn create_sse_stream(rx: mpsc::UnboundedReceiver<StreamEvent>) -> SseStream {
     let stream = stream! {
-        let mut rx = ReceiverStream::new(rx);
+        let mut rx = UnboundedReceiverStream::new(rx);
         while let Some(event) = futures::StreamExt::next(&mut rx).await {
             if let Ok(json) = serde_json::to_string(&event) {
                 yield Ok(Event::default().data(json));
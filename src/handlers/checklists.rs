@@ -0,0 +1,169 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::sync::Arc;
+use ticketing_system::{checklists, ChecklistItem, SqlitePool};
+
+#[derive(Debug, Deserialize)]
+pub struct CreateChecklistItemRequest {
+    pub text: String,
+    /// Insert position (0-based); appended when omitted
+    pub position: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateChecklistItemRequest {
+    pub text: Option<String>,
+    pub completed: Option<bool>,
+    pub position: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ChecklistResponse {
+    pub ticket_id: String,
+    pub items: Vec<ChecklistItem>,
+    pub completed: usize,
+    pub total: usize,
+}
+
+impl ChecklistResponse {
+    fn new(ticket_id: String, items: Vec<ChecklistItem>) -> Self {
+        let completed = items.iter().filter(|i| i.completed).count();
+        let total = items.len();
+        Self { ticket_id, items, completed, total }
+    }
+}
+
+/// Checklist rendered for agent prompts, or `None` if the ticket has no items
+pub async fn checklist_context(pool: &SqlitePool, ticket_id: &str) -> Option<String> {
+    let items = checklists::list_items(pool, ticket_id).await.ok()?;
+    if items.is_empty() {
+        return None;
+    }
+    let lines: Vec<String> = items
+        .iter()
+        .map(|i| format!("- [{}] ({}) {}", if i.completed { "x" } else { " " }, i.item_id, i.text))
+        .collect();
+    Some(lines.join("\n"))
+}
+
+/// Add `checklist_progress` to each ticket in a list response (either a bare
+/// array or an object with a `tickets` array). Tickets without items are left alone.
+pub async fn attach_checklist_progress(pool: &SqlitePool, response: &mut serde_json::Value) {
+    let tickets = match response {
+        serde_json::Value::Array(items) => items,
+        serde_json::Value::Object(obj) => match obj.get_mut("tickets").and_then(|t| t.as_array_mut()) {
+            Some(items) => items,
+            None => return,
+        },
+        _ => return,
+    };
+
+    let ids: Vec<String> = tickets
+        .iter()
+        .filter_map(|t| t.get("ticket_id").and_then(|id| id.as_str()).map(|s| s.to_string()))
+        .collect();
+    let progress = match checklists::progress_for_tickets(pool, &ids).await {
+        Ok(progress) => progress,
+        Err(e) => {
+            tracing::warn!("Failed to load checklist progress: {}", e);
+            return;
+        }
+    };
+
+    for ticket in tickets.iter_mut() {
+        let Some(id) = ticket.get("ticket_id").and_then(|id| id.as_str()) else {
+            continue;
+        };
+        if let Some(p) = progress.get(id) {
+            ticket["checklist_progress"] = json!({ "completed": p.completed, "total": p.total });
+        }
+    }
+}
+
+/// List checklist items (GET /api/tickets/:ticket_id/checklist)
+pub async fn list_checklist(
+    State(pool): State<Arc<SqlitePool>>,
+    Path(ticket_id): Path<String>,
+) -> Result<Json<ChecklistResponse>, (StatusCode, String)> {
+    let items = checklists::list_items(&pool, &ticket_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Ok(Json(ChecklistResponse::new(ticket_id, items)))
+}
+
+/// Add a checklist item (POST /api/tickets/:ticket_id/checklist)
+pub async fn create_checklist_item(
+    State(pool): State<Arc<SqlitePool>>,
+    Path(ticket_id): Path<String>,
+    Json(req): Json<CreateChecklistItemRequest>,
+) -> Result<(StatusCode, Json<ChecklistItem>), (StatusCode, String)> {
+    let text = req.text.trim();
+    if text.is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "Checklist item text must not be empty".to_string()));
+    }
+    ticketing_system::tickets::get_ticket_by_id(&pool, &ticket_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or_else(|| (StatusCode::NOT_FOUND, "Ticket not found".to_string()))?;
+
+    let item = checklists::create_item(&pool, &ticket_id, text, req.position)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Ok((StatusCode::CREATED, Json(item)))
+}
+
+/// Update a checklist item's text, position or completion (PATCH /api/tickets/:ticket_id/checklist/:item_id)
+pub async fn update_checklist_item(
+    State(pool): State<Arc<SqlitePool>>,
+    Path((ticket_id, item_id)): Path<(String, i64)>,
+    Json(req): Json<UpdateChecklistItemRequest>,
+) -> Result<Json<ChecklistItem>, (StatusCode, String)> {
+    let item = checklists::update_item(
+        &pool,
+        &ticket_id,
+        item_id,
+        req.text.as_deref().map(str::trim),
+        req.completed,
+        req.position,
+    )
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+    .ok_or_else(|| (StatusCode::NOT_FOUND, "Checklist item not found".to_string()))?;
+    Ok(Json(item))
+}
+
+/// Flip an item's completion (POST /api/tickets/:ticket_id/checklist/:item_id/toggle)
+pub async fn toggle_checklist_item(
+    State(pool): State<Arc<SqlitePool>>,
+    Path((ticket_id, item_id)): Path<(String, i64)>,
+) -> Result<Json<ChecklistItem>, (StatusCode, String)> {
+    let current = checklists::get_item(&pool, &ticket_id, item_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or_else(|| (StatusCode::NOT_FOUND, "Checklist item not found".to_string()))?;
+
+    let item = checklists::update_item(&pool, &ticket_id, item_id, None, Some(!current.completed), None)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or_else(|| (StatusCode::NOT_FOUND, "Checklist item not found".to_string()))?;
+    Ok(Json(item))
+}
+
+/// Delete a checklist item (DELETE /api/tickets/:ticket_id/checklist/:item_id)
+pub async fn delete_checklist_item(
+    State(pool): State<Arc<SqlitePool>>,
+    Path((ticket_id, item_id)): Path<(String, i64)>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let deleted = checklists::delete_item(&pool, &ticket_id, item_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    if !deleted {
+        return Err((StatusCode::NOT_FOUND, "Checklist item not found".to_string()));
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,182 @@
+//! Context pack CRUD. Packs are per organization; see `crate::context_packs`
+//! for how they are rendered into agent prompts.
+
+use axum::{
+    extract::{Extension, Path, State},
+    http::StatusCode,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use ticketing_system::{
+    context_packs, research_docs, tickets, ContextPack, ContextPackItem, CreateContextPackRequest, SqlitePool,
+    UpdateContextPackRequest, User,
+};
+
+use crate::tenancy::Organization;
+
+const MAX_PACK_ITEMS: usize = 50;
+
+#[derive(Debug, Deserialize)]
+pub struct ContextPackBody {
+    pub name: String,
+    pub description: Option<String>,
+    #[serde(default)]
+    pub items: Vec<ContextPackItem>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ContextPackListResponse {
+    pub packs: Vec<ContextPack>,
+}
+
+/// Check the body, and that every linked doc and ticket exists in the organization
+async fn validate_pack(pool: &SqlitePool, organization: &str, body: &ContextPackBody) -> Result<(), (StatusCode, String)> {
+    if body.name.trim().is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "name must not be empty".to_string()));
+    }
+    if body.items.len() > MAX_PACK_ITEMS {
+        return Err((StatusCode::BAD_REQUEST, format!("A pack holds at most {} items", MAX_PACK_ITEMS)));
+    }
+
+    let mut problems = Vec::new();
+    for item in &body.items {
+        match item {
+            ContextPackItem::Note { text, .. } => {
+                if text.trim().is_empty() {
+                    problems.push("notes must not be empty".to_string());
+                }
+            }
+            ContextPackItem::Document { doc_id } => {
+                let doc = research_docs::get_doc(pool, doc_id)
+                    .await
+                    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+                if doc.is_none_or(|d| d.organization != organization) {
+                    problems.push(format!("unknown research doc '{}'", doc_id));
+                }
+            }
+            ContextPackItem::Ticket { ticket_id } => {
+                let ticket = tickets::get_ticket_by_id(pool, ticket_id)
+                    .await
+                    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+                if ticket.is_none_or(|t| t.organization != organization) {
+                    problems.push(format!("unknown ticket '{}'", ticket_id));
+                }
+            }
+        }
+    }
+    if problems.is_empty() {
+        Ok(())
+    } else {
+        Err((StatusCode::UNPROCESSABLE_ENTITY, problems.join("; ")))
+    }
+}
+
+/// Load a pack the request's user may see
+async fn load_pack(pool: &SqlitePool, org: &Organization, pack_id: &str) -> Result<ContextPack, (StatusCode, String)> {
+    let pack = context_packs::get_pack(pool, pack_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or_else(|| (StatusCode::NOT_FOUND, "Context pack not found".to_string()))?;
+    org.authorize(&pack.organization)?;
+    Ok(pack)
+}
+
+/// Check a pack named on an agent run belongs to the ticket's organization
+pub async fn require_context_pack(
+    pool: &SqlitePool,
+    organization: &str,
+    pack_id: &str,
+) -> Result<(), (StatusCode, String)> {
+    let pack = context_packs::get_pack(pool, pack_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    match pack {
+        Some(pack) if pack.organization == organization => Ok(()),
+        _ => Err((StatusCode::NOT_FOUND, format!("Context pack {} not found", pack_id))),
+    }
+}
+
+/// List the organization's packs (GET /api/context-packs)
+pub async fn list_context_packs(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(org): Extension<Organization>,
+) -> Result<Json<ContextPackListResponse>, (StatusCode, String)> {
+    let packs = context_packs::list_packs(&pool, &org.name)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Ok(Json(ContextPackListResponse { packs }))
+}
+
+/// Create a pack (POST /api/context-packs)
+pub async fn create_context_pack(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(user): Extension<User>,
+    Extension(org): Extension<Organization>,
+    Json(body): Json<ContextPackBody>,
+) -> Result<(StatusCode, Json<ContextPack>), (StatusCode, String)> {
+    validate_pack(&pool, &org.name, &body).await?;
+    let pack = context_packs::create_pack(
+        &pool,
+        &CreateContextPackRequest {
+            organization: org.name,
+            name: body.name.trim().to_string(),
+            description: body.description,
+            items: body.items,
+            created_by: user.user_id,
+        },
+    )
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Ok((StatusCode::CREATED, Json(pack)))
+}
+
+/// Get a pack (GET /api/context-packs/:pack_id)
+pub async fn get_context_pack(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(org): Extension<Organization>,
+    Path(pack_id): Path<String>,
+) -> Result<Json<ContextPack>, (StatusCode, String)> {
+    Ok(Json(load_pack(&pool, &org, &pack_id).await?))
+}
+
+/// Replace a pack (PUT /api/context-packs/:pack_id)
+pub async fn update_context_pack(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(org): Extension<Organization>,
+    Path(pack_id): Path<String>,
+    Json(body): Json<ContextPackBody>,
+) -> Result<Json<ContextPack>, (StatusCode, String)> {
+    let existing = load_pack(&pool, &org, &pack_id).await?;
+    validate_pack(&pool, &existing.organization, &body).await?;
+    let pack = context_packs::update_pack(
+        &pool,
+        &pack_id,
+        &UpdateContextPackRequest {
+            name: body.name.trim().to_string(),
+            description: body.description,
+            items: body.items,
+        },
+    )
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+    .ok_or_else(|| (StatusCode::NOT_FOUND, "Context pack not found".to_string()))?;
+    Ok(Json(pack))
+}
+
+/// Delete a pack (DELETE /api/context-packs/:pack_id). Steps still naming it
+/// run without it.
+pub async fn delete_context_pack(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(org): Extension<Organization>,
+    Path(pack_id): Path<String>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    load_pack(&pool, &org, &pack_id).await?;
+    let deleted = context_packs::delete_pack(&pool, &pack_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    if !deleted {
+        return Err((StatusCode::NOT_FOUND, "Context pack not found".to_string()));
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
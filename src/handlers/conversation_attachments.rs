@@ -0,0 +1,306 @@
+//! Files and images attached to a conversation
+//!
+//! Uploads are stored under `~/.agentic-flowstate/conversation-attachments/<conversation_id>/`
+//! with a JSON sidecar per file. A chat message can name attachments by id;
+//! their paths are listed ahead of the message and the directory is added to
+//! the agent session, so the agent can open specs and view screenshots with
+//! its Read tool.
+
+use axum::{
+    extract::{Extension, Path, State},
+    http::StatusCode,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::Arc;
+use ticketing_system::{conversations, SqlitePool, User};
+
+use crate::tenancy::Organization;
+
+/// Largest file accepted, after base64 decoding
+const MAX_ATTACHMENT_BYTES: usize = 20 * 1024 * 1024;
+
+/// Content types Claude can view as images
+const IMAGE_TYPES: &[&str] = &["image/png", "image/jpeg", "image/gif", "image/webp"];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConversationAttachment {
+    pub attachment_id: String,
+    pub conversation_id: String,
+    pub file_name: String,
+    pub content_type: String,
+    pub size: usize,
+    /// Absolute path of the stored file, as given to the agent
+    pub path: String,
+    pub uploaded_by: String,
+    pub created_at: String,
+}
+
+impl ConversationAttachment {
+    pub fn is_image(&self) -> bool {
+        IMAGE_TYPES.contains(&self.content_type.as_str())
+    }
+}
+
+/// Whether a conversation id is safe to use as a directory name
+fn is_valid_id(conversation_id: &str) -> bool {
+    !conversation_id.is_empty()
+        && conversation_id.len() <= 128
+        && conversation_id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Directory holding a conversation's attachments
+pub fn attachments_dir(conversation_id: &str) -> Result<PathBuf, (StatusCode, String)> {
+    if !is_valid_id(conversation_id) {
+        return Err((StatusCode::BAD_REQUEST, "Invalid conversation id".to_string()));
+    }
+    Ok(dirs::home_dir()
+        .unwrap_or_default()
+        .join(".agentic-flowstate")
+        .join("conversation-attachments")
+        .join(conversation_id))
+}
+
+/// File name safe to store: no directories, only ordinary characters
+fn safe_file_name(name: &str) -> String {
+    let base = name.rsplit(['/', '\\']).next().unwrap_or_default();
+    let cleaned: String = base
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_') { c } else { '_' })
+        .collect();
+    let cleaned = cleaned.trim_start_matches('.');
+    if cleaned.is_empty() {
+        "attachment".to_string()
+    } else {
+        cleaned.to_string()
+    }
+}
+
+/// Content type from the upload, or guessed from the file extension
+fn content_type_for(file_name: &str, given: Option<&str>) -> String {
+    if let Some(given) = given.map(str::trim).filter(|t| !t.is_empty()) {
+        return given.to_lowercase();
+    }
+    let extension = file_name.rsplit_once('.').map(|(_, ext)| ext.to_lowercase()).unwrap_or_default();
+    match extension.as_str() {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "pdf" => "application/pdf",
+        "md" => "text/markdown",
+        "txt" | "log" => "text/plain",
+        "json" => "application/json",
+        "csv" => "text/csv",
+        _ => "application/octet-stream",
+    }
+    .to_string()
+}
+
+/// 404 unless the conversation exists; 403 unless the caller belongs to its organization
+async fn require_conversation(
+    pool: &SqlitePool,
+    org: &Organization,
+    conversation_id: &str,
+) -> Result<(), (StatusCode, String)> {
+    let conv = conversations::get_conversation(pool, conversation_id, false)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or((StatusCode::NOT_FOUND, "Conversation not found".to_string()))?;
+    if let Some(organization) = &conv.organization {
+        org.authorize(organization)?;
+    }
+    Ok(())
+}
+
+async fn read_attachments(conversation_id: &str) -> Result<Vec<ConversationAttachment>, (StatusCode, String)> {
+    let dir = attachments_dir(conversation_id)?;
+    let mut entries = match tokio::fs::read_dir(&dir).await {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err((StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to read attachments: {}", e))),
+    };
+    let mut attachments = Vec::new();
+    while let Ok(Some(entry)) = entries.next_entry().await {
+        let path = entry.path();
+        if path.extension().and_then(|e| e.to_str()) != Some("json") {
+            continue;
+        }
+        match tokio::fs::read_to_string(&path).await.map(|s| serde_json::from_str::<ConversationAttachment>(&s)) {
+            Ok(Ok(attachment)) => attachments.push(attachment),
+            _ => tracing::warn!("Skipping unreadable attachment metadata {}", path.display()),
+        }
+    }
+    attachments.sort_by(|a, b| a.created_at.cmp(&b.created_at));
+    Ok(attachments)
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UploadAttachmentRequest {
+    pub file_name: String,
+    /// Guessed from the file name when absent
+    pub content_type: Option<String>,
+    /// File contents, base64 encoded
+    pub data: String,
+}
+
+/// Upload a file or image (POST /api/conversations/:id/attachments)
+pub async fn upload_conversation_attachment(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(user): Extension<User>,
+    Extension(org): Extension<Organization>,
+    Path(conversation_id): Path<String>,
+    Json(req): Json<UploadAttachmentRequest>,
+) -> Result<(StatusCode, Json<ConversationAttachment>), (StatusCode, String)> {
+    use base64::Engine;
+
+    require_conversation(&pool, &org, &conversation_id).await?;
+    let bytes = base64::engine::general_purpose::STANDARD
+        .decode(&req.data)
+        .map_err(|e| (StatusCode::BAD_REQUEST, format!("Invalid base64: {}", e)))?;
+    if bytes.is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "Attachment is empty".to_string()));
+    }
+    if bytes.len() > MAX_ATTACHMENT_BYTES {
+        return Err((
+            StatusCode::PAYLOAD_TOO_LARGE,
+            format!("Attachments are limited to {} MB", MAX_ATTACHMENT_BYTES / (1024 * 1024)),
+        ));
+    }
+
+    let dir = attachments_dir(&conversation_id)?;
+    tokio::fs::create_dir_all(&dir)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to create attachment dir: {}", e)))?;
+
+    let attachment_id = uuid::Uuid::new_v4().to_string();
+    let file_name = safe_file_name(&req.file_name);
+    let path = dir.join(format!("{}_{}", attachment_id, file_name));
+    tokio::fs::write(&path, &bytes)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to write attachment: {}", e)))?;
+
+    let attachment = ConversationAttachment {
+        content_type: content_type_for(&file_name, req.content_type.as_deref()),
+        attachment_id,
+        conversation_id,
+        file_name,
+        size: bytes.len(),
+        path: path.to_string_lossy().into_owned(),
+        uploaded_by: user.user_id,
+        created_at: chrono::Utc::now().to_rfc3339(),
+    };
+    let meta_path = dir.join(format!("{}.json", attachment.attachment_id));
+    tokio::fs::write(&meta_path, serde_json::to_string_pretty(&attachment).unwrap_or_default())
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to write attachment metadata: {}", e)))?;
+
+    tracing::info!(
+        "Attached {} ({} bytes) to conversation {}",
+        attachment.file_name, attachment.size, attachment.conversation_id
+    );
+    Ok((StatusCode::CREATED, Json(attachment)))
+}
+
+/// List a conversation's attachments (GET /api/conversations/:id/attachments)
+pub async fn list_conversation_attachments(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(org): Extension<Organization>,
+    Path(conversation_id): Path<String>,
+) -> Result<Json<Vec<ConversationAttachment>>, (StatusCode, String)> {
+    require_conversation(&pool, &org, &conversation_id).await?;
+    Ok(Json(read_attachments(&conversation_id).await?))
+}
+
+/// Delete an attachment (DELETE /api/conversations/:id/attachments/:attachment_id)
+pub async fn delete_conversation_attachment(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(org): Extension<Organization>,
+    Path((conversation_id, attachment_id)): Path<(String, String)>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    require_conversation(&pool, &org, &conversation_id).await?;
+    let attachment = read_attachments(&conversation_id)
+        .await?
+        .into_iter()
+        .find(|a| a.attachment_id == attachment_id)
+        .ok_or((StatusCode::NOT_FOUND, "Attachment not found".to_string()))?;
+    let dir = attachments_dir(&conversation_id)?;
+    for path in [PathBuf::from(&attachment.path), dir.join(format!("{}.json", attachment.attachment_id))] {
+        if let Err(e) = tokio::fs::remove_file(&path).await {
+            tracing::warn!("Failed to remove {}: {}", path.display(), e);
+        }
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Remove every attachment of a deleted conversation
+pub async fn remove_conversation_attachments(conversation_id: &str) {
+    let Ok(dir) = attachments_dir(conversation_id) else {
+        return;
+    };
+    match tokio::fs::remove_dir_all(&dir).await {
+        Ok(()) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => tracing::warn!("Failed to remove attachments of conversation {}: {}", conversation_id, e),
+    }
+}
+
+/// Attachments named in a chat message, listed ahead of the message, and the
+/// directory the agent needs access to for them. The conversation chatted in
+/// must be the caller's, since the chat writes to it and reads its
+/// attachments. Unknown ids are an error.
+pub async fn attach_to_message(
+    pool: &SqlitePool,
+    org: &Organization,
+    conversation_id: Option<&str>,
+    attachment_ids: &[String],
+    message: String,
+) -> Result<(String, Option<PathBuf>), (StatusCode, String)> {
+    if let Some(conversation_id) = conversation_id {
+        require_conversation(pool, org, conversation_id).await?;
+    }
+    if attachment_ids.is_empty() {
+        return Ok((message, None));
+    }
+    let conversation_id = conversation_id.ok_or((
+        StatusCode::BAD_REQUEST,
+        "conversation_id is required to send attachments".to_string(),
+    ))?;
+    let stored = read_attachments(conversation_id).await?;
+    let mut lines = vec!["[Attachments]".to_string()];
+    for id in attachment_ids {
+        let attachment = stored
+            .iter()
+            .find(|a| a.attachment_id == *id)
+            .ok_or_else(|| (StatusCode::BAD_REQUEST, format!("Unknown attachment '{}'", id)))?;
+        lines.push(attachment_line(attachment));
+    }
+    lines.push("---".to_string());
+    lines.push(String::new());
+    lines.push(message);
+    Ok((lines.join("\n"), Some(attachments_dir(conversation_id)?)))
+}
+
+fn attachment_line(attachment: &ConversationAttachment) -> String {
+    let kind = if attachment.is_image() { "image, view it with the Read tool" } else { attachment.content_type.as_str() };
+    format!("- {} ({}): {}", attachment.file_name, kind, attachment.path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stores_uploads_under_safe_names() {
+        assert_eq!(safe_file_name("../../etc/passwd"), "passwd");
+        assert_eq!(safe_file_name("C:\\Users\\me\\Screen Shot 1.png"), "Screen_Shot_1.png");
+        assert_eq!(safe_file_name("..."), "attachment");
+        assert_eq!(content_type_for("shot.PNG", None), "image/png");
+        assert_eq!(content_type_for("spec", Some("Application/PDF")), "application/pdf");
+
+        assert!(attachments_dir("3f2b9c1e-7a4d-4e8b-9c1a-2b3c4d5e6f70").is_ok());
+        assert_eq!(attachments_dir("../other").unwrap_err().0, StatusCode::BAD_REQUEST);
+        assert!(attachments_dir("").is_err());
+    }
+}
@@ -1,7 +1,10 @@
 use axum::{
-    extract::{Path, Query, State},
+    extract::{Extension, Path, Query, State},
     http::StatusCode,
-    response::sse::{Event, KeepAlive, Sse},
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse, Response,
+    },
     Json,
 };
 use futures::stream::Stream;
@@ -9,14 +12,22 @@ use serde::{Deserialize, Serialize};
 use std::convert::Infallible;
 use std::sync::Arc;
 use std::time::Duration;
+use tokio::sync::broadcast;
 use ticketing_system::{
-    conversations, AddMessageRequest, Conversation, ConversationMessage,
-    CreateConversationRequest, SqlitePool, UpdateConversationRequest,
+    checkpoints, conversations, AddMessageRequest, Conversation, ConversationMessage,
+    CreateConversationRequest, SqlitePool, UpdateConversationRequest, User,
 };
 
+use crate::agents::StreamEvent;
+use crate::tenancy::Organization;
+use crate::user_events::{self, Subject, UserEvent};
+
 #[derive(Debug, Deserialize)]
 pub struct ListConversationsQuery {
     pub organization: Option<String>,
+    /// List archived conversations instead of active ones
+    #[serde(default)]
+    pub archived: bool,
 }
 
 #[derive(Debug, Serialize)]
@@ -28,9 +39,13 @@ pub struct ConversationListResponse {
 /// List conversations (GET /api/conversations)
 pub async fn list_conversations(
     State(pool): State<Arc<SqlitePool>>,
+    Extension(org): Extension<Organization>,
     Query(params): Query<ListConversationsQuery>,
 ) -> Result<Json<ConversationListResponse>, (StatusCode, String)> {
-    let list = conversations::list_conversations(&pool, params.organization.as_deref())
+    if let Some(organization) = &params.organization {
+        org.authorize(organization)?;
+    }
+    let list = conversations::list_conversations(&pool, params.organization.as_deref(), params.archived)
         .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
@@ -42,6 +57,24 @@ pub async fn list_conversations(
     }))
 }
 
+/// Load a conversation, checking the caller belongs to the organization owning
+/// it. Conversations without an organization predate tenancy and stay open.
+async fn load_conversation(
+    pool: &SqlitePool,
+    org: &Organization,
+    id: &str,
+    include_messages: bool,
+) -> Result<Conversation, (StatusCode, String)> {
+    let conv = conversations::get_conversation(pool, id, include_messages)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or((StatusCode::NOT_FOUND, "Conversation not found".to_string()))?;
+    if let Some(organization) = &conv.organization {
+        org.authorize(organization)?;
+    }
+    Ok(conv)
+}
+
 /// Get single conversation by ID (GET /api/conversations/:id)
 pub async fn get_conversation(
     State(pool): State<Arc<SqlitePool>>,
@@ -58,11 +91,13 @@ pub async fn get_conversation(
 /// Create a conversation (POST /api/conversations)
 pub async fn create_conversation(
     State(pool): State<Arc<SqlitePool>>,
+    Extension(user): Extension<User>,
     Json(req): Json<CreateConversationRequest>,
 ) -> Result<(StatusCode, Json<Conversation>), (StatusCode, String)> {
     let conv = conversations::create_conversation(&pool, req)
         .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    user_events::follow(&pool, &user.user_id, Subject::Conversation, &conv.id).await;
 
     Ok((StatusCode::CREATED, Json(conv)))
 }
@@ -88,13 +123,69 @@ pub async fn delete_conversation(
     conversations::delete_conversation(&pool, &id)
         .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    super::conversation_attachments::remove_conversation_attachments(&id).await;
 
     Ok(StatusCode::NO_CONTENT)
 }
 
+/// Archive a conversation, hiding it from the default listing (POST /api/conversations/:id/archive)
+pub async fn archive_conversation(
+    State(pool): State<Arc<SqlitePool>>,
+    Path(id): Path<String>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    set_archived(&pool, &id, true).await
+}
+
+/// Restore an archived conversation (POST /api/conversations/:id/unarchive)
+pub async fn unarchive_conversation(
+    State(pool): State<Arc<SqlitePool>>,
+    Path(id): Path<String>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    set_archived(&pool, &id, false).await
+}
+
+async fn set_archived(pool: &SqlitePool, id: &str, archived: bool) -> Result<StatusCode, (StatusCode, String)> {
+    let found = conversations::set_archived(pool, id, archived)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    if !found {
+        return Err((StatusCode::NOT_FOUND, "Conversation not found".to_string()));
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// The conversation's rolled-up memory (GET /api/conversations/:id/memory)
+pub async fn get_conversation_memory(
+    State(pool): State<Arc<SqlitePool>>,
+    Path(id): Path<String>,
+) -> Result<Json<crate::conversation_memory::ConversationMemory>, (StatusCode, String)> {
+    let conv = conversations::get_conversation(&pool, &id, false)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or((StatusCode::NOT_FOUND, "Conversation not found".to_string()))?;
+    crate::conversation_memory::stored_memory(&conv)
+        .map(Json)
+        .ok_or((StatusCode::NOT_FOUND, "Conversation has no memory yet".to_string()))
+}
+
+/// Forget the memory, e.g. after a bad summary; the next rollup starts over
+/// (DELETE /api/conversations/:id/memory)
+pub async fn reset_conversation_memory(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(org): Extension<Organization>,
+    Path(id): Path<String>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    load_conversation(&pool, &org, &id, false).await?;
+    conversations::set_memory(&pool, &id, None)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
 /// Add a message to a conversation (POST /api/conversations/:id/messages)
 pub async fn add_message(
     State(pool): State<Arc<SqlitePool>>,
+    Extension(user): Extension<User>,
     Path(id): Path<String>,
     Json(req): Json<AddMessageRequest>,
 ) -> Result<(StatusCode, Json<ConversationMessage>), (StatusCode, String)> {
@@ -108,32 +199,155 @@ pub async fn add_message(
         .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
+    user_events::follow(&pool, &user.user_id, Subject::Conversation, &id).await;
+    let event = UserEvent::ConversationMessage {
+        conversation_id: id.clone(),
+        message_id: msg.id.clone(),
+        role: msg.role.clone(),
+    };
+    user_events::publish(&pool, Subject::Conversation, &id, Some(&user.user_id), event).await;
+
     Ok((StatusCode::CREATED, Json(msg)))
 }
 
+/// Agent that regenerates an edited message's reply or recovers an interrupted
+/// one: always the agent the conversation is held with
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ChatAgent {
+    WorkspaceManager,
+    LifePlanner,
+}
+
+impl ChatAgent {
+    /// The agent a chat prompt belongs to, as recorded on its conversations
+    fn from_prompt_name(prompt_name: &str) -> Option<Self> {
+        match prompt_name {
+            "workspace-manager" => Some(ChatAgent::WorkspaceManager),
+            "life-planner" => Some(ChatAgent::LifePlanner),
+            _ => None,
+        }
+    }
+
+    /// The agent a conversation is held with. Conversations from before the
+    /// agent was recorded are with the workspace manager.
+    fn of(conv: &Conversation) -> Result<Self, (StatusCode, String)> {
+        Self::recorded(conv.agent.as_deref())
+    }
+
+    fn recorded(agent: Option<&str>) -> Result<Self, (StatusCode, String)> {
+        match agent {
+            None => Ok(ChatAgent::WorkspaceManager),
+            Some(name) => Self::from_prompt_name(name)
+                .ok_or_else(|| (StatusCode::CONFLICT, format!("Conversation is with an unknown agent: {}", name))),
+        }
+    }
+}
+
 #[derive(Debug, Deserialize)]
 pub struct UpdateMessageRequest {
     pub content: String,
     pub tool_uses: Option<Vec<ticketing_system::ToolUse>>,
+    /// Edit a user message and regenerate the reply with the conversation's agent
+    #[serde(default)]
+    pub regenerate: bool,
 }
 
+/// Response header naming the conversation that keeps the pre-edit branch
+pub const FORKED_CONVERSATION_HEADER: &str = "X-Forked-Conversation";
+
 /// Update a message (PATCH /api/conversations/:id/messages/:message_id)
+///
+/// With `regenerate`, the message must be a user message: the conversation as
+/// it stands is first forked into a new conversation (named in the
+/// `X-Forked-Conversation` header) so the old branch is kept, then every later
+/// message is removed, the message is edited, and the reply is regenerated
+/// and streamed as SSE.
 pub async fn update_message(
     State(pool): State<Arc<SqlitePool>>,
+    Extension(user): Extension<User>,
+    Extension(org): Extension<Organization>,
     Path((conv_id, message_id)): Path<(String, String)>,
     Json(req): Json<UpdateMessageRequest>,
-) -> Result<StatusCode, (StatusCode, String)> {
-    // Verify conversation exists
-    let _ = conversations::get_conversation(&pool, &conv_id, false)
-        .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
-        .ok_or((StatusCode::NOT_FOUND, "Conversation not found".to_string()))?;
+) -> Result<Response, (StatusCode, String)> {
+    let conv = load_conversation(&pool, &org, &conv_id, false).await?;
+
+    if req.regenerate {
+        return regenerate_from(pool, &user, &org, conv, &message_id, &req.content).await;
+    }
 
     conversations::update_message(&pool, &message_id, &req.content, req.tool_uses.as_deref())
         .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
-    Ok(StatusCode::NO_CONTENT)
+    Ok(StatusCode::NO_CONTENT.into_response())
+}
+
+async fn regenerate_from(
+    pool: Arc<SqlitePool>,
+    user: &User,
+    org: &Organization,
+    conv: Conversation,
+    message_id: &str,
+    content: &str,
+) -> Result<Response, (StatusCode, String)> {
+    if content.trim().is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "content must not be empty".to_string()));
+    }
+    let agent = ChatAgent::of(&conv)?;
+    let organization = match conv.organization.clone() {
+        Some(organization) => organization,
+        None => org.clone().resolve(None)?,
+    };
+    crate::quotas::check_agent_run(&pool, &organization).await?;
+
+    let messages = conversations::list_messages(&pool, &conv.id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let idx = messages
+        .iter()
+        .position(|m| m.id == message_id)
+        .ok_or((StatusCode::NOT_FOUND, "Message not found".to_string()))?;
+    if messages[idx].role != "user" {
+        return Err((StatusCode::BAD_REQUEST, "Only user messages can be edited and regenerated".to_string()));
+    }
+
+    // Claimed before anything is changed, so a concurrent reply or second
+    // edit can't interleave with this one
+    let streams = &super::chat_stream::CHAT_STREAMS;
+    let live_tx = streams
+        .claim(&conv.id)
+        .await
+        .ok_or((StatusCode::CONFLICT, "A reply is still being generated".to_string()))?;
+    let edited = async {
+        let title = conv.title.clone().unwrap_or_else(|| "Conversation".to_string());
+        let fork = conversations::fork_conversation(&pool, &conv.id, &format!("{} (before edit)", title))
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        conversations::delete_messages_after(&pool, &conv.id, message_id)
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        conversations::update_message(&pool, message_id, content, None)
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        Ok::<_, (StatusCode, String)>(fork)
+    }
+    .await;
+    let fork = match edited {
+        Ok(fork) => fork,
+        Err(e) => {
+            streams.release(&conv.id).await;
+            return Err(e);
+        }
+    };
+    tracing::info!("Regenerating conversation {} from message {}; previous branch kept as {}", conv.id, message_id, fork.id);
+
+    let prompt = super::chat_stream::replay_prompt(&messages[..idx], content);
+    let (message, config) = match agent {
+        ChatAgent::WorkspaceManager => super::workspace_manager::regeneration(&pool, org, &organization, &prompt).await,
+        ChatAgent::LifePlanner => super::life_planner::regeneration(&pool, &user.user_id, &prompt).await,
+    };
+    let stream = super::chat_stream::chat_claimed(pool, message, conv.id, config, live_tx);
+    Ok(([(FORKED_CONVERSATION_HEADER, fork.id)], stream).into_response())
 }
 
 /// List messages for a conversation (GET /api/conversations/:id/messages)
@@ -154,6 +368,145 @@ pub async fn list_messages(
     Ok(Json(messages))
 }
 
+/// GET /api/conversations/:id/stream
+/// Re-attach to a workspace manager generation: replays the persisted events of
+/// the latest generation, then follows it live if it is still running.
+pub async fn reconnect_conversation_stream(
+    State(pool): State<Arc<SqlitePool>>,
+    Path(id): Path<String>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    // Subscribe before reading history so no event falls between the two
+    let live_rx = super::chat_stream::CHAT_STREAMS
+        .channels
+        .read()
+        .await
+        .get(&id)
+        .map(|tx| tx.subscribe());
+
+    let stream = async_stream::stream! {
+        let events = match conversations::get_events(&pool, &id).await {
+            Ok(events) => events,
+            Err(e) => {
+                tracing::error!("Failed to load stream events for {}: {}", id, e);
+                Vec::new()
+            }
+        };
+
+        let mut last_index: i32 = -1;
+        for stored in &events {
+            last_index = last_index.max(stored.event_index);
+            yield Ok(Event::default().data(stored.event_data.clone()));
+        }
+
+        let replay_complete = StreamEvent::ReplayComplete {
+            total_events: events.len(),
+            agent_status: if live_rx.is_some() { "running" } else { "completed" }.to_string(),
+        };
+        if let Ok(json) = serde_json::to_string(&replay_complete) {
+            yield Ok(Event::default().data(json));
+        }
+
+        if let Some(mut rx) = live_rx {
+            loop {
+                match rx.recv().await {
+                    Ok((index, event)) => {
+                        if index <= last_index {
+                            continue;
+                        }
+                        // Deltas share the index of the stored event that follows them
+                        if !matches!(event, StreamEvent::TextDelta { .. }) {
+                            last_index = index;
+                        }
+                        if let Ok(json) = serde_json::to_string(&event) {
+                            yield Ok(Event::default().data(json));
+                        }
+                    }
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        tracing::warn!("Conversation stream {} lagged by {} events", id, skipped);
+                        continue;
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        }
+    };
+
+    Sse::new(stream).keep_alive(
+        KeepAlive::new()
+            .interval(Duration::from_secs(15))
+            .text("ping")
+    )
+}
+
+/// Message sent to a resumed session to carry on an interrupted reply
+const RECOVERY_MESSAGE: &str = "Your previous reply was interrupted by a server restart. \
+Continue it from where it stopped, without repeating what you already wrote or redoing completed tool calls.";
+
+/// POST /api/conversations/:id/recover
+/// Resume a reply that a server restart interrupted, from the conversation's
+/// last checkpoint, with the agent and organization the conversation was held
+/// with. Streams the interrupted reply's stored events, then the continuation,
+/// as SSE.
+pub async fn recover_conversation(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(org): Extension<Organization>,
+    Path(id): Path<String>,
+) -> Result<super::chat_stream::SseStream, (StatusCode, String)> {
+    let conv = load_conversation(&pool, &org, &id, false).await?;
+    let agent = ChatAgent::of(&conv)?;
+
+    let checkpoint = checkpoints::get_checkpoint(&pool, &id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or((StatusCode::NOT_FOUND, "Conversation has no checkpoint".to_string()))?;
+    if checkpoint.status != "interrupted" {
+        return Err((StatusCode::CONFLICT, format!("Nothing to recover: the last reply is {}", checkpoint.status)));
+    }
+    // Checkpoints written before the session started have no session to resume
+    if checkpoint.session_id == "pending" {
+        return Err((
+            StatusCode::CONFLICT,
+            "The interrupted reply never started a session; send the message again".to_string(),
+        ));
+    }
+    crate::quotas::check_agent_run(&pool, conv.organization.as_deref().unwrap_or(&org.name)).await?;
+
+    let events = conversations::get_events(&pool, &id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let next_event_index = events.iter().map(|e| e.event_index + 1).max().unwrap_or(0);
+    let replayed: Vec<String> = events.into_iter().map(|e| e.event_data).collect();
+
+    let config = match agent {
+        ChatAgent::WorkspaceManager => {
+            let organization = match conv.organization {
+                Some(organization) => organization,
+                None => org.clone().resolve(None)?,
+            };
+            super::workspace_manager::config(&pool, &org, &organization).await
+        }
+        ChatAgent::LifePlanner => super::life_planner::config(),
+    };
+    let live_tx = super::chat_stream::CHAT_STREAMS
+        .claim(&id)
+        .await
+        .ok_or((StatusCode::CONFLICT, "A reply is still being generated".to_string()))?;
+    tracing::info!(
+        "Recovering conversation {} from session {} after {} tool call(s)",
+        id, checkpoint.session_id, checkpoint.tool_call_count
+    );
+    Ok(super::chat_stream::recover(
+        pool,
+        RECOVERY_MESSAGE.to_string(),
+        checkpoint.session_id,
+        id,
+        replayed,
+        next_event_index,
+        config,
+        live_tx,
+    ))
+}
+
 /// SSE event types for conversation updates
 #[derive(Debug, Serialize)]
 #[serde(tag = "type")]
@@ -170,15 +523,19 @@ pub enum ConversationStreamEvent {
 /// SSE endpoint for real-time conversation list updates
 pub async fn subscribe_conversations(
     State(pool): State<Arc<SqlitePool>>,
+    Extension(org): Extension<Organization>,
     Query(params): Query<ListConversationsQuery>,
-) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, (StatusCode, String)> {
+    if let Some(organization) = &params.organization {
+        org.authorize(organization)?;
+    }
     let stream = async_stream::stream! {
         // Track the last update time we've seen
         let mut last_sync_hash: u64 = 0;
 
         loop {
             // Get current conversations
-            match conversations::list_conversations(&pool, params.organization.as_deref()).await {
+            match conversations::list_conversations(&pool, params.organization.as_deref(), params.archived).await {
                 Ok(convs) => {
                     // Simple change detection: hash the updated_at timestamps
                     use std::hash::{Hash, Hasher};
@@ -212,9 +569,208 @@ pub async fn subscribe_conversations(
         }
     };
 
-    Sse::new(stream).keep_alive(
+    Ok(Sse::new(stream).keep_alive(
         KeepAlive::new()
             .interval(Duration::from_secs(15))
             .text("ping")
+    ))
+}
+
+// ============================================================================
+// Message Search
+// ============================================================================
+
+/// Characters of context kept on each side of the first match in a snippet
+const SNIPPET_CONTEXT_CHARS: usize = 60;
+
+const DEFAULT_SEARCH_LIMIT: i64 = 50;
+
+#[derive(Debug, Deserialize)]
+pub struct MessageSearchQuery {
+    pub q: String,
+    pub organization: Option<String>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MessageSearchResult {
+    pub conversation_id: String,
+    pub conversation_title: Option<String>,
+    pub message_id: String,
+    pub role: String,
+    /// "content" or "tool_result"
+    pub field: String,
+    /// Tool use the matching result belongs to, for `tool_result` hits
+    pub tool_use_id: Option<String>,
+    pub snippet: String,
+    /// Character ranges `[start, end)` of each match within the full field text
+    pub offsets: Vec<(usize, usize)>,
+    pub created_at: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MessageSearchResponse {
+    pub query: String,
+    pub results: Vec<MessageSearchResult>,
+}
+
+/// Character offsets of every case-insensitive occurrence of each query term
+pub fn find_match_offsets(text: &str, query: &str) -> Vec<(usize, usize)> {
+    let haystack: Vec<char> = text.chars().flat_map(|c| c.to_lowercase()).collect();
+    // Lowercasing can change lengths for a few scripts; fall back to no offsets there
+    if haystack.len() != text.chars().count() {
+        return Vec::new();
+    }
+
+    let mut offsets = Vec::new();
+    for term in query.split_whitespace() {
+        let needle: Vec<char> = term.trim_matches('"').chars().flat_map(|c| c.to_lowercase()).collect();
+        if needle.is_empty() || needle.len() > haystack.len() {
+            continue;
+        }
+        for start in 0..=haystack.len() - needle.len() {
+            if haystack[start..start + needle.len()] == needle[..] {
+                offsets.push((start, start + needle.len()));
+            }
+        }
+    }
+    offsets.sort_unstable();
+    offsets.dedup();
+    offsets
+}
+
+/// Window of text around the first match
+pub fn snippet_around(text: &str, offsets: &[(usize, usize)]) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let (start, end) = offsets.first().copied().unwrap_or((0, 0));
+    let from = start.saturating_sub(SNIPPET_CONTEXT_CHARS);
+    let to = (end + SNIPPET_CONTEXT_CHARS).min(chars.len());
+
+    let mut snippet: String = chars[from..to].iter().collect();
+    if from > 0 {
+        snippet.insert(0, '…');
+    }
+    if to < chars.len() {
+        snippet.push('…');
+    }
+    snippet
+}
+
+fn to_search_results(query: &str, hits: Vec<ticketing_system::MessageSearchHit>) -> Vec<MessageSearchResult> {
+    hits.into_iter()
+        .map(|hit| {
+            let offsets = find_match_offsets(&hit.text, query);
+            MessageSearchResult {
+                snippet: snippet_around(&hit.text, &offsets),
+                offsets,
+                conversation_id: hit.conversation_id,
+                conversation_title: hit.conversation_title,
+                message_id: hit.message_id,
+                role: hit.role,
+                field: hit.field,
+                tool_use_id: hit.tool_use_id,
+                created_at: hit.created_at,
+            }
+        })
+        .collect()
+}
+
+/// Search messages in one conversation (GET /api/conversations/:id/messages/search?q=)
+pub async fn search_conversation_messages(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(org): Extension<Organization>,
+    Path(id): Path<String>,
+    Query(params): Query<MessageSearchQuery>,
+) -> Result<Json<MessageSearchResponse>, (StatusCode, String)> {
+    load_conversation(&pool, &org, &id, false).await?;
+    let query = params.q.trim();
+    if query.is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "q must not be empty".to_string()));
+    }
+
+    let hits = conversations::search_messages(
+        &pool,
+        query,
+        Some(&id),
+        None,
+        params.limit.unwrap_or(DEFAULT_SEARCH_LIMIT),
+    )
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(Json(MessageSearchResponse {
+        query: query.to_string(),
+        results: to_search_results(query, hits),
+    }))
+}
+
+/// Search messages across the organization's conversations (GET /api/conversations/search?q=)
+pub async fn search_conversations(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(org): Extension<Organization>,
+    Query(params): Query<MessageSearchQuery>,
+) -> Result<Json<MessageSearchResponse>, (StatusCode, String)> {
+    let organization = org.resolve(params.organization)?;
+    let query = params.q.trim();
+    if query.is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "q must not be empty".to_string()));
+    }
+
+    let hits = conversations::search_messages(
+        &pool,
+        query,
+        None,
+        Some(&organization),
+        params.limit.unwrap_or(DEFAULT_SEARCH_LIMIT),
     )
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(Json(MessageSearchResponse {
+        query: query.to_string(),
+        results: to_search_results(query, hits),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_case_insensitive_term_offsets() {
+        let offsets = find_match_offsets("Plan the API; then test the api", "api plan");
+        assert_eq!(offsets, vec![(0, 4), (9, 12), (28, 31)]);
+        assert_eq!(snippet_around("short text", &[(0, 5)]), "short text");
+    }
+
+    #[test]
+    fn recovers_with_the_recorded_agent() {
+        assert!(matches!(ChatAgent::from_prompt_name("workspace-manager"), Some(ChatAgent::WorkspaceManager)));
+        assert!(matches!(ChatAgent::from_prompt_name("life-planner"), Some(ChatAgent::LifePlanner)));
+        assert!(ChatAgent::from_prompt_name("email").is_none());
+    }
+
+    #[test]
+    fn regenerates_with_the_conversation_agent() {
+        assert_eq!(ChatAgent::recorded(None).unwrap(), ChatAgent::WorkspaceManager);
+        assert_eq!(ChatAgent::recorded(Some("life-planner")).unwrap(), ChatAgent::LifePlanner);
+        assert_eq!(ChatAgent::recorded(Some("email")).unwrap_err().0, StatusCode::CONFLICT);
+
+        // The client only asks for a regeneration; it can't choose the agent
+        let req: UpdateMessageRequest = serde_json::from_str(r#"{"content":"hi","regenerate":true}"#).unwrap();
+        assert!(req.regenerate);
+        assert!(serde_json::from_str::<UpdateMessageRequest>(r#"{"content":"hi","regenerate":"life_planner"}"#).is_err());
+    }
+
+    #[tokio::test]
+    async fn only_one_generation_claims_a_conversation() {
+        let streams = &super::super::chat_stream::CHAT_STREAMS;
+        let id = format!("test-claim-{}", uuid::Uuid::new_v4());
+        let claimed = streams.claim(&id).await;
+        assert!(claimed.is_some());
+        assert!(streams.claim(&id).await.is_none());
+        streams.release(&id).await;
+        assert!(streams.claim(&id).await.is_some());
+        streams.release(&id).await;
+    }
 }
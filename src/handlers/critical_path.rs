@@ -0,0 +1,220 @@
+//! Epic dependency graph with its critical path, see `crate::critical_path`
+//!
+//! Blocking comes from the tickets' `blocked_by` lists, plus any `blocks`
+//! relations not yet migrated onto them. The workspace manager gets the
+//! critical paths of the organization's busiest epics as context.
+
+use axum::{
+    extract::{Extension, Path, State},
+    http::StatusCode,
+    Json,
+};
+use serde::Serialize;
+use serde_json::json;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use ticketing_system::{relationships, SqlitePool, Ticket};
+
+use super::ticket_relationships::blocking_edges;
+
+use crate::critical_path::{critical_path, Bottleneck, CriticalPath, Schedule, WorkItem};
+use crate::mcp_wrapper::call_mcp_tool;
+use crate::tenancy::Organization;
+
+#[derive(Debug, Serialize)]
+pub struct DependencyNode {
+    pub title: String,
+    pub status: String,
+    pub slice_id: String,
+    pub assignee: Option<String>,
+    #[serde(flatten)]
+    pub schedule: Schedule,
+    /// Tickets outside the epic that block this one
+    pub external_blockers: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DependencyEdge {
+    /// The blocking ticket
+    pub from: String,
+    pub to: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CriticalPathResponse {
+    pub epic_id: String,
+    pub total_hours: f64,
+    pub critical_path: Vec<String>,
+    pub bottlenecks: Vec<Bottleneck>,
+    /// Open tickets weighted with the default because they have no estimate
+    pub unestimated: Vec<String>,
+    /// Tickets caught in a blocking cycle, left out of `nodes`
+    pub cyclic: Vec<String>,
+    pub nodes: Vec<DependencyNode>,
+    pub edges: Vec<DependencyEdge>,
+}
+
+/// Epics whose critical paths go into the workspace manager's context
+const MAX_CONTEXT_EPICS: usize = 5;
+
+/// An organization's tickets and the blocking edges between them
+async fn blocking_graph(pool: &SqlitePool, organization: &str) -> anyhow::Result<(Vec<Ticket>, Vec<(String, String)>)> {
+    let tickets = ticketing_system::tickets::list_tickets_by_organization(pool, organization).await?;
+    let mut edges = blocking_edges(&tickets);
+    edges.extend(relationships::list_edges(pool, "blocks").await?);
+    let ids: HashSet<&str> = tickets.iter().map(|t| t.ticket_id.as_str()).collect();
+    edges.retain(|(from, to)| ids.contains(from.as_str()) && ids.contains(to.as_str()));
+    edges.sort();
+    edges.dedup();
+    Ok((tickets, edges))
+}
+
+/// Split an organization's edges into those within the epic, and blockers of
+/// the epic's tickets from outside it
+fn split_edges(
+    edges: &[(String, String)],
+    epic_ids: &HashSet<&str>,
+) -> (Vec<(String, String)>, HashMap<String, Vec<String>>) {
+    let mut within = Vec::new();
+    let mut external: HashMap<String, Vec<String>> = HashMap::new();
+    for (from, to) in edges {
+        match (epic_ids.contains(from.as_str()), epic_ids.contains(to.as_str())) {
+            (true, true) => within.push((from.clone(), to.clone())),
+            (false, true) => external.entry(to.clone()).or_default().push(from.clone()),
+            _ => {}
+        }
+    }
+    (within, external)
+}
+
+fn work_item(ticket: &Ticket) -> WorkItem {
+    let fields = serde_json::to_value(ticket).ok().and_then(|t| t.get("custom_fields").cloned());
+    WorkItem::new(&ticket.ticket_id, crate::deadlines::is_open(ticket), fields.as_ref())
+}
+
+/// The epic's blocking graph, scheduled by estimate, with its critical path
+/// and bottlenecks (GET /api/epics/:epic_id/critical-path)
+pub async fn get_epic_critical_path(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(org): Extension<Organization>,
+    Path(epic_id): Path<String>,
+) -> Result<Json<CriticalPathResponse>, (StatusCode, String)> {
+    if call_mcp_tool("get_epic", Some(json!({ "organization": org.name, "epic_id": epic_id }))).await.is_err() {
+        return Err((StatusCode::NOT_FOUND, "Epic not found".to_string()));
+    }
+    let (tickets, org_edges) = blocking_graph(&pool, &org.name)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to load blocking graph: {}", e)))?;
+    let tickets: Vec<Ticket> = tickets.into_iter().filter(|t| t.epic_id == epic_id).collect();
+
+    let ids: HashSet<&str> = tickets.iter().map(|t| t.ticket_id.as_str()).collect();
+    let (blocks, mut external_blockers) = split_edges(&org_edges, &ids);
+    let edges = blocks
+        .iter()
+        .map(|(from, to)| DependencyEdge { from: from.clone(), to: to.clone() })
+        .collect();
+
+    let items: Vec<WorkItem> = tickets.iter().map(work_item).collect();
+    let unestimated = items
+        .iter()
+        .filter(|item| !item.estimated && item.hours > 0.0)
+        .map(|item| item.ticket_id.clone())
+        .collect();
+    let result = critical_path(&items, &blocks);
+
+    let by_id: HashMap<&str, &Ticket> = tickets.iter().map(|t| (t.ticket_id.as_str(), t)).collect();
+    let nodes = result
+        .schedule
+        .into_iter()
+        .filter_map(|schedule| {
+            let ticket = by_id.get(schedule.ticket_id.as_str())?;
+            Some(DependencyNode {
+                title: ticket.title.clone(),
+                status: ticket.status.clone(),
+                slice_id: ticket.slice_id.clone(),
+                assignee: ticket.assignee.clone(),
+                external_blockers: external_blockers.remove(&ticket.ticket_id).unwrap_or_default(),
+                schedule,
+            })
+        })
+        .collect();
+
+    Ok(Json(CriticalPathResponse {
+        epic_id,
+        total_hours: result.total_hours,
+        critical_path: result.path,
+        bottlenecks: result.bottlenecks,
+        unestimated,
+        cyclic: result.cyclic,
+        nodes,
+        edges,
+    }))
+}
+
+/// Critical paths of the organization's epics with the most remaining work on
+/// them, rendered for the workspace manager. `None` when no epic has blocking
+/// chains.
+pub async fn critical_path_context(db: &SqlitePool, organization: &str) -> Option<String> {
+    let (tickets, edges) = match blocking_graph(db, organization).await {
+        Ok(graph) => graph,
+        Err(e) => {
+            tracing::warn!("Failed to load blocking graph for {}: {}", organization, e);
+            return None;
+        }
+    };
+    let mut epics: HashMap<&str, Vec<&Ticket>> = HashMap::new();
+    for ticket in &tickets {
+        epics.entry(ticket.epic_id.as_str()).or_default().push(ticket);
+    }
+
+    let mut paths: Vec<(&str, CriticalPath)> = epics
+        .into_iter()
+        .filter_map(|(epic_id, tickets)| {
+            let ids: HashSet<&str> = tickets.iter().map(|t| t.ticket_id.as_str()).collect();
+            let (blocks, _) = split_edges(&edges, &ids);
+            if blocks.is_empty() {
+                return None;
+            }
+            let items: Vec<WorkItem> = tickets.iter().copied().map(work_item).collect();
+            Some((epic_id, critical_path(&items, &blocks))).filter(|(_, path)| path.path.len() > 1)
+        })
+        .collect();
+    if paths.is_empty() {
+        return None;
+    }
+    paths.sort_by(|a, b| b.1.total_hours.total_cmp(&a.1.total_hours).then(a.0.cmp(b.0)));
+
+    let lines: Vec<String> = paths
+        .iter()
+        .take(MAX_CONTEXT_EPICS)
+        .map(|(epic_id, path)| {
+            let mut line = format!("- {}: {} ({:.0}h remaining)", epic_id, path.path.join(" → "), path.total_hours);
+            if !path.bottlenecks.is_empty() {
+                let bottlenecks: Vec<String> = path
+                    .bottlenecks
+                    .iter()
+                    .map(|b| format!("{} (holds up {} ticket(s), {:.0}h)", b.ticket_id, b.blocked_tickets, b.blocked_hours))
+                    .collect();
+                line.push_str(&format!("; bottlenecks: {}", bottlenecks.join(", ")));
+            }
+            line
+        })
+        .collect();
+    Some(format!("[Epic Critical Paths]\n{}", lines.join("\n")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_edges_at_the_epic_boundary() {
+        let edge = |a: &str, b: &str| (a.to_string(), b.to_string());
+        let edges = vec![edge("a", "b"), edge("x", "b"), edge("b", "y")];
+        let ids: HashSet<&str> = ["a", "b"].into_iter().collect();
+        let (within, external) = split_edges(&edges, &ids);
+        assert_eq!(within, vec![edge("a", "b")]);
+        assert_eq!(external.get("b"), Some(&vec!["x".to_string()]));
+        assert_eq!(external.len(), 1);
+    }
+}
@@ -1,11 +1,11 @@
 //! Daily plan REST API handlers
 
 use axum::{
-    extract::{Path, Query, State},
+    extract::{Extension, Path, Query, State},
     http::StatusCode,
     Json,
 };
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use sqlx::SqlitePool;
 use std::sync::Arc;
 
@@ -15,23 +15,132 @@ use ticketing_system::{
     UpdateDailyPlanItemRequest,
 };
 
+use crate::tenancy::Organization;
+
+/// Most days returned by the history endpoint
+const MAX_HISTORY_DAYS: i64 = 31;
+
 #[derive(Deserialize)]
 pub struct DateQuery {
     pub date: Option<String>,
+    /// Whose deadlines to include, defaults to the request's organization
+    pub organization: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct HistoryQuery {
+    /// First day, defaults to a week before `to`
+    pub from: Option<String>,
+    /// Last day, defaults to yesterday
+    pub to: Option<String>,
+}
+
+fn parse_date(date: &str) -> Result<chrono::NaiveDate, (StatusCode, String)> {
+    chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d")
+        .map_err(|_| (StatusCode::BAD_REQUEST, format!("Invalid date '{}', expected YYYY-MM-DD", date)))
+}
+
+/// An open ticket due on or before the plan's date
+#[derive(Serialize)]
+pub struct DailyPlanDeadline {
+    pub ticket_id: String,
+    pub epic_id: String,
+    pub slice_id: String,
+    pub organization: String,
+    pub title: String,
+    pub status: String,
+    pub due_date: String,
+    pub overdue: bool,
+}
+
+#[derive(Serialize)]
+pub struct DailyPlanResponse {
+    #[serde(flatten)]
+    pub plan: DailyPlanView,
+    pub deadlines: Vec<DailyPlanDeadline>,
 }
 
 /// GET /api/daily-plan?date=2026-02-12
+///
+/// Past days are served as they ended when a snapshot was stored (see
+/// `crate::plan_carryover`), and rebuilt from the current items otherwise.
 pub async fn get_daily_plan(
     State(db): State<Arc<SqlitePool>>,
+    Extension(org): Extension<Organization>,
     Query(query): Query<DateQuery>,
-) -> Result<Json<DailyPlanView>, (StatusCode, String)> {
-    let date = query.date.unwrap_or_else(|| chrono::Utc::now().format("%Y-%m-%d").to_string());
+) -> Result<Json<DailyPlanResponse>, (StatusCode, String)> {
+    let organization = org.resolve(query.organization.clone())?;
+    let today = chrono::Utc::now().date_naive();
+    let date = query.date.unwrap_or_else(|| today.format("%Y-%m-%d").to_string());
+    let day = parse_date(&date)?;
+
+    let snapshot = if day < today {
+        ticketing_system::daily_plan::get_plan_snapshot(&db, &date)
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+    } else {
+        None
+    };
+    let plan = match snapshot {
+        Some(plan) => plan,
+        None => ticketing_system::daily_plan::get_plan_for_date(&db, &date)
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?,
+    };
 
-    let plan = ticketing_system::daily_plan::get_plan_for_date(&db, &date)
+    let deadlines = crate::deadlines::upcoming_deadlines(&db, Some(&organization), day)
         .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .into_iter()
+        .filter_map(|t| {
+            let due_date = t.due_date?;
+            Some(DailyPlanDeadline {
+                overdue: due_date < date,
+                ticket_id: t.ticket_id,
+                epic_id: t.epic_id,
+                slice_id: t.slice_id,
+                organization: t.organization,
+                title: t.title,
+                status: t.status,
+                due_date,
+            })
+        })
+        .collect();
+
+    Ok(Json(DailyPlanResponse { plan, deadlines }))
+}
+
+/// GET /api/daily-plan/history?from=2026-02-01&to=2026-02-12
+///
+/// Stored plans of past days in the range, newest first, up to 31 days
+pub async fn get_daily_plan_history(
+    State(db): State<Arc<SqlitePool>>,
+    Query(query): Query<HistoryQuery>,
+) -> Result<Json<Vec<DailyPlanView>>, (StatusCode, String)> {
+    let to = match &query.to {
+        Some(to) => parse_date(to)?,
+        None => chrono::Utc::now().date_naive() - chrono::Duration::days(1),
+    };
+    let from = match &query.from {
+        Some(from) => parse_date(from)?,
+        None => to - chrono::Duration::days(6),
+    };
+    if from > to {
+        return Err((StatusCode::BAD_REQUEST, "from must not be after to".to_string()));
+    }
+    if (to - from).num_days() >= MAX_HISTORY_DAYS {
+        return Err((StatusCode::BAD_REQUEST, format!("At most {} days of history at a time", MAX_HISTORY_DAYS)));
+    }
 
-    Ok(Json(plan))
+    let plans = ticketing_system::daily_plan::list_plan_snapshots(
+        &db,
+        &from.format("%Y-%m-%d").to_string(),
+        &to.format("%Y-%m-%d").to_string(),
+    )
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(Json(plans))
 }
 
 #[derive(Deserialize)]
@@ -56,6 +165,13 @@ pub async fn toggle_daily_plan_item(
         .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
+    // Keep a past day's stored plan in step with late check-offs
+    if let Some(day) = parse_date(&req.date).ok().filter(|day| *day < chrono::Utc::now().date_naive()) {
+        if let Err(e) = crate::plan_carryover::snapshot_plan(&db, day).await {
+            tracing::warn!("Failed to update daily plan snapshot for {}: {:?}", req.date, e);
+        }
+    }
+
     Ok(Json(serde_json::json!({
         "item_id": req.item_id,
         "date": req.date,
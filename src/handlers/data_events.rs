@@ -1,5 +1,6 @@
 use axum::{
-    extract::{Query, State},
+    extract::{Extension, Query, State},
+    http::StatusCode,
     response::sse::{Event, KeepAlive, Sse},
 };
 use futures::stream::Stream;
@@ -9,7 +10,12 @@ use std::convert::Infallible;
 use std::hash::{Hash, Hasher};
 use std::sync::Arc;
 use std::time::Duration;
-use ticketing_system::{epics, slices, tickets, Epic, Slice, SqlitePool, Ticket};
+use tokio::sync::broadcast::error::TryRecvError;
+use ticketing_system::{epics, slices, tickets, Epic, Slice, SqlitePool, Ticket, User};
+
+use crate::deadlines::{self, DeadlineEvent};
+use crate::tenancy::Organization;
+use crate::user_events::USER_EVENTS;
 
 #[derive(Debug, Deserialize)]
 pub struct DataSubscribeQuery {
@@ -29,6 +35,9 @@ pub enum DataEvent {
     /// Full sync of tickets for selected slices
     #[serde(rename = "tickets")]
     Tickets { tickets: Vec<Ticket> },
+    /// A ticket became due soon or overdue
+    #[serde(rename = "deadline")]
+    Deadline { deadline: DeadlineEvent },
 }
 
 fn hash_epics(epics: &[Epic]) -> u64 {
@@ -72,16 +81,34 @@ fn hash_tickets(tickets: &[Ticket]) -> u64 {
 /// SSE endpoint for real-time data updates (epics, slices, tickets)
 pub async fn subscribe_data(
     State(pool): State<Arc<SqlitePool>>,
+    Extension(tenant): Extension<Organization>,
     Query(params): Query<DataSubscribeQuery>,
-) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, (StatusCode, String)> {
+    tenant.authorize(&params.organization)?;
     let org = params.organization;
 
+    let mut deadline_rx = deadlines::subscribe();
+
     let stream = async_stream::stream! {
         let mut last_epics_hash: u64 = 0;
         let mut last_slices_hash: u64 = 0;
         let mut last_tickets_hash: u64 = 0;
 
         loop {
+            // Deadline reminders fired since the last poll
+            loop {
+                match deadline_rx.try_recv() {
+                    Ok(deadline) if deadline.organization == org => {
+                        let event = DataEvent::Deadline { deadline };
+                        if let Ok(json) = serde_json::to_string(&event) {
+                            yield Ok(Event::default().data(json));
+                        }
+                    }
+                    Ok(_) | Err(TryRecvError::Lagged(_)) => {}
+                    Err(_) => break,
+                }
+            }
+
             // Check epics
             if let Ok(epic_list) = epics::list_epics(&pool, Some(&org)).await {
                 let hash = hash_epics(&epic_list);
@@ -131,6 +158,29 @@ pub async fn subscribe_data(
         }
     };
 
+    Ok(Sse::new(stream).keep_alive(
+        KeepAlive::new()
+            .interval(Duration::from_secs(15))
+            .text("ping"),
+    ))
+}
+
+/// GET /api/users/me/subscribe
+/// SSE endpoint for events about the tickets and conversations the
+/// authenticated user follows (see `user_events`)
+pub async fn subscribe_user_events(
+    Extension(user): Extension<User>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let mut subscription = USER_EVENTS.subscribe(&user.user_id);
+
+    let stream = async_stream::stream! {
+        while let Some(event) = subscription.recv().await {
+            if let Ok(json) = serde_json::to_string(&event) {
+                yield Ok(Event::default().data(json));
+            }
+        }
+    };
+
     Sse::new(stream).keep_alive(
         KeepAlive::new()
             .interval(Duration::from_secs(15))
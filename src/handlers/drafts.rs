@@ -1,11 +1,30 @@
 use axum::{
-    extract::{Path, Query, State},
+    extract::{Extension, Path, Query, State},
     http::StatusCode,
     Json,
 };
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
-use ticketing_system::{drafts, email_thread_tickets, CreateDraftRequest, EmailDraft, LinkThreadTicketRequest, SqlitePool, UpdateDraftRequest};
+use ticketing_system::{drafts, email_thread_tickets, CreateDraftRequest, EmailDraft, LinkThreadTicketRequest, SqlitePool, UpdateDraftRequest, User};
+
+use crate::email_style::EmailStyle;
+use crate::tenancy::Organization;
+
+use super::email_grants::{filter_readable, require_email_permission, EmailPermission};
+
+/// Load a draft and check the user holds `required` on its mailbox
+async fn get_accessible_draft(
+    pool: &SqlitePool,
+    user: &User,
+    id: i64,
+    required: EmailPermission,
+) -> Result<EmailDraft, (StatusCode, String)> {
+    let draft = drafts::get_draft_by_id(pool, id)
+        .await
+        .map_err(|e| (StatusCode::NOT_FOUND, e.to_string()))?;
+    require_email_permission(pool, user, &draft.from_address, required).await?;
+    Ok(draft)
+}
 
 #[derive(Debug, Deserialize)]
 pub struct ListDraftsQuery {
@@ -21,6 +40,7 @@ pub struct DraftListResponse {
 /// List drafts (GET /api/drafts)
 pub async fn list_drafts(
     State(pool): State<Arc<SqlitePool>>,
+    Extension(user): Extension<User>,
     Query(params): Query<ListDraftsQuery>,
 ) -> Result<Json<DraftListResponse>, (StatusCode, String)> {
     let include_all = params.include_all.unwrap_or(false);
@@ -28,6 +48,7 @@ pub async fn list_drafts(
     let draft_list = drafts::list_drafts(&pool, include_all)
         .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let draft_list = filter_readable(&pool, &user, draft_list, |d| d.from_address.as_str()).await?;
 
     let total = draft_list.len() as i64;
 
@@ -40,20 +61,24 @@ pub async fn list_drafts(
 /// Get single draft by ID (GET /api/drafts/:id)
 pub async fn get_draft(
     State(pool): State<Arc<SqlitePool>>,
+    Extension(user): Extension<User>,
     Path(id): Path<i64>,
 ) -> Result<Json<EmailDraft>, (StatusCode, String)> {
-    let draft = drafts::get_draft_by_id(&pool, id)
-        .await
-        .map_err(|e| (StatusCode::NOT_FOUND, e.to_string()))?;
+    let draft = get_accessible_draft(&pool, &user, id, EmailPermission::Read).await?;
 
     Ok(Json(draft))
 }
 
 /// Create a draft (POST /api/drafts)
+///
+/// `tone`, `length` and `language` record the style the draft was written in.
 pub async fn create_draft(
     State(pool): State<Arc<SqlitePool>>,
+    Extension(user): Extension<User>,
     Json(req): Json<CreateDraftRequest>,
 ) -> Result<(StatusCode, Json<EmailDraft>), (StatusCode, String)> {
+    require_email_permission(&pool, &user, &req.from_address, EmailPermission::Read).await?;
+    EmailStyle { tone: req.tone.clone(), length: req.length.clone(), language: req.language.clone() }.check()?;
     let draft = drafts::create_draft(&pool, &req)
         .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
@@ -77,9 +102,11 @@ pub async fn create_draft(
 /// Update a draft (PATCH /api/drafts/:id)
 pub async fn update_draft(
     State(pool): State<Arc<SqlitePool>>,
+    Extension(user): Extension<User>,
     Path(id): Path<i64>,
     Json(req): Json<UpdateDraftRequest>,
 ) -> Result<Json<EmailDraft>, (StatusCode, String)> {
+    get_accessible_draft(&pool, &user, id, EmailPermission::Read).await?;
     let draft = drafts::update_draft(&pool, id, &req)
         .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
@@ -95,6 +122,7 @@ pub struct UpdateStatusRequest {
 /// Update draft status (POST /api/drafts/:id/status)
 pub async fn update_draft_status(
     State(pool): State<Arc<SqlitePool>>,
+    Extension(user): Extension<User>,
     Path(id): Path<i64>,
     Json(req): Json<UpdateStatusRequest>,
 ) -> Result<StatusCode, (StatusCode, String)> {
@@ -102,6 +130,8 @@ pub async fn update_draft_status(
     if !["draft", "sent", "discarded"].contains(&req.status.as_str()) {
         return Err((StatusCode::BAD_REQUEST, "Invalid status".to_string()));
     }
+    let required = if req.status == "sent" { EmailPermission::Send } else { EmailPermission::Read };
+    get_accessible_draft(&pool, &user, id, required).await?;
 
     drafts::update_draft_status(&pool, id, &req.status)
         .await
@@ -113,8 +143,10 @@ pub async fn update_draft_status(
 /// Delete a draft (DELETE /api/drafts/:id)
 pub async fn delete_draft(
     State(pool): State<Arc<SqlitePool>>,
+    Extension(user): Extension<User>,
     Path(id): Path<i64>,
 ) -> Result<StatusCode, (StatusCode, String)> {
+    get_accessible_draft(&pool, &user, id, EmailPermission::Read).await?;
     drafts::delete_draft(&pool, id)
         .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
@@ -125,18 +157,39 @@ pub async fn delete_draft(
 /// Send a draft via SES (POST /api/drafts/:id/send)
 pub async fn send_draft(
     State(pool): State<Arc<SqlitePool>>,
+    Extension(user): Extension<User>,
+    Extension(org): Extension<Organization>,
     Path(id): Path<i64>,
 ) -> Result<Json<SendDraftResponse>, (StatusCode, String)> {
-    use aws_sdk_sesv2::types::{Body, Content, Destination, EmailContent, Message};
+    // Get the draft; sending requires send access to its mailbox
+    let draft = get_accessible_draft(&pool, &user, id, EmailPermission::Send).await?;
+    let message_id = deliver_draft(&pool, &org.name, draft).await?;
 
-    // Get the draft
-    let draft = drafts::get_draft_by_id(&pool, id)
-        .await
-        .map_err(|e| (StatusCode::NOT_FOUND, e.to_string()))?;
+    Ok(Json(SendDraftResponse {
+        message_id,
+        success: true,
+    }))
+}
 
+/// Send a draft via SES, file it in Sent and link it to its ticket.
+/// Returns the outbound message ID. Mailbox permissions are the caller's job.
+pub async fn deliver_draft(
+    pool: &SqlitePool,
+    organization: &str,
+    draft: EmailDraft,
+) -> Result<String, (StatusCode, String)> {
+    use aws_sdk_sesv2::types::{Body, Content, Destination, EmailContent, Message};
+
+    let id = draft.id;
     if draft.status != "draft" {
         return Err((StatusCode::BAD_REQUEST, "Draft has already been sent or discarded".to_string()));
     }
+    let recipients: Vec<&str> = std::iter::once(draft.to_address.as_str())
+        .chain(draft.cc_address.as_deref())
+        .flat_map(|addresses| addresses.split(','))
+        .collect();
+    crate::email_suppressions::check_recipients(pool, organization, &recipients).await?;
+    crate::quotas::check_email(pool, organization).await?;
 
     // Load AWS config
     let config = aws_config::defaults(aws_config::BehaviorVersion::latest())
@@ -208,11 +261,15 @@ pub async fn send_draft(
 
     let message_id = result.message_id().unwrap_or("unknown").to_string();
     tracing::info!("Draft {} sent successfully, message_id: {}", id, message_id);
+    crate::quotas::record_email_sent(pool, organization).await;
 
-    // Mark draft as sent
-    drafts::update_draft_status(&pool, id, "sent")
+    // Mark draft as sent and remember the outbound message ID for bounce matching
+    drafts::update_draft_status(pool, id, "sent")
         .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    if let Err(e) = drafts::set_draft_message_id(pool, id, &message_id).await {
+        tracing::warn!("Failed to record message_id for draft {}: {}", id, e);
+    }
 
     // Store in Sent folder
     let now = chrono::Utc::now().timestamp();
@@ -249,10 +306,13 @@ pub async fn send_draft(
         received_at: now,
         thread_id: Some(thread_id.clone()),
         in_reply_to: None,
+        header_message_id: None,
     };
 
-    if let Err(e) = ticketing_system::emails::create_email(&pool, &create_req).await {
+    if let Err(e) = ticketing_system::emails::create_email(pool, &create_req).await {
         tracing::warn!("Failed to store sent email in database: {}", e);
+    } else if let Err(e) = ticketing_system::emails::update_delivery_status(pool, &message_id, "sent", None).await {
+        tracing::warn!("Failed to set delivery status for {}: {}", message_id, e);
     }
 
     // Link thread to ticket if draft had a ticket_id
@@ -263,7 +323,7 @@ pub async fn send_draft(
             epic_id: draft.epic_id.clone(),
             slice_id: draft.slice_id.clone(),
         };
-        if let Err(e) = email_thread_tickets::link_thread_to_ticket(&pool, &link_req).await {
+        if let Err(e) = email_thread_tickets::link_thread_to_ticket(pool, &link_req).await {
             tracing::warn!("Failed to link thread to ticket: {}", e);
         } else {
             tracing::info!("Linked thread {} to ticket {}", thread_id, ticket_id);
@@ -271,7 +331,7 @@ pub async fn send_draft(
 
         // Log email sent to ticket history
         if let Err(e) = ticketing_system::ticket_history::log_email_sent(
-            &pool,
+            pool,
             ticket_id,
             id,
             &history_to_address,
@@ -282,10 +342,7 @@ pub async fn send_draft(
         }
     }
 
-    Ok(Json(SendDraftResponse {
-        message_id,
-        success: true,
-    }))
+    Ok(message_id)
 }
 
 #[derive(Debug, Serialize)]
@@ -293,3 +350,68 @@ pub struct SendDraftResponse {
     pub message_id: String,
     pub success: bool,
 }
+
+// ============================================================================
+// Draft Variants
+// ============================================================================
+
+#[derive(Debug, Serialize)]
+pub struct DraftVariantsResponse {
+    pub variant_group: String,
+    pub drafts: Vec<EmailDraft>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SelectVariantRequest {
+    pub draft_id: i64,
+}
+
+/// List the sibling drafts of a variant group (GET /api/drafts/variants/:group_id)
+pub async fn list_draft_variants(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(user): Extension<User>,
+    Path(group_id): Path<String>,
+) -> Result<Json<DraftVariantsResponse>, (StatusCode, String)> {
+    let variants = drafts::list_variants(&pool, &group_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let variants = filter_readable(&pool, &user, variants, |d| d.from_address.as_str()).await?;
+    if variants.is_empty() {
+        return Err((StatusCode::NOT_FOUND, "Variant group not found".to_string()));
+    }
+
+    Ok(Json(DraftVariantsResponse {
+        variant_group: group_id,
+        drafts: variants,
+    }))
+}
+
+/// Keep one variant and discard its siblings (POST /api/drafts/variants/:group_id/select)
+pub async fn select_draft_variant(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(user): Extension<User>,
+    Path(group_id): Path<String>,
+    Json(req): Json<SelectVariantRequest>,
+) -> Result<Json<EmailDraft>, (StatusCode, String)> {
+    let variants = drafts::list_variants(&pool, &group_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let variants = filter_readable(&pool, &user, variants, |d| d.from_address.as_str()).await?;
+
+    let selected = variants
+        .iter()
+        .find(|d| d.id == req.draft_id)
+        .cloned()
+        .ok_or_else(|| (StatusCode::NOT_FOUND, "Draft is not part of this variant group".to_string()))?;
+    if selected.status != "draft" {
+        return Err((StatusCode::BAD_REQUEST, "Draft has already been sent or discarded".to_string()));
+    }
+
+    for sibling in variants.iter().filter(|d| d.id != req.draft_id && d.status == "draft") {
+        drafts::update_draft_status(&pool, sibling.id, "discarded")
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    }
+
+    Ok(Json(selected))
+}
@@ -0,0 +1,110 @@
+//! Pages behind the links in approval emails, see `crate::email_approvals`.
+//! They're public: the signed token is the credential.
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::Html,
+    Form,
+};
+use serde::Deserialize;
+use std::sync::Arc;
+use ticketing_system::{ApprovalLink, SqlitePool};
+
+use crate::email_approvals::{decide, resolve_token, DECISION_APPROVE, DECISION_REJECT};
+use crate::handlers::escape_html;
+
+type PageResult = Result<Html<String>, (StatusCode, Html<String>)>;
+
+#[derive(Debug, Deserialize)]
+pub struct EmailApprovalQuery {
+    /// `approve` or `reject`, from the link that was followed
+    pub decision: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct EmailRejectForm {
+    pub feedback: Option<String>,
+}
+
+fn page(title: &str, body: &str) -> Html<String> {
+    Html(format!(
+        "<!DOCTYPE html><html><head><meta charset=\"utf-8\"><meta name=\"viewport\" content=\"width=device-width\">\
+<title>{0}</title></head><body style=\"font-family:system-ui,sans-serif;max-width:30rem;margin:2rem auto;padding:0 1rem\">\
+<h1>{0}</h1>{1}</body></html>",
+        escape_html(title),
+        body
+    ))
+}
+
+fn error_page(status: StatusCode, message: &str) -> (StatusCode, Html<String>) {
+    (status, page("Can't use this link", &format!("<p>{}</p>", escape_html(message))))
+}
+
+async fn resolve(db: &SqlitePool, token: &str) -> Result<ApprovalLink, (StatusCode, Html<String>)> {
+    resolve_token(db, token)
+        .await
+        .map_err(|e| error_page(StatusCode::GONE, &e.to_string()))
+}
+
+/// GET /api/email-approvals/:token
+///
+/// Confirmation page for the decision in the link. Only the form on it
+/// decides, since mail scanners open links before the approver does.
+pub async fn get_email_approval(
+    State(db): State<Arc<SqlitePool>>,
+    Path(token): Path<String>,
+    Query(query): Query<EmailApprovalQuery>,
+) -> PageResult {
+    let link = resolve(&db, &token).await?;
+    let ticket = ticketing_system::tickets::get_ticket_by_id(&db, &link.ticket_id)
+        .await
+        .ok()
+        .flatten()
+        .ok_or_else(|| error_page(StatusCode::GONE, "The ticket no longer exists"))?;
+
+    let summary = format!(
+        "<p>Step <strong>{}</strong> on <strong>{}</strong> is waiting for approval by {}.</p>",
+        escape_html(&link.step_id),
+        escape_html(&ticket.title),
+        escape_html(&link.approver_email)
+    );
+    let token = escape_html(&token);
+    let form = if query.decision.as_deref() == Some(DECISION_REJECT) {
+        format!(
+            "<form method=\"post\" action=\"/api/email-approvals/{}/reject\">\
+<p><label>Feedback for the next attempt (optional)<br><textarea name=\"feedback\" rows=\"4\" style=\"width:100%\"></textarea></label></p>\
+<button type=\"submit\">Reject</button></form>",
+            token
+        )
+    } else {
+        format!(
+            "<form method=\"post\" action=\"/api/email-approvals/{}/approve\"><button type=\"submit\">Approve</button></form>",
+            token
+        )
+    };
+    Ok(page("Review step", &format!("{}{}", summary, form)))
+}
+
+async fn decide_page(db: &SqlitePool, token: &str, decision: &str, feedback: Option<String>) -> PageResult {
+    let link = resolve(db, token).await?;
+    decide(db, &link, decision, feedback)
+        .await
+        .map_err(|e| error_page(StatusCode::CONFLICT, &e.to_string()))?;
+    let done = if decision == DECISION_APPROVE { "Approved" } else { "Rejected" };
+    Ok(page(done, &format!("<p>{} step <strong>{}</strong>. You can close this page.</p>", done, escape_html(&link.step_id))))
+}
+
+/// POST /api/email-approvals/:token/approve
+pub async fn approve_by_email(State(db): State<Arc<SqlitePool>>, Path(token): Path<String>) -> PageResult {
+    decide_page(&db, &token, DECISION_APPROVE, None).await
+}
+
+/// POST /api/email-approvals/:token/reject
+pub async fn reject_by_email(
+    State(db): State<Arc<SqlitePool>>,
+    Path(token): Path<String>,
+    Form(form): Form<EmailRejectForm>,
+) -> PageResult {
+    decide_page(&db, &token, DECISION_REJECT, form.feedback).await
+}
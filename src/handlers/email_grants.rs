@@ -0,0 +1,255 @@
+//! Per-user permissions on email accounts.
+//!
+//! An email account belongs to the organization set in its
+//! `email-accounts.json` entry, or to the instance admins without one. A
+//! mailbox with no grants is open to members of that organization (or to
+//! instance admins), so existing setups keep working. Once any grant exists
+//! for a mailbox, only users holding a grant can use it, at the level they
+//! were granted: `read` (view emails, work on drafts), `send` (also send as
+//! the mailbox) or `manage` (also delete emails and change grants).
+
+use axum::{
+    extract::{Extension, Path, State},
+    http::StatusCode,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use ticketing_system::{email_grants, EmailGrant, SqlitePool, UpsertEmailGrantRequest, User};
+
+use crate::email_fetcher::find_account;
+use crate::tenancy::has_standing;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EmailPermission {
+    Read,
+    Send,
+    Manage,
+}
+
+impl EmailPermission {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EmailPermission::Read => "read",
+            EmailPermission::Send => "send",
+            EmailPermission::Manage => "manage",
+        }
+    }
+
+    fn parse(s: &str) -> Option<Self> {
+        match s {
+            "read" => Some(EmailPermission::Read),
+            "send" => Some(EmailPermission::Send),
+            "manage" => Some(EmailPermission::Manage),
+            _ => None,
+        }
+    }
+}
+
+/// What `user_id` may do with `grants` (all grants of one mailbox).
+/// `None` means no access; an ungoverned mailbox grants everything to users
+/// with standing on it (see `mailbox_standing`) and nothing to anyone else.
+fn effective_permission(grants: &[EmailGrant], user_id: &str, standing: bool) -> Option<EmailPermission> {
+    if grants.is_empty() {
+        return standing.then_some(EmailPermission::Manage);
+    }
+    grants
+        .iter()
+        .filter(|g| g.user_id == user_id)
+        .filter_map(|g| EmailPermission::parse(&g.permission))
+        .max()
+}
+
+/// Whether the user belongs to the organization owning the mailbox, or is an
+/// instance admin for accounts without one (or no longer configured)
+async fn mailbox_standing(pool: &SqlitePool, user: &User, mailbox: &str) -> Result<bool, (StatusCode, String)> {
+    let owner = find_account(mailbox).await.and_then(|account| account.organization);
+    has_standing(pool, &user.user_id, owner.as_deref())
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
+
+async fn load_grants(
+    pool: &SqlitePool,
+    user: &User,
+    mailbox: &str,
+) -> Result<(Vec<EmailGrant>, bool), (StatusCode, String)> {
+    let grants = email_grants::list_grants(pool, mailbox)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    // Only needed while nobody holds a grant
+    let standing = grants.is_empty() && mailbox_standing(pool, user, mailbox).await?;
+    Ok((grants, standing))
+}
+
+/// The user's permission on a mailbox
+pub async fn email_permission(
+    pool: &SqlitePool,
+    user: &User,
+    mailbox: &str,
+) -> Result<Option<EmailPermission>, (StatusCode, String)> {
+    let (grants, standing) = load_grants(pool, user, mailbox).await?;
+    Ok(effective_permission(&grants, &user.user_id, standing))
+}
+
+/// Fail with 403 unless the user holds at least `required` on the mailbox
+pub async fn require_email_permission(
+    pool: &SqlitePool,
+    user: &User,
+    mailbox: &str,
+    required: EmailPermission,
+) -> Result<(), (StatusCode, String)> {
+    match email_permission(pool, user, mailbox).await? {
+        Some(permission) if permission >= required => Ok(()),
+        _ => Err((
+            StatusCode::FORBIDDEN,
+            format!("You need {} access to {}", required.as_str(), mailbox),
+        )),
+    }
+}
+
+/// Filter items down to those in mailboxes the user can read
+pub async fn filter_readable<T>(
+    pool: &SqlitePool,
+    user: &User,
+    items: Vec<T>,
+    mailbox: impl Fn(&T) -> &str,
+) -> Result<Vec<T>, (StatusCode, String)> {
+    let mut readable = std::collections::HashMap::new();
+    let mut kept = Vec::with_capacity(items.len());
+    for item in items {
+        let name = mailbox(&item).to_string();
+        let allowed = match readable.get(&name) {
+            Some(allowed) => *allowed,
+            None => {
+                let allowed = email_permission(pool, user, &name).await?.is_some();
+                readable.insert(name, allowed);
+                allowed
+            }
+        };
+        if allowed {
+            kept.push(item);
+        }
+    }
+    Ok(kept)
+}
+
+#[derive(Debug, Serialize)]
+pub struct EmailGrantsResponse {
+    pub mailbox: String,
+    pub grants: Vec<EmailGrant>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SetEmailGrantRequest {
+    pub permission: EmailPermission,
+}
+
+/// List grants on a mailbox (GET /api/email-accounts/:mailbox/grants)
+pub async fn list_email_grants(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(user): Extension<User>,
+    Path(mailbox): Path<String>,
+) -> Result<Json<EmailGrantsResponse>, (StatusCode, String)> {
+    require_email_permission(&pool, &user, &mailbox, EmailPermission::Manage).await?;
+    let grants = email_grants::list_grants(&pool, &mailbox)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Ok(Json(EmailGrantsResponse { mailbox, grants }))
+}
+
+/// Grant or change a user's permission (PUT /api/email-accounts/:mailbox/grants/:user_id)
+///
+/// Only a member of the organization owning the mailbox (or an instance
+/// admin) may make its first grant, which also gives them `manage`, so
+/// whoever locks an account down keeps control of it.
+pub async fn set_email_grant(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(user): Extension<User>,
+    Path((mailbox, user_id)): Path<(String, String)>,
+    Json(req): Json<SetEmailGrantRequest>,
+) -> Result<Json<EmailGrantsResponse>, (StatusCode, String)> {
+    let (grants, standing) = load_grants(&pool, &user, &mailbox).await?;
+    if effective_permission(&grants, &user.user_id, standing) != Some(EmailPermission::Manage) {
+        return Err((StatusCode::FORBIDDEN, format!("You need manage access to {}", mailbox)));
+    }
+
+    let mut upserts = vec![(user_id, req.permission)];
+    if grants.is_empty() && upserts[0].0 != user.user_id {
+        upserts.push((user.user_id.clone(), EmailPermission::Manage));
+    }
+    for (grantee, permission) in upserts {
+        email_grants::upsert_grant(
+            &pool,
+            &UpsertEmailGrantRequest {
+                mailbox: mailbox.clone(),
+                user_id: grantee,
+                permission: permission.as_str().to_string(),
+                granted_by: Some(user.user_id.clone()),
+            },
+        )
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    }
+
+    let grants = email_grants::list_grants(&pool, &mailbox)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Ok(Json(EmailGrantsResponse { mailbox, grants }))
+}
+
+/// Revoke a user's grant (DELETE /api/email-accounts/:mailbox/grants/:user_id)
+pub async fn delete_email_grant(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(user): Extension<User>,
+    Path((mailbox, user_id)): Path<(String, String)>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let (grants, standing) = load_grants(&pool, &user, &mailbox).await?;
+    if effective_permission(&grants, &user.user_id, standing) != Some(EmailPermission::Manage) {
+        return Err((StatusCode::FORBIDDEN, format!("You need manage access to {}", mailbox)));
+    }
+    // Removing the last manager would leave nobody able to change grants
+    let remaining_managers = grants
+        .iter()
+        .filter(|g| g.user_id != user_id && g.permission == EmailPermission::Manage.as_str())
+        .count();
+    if remaining_managers == 0 && grants.len() > 1 {
+        return Err((StatusCode::CONFLICT, "Cannot remove the last manager of a mailbox".to_string()));
+    }
+
+    let deleted = email_grants::delete_grant(&pool, &mailbox, &user_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    if !deleted {
+        return Err((StatusCode::NOT_FOUND, "Grant not found".to_string()));
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn grant(user_id: &str, permission: &str) -> EmailGrant {
+        EmailGrant {
+            mailbox: "support@example.com".to_string(),
+            user_id: user_id.to_string(),
+            permission: permission.to_string(),
+            granted_by: None,
+            created_at: String::new(),
+        }
+    }
+
+    #[test]
+    fn ungoverned_mailbox_is_open_to_its_organization_and_grants_restrict() {
+        assert_eq!(effective_permission(&[], "member", true), Some(EmailPermission::Manage));
+        assert_eq!(effective_permission(&[], "outsider", false), None);
+
+        let grants = vec![grant("ana", "read"), grant("ana", "send"), grant("bo", "read")];
+        assert_eq!(effective_permission(&grants, "ana", false), Some(EmailPermission::Send));
+        assert_eq!(effective_permission(&grants, "bo", false), Some(EmailPermission::Read));
+        assert_eq!(effective_permission(&grants, "cy", true), None);
+        assert!(EmailPermission::Manage > EmailPermission::Send);
+    }
+}
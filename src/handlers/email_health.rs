@@ -0,0 +1,26 @@
+//! Sync health of a synced email account, as recorded by the fetcher
+
+use axum::{
+    extract::{Extension, Path, State},
+    http::StatusCode,
+    Json,
+};
+use std::sync::Arc;
+use ticketing_system::{SqlitePool, User};
+
+use super::email_grants::{require_email_permission, EmailPermission};
+use crate::email_fetcher;
+use crate::email_health::{self, AccountHealth};
+
+/// GET /api/email-accounts/:mailbox/health
+pub async fn get_email_account_health(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(user): Extension<User>,
+    Path(mailbox): Path<String>,
+) -> Result<Json<AccountHealth>, (StatusCode, String)> {
+    require_email_permission(&pool, &user, &mailbox, EmailPermission::Read).await?;
+    let account = email_fetcher::find_account(&mailbox)
+        .await
+        .ok_or((StatusCode::NOT_FOUND, format!("{} is not a synced mailbox", mailbox)))?;
+    Ok(Json(email_health::account_health(&account.email).await))
+}
@@ -0,0 +1,150 @@
+//! Suggested replies to an email, see `crate::email_replies`
+
+use axum::{
+    extract::{Extension, Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use ticketing_system::{drafts, emails, CreateDraftRequest, EmailDraft, SqlitePool, User};
+
+use super::email_grants::{require_email_permission, EmailPermission};
+use crate::email_replies::{cached_suggestions, reply_subject, suggest_replies, SuggestedReply};
+use crate::email_style::EmailStyle;
+use crate::tenancy::Organization;
+
+#[derive(Debug, Deserialize)]
+pub struct SuggestedRepliesQuery {
+    /// Run the agent again instead of returning cached suggestions
+    #[serde(default)]
+    pub refresh: bool,
+    pub tone: Option<String>,
+    pub length: Option<String>,
+    pub language: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SuggestedRepliesResponse {
+    pub email_id: i64,
+    pub suggestions: Vec<SuggestedReply>,
+    /// Whether these came from the cache rather than a fresh agent run
+    pub cached: bool,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct AcceptSuggestedReplyRequest {
+    /// Replace the suggestion's text, e.g. after a quick edit
+    pub body: Option<String>,
+    pub ticket_id: Option<String>,
+}
+
+/// Suggest replies to an email (GET /api/emails/:id/suggested-replies)
+///
+/// Optional `tone`, `length` and `language` set the replies' style; cached
+/// suggestions are only reused when they were written in the same one.
+pub async fn get_suggested_replies(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(user): Extension<User>,
+    Extension(org): Extension<Organization>,
+    Path(id): Path<i64>,
+    Query(params): Query<SuggestedRepliesQuery>,
+) -> Result<Json<SuggestedRepliesResponse>, (StatusCode, String)> {
+    let email = emails::get_email_by_id(&pool, id)
+        .await
+        .map_err(|e| (StatusCode::NOT_FOUND, e.to_string()))?;
+    let style = EmailStyle { tone: params.tone, length: params.length, language: params.language };
+    style.check()?;
+    require_email_permission(&pool, &user, &email.mailbox, EmailPermission::Read).await?;
+
+    if !params.refresh {
+        let cached = cached_suggestions(&email).filter(|s| s.iter().all(|reply| reply.style == style));
+        if let Some(suggestions) = cached {
+            return Ok(Json(SuggestedRepliesResponse { email_id: id, suggestions, cached: true }));
+        }
+    }
+
+    // The mailbox's organization pays for its replies; instance mailboxes bill the caller's
+    let organization = crate::email_fetcher::find_account(&email.mailbox)
+        .await
+        .and_then(|account| account.organization)
+        .unwrap_or(org.name);
+    crate::quotas::check_agent_run(&pool, &organization).await?;
+    let suggestions = suggest_replies(&pool, &organization, &email, &style)
+        .await
+        .map_err(|e| (StatusCode::BAD_GATEWAY, format!("Failed to suggest replies: {:#}", e)))?;
+    Ok(Json(SuggestedRepliesResponse { email_id: id, suggestions, cached: false }))
+}
+
+/// Turn a suggested reply into a draft reply from the email's mailbox
+/// (POST /api/emails/:id/suggested-replies/:kind/accept)
+pub async fn accept_suggested_reply(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(user): Extension<User>,
+    Extension(org): Extension<Organization>,
+    Path((id, kind)): Path<(i64, String)>,
+    body: Option<Json<AcceptSuggestedReplyRequest>>,
+) -> Result<(StatusCode, Json<EmailDraft>), (StatusCode, String)> {
+    let req = body.map(|Json(req)| req).unwrap_or_default();
+    let email = emails::get_email_by_id(&pool, id)
+        .await
+        .map_err(|e| (StatusCode::NOT_FOUND, e.to_string()))?;
+    require_email_permission(&pool, &user, &email.mailbox, EmailPermission::Read).await?;
+
+    let suggestion = cached_suggestions(&email)
+        .unwrap_or_default()
+        .into_iter()
+        .find(|s| s.kind == kind)
+        .ok_or_else(|| (StatusCode::NOT_FOUND, format!("No '{}' reply suggested for this email", kind)))?;
+
+    let ticket = match &req.ticket_id {
+        Some(ticket_id) => Some(
+            ticketing_system::tickets::get_ticket_by_id(&pool, ticket_id)
+                .await
+                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+                .ok_or_else(|| (StatusCode::NOT_FOUND, "Ticket not found".to_string()))?,
+        ),
+        None => None,
+    };
+    if let Some(ticket) = &ticket {
+        org.authorize(&ticket.organization)?;
+    }
+    let style = suggestion.style;
+    let draft = drafts::create_draft(
+        &pool,
+        &CreateDraftRequest {
+            from_address: email.mailbox.clone(),
+            to_address: email.from_address.clone(),
+            cc_address: None,
+            subject: reply_subject(email.subject.as_deref()),
+            body: req.body.filter(|b| !b.trim().is_empty()).unwrap_or(suggestion.body),
+            ticket_id: ticket.as_ref().map(|t| t.ticket_id.clone()),
+            epic_id: ticket.as_ref().map(|t| t.epic_id.clone()),
+            slice_id: ticket.as_ref().map(|t| t.slice_id.clone()),
+            variant_group: None,
+            variant_label: None,
+            tone: style.tone,
+            length: style.length,
+            language: style.language,
+        },
+    )
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    if let Some(ticket_id) = &draft.ticket_id {
+        if let Err(e) = ticketing_system::ticket_history::log_draft_created(
+            &pool,
+            ticket_id,
+            draft.id,
+            &draft.to_address,
+            &draft.subject,
+        )
+        .await
+        {
+            tracing::warn!("Failed to log draft creation to ticket history: {}", e);
+        }
+    }
+    tracing::info!("Drafted '{}' reply to email {} as draft {}", kind, id, draft.id);
+
+    Ok((StatusCode::CREATED, Json(draft)))
+}
@@ -0,0 +1,95 @@
+//! Email suppression list endpoints: view, add and remove addresses the
+//! organization must not email. Sends to listed addresses are refused in
+//! `deliver_email` and `deliver_draft`.
+
+use axum::{
+    extract::{Extension, Path, State},
+    http::StatusCode,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use ticketing_system::{email_suppressions, CreateEmailSuppressionRequest, EmailSuppression, SqlitePool};
+
+use crate::email_suppressions::{normalize_address, ALL_ORGANIZATIONS, REASON_MANUAL, USER_REASONS};
+use crate::tenancy::Organization;
+
+#[derive(Debug, Serialize)]
+pub struct EmailSuppressionListResponse {
+    /// The organization's entries, and for instance admins those applying everywhere
+    pub suppressions: Vec<EmailSuppression>,
+}
+
+/// List suppressed addresses (GET /api/email-suppressions)
+pub async fn list_email_suppressions(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(org): Extension<Organization>,
+) -> Result<Json<EmailSuppressionListResponse>, (StatusCode, String)> {
+    let mut organizations = vec![org.name.as_str()];
+    if org.require_instance_admin().is_ok() {
+        organizations.push(ALL_ORGANIZATIONS);
+    }
+    let suppressions = email_suppressions::list_suppressions(&pool, &organizations)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Ok(Json(EmailSuppressionListResponse { suppressions }))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AddEmailSuppressionRequest {
+    pub address: String,
+    /// `unsubscribed` or `manual` (default)
+    pub reason: Option<String>,
+    pub note: Option<String>,
+}
+
+/// Suppress an address for the organization (POST /api/email-suppressions)
+pub async fn add_email_suppression(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(org): Extension<Organization>,
+    Json(req): Json<AddEmailSuppressionRequest>,
+) -> Result<(StatusCode, Json<EmailSuppression>), (StatusCode, String)> {
+    let address = normalize_address(&req.address)
+        .ok_or_else(|| (StatusCode::BAD_REQUEST, format!("'{}' is not an email address", req.address)))?;
+    let reason = req.reason.unwrap_or_else(|| REASON_MANUAL.to_string());
+    if !USER_REASONS.contains(&reason.as_str()) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("Unknown reason '{}', expected one of {}", reason, USER_REASONS.join(", ")),
+        ));
+    }
+    let suppression = email_suppressions::add_suppression(
+        &pool,
+        CreateEmailSuppressionRequest { organization: org.name, address, reason, note: req.note },
+    )
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Ok((StatusCode::CREATED, Json(suppression)))
+}
+
+/// Allow an address again (DELETE /api/email-suppressions/:address), e.g. a
+/// hard-bounce entry for a mailbox that has since been fixed. Instance admins
+/// also clear the entry applying everywhere.
+pub async fn remove_email_suppression(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(org): Extension<Organization>,
+    Path(address): Path<String>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let address = normalize_address(&address)
+        .ok_or_else(|| (StatusCode::BAD_REQUEST, format!("'{}' is not an email address", address)))?;
+    let mut organizations = vec![org.name.as_str()];
+    if org.require_instance_admin().is_ok() {
+        organizations.push(ALL_ORGANIZATIONS);
+    }
+    let mut removed = false;
+    for organization in organizations {
+        removed |= email_suppressions::remove_suppression(&pool, organization, &address)
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    }
+    if !removed {
+        return Err((StatusCode::NOT_FOUND, format!("{} is not suppressed", address)));
+    }
+    tracing::info!("Removed {} from the suppression list for {}", address, org.name);
+    Ok(StatusCode::NO_CONTENT)
+}
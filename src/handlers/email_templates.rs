@@ -0,0 +1,433 @@
+//! Email template library: reusable subject/body templates with `{{placeholder}}`
+//! variables, rendered into drafts for a ticket or thread and shown to the
+//! email agent so generated drafts follow house style.
+
+use axum::{
+    extract::{Extension, Path, State},
+    http::StatusCode,
+    Json,
+};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use ticketing_system::{
+    drafts, email_templates, CreateDraftRequest, CreateEmailTemplateRequest, EmailDraft, EmailTemplate, SqlitePool,
+    UpdateEmailTemplateRequest, User,
+};
+
+use super::email_grants::{require_email_permission, EmailPermission};
+use crate::agents::AgentType;
+use crate::tenancy::Organization;
+
+/// Placeholders a template may use. `var.<name>` values are supplied at render time.
+pub const TEMPLATE_VARIABLES: &[&str] = &[
+    "ticket.id",
+    "ticket.title",
+    "ticket.description",
+    "ticket.assignee",
+    "thread.subject",
+    "recipient.name",
+    "recipient.email",
+    "sender.name",
+    "sender.email",
+    "date",
+];
+
+/// Max characters of templates shown to the email agent
+const AGENT_CONTEXT_BUDGET_CHARS: usize = 12_000;
+
+lazy_static::lazy_static! {
+    static ref PLACEHOLDER: Regex = Regex::new(r"\{\{\s*([A-Za-z0-9_.\-]+)\s*\}\}").unwrap();
+}
+
+/// Placeholders used in `text` that aren't known variables
+fn unknown_placeholders(text: &str) -> Vec<String> {
+    PLACEHOLDER
+        .captures_iter(text)
+        .map(|c| c[1].to_string())
+        .filter(|name| {
+            !TEMPLATE_VARIABLES.contains(&name.as_str())
+                && name.strip_prefix("var.").is_none_or(|v| v.is_empty())
+        })
+        .collect()
+}
+
+/// Substitute placeholders; unknown or missing ones render empty and are returned
+fn render_text(text: &str, vars: &HashMap<String, String>, missing: &mut Vec<String>) -> String {
+    PLACEHOLDER
+        .replace_all(text, |c: &regex::Captures| match vars.get(&c[1]) {
+            Some(value) => value.clone(),
+            None => {
+                if !missing.contains(&c[1].to_string()) {
+                    missing.push(c[1].to_string());
+                }
+                String::new()
+            }
+        })
+        .into_owned()
+}
+
+fn validate_template(name: &str, subject: &str, body: &str) -> Result<(), (StatusCode, String)> {
+    if name.trim().is_empty() || body.trim().is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "name and body must not be empty".to_string()));
+    }
+    let unknown: Vec<String> = [subject, body].iter().flat_map(|t| unknown_placeholders(t)).collect();
+    if !unknown.is_empty() {
+        return Err((
+            StatusCode::UNPROCESSABLE_ENTITY,
+            format!(
+                "Unknown placeholders: {}. Use one of {} or var.<name>",
+                unknown.join(", "),
+                TEMPLATE_VARIABLES.join(", ")
+            ),
+        ));
+    }
+    Ok(())
+}
+
+/// Load a template, checking the caller belongs to the organization owning it
+async fn load_template(
+    pool: &SqlitePool,
+    org: &Organization,
+    template_id: &str,
+) -> Result<EmailTemplate, (StatusCode, String)> {
+    let template = email_templates::get_template(pool, template_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or_else(|| (StatusCode::NOT_FOUND, "Email template not found".to_string()))?;
+    org.authorize(&template.organization)?;
+    Ok(template)
+}
+
+/// Subject of a reply to an email with this subject
+fn reply_subject(subject: &str) -> String {
+    if subject.to_lowercase().starts_with("re:") {
+        subject.to_string()
+    } else {
+        format!("Re: {}", subject)
+    }
+}
+
+/// The organization's templates rendered for the email agent prompt.
+/// `None` for other agents or when the organization has none.
+pub async fn email_templates_context(pool: &SqlitePool, agent_type: &AgentType, organization: &str) -> Option<String> {
+    if *agent_type != AgentType::Email {
+        return None;
+    }
+    let templates = email_templates::list_templates(pool, organization).await.ok()?;
+
+    let mut remaining = AGENT_CONTEXT_BUDGET_CHARS;
+    let mut sections = Vec::new();
+    for template in templates {
+        let section = format!(
+            "### {}{}\nSubject: {}\n\n{}",
+            template.name,
+            template.description.map(|d| format!(" — {}", d)).unwrap_or_default(),
+            template.subject,
+            template.body
+        );
+        if section.len() > remaining {
+            break;
+        }
+        remaining -= section.len();
+        sections.push(section);
+    }
+
+    if sections.is_empty() {
+        None
+    } else {
+        Some(sections.join("\n\n---\n\n"))
+    }
+}
+
+// ============================================================================
+// CRUD
+// ============================================================================
+
+#[derive(Debug, Deserialize)]
+pub struct EmailTemplateBody {
+    pub name: String,
+    #[serde(default)]
+    pub subject: String,
+    pub body: String,
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct EmailTemplateListResponse {
+    pub templates: Vec<EmailTemplate>,
+    pub variables: &'static [&'static str],
+}
+
+/// List the organization's templates (GET /api/email-templates)
+pub async fn list_email_templates(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(org): Extension<Organization>,
+) -> Result<Json<EmailTemplateListResponse>, (StatusCode, String)> {
+    let templates = email_templates::list_templates(&pool, &org.name)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Ok(Json(EmailTemplateListResponse { templates, variables: TEMPLATE_VARIABLES }))
+}
+
+/// Create a template (POST /api/email-templates)
+pub async fn create_email_template(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(org): Extension<Organization>,
+    Json(body): Json<EmailTemplateBody>,
+) -> Result<(StatusCode, Json<EmailTemplate>), (StatusCode, String)> {
+    validate_template(&body.name, &body.subject, &body.body)?;
+    let template = email_templates::create_template(
+        &pool,
+        &CreateEmailTemplateRequest {
+            organization: org.name,
+            name: body.name.trim().to_string(),
+            subject: body.subject,
+            body: body.body,
+            description: body.description,
+        },
+    )
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Ok((StatusCode::CREATED, Json(template)))
+}
+
+/// Get a template (GET /api/email-templates/:template_id)
+pub async fn get_email_template(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(org): Extension<Organization>,
+    Path(template_id): Path<String>,
+) -> Result<Json<EmailTemplate>, (StatusCode, String)> {
+    Ok(Json(load_template(&pool, &org, &template_id).await?))
+}
+
+/// Replace a template (PUT /api/email-templates/:template_id)
+pub async fn update_email_template(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(org): Extension<Organization>,
+    Path(template_id): Path<String>,
+    Json(body): Json<EmailTemplateBody>,
+) -> Result<Json<EmailTemplate>, (StatusCode, String)> {
+    load_template(&pool, &org, &template_id).await?;
+    validate_template(&body.name, &body.subject, &body.body)?;
+    let template = email_templates::update_template(
+        &pool,
+        &template_id,
+        &UpdateEmailTemplateRequest {
+            name: body.name.trim().to_string(),
+            subject: body.subject,
+            body: body.body,
+            description: body.description,
+        },
+    )
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+    .ok_or_else(|| (StatusCode::NOT_FOUND, "Email template not found".to_string()))?;
+    Ok(Json(template))
+}
+
+/// Delete a template (DELETE /api/email-templates/:template_id)
+pub async fn delete_email_template(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(org): Extension<Organization>,
+    Path(template_id): Path<String>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    load_template(&pool, &org, &template_id).await?;
+    let deleted = email_templates::delete_template(&pool, &template_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    if !deleted {
+        return Err((StatusCode::NOT_FOUND, "Email template not found".to_string()));
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// ============================================================================
+// Rendering
+// ============================================================================
+
+#[derive(Debug, Deserialize)]
+pub struct RenderEmailTemplateRequest {
+    pub ticket_id: Option<String>,
+    /// Reply on this thread: recipient and subject default from its latest inbound email
+    pub thread_id: Option<String>,
+    /// Defaults to the requesting user's email
+    pub from_address: Option<String>,
+    pub to_address: Option<String>,
+    pub cc_address: Option<String>,
+    /// Values for `var.<name>` placeholders, keyed by name
+    #[serde(default)]
+    pub vars: HashMap<String, String>,
+    /// Only render, without storing a draft
+    #[serde(default)]
+    pub preview: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RenderEmailTemplateResponse {
+    pub subject: String,
+    pub body: String,
+    /// Placeholders that had no value and rendered empty
+    pub missing: Vec<String>,
+    pub draft: Option<EmailDraft>,
+}
+
+/// Render a template into a draft (POST /api/email-templates/:template_id/render)
+pub async fn render_email_template(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(user): Extension<User>,
+    Extension(org): Extension<Organization>,
+    Path(template_id): Path<String>,
+    Json(req): Json<RenderEmailTemplateRequest>,
+) -> Result<(StatusCode, Json<RenderEmailTemplateResponse>), (StatusCode, String)> {
+    let template = load_template(&pool, &org, &template_id).await?;
+
+    let mut vars: HashMap<String, String> = req.vars.iter().map(|(k, v)| (format!("var.{}", k), v.clone())).collect();
+    vars.insert("date".to_string(), chrono::Utc::now().format("%B %-d, %Y").to_string());
+    vars.insert("sender.name".to_string(), user.name.clone());
+    if let Some(email) = &user.email {
+        vars.insert("sender.email".to_string(), email.clone());
+    }
+
+    let ticket = match &req.ticket_id {
+        Some(ticket_id) => Some(
+            ticketing_system::tickets::get_ticket_by_id(&pool, ticket_id)
+                .await
+                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+                .ok_or_else(|| (StatusCode::NOT_FOUND, "Ticket not found".to_string()))?,
+        ),
+        None => None,
+    };
+    if let Some(ticket) = &ticket {
+        org.authorize(&ticket.organization)?;
+        vars.insert("ticket.id".to_string(), ticket.ticket_id.clone());
+        vars.insert("ticket.title".to_string(), ticket.title.clone());
+        if let Some(description) = &ticket.description {
+            vars.insert("ticket.description".to_string(), description.clone());
+        }
+        if let Some(assignee) = &ticket.assignee {
+            vars.insert("ticket.assignee".to_string(), assignee.clone());
+        }
+    }
+
+    let from_address = req
+        .from_address
+        .or(user.email.clone())
+        .ok_or_else(|| (StatusCode::BAD_REQUEST, "from_address is required".to_string()))?;
+
+    let mut to_address = req.to_address;
+    let mut thread_subject = None;
+    if let Some(thread_id) = &req.thread_id {
+        let thread = ticketing_system::emails::list_thread_emails(&pool, thread_id)
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        let latest = thread
+            .iter()
+            .rev()
+            .find(|e| !e.from_address.eq_ignore_ascii_case(&from_address))
+            .ok_or_else(|| (StatusCode::NOT_FOUND, "No inbound email on this thread".to_string()))?;
+        require_email_permission(&pool, &user, &latest.mailbox, EmailPermission::Read).await?;
+
+        let subject = latest.subject.clone().unwrap_or_default();
+        vars.insert("thread.subject".to_string(), subject.clone());
+        vars.insert("recipient.email".to_string(), latest.from_address.clone());
+        if let Some(name) = &latest.from_name {
+            vars.insert("recipient.name".to_string(), name.clone());
+        }
+        to_address.get_or_insert_with(|| latest.from_address.clone());
+        thread_subject = Some(reply_subject(&subject));
+    } else if let Some(to) = &to_address {
+        vars.insert("recipient.email".to_string(), to.clone());
+    }
+
+    let mut missing = Vec::new();
+    let subject = render_text(&template.subject, &vars, &mut missing);
+    let subject = if subject.trim().is_empty() { thread_subject.unwrap_or_default() } else { subject };
+    let body = render_text(&template.body, &vars, &mut missing);
+
+    if req.preview {
+        return Ok((StatusCode::OK, Json(RenderEmailTemplateResponse { subject, body, missing, draft: None })));
+    }
+
+    require_email_permission(&pool, &user, &from_address, EmailPermission::Read).await?;
+    let to_address = to_address
+        .ok_or_else(|| (StatusCode::BAD_REQUEST, "to_address or thread_id is required".to_string()))?;
+    let draft = drafts::create_draft(
+        &pool,
+        &CreateDraftRequest {
+            from_address,
+            to_address,
+            cc_address: req.cc_address,
+            subject: subject.clone(),
+            body: body.clone(),
+            ticket_id: ticket.as_ref().map(|t| t.ticket_id.clone()),
+            epic_id: ticket.as_ref().map(|t| t.epic_id.clone()),
+            slice_id: ticket.as_ref().map(|t| t.slice_id.clone()),
+            variant_group: None,
+            variant_label: None,
+            tone: None,
+            length: None,
+            language: None,
+        },
+    )
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    if let Some(ticket_id) = &draft.ticket_id {
+        if let Err(e) = ticketing_system::ticket_history::log_draft_created(
+            &pool,
+            ticket_id,
+            draft.id,
+            &draft.to_address,
+            &draft.subject,
+        )
+        .await
+        {
+            tracing::warn!("Failed to log draft creation to ticket history: {}", e);
+        }
+    }
+
+    Ok((
+        StatusCode::CREATED,
+        Json(RenderEmailTemplateResponse { subject, body, missing, draft: Some(draft) }),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_known_placeholders_and_reports_missing() {
+        let vars = HashMap::from([
+            ("ticket.title".to_string(), "Renewal".to_string()),
+            ("var.deadline".to_string(), "Friday".to_string()),
+        ]);
+        let mut missing = Vec::new();
+        let text = render_text("Re {{ ticket.title }} by {{var.deadline}}, {{recipient.name}}", &vars, &mut missing);
+        assert_eq!(text, "Re Renewal by Friday, ");
+        assert_eq!(missing, vec!["recipient.name"]);
+
+        assert!(unknown_placeholders("{{ticket.title}} {{var.x}}").is_empty());
+        assert_eq!(unknown_placeholders("{{ticket.owner}} {{var.}}"), vec!["ticket.owner", "var."]);
+    }
+
+    #[test]
+    fn validates_templates_before_saving() {
+        assert!(validate_template("Renewal", "Re {{ticket.title}}", "Hi {{recipient.name}}").is_ok());
+        assert_eq!(validate_template(" ", "", "Hi").unwrap_err().0, StatusCode::BAD_REQUEST);
+        assert_eq!(validate_template("Renewal", "", "\n").unwrap_err().0, StatusCode::BAD_REQUEST);
+
+        let (status, message) = validate_template("Renewal", "{{ticket.owner}}", "Hi {{var.name}}").unwrap_err();
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert!(message.contains("ticket.owner"));
+    }
+
+    #[test]
+    fn prefixes_reply_subjects_once() {
+        assert_eq!(reply_subject("Renewal"), "Re: Renewal");
+        assert_eq!(reply_subject("RE: Renewal"), "RE: Renewal");
+        assert_eq!(reply_subject(""), "Re: ");
+    }
+}
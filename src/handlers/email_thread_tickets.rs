@@ -1,11 +1,17 @@
 use axum::{
-    extract::{Path, State},
+    extract::{Extension, Path, State},
     http::StatusCode,
     Json,
 };
 use serde::Serialize;
 use std::sync::Arc;
-use ticketing_system::{email_thread_tickets, EmailThreadTicket, LinkThreadTicketRequest, SqlitePool};
+use ticketing_system::{
+    email_auto_links, email_thread_tickets, emails, tickets, EmailAutoLink, EmailThreadTicket, LinkThreadTicketRequest,
+    SqlitePool, User,
+};
+
+use super::email_grants::filter_readable;
+use crate::tenancy::Organization;
 
 #[derive(Debug, Serialize)]
 pub struct ThreadTicketsResponse {
@@ -66,3 +72,71 @@ pub async fn unlink_thread_from_ticket(
 
     Ok(StatusCode::NO_CONTENT)
 }
+
+#[derive(Debug, Serialize)]
+pub struct ThreadAutoLinksResponse {
+    pub thread_id: String,
+    pub links: Vec<EmailAutoLink>,
+}
+
+/// 404 unless the user can read a mailbox the thread is in
+async fn require_thread_access(pool: &SqlitePool, user: &User, thread_id: &str) -> Result<(), (StatusCode, String)> {
+    let thread = emails::list_thread_emails(pool, thread_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    if filter_readable(pool, user, thread, |email| email.mailbox.as_str()).await?.is_empty() {
+        return Err((StatusCode::NOT_FOUND, "Thread not found".to_string()));
+    }
+    Ok(())
+}
+
+/// Tickets linked to a thread automatically, and why, oldest first
+/// (GET /api/email-threads/:thread_id/auto-links); see `crate::email_auto_link`
+pub async fn list_thread_auto_links(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(user): Extension<User>,
+    Path(thread_id): Path<String>,
+) -> Result<Json<ThreadAutoLinksResponse>, (StatusCode, String)> {
+    require_thread_access(&pool, &user, &thread_id).await?;
+    let links = email_auto_links::list_for_thread(&pool, &thread_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(Json(ThreadAutoLinksResponse { thread_id, links }))
+}
+
+/// Undo an automatic link: unlinks the ticket and keeps later messages in the
+/// thread from linking it again
+/// (POST /api/email-threads/:thread_id/auto-links/:link_id/undo)
+pub async fn undo_thread_auto_link(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(user): Extension<User>,
+    Extension(org): Extension<Organization>,
+    Path((thread_id, link_id)): Path<(String, String)>,
+) -> Result<Json<EmailAutoLink>, (StatusCode, String)> {
+    require_thread_access(&pool, &user, &thread_id).await?;
+    let link = email_auto_links::get_link(&pool, &link_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .filter(|link| link.thread_id == thread_id)
+        .ok_or((StatusCode::NOT_FOUND, "Auto-link not found".to_string()))?;
+    if let Some(ticket) = tickets::get_ticket_by_id(&pool, &link.ticket_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+    {
+        org.authorize(&ticket.organization)?;
+    }
+    if link.undone_at.is_some() {
+        return Err((StatusCode::CONFLICT, "Auto-link was already undone".to_string()));
+    }
+
+    email_thread_tickets::unlink_thread_from_ticket(&pool, &thread_id, &link.ticket_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let link = email_auto_links::mark_undone(&pool, &link_id, &user.user_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    tracing::info!("{} undid auto-link of thread {} to ticket {}", user.user_id, thread_id, link.ticket_id);
+
+    Ok(Json(link))
+}
@@ -0,0 +1,100 @@
+//! Filing a ticket from an email, see `crate::email_tickets`
+
+use axum::{
+    extract::{Extension, Path, State},
+    http::StatusCode,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::sync::Arc;
+use ticketing_system::{emails, SqlitePool, User};
+
+use super::email_grants::{require_email_permission, EmailPermission};
+use crate::email_tickets::{
+    check_proposal, existing_ticket, file_email_ticket, list_places, propose_ticket, TicketProposal,
+};
+use crate::tenancy::Organization;
+
+fn default_confirm() -> bool {
+    true
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateTicketFromEmailRequest {
+    /// Only return the proposal, for the reader to confirm; `false` files it
+    #[serde(default = "default_confirm")]
+    pub confirm: bool,
+    /// The (edited) proposal to use instead of asking the agent
+    pub proposal: Option<TicketProposal>,
+}
+
+impl Default for CreateTicketFromEmailRequest {
+    fn default() -> Self {
+        Self { confirm: true, proposal: None }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct CreateTicketFromEmailResponse {
+    pub email_id: i64,
+    pub proposal: TicketProposal,
+    /// Set once the ticket has been filed
+    pub ticket: Option<Value>,
+    /// A ticket already filed from this email
+    pub existing_ticket_id: Option<String>,
+}
+
+/// Propose or file a ticket for an email (POST /api/emails/:id/create-ticket)
+///
+/// Without a `proposal` the email-ticket agent writes one. With `confirm`
+/// (the default) it's returned for review; `confirm: false` files it and
+/// answers 201, or 409 when the email already has a ticket.
+pub async fn create_ticket_from_email(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(user): Extension<User>,
+    Extension(org): Extension<Organization>,
+    Path(id): Path<i64>,
+    body: Option<Json<CreateTicketFromEmailRequest>>,
+) -> Result<(StatusCode, Json<CreateTicketFromEmailResponse>), (StatusCode, String)> {
+    let req = body.map(|Json(req)| req).unwrap_or_default();
+    let email = emails::get_email_by_id(&pool, id)
+        .await
+        .map_err(|e| (StatusCode::NOT_FOUND, e.to_string()))?;
+    require_email_permission(&pool, &user, &email.mailbox, EmailPermission::Read).await?;
+
+    let existing_ticket_id = existing_ticket(&pool, &org.name, &email)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("{:#}", e)))?;
+    if !req.confirm {
+        if let Some(ticket_id) = &existing_ticket_id {
+            return Err((StatusCode::CONFLICT, format!("Email already filed as ticket {}", ticket_id)));
+        }
+    }
+
+    let places = list_places(&pool, &org.name)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to list epics and slices: {:#}", e)))?;
+    let proposal = match req.proposal {
+        Some(proposal) => proposal,
+        None => {
+            crate::quotas::check_agent_run(&pool, &org.name).await?;
+            propose_ticket(&pool, &org.name, &email, &places)
+                .await
+                .map_err(|e| (StatusCode::BAD_GATEWAY, format!("Failed to propose a ticket: {:#}", e)))?
+        }
+    };
+
+    if req.confirm {
+        let response = CreateTicketFromEmailResponse { email_id: id, proposal, ticket: None, existing_ticket_id };
+        return Ok((StatusCode::OK, Json(response)));
+    }
+    let proposal = check_proposal(proposal, &places).map_err(|e| (StatusCode::UNPROCESSABLE_ENTITY, e))?;
+    let ticket = file_email_ticket(&pool, &org.name, &user.user_id, &email, &proposal)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("{:#}", e)))?;
+    Ok((
+        StatusCode::CREATED,
+        Json(CreateTicketFromEmailResponse { email_id: id, proposal, ticket: Some(ticket), existing_ticket_id: None }),
+    ))
+}
@@ -1,11 +1,21 @@
 use axum::{
-    extract::{Path, Query, State},
-    http::StatusCode,
+    extract::{Extension, Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
     Json,
 };
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
-use ticketing_system::{emails, Email, SqlitePool};
+use ticketing_system::{email_digests, emails, Email, EmailDigest, SqlitePool, User};
+
+use crate::email_fetcher;
+use crate::ndjson::{self, Page};
+use crate::tenancy::Organization;
+
+use super::email_grants::{filter_readable, require_email_permission, EmailPermission};
+
+/// Stored digests returned by `list_email_digests`
+const DIGEST_LIST_LIMIT: i64 = 30;
 
 #[derive(Debug, Deserialize)]
 pub struct ListEmailsQuery {
@@ -23,14 +33,22 @@ pub struct EmailListResponse {
 }
 
 /// List emails (GET /api/emails)
+/// With `Accept: application/x-ndjson`, streams one email per line instead;
+/// `limit` then defaults to everything after `offset`
 pub async fn list_emails(
     State(pool): State<Arc<SqlitePool>>,
+    Extension(user): Extension<User>,
     Query(params): Query<ListEmailsQuery>,
-) -> Result<Json<EmailListResponse>, (StatusCode, String)> {
+    headers: HeaderMap,
+) -> Result<Response, (StatusCode, String)> {
+    if ndjson::wants_ndjson(&headers) {
+        return stream_emails(pool, user, params).await;
+    }
     let limit = params.limit.unwrap_or(50);
     let offset = params.offset.unwrap_or(0);
 
     let (email_list, total, unread) = if let Some(mailbox) = &params.mailbox {
+        require_email_permission(&pool, &user, mailbox, EmailPermission::Read).await?;
         let folder = params.folder.as_deref();
         let list = emails::list_emails(&pool, mailbox, folder, limit, offset)
             .await
@@ -47,6 +65,7 @@ pub async fn list_emails(
         let list = emails::list_all_emails(&pool, limit, offset)
             .await
             .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        let list = filter_readable(&pool, &user, list, |e| e.mailbox.as_str()).await?;
         // For unified inbox, count is the list length (simplified)
         let total = list.len() as i64;
         let unread = list.iter().filter(|e| !e.is_read).count() as i64;
@@ -57,17 +76,56 @@ pub async fn list_emails(
         emails: email_list,
         total,
         unread,
+    })
+    .into_response())
+}
+
+async fn stream_emails(
+    pool: Arc<SqlitePool>,
+    user: User,
+    params: ListEmailsQuery,
+) -> Result<Response, (StatusCode, String)> {
+    let start = params.offset.unwrap_or(0);
+    if let Some(mailbox) = params.mailbox {
+        require_email_permission(&pool, &user, &mailbox, EmailPermission::Read).await?;
+        let folder = params.folder;
+        return Ok(ndjson::pages(params.limit, move |offset, limit| {
+            let (pool, mailbox, folder) = (pool.clone(), mailbox.clone(), folder.clone());
+            async move {
+                emails::list_emails(&pool, &mailbox, folder.as_deref(), limit, start + offset)
+                    .await
+                    .map(Page::from)
+                    .map_err(|e| e.to_string())
+            }
+        }));
+    }
+
+    Ok(ndjson::pages(params.limit, move |offset, limit| {
+        let (pool, user) = (pool.clone(), user.clone());
+        async move {
+            let list = emails::list_all_emails(&pool, limit, start + offset)
+                .await
+                .map_err(|e| e.to_string())?;
+            let read = list.len() as i64;
+            let rows = filter_readable(&pool, &user, list, |e| e.mailbox.as_str())
+                .await
+                .map_err(|(_, e)| e)?;
+            Ok(Page { rows, read })
+        }
     }))
 }
 
 /// Get single email by ID (GET /api/emails/:id)
+/// Sent emails include `delivery_status` (sent/bounced) and `bounce_reason`
 pub async fn get_email(
     State(pool): State<Arc<SqlitePool>>,
+    Extension(user): Extension<User>,
     Path(id): Path<i64>,
 ) -> Result<Json<Email>, (StatusCode, String)> {
     let email = emails::get_email_by_id(&pool, id)
         .await
         .map_err(|e| (StatusCode::NOT_FOUND, e.to_string()))?;
+    require_email_permission(&pool, &user, &email.mailbox, EmailPermission::Read).await?;
 
     Ok(Json(email))
 }
@@ -76,14 +134,31 @@ pub async fn get_email(
 pub struct UpdateEmailRequest {
     pub is_read: Option<bool>,
     pub is_starred: Option<bool>,
+    /// Move the message to another synced folder on the server (archive, etc.)
+    pub folder: Option<String>,
 }
 
 /// Update email (PATCH /api/emails/:id)
 pub async fn update_email(
     State(pool): State<Arc<SqlitePool>>,
+    Extension(user): Extension<User>,
     Path(id): Path<i64>,
     Json(req): Json<UpdateEmailRequest>,
 ) -> Result<Json<Email>, (StatusCode, String)> {
+    let email = emails::get_email_by_id(&pool, id)
+        .await
+        .map_err(|e| (StatusCode::NOT_FOUND, e.to_string()))?;
+    let required = if req.folder.is_some() { EmailPermission::Manage } else { EmailPermission::Read };
+    require_email_permission(&pool, &user, &email.mailbox, required).await?;
+
+    // Flags are set before a move, while the message's UID is still known
+    if req.is_read.is_some() || req.is_starred.is_some() {
+        push_flags(&email, req.is_read, req.is_starred).await?;
+    }
+    if let Some(folder) = req.folder.as_deref().filter(|f| !f.eq_ignore_ascii_case(&email.folder)) {
+        move_to_folder(&pool, &email, folder).await?;
+    }
+
     if let Some(is_read) = req.is_read {
         emails::mark_email_read(&pool, id, is_read)
             .await
@@ -103,11 +178,61 @@ pub async fn update_email(
     Ok(Json(email))
 }
 
+/// Set the read and starred flags of an email synced over IMAP on the server.
+/// Emails that aren't (e.g. sent through SES) only change locally.
+async fn push_flags(email: &Email, is_read: Option<bool>, is_starred: Option<bool>) -> Result<(), (StatusCode, String)> {
+    let Some(account) = email_fetcher::find_account(&email.mailbox).await else {
+        return Ok(());
+    };
+    let Some(folder) = account.folder(&email.folder) else {
+        return Ok(());
+    };
+    email_fetcher::server_uid(&email.message_id).map_err(|e| (StatusCode::CONFLICT, e.to_string()))?;
+    email_fetcher::set_flags(&account, &email.message_id, folder, is_read, is_starred)
+        .await
+        .map_err(|e| (StatusCode::BAD_GATEWAY, format!("Failed to update flags on the server: {:#}", e)))
+}
+
+/// Move a stored email on the IMAP server, then record its new folder and UID
+async fn move_to_folder(pool: &SqlitePool, email: &Email, folder: &str) -> Result<(), (StatusCode, String)> {
+    let account = email_fetcher::find_account(&email.mailbox).await.ok_or((
+        StatusCode::CONFLICT,
+        format!("Mailbox {} is not synced over IMAP", email.mailbox),
+    ))?;
+    let unknown_folder = || {
+        let names: Vec<&str> = account.folders.iter().map(|f| f.name.as_str()).collect();
+        (
+            StatusCode::BAD_REQUEST,
+            format!("Unknown folder '{}'; synced folders are: {}", folder, names.join(", ")),
+        )
+    };
+    let target = account.folder(folder).ok_or_else(unknown_folder)?;
+    let source = account.folder(&email.folder).ok_or((
+        StatusCode::CONFLICT,
+        format!("Email is in folder '{}', which is no longer synced", email.folder),
+    ))?;
+    email_fetcher::server_uid(&email.message_id).map_err(|e| (StatusCode::CONFLICT, e.to_string()))?;
+
+    let message_id = email_fetcher::move_message(&account, &email.message_id, source, target)
+        .await
+        .map_err(|e| (StatusCode::BAD_GATEWAY, format!("IMAP move failed: {:#}", e)))?;
+
+    emails::move_email(pool, email.id, &target.name, &message_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
+
 /// Delete email (DELETE /api/emails/:id)
 pub async fn delete_email(
     State(pool): State<Arc<SqlitePool>>,
+    Extension(user): Extension<User>,
     Path(id): Path<i64>,
 ) -> Result<StatusCode, (StatusCode, String)> {
+    let email = emails::get_email_by_id(&pool, id)
+        .await
+        .map_err(|e| (StatusCode::NOT_FOUND, e.to_string()))?;
+    require_email_permission(&pool, &user, &email.mailbox, EmailPermission::Manage).await?;
+
     emails::delete_email(&pool, id)
         .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
@@ -130,9 +255,11 @@ pub struct MailboxStats {
 /// Get email stats (GET /api/emails/stats)
 pub async fn get_email_stats(
     State(pool): State<Arc<SqlitePool>>,
+    Extension(user): Extension<User>,
 ) -> Result<Json<EmailStatsResponse>, (StatusCode, String)> {
     // For now, hardcode the mailbox - could query distinct mailboxes later
     let mailboxes = vec!["jakeGreene@ballotradar.com".to_string()];
+    let mailboxes = filter_readable(&pool, &user, mailboxes, |m| m.as_str()).await?;
 
     let mut stats = Vec::new();
     for mailbox in mailboxes {
@@ -153,6 +280,75 @@ pub async fn get_email_stats(
     Ok(Json(EmailStatsResponse { mailboxes: stats }))
 }
 
+#[derive(Debug, Default, Deserialize)]
+pub struct EmailDigestRequest {
+    /// Hours of email covered, counting back from now (default 24)
+    pub hours: Option<i64>,
+    /// Mailboxes to cover; every mailbox the user can read when empty
+    #[serde(default)]
+    pub mailboxes: Vec<String>,
+    /// Also email the digest to the user, from this mailbox
+    pub email_from: Option<String>,
+}
+
+/// Generate an email digest now and store it for the user (POST /api/emails/digest)
+pub async fn generate_email_digest(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(user): Extension<User>,
+    Extension(org): Extension<Organization>,
+    Json(req): Json<EmailDigestRequest>,
+) -> Result<Json<crate::email_digest::Digest>, (StatusCode, String)> {
+    let hours = req.hours.unwrap_or(24);
+    if !(1..=7 * 24).contains(&hours) {
+        return Err((StatusCode::BAD_REQUEST, "hours must be between 1 and 168".to_string()));
+    }
+    for mailbox in &req.mailboxes {
+        require_email_permission(&pool, &user, mailbox, EmailPermission::Read).await?;
+    }
+    let mailboxes = if req.mailboxes.is_empty() {
+        let all = email_fetcher::mailbox_addresses().await;
+        filter_readable(&pool, &user, all, |m| m.as_str()).await?
+    } else {
+        req.mailboxes
+    };
+    if mailboxes.is_empty() {
+        return Err((StatusCode::FORBIDDEN, "No readable mailboxes".to_string()));
+    }
+
+    crate::quotas::check_agent_run(&pool, &org.name).await?;
+    let mut digest = crate::email_digest::generate_digest(&pool, &org.name, &user, &mailboxes, hours, chrono::Utc::now())
+        .await
+        .map_err(|e| (StatusCode::BAD_GATEWAY, format!("Failed to generate digest: {:#}", e)))?;
+
+    if let Some(from) = &req.email_from {
+        let to = user
+            .email
+            .clone()
+            .ok_or_else(|| (StatusCode::BAD_REQUEST, "Your account has no email address".to_string()))?;
+        require_email_permission(&pool, &user, from, EmailPermission::Send).await?;
+        digest.emailed_message_id = Some(crate::email_digest::email_digest(&pool, &org.name, &digest, from, &to).await?);
+    }
+
+    Ok(Json(digest))
+}
+
+#[derive(Debug, Serialize)]
+pub struct EmailDigestListResponse {
+    pub digests: Vec<EmailDigest>,
+}
+
+/// The user's stored digests in the organization, newest first (GET /api/emails/digests)
+pub async fn list_email_digests(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(user): Extension<User>,
+    Extension(org): Extension<Organization>,
+) -> Result<Json<EmailDigestListResponse>, (StatusCode, String)> {
+    let digests = email_digests::list_for_user(&pool, &user.user_id, &org.name, DIGEST_LIST_LIMIT)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Ok(Json(EmailDigestListResponse { digests }))
+}
+
 #[derive(Debug, Deserialize)]
 pub struct SendEmailRequest {
     pub to: Vec<String>,
@@ -181,10 +377,32 @@ pub struct SendEmailResponse {
 /// Send email via SES and store in Sent folder (POST /api/emails/send)
 pub async fn send_email(
     State(pool): State<Arc<SqlitePool>>,
+    Extension(user): Extension<User>,
+    Extension(org): Extension<Organization>,
     Json(req): Json<SendEmailRequest>,
 ) -> Result<Json<SendEmailResponse>, (StatusCode, String)> {
+    require_email_permission(&pool, &user, &req.from, EmailPermission::Send).await?;
+    let message_id = deliver_email(&pool, &org.name, &req).await?;
+
+    Ok(Json(SendEmailResponse {
+        message_id,
+        success: true,
+    }))
+}
+
+/// Send an email via SES against the organization's quota and store it in the
+/// Sent folder, returning the SES message id. Callers check send permission.
+pub async fn deliver_email(
+    pool: &SqlitePool,
+    organization: &str,
+    req: &SendEmailRequest,
+) -> Result<String, (StatusCode, String)> {
     use aws_sdk_sesv2::types::{Body, Content, Destination, EmailContent, Message};
 
+    let recipients: Vec<&str> = req.to.iter().chain(&req.cc).chain(&req.bcc).map(String::as_str).collect();
+    crate::email_suppressions::check_recipients(pool, organization, &recipients).await?;
+    crate::quotas::check_email(pool, organization).await?;
+
     // Load AWS config with ballotradar-shared profile
     let config = aws_config::defaults(aws_config::BehaviorVersion::latest())
         .profile_name("ballotradar-shared")
@@ -266,6 +484,7 @@ pub async fn send_email(
 
     let message_id = result.message_id().unwrap_or("unknown").to_string();
     tracing::info!("Email sent successfully, message_id: {}", message_id);
+    crate::quotas::record_email_sent(pool, organization).await;
 
     // Store in Sent folder
     let now = chrono::Utc::now().timestamp();
@@ -283,15 +502,15 @@ pub async fn send_email(
         received_at: now,
         thread_id: None,
         in_reply_to: None,
+        header_message_id: None,
     };
 
-    if let Err(e) = emails::create_email(&pool, &create_req).await {
+    if let Err(e) = emails::create_email(pool, &create_req).await {
         tracing::warn!("Failed to store sent email in database: {}", e);
         // Don't fail the request - email was sent successfully
+    } else if let Err(e) = emails::update_delivery_status(pool, &message_id, "sent", None).await {
+        tracing::warn!("Failed to set delivery status for {}: {}", message_id, e);
     }
 
-    Ok(Json(SendEmailResponse {
-        message_id,
-        success: true,
-    }))
+    Ok(message_id)
 }
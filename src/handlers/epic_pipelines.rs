@@ -0,0 +1,204 @@
+//! Epic pipelines, see `crate::epic_pipelines`
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use ticketing_system::{
+    epic_pipelines, pipelines, CreateEpicPipelineRequest, CreateEpicPipelineStep, EpicPipeline, SqlitePool,
+};
+
+use super::plans::{epic_exists, slice_exists};
+use crate::epic_pipelines::{
+    check_epic_pipeline, step_progress, StepProgress, COMPLETIONS, COMPLETION_ALL_COMPLETED, COMPLETION_MIN_PERCENT,
+    STEP_PENDING,
+};
+use crate::template_sharing::template_source;
+use crate::tenancy::Organization;
+
+const MAX_EPIC_PIPELINE_STEPS: usize = 50;
+
+#[derive(Debug, Deserialize)]
+pub struct EpicPipelineStepRequest {
+    pub slice_id: String,
+    pub template_id: String,
+    /// `all_completed` (default), `all_finished` or `min_completed_percent`
+    pub completion: Option<String>,
+    pub min_completed_percent: Option<u32>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateEpicPipelineBody {
+    pub steps: Vec<EpicPipelineStepRequest>,
+}
+
+/// An epic pipeline with the progress of each step that has started
+#[derive(Debug, Serialize)]
+pub struct EpicPipelineView {
+    #[serde(flatten)]
+    pub pipeline: EpicPipeline,
+    pub progress: Vec<Option<StepProgress>>,
+}
+
+async fn view(pool: &SqlitePool, pipeline: EpicPipeline) -> Result<EpicPipelineView, (StatusCode, String)> {
+    let mut progress = Vec::with_capacity(pipeline.steps.len());
+    for step in &pipeline.steps {
+        if step.status == STEP_PENDING {
+            progress.push(None);
+            continue;
+        }
+        let step_progress = step_progress(pool, &pipeline, step)
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?;
+        progress.push(Some(step_progress));
+    }
+    Ok(EpicPipelineView { pipeline, progress })
+}
+
+async fn load(pool: &SqlitePool, org: &Organization, epic_pipeline_id: &str) -> Result<EpicPipeline, (StatusCode, String)> {
+    let pipeline = epic_pipelines::get_epic_pipeline(pool, epic_pipeline_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?
+        .ok_or_else(|| (StatusCode::NOT_FOUND, "Epic pipeline not found".to_string()))?;
+    org.authorize(&pipeline.organization)?;
+    Ok(pipeline)
+}
+
+/// Problems with an epic pipeline's steps, empty when it can be created
+async fn validate_steps(
+    pool: &SqlitePool,
+    organization: &str,
+    epic_id: &str,
+    steps: &[EpicPipelineStepRequest],
+) -> Result<Vec<String>, (StatusCode, String)> {
+    let mut problems = Vec::new();
+    if steps.is_empty() {
+        problems.push("An epic pipeline needs at least one step".to_string());
+    }
+    if steps.len() > MAX_EPIC_PIPELINE_STEPS {
+        problems.push(format!("An epic pipeline can have at most {} steps", MAX_EPIC_PIPELINE_STEPS));
+    }
+    for (idx, step) in steps.iter().enumerate() {
+        if !slice_exists(organization, epic_id, &step.slice_id).await {
+            problems.push(format!("Step {}: slice {} not found in epic {}", idx + 1, step.slice_id, epic_id));
+        }
+        let completion = step.completion.as_deref().unwrap_or(COMPLETION_ALL_COMPLETED);
+        if !COMPLETIONS.contains(&completion) {
+            problems.push(format!(
+                "Step {}: unknown completion '{}', expected one of: {}",
+                idx + 1,
+                completion,
+                COMPLETIONS.join(", ")
+            ));
+        }
+        match step.min_completed_percent {
+            Some(percent) if !(1..=100).contains(&percent) => {
+                problems.push(format!("Step {}: min_completed_percent must be between 1 and 100", idx + 1))
+            }
+            None if completion == COMPLETION_MIN_PERCENT => {
+                problems.push(format!("Step {}: min_completed_percent is required", idx + 1))
+            }
+            _ => {}
+        }
+        let template = pipelines::get_template(pool, &step.template_id)
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?;
+        let template =
+            template.filter(|t| template_source(t.organization.as_deref(), t.shared, organization).is_some());
+        match template {
+            None => problems.push(format!("Step {}: template {} not found", idx + 1, step.template_id)),
+            Some(template) if template.steps.is_empty() => {
+                problems.push(format!("Step {}: template {} has no steps", idx + 1, step.template_id))
+            }
+            Some(_) => {}
+        }
+    }
+    Ok(problems)
+}
+
+/// POST /api/epics/:epic_id/pipelines
+///
+/// Creates the epic pipeline and starts its first step right away
+pub async fn create_epic_pipeline(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(org): Extension<Organization>,
+    Path(epic_id): Path<String>,
+    Json(body): Json<CreateEpicPipelineBody>,
+) -> Result<Json<EpicPipelineView>, (StatusCode, String)> {
+    if !epic_exists(&org.name, &epic_id).await {
+        return Err((StatusCode::NOT_FOUND, format!("Epic not found: {}", epic_id)));
+    }
+    let problems = validate_steps(&pool, &org.name, &epic_id, &body.steps).await?;
+    if !problems.is_empty() {
+        return Err((StatusCode::UNPROCESSABLE_ENTITY, problems.join("; ")));
+    }
+
+    let request = CreateEpicPipelineRequest {
+        epic_id,
+        organization: org.name.clone(),
+        steps: body
+            .steps
+            .into_iter()
+            .map(|step| CreateEpicPipelineStep {
+                slice_id: step.slice_id,
+                template_id: step.template_id,
+                completion: step.completion.unwrap_or_else(|| COMPLETION_ALL_COMPLETED.to_string()),
+                min_completed_percent: step.min_completed_percent,
+            })
+            .collect(),
+    };
+    let created = epic_pipelines::create_epic_pipeline(&pool, &request)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to create epic pipeline: {}", e)))?;
+    tracing::info!("Created epic pipeline {} on epic {}", created.epic_pipeline_id, created.epic_id);
+
+    check_epic_pipeline(&pool, &created.epic_pipeline_id).await;
+    let pipeline = load(&pool, &org, &created.epic_pipeline_id).await?;
+    Ok(Json(view(&pool, pipeline).await?))
+}
+
+/// GET /api/epics/:epic_id/pipelines
+pub async fn list_epic_pipelines(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(org): Extension<Organization>,
+    Path(epic_id): Path<String>,
+) -> Result<Json<Vec<EpicPipelineView>>, (StatusCode, String)> {
+    let pipelines = epic_pipelines::list_for_epic(&pool, &org.name, &epic_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?;
+    let mut views = Vec::with_capacity(pipelines.len());
+    for pipeline in pipelines {
+        views.push(view(&pool, pipeline).await?);
+    }
+    Ok(Json(views))
+}
+
+/// GET /api/epic-pipelines/:epic_pipeline_id
+pub async fn get_epic_pipeline(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(org): Extension<Organization>,
+    Path(epic_pipeline_id): Path<String>,
+) -> Result<Json<EpicPipelineView>, (StatusCode, String)> {
+    let pipeline = load(&pool, &org, &epic_pipeline_id).await?;
+    Ok(Json(view(&pool, pipeline).await?))
+}
+
+/// POST /api/epic-pipelines/:epic_pipeline_id/cancel
+///
+/// Stops starting tickets and moving on to later steps. Ticket pipelines
+/// already started keep running.
+pub async fn cancel_epic_pipeline(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(org): Extension<Organization>,
+    Path(epic_pipeline_id): Path<String>,
+) -> Result<Json<EpicPipelineView>, (StatusCode, String)> {
+    let pipeline = load(&pool, &org, &epic_pipeline_id).await?;
+    let cancelled = crate::epic_pipelines::cancel_epic_pipeline(&pool, &epic_pipeline_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?
+        .ok_or_else(|| (StatusCode::CONFLICT, format!("Epic pipeline is already {}", pipeline.status)))?;
+    Ok(Json(view(&pool, cancelled).await?))
+}
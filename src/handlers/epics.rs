@@ -1,42 +1,170 @@
 use axum::{
-    extract::{Path, Query, State},
-    http::{StatusCode, HeaderMap},
+    extract::{Extension, Path, Query, State},
+    http::StatusCode,
     Json,
     response::{IntoResponse, Response},
 };
 use serde::Deserialize;
-use serde_json::json;
+use serde_json::{json, Value};
 use sqlx::SqlitePool;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
-use tracing::{error, info};
+use ticketing_system::{structure, EpicLayout, ProgressRollup};
+use tracing::{error, info, warn};
 
 use crate::{
     models::CreateEpicRequest,
     mcp_wrapper::call_mcp_tool,
+    tenancy::Organization,
 };
 
-use super::get_organization;
-
 #[derive(Debug, Deserialize)]
 pub struct ListEpicsQuery {
     pub organization: Option<String>,
 }
 
+/// Longest epic group or theme name
+const MAX_LAYOUT_LABEL_CHARS: usize = 64;
+
+#[derive(Debug, Deserialize)]
+pub struct ReorderEpicsRequest {
+    pub organization: Option<String>,
+    /// Epics in display order; epics left out sort after these
+    pub epic_ids: Vec<String>,
+}
+
+/// Planning metadata for an epic. An empty string clears a field; an absent
+/// field is left as it is.
+#[derive(Debug, Deserialize)]
+pub struct UpdateEpicMetadataRequest {
+    pub group: Option<String>,
+    pub theme: Option<String>,
+}
+
+/// Add a `progress` object to each item of `result[list_key]` from its rollup,
+/// matched on `id_key`. Items without tickets get an empty rollup.
+pub(crate) fn attach_progress(result: &mut Value, list_key: &str, id_key: &str, rollups: Vec<ProgressRollup>) {
+    let mut by_id: HashMap<String, ProgressRollup> = rollups.into_iter().map(|r| (r.id.clone(), r)).collect();
+    let Some(items) = result.get_mut(list_key).and_then(|v| v.as_array_mut()) else {
+        return;
+    };
+    for item in items {
+        let Some(id) = item.get(id_key).and_then(|v| v.as_str()).map(str::to_string) else {
+            continue;
+        };
+        let rollup = by_id.remove(&id).unwrap_or_else(|| ProgressRollup { id, ..Default::default() });
+        if let Some(obj) = item.as_object_mut() {
+            obj.insert(
+                "progress".to_string(),
+                json!({
+                    "ticket_count": rollup.ticket_count,
+                    "status_counts": rollup.status_counts,
+                    "pipelines_running": rollup.pipelines_running,
+                    "pipelines_awaiting_approval": rollup.pipelines_awaiting_approval,
+                    "last_activity_at": rollup.last_activity_at,
+                }),
+            );
+        }
+    }
+}
+
+/// Sort `result[list_key]` by the stored positions, keeping the original order
+/// for ties and putting unpositioned items last, and add each item's `position`
+pub(crate) fn apply_order(result: &mut Value, list_key: &str, id_key: &str, positions: &HashMap<String, i64>) {
+    let Some(items) = result.get_mut(list_key).and_then(|v| v.as_array_mut()) else {
+        return;
+    };
+    let position_of = |item: &Value| {
+        item.get(id_key)
+            .and_then(|v| v.as_str())
+            .and_then(|id| positions.get(id).copied())
+    };
+    // Stable sort: None sorts after every Some
+    items.sort_by_key(|item| position_of(item).map_or((1, 0), |p| (0, p)));
+    for item in items.iter_mut() {
+        let position = position_of(item);
+        if let Some(obj) = item.as_object_mut() {
+            obj.insert("position".to_string(), json!(position));
+        }
+    }
+}
+
+/// Add `group` and `theme` to an epic object
+fn attach_metadata(epic: &mut Value, layout: Option<&EpicLayout>) {
+    if let Some(obj) = epic.as_object_mut() {
+        obj.insert("group".to_string(), json!(layout.and_then(|l| l.group_name.clone())));
+        obj.insert("theme".to_string(), json!(layout.and_then(|l| l.theme.clone())));
+    }
+}
+
+/// Every id listed once, and each one among `known`
+fn check_order(ids: &[String], known: &HashSet<String>, kind: &str) -> Result<(), String> {
+    let mut seen = HashSet::new();
+    for id in ids {
+        if !seen.insert(id) {
+            return Err(format!("{} '{}' is listed more than once", kind, id));
+        }
+        if !known.contains(id) {
+            return Err(format!("Unknown {} '{}'", kind, id));
+        }
+    }
+    Ok(())
+}
+
+/// Ids of the items in `result[list_key]`
+pub(crate) fn listed_ids(result: &Value, list_key: &str, id_key: &str) -> HashSet<String> {
+    result
+        .get(list_key)
+        .and_then(|v| v.as_array())
+        .map(|items| {
+            items
+                .iter()
+                .filter_map(|item| item.get(id_key).and_then(|v| v.as_str()).map(str::to_string))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+pub(crate) fn validate_order(ids: &[String], known: &HashSet<String>, kind: &str) -> Result<(), Response> {
+    check_order(ids, known, kind)
+        .map_err(|e| (StatusCode::UNPROCESSABLE_ENTITY, Json(json!({ "error": e }))).into_response())
+}
+
 pub async fn list_epics(
-    State(_pool): State<Arc<SqlitePool>>,
-    headers: HeaderMap,
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(org): Extension<Organization>,
     Query(query): Query<ListEpicsQuery>,
 ) -> Response {
-    // Use query param if provided, otherwise check header, otherwise list ALL
-    let org = query.organization.or_else(|| {
-        headers.get("X-Organization")
-            .and_then(|v| v.to_str().ok())
-            .map(|s| s.to_string())
-    });
-    let args = org.map(|o| json!({ "organization": o }));
+    // Use query param if provided, otherwise the request's organization
+    let organization = match org.resolve(query.organization) {
+        Ok(organization) => organization,
+        Err((status, message)) => return (status, Json(json!({ "error": message }))).into_response(),
+    };
+    let args = json!({ "organization": organization });
 
-    match call_mcp_tool("list_epics", args).await {
-        Ok(result) => {
+    match call_mcp_tool("list_epics", Some(args)).await {
+        Ok(mut result) => {
+            // One grouped query for every epic's ticket rollup
+            match ticketing_system::rollups::epic_rollups(&pool, Some(&organization)).await {
+                Ok(rollups) => attach_progress(&mut result, "epics", "epic_id", rollups),
+                Err(e) => warn!("Failed to compute epic progress rollups: {:?}", e),
+            }
+            match structure::epic_layouts(&pool, &organization).await {
+                Ok(layouts) => {
+                    let positions = layouts
+                        .iter()
+                        .filter_map(|l| l.position.map(|p| (l.epic_id.clone(), p)))
+                        .collect();
+                    apply_order(&mut result, "epics", "epic_id", &positions);
+                    if let Some(epics) = result.get_mut("epics").and_then(|v| v.as_array_mut()) {
+                        for epic in epics {
+                            let epic_id = epic.get("epic_id").and_then(|v| v.as_str()).unwrap_or_default().to_string();
+                            attach_metadata(epic, layouts.iter().find(|l| l.epic_id == epic_id));
+                        }
+                    }
+                }
+                Err(e) => warn!("Failed to load epic layout: {:?}", e),
+            }
             (StatusCode::OK, Json(result)).into_response()
         }
         Err(e) => {
@@ -50,15 +178,19 @@ pub async fn list_epics(
 }
 
 pub async fn get_epic(
-    State(_pool): State<Arc<SqlitePool>>,
-    headers: HeaderMap,
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(org): Extension<Organization>,
     Path(epic_id): Path<String>,
 ) -> Response {
-    let organization = get_organization(&headers);
+    let organization = org.name;
     let args = json!({ "organization": organization, "epic_id": epic_id });
 
     match call_mcp_tool("get_epic", Some(args)).await {
-        Ok(result) => {
+        Ok(mut result) => {
+            match structure::epic_layouts(&pool, &organization).await {
+                Ok(layouts) => attach_metadata(&mut result, layouts.iter().find(|l| l.epic_id == epic_id)),
+                Err(e) => warn!("Failed to load epic layout: {:?}", e),
+            }
             (StatusCode::OK, Json(result)).into_response()
         }
         Err(e) => {
@@ -80,8 +212,12 @@ pub async fn get_epic(
 
 pub async fn create_epic(
     State(_pool): State<Arc<SqlitePool>>,
+    Extension(org): Extension<Organization>,
     Json(request): Json<CreateEpicRequest>,
 ) -> Response {
+    if let Err((status, message)) = org.authorize(&request.organization) {
+        return (status, Json(json!({ "error": message }))).into_response();
+    }
     let args = json!({
         "organization": request.organization,
         "epics": [{
@@ -114,10 +250,10 @@ pub async fn create_epic(
 
 pub async fn delete_epic(
     State(_pool): State<Arc<SqlitePool>>,
-    headers: HeaderMap,
+    Extension(org): Extension<Organization>,
     Path(epic_id): Path<String>,
 ) -> Response {
-    let organization = get_organization(&headers);
+    let organization = org.name;
     let args = json!({ "organization": organization, "epic_id": epic_id });
 
     match call_mcp_tool("delete_epic", Some(args)).await {
@@ -140,4 +276,109 @@ pub async fn delete_epic(
             }
         }
     }
-}
\ No newline at end of file
+}
+
+/// PUT /api/epics/order - set the display order of an organization's epics
+pub async fn reorder_epics(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(org): Extension<Organization>,
+    Json(request): Json<ReorderEpicsRequest>,
+) -> Response {
+    let organization = match org.resolve(request.organization) {
+        Ok(organization) => organization,
+        Err((status, message)) => return (status, Json(json!({ "error": message }))).into_response(),
+    };
+
+    let epics = match call_mcp_tool("list_epics", Some(json!({ "organization": organization }))).await {
+        Ok(result) => result,
+        Err(e) => {
+            error!("Failed to list epics: {:?}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({ "error": format!("Failed to list epics: {}", e) }))
+            ).into_response();
+        }
+    };
+    if let Err(response) = validate_order(&request.epic_ids, &listed_ids(&epics, "epics", "epic_id"), "epic") {
+        return response;
+    }
+
+    match structure::set_epic_positions(&pool, &organization, &request.epic_ids).await {
+        Ok(()) => {
+            info!("Reordered {} epics in {}", request.epic_ids.len(), organization);
+            (StatusCode::OK, Json(json!({ "organization": organization, "epic_ids": request.epic_ids }))).into_response()
+        }
+        Err(e) => {
+            error!("Failed to reorder epics: {:?}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({ "error": format!("Failed to reorder epics: {}", e) }))
+            ).into_response()
+        }
+    }
+}
+
+/// Trimmed label, `Some("")` meaning clear it
+fn normalize_label(label: Option<String>, field: &str) -> Result<Option<String>, String> {
+    let Some(label) = label else { return Ok(None) };
+    let label = label.trim().to_string();
+    if label.chars().count() > MAX_LAYOUT_LABEL_CHARS {
+        return Err(format!("{} is limited to {} characters", field, MAX_LAYOUT_LABEL_CHARS));
+    }
+    Ok(Some(label))
+}
+
+/// PATCH /api/epics/:epic_id - set the epic's planning group and theme
+pub async fn update_epic_metadata(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(org): Extension<Organization>,
+    Path(epic_id): Path<String>,
+    Json(request): Json<UpdateEpicMetadataRequest>,
+) -> Response {
+    let organization = org.name;
+    let (group, theme) = match (normalize_label(request.group, "group"), normalize_label(request.theme, "theme")) {
+        (Ok(group), Ok(theme)) => (group, theme),
+        (Err(e), _) | (_, Err(e)) => return (StatusCode::BAD_REQUEST, Json(json!({ "error": e }))).into_response(),
+    };
+
+    let args = json!({ "organization": organization, "epic_id": epic_id });
+    if let Err(e) = call_mcp_tool("get_epic", Some(args)).await {
+        let status = if e.to_string().contains("not found") { StatusCode::NOT_FOUND } else { StatusCode::INTERNAL_SERVER_ERROR };
+        return (status, Json(json!({ "error": format!("Failed to get epic: {}", e) }))).into_response();
+    }
+
+    match structure::update_epic_metadata(&pool, &organization, &epic_id, group.as_deref(), theme.as_deref()).await {
+        Ok(layout) => (StatusCode::OK, Json(layout)).into_response(),
+        Err(e) => {
+            error!("Failed to update epic metadata: {:?}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({ "error": format!("Failed to update epic: {}", e) }))
+            ).into_response()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn orders_positioned_items_first_and_keeps_the_rest_in_place() {
+        let mut result = json!({ "epics": [
+            { "epic_id": "a" }, { "epic_id": "b" }, { "epic_id": "c" }, { "epic_id": "d" }
+        ]});
+        let positions = HashMap::from([("c".to_string(), 0), ("a".to_string(), 1)]);
+        apply_order(&mut result, "epics", "epic_id", &positions);
+
+        let order: Vec<&str> = result["epics"].as_array().unwrap().iter().map(|e| e["epic_id"].as_str().unwrap()).collect();
+        assert_eq!(order, ["c", "a", "b", "d"]);
+        assert_eq!(result["epics"][0]["position"], json!(0));
+        assert_eq!(result["epics"][2]["position"], Value::Null);
+
+        let known = listed_ids(&result, "epics", "epic_id");
+        assert!(check_order(&["b".to_string(), "a".to_string()], &known, "epic").is_ok());
+        assert!(check_order(&["a".to_string(), "a".to_string()], &known, "epic").is_err());
+        assert!(check_order(&["z".to_string()], &known, "epic").is_err());
+    }
+}
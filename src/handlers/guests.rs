@@ -0,0 +1,174 @@
+//! Guest collaborators: invitations, shares, and the guest's own ticket list.
+//! What guests may do is enforced by `crate::guest_access`.
+
+use axum::{
+    extract::{Extension, Path, State},
+    http::StatusCode,
+    Json,
+};
+use chrono::{Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use ticketing_system::{
+    guests, tickets, CreateGuestInvitationRequest, GuestAccess, GuestInvitation, GuestShare, SqlitePool, Ticket, User,
+};
+
+use crate::tenancy::Organization;
+
+const INVITATION_VALID_DAYS: i64 = 14;
+
+#[derive(Debug, Deserialize)]
+pub struct InviteGuestRequest {
+    pub email: String,
+    #[serde(default)]
+    pub shares: Vec<GuestShare>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateGuestSharesRequest {
+    pub shares: Vec<GuestShare>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct GuestListResponse {
+    pub guests: Vec<GuestAccess>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SharedTicket {
+    pub ticket: Ticket,
+    /// Steps the guest may approve or reject
+    pub step_ids: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SharedTicketsResponse {
+    pub tickets: Vec<SharedTicket>,
+}
+
+/// Check each shared ticket is in the organization and each assigned step is in its pipeline
+async fn validate_shares(pool: &SqlitePool, organization: &str, shares: &[GuestShare]) -> Result<(), (StatusCode, String)> {
+    let mut problems = Vec::new();
+    for share in shares {
+        let ticket = tickets::get_ticket_by_id(pool, &share.ticket_id)
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        let Some(ticket) = ticket.filter(|t| t.organization == organization) else {
+            problems.push(format!("unknown ticket '{}'", share.ticket_id));
+            continue;
+        };
+        for step_id in &share.step_ids {
+            let in_pipeline = ticket
+                .pipeline
+                .as_ref()
+                .is_some_and(|p| p.steps.iter().any(|s| &s.step_id == step_id));
+            if !in_pipeline {
+                problems.push(format!("ticket '{}' has no pipeline step '{}'", share.ticket_id, step_id));
+            }
+        }
+    }
+    if problems.is_empty() {
+        Ok(())
+    } else {
+        Err((StatusCode::UNPROCESSABLE_ENTITY, problems.join("; ")))
+    }
+}
+
+/// Load a guest of the request's organization
+async fn load_guest(pool: &SqlitePool, org: &Organization, user_id: &str) -> Result<GuestAccess, (StatusCode, String)> {
+    guests::get_guest(pool, user_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .filter(|g| g.organization == org.name)
+        .ok_or_else(|| (StatusCode::NOT_FOUND, "Guest not found".to_string()))
+}
+
+/// POST /api/guests/invitations
+///
+/// The returned token is what the guest accepts at
+/// `POST /api/auth/guest-invitations/:token/accept`.
+pub async fn invite_guest(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(user): Extension<User>,
+    Extension(org): Extension<Organization>,
+    Json(req): Json<InviteGuestRequest>,
+) -> Result<(StatusCode, Json<GuestInvitation>), (StatusCode, String)> {
+    let email = req.email.trim();
+    if !email.contains('@') {
+        return Err((StatusCode::BAD_REQUEST, "A valid email is required".to_string()));
+    }
+    validate_shares(&pool, &org.name, &req.shares).await?;
+
+    let invitation = guests::create_invitation(
+        &pool,
+        &CreateGuestInvitationRequest {
+            organization: org.name,
+            email: email.to_string(),
+            shares: req.shares,
+            invited_by: user.user_id,
+            expires_at: (Utc::now() + Duration::days(INVITATION_VALID_DAYS)).to_rfc3339(),
+        },
+    )
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Ok((StatusCode::CREATED, Json(invitation)))
+}
+
+/// GET /api/guests
+pub async fn list_guests(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(org): Extension<Organization>,
+) -> Result<Json<GuestListResponse>, (StatusCode, String)> {
+    let guests = guests::list_guests(&pool, &org.name)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Ok(Json(GuestListResponse { guests }))
+}
+
+/// PUT /api/guests/:user_id/shares - replace what is shared with a guest
+pub async fn update_guest_shares(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(org): Extension<Organization>,
+    Path(user_id): Path<String>,
+    Json(req): Json<UpdateGuestSharesRequest>,
+) -> Result<Json<GuestAccess>, (StatusCode, String)> {
+    load_guest(&pool, &org, &user_id).await?;
+    validate_shares(&pool, &org.name, &req.shares).await?;
+    guests::set_shares(&pool, &user_id, &req.shares)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Ok(Json(load_guest(&pool, &org, &user_id).await?))
+}
+
+/// DELETE /api/guests/:user_id - unshare everything. The account stays a
+/// guest, so it can't fall back to member access.
+pub async fn revoke_guest(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(org): Extension<Organization>,
+    Path(user_id): Path<String>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    load_guest(&pool, &org, &user_id).await?;
+    guests::set_shares(&pool, &user_id, &[])
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// GET /api/guest/tickets - the tickets shared with the calling guest
+pub async fn list_shared_tickets(
+    State(pool): State<Arc<SqlitePool>>,
+    guest: Option<Extension<GuestAccess>>,
+) -> Result<Json<SharedTicketsResponse>, (StatusCode, String)> {
+    let Some(Extension(access)) = guest else {
+        return Err((StatusCode::FORBIDDEN, "Only guests have shared tickets".to_string()));
+    };
+    let mut shared = Vec::new();
+    for share in access.shares {
+        match tickets::get_ticket_by_id(&pool, &share.ticket_id).await {
+            Ok(Some(ticket)) => shared.push(SharedTicket { ticket, step_ids: share.step_ids }),
+            Ok(None) => {}
+            Err(e) => return Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
+        }
+    }
+    Ok(Json(SharedTicketsResponse { tickets: shared }))
+}
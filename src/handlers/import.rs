@@ -0,0 +1,356 @@
+//! Ticket import from Jira, Linear and GitHub Issues
+//!
+//! Projects map to epics (one "imported" slice each) and issues map to
+//! tickets. Requests are dry runs unless `dry_run: false`, so the mapping
+//! report can be reviewed before anything is created. Issues already imported
+//! into the organization are skipped; other organizations may import them too.
+
+use axum::{
+    extract::{Extension, Path, State},
+    http::StatusCode,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use ticketing_system::{imports, SqlitePool};
+use tracing::{error, info};
+
+use crate::importers::{self, ExportFormat, ImportCredentials, ImportSource, ImportedIssue, ImportedProject};
+use crate::mcp_wrapper::call_mcp_tool;
+use crate::tenancy::Organization;
+
+use super::slices::slice_pipeline_defaults;
+
+/// Slice that imported tickets are created in
+const IMPORT_SLICE_ID: &str = "imported";
+
+/// Status for source statuses with no mapping
+const FALLBACK_STATUS: &str = "open";
+
+const TICKET_STATUSES: &[&str] = &["open", "in_progress", "blocked", "completed", "cancelled"];
+
+#[derive(Debug, Deserialize)]
+pub struct ImportRequest {
+    /// Exported payload; required unless `credentials` is set
+    pub data: Option<String>,
+    /// "json" (default) or "csv"
+    pub format: Option<ExportFormat>,
+    /// Fetch from the source API instead of parsing `data`
+    pub credentials: Option<ImportCredentials>,
+    /// Defaults to true: report the mapping without creating anything
+    pub dry_run: Option<bool>,
+    /// Source status (case-insensitive) -> ticket status overrides
+    #[serde(default)]
+    pub status_map: HashMap<String, String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct EpicPlan {
+    pub epic_id: String,
+    pub title: String,
+    pub source_project: String,
+    /// False when the epic will be created by this import
+    pub exists: bool,
+    pub ticket_count: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TicketPlan {
+    pub source_key: String,
+    pub title: String,
+    pub epic_id: String,
+    pub source_status: String,
+    pub status: String,
+    pub labels: Vec<String>,
+    pub comment_count: usize,
+    /// Set when the issue won't be imported
+    pub skipped: Option<String>,
+    /// Set once the ticket has been created
+    pub ticket_id: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ImportReport {
+    pub source: ImportSource,
+    pub dry_run: bool,
+    pub epics: Vec<EpicPlan>,
+    pub tickets: Vec<TicketPlan>,
+    /// Source status -> ticket status, for every status seen
+    pub status_mapping: BTreeMap<String, String>,
+    /// Source statuses with no mapping (imported as "open")
+    pub unmapped_statuses: Vec<String>,
+    pub created_epics: usize,
+    pub created_tickets: usize,
+    pub errors: Vec<String>,
+}
+
+async fn epic_exists(organization: &str, epic_id: &str) -> bool {
+    call_mcp_tool("get_epic", Some(json!({ "organization": organization, "epic_id": epic_id })))
+        .await
+        .is_ok()
+}
+
+fn ticket_description(source: ImportSource, issue: &ImportedIssue) -> String {
+    let mut description = issue.description.clone().unwrap_or_default();
+    let mut origin = format!("Imported from {} {}", source.as_str(), issue.key);
+    if let Some(url) = &issue.url {
+        origin.push_str(&format!(" ({})", url));
+    }
+    if let Some(assignee) = &issue.assignee {
+        origin.push_str(&format!(", assigned to {}", assignee));
+    }
+    if !description.is_empty() {
+        description.push_str("\n\n---\n");
+    }
+    description.push_str(&origin);
+    description
+}
+
+/// Build the mapping report without touching tickets
+async fn plan_import(
+    pool: &SqlitePool,
+    organization: &str,
+    source: ImportSource,
+    projects: &[ImportedProject],
+    status_map: &HashMap<String, String>,
+    dry_run: bool,
+) -> ImportReport {
+    let mut report = ImportReport {
+        source,
+        dry_run,
+        epics: Vec::new(),
+        tickets: Vec::new(),
+        status_mapping: BTreeMap::new(),
+        unmapped_statuses: Vec::new(),
+        created_epics: 0,
+        created_tickets: 0,
+        errors: Vec::new(),
+    };
+
+    for project in projects {
+        let epic_id = importers::epic_id_for(source, &project.key);
+        report.epics.push(EpicPlan {
+            exists: epic_exists(organization, &epic_id).await,
+            epic_id: epic_id.clone(),
+            title: project.name.clone(),
+            source_project: project.key.clone(),
+            ticket_count: project.issues.len(),
+        });
+
+        for issue in &project.issues {
+            let status = match importers::map_status(&issue.status, status_map) {
+                Some(status) => status,
+                None => {
+                    if !report.unmapped_statuses.contains(&issue.status) {
+                        report.unmapped_statuses.push(issue.status.clone());
+                    }
+                    FALLBACK_STATUS.to_string()
+                }
+            };
+            report.status_mapping.insert(issue.status.clone(), status.clone());
+
+            let skipped = match imports::find_ticket_by_external_id(pool, organization, source.as_str(), &issue.external_id)
+                .await
+            {
+                Ok(Some(ticket_id)) => Some(format!("Already imported as {}", ticket_id)),
+                Ok(None) => None,
+                Err(e) => Some(format!("Failed to check for previous import: {}", e)),
+            };
+
+            report.tickets.push(TicketPlan {
+                source_key: issue.key.clone(),
+                title: issue.title.clone(),
+                epic_id: epic_id.clone(),
+                source_status: issue.status.clone(),
+                status,
+                labels: issue.labels.clone(),
+                comment_count: issue.comments.len(),
+                skipped,
+                ticket_id: None,
+            });
+        }
+    }
+    report
+}
+
+/// Create epics, tickets, statuses and comments for a reviewed plan. Tickets
+/// in `report` are in the same order as the projects' issues.
+async fn commit_import(
+    pool: &SqlitePool,
+    organization: &str,
+    source: ImportSource,
+    projects: &[ImportedProject],
+    report: &mut ImportReport,
+) {
+    let mut plans = report.tickets.iter_mut();
+
+    for (project, epic) in projects.iter().zip(report.epics.iter()) {
+        let project_plans: Vec<&mut TicketPlan> = plans.by_ref().take(project.issues.len()).collect();
+        let pending: Vec<(&ImportedIssue, &mut TicketPlan)> = project
+            .issues
+            .iter()
+            .zip(project_plans)
+            .filter(|(_, plan)| plan.skipped.is_none())
+            .collect();
+        if pending.is_empty() {
+            continue;
+        }
+
+        if !epic.exists {
+            let args = json!({
+                "organization": organization,
+                "epics": [{
+                    "epic_id": epic.epic_id,
+                    "title": epic.title,
+                    "notes": format!("Imported from {} project {}", source.as_str(), epic.source_project),
+                }]
+            });
+            if let Err(e) = call_mcp_tool("create_epics", Some(args)).await {
+                error!("Failed to create imported epic {}: {:?}", epic.epic_id, e);
+                report.errors.push(format!("Failed to create epic {}: {}", epic.epic_id, e));
+                continue;
+            }
+            report.created_epics += 1;
+
+            let args = json!({
+                "organization": organization,
+                "slices": [{
+                    "epic_id": epic.epic_id,
+                    "slice_id": IMPORT_SLICE_ID,
+                    "title": format!("Imported from {}", source.as_str()),
+                }]
+            });
+            if let Err(e) = call_mcp_tool("create_slices", Some(args)).await {
+                error!("Failed to create import slice in {}: {:?}", epic.epic_id, e);
+                report.errors.push(format!("Failed to create slice in {}: {}", epic.epic_id, e));
+                continue;
+            }
+        }
+
+        let defaults = slice_pipeline_defaults(pool, organization, &epic.epic_id, IMPORT_SLICE_ID).await;
+        let specs: Vec<_> = pending
+            .iter()
+            .enumerate()
+            .map(|(i, (issue, _))| {
+                json!({
+                    "ref": format!("import-{}", i),
+                    "title": issue.title,
+                    "ticket_type": "milestone",
+                    "pipeline_template_id": defaults.template_id,
+                    "description": ticket_description(source, issue),
+                    "labels": issue.labels,
+                })
+            })
+            .collect();
+        let args = json!({
+            "organization": organization,
+            "epic_id": epic.epic_id,
+            "slice_id": IMPORT_SLICE_ID,
+            "tickets": specs,
+        });
+        let created = match call_mcp_tool("create_slice_tickets", Some(args)).await {
+            Ok(result) => result.get("tickets").and_then(|t| t.as_array()).cloned().unwrap_or_default(),
+            Err(e) => {
+                error!("Failed to create imported tickets in {}: {:?}", epic.epic_id, e);
+                report.errors.push(format!("Failed to create tickets in {}: {}", epic.epic_id, e));
+                continue;
+            }
+        };
+
+        for ((issue, plan), result) in pending.into_iter().zip(created.iter()) {
+            let Some(ticket_id) = result.pointer("/ticket/ticket_id").and_then(|id| id.as_str()) else {
+                report.errors.push(format!("No ticket id returned for {}", issue.key));
+                continue;
+            };
+            plan.ticket_id = Some(ticket_id.to_string());
+            report.created_tickets += 1;
+
+            if let Err(e) =
+                imports::record_external_id(pool, organization, source.as_str(), &issue.external_id, ticket_id).await
+            {
+                report.errors.push(format!("Failed to record source id for {}: {}", issue.key, e));
+            }
+
+            if plan.status != FALLBACK_STATUS {
+                let args = json!({
+                    "organization": organization,
+                    "epic_id": epic.epic_id,
+                    "slice_id": IMPORT_SLICE_ID,
+                    "ticket_id": ticket_id,
+                    "new_status": plan.status,
+                });
+                if let Err(e) = call_mcp_tool("update_ticket_status", Some(args)).await {
+                    report.errors.push(format!("Failed to set status of {}: {}", issue.key, e));
+                }
+            }
+
+            for comment in &issue.comments {
+                if let Err(e) = ticketing_system::ticket_history::log_imported_comment(
+                    pool,
+                    ticket_id,
+                    &comment.author,
+                    &comment.body,
+                    comment.created_at.as_deref(),
+                )
+                .await
+                {
+                    report.errors.push(format!("Failed to import comment on {}: {}", issue.key, e));
+                    break;
+                }
+            }
+        }
+    }
+}
+
+/// Import tickets from Jira, Linear or GitHub (POST /api/import/:source)
+pub async fn import_tickets(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(org): Extension<Organization>,
+    Path(source): Path<String>,
+    Json(request): Json<ImportRequest>,
+) -> Result<Json<ImportReport>, (StatusCode, String)> {
+    let source: ImportSource = source.parse().map_err(|e: anyhow::Error| (StatusCode::NOT_FOUND, e.to_string()))?;
+    let organization = org.name;
+
+    let mut status_map = HashMap::new();
+    for (raw, status) in request.status_map {
+        if !TICKET_STATUSES.contains(&status.as_str()) {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                format!("Invalid status '{}' for '{}' (expected one of {})", status, raw, TICKET_STATUSES.join(", ")),
+            ));
+        }
+        status_map.insert(raw.trim().to_lowercase(), status);
+    }
+
+    let projects = match (&request.credentials, &request.data) {
+        (Some(credentials), _) => importers::fetch(source, credentials)
+            .await
+            .map_err(|e| (StatusCode::BAD_GATEWAY, format!("Failed to fetch from {}: {}", source.as_str(), e)))?,
+        (None, Some(data)) => {
+            importers::parse_export(source, request.format.unwrap_or(ExportFormat::Json), data)
+                .map_err(|e| (StatusCode::BAD_REQUEST, format!("Failed to parse export: {}", e)))?
+        }
+        (None, None) => {
+            return Err((StatusCode::BAD_REQUEST, "Either data or credentials is required".to_string()));
+        }
+    };
+    if projects.is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "No issues found to import".to_string()));
+    }
+
+    let dry_run = request.dry_run.unwrap_or(true);
+    let mut report = plan_import(&pool, &organization, source, &projects, &status_map, dry_run).await;
+    if !dry_run {
+        commit_import(&pool, &organization, source, &projects, &mut report).await;
+        info!(
+            "Imported {} tickets ({} epics created) from {}",
+            report.created_tickets,
+            report.created_epics,
+            source.as_str()
+        );
+    }
+    Ok(Json(report))
+}
@@ -1,12 +1,16 @@
-use axum::{extract::State, Json};
+use axum::{extract::State, Extension, Json};
 use std::sync::Arc;
 use std::path::PathBuf;
 use std::collections::HashMap;
 use sqlx::SqlitePool;
 use serde::Deserialize;
 
+use ticketing_system::User;
+
 use crate::agents::AgentType;
+use crate::tenancy::Organization;
 use super::chat_stream::{self, ChatConfig, SseStream};
+use super::project_workload::build_capacity_summary;
 
 #[derive(Debug, Deserialize)]
 pub struct LifePlannerRequest {
@@ -15,39 +19,66 @@ pub struct LifePlannerRequest {
     pub conversation_id: Option<String>,
 }
 
-fn config() -> ChatConfig {
+pub(super) fn config() -> ChatConfig {
     ChatConfig {
         agent_type: AgentType::LifePlanner,
         prompt_name: "life-planner",
         working_dir: PathBuf::from("/Users/jarvisgpt/projects"),
         prompt_vars: HashMap::new(),
+        env: HashMap::new(),
+        add_dirs: Vec::new(),
+        organization: None,
     }
 }
 
 /// Build the context-injected message by prepending all life context entries
-async fn inject_life_context(db: &SqlitePool, message: &str) -> String {
-    match ticketing_system::life_context::list_contexts(db).await {
-        Ok(contexts) if !contexts.is_empty() => {
-            let mut parts = vec!["[Life Context]".to_string()];
+/// and the user's workload capacity (used when generating the daily plan)
+async fn inject_life_context(db: &SqlitePool, user_id: &str, message: &str) -> String {
+    let mut parts = Vec::new();
+
+    if let Ok(contexts) = ticketing_system::life_context::list_contexts(db).await {
+        if !contexts.is_empty() {
+            parts.push("[Life Context]".to_string());
             for ctx in &contexts {
                 parts.push(format!("\n## {}\n{}", ctx.key, ctx.content));
             }
-            parts.push("---".to_string());
-            parts.push(String::new());
-            parts.push(message.to_string());
-            parts.join("\n")
         }
-        _ => message.to_string(),
     }
+
+    if let Ok(capacity) = build_capacity_summary(db, user_id).await {
+        parts.push("\n[Workload Capacity]".to_string());
+        parts.push(format!(
+            "Open project items: {}{}",
+            capacity.open_items,
+            capacity.wip_limit.map(|l| format!(" (WIP limit {})", l)).unwrap_or_default()
+        ));
+        if let Some(hours) = capacity.hours_per_day {
+            parts.push(format!("Project hours available per day: {}", hours));
+        }
+    }
+
+    if parts.is_empty() {
+        return message.to_string();
+    }
+
+    parts.push("---".to_string());
+    parts.push(String::new());
+    parts.push(message.to_string());
+    parts.join("\n")
 }
 
 /// POST /api/life-planner/chat
 pub async fn life_planner_chat(
     State(db): State<Arc<SqlitePool>>,
+    Extension(user): Extension<User>,
+    Extension(org): Extension<Organization>,
     Json(req): Json<LifePlannerRequest>,
 ) -> SseStream {
     tracing::info!("=== LIFE_PLANNER_CHAT START ===");
-    let injected_message = inject_life_context(&db, &req.message).await;
+    if let Err(exceeded) = crate::quotas::check_agent_run(&db, &org.name).await {
+        return chat_stream::create_error_sse(exceeded.message());
+    }
+    let injected_message = inject_life_context(&db, &user.user_id, &req.message).await;
     chat_stream::chat(
         db,
         injected_message,
@@ -60,13 +91,25 @@ pub async fn life_planner_chat(
 /// POST /api/life-planner/resume
 pub async fn life_planner_resume(
     State(db): State<Arc<SqlitePool>>,
+    Extension(user): Extension<User>,
+    Extension(org): Extension<Organization>,
     Json(req): Json<LifePlannerRequest>,
 ) -> SseStream {
     tracing::info!("=== LIFE_PLANNER_RESUME START ===");
+    if let Err(exceeded) = crate::quotas::check_agent_run(&db, &org.name).await {
+        return chat_stream::create_error_sse(exceeded.message());
+    }
     let session_id = match req.session_id {
         Some(id) => id,
         None => return chat_stream::create_error_sse("session_id is required for resume".to_string()),
     };
+    // A thread past its history budget continues in a fresh session from its memory
+    if let Some(conversation_id) = req.conversation_id.as_deref() {
+        if let Some(prompt) = crate::conversation_memory::compacted_resume(&db, conversation_id, &req.message).await {
+            let injected_message = inject_life_context(&db, &user.user_id, &prompt).await;
+            return chat_stream::chat(db, injected_message, None, req.conversation_id, config());
+        }
+    }
     chat_stream::resume(
         db,
         req.message,
@@ -75,3 +118,9 @@ pub async fn life_planner_resume(
         config(),
     )
 }
+
+/// First message and config for regenerating a life planner reply from an
+/// edited message (see `update_message`)
+pub(super) async fn regeneration(db: &SqlitePool, user_id: &str, message: &str) -> (String, ChatConfig) {
+    (inject_life_context(db, user_id, message).await, config())
+}
@@ -0,0 +1,172 @@
+//! Shared meeting notepad, see `crate::co_notes`
+
+use axum::{
+    extract::{Extension, Path, State},
+    http::StatusCode,
+    response::sse::{Event, KeepAlive, Sse},
+    Json,
+};
+use futures::stream::Stream;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::sync::Arc;
+use std::time::Duration;
+use ticketing_system::{SqlitePool, User};
+use tokio::sync::{broadcast, Mutex, RwLock};
+
+use crate::co_notes::{CoNotes, NotesOp};
+
+#[derive(Debug, Deserialize)]
+pub struct PatchCoNotesRequest {
+    pub ops: Vec<NotesOp>,
+}
+
+/// A patch as applied, sent to subscribers
+#[derive(Debug, Clone, Serialize)]
+pub struct CoNotesPatch {
+    pub room_id: String,
+    pub revision: u64,
+    pub ops: Vec<NotesOp>,
+    pub author: String,
+    pub updated_at: String,
+}
+
+pub struct CoNotesHub {
+    /// Serializes patches so each applies to the latest revision
+    writes: Mutex<()>,
+    channels: RwLock<HashMap<String, broadcast::Sender<CoNotesPatch>>>,
+}
+
+impl CoNotesHub {
+    fn new() -> Self {
+        Self { writes: Mutex::new(()), channels: RwLock::new(HashMap::new()) }
+    }
+
+    async fn subscribe(&self, room_id: &str) -> broadcast::Receiver<CoNotesPatch> {
+        let mut channels = self.channels.write().await;
+        channels.entry(room_id.to_string()).or_insert_with(|| broadcast::channel(64).0).subscribe()
+    }
+
+    async fn publish(&self, patch: CoNotesPatch) {
+        let mut channels = self.channels.write().await;
+        let room_id = patch.room_id.clone();
+        if let Some(tx) = channels.get(&room_id) {
+            // Drop the channel once everyone has left
+            if tx.send(patch).is_err() {
+                channels.remove(&room_id);
+            }
+        }
+    }
+}
+
+lazy_static::lazy_static! {
+    static ref CO_NOTES: CoNotesHub = CoNotesHub::new();
+}
+
+/// A meeting's co-notes, empty until the first patch
+pub(crate) async fn load_co_notes(db: &SqlitePool, room_id: &str) -> Result<CoNotes, (StatusCode, String)> {
+    let stored = ticketing_system::meetings::get_co_notes(db, room_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    match stored {
+        Some(json) => serde_json::from_str(&json)
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Co-notes for {} are unreadable: {}", room_id, e))),
+        None => Ok(CoNotes::default()),
+    }
+}
+
+async fn require_meeting(db: &SqlitePool, room_id: &str) -> Result<(), (StatusCode, String)> {
+    ticketing_system::meetings::get_meeting(db, room_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or_else(|| (StatusCode::NOT_FOUND, "Meeting not found".to_string()))?;
+    Ok(())
+}
+
+/// GET /api/meetings/:room_id/notes
+pub async fn get_co_notes(
+    Path(room_id): Path<String>,
+    State(db): State<Arc<SqlitePool>>,
+) -> Result<Json<CoNotes>, (StatusCode, String)> {
+    require_meeting(&db, &room_id).await?;
+    Ok(Json(load_co_notes(&db, &room_id).await?))
+}
+
+/// PATCH /api/meetings/:room_id/notes
+///
+/// Applies the ops to the latest revision; concurrent patches to the same
+/// block resolve last writer wins. Returns the notes after the patch.
+pub async fn patch_co_notes(
+    Path(room_id): Path<String>,
+    State(db): State<Arc<SqlitePool>>,
+    Extension(user): Extension<User>,
+    Json(req): Json<PatchCoNotesRequest>,
+) -> Result<Json<CoNotes>, (StatusCode, String)> {
+    require_meeting(&db, &room_id).await?;
+
+    let _write = CO_NOTES.writes.lock().await;
+    let mut notes = load_co_notes(&db, &room_id).await?;
+    let now = chrono::Utc::now().to_rfc3339();
+    notes
+        .apply(&req.ops, &user.name, &now)
+        .map_err(|problems| (StatusCode::UNPROCESSABLE_ENTITY, problems.join("; ")))?;
+    let json = serde_json::to_string(&notes).map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    ticketing_system::meetings::set_co_notes(&db, &room_id, &json)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    CO_NOTES
+        .publish(CoNotesPatch {
+            room_id,
+            revision: notes.revision,
+            ops: req.ops,
+            author: user.name,
+            updated_at: now,
+        })
+        .await;
+    Ok(Json(notes))
+}
+
+/// GET /api/meetings/:room_id/notes/stream
+///
+/// SSE: a `snapshot` event with the current notes, then a `patch` event per
+/// applied patch. Patches at or below the snapshot's revision can be
+/// ignored; a subscriber that falls behind gets a fresh snapshot.
+pub async fn stream_co_notes(
+    Path(room_id): Path<String>,
+    State(db): State<Arc<SqlitePool>>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, (StatusCode, String)> {
+    require_meeting(&db, &room_id).await?;
+    // Subscribe before the snapshot so no patch falls between them
+    let mut rx = CO_NOTES.subscribe(&room_id).await;
+    let snapshot = load_co_notes(&db, &room_id).await?;
+
+    let stream = async_stream::stream! {
+        if let Ok(json) = serde_json::to_string(&snapshot) {
+            yield Ok(Event::default().event("snapshot").data(json));
+        }
+        loop {
+            match rx.recv().await {
+                Ok(patch) => {
+                    if let Ok(json) = serde_json::to_string(&patch) {
+                        yield Ok(Event::default().event("patch").data(json));
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(_)) => {
+                    let Ok(notes) = load_co_notes(&db, &room_id).await else { break };
+                    if let Ok(json) = serde_json::to_string(&notes) {
+                        yield Ok(Event::default().event("snapshot").data(json));
+                    }
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            }
+        }
+    };
+
+    Ok(Sse::new(stream).keep_alive(
+        KeepAlive::new()
+            .interval(Duration::from_secs(15))
+            .text("ping"),
+    ))
+}
@@ -1,12 +1,17 @@
 use axum::{
-    extract::{Path, State},
+    extract::{Extension, Path, State},
     http::StatusCode,
+    response::sse::{Event, KeepAlive, Sse},
     Json,
 };
-use serde::Deserialize;
+use futures::stream::Stream;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::convert::Infallible;
 use std::sync::Arc;
+use std::time::Duration;
 use sqlx::SqlitePool;
+use tokio::sync::{broadcast, RwLock, Semaphore};
 
 use cc_sdk::{query, ClaudeCodeOptions, Message as CcMessage, ContentBlock, ToolsConfig};
 use futures::StreamExt;
@@ -16,16 +21,28 @@ use ticketing_system::{
 };
 
 use crate::agents::prompts::load_prompt;
+use crate::agents::tool_audit::{store_standalone_tool_calls, ToolCallLog};
 use crate::agents::AgentType;
+use crate::llm_keys::{self, LlmProvider};
+use crate::tenancy::Organization;
 
 // ============================================================================
 // Transcription Handler (OpenAI Whisper)
 // ============================================================================
 
+/// OpenAI key to transcribe with: the organization's own, else `OPENAI_KEY`
+async fn openai_key(db: &SqlitePool, organization: &str) -> Result<String, (StatusCode, String)> {
+    llm_keys::api_key(db, organization, LlmProvider::OpenAi).await.ok_or((
+        StatusCode::INTERNAL_SERVER_ERROR,
+        "No OpenAI key: set one for the organization or set OPENAI_KEY".to_string(),
+    ))
+}
+
 /// POST /api/meetings/:room_id/transcribe
 pub async fn transcribe_meeting(
     Path(room_id): Path<String>,
     State(db): State<Arc<SqlitePool>>,
+    Extension(org): Extension<Organization>,
     Json(req): Json<TranscribeAudioRequest>,
 ) -> Result<Json<TranscriptionResponse>, (StatusCode, String)> {
     use base64::Engine;
@@ -33,8 +50,8 @@ pub async fn transcribe_meeting(
         .decode(&req.audio_data)
         .map_err(|e| (StatusCode::BAD_REQUEST, format!("Invalid base64: {}", e)))?;
 
-    let api_key = std::env::var("OPENAI_KEY")
-        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "OPENAI_KEY not set".to_string()))?;
+    let api_key = openai_key(&db, &org.name).await?;
+    crate::quotas::check_transcription(&db, &org.name).await?;
 
     let file_name = format!("audio.{}", req.format);
     let mime_type = match req.format.as_str() {
@@ -53,7 +70,9 @@ pub async fn transcribe_meeting(
 
     let mut form = reqwest::multipart::Form::new()
         .part("file", part)
-        .text("model", "whisper-1");
+        .text("model", "whisper-1")
+        // For the duration, which transcription is billed by
+        .text("response_format", "verbose_json");
 
     if let Some(lang) = &req.language {
         form = form.text("language", lang.clone());
@@ -79,12 +98,15 @@ pub async fn transcribe_meeting(
     #[derive(Deserialize)]
     struct WhisperResponse {
         text: String,
+        #[serde(default)]
+        duration: f64,
     }
 
     let whisper_response: WhisperResponse = response
         .json()
         .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to parse response: {}", e)))?;
+    crate::quotas::record_transcription(&db, &org.name, &room_id, whisper_response.duration).await;
 
     let session_id = format!("mtg-{}", room_id);
 
@@ -106,7 +128,7 @@ pub async fn transcribe_meeting(
     let entry_req = CreateTranscriptEntryRequest {
         session_id: session_id.clone(),
         user_id: "meeting".to_string(),
-        username: "Meeting Transcript".to_string(),
+        username: MERGED_TRANSCRIPT_SPEAKER.to_string(),
         text: whisper_response.text.clone(),
         timestamp: chrono::Utc::now().to_rfc3339(),
     };
@@ -124,7 +146,7 @@ pub async fn transcribe_meeting(
 
     Ok(Json(TranscriptionResponse {
         text: whisper_response.text,
-        duration_seconds: None,
+        duration_seconds: Some(whisper_response.duration),
     }))
 }
 
@@ -186,6 +208,9 @@ pub async fn upload_meeting_audio(
 struct WhisperVerboseResponse {
     #[allow(dead_code)]
     text: String,
+    /// Seconds of audio, which transcription is billed by
+    #[serde(default)]
+    duration: f64,
     segments: Vec<WhisperSegment>,
 }
 
@@ -197,42 +222,281 @@ struct WhisperSegment {
     text: String,
 }
 
+// ============================================================================
+// Transcription Jobs
+// ============================================================================
+
+/// Maximum number of finalization jobs processed at the same time. Jobs beyond
+/// this stay `queued` until a worker slot frees up.
+const MAX_CONCURRENT_TRANSCRIPTIONS: usize = 2;
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum TranscriptionJobState {
+    Queued,
+    Transcribing { segment: usize, total: usize },
+    ExtractingNotes,
+    Completed,
+    Failed { error: String },
+}
+
+impl TranscriptionJobState {
+    pub fn is_finished(&self) -> bool {
+        matches!(self, Self::Completed | Self::Failed { .. })
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TranscriptionJobStatus {
+    pub room_id: String,
+    #[serde(flatten)]
+    pub state: TranscriptionJobState,
+    pub updated_at: String,
+}
+
+pub struct TranscriptionJobs {
+    pub jobs: RwLock<HashMap<String, TranscriptionJobStatus>>,
+    pub channels: RwLock<HashMap<String, broadcast::Sender<TranscriptionJobStatus>>>,
+    pub workers: Semaphore,
+}
+
+impl TranscriptionJobs {
+    pub fn new() -> Self {
+        Self {
+            jobs: RwLock::new(HashMap::new()),
+            channels: RwLock::new(HashMap::new()),
+            workers: Semaphore::new(MAX_CONCURRENT_TRANSCRIPTIONS),
+        }
+    }
+
+    pub async fn get(&self, room_id: &str) -> Option<TranscriptionJobStatus> {
+        self.jobs.read().await.get(room_id).cloned()
+    }
+
+    /// The room's job status and, while the job runs, a receiver for its
+    /// updates. `None` when the room has no job, so no channel is left
+    /// behind for rooms nobody transcribes.
+    pub async fn watch(
+        &self,
+        room_id: &str,
+    ) -> Option<(TranscriptionJobStatus, Option<broadcast::Receiver<TranscriptionJobStatus>>)> {
+        // Holding the jobs lock keeps the job from finishing, and its channel
+        // from being dropped, between the check and subscribing
+        let jobs = self.jobs.read().await;
+        let status = jobs.get(room_id)?.clone();
+        if status.state.is_finished() {
+            return Some((status, None));
+        }
+        let rx = self
+            .channels
+            .write()
+            .await
+            .entry(room_id.to_string())
+            .or_insert_with(|| broadcast::channel(32).0)
+            .subscribe();
+        Some((status, Some(rx)))
+    }
+
+    fn status(room_id: &str, state: TranscriptionJobState) -> TranscriptionJobStatus {
+        TranscriptionJobStatus {
+            room_id: room_id.to_string(),
+            state,
+            updated_at: chrono::Utc::now().to_rfc3339(),
+        }
+    }
+
+    async fn broadcast(&self, status: &TranscriptionJobStatus) {
+        let mut channels = self.channels.write().await;
+        if let Some(tx) = channels.get(&status.room_id) {
+            let _ = tx.send(status.clone());
+        }
+        if status.state.is_finished() {
+            channels.remove(&status.room_id);
+        }
+    }
+
+    /// Queue a job for the room unless one is already in progress, checking
+    /// and registering under one lock so concurrent requests can't both start
+    /// one. `None` when a job is in progress.
+    pub async fn try_start(&self, room_id: &str) -> Option<TranscriptionJobStatus> {
+        let status = {
+            let mut jobs = self.jobs.write().await;
+            if jobs.get(room_id).is_some_and(|job| !job.state.is_finished()) {
+                return None;
+            }
+            let status = Self::status(room_id, TranscriptionJobState::Queued);
+            jobs.insert(room_id.to_string(), status.clone());
+            status
+        };
+        self.broadcast(&status).await;
+        Some(status)
+    }
+
+    /// Record a new state for a room's job and notify any progress subscribers
+    pub async fn set_state(&self, room_id: &str, state: TranscriptionJobState) {
+        let status = Self::status(room_id, state);
+        self.jobs
+            .write()
+            .await
+            .insert(room_id.to_string(), status.clone());
+        self.broadcast(&status).await;
+    }
+}
+
+lazy_static::lazy_static! {
+    pub static ref TRANSCRIPTION_JOBS: TranscriptionJobs = TranscriptionJobs::new();
+}
+
+fn already_in_progress() -> (StatusCode, String) {
+    (StatusCode::CONFLICT, "Transcription already in progress for this meeting".to_string())
+}
+
 /// POST /api/meetings/:room_id/finalize-transcript
+///
+/// Queues the merge/transcribe/notes pipeline as a background job and returns
+/// immediately. Poll `transcription-status` or subscribe to its SSE stream for
+/// progress.
 pub async fn finalize_meeting_transcript(
     Path(room_id): Path<String>,
     State(db): State<Arc<SqlitePool>>,
-) -> Result<Json<TranscriptionResponse>, (StatusCode, String)> {
-    ticketing_system::meetings::update_processing_status(&db, &room_id, "transcribing")
+    Extension(org): Extension<Organization>,
+) -> Result<(StatusCode, Json<TranscriptionJobStatus>), (StatusCode, String)> {
+    let api_key = openai_key(&db, &org.name).await?;
+    crate::quotas::check_transcription(&db, &org.name).await?;
+
+    if !meeting_audio_dir(&room_id).exists() {
+        return Err((StatusCode::NOT_FOUND, "No audio segments found".to_string()));
+    }
+
+    let status = TRANSCRIPTION_JOBS.try_start(&room_id).await.ok_or_else(already_in_progress)?;
+    if let Err(e) = ticketing_system::meetings::update_processing_status(&db, &room_id, "queued").await {
+        TRANSCRIPTION_JOBS
+            .set_state(&room_id, TranscriptionJobState::Failed { error: e.to_string() })
+            .await;
+        return Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string()));
+    }
+
+    let job_room_id = room_id.clone();
+    let organization = org.name;
+    tokio::spawn(async move {
+        let _permit = match TRANSCRIPTION_JOBS.workers.acquire().await {
+            Ok(permit) => permit,
+            Err(_) => return,
+        };
+
+        match run_transcription_job(&db, &job_room_id, &organization, &api_key).await {
+            Ok(()) => {
+                TRANSCRIPTION_JOBS
+                    .set_state(&job_room_id, TranscriptionJobState::Completed)
+                    .await;
+            }
+            Err(e) => {
+                tracing::error!("Transcription job failed for meeting {}: {}", job_room_id, e);
+                ticketing_system::meetings::update_processing_status(&db, &job_room_id, "failed")
+                    .await
+                    .ok();
+                TRANSCRIPTION_JOBS
+                    .set_state(&job_room_id, TranscriptionJobState::Failed { error: e })
+                    .await;
+            }
+        }
+    });
+
+    Ok((StatusCode::ACCEPTED, Json(status)))
+}
+
+/// GET /api/meetings/:room_id/transcription-status
+pub async fn get_transcription_status(
+    Path(room_id): Path<String>,
+    State(db): State<Arc<SqlitePool>>,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    if let Some(status) = TRANSCRIPTION_JOBS.get(&room_id).await {
+        return Ok(Json(serde_json::to_value(status).unwrap_or_default()));
+    }
+
+    // No job in memory (e.g. after a restart) - fall back to the persisted status
+    let meeting = ticketing_system::meetings::get_meeting(&db, &room_id)
         .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or_else(|| (StatusCode::NOT_FOUND, "Meeting not found".to_string()))?;
+
+    Ok(Json(serde_json::json!({
+        "room_id": room_id,
+        "state": meeting.processing_status,
+        "updated_at": null,
+    })))
+}
 
-    let api_key = std::env::var("OPENAI_KEY")
-        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "OPENAI_KEY not set".to_string()))?;
+/// GET /api/meetings/:room_id/transcription-status/stream
+/// SSE endpoint emitting a status event on every job state change. 404 when
+/// the meeting has no job since the server started.
+pub async fn stream_transcription_status(
+    Path(room_id): Path<String>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, (StatusCode, String)> {
+    let (current, rx) = TRANSCRIPTION_JOBS
+        .watch(&room_id)
+        .await
+        .ok_or_else(|| (StatusCode::NOT_FOUND, "No transcription job for this meeting".to_string()))?;
 
-    let audio_dir = dirs::home_dir()
+    let stream = async_stream::stream! {
+        if let Ok(json) = serde_json::to_string(&current) {
+            yield Ok(Event::default().event("status").data(json));
+        }
+        let Some(mut rx) = rx else {
+            return;
+        };
+
+        loop {
+            match rx.recv().await {
+                Ok(status) => {
+                    let finished = status.state.is_finished();
+                    if let Ok(json) = serde_json::to_string(&status) {
+                        yield Ok(Event::default().event("status").data(json));
+                    }
+                    if finished {
+                        break;
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => break,
+            }
+        }
+    };
+
+    Ok(Sse::new(stream).keep_alive(
+        KeepAlive::new()
+            .interval(Duration::from_secs(15))
+            .text("ping"),
+    ))
+}
+
+fn meeting_audio_dir(room_id: &str) -> std::path::PathBuf {
+    dirs::home_dir()
         .unwrap_or_default()
         .join(".agentic-flowstate")
         .join("meeting-audio")
-        .join(&room_id);
+        .join(room_id)
+}
 
-    if !audio_dir.exists() {
-        return Err((StatusCode::NOT_FOUND, "No audio segments found".to_string()));
-    }
+/// Transcribe all uploaded segments, store the merged transcript and extract notes
+async fn run_transcription_job(db: &SqlitePool, room_id: &str, organization: &str, api_key: &str) -> Result<(), String> {
+    ticketing_system::meetings::update_processing_status(db, room_id, "transcribing")
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let audio_dir = meeting_audio_dir(room_id);
 
     // Collect all audio segments with metadata
     let mut segments: Vec<(String, i64, std::path::PathBuf)> = Vec::new();
 
-    for entry in std::fs::read_dir(&audio_dir)
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
-    {
-        let entry = entry.map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    for entry in std::fs::read_dir(&audio_dir).map_err(|e| e.to_string())? {
+        let entry = entry.map_err(|e| e.to_string())?;
         let path = entry.path();
 
         if path.extension().map(|e| e == "json").unwrap_or(false) {
             let meta: serde_json::Value = serde_json::from_str(
-                &std::fs::read_to_string(&path)
-                    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
-            ).map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+                &std::fs::read_to_string(&path).map_err(|e| e.to_string())?
+            ).map_err(|e| e.to_string())?;
 
             let username = meta["username"].as_str().unwrap_or("Unknown").to_string();
             let start_time = meta["start_time"].as_i64().unwrap_or(0);
@@ -246,7 +510,7 @@ pub async fn finalize_meeting_transcript(
     }
 
     if segments.is_empty() {
-        return Err((StatusCode::NOT_FOUND, "No audio segments found".to_string()));
+        return Err("No audio segments found".to_string());
     }
 
     tracing::info!("Processing {} audio segments for meeting {}", segments.len(), room_id);
@@ -254,10 +518,15 @@ pub async fn finalize_meeting_transcript(
     // Transcribe each segment with timestamps
     let client = reqwest::Client::new();
     let mut all_entries: Vec<(i64, String, String)> = Vec::new();
+    let total = segments.len();
+
+    for (index, (username, start_time_ms, audio_path)) in segments.into_iter().enumerate() {
+        TRANSCRIPTION_JOBS
+            .set_state(room_id, TranscriptionJobState::Transcribing { segment: index + 1, total })
+            .await;
 
-    for (username, start_time_ms, audio_path) in segments {
         let audio_bytes = std::fs::read(&audio_path)
-            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to read audio: {}", e)))?;
+            .map_err(|e| format!("Failed to read audio: {}", e))?;
 
         let ext = audio_path.extension().and_then(|e| e.to_str()).unwrap_or("webm");
         let mime_type = match ext {
@@ -270,7 +539,7 @@ pub async fn finalize_meeting_transcript(
         let part = reqwest::multipart::Part::bytes(audio_bytes)
             .file_name(format!("audio.{}", ext))
             .mime_str(mime_type)
-            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+            .map_err(|e| e.to_string())?;
 
         let form = reqwest::multipart::Form::new()
             .part("file", part)
@@ -285,7 +554,7 @@ pub async fn finalize_meeting_transcript(
             .multipart(form)
             .send()
             .await
-            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("API request failed: {}", e)))?;
+            .map_err(|e| format!("API request failed: {}", e))?;
 
         if !response.status().is_success() {
             let error_text = response.text().await.unwrap_or_default();
@@ -296,7 +565,8 @@ pub async fn finalize_meeting_transcript(
         let whisper_response: WhisperVerboseResponse = response
             .json()
             .await
-            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to parse response: {}", e)))?;
+            .map_err(|e| format!("Failed to parse response: {}", e))?;
+        crate::quotas::record_transcription(db, organization, room_id, whisper_response.duration).await;
 
         for seg in whisper_response.segments {
             let absolute_start = start_time_ms + (seg.start * 1000.0) as i64;
@@ -327,103 +597,311 @@ pub async fn finalize_meeting_transcript(
     let final_transcript = transcript_lines.join("").trim().to_string();
 
     // Store the transcript
-    let session_id = format!("mtg-{}", room_id);
+    let session_id = meeting_session_id(room_id);
 
-    if ticketing_system::transcripts::get_session(&db, &session_id)
+    if ticketing_system::transcripts::get_session(db, &session_id)
         .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .map_err(|e| e.to_string())?
         .is_none()
     {
         let create_req = CreateTranscriptSessionRequest {
             session_id: session_id.clone(),
-            guild_id: room_id.clone(),
+            guild_id: room_id.to_string(),
             channel_name: Some("Meeting".to_string()),
         };
-        ticketing_system::transcripts::create_session(&db, create_req)
+        ticketing_system::transcripts::create_session(db, create_req)
             .await
-            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+            .map_err(|e| e.to_string())?;
     }
 
     let entry_req = CreateTranscriptEntryRequest {
         session_id: session_id.clone(),
         user_id: "meeting".to_string(),
-        username: "Meeting Transcript".to_string(),
+        username: MERGED_TRANSCRIPT_SPEAKER.to_string(),
         text: final_transcript.clone(),
         timestamp: chrono::Utc::now().to_rfc3339(),
     };
-    ticketing_system::transcripts::add_entry(&db, entry_req)
+    ticketing_system::transcripts::add_entry(db, entry_req)
         .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        .map_err(|e| e.to_string())?;
 
-    ticketing_system::transcripts::end_session(&db, &session_id)
+    ticketing_system::transcripts::end_session(db, &session_id)
         .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        .map_err(|e| e.to_string())?;
 
-    ticketing_system::meetings::end_meeting(&db, &room_id, Some(&session_id))
+    ticketing_system::meetings::end_meeting(db, room_id, Some(&session_id))
         .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        .map_err(|e| e.to_string())?;
 
     // Cleanup audio files
     let _ = std::fs::remove_dir_all(&audio_dir);
 
     tracing::info!("Finalized transcript for meeting {}", room_id);
 
-    // Extract meeting notes using Claude
-    ticketing_system::meetings::update_processing_status(&db, &room_id, "extracting_notes")
+    let (notes, title) = store_meeting_notes(db, room_id, organization, &final_transcript).await?;
+
+    crate::meeting_followups::auto_push(db, organization, &notes, title.as_deref()).await;
+    crate::meeting_followups::run_follow_up_pipeline(db, room_id, &notes, title.as_deref()).await;
+
+    Ok(())
+}
+
+/// Extract notes from a transcript and save them, with a generated title, on
+/// the meeting
+async fn store_meeting_notes(
+    db: &SqlitePool,
+    room_id: &str,
+    organization: &str,
+    transcript: &str,
+) -> Result<(String, Option<String>), String> {
+    TRANSCRIPTION_JOBS
+        .set_state(room_id, TranscriptionJobState::ExtractingNotes)
+        .await;
+    ticketing_system::meetings::update_processing_status(db, room_id, "extracting_notes")
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let co_notes = match super::meeting_co_notes::load_co_notes(db, room_id).await {
+        Ok(notes) => notes.text(),
+        Err((_, e)) => {
+            tracing::warn!("Extracting notes for {} without its co-notes: {}", room_id, e);
+            String::new()
+        }
+    };
+    let co_notes = Some(co_notes).filter(|n| !n.is_empty());
+    let notes = extract_meeting_notes(db, room_id, organization, transcript, co_notes.as_deref()).await?;
+
+    let title = generate_meeting_title(&notes);
+    if let Some(t) = &title {
+        ticketing_system::meetings::update_meeting_title(db, room_id, t)
+            .await
+            .ok();
+    }
+
+    ticketing_system::meetings::update_meeting_notes(db, room_id, &notes, "completed")
+        .await
+        .map_err(|e| e.to_string())?;
+    tracing::info!("Extracted meeting notes for {}", room_id);
+
+    Ok((notes, title))
+}
+
+/// POST /api/meetings/:room_id/extract-notes
+///
+/// Re-runs notes extraction over the meeting's stored transcript, including
+/// any corrections made through the transcript entry API. Follow-ups are not
+/// pushed to the daily plan again, nor is the follow-up pipeline re-run.
+/// Progress is reported like finalization.
+pub async fn reextract_meeting_notes(
+    Path(room_id): Path<String>,
+    State(db): State<Arc<SqlitePool>>,
+    Extension(org): Extension<Organization>,
+) -> Result<(StatusCode, Json<TranscriptionJobStatus>), (StatusCode, String)> {
+    let entries = ticketing_system::transcripts::get_entries(&db, &meeting_session_id(&room_id))
         .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let transcript = transcript_text(entries.iter().map(|e| (e.username.as_str(), e.text.as_str())));
+    if transcript.is_empty() {
+        return Err((StatusCode::NOT_FOUND, "No transcript found for this meeting".to_string()));
+    }
+
+    crate::quotas::check_agent_run(&db, &org.name).await?;
+    let status = TRANSCRIPTION_JOBS.try_start(&room_id).await.ok_or_else(already_in_progress)?;
 
-    match extract_meeting_notes(&final_transcript).await {
-        Ok(notes) => {
-            let title = generate_meeting_title(&notes);
-            if let Some(t) = &title {
-                ticketing_system::meetings::update_meeting_title(&db, &room_id, t)
+    let job_room_id = room_id.clone();
+    let organization = org.name;
+    tokio::spawn(async move {
+        let _permit = match TRANSCRIPTION_JOBS.workers.acquire().await {
+            Ok(permit) => permit,
+            Err(_) => return,
+        };
+
+        match store_meeting_notes(&db, &job_room_id, &organization, &transcript).await {
+            Ok(_) => {
+                TRANSCRIPTION_JOBS
+                    .set_state(&job_room_id, TranscriptionJobState::Completed)
+                    .await;
+            }
+            Err(e) => {
+                tracing::error!("Notes re-extraction failed for meeting {}: {}", job_room_id, e);
+                ticketing_system::meetings::update_processing_status(&db, &job_room_id, "failed")
                     .await
                     .ok();
+                TRANSCRIPTION_JOBS
+                    .set_state(&job_room_id, TranscriptionJobState::Failed { error: e })
+                    .await;
             }
+        }
+    });
+
+    Ok((StatusCode::ACCEPTED, Json(status)))
+}
+
+/// Transcript session holding a meeting's finalized transcript
+fn meeting_session_id(room_id: &str) -> String {
+    format!("mtg-{}", room_id)
+}
 
-            ticketing_system::meetings::update_meeting_notes(&db, &room_id, &notes, "completed")
-                .await
-                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
-            tracing::info!("Extracted meeting notes for {}", room_id);
+/// Speaker of the single merged entry written by finalization; its text
+/// already carries `[Speaker]:` labels
+pub(crate) const MERGED_TRANSCRIPT_SPEAKER: &str = "Meeting Transcript";
+
+/// Rebuild transcript text from stored `(speaker, text)` entries in the same
+/// `[Speaker]: text` shape finalization produces
+fn transcript_text<'a>(entries: impl IntoIterator<Item = (&'a str, &'a str)>) -> String {
+    let mut lines: Vec<String> = Vec::new();
+    let mut current_speaker: Option<&str> = None;
+
+    for (speaker, text) in entries {
+        let text = text.trim();
+        if text.is_empty() {
+            continue;
         }
-        Err(e) => {
-            tracing::error!("Failed to extract meeting notes: {}", e);
-            ticketing_system::meetings::update_processing_status(&db, &room_id, "failed")
-                .await
-                .ok();
+        if speaker == MERGED_TRANSCRIPT_SPEAKER {
+            lines.push(text.to_string());
+            current_speaker = None;
+        } else if current_speaker == Some(speaker) {
+            if let Some(last) = lines.last_mut() {
+                last.push(' ');
+                last.push_str(text);
+            }
+        } else {
+            lines.push(format!("[{}]: {}", speaker, text));
+            current_speaker = Some(speaker);
         }
-    };
+    }
 
-    Ok(Json(TranscriptionResponse {
-        text: final_transcript,
-        duration_seconds: None,
-    }))
+    lines.join("\n")
 }
 
-/// Extract structured meeting notes from a transcript using Claude
-async fn extract_meeting_notes(transcript: &str) -> Result<String, String> {
-    tracing::info!("Starting meeting notes extraction, transcript length: {} chars", transcript.len());
+// ============================================================================
+// Notes Extraction
+// ============================================================================
+
+/// How long transcripts are split for notes extraction, read from
+/// `~/.agentic-flowstate/meeting-notes.json`
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+struct NotesChunking {
+    /// Longest transcript, in characters, extracted in one prompt. Longer ones
+    /// are split into parts of at most this size whose notes are then merged.
+    chunk_chars: usize,
+    /// Lines from the end of a part repeated at the start of the next, so a
+    /// topic spanning the split keeps its context
+    overlap_lines: usize,
+    /// Parts extracted at the same time
+    max_parallel: usize,
+}
+
+impl Default for NotesChunking {
+    fn default() -> Self {
+        Self { chunk_chars: 60_000, overlap_lines: 4, max_parallel: 3 }
+    }
+}
+
+/// Smallest `chunk_chars` accepted, so a bad config can't fan out into
+/// hundreds of prompts
+const MIN_NOTES_CHUNK_CHARS: usize = 4_000;
+
+/// Load the chunking config, falling back to the defaults when it is missing or invalid
+fn notes_chunking() -> NotesChunking {
+    let Some(path) = dirs::home_dir().map(|home| home.join(".agentic-flowstate").join("meeting-notes.json")) else {
+        return NotesChunking::default();
+    };
+    if !path.exists() {
+        return NotesChunking::default();
+    }
+    let config = std::fs::read_to_string(&path)
+        .map_err(|e| e.to_string())
+        .and_then(|content| serde_json::from_str::<NotesChunking>(&content).map_err(|e| e.to_string()));
+    match config {
+        Ok(config) => NotesChunking {
+            chunk_chars: config.chunk_chars.max(MIN_NOTES_CHUNK_CHARS),
+            max_parallel: config.max_parallel.max(1),
+            ..config
+        },
+        Err(e) => {
+            tracing::warn!("Ignoring invalid meeting notes config {:?}: {}", path, e);
+            NotesChunking::default()
+        }
+    }
+}
+
+/// Split a line longer than `max_chars` into pieces, breaking at whitespace where possible
+fn split_long_line(line: &str, max_chars: usize) -> Vec<String> {
+    let mut pieces = Vec::new();
+    let mut rest = line;
+    while rest.chars().count() > max_chars {
+        let limit = rest.char_indices().nth(max_chars).map(|(i, _)| i).unwrap_or(rest.len());
+        let cut = rest[..limit].rfind(char::is_whitespace).filter(|&i| i > 0).unwrap_or(limit);
+        pieces.push(rest[..cut].trim_end().to_string());
+        rest = rest[cut..].trim_start();
+    }
+    if !rest.is_empty() {
+        pieces.push(rest.to_string());
+    }
+    pieces
+}
 
-    let mut vars = HashMap::new();
-    vars.insert("transcript".to_string(), transcript.to_string());
+/// Split a transcript into parts of at most `chunk_chars` characters at line
+/// breaks, each starting with the last `overlap_lines` lines of the part before
+/// it. The overlap is dropped when it would take up more than half a part.
+fn chunk_transcript(transcript: &str, chunk_chars: usize, overlap_lines: usize) -> Vec<String> {
+    let chars = |lines: &[String]| lines.iter().map(|l| l.chars().count() + 1).sum::<usize>();
+    let lines: Vec<String> = transcript
+        .lines()
+        .filter(|l| !l.trim().is_empty())
+        .flat_map(|l| split_long_line(l, chunk_chars))
+        .collect();
+
+    let mut parts = Vec::new();
+    let mut current: Vec<String> = Vec::new();
+    // Lines at the start of `current` repeated from the previous part
+    let mut carried = 0;
+    for line in lines {
+        if current.len() > carried && chars(&current) + line.chars().count() > chunk_chars {
+            parts.push(current.join("\n"));
+            let overlap = current[current.len().saturating_sub(overlap_lines)..].to_vec();
+            let overlap_chars = chars(&overlap);
+            let keep = overlap_chars <= chunk_chars / 2 && overlap_chars + line.chars().count() <= chunk_chars;
+            current = if keep { overlap } else { Vec::new() };
+            carried = current.len();
+        }
+        current.push(line);
+    }
+    if current.len() > carried {
+        parts.push(current.join("\n"));
+    }
+    parts
+}
 
-    let system_prompt = load_prompt("meeting-notes", vars)
-        .map_err(|e| format!("Failed to load meeting-notes prompt: {}", e))?;
+/// Run the notes model once and return its text output
+async fn run_notes_prompt(
+    db: &SqlitePool,
+    organization: &str,
+    prompt_name: &str,
+    vars: HashMap<String, String>,
+) -> Result<String, String> {
+    let system_prompt = load_prompt(prompt_name, vars)
+        .map_err(|e| format!("Failed to load {} prompt: {}", prompt_name, e))?;
 
     let agent_config = AgentType::MeetingNotes;
     let working_dir = std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR"));
 
-    let options = ClaudeCodeOptions::builder()
+    let mut options = ClaudeCodeOptions::builder()
         .system_prompt(&system_prompt)
         .model(agent_config.model())
         .tools(ToolsConfig::none())
         .max_turns(1)
         .cwd(&working_dir)
         .build();
+    options.env.extend(llm_keys::anthropic_env(db, organization).await);
+    let env = options.env.clone();
 
     let prompt = "Extract structured notes from the transcript provided in the system prompt.";
     let mut output_parts = Vec::new();
+    let mut tool_log = ToolCallLog::new(&env);
+    let mut session_id = None;
 
     match query(prompt, Some(options)).await {
         Ok(stream) => {
@@ -432,6 +910,7 @@ async fn extract_meeting_notes(transcript: &str) -> Result<String, String> {
             while let Some(message_result) = stream.next().await {
                 match message_result {
                     Ok(message) => {
+                        tool_log.observe(&message);
                         if let CcMessage::Assistant { message: assistant_msg } = &message {
                             for block in &assistant_msg.content {
                                 if let ContentBlock::Text(text_content) = block {
@@ -439,7 +918,8 @@ async fn extract_meeting_notes(transcript: &str) -> Result<String, String> {
                                 }
                             }
                         }
-                        if let CcMessage::Result { .. } = &message {
+                        if let CcMessage::Result { session_id: sess_id, .. } = &message {
+                            session_id = Some(sess_id.clone());
                             break;
                         }
                     }
@@ -454,6 +934,7 @@ async fn extract_meeting_notes(transcript: &str) -> Result<String, String> {
             return Err(format!("Failed to run meeting-notes agent: {}", e));
         }
     }
+    store_standalone_tool_calls(db, session_id.as_deref(), Some(organization), &agent_config, &tool_log.into_calls()).await;
 
     if output_parts.is_empty() {
         return Err("No output from meeting-notes agent".to_string());
@@ -462,6 +943,107 @@ async fn extract_meeting_notes(transcript: &str) -> Result<String, String> {
     Ok(output_parts.join("\n\n"))
 }
 
+/// Group consecutive notes, by length, into merge batches of at least two
+/// whose combined length stays within `max_chars` where possible
+fn merge_batches(lengths: &[usize], max_chars: usize) -> Vec<std::ops::Range<usize>> {
+    let mut batches = Vec::new();
+    let mut start = 0;
+    let mut total = 0;
+    for (idx, &len) in lengths.iter().enumerate() {
+        if idx - start >= 2 && total + len > max_chars {
+            batches.push(start..idx);
+            start = idx;
+            total = 0;
+        }
+        total += len;
+    }
+    // A single leftover can't be merged on its own; fold it into the last batch
+    match batches.last_mut() {
+        Some(last) if lengths.len() - start < 2 => last.end = lengths.len(),
+        _ => batches.push(start..lengths.len()),
+    }
+    batches
+}
+
+/// Extract structured meeting notes from a transcript using Claude, billed to
+/// the organization's Anthropic key when it has one. Transcripts longer than
+/// the configured chunk size are split into parts, notes are extracted from
+/// each part, and the parts' notes are merged into one set. Participants'
+/// co-notes are merged in by the single prompt, or by the last merge.
+async fn extract_meeting_notes(
+    db: &SqlitePool,
+    room_id: &str,
+    organization: &str,
+    transcript: &str,
+    co_notes: Option<&str>,
+) -> Result<String, String> {
+    crate::quotas::check_agent_run(db, organization).await.map_err(|exceeded| exceeded.message())?;
+    tracing::info!("Starting meeting notes extraction, transcript length: {} chars", transcript.len());
+
+    let (transcript, mut redactions) = crate::redaction::redact(transcript);
+    let co_notes = co_notes.map(|n| {
+        let (n, co_note_redactions) = crate::redaction::redact(n);
+        redactions.merge(co_note_redactions);
+        n
+    });
+    if !redactions.is_empty() {
+        tracing::info!("Redacted from meeting transcript before notes extraction: {:?}", redactions.counts);
+    }
+    let subject = format!("meeting:{}", room_id);
+    crate::redaction::store_audit(db, Some(organization), &AgentType::MeetingNotes, &subject, &redactions).await;
+
+    let chunking = notes_chunking();
+    if transcript.chars().count() <= chunking.chunk_chars {
+        let mut vars = HashMap::from([("transcript".to_string(), transcript)]);
+        vars.extend(co_notes.map(|n| ("shared_notes".to_string(), n)));
+        return run_notes_prompt(db, organization, "meeting-notes", vars).await;
+    }
+
+    let parts = chunk_transcript(&transcript, chunking.chunk_chars, chunking.overlap_lines);
+    let total = parts.len();
+    tracing::info!("Extracting meeting notes in {} parts of up to {} chars", total, chunking.chunk_chars);
+    let mut notes: Vec<String> = futures::stream::iter(parts.into_iter().enumerate())
+        .map(|(idx, part)| {
+            let vars = HashMap::from([
+                ("transcript".to_string(), part),
+                ("part".to_string(), format!("{} of {}", idx + 1, total)),
+            ]);
+            async move {
+                run_notes_prompt(db, organization, "meeting-notes", vars)
+                    .await
+                    .map_err(|e| format!("Part {} of {}: {}", idx + 1, total, e))
+            }
+        })
+        .buffered(chunking.max_parallel)
+        .collect::<Vec<_>>()
+        .await
+        .into_iter()
+        .collect::<Result<_, _>>()?;
+
+    // Merge in rounds so the merge prompt stays within the chunk size too
+    while notes.len() > 1 {
+        let lengths: Vec<usize> = notes.iter().map(|n| n.chars().count()).collect();
+        let batches = merge_batches(&lengths, chunking.chunk_chars);
+        let last_round = batches.len() == 1;
+        let mut merged = Vec::new();
+        for batch in batches {
+            let part_notes = notes[batch.clone()]
+                .iter()
+                .zip(batch.clone())
+                .map(|(n, idx)| format!("## Part {}\n{}", idx + 1, n))
+                .collect::<Vec<_>>()
+                .join("\n\n");
+            let mut vars = HashMap::from([("part_notes".to_string(), part_notes)]);
+            if last_round {
+                vars.extend(co_notes.clone().map(|n| ("shared_notes".to_string(), n)));
+            }
+            merged.push(run_notes_prompt(db, organization, "meeting-notes-merge", vars).await?);
+        }
+        notes = merged;
+    }
+    notes.pop().ok_or_else(|| "No output from meeting-notes agent".to_string())
+}
+
 /// Generate a meeting title from the extracted notes
 fn generate_meeting_title(notes: &str) -> Option<String> {
     if let Some(start) = notes.find("**Issue 1:") {
@@ -482,3 +1064,41 @@ fn generate_meeting_title(notes: &str) -> Option<String> {
 
     None
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rebuilds_transcript_from_corrected_entries() {
+        let entries = [
+            ("Alice", "Let's start. "),
+            ("Alice", "First item is pricing."),
+            ("Bob", "Quote came in high."),
+            ("Bob", "  "),
+            ("Alice", "Okay."),
+        ];
+        assert_eq!(
+            transcript_text(entries),
+            "[Alice]: Let's start. First item is pricing.\n[Bob]: Quote came in high.\n[Alice]: Okay."
+        );
+
+        let merged = [(MERGED_TRANSCRIPT_SPEAKER, "[Alice]: Hi\n[Bob]: Hello")];
+        assert_eq!(transcript_text(merged), "[Alice]: Hi\n[Bob]: Hello");
+    }
+
+    #[test]
+    fn chunks_long_transcripts_with_overlap() {
+        let transcript = "[A]: one\n[B]: two\n[A]: three\n[B]: four";
+        assert_eq!(chunk_transcript(transcript, 100, 1), vec![transcript.to_string()]);
+        assert_eq!(
+            chunk_transcript(transcript, 24, 1),
+            vec!["[A]: one\n[B]: two", "[B]: two\n[A]: three", "[A]: three\n[B]: four"]
+        );
+        assert_eq!(chunk_transcript("aaaa bbbb cccc", 9, 0), vec!["aaaa", "bbbb cccc"]);
+
+        assert_eq!(merge_batches(&[10, 10, 10, 10], 25), vec![0..2, 2..4]);
+        assert_eq!(merge_batches(&[10, 10, 10], 25), vec![0..3]);
+        assert_eq!(merge_batches(&[30, 30], 25), vec![0..2]);
+    }
+}
@@ -1,7 +1,7 @@
 use axum::{
     extract::{
         ws::{Message as WsMessage, WebSocket, WebSocketUpgrade},
-        Path, Query, State,
+        Extension, Path, Query, State,
     },
     http::StatusCode,
     response::IntoResponse,
@@ -14,7 +14,18 @@ use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::{broadcast, RwLock};
 
-use ticketing_system::{CreateMeetingRequest, Meeting};
+use ticketing_system::{CreateMeetingRequest, DailyPlanDateItem, Meeting, MeetingSearchQuery};
+
+use crate::meeting_followups::{parse_follow_ups, push_to_daily_plan, FollowUp};
+use crate::tenancy::Organization;
+
+use super::pipeline_templates::validate_template_steps;
+
+use super::conversations::{find_match_offsets, snippet_around};
+
+const MAX_TAGS_PER_MEETING: usize = 20;
+const MAX_TAG_LEN: usize = 40;
+const DEFAULT_SEARCH_LIMIT: i64 = 50;
 
 // ============================================================================
 // State for WebSocket signaling
@@ -158,8 +169,10 @@ pub async fn list_meetings(
 /// POST /api/meetings
 pub async fn create_meeting(
     State(db): State<Arc<SqlitePool>>,
-    Json(req): Json<CreateMeetingRequest>,
+    Extension(org): Extension<Organization>,
+    Json(mut req): Json<CreateMeetingRequest>,
 ) -> Result<Json<Meeting>, (StatusCode, String)> {
+    req.organization = Some(org.name);
     let meeting = ticketing_system::meetings::create_meeting(&db, req)
         .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
@@ -219,6 +232,29 @@ pub async fn delete_meeting(
 #[derive(Debug, Deserialize)]
 pub struct UpdateMeetingRequest {
     pub title: Option<String>,
+    /// Replaces the meeting's tags
+    pub tags: Option<Vec<String>>,
+}
+
+/// Lowercase, trim and dedupe tags, keeping their order
+fn normalize_tags(tags: &[String]) -> Result<Vec<String>, String> {
+    let mut normalized: Vec<String> = Vec::new();
+    for tag in tags {
+        let tag = tag.trim().to_lowercase();
+        if tag.is_empty() {
+            continue;
+        }
+        if tag.len() > MAX_TAG_LEN {
+            return Err(format!("Tags are limited to {} characters", MAX_TAG_LEN));
+        }
+        if !normalized.contains(&tag) {
+            normalized.push(tag);
+        }
+    }
+    if normalized.len() > MAX_TAGS_PER_MEETING {
+        return Err(format!("A meeting can have at most {} tags", MAX_TAGS_PER_MEETING));
+    }
+    Ok(normalized)
 }
 
 /// PATCH /api/meetings/:room_id
@@ -227,11 +263,23 @@ pub async fn update_meeting(
     State(db): State<Arc<SqlitePool>>,
     Json(req): Json<UpdateMeetingRequest>,
 ) -> Result<Json<Meeting>, (StatusCode, String)> {
+    let tags = req
+        .tags
+        .as_deref()
+        .map(normalize_tags)
+        .transpose()
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+
     if let Some(title) = &req.title {
         ticketing_system::meetings::update_meeting_title(&db, &room_id, title)
             .await
             .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
     }
+    if let Some(tags) = &tags {
+        ticketing_system::meetings::set_meeting_tags(&db, &room_id, tags)
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    }
 
     let meeting = ticketing_system::meetings::get_meeting(&db, &room_id)
         .await
@@ -258,6 +306,90 @@ pub async fn toggle_meeting_favorite(
     Ok(Json(ToggleFavoriteResponse { is_favorited }))
 }
 
+#[derive(Debug, Deserialize)]
+pub struct SearchMeetingsQuery {
+    /// Full-text query over titles, notes and transcripts
+    pub q: Option<String>,
+    pub tag: Option<String>,
+    pub favorites: Option<bool>,
+    /// Inclusive date range (YYYY-MM-DD) on when the meeting was created
+    pub from: Option<String>,
+    pub to: Option<String>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MeetingSearchResult {
+    pub meeting: Meeting,
+    /// Where the query matched ("title", "notes" or "transcript"); absent without `q`
+    pub field: Option<String>,
+    pub snippet: Option<String>,
+    /// Character ranges `[start, end)` of each match within the full field text
+    pub offsets: Vec<(usize, usize)>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MeetingSearchResponse {
+    pub results: Vec<MeetingSearchResult>,
+}
+
+fn parse_date(value: Option<String>, name: &str) -> Result<Option<String>, (StatusCode, String)> {
+    value
+        .map(|d| {
+            chrono::NaiveDate::parse_from_str(&d, "%Y-%m-%d")
+                .map(|date| date.to_string())
+                .map_err(|_| (StatusCode::BAD_REQUEST, format!("{} must be YYYY-MM-DD", name)))
+        })
+        .transpose()
+}
+
+/// GET /api/meetings/search?q=&tag=&favorites=&from=&to=
+///
+/// Every parameter is optional; without `q` this filters the archive, newest first.
+pub async fn search_meetings(
+    State(db): State<Arc<SqlitePool>>,
+    Query(params): Query<SearchMeetingsQuery>,
+) -> Result<Json<MeetingSearchResponse>, (StatusCode, String)> {
+    let from = parse_date(params.from, "from")?;
+    let to = parse_date(params.to, "to")?;
+    if let (Some(from), Some(to)) = (&from, &to) {
+        if from > to {
+            return Err((StatusCode::BAD_REQUEST, "from must not be after to".to_string()));
+        }
+    }
+    let q = params.q.map(|q| q.trim().to_string()).filter(|q| !q.is_empty());
+
+    let hits = ticketing_system::meetings::search_meetings(
+        &db,
+        &MeetingSearchQuery {
+            q: q.clone(),
+            tag: params.tag.map(|t| t.trim().to_lowercase()).filter(|t| !t.is_empty()),
+            favorites_only: params.favorites.unwrap_or(false),
+            from,
+            to,
+            limit: params.limit.unwrap_or(DEFAULT_SEARCH_LIMIT),
+        },
+    )
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let results = hits
+        .into_iter()
+        .map(|hit| {
+            let (snippet, offsets) = match (&q, &hit.text) {
+                (Some(q), Some(text)) => {
+                    let offsets = find_match_offsets(text, q);
+                    (Some(snippet_around(text, &offsets)), offsets)
+                }
+                _ => (None, Vec::new()),
+            };
+            MeetingSearchResult { meeting: hit.meeting, field: hit.field, snippet, offsets }
+        })
+        .collect();
+
+    Ok(Json(MeetingSearchResponse { results }))
+}
+
 // ============================================================================
 // WebSocket Signaling Handler
 // ============================================================================
@@ -397,3 +529,123 @@ async fn handle_signaling(socket: WebSocket) {
 
     send_task.abort();
 }
+
+#[derive(Debug, Default, Deserialize)]
+pub struct PushToDailyPlanRequest {
+    /// Date (YYYY-MM-DD) for follow-ups without a today/tomorrow tag; they are skipped when omitted
+    pub undated_date: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PushToDailyPlanResponse {
+    pub follow_ups: Vec<FollowUp>,
+    pub created: Vec<DailyPlanDateItem>,
+}
+
+/// POST /api/meetings/:room_id/push-to-daily-plan
+pub async fn push_meeting_to_daily_plan(
+    Path(room_id): Path<String>,
+    State(db): State<Arc<SqlitePool>>,
+    body: Option<Json<PushToDailyPlanRequest>>,
+) -> Result<Json<PushToDailyPlanResponse>, (StatusCode, String)> {
+    let req = body.map(|Json(req)| req).unwrap_or_default();
+    let undated_date = req
+        .undated_date
+        .map(|d| {
+            chrono::NaiveDate::parse_from_str(&d, "%Y-%m-%d")
+                .map_err(|_| (StatusCode::BAD_REQUEST, "undated_date must be YYYY-MM-DD".to_string()))
+        })
+        .transpose()?;
+
+    let meeting = ticketing_system::meetings::get_meeting(&db, &room_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or_else(|| (StatusCode::NOT_FOUND, "Meeting not found".to_string()))?;
+    let notes = meeting
+        .notes
+        .filter(|n| !n.trim().is_empty())
+        .ok_or_else(|| (StatusCode::CONFLICT, "Meeting notes have not been extracted yet".to_string()))?;
+
+    let follow_ups = parse_follow_ups(&notes);
+    let created = push_to_daily_plan(
+        &db,
+        &follow_ups,
+        meeting.title.as_deref(),
+        chrono::Utc::now().date_naive(),
+        undated_date,
+    )
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(Json(PushToDailyPlanResponse { follow_ups, created }))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SetFollowUpPipelineRequest {
+    /// Ticket the pipeline is attached to and run on
+    pub ticket_id: String,
+    pub template_id: String,
+}
+
+/// PUT /api/meetings/:room_id/follow-up-pipeline
+/// Once the meeting's notes are extracted, attach `template_id` to `ticket_id`
+/// and run it, with `meeting_id`, `meeting_title` and `meeting_notes` as
+/// inputs of its first step
+pub async fn set_meeting_follow_up_pipeline(
+    Path(room_id): Path<String>,
+    State(db): State<Arc<SqlitePool>>,
+    Extension(org): Extension<Organization>,
+    Json(req): Json<SetFollowUpPipelineRequest>,
+) -> Result<Json<Meeting>, (StatusCode, String)> {
+    let ticket = ticketing_system::tickets::get_ticket_by_id(&db, &req.ticket_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or_else(|| (StatusCode::NOT_FOUND, "Ticket not found".to_string()))?;
+    org.authorize(&ticket.organization)?;
+
+    let template = ticketing_system::pipelines::get_template(&db, &req.template_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or_else(|| (StatusCode::NOT_FOUND, "Template not found".to_string()))?;
+    let problems = validate_template_steps(&template.steps);
+    if !problems.is_empty() {
+        return Err((StatusCode::UNPROCESSABLE_ENTITY, format!("Invalid pipeline: {}", problems.join("; "))));
+    }
+
+    ticketing_system::meetings::set_follow_up_pipeline(&db, &room_id, Some(&req.ticket_id), Some(&req.template_id))
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let meeting = ticketing_system::meetings::get_meeting(&db, &room_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or_else(|| (StatusCode::NOT_FOUND, "Meeting not found".to_string()))?;
+
+    Ok(Json(meeting))
+}
+
+/// DELETE /api/meetings/:room_id/follow-up-pipeline
+pub async fn clear_meeting_follow_up_pipeline(
+    Path(room_id): Path<String>,
+    State(db): State<Arc<SqlitePool>>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    ticketing_system::meetings::set_follow_up_pipeline(&db, &room_id, None, None)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalizes_tags() {
+        let tags = vec![" Client ".to_string(), "client".to_string(), "".to_string(), "Q3 Review".to_string()];
+        assert_eq!(normalize_tags(&tags).unwrap(), vec!["client", "q3 review"]);
+
+        let too_many: Vec<String> = (0..=MAX_TAGS_PER_MEETING).map(|i| format!("t{}", i)).collect();
+        assert!(normalize_tags(&too_many).is_err());
+        assert!(normalize_tags(&["x".repeat(MAX_TAG_LEN + 1)]).is_err());
+    }
+}
@@ -0,0 +1,287 @@
+//! Reactions and bookmarks on conversation messages
+//!
+//! Both belong to the user who made them. Bookmarks can be listed across
+//! conversations and, when asked for, are given to the workspace manager at
+//! the start of a new session (see `bookmarks_context`).
+
+use axum::{
+    extract::{Extension, Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use ticketing_system::{
+    conversations, message_bookmarks, message_reactions, ConversationMessage, CreateMessageBookmarkRequest,
+    MessageBookmark, SqlitePool, User,
+};
+
+use crate::tenancy::Organization;
+
+/// Longest reaction accepted, in characters (an emoji or a short shortcode)
+const MAX_REACTION_CHARS: usize = 32;
+
+const MAX_BOOKMARK_NOTE_CHARS: usize = 500;
+
+/// Characters of a bookmarked message shown in listings
+const BOOKMARK_SNIPPET_CHARS: usize = 280;
+
+/// Approximate token budget for bookmarks given to a new workspace manager session
+const BOOKMARK_CONTEXT_TOKEN_BUDGET: usize = 2000;
+
+/// Characters of each bookmarked message given to the workspace manager
+const BOOKMARK_CONTEXT_MESSAGE_CHARS: usize = 1500;
+
+#[derive(Debug, Deserialize)]
+pub struct ReactionRequest {
+    pub emoji: String,
+}
+
+/// One reaction on a message with how many users left it
+#[derive(Debug, PartialEq, Serialize)]
+pub struct ReactionSummary {
+    pub emoji: String,
+    pub count: usize,
+    /// Whether the requesting user is one of them
+    pub reacted: bool,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct BookmarkRequest {
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ListBookmarksQuery {
+    pub organization: Option<String>,
+    pub conversation_id: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BookmarkView {
+    #[serde(flatten)]
+    pub bookmark: MessageBookmark,
+    pub snippet: String,
+}
+
+fn is_valid_reaction(emoji: &str) -> bool {
+    let len = emoji.chars().count();
+    len > 0 && len <= MAX_REACTION_CHARS && !emoji.chars().any(char::is_whitespace)
+}
+
+/// First `max` characters of `text`, marked when cut
+fn snippet(text: &str, max: usize) -> String {
+    let text = text.trim();
+    if text.chars().count() <= max {
+        return text.to_string();
+    }
+    let mut cut: String = text.chars().take(max).collect();
+    cut.push('…');
+    cut
+}
+
+/// Group `(emoji, user_id)` reactions by emoji, in the order each was first used
+fn summarize_reactions<'a>(reactions: impl IntoIterator<Item = (&'a str, &'a str)>, user_id: &str) -> Vec<ReactionSummary> {
+    let mut summaries: Vec<ReactionSummary> = Vec::new();
+    for (emoji, reactor) in reactions {
+        let idx = match summaries.iter().position(|s| s.emoji == emoji) {
+            Some(idx) => idx,
+            None => {
+                summaries.push(ReactionSummary { emoji: emoji.to_string(), count: 0, reacted: false });
+                summaries.len() - 1
+            }
+        };
+        summaries[idx].count += 1;
+        summaries[idx].reacted |= reactor == user_id;
+    }
+    summaries
+}
+
+/// The message, if it's in the conversation
+async fn find_message(pool: &SqlitePool, conv_id: &str, message_id: &str) -> Result<ConversationMessage, (StatusCode, String)> {
+    conversations::list_messages(pool, conv_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .into_iter()
+        .find(|m| m.id == message_id)
+        .ok_or((StatusCode::NOT_FOUND, "Message not found".to_string()))
+}
+
+async fn reaction_summaries(pool: &SqlitePool, message_id: &str, user: &User) -> Result<Json<Vec<ReactionSummary>>, (StatusCode, String)> {
+    let reactions = message_reactions::list_for_message(pool, message_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Ok(Json(summarize_reactions(
+        reactions.iter().map(|r| (r.emoji.as_str(), r.user_id.as_str())),
+        &user.user_id,
+    )))
+}
+
+/// List a message's reactions (GET /api/conversations/:conv_id/messages/:message_id/reactions)
+pub async fn list_message_reactions(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(user): Extension<User>,
+    Path((conv_id, message_id)): Path<(String, String)>,
+) -> Result<Json<Vec<ReactionSummary>>, (StatusCode, String)> {
+    find_message(&pool, &conv_id, &message_id).await?;
+    reaction_summaries(&pool, &message_id, &user).await
+}
+
+/// React to a message; reacting twice with the same emoji is a no-op
+/// (POST /api/conversations/:conv_id/messages/:message_id/reactions)
+pub async fn add_message_reaction(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(user): Extension<User>,
+    Path((conv_id, message_id)): Path<(String, String)>,
+    Json(req): Json<ReactionRequest>,
+) -> Result<Json<Vec<ReactionSummary>>, (StatusCode, String)> {
+    let emoji = req.emoji.trim();
+    if !is_valid_reaction(emoji) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("emoji must be 1 to {} characters without spaces", MAX_REACTION_CHARS),
+        ));
+    }
+    find_message(&pool, &conv_id, &message_id).await?;
+    message_reactions::add_reaction(&pool, &message_id, &user.user_id, emoji)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    reaction_summaries(&pool, &message_id, &user).await
+}
+
+/// Take back a reaction (DELETE /api/conversations/:conv_id/messages/:message_id/reactions/:emoji)
+pub async fn remove_message_reaction(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(user): Extension<User>,
+    Path((conv_id, message_id, emoji)): Path<(String, String, String)>,
+) -> Result<Json<Vec<ReactionSummary>>, (StatusCode, String)> {
+    find_message(&pool, &conv_id, &message_id).await?;
+    message_reactions::remove_reaction(&pool, &message_id, &user.user_id, &emoji)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    reaction_summaries(&pool, &message_id, &user).await
+}
+
+/// Bookmark a message, or update the note on an existing bookmark
+/// (PUT /api/conversations/:conv_id/messages/:message_id/bookmark)
+pub async fn bookmark_message(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(user): Extension<User>,
+    Path((conv_id, message_id)): Path<(String, String)>,
+    body: Option<Json<BookmarkRequest>>,
+) -> Result<Json<BookmarkView>, (StatusCode, String)> {
+    let note = body
+        .and_then(|Json(req)| req.note)
+        .map(|note| note.trim().to_string())
+        .filter(|note| !note.is_empty());
+    if note.as_ref().is_some_and(|note| note.chars().count() > MAX_BOOKMARK_NOTE_CHARS) {
+        return Err((StatusCode::BAD_REQUEST, format!("note must be at most {} characters", MAX_BOOKMARK_NOTE_CHARS)));
+    }
+    find_message(&pool, &conv_id, &message_id).await?;
+
+    let bookmark = message_bookmarks::upsert_bookmark(
+        &pool,
+        CreateMessageBookmarkRequest {
+            user_id: user.user_id.clone(),
+            conversation_id: conv_id,
+            message_id,
+            note,
+        },
+    )
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Ok(Json(BookmarkView { snippet: snippet(&bookmark.content, BOOKMARK_SNIPPET_CHARS), bookmark }))
+}
+
+/// Remove a bookmark (DELETE /api/conversations/:conv_id/messages/:message_id/bookmark)
+pub async fn unbookmark_message(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(user): Extension<User>,
+    Path((_conv_id, message_id)): Path<(String, String)>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let removed = message_bookmarks::delete_bookmark(&pool, &user.user_id, &message_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    if !removed {
+        return Err((StatusCode::NOT_FOUND, "Bookmark not found".to_string()));
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// The user's bookmarks across conversations, newest first (GET /api/bookmarks)
+pub async fn list_bookmarks(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(user): Extension<User>,
+    Extension(org): Extension<Organization>,
+    Query(params): Query<ListBookmarksQuery>,
+) -> Result<Json<Vec<BookmarkView>>, (StatusCode, String)> {
+    if let Some(organization) = &params.organization {
+        org.authorize(organization)?;
+    }
+    let bookmarks = message_bookmarks::list_for_user(
+        &pool,
+        &user.user_id,
+        params.organization.as_deref(),
+        params.conversation_id.as_deref(),
+    )
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(Json(
+        bookmarks
+            .into_iter()
+            .map(|bookmark| BookmarkView { snippet: snippet(&bookmark.content, BOOKMARK_SNIPPET_CHARS), bookmark })
+            .collect(),
+    ))
+}
+
+/// The user's bookmarked messages in an organization's conversations, newest
+/// first and within a token budget, to open a workspace manager session with
+pub(super) async fn bookmarks_context(db: &SqlitePool, user_id: &str, organization: &str) -> Option<String> {
+    let bookmarks = message_bookmarks::list_for_user(db, user_id, Some(organization), None)
+        .await
+        .unwrap_or_else(|e| {
+            tracing::warn!("Failed to load bookmarks for {}: {}", user_id, e);
+            Vec::new()
+        });
+
+    let mut remaining = BOOKMARK_CONTEXT_TOKEN_BUDGET * super::workspace_manager::CHARS_PER_TOKEN;
+    let mut parts = vec!["[Bookmarked Messages]".to_string()];
+    for bookmark in &bookmarks {
+        let title = bookmark.conversation_title.as_deref().unwrap_or("Untitled conversation");
+        let mut section = format!("\n## {} ({})", title, bookmark.role);
+        if let Some(note) = &bookmark.note {
+            section.push_str(&format!("\nNote: {}", note));
+        }
+        section.push_str(&format!("\n{}", snippet(&bookmark.content, BOOKMARK_CONTEXT_MESSAGE_CHARS)));
+        if section.len() > remaining {
+            break;
+        }
+        remaining -= section.len();
+        parts.push(section);
+    }
+    (parts.len() > 1).then(|| parts.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn summarizes_reactions_and_snippets() {
+        let reactions = [("👍", "u1"), ("🎉", "u2"), ("👍", "u2")];
+        assert_eq!(
+            summarize_reactions(reactions, "u1"),
+            vec![
+                ReactionSummary { emoji: "👍".to_string(), count: 2, reacted: true },
+                ReactionSummary { emoji: "🎉".to_string(), count: 1, reacted: false },
+            ]
+        );
+        assert!(is_valid_reaction("👍"));
+        assert!(!is_valid_reaction("thumbs up"));
+        assert!(!is_valid_reaction(""));
+
+        assert_eq!(snippet("  short  ", 10), "short");
+        assert_eq!(snippet("héllo world", 5), "héllo…");
+    }
+}
@@ -19,6 +19,44 @@ pub mod meeting_transcription;
 pub mod life_planner;
 pub mod daily_plan;
 pub mod project_workload;
+pub mod notifications;
+pub mod secrets;
+pub mod ticket_merge;
+pub mod checklists;
+pub mod presence;
+pub mod analytics;
+pub mod research_docs;
+pub mod semantic_search;
+pub mod admin;
+pub mod import;
+pub mod plans;
+pub mod org_settings;
+pub mod email_grants;
+pub mod email_templates;
+pub mod email_suppressions;
+pub mod conversation_attachments;
+pub mod org_invitations;
+pub mod ticket_relationships;
+pub mod context_packs;
+pub mod ticket_comments;
+pub mod guests;
+pub mod email_health;
+pub mod shadow_runs;
+pub mod tool_audit;
+pub mod epic_pipelines;
+pub mod email_approvals;
+pub mod message_bookmarks;
+pub mod email_replies;
+pub mod saved_views;
+pub mod similar_tickets;
+pub mod transcript_ingest;
+pub mod critical_path;
+pub mod pipeline_overview;
+pub mod step_library;
+pub mod meeting_co_notes;
+pub mod runners;
+pub mod email_tickets;
+pub mod template_sharing;
 
 pub use epics::*;
 pub use slices::*;
@@ -39,13 +77,41 @@ pub use meeting_transcription::*;
 pub use life_planner::*;
 pub use daily_plan::*;
 pub use project_workload::*;
-
-use axum::http::HeaderMap;
-
-/// Extract organization from X-Organization header, defaulting to "telemetryops"
-pub fn get_organization(headers: &HeaderMap) -> String {
-    headers.get("X-Organization")
-        .and_then(|v| v.to_str().ok())
-        .unwrap_or("telemetryops")
-        .to_string()
-}
+pub use notifications::*;
+pub use secrets::*;
+pub use ticket_merge::*;
+pub use checklists::*;
+pub use presence::*;
+pub use analytics::*;
+pub use research_docs::*;
+pub use semantic_search::*;
+pub use admin::*;
+pub use import::*;
+pub use plans::*;
+pub use org_settings::*;
+pub use email_grants::*;
+pub use email_templates::*;
+pub use email_suppressions::*;
+pub use conversation_attachments::*;
+pub use org_invitations::*;
+pub use ticket_relationships::*;
+pub use context_packs::*;
+pub use ticket_comments::*;
+pub use guests::*;
+pub use email_health::*;
+pub use shadow_runs::*;
+pub use tool_audit::*;
+pub use epic_pipelines::*;
+pub use email_approvals::*;
+pub use message_bookmarks::*;
+pub use email_replies::*;
+pub use saved_views::*;
+pub use similar_tickets::*;
+pub use transcript_ingest::*;
+pub use critical_path::*;
+pub use pipeline_overview::*;
+pub use step_library::*;
+pub use meeting_co_notes::*;
+pub use runners::*;
+pub use email_tickets::*;
+pub use template_sharing::*;
@@ -0,0 +1,80 @@
+use axum::{
+    extract::{Extension, Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use ticketing_system::{notifications, Notification, SqlitePool};
+
+use crate::tenancy::Organization;
+
+#[derive(Debug, Deserialize)]
+pub struct ListNotificationsQuery {
+    pub unread_only: Option<bool>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct NotificationListResponse {
+    pub notifications: Vec<Notification>,
+    pub unread: i64,
+}
+
+/// Notifications without an organization (e.g. about the instance's own
+/// mailboxes) are for instance admins
+fn sees_unowned(org: &Organization) -> bool {
+    org.require_instance_admin().is_ok()
+}
+
+/// List the organization's notifications (GET /api/notifications)
+pub async fn list_notifications(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(org): Extension<Organization>,
+    Query(params): Query<ListNotificationsQuery>,
+) -> Result<Json<NotificationListResponse>, (StatusCode, String)> {
+    let unowned = sees_unowned(&org);
+    let list = notifications::list_notifications(
+        &pool,
+        &org.name,
+        unowned,
+        params.unread_only.unwrap_or(false),
+        params.limit.unwrap_or(50),
+    )
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let unread = notifications::count_unread(&pool, &org.name, unowned)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(Json(NotificationListResponse {
+        notifications: list,
+        unread,
+    }))
+}
+
+/// Mark a notification as read (POST /api/notifications/:id/read)
+pub async fn mark_notification_read(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(org): Extension<Organization>,
+    Path(id): Path<i64>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let notification = notifications::get_notification(&pool, id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or_else(|| (StatusCode::NOT_FOUND, "Notification not found".to_string()))?;
+    let visible = match notification.organization.as_deref() {
+        Some(organization) => organization == org.name,
+        None => sees_unowned(&org),
+    };
+    if !visible {
+        return Err((StatusCode::NOT_FOUND, "Notification not found".to_string()));
+    }
+
+    notifications::mark_read(&pool, id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,153 @@
+//! Organization invitations: admins invite people by email with a role, and
+//! the invitee accepts at `POST /api/auth/invitations/:token/accept`, which
+//! creates their account (or links a signed-in one) and adds the membership.
+//! Token signing lives in `crate::invitations`.
+
+use axum::{
+    extract::{Extension, Path, State},
+    http::StatusCode,
+    Json,
+};
+use chrono::{Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use ticketing_system::{org_invitations, CreateOrgInvitationRequest, OrgInvitation, SqlitePool, User};
+
+use crate::email_suppressions::normalize_address;
+use crate::invitations::{self, INVITATION_VALID_DAYS, ROLES, ROLE_MEMBER};
+use crate::tenancy::Organization;
+
+#[derive(Debug, Deserialize)]
+pub struct CreateInvitationRequest {
+    pub email: String,
+    /// `admin` or `member` (default)
+    pub role: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CreateInvitationResponse {
+    pub invitation: OrgInvitation,
+    /// Link to share by hand when the email wasn't sent
+    pub accept_url: Option<String>,
+    pub token: String,
+    pub email_sent: bool,
+    pub email_error: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct InvitationListResponse {
+    pub invitations: Vec<OrgInvitation>,
+}
+
+/// POST /api/organizations/:org/invitations (admins only)
+pub async fn create_org_invitation(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(user): Extension<User>,
+    Extension(org): Extension<Organization>,
+    Path(organization): Path<String>,
+    Json(req): Json<CreateInvitationRequest>,
+) -> Result<(StatusCode, Json<CreateInvitationResponse>), (StatusCode, String)> {
+    org.require_admin(&organization)?;
+    let email = normalize_address(&req.email)
+        .ok_or_else(|| (StatusCode::BAD_REQUEST, format!("'{}' is not an email address", req.email)))?;
+    let role = req.role.unwrap_or_else(|| ROLE_MEMBER.to_string());
+    if !ROLES.contains(&role.as_str()) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("Unknown role '{}', expected one of {}", role, ROLES.join(", ")),
+        ));
+    }
+    let config = invitations::load_config().map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("{:#}", e)))?;
+
+    let invitation = org_invitations::create_invitation(
+        &pool,
+        &CreateOrgInvitationRequest {
+            organization,
+            email,
+            role,
+            invited_by: user.user_id.clone(),
+            expires_at: (Utc::now() + Duration::days(INVITATION_VALID_DAYS)).to_rfc3339(),
+        },
+    )
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let token = invitations::sign(&invitation).map_err(|e| {
+        (StatusCode::INTERNAL_SERVER_ERROR, format!("Invitations are not configured: {:#}", e))
+    })?;
+
+    let (email_sent, email_error) =
+        match invitations::send_invitation_email(&pool, &config, &invitation, &user.name, &token).await {
+            Ok(message_id) => (message_id.is_some(), None),
+            Err((_, e)) => {
+                tracing::warn!("Failed to email invitation {}: {}", invitation.invitation_id, e);
+                (false, Some(e))
+            }
+        };
+    tracing::info!(
+        "{} invited {} to {} as {}",
+        user.user_id, invitation.email, invitation.organization, invitation.role
+    );
+
+    Ok((
+        StatusCode::CREATED,
+        Json(CreateInvitationResponse {
+            accept_url: invitations::accept_link(&config, &token),
+            invitation,
+            token,
+            email_sent,
+            email_error,
+        }),
+    ))
+}
+
+/// GET /api/organizations/:org/invitations
+pub async fn list_org_invitations(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(org): Extension<Organization>,
+    Path(organization): Path<String>,
+) -> Result<Json<InvitationListResponse>, (StatusCode, String)> {
+    org.authorize(&organization)?;
+    let invitations = org_invitations::list_invitations(&pool, &organization)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Ok(Json(InvitationListResponse { invitations }))
+}
+
+/// DELETE /api/organizations/:org/invitations/:invitation_id (admins only)
+pub async fn revoke_org_invitation(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(org): Extension<Organization>,
+    Path((organization, invitation_id)): Path<(String, String)>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    org.require_admin(&organization)?;
+    let revoked = org_invitations::revoke_invitation(&pool, &organization, &invitation_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    if !revoked {
+        return Err((StatusCode::NOT_FOUND, "Invitation not found".to_string()));
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Load the invitation a token was signed for, if it can still be accepted
+pub(super) async fn load_invitation_for_token(
+    pool: &SqlitePool,
+    token: &str,
+) -> Result<OrgInvitation, (StatusCode, String)> {
+    let not_found = || (StatusCode::NOT_FOUND, "Invitation not found".to_string());
+    let invitation_id = invitations::token_invitation_id(token).ok_or_else(not_found)?;
+    let invitation = org_invitations::get_invitation(pool, invitation_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or_else(not_found)?;
+    let valid = invitations::verify(token, &invitation).map_err(|e| {
+        (StatusCode::INTERNAL_SERVER_ERROR, format!("Invitations are not configured: {:#}", e))
+    })?;
+    if !valid {
+        return Err(not_found());
+    }
+    if let Some(reason) = invitations::unusable_reason(&invitation) {
+        return Err((StatusCode::GONE, reason.to_string()));
+    }
+    Ok(invitation)
+}
@@ -0,0 +1,107 @@
+use axum::{extract::{Extension, Path, State}, http::StatusCode, Json};
+use serde::Deserialize;
+use std::sync::Arc;
+use ticketing_system::{org_settings, OrgSettings, SqlitePool};
+
+use crate::quotas::UsageReport;
+use crate::tenancy::Organization;
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateOrgSettingsRequest {
+    pub meeting_followups_to_daily_plan: Option<bool>,
+    /// Repo type artifacts are written to; empty resets to the documentation repo
+    pub artifact_repo_type: Option<String>,
+    /// Directory inside the repo; empty resets to the per-agent `docs/` directories
+    pub artifact_base_dir: Option<String>,
+    /// Filename without extension, using `{ticket_id}`, `{agent_type}` and `{date}`;
+    /// empty resets to `{ticket_id}-{agent_type}`
+    pub artifact_filename_template: Option<String>,
+    /// `md` or `json`
+    pub artifact_format: Option<String>,
+    /// Agent types whose output isn't written as an artifact
+    pub artifact_disabled_agent_types: Option<Vec<String>>,
+}
+
+/// An optional setting from an update: unchanged when absent, cleared when empty
+fn update_optional(update: Option<String>, existing: Option<String>) -> Option<String> {
+    match update {
+        Some(value) if value.trim().is_empty() => None,
+        Some(value) => Some(value.trim().to_string()),
+        None => existing,
+    }
+}
+
+/// Settings for an organization, or the defaults if none were saved
+pub async fn load_org_settings(pool: &SqlitePool, organization: &str) -> OrgSettings {
+    let saved = org_settings::get_org_settings(pool, organization)
+        .await
+        .unwrap_or_else(|e| {
+            tracing::warn!("Failed to load settings for organization {}: {:?}", organization, e);
+            None
+        });
+    saved.unwrap_or_else(|| OrgSettings {
+        organization: organization.to_string(),
+        meeting_followups_to_daily_plan: false,
+        artifact_repo_type: None,
+        artifact_base_dir: None,
+        artifact_filename_template: None,
+        artifact_format: super::agent_runs::ARTIFACT_FORMAT_MARKDOWN.to_string(),
+        artifact_disabled_agent_types: Vec::new(),
+        updated_at: String::new(),
+    })
+}
+
+/// GET /api/org-settings
+pub async fn get_org_settings(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(org): Extension<Organization>,
+) -> Json<OrgSettings> {
+    Json(load_org_settings(&pool, &org.name).await)
+}
+
+/// PUT /api/org-settings
+pub async fn update_org_settings(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(org): Extension<Organization>,
+    Json(request): Json<UpdateOrgSettingsRequest>,
+) -> Result<Json<OrgSettings>, (StatusCode, String)> {
+    let existing = load_org_settings(&pool, &org.name).await;
+    let settings = OrgSettings {
+        meeting_followups_to_daily_plan: request
+            .meeting_followups_to_daily_plan
+            .unwrap_or(existing.meeting_followups_to_daily_plan),
+        artifact_repo_type: update_optional(request.artifact_repo_type, existing.artifact_repo_type),
+        artifact_base_dir: update_optional(request.artifact_base_dir, existing.artifact_base_dir),
+        artifact_filename_template: update_optional(
+            request.artifact_filename_template,
+            existing.artifact_filename_template,
+        ),
+        artifact_format: request
+            .artifact_format
+            .map(|format| format.trim().to_lowercase())
+            .unwrap_or(existing.artifact_format),
+        artifact_disabled_agent_types: request
+            .artifact_disabled_agent_types
+            .unwrap_or(existing.artifact_disabled_agent_types),
+        updated_at: chrono::Utc::now().to_rfc3339(),
+        ..existing
+    };
+    super::agent_runs::validate_artifact_settings(&settings).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    org_settings::upsert_org_settings(&pool, &settings)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Ok(Json(settings))
+}
+
+/// GET /api/organizations/:org/usage
+pub async fn get_organization_usage(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(org): Extension<Organization>,
+    Path(organization): Path<String>,
+) -> Result<Json<UsageReport>, (StatusCode, String)> {
+    org.authorize(&organization)?;
+    let report = crate::quotas::usage_report(&pool, &organization)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Ok(Json(report))
+}
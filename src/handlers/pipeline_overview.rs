@@ -0,0 +1,148 @@
+//! Everything in flight across an organization's pipelines, for operators
+
+use axum::{
+    extract::{Extension, Query, State},
+    http::StatusCode,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use ticketing_system::{
+    models::{Pipeline, PipelineStep, PipelineStepStatus},
+    SqlitePool, Ticket,
+};
+
+use crate::pipeline_status::{phase, PipelinePhase};
+use crate::tenancy::Organization;
+
+#[derive(Debug, Deserialize)]
+pub struct ActivePipelinesQuery {
+    pub organization: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ActiveStep {
+    pub step_id: String,
+    pub agent_type: String,
+    pub status: PipelineStepStatus,
+    /// Agent session of the step's latest run
+    pub agent_session: Option<String>,
+    /// When that run started
+    pub started_at: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ActivePipeline {
+    pub ticket_id: String,
+    pub title: String,
+    pub epic_id: String,
+    pub slice_id: String,
+    pub phase: PipelinePhase,
+    /// The running or awaiting-approval step, else the next queued one
+    pub current_step: Option<ActiveStep>,
+    pub completed_steps: usize,
+    pub total_steps: usize,
+    /// When the pipeline's first agent run started
+    pub started_at: Option<String>,
+    /// Cost of the pipeline's finished agent runs, retries included; a run
+    /// still going is counted once it finishes
+    pub cost_usd: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ActivePipelinesResponse {
+    pub organization: String,
+    pub pipelines: Vec<ActivePipeline>,
+}
+
+fn current_step(pipeline: &Pipeline) -> Option<&PipelineStep> {
+    let in_flight = |s: &&PipelineStep| {
+        matches!(s.status, PipelineStepStatus::Running | PipelineStepStatus::AwaitingApproval)
+    };
+    pipeline
+        .steps
+        .iter()
+        .find(in_flight)
+        .or_else(|| pipeline.steps.iter().find(|s| s.status == PipelineStepStatus::Queued))
+}
+
+async fn active_pipeline(pool: &SqlitePool, ticket: &Ticket, pipeline: &Pipeline, phase: PipelinePhase) -> Result<ActivePipeline, (StatusCode, String)> {
+    let runs = ticketing_system::agent_runs::list_runs_by_ticket(pool, &ticket.ticket_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let run_started: HashMap<&str, &str> = runs.iter().map(|r| (r.session_id.as_str(), r.started_at.as_str())).collect();
+
+    // The pipeline started with the earliest run any of its steps points at
+    let started_at = pipeline
+        .steps
+        .iter()
+        .filter_map(|s| run_started.get(s.agent_run_id.as_deref()?).copied())
+        .min()
+        .map(str::to_string);
+    let sessions: Vec<String> = match &started_at {
+        Some(since) => runs.iter().filter(|r| r.started_at >= *since).map(|r| r.session_id.clone()).collect(),
+        None => Vec::new(),
+    };
+    let cost_usd = if sessions.is_empty() {
+        0.0
+    } else {
+        ticketing_system::agent_runs::total_cost(pool, &sessions)
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+    };
+
+    let current_step = current_step(pipeline).map(|step| ActiveStep {
+        step_id: step.step_id.clone(),
+        agent_type: step.agent_type.clone(),
+        status: step.status.clone(),
+        agent_session: step.agent_run_id.clone(),
+        started_at: step
+            .agent_run_id
+            .as_deref()
+            .and_then(|id| run_started.get(id))
+            .map(|s| s.to_string()),
+    });
+
+    Ok(ActivePipeline {
+        ticket_id: ticket.ticket_id.clone(),
+        title: ticket.title.clone(),
+        epic_id: ticket.epic_id.clone(),
+        slice_id: ticket.slice_id.clone(),
+        phase,
+        current_step,
+        completed_steps: pipeline.steps.iter().filter(|s| s.status == PipelineStepStatus::Completed).count(),
+        total_steps: pipeline.steps.len(),
+        started_at,
+        cost_usd,
+    })
+}
+
+/// Pipelines running or awaiting approval in the organization, longest
+/// running first (GET /api/pipelines/active)
+pub async fn list_active_pipelines(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(org): Extension<Organization>,
+    Query(params): Query<ActivePipelinesQuery>,
+) -> Result<Json<ActivePipelinesResponse>, (StatusCode, String)> {
+    let organization = org.resolve(params.organization)?;
+    let tickets = ticketing_system::tickets::list_tickets_by_organization(&pool, &organization)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to list tickets: {}", e)))?;
+
+    let mut pipelines = Vec::new();
+    for ticket in &tickets {
+        let Some(pipeline) = &ticket.pipeline else { continue };
+        let Some(phase @ (PipelinePhase::Started | PipelinePhase::AwaitingApproval)) = phase(pipeline) else {
+            continue;
+        };
+        pipelines.push(active_pipeline(&pool, ticket, pipeline, phase).await?);
+    }
+    // Pipelines without a run yet sort last
+    pipelines.sort_by(|a, b| match (&a.started_at, &b.started_at) {
+        (Some(a), Some(b)) => a.cmp(b),
+        (a, b) => b.is_some().cmp(&a.is_some()),
+    });
+
+    Ok(Json(ActivePipelinesResponse { organization, pipelines }))
+}
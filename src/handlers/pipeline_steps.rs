@@ -1,5 +1,5 @@
 use axum::{
-    extract::{Path, State},
+    extract::{Extension, Path, State},
     http::StatusCode,
     response::{IntoResponse, Response},
     Json,
@@ -8,14 +8,24 @@ use serde::{Deserialize, Serialize};
 use serde_json::json;
 use sqlx::SqlitePool;
 use std::sync::Arc;
-use tracing::{error, info};
+use tracing::{error, info, warn};
 
 use ticketing_system::{
-    models::{Pipeline, PipelineStep, PipelineStepStatus},
-    pipelines, tickets,
+    models::{Pipeline, PipelineStep, PipelineStepStatus, StepComment},
+    pipelines, tickets, GuestAccess, User,
 };
 
-use crate::pipeline_automation;
+use crate::pipeline_read_model::{self, PipelineStatusView};
+use crate::pipeline_status::save_pipeline;
+use crate::user_events::{self, Subject};
+use crate::{analytics, email_steps, pipeline_automation, template_sharing};
+
+use super::email_grants::{require_email_permission, EmailPermission};
+use super::pipeline_templates::{validate_step_inputs, validate_template_steps};
+use super::presence::{ticket_entity, DecisionGuard, PRESENCE};
+
+/// Longest review comment accepted on a step
+const MAX_STEP_COMMENT_CHARS: usize = 10_000;
 
 // ============================================================================
 // Request/Response Types
@@ -28,6 +38,13 @@ pub struct SetPipelineRequest {
     pub step_inputs: Option<std::collections::HashMap<String, serde_json::Value>>,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct AttachAndRunRequest {
+    pub template_id: String,
+    /// Input overrides keyed by step id, each an object merged over the template's default inputs
+    pub step_inputs: Option<std::collections::HashMap<String, serde_json::Value>>,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct StartStepRequest {
     pub agent_run_id: String,
@@ -48,6 +65,16 @@ pub struct RejectStepRequest {
     pub feedback: Option<String>,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct StepCommentRequest {
+    pub body: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SkipStepRequest {
+    pub reason: String,
+}
+
 #[derive(Debug, Serialize)]
 pub struct PipelineResponse {
     pub pipeline: Pipeline,
@@ -57,6 +84,32 @@ pub struct PipelineResponse {
 pub struct StepResponse {
     pub step: PipelineStep,
     pub pipeline_status: Option<String>,
+    /// Why the step was skipped, by a user or an unmet condition
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub skip_reason: Option<String>,
+}
+
+impl StepResponse {
+    fn new(step: PipelineStep, pipeline_status: Option<String>) -> Self {
+        let skip_reason = match step.status {
+            PipelineStepStatus::Skipped => step
+                .outputs
+                .as_ref()
+                .and_then(|o| o.get("skipped"))
+                .and_then(|r| r.as_str())
+                .map(|r| r.to_string()),
+            _ => None,
+        };
+        Self { step, pipeline_status, skip_reason }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct AttachAndRunResponse {
+    pub pipeline: Pipeline,
+    pub first_step_id: String,
+    pub session_id: Option<String>,
+    pub message: String,
 }
 
 #[derive(Debug, Serialize)]
@@ -101,6 +154,23 @@ pub async fn get_ticket_pipeline(
     }
 }
 
+/// GET /api/tickets/:ticket_id/pipeline/status
+///
+/// Step statuses only, served from the pipeline read model; cheap enough to poll.
+pub async fn get_ticket_pipeline_status(
+    State(pool): State<Arc<SqlitePool>>,
+    Path(ticket_id): Path<String>,
+) -> Result<Json<PipelineStatusView>, (StatusCode, String)> {
+    let view = pipeline_read_model::status_view(&pool, &ticket_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to get pipeline: {}", e)))?
+        .ok_or((StatusCode::NOT_FOUND, "Ticket not found".to_string()))?;
+    if !view.has_pipeline() {
+        return Err((StatusCode::NOT_FOUND, "Ticket has no pipeline".to_string()));
+    }
+    Ok(Json(view.as_ref().clone()))
+}
+
 /// POST /api/tickets/:ticket_id/pipeline
 pub async fn set_ticket_pipeline(
     State(pool): State<Arc<SqlitePool>>,
@@ -108,8 +178,8 @@ pub async fn set_ticket_pipeline(
     Json(request): Json<SetPipelineRequest>,
 ) -> Response {
     // First verify the ticket exists
-    match tickets::get_ticket_by_id(&pool, &ticket_id).await {
-        Ok(Some(_)) => {}
+    let organization = match tickets::get_ticket_by_id(&pool, &ticket_id).await {
+        Ok(Some(ticket)) => ticket.organization,
         Ok(None) => {
             return (
                 StatusCode::NOT_FOUND,
@@ -125,13 +195,14 @@ pub async fn set_ticket_pipeline(
             )
                 .into_response();
         }
-    }
+    };
 
     // Resolve pipeline: from template or custom
     let pipeline = if let Some(template_id) = request.template_id {
-        match tickets::attach_pipeline_from_template(
+        match template_sharing::attach_template(
             &pool,
             &ticket_id,
+            &organization,
             &template_id,
             request.step_inputs.as_ref(),
         )
@@ -173,6 +244,7 @@ pub async fn set_ticket_pipeline(
             .into_response();
     };
 
+    pipeline_read_model::record(&ticket_id, Some(&pipeline));
     info!("Set pipeline on ticket {}", ticket_id);
     (StatusCode::OK, Json(PipelineResponse { pipeline })).into_response()
 }
@@ -210,6 +282,7 @@ pub async fn delete_ticket_pipeline(
             .into_response();
     }
 
+    pipeline_read_model::record(&ticket_id, None);
     info!("Removed pipeline from ticket {}", ticket_id);
     (StatusCode::OK, Json(json!({ "deleted": true }))).into_response()
 }
@@ -298,7 +371,7 @@ pub async fn start_step(
 
     pipelines::start_step(pipeline, &step_id, &request.agent_run_id);
 
-    if let Err(e) = tickets::update_ticket_pipeline(&pool, &ticket_id, Some(pipeline)).await {
+    if let Err(e) = save_pipeline(&pool, &ticket_id, pipeline).await {
         error!("Failed to update pipeline after start_step: {:?}", e);
         return (
             StatusCode::INTERNAL_SERVER_ERROR,
@@ -311,10 +384,7 @@ pub async fn start_step(
     info!("Started step {} on ticket {}", step_id, ticket_id);
     (
         StatusCode::OK,
-        Json(StepResponse {
-            step,
-            pipeline_status: pipeline.status.clone(),
-        }),
+        Json(StepResponse::new(step, pipeline.status.clone())),
     )
         .into_response()
 }
@@ -345,7 +415,7 @@ pub async fn complete_step(
 
     pipelines::complete_step(pipeline, &step_id, request.outputs);
 
-    if let Err(e) = tickets::update_ticket_pipeline(&pool, &ticket_id, Some(pipeline)).await {
+    if let Err(e) = save_pipeline(&pool, &ticket_id, pipeline).await {
         error!("Failed to update pipeline after complete_step: {:?}", e);
         return (
             StatusCode::INTERNAL_SERVER_ERROR,
@@ -361,7 +431,7 @@ pub async fn complete_step(
     let pool_clone = pool.clone();
     let ticket_id_clone = ticket_id.clone();
     let step_id_clone = step_id.clone();
-    tokio::spawn(async move {
+    crate::logging::spawn_in_span(crate::logging::pipeline_span(&ticket_id), async move {
         match pipeline_automation::process_next_step(&pool_clone, &ticket_id_clone, &step_id_clone, 0).await {
             Ok(result) => {
                 info!("Pipeline automation result for ticket {}: {:?}", ticket_id_clone, result);
@@ -374,10 +444,7 @@ pub async fn complete_step(
 
     (
         StatusCode::OK,
-        Json(StepResponse {
-            step,
-            pipeline_status: pipeline.status.clone(),
-        }),
+        Json(StepResponse::new(step, pipeline.status.clone())),
     )
         .into_response()
 }
@@ -408,7 +475,7 @@ pub async fn fail_step(
 
     pipelines::fail_step(pipeline, &step_id, request.error);
 
-    if let Err(e) = tickets::update_ticket_pipeline(&pool, &ticket_id, Some(pipeline)).await {
+    if let Err(e) = save_pipeline(&pool, &ticket_id, pipeline).await {
         error!("Failed to update pipeline after fail_step: {:?}", e);
         return (
             StatusCode::INTERNAL_SERVER_ERROR,
@@ -418,27 +485,83 @@ pub async fn fail_step(
     }
 
     let step = pipeline.steps[step_idx].clone();
+    analytics::record_step_event(&pool, &ticket_id, &step_id, &step.agent_type, analytics::STEP_EVENT_FAILED).await;
     info!("Failed step {} on ticket {}", step_id, ticket_id);
     (
         StatusCode::OK,
-        Json(StepResponse {
-            step,
-            pipeline_status: pipeline.status.clone(),
-        }),
+        Json(StepResponse::new(step, pipeline.status.clone())),
     )
         .into_response()
 }
 
+/// Guard against conflicting approve/reject decisions: refuse if someone else
+/// is editing the ticket, then take the step's decision lock for the request.
+async fn claim_step_decision(
+    user: &User,
+    guest: Option<&GuestAccess>,
+    ticket_id: &str,
+    step_id: &str,
+) -> Result<DecisionGuard, Response> {
+    if guest.is_some_and(|access| !crate::guest_access::can_approve(access, ticket_id, step_id)) {
+        return Err((
+            StatusCode::FORBIDDEN,
+            Json(json!({ "error": "This step is not assigned to you" })),
+        )
+            .into_response());
+    }
+
+    if let Some(editor) = PRESENCE.other_editor(&ticket_entity(ticket_id), &user.user_id).await {
+        return Err((
+            StatusCode::CONFLICT,
+            Json(json!({
+                "error": format!("{} is currently editing this ticket", editor.name),
+                "editor": editor,
+            })),
+        )
+            .into_response());
+    }
+
+    PRESENCE.lock_step_decision(ticket_id, step_id).ok_or_else(|| {
+        (
+            StatusCode::CONFLICT,
+            Json(json!({ "error": "Another decision on this step is in progress" })),
+        )
+            .into_response()
+    })
+}
+
 /// POST /api/tickets/:ticket_id/pipeline/steps/:step_id/approve
 pub async fn approve_step(
     State(pool): State<Arc<SqlitePool>>,
+    Extension(user): Extension<User>,
+    guest: Option<Extension<GuestAccess>>,
     Path((ticket_id, step_id)): Path<(String, String)>,
 ) -> Response {
+    let _decision = match claim_step_decision(&user, guest.as_deref(), &ticket_id, &step_id).await {
+        Ok(guard) => guard,
+        Err(resp) => return resp,
+    };
+    user_events::follow(&pool, &user.user_id, Subject::Ticket, &ticket_id).await;
+
     let (mut ticket, step_idx) = match get_ticket_and_step(&pool, &ticket_id, &step_id).await {
         Ok(v) => v,
         Err(resp) => return resp,
     };
 
+    // Approving a send step sends its draft, which needs send access to the draft's mailbox
+    let is_send_step = email_steps::is_send_step(&ticket.pipeline.as_ref().unwrap().steps[step_idx].agent_type);
+    if is_send_step {
+        let draft = match email_steps::draft_for_send_step(&pool, &ticket, &step_id).await {
+            Ok(draft) => draft,
+            Err(e) => return (StatusCode::CONFLICT, Json(json!({ "error": e }))).into_response(),
+        };
+        if let Err((status, message)) =
+            require_email_permission(&pool, &user, &draft.from_address, EmailPermission::Send).await
+        {
+            return (status, Json(json!({ "error": message }))).into_response();
+        }
+    }
+
     let pipeline = ticket.pipeline.as_mut().unwrap();
     let step = &pipeline.steps[step_idx];
 
@@ -454,7 +577,7 @@ pub async fn approve_step(
 
     pipelines::approve_step(pipeline, &step_id);
 
-    if let Err(e) = tickets::update_ticket_pipeline(&pool, &ticket_id, Some(pipeline)).await {
+    if let Err(e) = save_pipeline(&pool, &ticket_id, pipeline).await {
         error!("Failed to update pipeline after approve_step: {:?}", e);
         return (
             StatusCode::INTERNAL_SERVER_ERROR,
@@ -466,12 +589,17 @@ pub async fn approve_step(
     let step = pipeline.steps[step_idx].clone();
     info!("Approved step {} on ticket {}", step_id, ticket_id);
 
+    if is_send_step {
+        let pool = pool.clone();
+        let (ticket_id, step_id) = (ticket_id.clone(), step_id.clone());
+        crate::logging::spawn_in_span(crate::logging::pipeline_span(&ticket_id), async move {
+            email_steps::run_send_step(&pool, &ticket_id, &step_id).await;
+        });
+    }
+
     (
         StatusCode::OK,
-        Json(StepResponse {
-            step,
-            pipeline_status: pipeline.status.clone(),
-        }),
+        Json(StepResponse::new(step, pipeline.status.clone())),
     )
         .into_response()
 }
@@ -479,9 +607,17 @@ pub async fn approve_step(
 /// POST /api/tickets/:ticket_id/pipeline/steps/:step_id/reject
 pub async fn reject_step(
     State(pool): State<Arc<SqlitePool>>,
+    Extension(user): Extension<User>,
+    guest: Option<Extension<GuestAccess>>,
     Path((ticket_id, step_id)): Path<(String, String)>,
     Json(request): Json<RejectStepRequest>,
 ) -> Response {
+    let _decision = match claim_step_decision(&user, guest.as_deref(), &ticket_id, &step_id).await {
+        Ok(guard) => guard,
+        Err(resp) => return resp,
+    };
+    user_events::follow(&pool, &user.user_id, Subject::Ticket, &ticket_id).await;
+
     let (mut ticket, step_idx) = match get_ticket_and_step(&pool, &ticket_id, &step_id).await {
         Ok(v) => v,
         Err(resp) => return resp,
@@ -500,14 +636,20 @@ pub async fn reject_step(
             .into_response();
     }
 
-    let error = request
-        .feedback
-        .map(|f| json!({ "rejected": true, "feedback": f }))
-        .unwrap_or_else(|| json!({ "rejected": true }));
+    let agent_type = step.agent_type.clone();
+    let feedback = request.feedback.map(|f| f.trim().to_string()).filter(|f| !f.is_empty());
+    let error = match &feedback {
+        Some(f) => json!({ "rejected": true, "feedback": f }),
+        None => json!({ "rejected": true }),
+    };
 
     pipelines::fail_step(pipeline, &step_id, Some(error));
+    // Kept as a comment too, so the feedback reaches the agent when the step is retried
+    if let Some(feedback) = feedback {
+        pipeline.steps[step_idx].comments.push(step_comment(&user, pipeline_automation::STEP_COMMENT_REJECTION, feedback));
+    }
 
-    if let Err(e) = tickets::update_ticket_pipeline(&pool, &ticket_id, Some(pipeline)).await {
+    if let Err(e) = save_pipeline(&pool, &ticket_id, pipeline).await {
         error!("Failed to update pipeline after reject_step: {:?}", e);
         return (
             StatusCode::INTERNAL_SERVER_ERROR,
@@ -516,18 +658,166 @@ pub async fn reject_step(
             .into_response();
     }
 
+    analytics::record_step_event(&pool, &ticket_id, &step_id, &agent_type, analytics::STEP_EVENT_REJECTED).await;
+
     let step = pipeline.steps[step_idx].clone();
     info!("Rejected step {} on ticket {}", step_id, ticket_id);
     (
         StatusCode::OK,
-        Json(StepResponse {
-            step,
-            pipeline_status: pipeline.status.clone(),
-        }),
+        Json(StepResponse::new(step, pipeline.status.clone())),
     )
         .into_response()
 }
 
+fn step_comment(user: &User, kind: &str, body: String) -> StepComment {
+    StepComment {
+        comment_id: uuid::Uuid::new_v4().to_string(),
+        kind: kind.to_string(),
+        author_id: user.user_id.clone(),
+        author_name: user.name.clone(),
+        body,
+        created_at: chrono::Utc::now().to_rfc3339(),
+    }
+}
+
+/// POST /api/tickets/:ticket_id/pipeline/steps/:step_id/comments
+///
+/// Leave a review note on a step in any status. Notes are returned with the
+/// step and given to the agent whenever the step runs again.
+pub async fn add_step_comment(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(user): Extension<User>,
+    guest: Option<Extension<GuestAccess>>,
+    Path((ticket_id, step_id)): Path<(String, String)>,
+    Json(request): Json<StepCommentRequest>,
+) -> Response {
+    let body = request.body.trim().to_string();
+    if body.is_empty() || body.len() > MAX_STEP_COMMENT_CHARS {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(json!({ "error": format!("Comments must be 1 to {} characters", MAX_STEP_COMMENT_CHARS) })),
+        )
+            .into_response();
+    }
+    if guest.is_some_and(|access| !crate::guest_access::can_approve(&access, &ticket_id, &step_id)) {
+        return (
+            StatusCode::FORBIDDEN,
+            Json(json!({ "error": "This step is not assigned to you" })),
+        )
+            .into_response();
+    }
+
+    let (mut ticket, step_idx) = match get_ticket_and_step(&pool, &ticket_id, &step_id).await {
+        Ok(v) => v,
+        Err(resp) => return resp,
+    };
+    let pipeline = ticket.pipeline.as_mut().unwrap();
+    pipeline.steps[step_idx].comments.push(step_comment(&user, pipeline_automation::STEP_COMMENT_NOTE, body));
+
+    if let Err(e) = save_pipeline(&pool, &ticket_id, pipeline).await {
+        error!("Failed to update pipeline after add_step_comment: {:?}", e);
+        return (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(json!({ "error": format!("Failed to update pipeline: {}", e) })),
+        )
+            .into_response();
+    }
+    user_events::follow(&pool, &user.user_id, Subject::Ticket, &ticket_id).await;
+
+    let step = pipeline.steps[step_idx].clone();
+    info!("{} commented on step {} of ticket {}", user.name, step_id, ticket_id);
+    (StatusCode::CREATED, Json(StepResponse::new(step, pipeline.status.clone()))).into_response()
+}
+
+/// POST /api/tickets/:ticket_id/pipeline/steps/:step_id/skip
+///
+/// Skips a queued or awaiting-approval step, recording who skipped it and why.
+/// If every earlier step is done, automation moves on to the following step.
+pub async fn skip_step(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(user): Extension<User>,
+    Path((ticket_id, step_id)): Path<(String, String)>,
+    Json(request): Json<SkipStepRequest>,
+) -> Response {
+    let reason = request.reason.trim().to_string();
+    if reason.is_empty() {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(json!({ "error": "A reason is required to skip a step" })),
+        )
+            .into_response();
+    }
+
+    let _decision = match claim_step_decision(&user, None, &ticket_id, &step_id).await {
+        Ok(guard) => guard,
+        Err(resp) => return resp,
+    };
+    user_events::follow(&pool, &user.user_id, Subject::Ticket, &ticket_id).await;
+
+    let (mut ticket, step_idx) = match get_ticket_and_step(&pool, &ticket_id, &step_id).await {
+        Ok(v) => v,
+        Err(resp) => return resp,
+    };
+
+    let pipeline = ticket.pipeline.as_mut().unwrap();
+    let step = &pipeline.steps[step_idx];
+
+    if !matches!(step.status, PipelineStepStatus::Queued | PipelineStepStatus::AwaitingApproval) {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(json!({
+                "error": format!("Cannot skip step in {:?} status, must be Queued or AwaitingApproval", step.status)
+            })),
+        )
+            .into_response();
+    }
+
+    pipelines::skip_step(
+        pipeline,
+        &step_id,
+        Some(json!({
+            "skipped": reason,
+            "skipped_by": user.name,
+            "skipped_by_user_id": user.user_id,
+            "skipped_at": chrono::Utc::now().to_rfc3339(),
+        })),
+    );
+
+    if let Err(e) = save_pipeline(&pool, &ticket_id, pipeline).await {
+        error!("Failed to update pipeline after skip_step: {:?}", e);
+        return (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(json!({ "error": format!("Failed to update pipeline: {}", e) })),
+        )
+            .into_response();
+    }
+
+    let step = pipeline.steps[step_idx].clone();
+    info!("{} skipped step {} on ticket {}: {}", user.name, step_id, ticket_id, reason);
+
+    // A later step skipped ahead of time waits for the pipeline to reach it
+    let reached = pipeline.steps[..step_idx]
+        .iter()
+        .all(|s| matches!(s.status, PipelineStepStatus::Completed | PipelineStepStatus::Skipped));
+    if reached {
+        let pool_clone = pool.clone();
+        let ticket_id_clone = ticket_id.clone();
+        let step_id_clone = step_id.clone();
+        crate::logging::spawn_in_span(crate::logging::pipeline_span(&ticket_id), async move {
+            match pipeline_automation::process_next_step(&pool_clone, &ticket_id_clone, &step_id_clone, 0).await {
+                Ok(result) => {
+                    info!("Pipeline automation result for ticket {}: {:?}", ticket_id_clone, result);
+                }
+                Err(e) => {
+                    error!("Pipeline automation failed for ticket {}: {:?}", ticket_id_clone, e);
+                }
+            }
+        });
+    }
+
+    (StatusCode::OK, Json(StepResponse::new(step, pipeline.status.clone()))).into_response()
+}
+
 /// POST /api/tickets/:ticket_id/pipeline/steps/:step_id/retry
 pub async fn retry_step(
     State(pool): State<Arc<SqlitePool>>,
@@ -561,7 +851,7 @@ pub async fn retry_step(
             .into_response();
     }
 
-    if let Err(e) = tickets::update_ticket_pipeline(&pool, &ticket_id, Some(pipeline)).await {
+    if let Err(e) = save_pipeline(&pool, &ticket_id, pipeline).await {
         error!("Failed to update pipeline after retry_step: {:?}", e);
         return (
             StatusCode::INTERNAL_SERVER_ERROR,
@@ -581,6 +871,7 @@ pub async fn retry_step(
         _ => {}
     }
 
+    analytics::record_step_event(&pool, &ticket_id, &step_id, &agent_type, analytics::STEP_EVENT_RETRIED).await;
     info!("Retrying step {} on ticket {}", step_id, ticket_id);
 
     let session_id = match pipeline_automation::start_step_execution(&pool, &ticket_id, &step_id).await {
@@ -765,6 +1056,10 @@ pub async fn run_pipeline(
 
     let first_step_id = first_step.step_id.clone();
 
+    if let Err(exceeded) = crate::quotas::check_agent_run(&pool, &ticket.organization).await {
+        return (exceeded.status(), Json(json!({ "error": exceeded.message() }))).into_response();
+    }
+
     let result = match pipeline_automation::start_step_execution(&pool, &ticket_id, &first_step_id).await {
         Ok(result) => result,
         Err(e) => {
@@ -796,6 +1091,7 @@ pub async fn run_pipeline(
     };
 
     info!("Started pipeline for ticket {}: {}", ticket_id, message);
+    crate::shadow_pipelines::start_shadow_runs(&pool, &ticket_id).await;
 
     (
         StatusCode::OK,
@@ -808,3 +1104,156 @@ pub async fn run_pipeline(
     )
         .into_response()
 }
+
+/// POST /api/tickets/:ticket_id/pipeline/attach-and-run
+///
+/// Validates the template and step inputs, attaches the pipeline and starts its
+/// first step in one call. Nothing is written if validation fails, and the
+/// ticket's previous pipeline is restored if the first step can't be started.
+pub async fn attach_and_run_pipeline(
+    State(pool): State<Arc<SqlitePool>>,
+    Path(ticket_id): Path<String>,
+    Json(request): Json<AttachAndRunRequest>,
+) -> Response {
+    // One attach per ticket at a time
+    let Some(_guard) = template_sharing::lock_attach(&ticket_id) else {
+        return (
+            StatusCode::CONFLICT,
+            Json(json!({ "error": "A pipeline is already being attached to this ticket" })),
+        )
+            .into_response();
+    };
+
+    let ticket = match tickets::get_ticket_by_id(&pool, &ticket_id).await {
+        Ok(Some(t)) => t,
+        Ok(None) => {
+            return (
+                StatusCode::NOT_FOUND,
+                Json(json!({ "error": "Ticket not found" })),
+            )
+                .into_response();
+        }
+        Err(e) => {
+            error!("Failed to get ticket: {:?}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({ "error": format!("Failed to get ticket: {}", e) })),
+            )
+                .into_response();
+        }
+    };
+    if ticket.pipeline.as_ref().and_then(|p| p.status.as_deref()) == Some("running") {
+        return (
+            StatusCode::CONFLICT,
+            Json(json!({ "error": "Ticket already has a running pipeline" })),
+        )
+            .into_response();
+    }
+    if let Err(exceeded) = crate::quotas::check_agent_run(&pool, &ticket.organization).await {
+        return (exceeded.status(), Json(json!({ "error": exceeded.message() }))).into_response();
+    }
+
+    let template = match pipelines::get_template(&pool, &request.template_id).await {
+        Ok(Some(t)) => t,
+        Ok(None) => {
+            return (
+                StatusCode::NOT_FOUND,
+                Json(json!({ "error": "Template not found" })),
+            )
+                .into_response();
+        }
+        Err(e) => {
+            error!("Failed to get template: {:?}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({ "error": format!("Failed to get template: {}", e) })),
+            )
+                .into_response();
+        }
+    };
+
+    let mut problems = validate_template_steps(&template.steps);
+    if let Some(step_inputs) = &request.step_inputs {
+        problems.extend(validate_step_inputs(&template.steps, step_inputs));
+    }
+    if !problems.is_empty() {
+        return (
+            StatusCode::UNPROCESSABLE_ENTITY,
+            Json(json!({ "error": "Invalid pipeline", "problems": problems })),
+        )
+            .into_response();
+    }
+
+    let previous_pipeline = ticket.pipeline;
+    let pipeline = match template_sharing::attach_template(
+        &pool,
+        &ticket_id,
+        &ticket.organization,
+        &request.template_id,
+        request.step_inputs.as_ref(),
+    )
+    .await
+    {
+        Ok(p) => p,
+        Err(e) if e.to_string().contains("not found") => {
+            return (StatusCode::NOT_FOUND, Json(json!({ "error": "Template not found" }))).into_response();
+        }
+        Err(e) => {
+            error!("Failed to attach pipeline from template: {:?}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({ "error": format!("Failed to set pipeline: {}", e) })),
+            )
+                .into_response();
+        }
+    };
+    pipeline_read_model::record(&ticket_id, Some(&pipeline));
+    let first_step_id = pipeline.steps[0].step_id.clone();
+
+    let failure = match pipeline_automation::start_step_execution(&pool, &ticket_id, &first_step_id).await {
+        Ok(pipeline_automation::PipelineProgressResult::PipelineFailed { reason }) => reason,
+        Ok(result) => {
+            let (session_id, message) = match result {
+                pipeline_automation::PipelineProgressResult::AgentSpawned { step_id, session_id } => {
+                    (Some(session_id), format!("Started auto step: {}", step_id))
+                }
+                pipeline_automation::PipelineProgressResult::AwaitingApproval { step_id } => {
+                    (None, format!("Step {} is awaiting approval", step_id))
+                }
+                other => (None, format!("Unexpected result: {:?}", other)),
+            };
+            let pipeline = match tickets::get_ticket_by_id(&pool, &ticket_id).await {
+                Ok(Some(t)) => t.pipeline.unwrap_or(pipeline),
+                _ => pipeline,
+            };
+            info!("Attached template {} and started pipeline for ticket {}: {}", request.template_id, ticket_id, message);
+            crate::shadow_pipelines::start_shadow_runs(&pool, &ticket_id).await;
+            return (
+                StatusCode::OK,
+                Json(AttachAndRunResponse { pipeline, first_step_id, session_id, message }),
+            )
+                .into_response();
+        }
+        Err(e) => e.to_string(),
+    };
+
+    // Don't leave a half-configured ticket behind
+    error!("Failed to start pipeline for ticket {}, restoring previous pipeline: {}", ticket_id, failure);
+    let restored = match template_sharing::restore_pipeline(&pool, &ticket_id, previous_pipeline.as_ref()).await {
+        Ok(true) => true,
+        Ok(false) => {
+            warn!("Pipeline of ticket {} changed before it could be restored; leaving it", ticket_id);
+            false
+        }
+        Err(e) => {
+            error!("Failed to restore pipeline on ticket {}: {:?}", ticket_id, e);
+            false
+        }
+    };
+    pipeline_read_model::invalidate(&ticket_id);
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(json!({ "error": format!("Failed to start pipeline: {}", failure), "restored": restored })),
+    )
+        .into_response()
+}
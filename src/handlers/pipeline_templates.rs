@@ -1,20 +1,26 @@
 use axum::{
-    extract::{Path, Query, State},
-    http::StatusCode,
+    extract::{Extension, Path, Query, State},
+    http::{header, HeaderMap, StatusCode},
     response::{IntoResponse, Response},
     Json,
 };
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use serde_json::json;
 use sqlx::SqlitePool;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 use tracing::{error, info};
 
 use ticketing_system::{
-    models::{CreatePipelineTemplateRequest, PipelineTemplateStep},
-    pipelines,
+    models::{CreatePipelineTemplateRequest, ExecutionType, PipelineTemplate, PipelineTemplateStep, StatusTransitions},
+    pipelines, template_overrides,
 };
 
+use crate::agents::AgentType;
+use crate::tenancy::Organization;
+use crate::template_sharing::{template_source, TemplateProvenance};
+use crate::pipeline_automation::{check_input_from, check_input_placeholder, input_placeholders, StepCondition, MAX_RETRY_ATTEMPTS};
+
 // ============================================================================
 // Request/Response Types
 // ============================================================================
@@ -26,44 +32,324 @@ pub struct ListTemplatesQuery {
     pub slice_id: Option<String>,
 }
 
-#[derive(Debug, Deserialize)]
+/// Template definition, accepted and exported as either JSON or YAML
+#[derive(Debug, Serialize, Deserialize)]
 pub struct CreateTemplateRequest {
     pub template_id: String,
     pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub organization: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub epic_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub slice_id: Option<String>,
     pub steps: Vec<PipelineTemplateStep>,
+    /// Ticket statuses to set as the pipeline progresses; unset phases use the defaults
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status_transitions: Option<StatusTransitions>,
+    /// Template this one dark-launches: it runs in the background alongside every
+    /// pipeline started from that template, see `crate::shadow_pipelines`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shadow_of: Option<String>,
+    /// Every organization may use the template, see `crate::template_sharing`
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub shared: bool,
+}
+
+impl From<PipelineTemplate> for CreateTemplateRequest {
+    fn from(template: PipelineTemplate) -> Self {
+        Self {
+            template_id: template.template_id,
+            name: template.name,
+            description: template.description,
+            organization: template.organization,
+            epic_id: template.epic_id,
+            slice_id: template.slice_id,
+            steps: template.steps,
+            status_transitions: template.status_transitions,
+            shadow_of: template.shadow_of,
+            shared: template.shared,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TemplateFormatQuery {
+    /// "json" (default for import) or "yaml"
+    pub format: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum TemplateFormat {
+    Json,
+    Yaml,
+}
+
+impl TemplateFormat {
+    /// Resolve from `?format=`, falling back to the Content-Type header
+    fn resolve(query: Option<&str>, headers: Option<&HeaderMap>) -> Result<Self, String> {
+        match query.map(|f| f.to_ascii_lowercase()).as_deref() {
+            Some("json") => return Ok(TemplateFormat::Json),
+            Some("yaml") | Some("yml") => return Ok(TemplateFormat::Yaml),
+            Some(other) => return Err(format!("Unsupported format '{}', expected json or yaml", other)),
+            None => {}
+        }
+
+        let is_yaml = headers
+            .and_then(|h| h.get(header::CONTENT_TYPE))
+            .and_then(|v| v.to_str().ok())
+            .map(|ct| ct.contains("yaml"))
+            .unwrap_or(false);
+        Ok(if is_yaml { TemplateFormat::Yaml } else { TemplateFormat::Json })
+    }
+}
+
+// ============================================================================
+// Validation
+// ============================================================================
+
+/// Check a template definition before it is stored. Returns every problem found.
+fn validate_template(request: &CreateTemplateRequest) -> Vec<String> {
+    let mut problems = Vec::new();
+
+    if request.template_id.trim().is_empty() {
+        problems.push("template_id must not be empty".to_string());
+    }
+    problems.extend(validate_template_steps(&request.steps));
+    if let Some(transitions) = &request.status_transitions {
+        problems.extend(crate::pipeline_status::validate_transitions(transitions));
+    }
+    if request.shadow_of.as_deref() == Some(request.template_id.as_str()) {
+        problems.push("a template can't shadow itself".to_string());
+    }
+    if request.shared && request.organization.is_none() {
+        problems.push("only an organization's template can be shared; templates without one are global".to_string());
+    }
+    problems
+}
+
+/// Problems with the send-email step at `step_idx`
+fn check_send_step(steps: &[PipelineTemplateStep], step_idx: usize) -> Vec<String> {
+    use crate::email_steps::{source_step_idx, DRAFT_STEP_INPUT, FROM_ADDRESS_INPUT};
+
+    let step = &steps[step_idx];
+    let mut problems = Vec::new();
+    if matches!(step.execution_type, ExecutionType::Auto) {
+        problems.push("send_email steps must use manual execution".to_string());
+    }
+    let input = |key: &str| step.default_inputs.as_ref().and_then(|inputs| inputs.get(key));
+    let draft_step = match input(DRAFT_STEP_INPUT) {
+        Some(value) => match value.as_str() {
+            Some(name) => Some(name),
+            None => {
+                problems.push(format!("{} must be a step id", DRAFT_STEP_INPUT));
+                None
+            }
+        },
+        None => None,
+    };
+    let pairs: Vec<(&str, &str)> = steps.iter().map(|s| (s.step_id.as_str(), s.agent_type.as_str())).collect();
+    if source_step_idx(&pairs, step_idx, draft_step).is_none() {
+        problems.push(match draft_step {
+            Some(name) => format!("{} '{}' is not an earlier email step", DRAFT_STEP_INPUT, name),
+            None => "send_email steps need an earlier email step".to_string(),
+        });
+    }
+    if input(FROM_ADDRESS_INPUT).is_some_and(|v| !v.as_str().is_some_and(|s| s.contains('@') || s.contains("{{"))) {
+        problems.push(format!("{} must be an email address", FROM_ADDRESS_INPUT));
+    }
+    problems
+}
+
+/// Check a template's steps: ids, agent types, conditions, retry, publish, input placeholders and fan-in
+pub(crate) fn validate_template_steps(steps: &[PipelineTemplateStep]) -> Vec<String> {
+    let mut problems = Vec::new();
+    if steps.is_empty() {
+        problems.push("template must have at least one step".to_string());
+    }
+
+    let mut seen = HashSet::new();
+    let mut earlier_steps: Vec<&str> = Vec::new();
+    for (idx, step) in steps.iter().enumerate() {
+        if !seen.insert(step.step_id.as_str()) {
+            problems.push(format!("duplicate step_id '{}'", step.step_id));
+        }
+
+        for path in step.default_inputs.iter().flat_map(|inputs| inputs.values()).flat_map(input_placeholders) {
+            if let Err(e) = check_input_placeholder(&path, &earlier_steps) {
+                problems.push(format!("step '{}': {}", step.step_id, e));
+            }
+        }
+        if let Some(input_from) = &step.input_from {
+            problems.extend(
+                check_input_from(input_from, &earlier_steps)
+                    .into_iter()
+                    .map(|e| format!("step '{}': {}", step.step_id, e)),
+            );
+        }
+        // Estimation only makes sense before any work has been done
+        if step.agent_type == crate::estimates::ESTIMATOR_AGENT && !earlier_steps.is_empty() {
+            problems.push(format!("step '{}': estimator steps must come first", step.step_id));
+        }
+        earlier_steps.push(step.step_id.as_str());
+
+        let is_human = step.agent_type == "human";
+        let is_send = crate::email_steps::is_send_step(&step.agent_type);
+        if is_human {
+            if matches!(step.execution_type, ExecutionType::Auto) {
+                problems.push(format!("step '{}': human steps must use manual execution", step.step_id));
+            }
+        } else if is_send {
+            problems.extend(check_send_step(steps, idx).into_iter().map(|e| format!("step '{}': {}", step.step_id, e)));
+        } else if serde_json::from_str::<AgentType>(&format!("\"{}\"", step.agent_type)).is_err() {
+            problems.push(format!("step '{}': unknown agent type '{}'", step.step_id, step.agent_type));
+        }
+
+        if let Some(condition) = &step.condition {
+            if let Err(e) = condition.parse::<StepCondition>() {
+                problems.push(format!("step '{}': {}", step.step_id, e));
+            }
+        }
+        if let Some(env) = &step.env {
+            problems.extend(
+                crate::secrets::validate_step_env(env)
+                    .into_iter()
+                    .map(|e| format!("step '{}': {}", step.step_id, e)),
+            );
+        }
+
+        if step.publish.is_some() && !crate::publish::supports_publish(&step.agent_type) {
+            problems.push(format!("step '{}': publish is only supported on execution steps", step.step_id));
+        }
+        if (is_human || is_send) && step.context_pack_id.is_some() {
+            problems.push(format!("step '{}': human steps can't use a context pack", step.step_id));
+        }
+        if (is_human || is_send) && step.input_from.as_ref().is_some_and(|sources| !sources.is_empty()) {
+            problems.push(format!("step '{}': input_from only applies to agent steps", step.step_id));
+        }
+        if (is_human || is_send) && step.env.as_ref().is_some_and(|env| !env.is_empty()) {
+            problems.push(format!("step '{}': env only applies to agent steps", step.step_id));
+        }
+
+        if let Some(approvers) = step.approval_emails.as_ref().filter(|approvers| !approvers.is_empty()) {
+            if is_send {
+                problems.push(format!("step '{}': send steps can't be approved by email", step.step_id));
+            } else if !matches!(step.execution_type, ExecutionType::Manual) {
+                problems.push(format!("step '{}': approval_emails only apply to manual steps", step.step_id));
+            }
+            for approver in approvers.iter().filter(|a| !crate::email_approvals::is_valid_email(a)) {
+                problems.push(format!("step '{}': '{}' is not an email address", step.step_id, approver));
+            }
+        }
+
+        if let Some(retry) = &step.retry {
+            if retry.max_attempts == 0 || retry.max_attempts > MAX_RETRY_ATTEMPTS {
+                problems.push(format!(
+                    "step '{}': retry.max_attempts must be between 1 and {}",
+                    step.step_id, MAX_RETRY_ATTEMPTS
+                ));
+            }
+        }
+    }
+
+    problems
+}
+
+/// Check per-step input overrides (keyed by step id) against a template's steps
+pub(crate) fn validate_step_inputs(
+    steps: &[PipelineTemplateStep],
+    step_inputs: &HashMap<String, serde_json::Value>,
+) -> Vec<String> {
+    let mut problems = Vec::new();
+    for (step_id, inputs) in step_inputs {
+        let Some(idx) = steps.iter().position(|s| &s.step_id == step_id) else {
+            problems.push(format!("step_inputs: unknown step '{}'", step_id));
+            continue;
+        };
+        let Some(inputs) = inputs.as_object() else {
+            problems.push(format!("step_inputs: inputs for step '{}' must be an object", step_id));
+            continue;
+        };
+        let earlier_steps: Vec<&str> = steps[..idx].iter().map(|s| s.step_id.as_str()).collect();
+        for path in inputs.values().flat_map(input_placeholders) {
+            if let Err(e) = check_input_placeholder(&path, &earlier_steps) {
+                problems.push(format!("step '{}': {}", step_id, e));
+            }
+        }
+    }
+    problems
 }
 
 // ============================================================================
 // Pipeline Template Handlers
 // ============================================================================
 
+#[derive(Debug, Serialize)]
+pub struct TemplateListing {
+    #[serde(flatten)]
+    pub template: PipelineTemplate,
+    pub provenance: TemplateProvenance,
+}
+
 /// GET /api/pipeline-templates
+///
+/// The organization's templates (`organization`, or the request's) followed
+/// by the shared and global ones it may use, each with its `provenance`.
+/// `epic_id`/`slice_id` narrow the organization's own templates; shared and
+/// global ones scoped to an epic or slice belong to their owner's and aren't listed.
 pub async fn list_templates(
     State(pool): State<Arc<SqlitePool>>,
+    Extension(org): Extension<Organization>,
     Query(params): Query<ListTemplatesQuery>,
 ) -> Response {
-    match pipelines::list_templates(
-        &pool,
-        params.organization.as_deref(),
-        params.epic_id.as_deref(),
-        params.slice_id.as_deref(),
-    )
-    .await
-    {
-        Ok(templates) => (StatusCode::OK, Json(json!({ "templates": templates }))).into_response(),
+    let organization = match org.resolve(params.organization) {
+        Ok(o) => o,
+        Err((status, message)) => return (status, Json(json!({ "error": message }))).into_response(),
+    };
+    let listed = async {
+        let own = pipelines::list_templates(
+            &pool,
+            Some(&organization),
+            params.epic_id.as_deref(),
+            params.slice_id.as_deref(),
+        )
+        .await?;
+        let shared = pipelines::list_shared_templates(&pool).await?;
+        let overridden = template_overrides::list_for_organization(&pool, &organization).await?;
+        Ok::<_, anyhow::Error>((own, shared, overridden))
+    };
+    let (own, shared, overridden) = match listed.await {
+        Ok(listed) => listed,
         Err(e) => {
             error!("Failed to list pipeline templates: {:?}", e);
-            (
+            return (
                 StatusCode::INTERNAL_SERVER_ERROR,
                 Json(json!({ "error": format!("Failed to list templates: {}", e) })),
             )
-                .into_response()
+                .into_response();
         }
-    }
+    };
+
+    let overridden: HashSet<String> = overridden.into_iter().map(|o| o.template_id).collect();
+    let mut seen = HashSet::new();
+    let shared = shared.into_iter().filter(|t| t.epic_id.is_none() && t.slice_id.is_none());
+    let templates: Vec<TemplateListing> = own
+        .into_iter()
+        .chain(shared)
+        .filter(|t| seen.insert(t.template_id.clone()))
+        .filter_map(|template| {
+            let source = template_source(template.organization.as_deref(), template.shared, &organization)?;
+            let provenance = TemplateProvenance {
+                source,
+                owner_organization: template.organization.clone(),
+                has_overrides: overridden.contains(&template.template_id),
+            };
+            Some(TemplateListing { template, provenance })
+        })
+        .collect();
+    (StatusCode::OK, Json(json!({ "templates": templates }))).into_response()
 }
 
 /// GET /api/pipeline-templates/:template_id
@@ -90,10 +376,97 @@ pub async fn get_template(
 }
 
 /// POST /api/pipeline-templates
+///
+/// Accepts a JSON body by default, or YAML with `?format=yaml` (or a YAML Content-Type).
+/// Steps may use a `library_step` reference, see `crate::step_library`.
 pub async fn create_template(
     State(pool): State<Arc<SqlitePool>>,
-    Json(request): Json<CreateTemplateRequest>,
+    Extension(org): Extension<Organization>,
+    Query(params): Query<TemplateFormatQuery>,
+    headers: HeaderMap,
+    body: String,
 ) -> Response {
+    let format = match TemplateFormat::resolve(params.format.as_deref(), Some(&headers)) {
+        Ok(f) => f,
+        Err(e) => return (StatusCode::BAD_REQUEST, Json(json!({ "error": e }))).into_response(),
+    };
+
+    let parsed = match format {
+        TemplateFormat::Json => serde_json::from_str::<serde_json::Value>(&body).map_err(|e| e.to_string()),
+        TemplateFormat::Yaml => serde_yaml::from_str::<serde_json::Value>(&body).map_err(|e| e.to_string()),
+    };
+    let mut definition = match parsed {
+        Ok(d) => d,
+        Err(e) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(json!({ "error": format!("Invalid template definition: {}", e) })),
+            )
+                .into_response()
+        }
+    };
+
+    let organization = definition.get("organization").and_then(|o| o.as_str()).map(str::to_string);
+    if let Some(Err((status, message))) = organization.as_deref().map(|o| org.authorize(o)) {
+        return (status, Json(json!({ "error": message }))).into_response();
+    }
+
+    // Library steps are expanded before the steps are read, since a reference
+    // leaves out the fields the library fills in
+    if let Some(steps) = definition.get_mut("steps").and_then(|s| s.as_array_mut()) {
+        match super::step_library::expand_library_steps(&pool, organization.as_deref(), steps, None).await {
+            Ok(problems) if problems.is_empty() => {}
+            Ok(problems) => {
+                return (
+                    StatusCode::UNPROCESSABLE_ENTITY,
+                    Json(json!({ "error": "Invalid template definition", "problems": problems })),
+                )
+                    .into_response()
+            }
+            Err((status, message)) => return (status, Json(json!({ "error": message }))).into_response(),
+        }
+    }
+
+    let request = match serde_json::from_value::<CreateTemplateRequest>(definition) {
+        Ok(r) => r,
+        Err(e) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(json!({ "error": format!("Invalid template definition: {}", e) })),
+            )
+                .into_response()
+        }
+    };
+
+    let mut problems = validate_template(&request);
+    if let Some(shadow_of) = &request.shadow_of {
+        // A shadow runs on the tickets of the template it shadows, so both must
+        // belong to the same organization
+        match pipelines::get_template(&pool, shadow_of).await {
+            Ok(Some(target)) if target.organization.is_some() && target.organization == request.organization => {}
+            Ok(Some(_)) => problems.push(format!(
+                "shadow_of: template '{}' is not a template of the same organization",
+                shadow_of
+            )),
+            Ok(None) => problems.push(format!("shadow_of: template '{}' not found", shadow_of)),
+            Err(e) => {
+                error!("Failed to get pipeline template: {:?}", e);
+                return (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(json!({ "error": format!("Failed to get template: {}", e) })),
+                )
+                    .into_response();
+            }
+        }
+    }
+    if !problems.is_empty() {
+        return (
+            StatusCode::UNPROCESSABLE_ENTITY,
+            Json(json!({ "error": "Invalid template definition", "problems": problems })),
+        )
+            .into_response();
+    }
+
     let req = CreatePipelineTemplateRequest {
         template_id: request.template_id,
         name: request.name,
@@ -102,6 +475,9 @@ pub async fn create_template(
         epic_id: request.epic_id,
         slice_id: request.slice_id,
         steps: request.steps,
+        status_transitions: request.status_transitions,
+        shadow_of: request.shadow_of,
+        shared: request.shared,
     };
 
     match pipelines::create_template(&pool, req).await {
@@ -120,6 +496,64 @@ pub async fn create_template(
     }
 }
 
+/// GET /api/pipeline-templates/:template_id/export
+///
+/// Returns the template definition in the same shape `create_template` accepts,
+/// as YAML by default or JSON with `?format=json`.
+pub async fn export_template(
+    State(pool): State<Arc<SqlitePool>>,
+    Path(template_id): Path<String>,
+    Query(params): Query<TemplateFormatQuery>,
+) -> Response {
+    let format = match params.format.as_deref() {
+        None => TemplateFormat::Yaml,
+        Some(f) => match TemplateFormat::resolve(Some(f), None) {
+            Ok(f) => f,
+            Err(e) => return (StatusCode::BAD_REQUEST, Json(json!({ "error": e }))).into_response(),
+        },
+    };
+
+    let template = match pipelines::get_template(&pool, &template_id).await {
+        Ok(Some(t)) => t,
+        Ok(None) => {
+            return (
+                StatusCode::NOT_FOUND,
+                Json(json!({ "error": "Template not found" })),
+            )
+                .into_response()
+        }
+        Err(e) => {
+            error!("Failed to get pipeline template: {:?}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({ "error": format!("Failed to get template: {}", e) })),
+            )
+                .into_response();
+        }
+    };
+
+    let definition = CreateTemplateRequest::from(template);
+    match format {
+        TemplateFormat::Json => (StatusCode::OK, Json(definition)).into_response(),
+        TemplateFormat::Yaml => match serde_yaml::to_string(&definition) {
+            Ok(yaml) => (
+                StatusCode::OK,
+                [(header::CONTENT_TYPE, "application/yaml")],
+                yaml,
+            )
+                .into_response(),
+            Err(e) => {
+                error!("Failed to serialize pipeline template to YAML: {:?}", e);
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(json!({ "error": format!("Failed to export template: {}", e) })),
+                )
+                    .into_response()
+            }
+        },
+    }
+}
+
 /// DELETE /api/pipeline-templates/:template_id
 pub async fn delete_template(
     State(pool): State<Arc<SqlitePool>>,
@@ -149,3 +583,50 @@ pub async fn delete_template(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_templates_through_yaml() {
+        let auto = serde_json::to_value(ExecutionType::Auto).unwrap();
+        let execution_type = format!("    execution_type: {}", auto.as_str().unwrap());
+        let yaml = [
+            "template_id: triage",
+            "name: Triage",
+            "steps:",
+            "  - step_id: research",
+            "    agent_type: exa-research",
+            execution_type.as_str(),
+            "  - step_id: escalate",
+            "    agent_type: planning",
+            execution_type.as_str(),
+            "    condition: \"output_contains: urgent\"",
+            "    retry:",
+            "      max_attempts: 3",
+            "      backoff_secs: 60",
+        ]
+        .join("\n");
+        let imported: CreateTemplateRequest =
+            serde_json::from_value(serde_yaml::from_str::<serde_json::Value>(&yaml).unwrap()).unwrap();
+        assert_eq!(imported.steps[1].condition.as_deref(), Some("output_contains: urgent"));
+        assert_eq!(imported.steps[1].retry.as_ref().map(|r| r.max_attempts), Some(3));
+        assert!(validate_template(&imported).is_empty());
+
+        let exported = serde_yaml::to_string(&imported).unwrap();
+        let reimported: CreateTemplateRequest =
+            serde_json::from_value(serde_yaml::from_str::<serde_json::Value>(&exported).unwrap()).unwrap();
+        assert_eq!(serde_json::to_value(&reimported).unwrap(), serde_json::to_value(&imported).unwrap());
+    }
+
+    #[test]
+    fn resolves_template_format() {
+        assert_eq!(TemplateFormat::resolve(Some("YML"), None), Ok(TemplateFormat::Yaml));
+        assert!(TemplateFormat::resolve(Some("toml"), None).is_err());
+        let mut headers = HeaderMap::new();
+        headers.insert(header::CONTENT_TYPE, "application/yaml".parse().unwrap());
+        assert_eq!(TemplateFormat::resolve(None, Some(&headers)), Ok(TemplateFormat::Yaml));
+        assert_eq!(TemplateFormat::resolve(None, None), Ok(TemplateFormat::Json));
+    }
+}
@@ -0,0 +1,685 @@
+//! Applying workspace manager plans
+//!
+//! Instead of the workspace manager issuing one MCP call per epic, slice and
+//! ticket, a whole plan (structured JSON, or the agent's `<proposal>` output
+//! as-is) is posted to `POST /api/plans/apply`. The plan is checked as a whole
+//! first; nothing is created while any problem remains. Applying then creates
+//! epics, slices, tickets with their pipelines and dependencies, and if any
+//! call fails everything created so far is deleted again, so a plan is never
+//! left half applied. Requests are dry runs unless `dry_run: false`.
+
+use axum::{
+    extract::{Extension, State},
+    http::StatusCode,
+    Json,
+};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use ticketing_system::{pipelines, tickets, SqlitePool};
+use tracing::{error, info, warn};
+
+use crate::mcp_wrapper::call_mcp_tool;
+use crate::tenancy::Organization;
+
+use super::slices::slice_pipeline_defaults;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct PlanEpic {
+    pub epic_id: String,
+    /// Defaults to the request's organization
+    #[serde(default)]
+    pub organization: Option<String>,
+    /// Required when the epic doesn't exist yet
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub notes: Option<String>,
+    #[serde(default)]
+    pub slices: Vec<PlanSlice>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct PlanSlice {
+    pub slice_id: String,
+    /// Required when the slice doesn't exist yet
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub notes: Option<String>,
+    #[serde(default)]
+    pub tickets: Vec<PlanTicket>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct PlanTicket {
+    /// Handle other tickets in the plan use to depend on this one
+    #[serde(rename = "ref")]
+    pub reference: String,
+    pub title: String,
+    #[serde(default)]
+    pub intent: Option<String>,
+    #[serde(default)]
+    pub description: Option<String>,
+    /// Defaults to the slice's default pipeline template
+    #[serde(default)]
+    pub pipeline_template_id: Option<String>,
+    #[serde(default)]
+    pub labels: Vec<String>,
+    /// Refs of tickets in the plan, or ids of existing tickets
+    #[serde(default)]
+    pub blocked_by: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ApplyPlanRequest {
+    #[serde(default)]
+    pub epics: Vec<PlanEpic>,
+    /// Workspace manager output containing `<proposal>` blocks, used instead of
+    /// (or in addition to) `epics`
+    pub proposals: Option<String>,
+    /// Defaults to true: report what would be created without creating it
+    pub dry_run: Option<bool>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct EpicPreview {
+    pub organization: String,
+    pub epic_id: String,
+    pub title: Option<String>,
+    /// False when applying the plan creates the epic
+    pub exists: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SlicePreview {
+    pub organization: String,
+    pub epic_id: String,
+    pub slice_id: String,
+    pub title: Option<String>,
+    pub exists: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TicketPreview {
+    #[serde(rename = "ref")]
+    pub reference: String,
+    pub title: String,
+    pub organization: String,
+    pub epic_id: String,
+    pub slice_id: String,
+    pub pipeline_template_id: String,
+    pub blocked_by: Vec<String>,
+    /// Set once the ticket has been created
+    pub ticket_id: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PlanReport {
+    pub dry_run: bool,
+    pub applied: bool,
+    pub epics: Vec<EpicPreview>,
+    pub slices: Vec<SlicePreview>,
+    pub tickets: Vec<TicketPreview>,
+    /// Reasons the plan can't be applied; nothing is created while any remain
+    pub problems: Vec<String>,
+    /// Failures while applying, and while undoing a failed apply
+    pub errors: Vec<String>,
+    pub rolled_back: bool,
+}
+
+// ============================================================================
+// Proposal Parsing
+// ============================================================================
+
+lazy_static::lazy_static! {
+    static ref PROPOSAL: Regex = Regex::new(r"(?s)<proposal>(.*?)</proposal>").unwrap();
+    static ref SLICE: Regex = Regex::new(r"(?s)<slice>(.*?)</slice>").unwrap();
+    static ref TICKET: Regex = Regex::new(r"(?s)<ticket\s+([^>]*)>(.*?)</ticket>").unwrap();
+    static ref ATTRIBUTE: Regex = Regex::new(r#"([A-Za-z_]+)\s*=\s*"([^"]*)""#).unwrap();
+}
+
+/// Text of the first `<tag>…</tag>` in `text`, trimmed
+fn tag<'a>(text: &'a str, tag: &str) -> Option<&'a str> {
+    let open = format!("<{}>", tag);
+    let start = text.find(&open)? + open.len();
+    let end = text[start..].find(&format!("</{}>", tag))? + start;
+    Some(text[start..end].trim()).filter(|t| !t.is_empty())
+}
+
+/// Turn the workspace manager's `<proposal>` blocks into plan epics. Proposals
+/// for the same organization and epic are merged.
+fn parse_proposals(text: &str) -> Result<Vec<PlanEpic>, String> {
+    let mut epics: Vec<PlanEpic> = Vec::new();
+    for (i, caps) in PROPOSAL.captures_iter(text).enumerate() {
+        let proposal = &caps[1];
+        // The graph repeats ticket ids in mermaid syntax; it carries nothing the tickets don't
+        let proposal = match proposal.find("<graph>") {
+            Some(idx) => &proposal[..idx],
+            None => proposal,
+        };
+        let epic_id = tag(proposal, "epic").ok_or_else(|| format!("proposal {} has no <epic>", i + 1))?;
+        let slice = SLICE
+            .captures(proposal)
+            .ok_or_else(|| format!("proposal {} has no <slice>", i + 1))?;
+        let slice_id = tag(&slice[1], "id").ok_or_else(|| format!("proposal {} has no slice <id>", i + 1))?;
+
+        let mut tickets = Vec::new();
+        for ticket in TICKET.captures_iter(proposal) {
+            let attributes: HashMap<&str, &str> = ATTRIBUTE
+                .captures_iter(&ticket[1])
+                .filter_map(|a| Some((a.get(1)?.as_str(), a.get(2)?.as_str())))
+                .collect();
+            let body = &ticket[2];
+            tickets.push(PlanTicket {
+                reference: attributes.get("id").copied().unwrap_or_default().trim().to_string(),
+                title: tag(body, "title").unwrap_or_default().to_string(),
+                intent: tag(body, "intent").map(str::to_string),
+                description: tag(body, "description").map(str::to_string),
+                pipeline_template_id: attributes.get("pipeline").map(|p| p.trim().to_string()).filter(|p| !p.is_empty()),
+                labels: Vec::new(),
+                blocked_by: attributes
+                    .get("blocked_by")
+                    .map(|b| b.split([',', ' ']).map(str::trim).filter(|r| !r.is_empty()).map(str::to_string).collect())
+                    .unwrap_or_default(),
+            });
+        }
+
+        let organization = tag(proposal, "organization").map(str::to_string);
+        let slice = PlanSlice {
+            slice_id: slice_id.to_string(),
+            title: tag(&slice[1], "title").map(str::to_string),
+            notes: None,
+            tickets,
+        };
+        match epics.iter_mut().find(|e| e.epic_id == epic_id && e.organization == organization) {
+            Some(epic) => epic.slices.push(slice),
+            None => epics.push(PlanEpic {
+                epic_id: epic_id.to_string(),
+                organization,
+                title: None,
+                notes: None,
+                slices: vec![slice],
+            }),
+        }
+    }
+    Ok(epics)
+}
+
+// ============================================================================
+// Validation
+// ============================================================================
+
+/// A dependency cycle among plan refs, if there is one
+fn find_cycle(deps: &HashMap<&str, Vec<&str>>) -> Option<Vec<String>> {
+    fn visit<'a>(
+        node: &'a str,
+        deps: &HashMap<&'a str, Vec<&'a str>>,
+        done: &mut HashSet<&'a str>,
+        path: &mut Vec<&'a str>,
+    ) -> Option<Vec<String>> {
+        if let Some(start) = path.iter().position(|n| *n == node) {
+            return Some(path[start..].iter().map(|n| n.to_string()).collect());
+        }
+        if !done.insert(node) {
+            return None;
+        }
+        path.push(node);
+        for next in deps.get(node).into_iter().flatten() {
+            if let Some(cycle) = visit(next, deps, done, path) {
+                return Some(cycle);
+            }
+        }
+        path.pop();
+        None
+    }
+
+    let mut nodes: Vec<&str> = deps.keys().copied().collect();
+    nodes.sort();
+    let mut done = HashSet::new();
+    nodes.into_iter().find_map(|node| visit(node, deps, &mut done, &mut Vec::new()))
+}
+
+/// Structural problems that don't need the database: missing or duplicate
+/// ids, empty titles and dependency cycles
+fn validate_plan(epics: &[PlanEpic]) -> Vec<String> {
+    let mut problems = Vec::new();
+    if epics.iter().all(|e| e.slices.iter().all(|s| s.tickets.is_empty())) {
+        problems.push("plan has no tickets".to_string());
+    }
+
+    let mut epic_keys = HashSet::new();
+    let mut refs = HashSet::new();
+    let mut deps: HashMap<&str, Vec<&str>> = HashMap::new();
+    for epic in epics {
+        if epic.epic_id.trim().is_empty() {
+            problems.push("an epic has no epic_id".to_string());
+        }
+        if !epic_keys.insert((epic.organization.as_deref(), epic.epic_id.as_str())) {
+            problems.push(format!("epic '{}' appears more than once", epic.epic_id));
+        }
+        let mut slice_ids = HashSet::new();
+        for slice in &epic.slices {
+            if slice.slice_id.trim().is_empty() {
+                problems.push(format!("a slice in epic '{}' has no slice_id", epic.epic_id));
+            }
+            if !slice_ids.insert(slice.slice_id.as_str()) {
+                problems.push(format!("slice '{}' appears more than once in epic '{}'", slice.slice_id, epic.epic_id));
+            }
+            for ticket in &slice.tickets {
+                if ticket.reference.trim().is_empty() {
+                    problems.push(format!("a ticket in slice '{}' has no ref", slice.slice_id));
+                } else if !refs.insert(ticket.reference.as_str()) {
+                    problems.push(format!("ticket ref '{}' is used more than once", ticket.reference));
+                }
+                if ticket.title.trim().is_empty() {
+                    problems.push(format!("ticket '{}' has no title", ticket.reference));
+                }
+                deps.insert(ticket.reference.as_str(), ticket.blocked_by.iter().map(String::as_str).collect());
+            }
+        }
+    }
+
+    // Only dependencies between plan tickets can form a cycle
+    for blockers in deps.values_mut() {
+        blockers.retain(|b| refs.contains(b));
+    }
+    if let Some(cycle) = find_cycle(&deps) {
+        problems.push(format!("tickets depend on each other in a cycle: {}", cycle.join(" -> ")));
+    }
+    problems
+}
+
+pub(crate) async fn epic_exists(organization: &str, epic_id: &str) -> bool {
+    call_mcp_tool("get_epic", Some(json!({ "organization": organization, "epic_id": epic_id })))
+        .await
+        .is_ok()
+}
+
+pub(crate) async fn slice_exists(organization: &str, epic_id: &str, slice_id: &str) -> bool {
+    let args = json!({ "organization": organization, "epic_id": epic_id, "slice_id": slice_id });
+    call_mcp_tool("get_slice", Some(args)).await.is_ok()
+}
+
+/// Build the preview, adding problems that need the database: unauthorized
+/// organizations, new epics or slices without titles, unknown pipeline
+/// templates and unknown existing tickets
+async fn preview_plan(pool: &SqlitePool, org: &Organization, epics: &[PlanEpic], dry_run: bool) -> PlanReport {
+    let mut report = PlanReport {
+        dry_run,
+        applied: false,
+        epics: Vec::new(),
+        slices: Vec::new(),
+        tickets: Vec::new(),
+        problems: validate_plan(epics),
+        errors: Vec::new(),
+        rolled_back: false,
+    };
+    let refs: HashSet<&str> = epics
+        .iter()
+        .flat_map(|e| &e.slices)
+        .flat_map(|s| &s.tickets)
+        .map(|t| t.reference.as_str())
+        .collect();
+    let mut known_templates: HashMap<String, bool> = HashMap::new();
+
+    for epic in epics {
+        let organization = epic.organization.clone().unwrap_or_else(|| org.name.clone());
+        if let Err((_, message)) = org.authorize(&organization) {
+            report.problems.push(message);
+            continue;
+        }
+
+        let exists = epic_exists(&organization, &epic.epic_id).await;
+        if !exists && epic.title.is_none() {
+            report.problems.push(format!("epic '{}' doesn't exist and has no title to create it with", epic.epic_id));
+        }
+        report.epics.push(EpicPreview {
+            organization: organization.clone(),
+            epic_id: epic.epic_id.clone(),
+            title: epic.title.clone(),
+            exists,
+        });
+
+        for slice in &epic.slices {
+            let slice_found = exists && slice_exists(&organization, &epic.epic_id, &slice.slice_id).await;
+            if !slice_found && slice.title.is_none() {
+                report.problems.push(format!("slice '{}' doesn't exist and has no title to create it with", slice.slice_id));
+            }
+            report.slices.push(SlicePreview {
+                organization: organization.clone(),
+                epic_id: epic.epic_id.clone(),
+                slice_id: slice.slice_id.clone(),
+                title: slice.title.clone(),
+                exists: slice_found,
+            });
+
+            let defaults = slice_pipeline_defaults(pool, &organization, &epic.epic_id, &slice.slice_id).await;
+            for ticket in &slice.tickets {
+                let template_id = ticket.pipeline_template_id.clone().unwrap_or_else(|| defaults.template_id.clone());
+                if !known_templates.contains_key(&template_id) {
+                    let found = matches!(pipelines::get_template(pool, &template_id).await, Ok(Some(_)));
+                    known_templates.insert(template_id.clone(), found);
+                }
+                if known_templates.get(&template_id) == Some(&false) {
+                    report.problems.push(format!("ticket '{}': unknown pipeline template '{}'", ticket.reference, template_id));
+                }
+
+                for blocker in ticket.blocked_by.iter().filter(|b| !refs.contains(b.as_str())) {
+                    match tickets::get_ticket_by_id(pool, blocker).await {
+                        Ok(Some(existing)) if org.authorize(&existing.organization).is_ok() => {}
+                        _ => report.problems.push(format!(
+                            "ticket '{}': blocked_by '{}' is neither a ref in the plan nor an existing ticket",
+                            ticket.reference, blocker
+                        )),
+                    }
+                }
+
+                report.tickets.push(TicketPreview {
+                    reference: ticket.reference.clone(),
+                    title: ticket.title.clone(),
+                    organization: organization.clone(),
+                    epic_id: epic.epic_id.clone(),
+                    slice_id: slice.slice_id.clone(),
+                    pipeline_template_id: template_id,
+                    blocked_by: ticket.blocked_by.clone(),
+                    ticket_id: None,
+                });
+            }
+        }
+    }
+    report
+}
+
+// ============================================================================
+// Applying
+// ============================================================================
+
+/// Everything an apply has created, in creation order, for rolling back
+#[derive(Default)]
+struct Created {
+    epics: Vec<(String, String)>,
+    slices: Vec<(String, String, String)>,
+    tickets: Vec<(String, String, String, String)>,
+}
+
+impl Created {
+    /// Delete in reverse: tickets, then slices, then epics. Returns the
+    /// deletions that failed.
+    async fn roll_back(self) -> Vec<String> {
+        let mut errors = Vec::new();
+        for (organization, epic_id, slice_id, ticket_id) in self.tickets.into_iter().rev() {
+            let args = json!({ "organization": organization, "epic_id": epic_id, "slice_id": slice_id, "ticket_id": ticket_id });
+            if let Err(e) = call_mcp_tool("delete_ticket", Some(args)).await {
+                errors.push(format!("Failed to roll back ticket {}: {}", ticket_id, e));
+            }
+        }
+        for (organization, epic_id, slice_id) in self.slices.into_iter().rev() {
+            let args = json!({ "organization": organization, "epic_id": epic_id, "slice_id": slice_id });
+            if let Err(e) = call_mcp_tool("delete_slice", Some(args)).await {
+                errors.push(format!("Failed to roll back slice {}/{}: {}", epic_id, slice_id, e));
+            }
+        }
+        for (organization, epic_id) in self.epics.into_iter().rev() {
+            if let Err(e) = call_mcp_tool("delete_epic", Some(json!({ "organization": organization, "epic_id": epic_id }))).await {
+                errors.push(format!("Failed to roll back epic {}: {}", epic_id, e));
+            }
+        }
+        errors
+    }
+}
+
+/// Create a previewed plan. Tickets, epics and slices in `report` are in plan
+/// order. Stops at the first failure; the caller rolls back `created`.
+async fn apply(epics: &[PlanEpic], report: &mut PlanReport, created: &mut Created) -> Result<(), String> {
+    let mut ticket_ids: HashMap<String, (String, String, String, String)> = HashMap::new();
+    let mut previews = report.tickets.iter_mut();
+    let mut slice_previews = report.slices.iter();
+
+    for (epic, epic_preview) in epics.iter().zip(&report.epics) {
+        let organization = &epic_preview.organization;
+        if !epic_preview.exists {
+            let args = json!({
+                "organization": organization,
+                "epics": [{ "epic_id": epic.epic_id, "title": epic.title, "notes": epic.notes }]
+            });
+            call_mcp_tool("create_epics", Some(args))
+                .await
+                .map_err(|e| format!("Failed to create epic {}: {}", epic.epic_id, e))?;
+            created.epics.push((organization.clone(), epic.epic_id.clone()));
+        }
+
+        for slice in &epic.slices {
+            let slice_preview = slice_previews.next().ok_or("Plan preview is out of step with the plan")?;
+            if !slice_preview.exists {
+                let args = json!({
+                    "organization": organization,
+                    "slices": [{
+                        "epic_id": epic.epic_id,
+                        "slice_id": slice.slice_id,
+                        "title": slice.title,
+                        "notes": slice.notes,
+                    }]
+                });
+                call_mcp_tool("create_slices", Some(args))
+                    .await
+                    .map_err(|e| format!("Failed to create slice {}/{}: {}", epic.epic_id, slice.slice_id, e))?;
+                created.slices.push((organization.clone(), epic.epic_id.clone(), slice.slice_id.clone()));
+            }
+            if slice.tickets.is_empty() {
+                continue;
+            }
+
+            // Dependencies inside the slice are wired by the batch call itself
+            let slice_refs: HashSet<&str> = slice.tickets.iter().map(|t| t.reference.as_str()).collect();
+            let ticket_previews: Vec<&mut TicketPreview> = previews.by_ref().take(slice.tickets.len()).collect();
+            let specs: Vec<_> = slice
+                .tickets
+                .iter()
+                .zip(&ticket_previews)
+                .map(|(ticket, preview)| {
+                    let blocked_by_refs: Vec<&String> =
+                        ticket.blocked_by.iter().filter(|b| slice_refs.contains(b.as_str())).collect();
+                    json!({
+                        "ref": ticket.reference,
+                        "title": ticket.title,
+                        "intent": ticket.intent,
+                        "description": ticket.description,
+                        "ticket_type": "milestone",
+                        "pipeline_template_id": preview.pipeline_template_id,
+                        "labels": ticket.labels,
+                        "blocked_by_refs": blocked_by_refs,
+                    })
+                })
+                .collect();
+            let args = json!({
+                "organization": organization,
+                "epic_id": epic.epic_id,
+                "slice_id": slice.slice_id,
+                "tickets": specs,
+            });
+            let result = call_mcp_tool("create_slice_tickets", Some(args))
+                .await
+                .map_err(|e| format!("Failed to create tickets in {}/{}: {}", epic.epic_id, slice.slice_id, e))?;
+
+            let results = result.get("tickets").and_then(|t| t.as_array()).cloned().unwrap_or_default();
+            // Record whatever was created before checking, so it is rolled back too
+            for (preview, result) in ticket_previews.into_iter().zip(&results) {
+                if let Some(ticket_id) = result.pointer("/ticket/ticket_id").and_then(|id| id.as_str()) {
+                    let location = (organization.clone(), epic.epic_id.clone(), slice.slice_id.clone(), ticket_id.to_string());
+                    created.tickets.push(location.clone());
+                    ticket_ids.insert(preview.reference.clone(), location);
+                    preview.ticket_id = Some(ticket_id.to_string());
+                }
+            }
+            if let Some(missing) = slice.tickets.iter().find(|t| !ticket_ids.contains_key(&t.reference)) {
+                return Err(format!("No ticket id returned for '{}'", missing.reference));
+            }
+        }
+    }
+
+    // Dependencies across slices, and on tickets that already existed
+    for ticket in epics.iter().flat_map(|e| &e.slices).flat_map(|s| &s.tickets) {
+        let (organization, epic_id, slice_id, ticket_id) = &ticket_ids[&ticket.reference];
+        for blocker in &ticket.blocked_by {
+            let target = match ticket_ids.get(blocker) {
+                Some((_, blocker_epic, blocker_slice, _)) if blocker_epic == epic_id && blocker_slice == slice_id => continue,
+                Some((_, _, _, blocker_id)) => blocker_id,
+                None => blocker,
+            };
+            let args = json!({
+                "organization": organization,
+                "epic_id": epic_id,
+                "slice_id": slice_id,
+                "ticket_id": ticket_id,
+                "relationship_type": "blocked_by",
+                "target_ticket_id": target,
+            });
+            call_mcp_tool("add_ticket_relationship", Some(args))
+                .await
+                .map_err(|e| format!("Failed to make {} blocked by {}: {}", ticket.reference, blocker, e))?;
+        }
+    }
+    Ok(())
+}
+
+/// POST /api/plans/apply
+/// Preview (default) or create a workspace manager plan as a whole
+pub async fn apply_plan(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(org): Extension<Organization>,
+    Json(request): Json<ApplyPlanRequest>,
+) -> (StatusCode, Json<PlanReport>) {
+    let dry_run = request.dry_run.unwrap_or(true);
+    let mut epics = request.epics;
+    let mut parse_problem = None;
+    if let Some(proposals) = &request.proposals {
+        match parse_proposals(proposals) {
+            Ok(parsed) => epics.extend(parsed),
+            Err(e) => parse_problem = Some(e),
+        }
+    }
+
+    let mut report = preview_plan(&pool, &org, &epics, dry_run).await;
+    report.problems.extend(parse_problem);
+    if dry_run {
+        return (StatusCode::OK, Json(report));
+    }
+    if !report.problems.is_empty() {
+        return (StatusCode::UNPROCESSABLE_ENTITY, Json(report));
+    }
+
+    let mut created = Created::default();
+    match apply(&epics, &mut report, &mut created).await {
+        Ok(()) => {
+            report.applied = true;
+            info!(
+                "Applied plan: {} epics and {} slices created, {} tickets",
+                created.epics.len(),
+                created.slices.len(),
+                created.tickets.len()
+            );
+            (StatusCode::CREATED, Json(report))
+        }
+        Err(e) => {
+            error!("Failed to apply plan, rolling back: {}", e);
+            report.errors.push(e);
+            let rollback_errors = created.roll_back().await;
+            if !rollback_errors.is_empty() {
+                warn!("Plan rollback left {} items behind", rollback_errors.len());
+            }
+            report.errors.extend(rollback_errors);
+            report.rolled_back = true;
+            for ticket in &mut report.tickets {
+                ticket.ticket_id = None;
+            }
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(report))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROPOSALS: &str = r#"
+<proposal>
+<organization>example-org</organization>
+<epic>frontend</epic>
+<slice>
+<id>dark-mode</id>
+<title>Dark Mode Support</title>
+</slice>
+<tickets>
+<ticket id="F1" pipeline="standard-dev">
+<title>Create ThemeProvider</title>
+<intent>Manage theme state</intent>
+<description>Stores the preference in localStorage</description>
+</ticket>
+<ticket id="F2" pipeline="quick-fix" blocked_by="F1">
+<title>Add dark mode toggle</title>
+<intent>UI control</intent>
+</ticket>
+</tickets>
+<graph>
+```mermaid
+graph TD
+    F1[ThemeProvider] --> F2[Toggle UI]
+```
+</graph>
+</proposal>
+
+<proposal>
+<organization>example-org</organization>
+<epic>frontend</epic>
+<slice>
+<id>dark-mode-tests</id>
+<title>Dark Mode Tests</title>
+</slice>
+<tickets>
+<ticket id="F3" pipeline="quick-fix" blocked_by="F1, F2">
+<title>Snapshot tests for dark theme</title>
+</ticket>
+</tickets>
+</proposal>
+
+<summary>2 slices, 3 tickets</summary>
+"#;
+
+    #[test]
+    fn parses_workspace_manager_proposals() {
+        let epics = parse_proposals(PROPOSALS).unwrap();
+        assert_eq!(epics.len(), 1);
+        let epic = &epics[0];
+        assert_eq!(epic.epic_id, "frontend");
+        assert_eq!(epic.organization.as_deref(), Some("example-org"));
+        assert_eq!(epic.slices.len(), 2);
+
+        let tickets = &epic.slices[0].tickets;
+        assert_eq!(epic.slices[0].title.as_deref(), Some("Dark Mode Support"));
+        assert_eq!(tickets.len(), 2);
+        assert_eq!(tickets[0].reference, "F1");
+        assert_eq!(tickets[0].pipeline_template_id.as_deref(), Some("standard-dev"));
+        assert_eq!(tickets[0].description.as_deref(), Some("Stores the preference in localStorage"));
+        assert_eq!(tickets[1].blocked_by, vec!["F1"]);
+        assert_eq!(tickets[1].description, None);
+        assert_eq!(epic.slices[1].tickets[0].blocked_by, vec!["F1", "F2"]);
+
+        assert!(validate_plan(&epics).is_empty());
+        assert!(parse_proposals("<proposal><epic>x</epic></proposal>").is_err());
+    }
+
+    #[test]
+    fn rejects_duplicate_refs_and_cycles() {
+        let mut epics = parse_proposals(PROPOSALS).unwrap();
+        epics[0].slices[0].tickets[0].blocked_by = vec!["F3".to_string()];
+        let duplicate = epics[0].slices[0].tickets[1].clone();
+        epics[0].slices[1].tickets.push(duplicate);
+
+        let problems = validate_plan(&epics);
+        assert!(problems.contains(&"ticket ref 'F2' is used more than once".to_string()));
+        assert!(problems.iter().any(|p| p.starts_with("tickets depend on each other in a cycle")));
+    }
+}
@@ -0,0 +1,319 @@
+//! Live presence on tickets and conversations
+//!
+//! Clients send a heartbeat every few seconds while a ticket or conversation is
+//! open; entries that miss heartbeats for `PRESENCE_TTL` are dropped. Presence is
+//! in-memory only — it describes who is here *now* and does not survive restarts.
+//!
+//! Pipeline step approve/reject consult presence (someone else editing the
+//! ticket) and take a per-step decision lock so two people cannot decide the
+//! same step at once.
+
+use axum::{
+    extract::{Extension, Query, State},
+    http::StatusCode,
+    response::sse::{Event, KeepAlive, Sse},
+    Json,
+};
+use chrono::{DateTime, Utc};
+use futures::stream::Stream;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::convert::Infallible;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{broadcast, RwLock};
+use ticketing_system::{conversations, tickets, SqlitePool, User};
+
+use crate::tenancy::Organization;
+
+/// Entries older than this are considered gone
+pub const PRESENCE_TTL: Duration = Duration::from_secs(30);
+
+const SWEEP_INTERVAL: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PresenceMode {
+    Viewing,
+    Editing,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PresenceEntry {
+    pub entity: String,
+    pub user_id: String,
+    pub name: String,
+    pub mode: PresenceMode,
+    pub last_seen: DateTime<Utc>,
+}
+
+impl PresenceEntry {
+    fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        now.signed_duration_since(self.last_seen).to_std().unwrap_or_default() > PRESENCE_TTL
+    }
+}
+
+/// Current presence for one entity, broadcast whenever it changes
+#[derive(Debug, Clone, Serialize)]
+pub struct PresenceSnapshot {
+    pub entity: String,
+    pub users: Vec<PresenceEntry>,
+}
+
+pub struct PresenceState {
+    /// entity -> user_id -> entry
+    entries: RwLock<HashMap<String, HashMap<String, PresenceEntry>>>,
+    changes: broadcast::Sender<PresenceSnapshot>,
+    /// Pipeline step decisions in flight ("ticket_id:step_id")
+    decisions: std::sync::Mutex<HashSet<String>>,
+}
+
+/// Held while a step decision is being written; releases the lock on drop
+pub struct DecisionGuard {
+    key: String,
+}
+
+impl Drop for DecisionGuard {
+    fn drop(&mut self) {
+        PRESENCE.decisions.lock().unwrap().remove(&self.key);
+    }
+}
+
+impl PresenceState {
+    fn new() -> Self {
+        let (changes, _) = broadcast::channel(256);
+        Self {
+            entries: RwLock::new(HashMap::new()),
+            changes,
+            decisions: std::sync::Mutex::new(HashSet::new()),
+        }
+    }
+
+    fn snapshot(entity: &str, users: Option<&HashMap<String, PresenceEntry>>) -> PresenceSnapshot {
+        let mut users: Vec<PresenceEntry> = users.map(|u| u.values().cloned().collect()).unwrap_or_default();
+        users.sort_by(|a, b| a.name.cmp(&b.name));
+        PresenceSnapshot { entity: entity.to_string(), users }
+    }
+
+    /// Record a heartbeat. Broadcasts only when the entity's membership or a mode changes.
+    pub async fn heartbeat(&self, entity: &str, user: &User, mode: PresenceMode) -> PresenceSnapshot {
+        let mut entries = self.entries.write().await;
+        let users = entries.entry(entity.to_string()).or_default();
+        let changed = users.get(&user.user_id).map(|e| e.mode != mode).unwrap_or(true);
+        users.insert(
+            user.user_id.clone(),
+            PresenceEntry {
+                entity: entity.to_string(),
+                user_id: user.user_id.clone(),
+                name: user.name.clone(),
+                mode,
+                last_seen: Utc::now(),
+            },
+        );
+        let snapshot = Self::snapshot(entity, Some(users));
+        if changed {
+            let _ = self.changes.send(snapshot.clone());
+        }
+        snapshot
+    }
+
+    pub async fn leave(&self, entity: &str, user_id: &str) {
+        let mut entries = self.entries.write().await;
+        let Some(users) = entries.get_mut(entity) else {
+            return;
+        };
+        if users.remove(user_id).is_some() {
+            let _ = self.changes.send(Self::snapshot(entity, Some(users)));
+        }
+        if users.is_empty() {
+            entries.remove(entity);
+        }
+    }
+
+    pub async fn list(&self, entity: &str) -> PresenceSnapshot {
+        let now = Utc::now();
+        let entries = self.entries.read().await;
+        let mut snapshot = Self::snapshot(entity, entries.get(entity));
+        snapshot.users.retain(|e| !e.is_expired(now));
+        snapshot
+    }
+
+    /// Someone other than `user_id` currently editing `entity`, if any
+    pub async fn other_editor(&self, entity: &str, user_id: &str) -> Option<PresenceEntry> {
+        self.list(entity)
+            .await
+            .users
+            .into_iter()
+            .find(|e| e.mode == PresenceMode::Editing && e.user_id != user_id)
+    }
+
+    /// Take the decision lock for a pipeline step, or `None` if another decision is in flight
+    pub fn lock_step_decision(&self, ticket_id: &str, step_id: &str) -> Option<DecisionGuard> {
+        let key = format!("{}:{}", ticket_id, step_id);
+        if self.decisions.lock().unwrap().insert(key.clone()) {
+            Some(DecisionGuard { key })
+        } else {
+            None
+        }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<PresenceSnapshot> {
+        self.changes.subscribe()
+    }
+
+    /// Drop expired entries and broadcast the affected entities
+    async fn sweep(&self) {
+        let now = Utc::now();
+        let mut entries = self.entries.write().await;
+        for (entity, users) in entries.iter_mut() {
+            let before = users.len();
+            users.retain(|_, e| !e.is_expired(now));
+            if users.len() != before {
+                let _ = self.changes.send(Self::snapshot(entity, Some(users)));
+            }
+        }
+        entries.retain(|_, users| !users.is_empty());
+    }
+}
+
+lazy_static::lazy_static! {
+    pub static ref PRESENCE: PresenceState = PresenceState::new();
+}
+
+/// Periodically expire stale presence entries
+pub fn start_presence_sweeper() {
+    tokio::spawn(async {
+        let mut interval = tokio::time::interval(SWEEP_INTERVAL);
+        loop {
+            interval.tick().await;
+            PRESENCE.sweep().await;
+        }
+    });
+}
+
+/// Presence entity key for a ticket
+pub fn ticket_entity(ticket_id: &str) -> String {
+    format!("ticket:{}", ticket_id)
+}
+
+fn validate_entity(entity: &str) -> Result<(), (StatusCode, String)> {
+    match entity.split_once(':') {
+        Some(("ticket" | "conversation", id)) if !id.is_empty() => Ok(()),
+        _ => Err((
+            StatusCode::BAD_REQUEST,
+            "entity must be ticket:<id> or conversation:<id>".to_string(),
+        )),
+    }
+}
+
+/// Check the caller may see the ticket or conversation an entity names.
+/// Conversations without an organization predate tenancy and stay open to all.
+async fn authorize_entity(pool: &SqlitePool, org: &Organization, entity: &str) -> Result<(), (StatusCode, String)> {
+    validate_entity(entity)?;
+    let owner = match entity.split_once(':') {
+        Some(("ticket", id)) => tickets::get_ticket_by_id(pool, id)
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+            .map(|t| Some(t.organization)),
+        Some((_, id)) => conversations::get_conversation(pool, id, false)
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+            .map(|c| c.organization),
+        None => None,
+    };
+    match owner {
+        Some(Some(owner)) => org.authorize(&owner),
+        Some(None) => Ok(()),
+        None => Err((StatusCode::NOT_FOUND, format!("{} not found", entity))),
+    }
+}
+
+// ============================================================================
+// HTTP Handlers
+// ============================================================================
+
+#[derive(Debug, Deserialize)]
+pub struct PresenceQuery {
+    pub entity: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct HeartbeatRequest {
+    pub entity: String,
+    #[serde(default = "default_mode")]
+    pub mode: PresenceMode,
+}
+
+fn default_mode() -> PresenceMode {
+    PresenceMode::Viewing
+}
+
+/// GET /api/presence?entity=ticket:<id>
+pub async fn get_presence(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(org): Extension<Organization>,
+    Query(query): Query<PresenceQuery>,
+) -> Result<Json<PresenceSnapshot>, (StatusCode, String)> {
+    authorize_entity(&pool, &org, &query.entity).await?;
+    Ok(Json(PRESENCE.list(&query.entity).await))
+}
+
+/// POST /api/presence/heartbeat
+pub async fn presence_heartbeat(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(user): Extension<User>,
+    Extension(org): Extension<Organization>,
+    Json(req): Json<HeartbeatRequest>,
+) -> Result<Json<PresenceSnapshot>, (StatusCode, String)> {
+    authorize_entity(&pool, &org, &req.entity).await?;
+    Ok(Json(PRESENCE.heartbeat(&req.entity, &user, req.mode).await))
+}
+
+/// POST /api/presence/leave
+pub async fn presence_leave(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(user): Extension<User>,
+    Extension(org): Extension<Organization>,
+    Json(req): Json<PresenceQuery>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    authorize_entity(&pool, &org, &req.entity).await?;
+    PRESENCE.leave(&req.entity, &user.user_id).await;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// GET /api/presence/stream?entity=ticket:<id> (SSE)
+pub async fn stream_presence(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(org): Extension<Organization>,
+    Query(query): Query<PresenceQuery>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, (StatusCode, String)> {
+    authorize_entity(&pool, &org, &query.entity).await?;
+    let entity = query.entity;
+    let mut rx = PRESENCE.subscribe();
+    let initial = PRESENCE.list(&entity).await;
+
+    let stream = async_stream::stream! {
+        if let Ok(json) = serde_json::to_string(&initial) {
+            yield Ok(Event::default().event("presence").data(json));
+        }
+        loop {
+            match rx.recv().await {
+                Ok(snapshot) if snapshot.entity == entity => {
+                    if let Ok(json) = serde_json::to_string(&snapshot) {
+                        yield Ok(Event::default().event("presence").data(json));
+                    }
+                }
+                Ok(_) => {}
+                Err(broadcast::error::RecvError::Lagged(_)) => {
+                    let snapshot = PRESENCE.list(&entity).await;
+                    if let Ok(json) = serde_json::to_string(&snapshot) {
+                        yield Ok(Event::default().event("presence").data(json));
+                    }
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            }
+        }
+    };
+
+    Ok(Sse::new(stream).keep_alive(KeepAlive::new().interval(Duration::from_secs(15)).text("ping")))
+}
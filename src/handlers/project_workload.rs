@@ -3,9 +3,9 @@
 use axum::{
     extract::{Path, State},
     http::StatusCode,
-    Json,
+    Extension, Json,
 };
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use sqlx::SqlitePool;
 use std::collections::HashMap;
 use std::path::PathBuf;
@@ -14,17 +14,19 @@ use std::sync::Arc;
 use cc_sdk::{query, ClaudeCodeOptions, Message, ContentBlock, ToolsConfig};
 use futures::StreamExt;
 
-use ticketing_system::WorkloadItem;
+use ticketing_system::{User, WorkloadCapacity, WorkloadItem};
 
 use crate::agents::types::AgentType;
 use crate::agents::prompts::load_prompt;
+use crate::tenancy::Organization;
 
 /// GET /api/project-workload
-/// Returns all unchecked workload items
+/// Returns the signed-in user's unchecked workload items
 pub async fn list_project_workload(
     State(db): State<Arc<SqlitePool>>,
+    Extension(user): Extension<User>,
 ) -> Result<Json<Vec<WorkloadItem>>, (StatusCode, String)> {
-    let items = ticketing_system::project_workload::list_workload(&db, false)
+    let items = ticketing_system::project_workload::list_user_workload(&db, &user.user_id, false)
         .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
@@ -34,27 +36,51 @@ pub async fn list_project_workload(
 #[derive(Deserialize)]
 pub struct PullRequest {
     pub organization: String,
+    /// Pull even if the user's WIP limit is already reached
+    #[serde(default)]
+    pub override_limit: bool,
 }
 
 /// POST /api/project-workload/pull
 /// Run pull-ticket agent, parse output, add to workload
 pub async fn pull_project_ticket(
     State(db): State<Arc<SqlitePool>>,
+    Extension(user): Extension<User>,
+    Extension(tenant): Extension<Organization>,
     Json(req): Json<PullRequest>,
 ) -> Result<Json<WorkloadItem>, (StatusCode, String)> {
+    tenant.authorize(&req.organization)?;
     let org = &req.organization;
 
-    // Get current workload for this org
-    let current = ticketing_system::project_workload::list_workload_ticket_ids(&db, org)
+    // Enforce WIP limit before spending an agent run
+    if !req.override_limit {
+        let summary = build_capacity_summary(&db, &user.user_id).await?;
+        if summary.over_limit {
+            return Err((
+                StatusCode::CONFLICT,
+                format!(
+                    "WIP limit reached ({} of {} open items). Pass override_limit to pull anyway.",
+                    summary.open_items,
+                    summary.wip_limit.unwrap_or_default()
+                ),
+            ));
+        }
+    }
+
+    // The user's own open workload in this org
+    let current: Vec<WorkloadItem> = ticketing_system::project_workload::list_user_workload(&db, &user.user_id, false)
         .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .into_iter()
+        .filter(|item| &item.organization == org)
+        .collect();
 
     let current_workload_str = if current.is_empty() {
         "(none)".to_string()
     } else {
         current
             .iter()
-            .map(|(tid, title)| format!("- {} — {}", tid, title))
+            .map(|item| format!("- {} — {}", item.ticket_id, item.ticket_title))
             .collect::<Vec<_>>()
             .join("\n")
     };
@@ -87,7 +113,8 @@ pub async fn pull_project_ticket(
         builder = builder.max_turns(turns);
     }
 
-    let options = builder.build();
+    let mut options = builder.build();
+    options.env.extend(crate::llm_keys::anthropic_env(&db, org).await);
 
     let prompt = format!("Select the best next ticket to work on for the {} organization.", org);
 
@@ -152,6 +179,7 @@ pub async fn pull_project_ticket(
     // Add to workload
     let item = ticketing_system::project_workload::add_to_workload(
         &db,
+        &user.user_id,
         org,
         &ticket.ticket_id,
         &ticket.title,
@@ -172,6 +200,16 @@ pub async fn pull_project_ticket(
     Ok(Json(item))
 }
 
+/// 404 unless the workload item exists and is the user's own
+async fn require_own_item(db: &SqlitePool, user: &User, id: &str) -> Result<(), (StatusCode, String)> {
+    ticketing_system::project_workload::get_workload_item(db, id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .filter(|item| item.user_id.as_deref() == Some(user.user_id.as_str()))
+        .map(|_| ())
+        .ok_or_else(|| (StatusCode::NOT_FOUND, "Workload item not found".to_string()))
+}
+
 #[derive(Deserialize)]
 pub struct ToggleWorkloadRequest {
     pub id: String,
@@ -180,8 +218,10 @@ pub struct ToggleWorkloadRequest {
 /// POST /api/project-workload/toggle
 pub async fn toggle_project_workload(
     State(db): State<Arc<SqlitePool>>,
+    Extension(user): Extension<User>,
     Json(req): Json<ToggleWorkloadRequest>,
 ) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    require_own_item(&db, &user, &req.id).await?;
     let checked = ticketing_system::project_workload::toggle_workload_item(&db, &req.id)
         .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
@@ -197,7 +237,9 @@ pub async fn toggle_project_workload(
 pub async fn remove_project_workload(
     Path(id): Path<String>,
     State(db): State<Arc<SqlitePool>>,
+    Extension(user): Extension<User>,
 ) -> Result<StatusCode, (StatusCode, String)> {
+    require_own_item(&db, &user, &id).await?;
     let deleted = ticketing_system::project_workload::remove_workload_item(&db, &id)
         .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
@@ -209,6 +251,96 @@ pub async fn remove_project_workload(
     }
 }
 
+// ============================================================================
+// Capacity
+// ============================================================================
+
+#[derive(Debug, Serialize)]
+pub struct CapacitySummary {
+    pub user_id: String,
+    pub wip_limit: Option<i64>,
+    pub hours_per_day: Option<f64>,
+    pub open_items: i64,
+    /// Open items per organization
+    pub by_organization: HashMap<String, i64>,
+    /// Slots left before the WIP limit; None when no limit is set
+    pub remaining_slots: Option<i64>,
+    pub over_limit: bool,
+}
+
+/// Summarize the user's own open workload, across their organizations,
+/// against their configured capacity. The WIP limit is per user.
+pub async fn build_capacity_summary(
+    db: &SqlitePool,
+    user_id: &str,
+) -> Result<CapacitySummary, (StatusCode, String)> {
+    let capacity = ticketing_system::project_workload::get_capacity(db, user_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let open = ticketing_system::project_workload::list_user_workload(db, user_id, false)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let mut by_organization: HashMap<String, i64> = HashMap::new();
+    for item in &open {
+        *by_organization.entry(item.organization.clone()).or_default() += 1;
+    }
+
+    let open_items = open.len() as i64;
+    let wip_limit = capacity.as_ref().and_then(|c| c.wip_limit);
+    let remaining_slots = wip_limit.map(|limit| (limit - open_items).max(0));
+
+    Ok(CapacitySummary {
+        user_id: user_id.to_string(),
+        wip_limit,
+        hours_per_day: capacity.as_ref().and_then(|c| c.hours_per_day),
+        open_items,
+        by_organization,
+        remaining_slots,
+        over_limit: wip_limit.map(|limit| open_items >= limit).unwrap_or(false),
+    })
+}
+
+/// GET /api/project-workload/capacity
+pub async fn get_workload_capacity(
+    State(db): State<Arc<SqlitePool>>,
+    Extension(user): Extension<User>,
+) -> Result<Json<CapacitySummary>, (StatusCode, String)> {
+    Ok(Json(build_capacity_summary(&db, &user.user_id).await?))
+}
+
+#[derive(Deserialize)]
+pub struct UpdateCapacityRequest {
+    pub wip_limit: Option<i64>,
+    pub hours_per_day: Option<f64>,
+}
+
+/// PUT /api/project-workload/capacity
+pub async fn update_workload_capacity(
+    State(db): State<Arc<SqlitePool>>,
+    Extension(user): Extension<User>,
+    Json(req): Json<UpdateCapacityRequest>,
+) -> Result<Json<WorkloadCapacity>, (StatusCode, String)> {
+    if req.wip_limit.map(|l| l < 1).unwrap_or(false) {
+        return Err((StatusCode::BAD_REQUEST, "wip_limit must be at least 1".to_string()));
+    }
+    if req.hours_per_day.map(|h| !(0.0..=24.0).contains(&h)).unwrap_or(false) {
+        return Err((StatusCode::BAD_REQUEST, "hours_per_day must be between 0 and 24".to_string()));
+    }
+
+    let capacity = ticketing_system::project_workload::upsert_capacity(
+        &db,
+        &user.user_id,
+        req.wip_limit,
+        req.hours_per_day,
+    )
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(Json(capacity))
+}
+
 /// Parse <selected_ticket>TICKET_ID</selected_ticket> from agent output
 fn parse_selected_ticket(text: &str) -> Option<String> {
     let start_tag = "<selected_ticket>";
@@ -0,0 +1,167 @@
+use axum::{
+    extract::{Extension, Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use ticketing_system::{research_docs, CreateResearchDocRequest, ResearchDoc, ResearchDocQuery, SqlitePool};
+
+use crate::embeddings::{self, SOURCE_RESEARCH_DOC};
+use crate::research_corpus::store_doc;
+use crate::tenancy::Organization;
+
+const DEFAULT_SEARCH_LIMIT: i64 = 20;
+
+#[derive(Debug, Deserialize)]
+pub struct ResearchDocSearchQuery {
+    /// Query over title and content
+    pub q: Option<String>,
+    /// "keyword" (default) or "semantic"
+    pub mode: Option<String>,
+    pub ticket_id: Option<String>,
+    pub epic_id: Option<String>,
+    pub topic: Option<String>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateResearchDocBody {
+    pub title: String,
+    pub content: String,
+    pub ticket_id: Option<String>,
+    pub epic_id: Option<String>,
+    pub topic: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    pub source_session_id: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ResearchDocListResponse {
+    pub docs: Vec<ResearchDoc>,
+    pub total: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct StoreResearchDocResponse {
+    pub doc: ResearchDoc,
+    /// False when identical content was already stored
+    pub created: bool,
+}
+
+/// Search or list research docs (GET /api/research-docs)
+pub async fn list_research_docs(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(org): Extension<Organization>,
+    Query(params): Query<ResearchDocSearchQuery>,
+) -> Result<Json<ResearchDocListResponse>, (StatusCode, String)> {
+    let limit = params.limit.unwrap_or(DEFAULT_SEARCH_LIMIT);
+
+    if params.mode.as_deref() == Some("semantic") {
+        let q = params
+            .q
+            .filter(|q| !q.trim().is_empty())
+            .ok_or_else(|| (StatusCode::BAD_REQUEST, "q is required for semantic search".to_string()))?;
+        let client = embeddings::client()
+            .ok_or_else(|| (StatusCode::SERVICE_UNAVAILABLE, "Semantic search is not configured".to_string()))?;
+        let organization = org.name.clone();
+        let hits = embeddings::search(&pool, client, &q, &organization, &[SOURCE_RESEARCH_DOC], limit.max(1) as usize)
+            .await
+            .map_err(|e| (StatusCode::BAD_GATEWAY, e.to_string()))?;
+
+        let mut docs = Vec::new();
+        for hit in hits {
+            if let Ok(Some(doc)) = research_docs::get_doc(&pool, &hit.source_id).await {
+                let matches_filters = params.ticket_id.as_ref().map_or(true, |t| doc.ticket_id.as_ref() == Some(t))
+                    && params.epic_id.as_ref().map_or(true, |e| doc.epic_id.as_ref() == Some(e))
+                    && params.topic.as_ref().map_or(true, |t| doc.topic.as_ref() == Some(t));
+                if matches_filters {
+                    docs.push(doc);
+                }
+            }
+        }
+        let total = docs.len();
+        return Ok(Json(ResearchDocListResponse { docs, total }));
+    }
+
+    let query = ResearchDocQuery {
+        organization: Some(org.name.clone()),
+        q: params.q.filter(|q| !q.trim().is_empty()),
+        ticket_id: params.ticket_id,
+        epic_id: params.epic_id,
+        topic: params.topic,
+        limit: Some(limit),
+    };
+    let docs = research_docs::search_docs(&pool, &query)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let total = docs.len();
+    Ok(Json(ResearchDocListResponse { docs, total }))
+}
+
+/// Store a research doc, deduplicating on content (POST /api/research-docs)
+pub async fn create_research_doc(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(org): Extension<Organization>,
+    Json(body): Json<CreateResearchDocBody>,
+) -> Result<(StatusCode, Json<StoreResearchDocResponse>), (StatusCode, String)> {
+    let organization = org.name.clone();
+    if body.title.trim().is_empty() || body.content.trim().is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "title and content must not be empty".to_string()));
+    }
+
+    let req = CreateResearchDocRequest {
+        organization,
+        ticket_id: body.ticket_id,
+        epic_id: body.epic_id,
+        topic: body.topic,
+        title: body.title.trim().to_string(),
+        content: body.content,
+        content_hash: String::new(),
+        tags: body.tags,
+        source_session_id: body.source_session_id,
+    };
+    let (doc, created) = store_doc(&pool, req)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let status = if created { StatusCode::CREATED } else { StatusCode::OK };
+    Ok((status, Json(StoreResearchDocResponse { doc, created })))
+}
+
+/// Load a research doc, checking the caller belongs to its organization
+async fn load_doc(pool: &SqlitePool, org: &Organization, doc_id: &str) -> Result<ResearchDoc, (StatusCode, String)> {
+    let doc = research_docs::get_doc(pool, doc_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or_else(|| (StatusCode::NOT_FOUND, "Research doc not found".to_string()))?;
+    org.authorize(&doc.organization)?;
+    Ok(doc)
+}
+
+/// Get a research doc (GET /api/research-docs/:doc_id)
+pub async fn get_research_doc(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(org): Extension<Organization>,
+    Path(doc_id): Path<String>,
+) -> Result<Json<ResearchDoc>, (StatusCode, String)> {
+    Ok(Json(load_doc(&pool, &org, &doc_id).await?))
+}
+
+/// Delete a research doc (DELETE /api/research-docs/:doc_id)
+pub async fn delete_research_doc(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(org): Extension<Organization>,
+    Path(doc_id): Path<String>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    load_doc(&pool, &org, &doc_id).await?;
+    let deleted = research_docs::delete_doc(&pool, &doc_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    if !deleted {
+        return Err((StatusCode::NOT_FOUND, "Research doc not found".to_string()));
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
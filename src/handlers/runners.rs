@@ -0,0 +1,153 @@
+//! Remote runner endpoints, see `crate::runners`
+//!
+//! Runners authenticate with `Authorization: Bearer <token>`, the value of
+//! their `token_env`, rather than a session.
+
+use axum::{
+    extract::{Extension, Path, Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::sync::Arc;
+use std::time::Duration;
+use ticketing_system::SqlitePool;
+
+use crate::runners::{RunOutcome, RunSummary, RunnerConfig, RUNNERS};
+use crate::tenancy::Organization;
+
+const DEFAULT_WAIT_SECS: u64 = 30;
+const MAX_WAIT_SECS: u64 = 60;
+const MAX_EVENTS_PER_REQUEST: usize = 500;
+
+/// The runner the request's bearer token belongs to
+fn authenticate(headers: &HeaderMap) -> Result<RunnerConfig, (StatusCode, String)> {
+    let token = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .map(str::trim)
+        .ok_or_else(|| (StatusCode::UNAUTHORIZED, "Runner token required".to_string()))?;
+    let config = crate::runners::load_config().map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("{:#}", e)))?;
+    config
+        .runner_for_token(token)
+        .cloned()
+        .ok_or_else(|| (StatusCode::UNAUTHORIZED, "Unknown runner token".to_string()))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RunnerQueueQuery {
+    /// Seconds to wait for a job when none is queued
+    pub wait: Option<u64>,
+}
+
+/// GET /api/runners/queue
+///
+/// Claims the oldest queued job the runner takes, waiting up to `wait`
+/// seconds for one. 204 when there is none.
+pub async fn claim_runner_job(
+    headers: HeaderMap,
+    Query(query): Query<RunnerQueueQuery>,
+) -> Result<Response, (StatusCode, String)> {
+    let runner = authenticate(&headers)?;
+    let wait = Duration::from_secs(query.wait.unwrap_or(DEFAULT_WAIT_SECS).min(MAX_WAIT_SECS));
+    match RUNNERS.claim(&runner, wait).await {
+        Some(job) => {
+            tracing::info!("Runner {} claimed run {} (step {} of ticket {})", runner.name, job.run_id, job.step_id, job.ticket_id);
+            Ok(Json(job).into_response())
+        }
+        None => Ok(StatusCode::NO_CONTENT.into_response()),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RunnerEventsRequest {
+    /// Stream events as the agent emitted them, each with a `type`
+    #[serde(default)]
+    pub events: Vec<Value>,
+    /// Sent with the last events, once the run has finished
+    #[serde(default)]
+    pub outcome: Option<RunOutcome>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RunnerEventsResponse {
+    pub stored: usize,
+}
+
+/// POST /api/runners/runs/:run_id/events
+///
+/// Stores the events with the run, where clients follow it as they do local
+/// runs; an empty batch keeps the claim alive. 404 means the run is no longer
+/// the runner's (finished, expired or the server restarted) and it should
+/// stop.
+pub async fn report_runner_events(
+    Path(run_id): Path<String>,
+    State(db): State<Arc<SqlitePool>>,
+    headers: HeaderMap,
+    Json(req): Json<RunnerEventsRequest>,
+) -> Result<Json<RunnerEventsResponse>, (StatusCode, String)> {
+    let runner = authenticate(&headers)?;
+    if req.events.len() > MAX_EVENTS_PER_REQUEST {
+        return Err((
+            StatusCode::PAYLOAD_TOO_LARGE,
+            format!("Send at most {} events per request", MAX_EVENTS_PER_REQUEST),
+        ));
+    }
+    let event_types = crate::runners::event_types(&req.events)
+        .map_err(|idx| (StatusCode::UNPROCESSABLE_ENTITY, format!("events[{}] has no type", idx)))?;
+
+    // Deltas go to live clients only, as with local runs
+    let stored: Vec<(&Value, &str)> = req
+        .events
+        .iter()
+        .zip(event_types)
+        .filter(|(_, event_type)| *event_type != "text_delta")
+        .collect();
+    let first_index = RUNNERS
+        .report(&run_id, &runner.name, stored.len(), req.outcome)
+        .await
+        .ok_or_else(|| (StatusCode::NOT_FOUND, "Run not found or not claimed by this runner".to_string()))?;
+
+    for (offset, (event, event_type)) in stored.iter().enumerate() {
+        let index = first_index + offset as i32;
+        if let Err(e) =
+            ticketing_system::agent_runs::store_event(&db, &run_id, index, event_type, &event.to_string()).await
+        {
+            tracing::warn!("Failed to store event #{} of remote run {}: {}", index, run_id, e);
+        }
+    }
+    if !stored.is_empty() {
+        let _ = crate::handlers::agent_runs::EVENTS_STORED.send(run_id.clone());
+    }
+    Ok(Json(RunnerEventsResponse { stored: stored.len() }))
+}
+
+#[derive(Debug, Serialize)]
+pub struct RunnerStatus {
+    pub name: String,
+    pub agent_types: Vec<String>,
+    /// Last time the runner asked for work since the server started
+    pub last_polled_at: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RunnersResponse {
+    pub runners: Vec<RunnerStatus>,
+    /// Queued and running remote runs, oldest first
+    pub runs: Vec<RunSummary>,
+}
+
+/// GET /api/runners (instance admins only)
+pub async fn list_runners(Extension(org): Extension<Organization>) -> Result<Json<RunnersResponse>, (StatusCode, String)> {
+    org.require_instance_admin()?;
+    let config = crate::runners::load_config().map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("{:#}", e)))?;
+    let mut runners = Vec::new();
+    for runner in config.runners {
+        let last_polled_at = RUNNERS.last_polled(&runner.name).await.map(|t| t.to_rfc3339());
+        runners.push(RunnerStatus { name: runner.name, agent_types: runner.agent_types, last_polled_at });
+    }
+    Ok(Json(RunnersResponse { runners, runs: RUNNERS.runs().await }))
+}
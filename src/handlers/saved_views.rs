@@ -0,0 +1,200 @@
+//! Saved ticket list views, see `crate::saved_views`
+
+use axum::{
+    extract::{Extension, Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use ticketing_system::{saved_views, CreateSavedViewRequest, SavedView, SqlitePool, User};
+
+use super::checklists::attach_checklist_progress;
+use super::tickets::ticket_list_response;
+use crate::deadlines::DueDateFilter;
+use crate::saved_views::{apply_view, validate_view, ViewDefinition};
+use crate::tenancy::Organization;
+
+#[derive(Debug, Deserialize)]
+pub struct SavedViewRequest {
+    pub name: String,
+    #[serde(flatten)]
+    pub definition: ViewDefinition,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SavedViewResponse {
+    pub view_id: String,
+    pub name: String,
+    pub organization: String,
+    #[serde(flatten)]
+    pub definition: ViewDefinition,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+impl SavedViewResponse {
+    fn new(view: SavedView, definition: ViewDefinition) -> Self {
+        Self {
+            view_id: view.view_id,
+            name: view.name,
+            organization: view.organization,
+            definition,
+            created_at: view.created_at,
+            updated_at: view.updated_at,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ViewTicketsQuery {
+    pub due_before: Option<chrono::NaiveDate>,
+    pub due_after: Option<chrono::NaiveDate>,
+    pub overdue: Option<bool>,
+    pub limit: Option<usize>,
+    #[serde(default)]
+    pub offset: usize,
+}
+
+fn definition_of(view: &SavedView) -> Result<ViewDefinition, (StatusCode, String)> {
+    serde_json::from_value(view.definition.clone())
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("View {} is unreadable: {}", view.view_id, e)))
+}
+
+fn check_request(req: &SavedViewRequest) -> Result<serde_json::Value, (StatusCode, String)> {
+    let problems = validate_view(&req.name, &req.definition);
+    if !problems.is_empty() {
+        return Err((StatusCode::BAD_REQUEST, problems.join("; ")));
+    }
+    serde_json::to_value(&req.definition).map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
+
+/// The user's own view in the current organization
+async fn load_view(pool: &SqlitePool, user: &User, org: &Organization, view_id: &str) -> Result<SavedView, (StatusCode, String)> {
+    saved_views::get_view(pool, view_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .filter(|view| view.user_id == user.user_id && view.organization == org.name)
+        .ok_or((StatusCode::NOT_FOUND, "View not found".to_string()))
+}
+
+/// List the user's views in the organization (GET /api/views)
+pub async fn list_views(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(user): Extension<User>,
+    Extension(org): Extension<Organization>,
+) -> Result<Json<Vec<SavedViewResponse>>, (StatusCode, String)> {
+    let views = saved_views::list_views(&pool, &user.user_id, &org.name)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let mut responses = Vec::with_capacity(views.len());
+    for view in views {
+        let definition = definition_of(&view)?;
+        responses.push(SavedViewResponse::new(view, definition));
+    }
+    Ok(Json(responses))
+}
+
+/// Create a view (POST /api/views)
+pub async fn create_view(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(user): Extension<User>,
+    Extension(org): Extension<Organization>,
+    Json(req): Json<SavedViewRequest>,
+) -> Result<(StatusCode, Json<SavedViewResponse>), (StatusCode, String)> {
+    let definition = check_request(&req)?;
+    let view = saved_views::create_view(
+        &pool,
+        CreateSavedViewRequest {
+            user_id: user.user_id.clone(),
+            organization: org.name.clone(),
+            name: req.name.trim().to_string(),
+            definition,
+        },
+    )
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Ok((StatusCode::CREATED, Json(SavedViewResponse::new(view, req.definition))))
+}
+
+/// Get a view (GET /api/views/:id)
+pub async fn get_view(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(user): Extension<User>,
+    Extension(org): Extension<Organization>,
+    Path(view_id): Path<String>,
+) -> Result<Json<SavedViewResponse>, (StatusCode, String)> {
+    let view = load_view(&pool, &user, &org, &view_id).await?;
+    let definition = definition_of(&view)?;
+    Ok(Json(SavedViewResponse::new(view, definition)))
+}
+
+/// Replace a view's name and definition (PUT /api/views/:id)
+pub async fn update_view(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(user): Extension<User>,
+    Extension(org): Extension<Organization>,
+    Path(view_id): Path<String>,
+    Json(req): Json<SavedViewRequest>,
+) -> Result<Json<SavedViewResponse>, (StatusCode, String)> {
+    load_view(&pool, &user, &org, &view_id).await?;
+    let definition = check_request(&req)?;
+    let view = saved_views::update_view(&pool, &view_id, req.name.trim(), &definition)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Ok(Json(SavedViewResponse::new(view, req.definition)))
+}
+
+/// Delete a view (DELETE /api/views/:id)
+pub async fn delete_view(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(user): Extension<User>,
+    Extension(org): Extension<Organization>,
+    Path(view_id): Path<String>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    load_view(&pool, &user, &org, &view_id).await?;
+    saved_views::delete_view(&pool, &view_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Run a view over the organization's tickets (GET /api/views/:id/tickets)
+///
+/// Takes the ticket list's due date filters and an optional `limit`/`offset`
+/// page; the total before paging is in the `X-Total-Count` header.
+pub async fn list_view_tickets(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(user): Extension<User>,
+    Extension(org): Extension<Organization>,
+    Path(view_id): Path<String>,
+    Query(params): Query<ViewTicketsQuery>,
+    headers: HeaderMap,
+) -> Result<Response, (StatusCode, String)> {
+    let view = load_view(&pool, &user, &org, &view_id).await?;
+    let definition = definition_of(&view)?;
+    let tickets = ticketing_system::tickets::list_tickets_by_organization(&pool, &org.name)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to list tickets: {}", e)))?;
+    let tickets = tickets
+        .into_iter()
+        .map(|t| serde_json::to_value(t).map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string())))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let due = DueDateFilter {
+        due_before: params.due_before,
+        due_after: params.due_after,
+        overdue: params.overdue,
+    };
+    let matching = apply_view(&definition, &due, tickets);
+    let total = matching.len();
+    let page: Vec<_> = matching
+        .into_iter()
+        .skip(params.offset)
+        .take(params.limit.unwrap_or(usize::MAX))
+        .collect();
+    let mut response = serde_json::Value::Array(page);
+    attach_checklist_progress(&pool, &mut response).await;
+    Ok(([("X-Total-Count", total.to_string())], ticket_list_response(&headers, response)).into_response())
+}
@@ -0,0 +1,214 @@
+use axum::{
+    extract::{Extension, Path, State},
+    http::StatusCode,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use ticketing_system::{
+    llm_keys as llm_key_store, secrets as secret_store, SqlitePool, UpsertLlmKeyRequest, UpsertSecretRequest,
+};
+
+use crate::agents::AgentType;
+use crate::llm_keys::{self, LlmProvider};
+use crate::secrets;
+use crate::tenancy::Organization;
+
+#[derive(Debug, Deserialize)]
+pub struct PutSecretRequest {
+    pub name: String,
+    pub value: String,
+    /// Agent types that receive this secret; empty means all agent types
+    #[serde(default)]
+    pub agent_types: Vec<String>,
+}
+
+/// Secret metadata. The value is write-only and never returned.
+#[derive(Debug, Serialize)]
+pub struct SecretSummary {
+    pub name: String,
+    pub agent_types: Vec<String>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+/// List secret names for the organization (GET /api/secrets)
+pub async fn list_secrets(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(org): Extension<Organization>,
+) -> Result<Json<Vec<SecretSummary>>, (StatusCode, String)> {
+    let organization = org.name;
+    let stored = secret_store::list_secrets(&pool, &organization)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(Json(
+        stored
+            .into_iter()
+            .map(|s| SecretSummary {
+                name: s.name,
+                agent_types: s.agent_types,
+                created_at: s.created_at,
+                updated_at: s.updated_at,
+            })
+            .collect(),
+    ))
+}
+
+/// Create or replace a secret (POST /api/secrets); organization admins only
+pub async fn put_secret(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(org): Extension<Organization>,
+    Json(req): Json<PutSecretRequest>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    org.require_admin(&org.name)?;
+    let organization = org.name;
+
+    secrets::validate_name(&req.name).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    if req.value.is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "Secret value must not be empty".to_string()));
+    }
+    for agent_type in &req.agent_types {
+        if serde_json::from_str::<AgentType>(&format!("\"{}\"", agent_type)).is_err() {
+            return Err((StatusCode::BAD_REQUEST, format!("Unknown agent type: {}", agent_type)));
+        }
+    }
+
+    let encrypted_value = secrets::encrypt(&req.value).map_err(|e| {
+        tracing::error!("Failed to encrypt secret {}: {}", req.name, e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Secrets store is not configured".to_string())
+    })?;
+
+    secret_store::upsert_secret(
+        &pool,
+        UpsertSecretRequest {
+            organization: organization.clone(),
+            name: req.name.clone(),
+            encrypted_value,
+            agent_types: req.agent_types,
+        },
+    )
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    tracing::info!("Stored secret {} for organization {}", req.name, organization);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Delete a secret (DELETE /api/secrets/:name); organization admins only
+pub async fn delete_secret(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(org): Extension<Organization>,
+    Path(name): Path<String>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    org.require_admin(&org.name)?;
+    let organization = org.name;
+    let deleted = secret_store::delete_secret(&pool, &organization, &name)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    if !deleted {
+        return Err((StatusCode::NOT_FOUND, "Secret not found".to_string()));
+    }
+    tracing::info!("Deleted secret {} for organization {}", name, organization);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// ============================================================================
+// LLM keys
+// ============================================================================
+
+/// An organization's key for one provider. Only the last characters are returned.
+#[derive(Debug, Serialize)]
+pub struct LlmKeySummary {
+    pub provider: String,
+    /// Whether the organization uses its own key rather than the shared one
+    pub configured: bool,
+    pub key_hint: Option<String>,
+    pub updated_at: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PutLlmKeyRequest {
+    pub api_key: String,
+}
+
+fn parse_provider(provider: &str) -> Result<LlmProvider, (StatusCode, String)> {
+    LlmProvider::parse(provider).map_err(|e| (StatusCode::BAD_REQUEST, e))
+}
+
+/// Which providers the organization has its own key for (GET /api/llm-keys)
+pub async fn list_llm_keys(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(org): Extension<Organization>,
+) -> Result<Json<Vec<LlmKeySummary>>, (StatusCode, String)> {
+    let stored = llm_key_store::list_keys(&pool, &org.name)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Ok(Json(
+        LlmProvider::ALL
+            .iter()
+            .map(|provider| {
+                let key = stored.iter().find(|k| k.provider == provider.as_str());
+                LlmKeySummary {
+                    provider: provider.as_str().to_string(),
+                    configured: key.is_some(),
+                    key_hint: key.map(|k| k.key_hint.clone()),
+                    updated_at: key.map(|k| k.updated_at.clone()),
+                }
+            })
+            .collect(),
+    ))
+}
+
+/// Set the organization's key for a provider (PUT /api/llm-keys/:provider);
+/// organization admins only
+pub async fn put_llm_key(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(org): Extension<Organization>,
+    Path(provider): Path<String>,
+    Json(req): Json<PutLlmKeyRequest>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    org.require_admin(&org.name)?;
+    let provider = parse_provider(&provider)?;
+    let api_key = req.api_key.trim();
+    provider.validate_key(api_key).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+
+    let encrypted_value = secrets::encrypt(api_key).map_err(|e| {
+        tracing::error!("Failed to encrypt {} key: {}", provider.as_str(), e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Secrets store is not configured".to_string())
+    })?;
+    llm_key_store::upsert_key(
+        &pool,
+        UpsertLlmKeyRequest {
+            organization: org.name.clone(),
+            provider: provider.as_str().to_string(),
+            encrypted_value,
+            key_hint: llm_keys::key_hint(api_key),
+        },
+    )
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    tracing::info!("Stored {} key for organization {}", provider.as_str(), org.name);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Go back to the shared key for a provider (DELETE /api/llm-keys/:provider);
+/// organization admins only
+pub async fn delete_llm_key(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(org): Extension<Organization>,
+    Path(provider): Path<String>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    org.require_admin(&org.name)?;
+    let provider = parse_provider(&provider)?;
+    let deleted = llm_key_store::delete_key(&pool, &org.name, provider.as_str())
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    if !deleted {
+        return Err((StatusCode::NOT_FOUND, format!("No {} key is set", provider.as_str())));
+    }
+    tracing::info!("Deleted {} key for organization {}", provider.as_str(), org.name);
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,78 @@
+use axum::{
+    extract::{Extension, Query, State},
+    http::StatusCode,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use ticketing_system::SqlitePool;
+
+use crate::embeddings::{self, SemanticHit, ALL_SOURCES};
+use crate::tenancy::Organization;
+
+const DEFAULT_LIMIT: usize = 10;
+const MAX_LIMIT: usize = 50;
+
+#[derive(Debug, Deserialize)]
+pub struct SemanticSearchQuery {
+    pub q: String,
+    /// Comma-separated source types (ticket, email, transcript, artifact, research_doc)
+    pub types: Option<String>,
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SemanticSearchResponse {
+    pub query: String,
+    pub results: Vec<SemanticHit>,
+}
+
+/// Parse a `types` filter, rejecting unknown source types
+fn parse_source_types(types: Option<&str>) -> Result<Vec<&'static str>, (StatusCode, String)> {
+    let Some(types) = types.filter(|t| !t.trim().is_empty()) else {
+        return Ok(ALL_SOURCES.to_vec());
+    };
+    types
+        .split(',')
+        .map(|t| {
+            let t = t.trim();
+            ALL_SOURCES
+                .iter()
+                .copied()
+                .find(|s| *s == t)
+                .ok_or_else(|| (StatusCode::BAD_REQUEST, format!("Unknown source type '{}'", t)))
+        })
+        .collect()
+}
+
+/// Semantic search across indexed content (GET /api/semantic-search?q=)
+pub async fn semantic_search(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(org): Extension<Organization>,
+    Query(params): Query<SemanticSearchQuery>,
+) -> Result<Json<SemanticSearchResponse>, (StatusCode, String)> {
+    let client = embeddings::client()
+        .ok_or_else(|| (StatusCode::SERVICE_UNAVAILABLE, "Semantic search is not configured".to_string()))?;
+    let query = params.q.trim();
+    if query.is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "q must not be empty".to_string()));
+    }
+    let source_types = parse_source_types(params.types.as_deref())?;
+    let organization = org.name;
+
+    let results = embeddings::search(
+        &pool,
+        client,
+        query,
+        &organization,
+        &source_types,
+        params.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT),
+    )
+    .await
+    .map_err(|e| (StatusCode::BAD_GATEWAY, e.to_string()))?;
+
+    Ok(Json(SemanticSearchResponse {
+        query: query.to_string(),
+        results,
+    }))
+}
@@ -0,0 +1,73 @@
+//! Shadow runs of dark-launched pipeline templates, see `crate::shadow_pipelines`
+
+use axum::{
+    extract::{Extension, Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+use ticketing_system::{pipelines, shadow_runs, tickets, ShadowRun, SqlitePool};
+
+use crate::tenancy::Organization;
+
+const DEFAULT_SHADOW_RUN_LIMIT: i64 = 50;
+const MAX_SHADOW_RUN_LIMIT: i64 = 500;
+
+#[derive(Debug, Deserialize)]
+pub struct ShadowRunsQuery {
+    pub limit: Option<i64>,
+}
+
+/// GET /api/tickets/:ticket_id/shadow-runs
+pub async fn list_ticket_shadow_runs(
+    State(pool): State<Arc<SqlitePool>>,
+    Path(ticket_id): Path<String>,
+) -> Result<Json<Vec<ShadowRun>>, (StatusCode, String)> {
+    let runs = shadow_runs::list_for_ticket(&pool, &ticket_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?;
+    Ok(Json(runs))
+}
+
+/// GET /api/pipeline-templates/:template_id/shadow-runs
+///
+/// Newest first, to compare a shadow template's runs across tickets. Only the
+/// organization owning the shadow sees its runs, and only on its own tickets.
+pub async fn list_template_shadow_runs(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(org): Extension<Organization>,
+    Path(template_id): Path<String>,
+    Query(params): Query<ShadowRunsQuery>,
+) -> Result<Json<Vec<ShadowRun>>, (StatusCode, String)> {
+    let template = pipelines::get_template(&pool, &template_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?
+        .ok_or_else(|| (StatusCode::NOT_FOUND, format!("Template not found: {}", template_id)))?;
+    let Some(owner) = template.organization else {
+        return Ok(Json(Vec::new()));
+    };
+    org.authorize(&owner)?;
+
+    let limit = params.limit.unwrap_or(DEFAULT_SHADOW_RUN_LIMIT).clamp(1, MAX_SHADOW_RUN_LIMIT);
+    let runs = shadow_runs::list_for_template(&pool, &template_id, limit)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?;
+
+    let mut ticket_organizations: HashMap<String, Option<String>> = HashMap::new();
+    let mut own = Vec::with_capacity(runs.len());
+    for run in runs {
+        if !ticket_organizations.contains_key(&run.ticket_id) {
+            let organization = tickets::get_ticket_by_id(&pool, &run.ticket_id)
+                .await
+                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?
+                .map(|t| t.organization);
+            ticket_organizations.insert(run.ticket_id.clone(), organization);
+        }
+        if ticket_organizations[&run.ticket_id].as_deref() == Some(owner.as_str()) {
+            own.push(run);
+        }
+    }
+    Ok(Json(own))
+}
@@ -0,0 +1,166 @@
+//! Likely duplicate and related tickets from the embeddings index, see
+//! `crate::embeddings`. Duplicates can be folded together with
+//! `POST /api/tickets/:ticket_id/merge`.
+
+use axum::{
+    extract::{Extension, Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use ticketing_system::{SqlitePool, Ticket};
+
+use crate::embeddings::{self, Similarity, DUPLICATE_SCORE, RELATED_SCORE};
+use crate::tenancy::Organization;
+
+const DEFAULT_LIMIT: usize = 10;
+const MAX_LIMIT: usize = 50;
+
+#[derive(Debug, Deserialize)]
+pub struct SimilarTicketsQuery {
+    /// Lowest similarity to include, from 0 to 1
+    pub min_score: Option<f32>,
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SimilarTicket {
+    pub ticket_id: String,
+    pub title: String,
+    pub status: String,
+    pub epic_id: String,
+    pub slice_id: String,
+    pub score: f32,
+    /// `duplicate` or `related`; `None` below the related threshold
+    pub similarity: Option<Similarity>,
+}
+
+impl SimilarTicket {
+    fn new(ticket: &Ticket, score: f32) -> Self {
+        Self {
+            ticket_id: ticket.ticket_id.clone(),
+            title: ticket.title.clone(),
+            status: ticket.status.clone(),
+            epic_id: ticket.epic_id.clone(),
+            slice_id: ticket.slice_id.clone(),
+            score,
+            similarity: Similarity::of(score),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct SimilarTicketsResponse {
+    pub ticket_id: String,
+    /// Whether the ticket has been embedded yet; without that there are no matches
+    pub indexed: bool,
+    pub results: Vec<SimilarTicket>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DuplicatePair {
+    pub ticket: SimilarTicket,
+    pub duplicate_of: SimilarTicket,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DuplicatesReport {
+    pub epic_id: String,
+    pub slice_id: String,
+    pub min_score: f32,
+    /// Slice tickets the indexer hasn't reached yet
+    pub unindexed: Vec<String>,
+    pub pairs: Vec<DuplicatePair>,
+}
+
+fn min_score(requested: Option<f32>, default: f32) -> Result<f32, (StatusCode, String)> {
+    match requested {
+        Some(score) if !(0.0..=1.0).contains(&score) => {
+            Err((StatusCode::BAD_REQUEST, "min_score must be between 0 and 1".to_string()))
+        }
+        Some(score) => Ok(score),
+        None => Ok(default),
+    }
+}
+
+async fn organization_tickets(pool: &SqlitePool, organization: &str) -> Result<HashMap<String, Ticket>, (StatusCode, String)> {
+    let tickets = ticketing_system::tickets::list_tickets_by_organization(pool, organization)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to list tickets: {}", e)))?;
+    Ok(tickets.into_iter().map(|t| (t.ticket_id.clone(), t)).collect())
+}
+
+async fn ticket_vectors(pool: &SqlitePool, organization: &str) -> Result<HashMap<String, Vec<Vec<f32>>>, (StatusCode, String)> {
+    embeddings::ticket_vectors(pool, organization)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to load embeddings: {}", e)))
+}
+
+/// Tickets similar to this one, best first (GET /api/tickets/:ticket_id/similar)
+pub async fn get_similar_tickets(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(org): Extension<Organization>,
+    Path(ticket_id): Path<String>,
+    Query(params): Query<SimilarTicketsQuery>,
+) -> Result<Json<SimilarTicketsResponse>, (StatusCode, String)> {
+    let min_score = min_score(params.min_score, RELATED_SCORE)?;
+    let ticket = ticketing_system::tickets::get_ticket_by_id(&pool, &ticket_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or((StatusCode::NOT_FOUND, "Ticket not found".to_string()))?;
+    org.authorize(&ticket.organization)?;
+
+    let vectors = ticket_vectors(&pool, &ticket.organization).await?;
+    let tickets = organization_tickets(&pool, &ticket.organization).await?;
+    let limit = params.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+    // Embeddings can outlive their ticket, so ask for a few spare
+    let results = embeddings::similar_to(&vectors, &ticket_id, min_score, limit * 2)
+        .into_iter()
+        .filter_map(|(id, score)| tickets.get(&id).map(|t| SimilarTicket::new(t, score)))
+        .take(limit)
+        .collect();
+
+    Ok(Json(SimilarTicketsResponse {
+        indexed: vectors.contains_key(&ticket_id),
+        ticket_id,
+        results,
+    }))
+}
+
+/// Likely duplicate pairs involving a slice's tickets, best first
+/// (GET /api/epics/:epic_id/slices/:slice_id/duplicates)
+///
+/// Each pair has a ticket from the slice and a similar ticket from anywhere
+/// in the organization.
+pub async fn get_slice_duplicates(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(org): Extension<Organization>,
+    Path((epic_id, slice_id)): Path<(String, String)>,
+    Query(params): Query<SimilarTicketsQuery>,
+) -> Result<Json<DuplicatesReport>, (StatusCode, String)> {
+    let min_score = min_score(params.min_score, DUPLICATE_SCORE)?;
+    let tickets = organization_tickets(&pool, &org.name).await?;
+    let mut slice_ticket_ids: Vec<String> = tickets
+        .values()
+        .filter(|t| t.epic_id == epic_id && t.slice_id == slice_id)
+        .map(|t| t.ticket_id.clone())
+        .collect();
+    slice_ticket_ids.sort();
+
+    let vectors = ticket_vectors(&pool, &org.name).await?;
+    let unindexed = slice_ticket_ids.iter().filter(|id| !vectors.contains_key(*id)).cloned().collect();
+    let pairs = embeddings::similar_pairs(&vectors, &slice_ticket_ids, min_score)
+        .into_iter()
+        .filter_map(|(id, other, score)| {
+            Some(DuplicatePair {
+                ticket: SimilarTicket::new(tickets.get(&id)?, score),
+                duplicate_of: SimilarTicket::new(tickets.get(&other)?, score),
+            })
+        })
+        .take(params.limit.unwrap_or(MAX_LIMIT).clamp(1, MAX_LIMIT))
+        .collect();
+
+    Ok(Json(DuplicatesReport { epic_id, slice_id, min_score, unindexed, pairs }))
+}
@@ -1,31 +1,104 @@
 use axum::{
-    extract::{Path, State},
-    http::{StatusCode, HeaderMap},
+    extract::{Extension, Path, State},
+    http::StatusCode,
     Json,
     response::{IntoResponse, Response},
 };
+use serde::Deserialize;
 use serde_json::json;
 use sqlx::SqlitePool;
+use std::collections::HashMap;
 use std::sync::Arc;
-use tracing::{error, info};
+use tracing::{error, info, warn};
+
+use ticketing_system::{pipelines, slice_settings, structure, SliceSettings};
 
 use crate::{
+    board::{self, BoardSettings, WipEnforcement},
     models::CreateSliceRequest,
     mcp_wrapper::call_mcp_tool,
+    tenancy::Organization,
+    template_sharing::template_source,
 };
 
-use super::get_organization;
+/// Pipeline template attached to new tickets when a slice has no override
+pub const DEFAULT_PIPELINE_TEMPLATE: &str = "human-task";
+
+#[derive(Debug, Deserialize)]
+pub struct ReorderSlicesRequest {
+    /// Slices in display order; slices left out sort after these
+    pub slice_ids: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateSliceSettingsRequest {
+    /// An empty string goes back to `DEFAULT_PIPELINE_TEMPLATE`
+    pub default_pipeline_template_id: Option<String>,
+    pub auto_run: Option<bool>,
+    /// Replaces the per-status WIP limits
+    pub wip_limits: Option<HashMap<String, u32>>,
+    /// "warn" or "block"
+    pub wip_enforcement: Option<String>,
+    /// Ticket field to split the board by; an empty string turns swimlanes off
+    pub swimlane_by: Option<String>,
+}
+
+/// Pipeline defaults applied to tickets created in a slice
+#[derive(Debug, Clone)]
+pub struct SlicePipelineDefaults {
+    pub template_id: String,
+    pub auto_run: bool,
+}
+
+/// Resolve the pipeline template (and auto-run flag) for new tickets in a slice,
+/// falling back to `DEFAULT_PIPELINE_TEMPLATE` when unset or on lookup failure.
+pub async fn slice_pipeline_defaults(
+    pool: &SqlitePool,
+    organization: &str,
+    epic_id: &str,
+    slice_id: &str,
+) -> SlicePipelineDefaults {
+    let settings = match slice_settings::get_slice_settings(pool, organization, epic_id, slice_id).await {
+        Ok(settings) => settings,
+        Err(e) => {
+            warn!("Failed to load settings for slice {}/{}: {:?}", epic_id, slice_id, e);
+            None
+        }
+    };
+
+    match settings {
+        Some(s) => SlicePipelineDefaults {
+            template_id: s
+                .default_pipeline_template_id
+                .unwrap_or_else(|| DEFAULT_PIPELINE_TEMPLATE.to_string()),
+            auto_run: s.auto_run,
+        },
+        None => SlicePipelineDefaults {
+            template_id: DEFAULT_PIPELINE_TEMPLATE.to_string(),
+            auto_run: false,
+        },
+    }
+}
 
 pub async fn list_slices(
-    State(_pool): State<Arc<SqlitePool>>,
-    headers: HeaderMap,
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(org): Extension<Organization>,
     Path(epic_id): Path<String>,
 ) -> Response {
-    let organization = get_organization(&headers);
+    let organization = org.name;
     let args = json!({ "organization": organization, "epic_id": epic_id });
 
     match call_mcp_tool("list_slices", Some(args)).await {
-        Ok(result) => {
+        Ok(mut result) => {
+            // One grouped query for every slice's ticket rollup
+            match ticketing_system::rollups::slice_rollups(&pool, &organization, &epic_id).await {
+                Ok(rollups) => super::epics::attach_progress(&mut result, "slices", "slice_id", rollups),
+                Err(e) => warn!("Failed to compute slice progress rollups: {:?}", e),
+            }
+            match structure::slice_positions(&pool, &organization, &epic_id).await {
+                Ok(positions) => super::epics::apply_order(&mut result, "slices", "slice_id", &positions),
+                Err(e) => warn!("Failed to load slice order: {:?}", e),
+            }
             (StatusCode::OK, Json(result)).into_response()
         }
         Err(e) => {
@@ -38,12 +111,52 @@ pub async fn list_slices(
     }
 }
 
+/// PUT /api/epics/:epic_id/slices/order - set the display order of an epic's slices
+pub async fn reorder_slices(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(org): Extension<Organization>,
+    Path(epic_id): Path<String>,
+    Json(request): Json<ReorderSlicesRequest>,
+) -> Response {
+    let organization = org.name;
+    let args = json!({ "organization": organization, "epic_id": epic_id });
+
+    let slices = match call_mcp_tool("list_slices", Some(args)).await {
+        Ok(result) => result,
+        Err(e) => {
+            error!("Failed to list slices: {:?}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({ "error": format!("Failed to list slices: {}", e) }))
+            ).into_response();
+        }
+    };
+    let known = super::epics::listed_ids(&slices, "slices", "slice_id");
+    if let Err(response) = super::epics::validate_order(&request.slice_ids, &known, "slice") {
+        return response;
+    }
+
+    match structure::set_slice_positions(&pool, &organization, &epic_id, &request.slice_ids).await {
+        Ok(()) => {
+            info!("Reordered {} slices in epic {}", request.slice_ids.len(), epic_id);
+            (StatusCode::OK, Json(json!({ "epic_id": epic_id, "slice_ids": request.slice_ids }))).into_response()
+        }
+        Err(e) => {
+            error!("Failed to reorder slices: {:?}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({ "error": format!("Failed to reorder slices: {}", e) }))
+            ).into_response()
+        }
+    }
+}
+
 pub async fn get_slice(
     State(_pool): State<Arc<SqlitePool>>,
-    headers: HeaderMap,
+    Extension(org): Extension<Organization>,
     Path((epic_id, slice_id)): Path<(String, String)>,
 ) -> Response {
-    let organization = get_organization(&headers);
+    let organization = org.name;
     let args = json!({
         "organization": organization,
         "epic_id": epic_id,
@@ -73,11 +186,11 @@ pub async fn get_slice(
 
 pub async fn create_slice(
     State(_pool): State<Arc<SqlitePool>>,
-    headers: HeaderMap,
+    Extension(org): Extension<Organization>,
     Path(epic_id): Path<String>,
     Json(request): Json<CreateSliceRequest>,
 ) -> Response {
-    let organization = get_organization(&headers);
+    let organization = org.name;
     let args = json!({
         "organization": organization,
         "slices": [{
@@ -110,10 +223,10 @@ pub async fn create_slice(
 
 pub async fn delete_slice(
     State(_pool): State<Arc<SqlitePool>>,
-    headers: HeaderMap,
+    Extension(org): Extension<Organization>,
     Path((epic_id, slice_id)): Path<(String, String)>,
 ) -> Response {
-    let organization = get_organization(&headers);
+    let organization = org.name;
     let args = json!({
         "organization": organization,
         "epic_id": epic_id,
@@ -140,4 +253,169 @@ pub async fn delete_slice(
             }
         }
     }
-}
\ No newline at end of file
+}
+
+/// GET /api/epics/:epic_id/slices/:slice_id/settings
+pub async fn get_slice_settings(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(org): Extension<Organization>,
+    Path((epic_id, slice_id)): Path<(String, String)>,
+) -> Response {
+    let organization = org.name;
+
+    match slice_settings::get_slice_settings(&pool, &organization, &epic_id, &slice_id).await {
+        Ok(Some(settings)) => (StatusCode::OK, Json(settings)).into_response(),
+        Ok(None) => {
+            (StatusCode::OK, Json(json!({
+                "organization": organization,
+                "epic_id": epic_id,
+                "slice_id": slice_id,
+                "default_pipeline_template_id": null,
+                "auto_run": false,
+                "wip_limits": {},
+                "wip_enforcement": WipEnforcement::default().as_str(),
+                "swimlane_by": null,
+            }))).into_response()
+        }
+        Err(e) => {
+            error!("Failed to get slice settings: {:?}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({ "error": format!("Failed to get slice settings: {}", e) }))
+            ).into_response()
+        }
+    }
+}
+
+/// PUT /api/epics/:epic_id/slices/:slice_id/settings
+pub async fn update_slice_settings(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(org): Extension<Organization>,
+    Path((epic_id, slice_id)): Path<(String, String)>,
+    Json(request): Json<UpdateSliceSettingsRequest>,
+) -> Response {
+    let organization = org.name;
+
+    let existing = match slice_settings::get_slice_settings(&pool, &organization, &epic_id, &slice_id).await {
+        Ok(existing) => existing,
+        Err(e) => {
+            error!("Failed to get slice settings: {:?}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({ "error": format!("Failed to get slice settings: {}", e) }))
+            ).into_response();
+        }
+    };
+
+    let template_id = match request.default_pipeline_template_id {
+        Some(template_id) if template_id.is_empty() => None,
+        Some(template_id) => Some(template_id),
+        None => existing.as_ref().and_then(|s| s.default_pipeline_template_id.clone()),
+    };
+
+    if let Some(template_id) = &template_id {
+        match pipelines::get_template(&pool, template_id).await {
+            Ok(Some(template))
+                if template_source(template.organization.as_deref(), template.shared, &organization).is_some() => {}
+            Ok(_) => {
+                return (
+                    StatusCode::BAD_REQUEST,
+                    Json(json!({ "error": format!("Pipeline template not found: {}", template_id) }))
+                ).into_response();
+            }
+            Err(e) => {
+                error!("Failed to look up pipeline template: {:?}", e);
+                return (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(json!({ "error": format!("Failed to look up template: {}", e) }))
+                ).into_response();
+            }
+        }
+    }
+
+    let wip_enforcement = match request.wip_enforcement.as_deref().map(WipEnforcement::parse).transpose() {
+        Ok(enforcement) => enforcement,
+        Err(e) => return (StatusCode::BAD_REQUEST, Json(json!({ "error": e }))).into_response(),
+    };
+    let wip_limits = request
+        .wip_limits
+        .or_else(|| existing.as_ref().and_then(|s| s.wip_limits.clone()));
+    let swimlane_by = match request.swimlane_by {
+        Some(field) if field.is_empty() => None,
+        Some(field) => Some(field),
+        None => existing.as_ref().and_then(|s| s.swimlane_by.clone()),
+    };
+    let problems = board::validate_board_settings(&wip_limits.clone().unwrap_or_default(), swimlane_by.as_deref());
+    if !problems.is_empty() {
+        return (
+            StatusCode::UNPROCESSABLE_ENTITY,
+            Json(json!({ "error": "Invalid board settings", "problems": problems }))
+        ).into_response();
+    }
+
+    let settings = SliceSettings {
+        organization,
+        epic_id,
+        slice_id,
+        default_pipeline_template_id: template_id,
+        auto_run: request
+            .auto_run
+            .or_else(|| existing.as_ref().map(|s| s.auto_run))
+            .unwrap_or(false),
+        wip_limits,
+        wip_enforcement: wip_enforcement
+            .map(|e| e.as_str().to_string())
+            .or_else(|| existing.as_ref().and_then(|s| s.wip_enforcement.clone())),
+        swimlane_by,
+        updated_at: chrono::Utc::now().to_rfc3339(),
+    };
+
+    match slice_settings::upsert_slice_settings(&pool, &settings).await {
+        Ok(()) => {
+            info!("Updated settings for slice {}/{}", settings.epic_id, settings.slice_id);
+            (StatusCode::OK, Json(settings)).into_response()
+        }
+        Err(e) => {
+            error!("Failed to update slice settings: {:?}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({ "error": format!("Failed to update slice settings: {}", e) }))
+            ).into_response()
+        }
+    }
+}
+
+/// Board settings for a slice, defaults when none are saved or on lookup failure
+pub async fn slice_board_settings(pool: &SqlitePool, organization: &str, epic_id: &str, slice_id: &str) -> BoardSettings {
+    match slice_settings::get_slice_settings(pool, organization, epic_id, slice_id).await {
+        Ok(settings) => BoardSettings::from_slice_settings(settings.as_ref()),
+        Err(e) => {
+            warn!("Failed to load settings for slice {}/{}: {:?}", epic_id, slice_id, e);
+            BoardSettings::default()
+        }
+    }
+}
+
+/// GET /api/epics/:epic_id/slices/:slice_id/board
+/// The slice's tickets by swimlane and status, with each column's WIP limit
+pub async fn get_slice_board(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(org): Extension<Organization>,
+    Path((epic_id, slice_id)): Path<(String, String)>,
+) -> Response {
+    let organization = org.name;
+    let tickets = match ticketing_system::tickets::list_tickets(&pool, &organization, &epic_id, &slice_id).await {
+        Ok(tickets) => tickets,
+        Err(e) => {
+            error!("Failed to list tickets for board: {:?}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({ "error": format!("Failed to list tickets: {}", e) }))
+            ).into_response();
+        }
+    };
+    let tickets = tickets.iter().filter_map(|t| serde_json::to_value(t).ok()).collect();
+    let settings = slice_board_settings(&pool, &organization, &epic_id, &slice_id).await;
+
+    (StatusCode::OK, Json(board::build_board(settings, tickets))).into_response()
+}
@@ -0,0 +1,356 @@
+//! Step library CRUD, see `crate::step_library`
+
+use axum::{
+    extract::{Extension, Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::sync::Arc;
+use ticketing_system::{
+    models::{LibraryStepRef, PipelineTemplate, PipelineTemplateStep},
+    pipelines, step_library, CreateLibraryStepRequest, LibraryStep, SqlitePool, UpdateLibraryStepRequest, User,
+};
+use tracing::info;
+
+use super::pipeline_templates::validate_template_steps;
+use crate::step_library::{expand_step, sample_step, StepDefinition};
+use crate::tenancy::Organization;
+
+#[derive(Debug, Deserialize)]
+pub struct LibraryStepBody {
+    /// Id templates reference the step by; only read on create
+    #[serde(default)]
+    pub library_step_id: String,
+    pub name: String,
+    pub description: Option<String>,
+    #[serde(flatten)]
+    pub definition: StepDefinition,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LibraryStepResponse {
+    pub library_step_id: String,
+    pub organization: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub version: u32,
+    #[serde(flatten)]
+    pub definition: StepDefinition,
+    pub updated_by: String,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+impl LibraryStepResponse {
+    fn new(step: LibraryStep) -> Result<Self, (StatusCode, String)> {
+        let definition = definition_of(&step)?;
+        Ok(Self {
+            library_step_id: step.library_step_id,
+            organization: step.organization,
+            name: step.name,
+            description: step.description,
+            version: step.version,
+            definition,
+            updated_by: step.updated_by,
+            created_at: step.created_at,
+            updated_at: step.updated_at,
+        })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LibraryStepQuery {
+    /// A past version; the latest by default
+    pub version: Option<u32>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PinnedTemplate {
+    pub template_id: String,
+    pub step_id: String,
+    pub version: u32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UpdateLibraryStepResponse {
+    pub step: LibraryStepResponse,
+    /// Templates re-expanded with the new version
+    pub updated_templates: Vec<String>,
+    /// Template steps pinned to an older version, left as they were
+    pub pinned: Vec<PinnedTemplate>,
+}
+
+fn definition_of(step: &LibraryStep) -> Result<StepDefinition, (StatusCode, String)> {
+    serde_json::from_value(step.definition.clone()).map_err(|e| {
+        (StatusCode::INTERNAL_SERVER_ERROR, format!("Library step {} is unreadable: {}", step.library_step_id, e))
+    })
+}
+
+fn check_body(library_step_id: &str, body: &LibraryStepBody) -> Result<Value, (StatusCode, String)> {
+    let mut problems = Vec::new();
+    if body.name.trim().is_empty() {
+        problems.push("name must not be empty".to_string());
+    }
+    problems.extend(body.definition.validate());
+    match serde_json::from_value::<PipelineTemplateStep>(Value::Object(sample_step(library_step_id, &body.definition))) {
+        Ok(step) => problems.extend(validate_template_steps(&[step])),
+        Err(e) => problems.push(e.to_string()),
+    }
+    if !problems.is_empty() {
+        return Err((StatusCode::UNPROCESSABLE_ENTITY, problems.join("; ")));
+    }
+    serde_json::to_value(&body.definition).map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
+
+/// Load a library step in the caller's organization. Ids are only unique
+/// within an organization.
+async fn load_step(
+    pool: &SqlitePool,
+    org: &Organization,
+    library_step_id: &str,
+    version: Option<u32>,
+) -> Result<LibraryStep, (StatusCode, String)> {
+    step_library::get_step(pool, &org.name, library_step_id, version)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or((StatusCode::NOT_FOUND, "Library step not found".to_string()))
+}
+
+fn uses(template: &PipelineTemplate, library_step_id: &str) -> bool {
+    template.steps.iter().any(|s| s.library_step.as_ref().is_some_and(|r| r.id == library_step_id))
+}
+
+/// Expand the `library_step` references in a template's steps (as JSON)
+/// from the organization's library. `candidate` stands in for the latest
+/// version of one step while an edit is checked. Returns the problems found;
+/// steps with a bad reference are left unexpanded.
+pub(crate) async fn expand_library_steps(
+    pool: &SqlitePool,
+    organization: Option<&str>,
+    steps: &mut [Value],
+    candidate: Option<(&str, &StepDefinition)>,
+) -> Result<Vec<String>, (StatusCode, String)> {
+    let mut problems = Vec::new();
+    for step in steps.iter_mut() {
+        let Some(fields) = step.as_object_mut() else { continue };
+        let Some(reference) = fields.get("library_step").cloned() else { continue };
+        let step_id = fields.get("step_id").and_then(Value::as_str).unwrap_or_default().to_string();
+        let reference: LibraryStepRef = match serde_json::from_value(reference) {
+            Ok(r) => r,
+            Err(e) => {
+                problems.push(format!("step '{}': library_step: {}", step_id, e));
+                continue;
+            }
+        };
+        let Some(organization) = organization else {
+            problems.push(format!("step '{}': templates using library steps must belong to an organization", step_id));
+            continue;
+        };
+
+        let definition = match candidate {
+            Some((id, definition)) if id == reference.id && reference.version.is_none() => definition.clone(),
+            _ => {
+                let library_step = step_library::get_step(pool, organization, &reference.id, reference.version)
+                    .await
+                    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+                match library_step {
+                    Some(library_step) => definition_of(&library_step)?,
+                    None => {
+                        let version = reference.version.map(|v| format!(" version {}", v)).unwrap_or_default();
+                        problems.push(format!("step '{}': unknown library step '{}'{}", step_id, reference.id, version));
+                        continue;
+                    }
+                }
+            }
+        };
+        problems.extend(
+            expand_step(fields, &definition, reference.inputs.as_ref())
+                .into_iter()
+                .map(|e| format!("step '{}': {}", step_id, e)),
+        );
+    }
+    Ok(problems)
+}
+
+/// List the organization's library steps at their latest versions (GET /api/step-library)
+pub async fn list_library_steps(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(org): Extension<Organization>,
+) -> Result<Json<Vec<LibraryStepResponse>>, (StatusCode, String)> {
+    let steps = step_library::list_steps(&pool, &org.name)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Ok(Json(steps.into_iter().map(LibraryStepResponse::new).collect::<Result<_, _>>()?))
+}
+
+/// Define a library step (POST /api/step-library)
+pub async fn create_library_step(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(user): Extension<User>,
+    Extension(org): Extension<Organization>,
+    Json(body): Json<LibraryStepBody>,
+) -> Result<(StatusCode, Json<LibraryStepResponse>), (StatusCode, String)> {
+    let library_step_id = body.library_step_id.trim().to_string();
+    if library_step_id.is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "library_step_id must not be empty".to_string()));
+    }
+    let definition = check_body(&library_step_id, &body)?;
+    let existing = step_library::get_step(&pool, &org.name, &library_step_id, None)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    if existing.is_some() {
+        return Err((StatusCode::CONFLICT, format!("Library step {} already exists", library_step_id)));
+    }
+
+    let step = step_library::create_step(
+        &pool,
+        &CreateLibraryStepRequest {
+            library_step_id,
+            organization: org.name,
+            name: body.name.trim().to_string(),
+            description: body.description,
+            definition,
+            created_by: user.user_id,
+        },
+    )
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Ok((StatusCode::CREATED, Json(LibraryStepResponse::new(step)?)))
+}
+
+/// Get a library step, optionally at a past version (GET /api/step-library/:library_step_id)
+pub async fn get_library_step(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(org): Extension<Organization>,
+    Path(library_step_id): Path<String>,
+    Query(params): Query<LibraryStepQuery>,
+) -> Result<Json<LibraryStepResponse>, (StatusCode, String)> {
+    let step = load_step(&pool, &org, &library_step_id, params.version).await?;
+    Ok(Json(LibraryStepResponse::new(step)?))
+}
+
+/// Every version of a library step, newest first (GET /api/step-library/:library_step_id/versions)
+pub async fn list_library_step_versions(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(org): Extension<Organization>,
+    Path(library_step_id): Path<String>,
+) -> Result<Json<Vec<LibraryStepResponse>>, (StatusCode, String)> {
+    load_step(&pool, &org, &library_step_id, None).await?;
+    let versions = step_library::list_versions(&pool, &org.name, &library_step_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Ok(Json(versions.into_iter().map(LibraryStepResponse::new).collect::<Result<_, _>>()?))
+}
+
+/// Save a new version of a library step and re-expand the templates that
+/// follow its latest version (PUT /api/step-library/:library_step_id)
+///
+/// The edit is refused if any of those templates would no longer be valid.
+/// The new version and the template rewrites are saved together, so templates
+/// never end up on a mix of versions.
+pub async fn update_library_step(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(user): Extension<User>,
+    Extension(org): Extension<Organization>,
+    Path(library_step_id): Path<String>,
+    Json(body): Json<LibraryStepBody>,
+) -> Result<Json<UpdateLibraryStepResponse>, (StatusCode, String)> {
+    load_step(&pool, &org, &library_step_id, None).await?;
+    let definition = check_body(&library_step_id, &body)?;
+
+    let templates = pipelines::list_templates(&pool, Some(&org.name), None, None)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let mut pinned = Vec::new();
+    let mut rewrites: Vec<(String, Vec<PipelineTemplateStep>)> = Vec::new();
+    let mut problems = Vec::new();
+    for template in templates.iter().filter(|t| uses(t, &library_step_id)) {
+        let mut follows_latest = false;
+        for step in &template.steps {
+            match step.library_step.as_ref().filter(|r| r.id == library_step_id).map(|r| r.version) {
+                Some(Some(version)) => pinned.push(PinnedTemplate {
+                    template_id: template.template_id.clone(),
+                    step_id: step.step_id.clone(),
+                    version,
+                }),
+                Some(None) => follows_latest = true,
+                None => {}
+            }
+        }
+        if !follows_latest {
+            continue;
+        }
+
+        let mut steps = template
+            .steps
+            .iter()
+            .map(serde_json::to_value)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        let mut template_problems =
+            expand_library_steps(&pool, template.organization.as_deref(), &mut steps, Some((&library_step_id, &body.definition))).await?;
+        match serde_json::from_value::<Vec<PipelineTemplateStep>>(Value::Array(steps)) {
+            Ok(steps) => {
+                template_problems.extend(validate_template_steps(&steps));
+                rewrites.push((template.template_id.clone(), steps));
+            }
+            Err(e) => template_problems.push(e.to_string()),
+        }
+        problems.extend(template_problems.into_iter().map(|p| format!("template '{}': {}", template.template_id, p)));
+    }
+    if !problems.is_empty() {
+        return Err((StatusCode::UNPROCESSABLE_ENTITY, problems.join("; ")));
+    }
+
+    let step = step_library::update_step_with_templates(
+        &pool,
+        &org.name,
+        &library_step_id,
+        &UpdateLibraryStepRequest {
+            name: body.name.trim().to_string(),
+            description: body.description,
+            definition,
+            updated_by: user.user_id,
+        },
+        &rewrites,
+    )
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let updated_templates: Vec<String> = rewrites.into_iter().map(|(template_id, _)| template_id).collect();
+    info!(
+        "Library step {} is now version {}, updated {} template(s)",
+        library_step_id,
+        step.version,
+        updated_templates.len()
+    );
+
+    Ok(Json(UpdateLibraryStepResponse { step: LibraryStepResponse::new(step)?, updated_templates, pinned }))
+}
+
+/// Delete a library step and its versions (DELETE /api/step-library/:library_step_id)
+///
+/// Refused while any template uses it.
+pub async fn delete_library_step(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(org): Extension<Organization>,
+    Path(library_step_id): Path<String>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    load_step(&pool, &org, &library_step_id, None).await?;
+    let templates = pipelines::list_templates(&pool, Some(&org.name), None, None)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let used_by: Vec<&str> = templates
+        .iter()
+        .filter(|t| uses(t, &library_step_id))
+        .map(|t| t.template_id.as_str())
+        .collect();
+    if !used_by.is_empty() {
+        return Err((StatusCode::CONFLICT, format!("Library step is used by templates: {}", used_by.join(", "))));
+    }
+    step_library::delete_step(&pool, &org.name, &library_step_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Ok(StatusCode::NO_CONTENT)
+}
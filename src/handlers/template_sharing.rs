@@ -0,0 +1,146 @@
+//! Sharing pipeline templates and per-organization overrides, see `crate::template_sharing`
+
+use axum::{
+    extract::{Extension, Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::Arc;
+use ticketing_system::{
+    models::PipelineTemplate, pipelines, template_overrides, SetTemplateOverridesRequest, SqlitePool, TemplateOverrides,
+    User,
+};
+
+use super::pipeline_templates::validate_step_inputs;
+use crate::template_sharing::template_source;
+use crate::tenancy::Organization;
+
+async fn load_template(pool: &SqlitePool, template_id: &str) -> Result<PipelineTemplate, (StatusCode, String)> {
+    pipelines::get_template(pool, template_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to get template: {}", e)))?
+        .ok_or_else(|| (StatusCode::NOT_FOUND, "Template not found".to_string()))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SetTemplateSharingRequest {
+    pub shared: bool,
+}
+
+/// Share a template with every organization, or stop sharing it
+/// (PUT /api/pipeline-templates/:template_id/sharing)
+///
+/// Only members of the owning organization may. Pipelines already attached
+/// from the template elsewhere keep running when sharing stops.
+pub async fn set_template_sharing(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(org): Extension<Organization>,
+    Path(template_id): Path<String>,
+    Json(req): Json<SetTemplateSharingRequest>,
+) -> Result<Json<PipelineTemplate>, (StatusCode, String)> {
+    let template = load_template(&pool, &template_id).await?;
+    let Some(owner) = &template.organization else {
+        return Err((StatusCode::BAD_REQUEST, "Templates without an organization are global already".to_string()));
+    };
+    org.authorize(owner)?;
+    pipelines::set_template_shared(&pool, &template_id, req.shared)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to update template: {}", e)))?;
+    let state = if req.shared { "shared" } else { "no longer shared" };
+    tracing::info!("Template {} of {} is {}", template_id, owner, state);
+    Ok(Json(PipelineTemplate { shared: req.shared, ..template }))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TemplateOverridesQuery {
+    pub organization: Option<String>,
+}
+
+/// The template and organization overrides are for, once the organization
+/// is authorized and may use the template
+async fn override_target(
+    pool: &SqlitePool,
+    org: Organization,
+    template_id: &str,
+    organization: Option<String>,
+) -> Result<(PipelineTemplate, String), (StatusCode, String)> {
+    let organization = org.resolve(organization)?;
+    let template = load_template(pool, template_id).await?;
+    if template_source(template.organization.as_deref(), template.shared, &organization).is_none() {
+        return Err((StatusCode::NOT_FOUND, "Template not found".to_string()));
+    }
+    Ok((template, organization))
+}
+
+/// The organization's step input overrides of a template
+/// (GET /api/pipeline-templates/:template_id/overrides)
+pub async fn get_template_overrides(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(org): Extension<Organization>,
+    Path(template_id): Path<String>,
+    Query(query): Query<TemplateOverridesQuery>,
+) -> Result<Json<TemplateOverrides>, (StatusCode, String)> {
+    let (_, organization) = override_target(&pool, org, &template_id, query.organization).await?;
+    template_overrides::get_overrides(&pool, &template_id, &organization)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?
+        .map(Json)
+        .ok_or_else(|| (StatusCode::NOT_FOUND, "No overrides for this template".to_string()))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PutTemplateOverridesRequest {
+    /// Inputs by step id, as `step_inputs` when attaching a pipeline
+    pub step_inputs: HashMap<String, Value>,
+}
+
+/// Replace the organization's step input overrides of a template
+/// (PUT /api/pipeline-templates/:template_id/overrides)
+pub async fn put_template_overrides(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(user): Extension<User>,
+    Extension(org): Extension<Organization>,
+    Path(template_id): Path<String>,
+    Query(query): Query<TemplateOverridesQuery>,
+    Json(req): Json<PutTemplateOverridesRequest>,
+) -> Result<Json<TemplateOverrides>, (StatusCode, String)> {
+    let (template, organization) = override_target(&pool, org, &template_id, query.organization).await?;
+    let problems = validate_step_inputs(&template.steps, &req.step_inputs);
+    if !problems.is_empty() {
+        return Err((StatusCode::UNPROCESSABLE_ENTITY, format!("Invalid overrides: {}", problems.join("; "))));
+    }
+    let overrides = template_overrides::set_overrides(
+        &pool,
+        &SetTemplateOverridesRequest {
+            template_id,
+            organization,
+            step_inputs: req.step_inputs,
+            updated_by: user.user_id,
+        },
+    )
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to save overrides: {}", e)))?;
+    Ok(Json(overrides))
+}
+
+/// Drop the organization's overrides of a template
+/// (DELETE /api/pipeline-templates/:template_id/overrides)
+pub async fn delete_template_overrides(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(org): Extension<Organization>,
+    Path(template_id): Path<String>,
+    Query(query): Query<TemplateOverridesQuery>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let organization = org.resolve(query.organization)?;
+    let deleted = template_overrides::delete_overrides(&pool, &template_id, &organization)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?;
+    if deleted {
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err((StatusCode::NOT_FOUND, "No overrides for this template".to_string()))
+    }
+}
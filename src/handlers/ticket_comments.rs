@@ -0,0 +1,93 @@
+//! Ticket comments, open to members and to guests the ticket is shared with
+
+use axum::{
+    extract::{Extension, Path, State},
+    http::StatusCode,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use ticketing_system::{comments, tickets, CreateNotificationRequest, GuestAccess, SqlitePool, TicketComment, User};
+
+use crate::tenancy::Organization;
+use crate::user_events::{self, Subject, UserEvent};
+
+const MAX_COMMENT_CHARS: usize = 10_000;
+
+#[derive(Debug, Deserialize)]
+pub struct CreateCommentRequest {
+    pub body: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CommentListResponse {
+    pub ticket_id: String,
+    pub comments: Vec<TicketComment>,
+}
+
+async fn authorize_ticket(pool: &SqlitePool, org: &Organization, ticket_id: &str) -> Result<(), (StatusCode, String)> {
+    let ticket = tickets::get_ticket_by_id(pool, ticket_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or_else(|| (StatusCode::NOT_FOUND, "Ticket not found".to_string()))?;
+    org.authorize(&ticket.organization)
+}
+
+/// GET /api/tickets/:ticket_id/comments (oldest first)
+pub async fn list_ticket_comments(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(org): Extension<Organization>,
+    Path(ticket_id): Path<String>,
+) -> Result<Json<CommentListResponse>, (StatusCode, String)> {
+    authorize_ticket(&pool, &org, &ticket_id).await?;
+    let comments = comments::list_comments(&pool, &ticket_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Ok(Json(CommentListResponse { ticket_id, comments }))
+}
+
+/// POST /api/tickets/:ticket_id/comments
+///
+/// Comments from guests also raise a notification, since the team may not be
+/// watching the ticket.
+pub async fn add_ticket_comment(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(user): Extension<User>,
+    Extension(org): Extension<Organization>,
+    guest: Option<Extension<GuestAccess>>,
+    Path(ticket_id): Path<String>,
+    Json(req): Json<CreateCommentRequest>,
+) -> Result<(StatusCode, Json<TicketComment>), (StatusCode, String)> {
+    let body = req.body.trim();
+    if body.is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "body must not be empty".to_string()));
+    }
+    if body.len() > MAX_COMMENT_CHARS {
+        return Err((StatusCode::BAD_REQUEST, format!("Comments are limited to {} characters", MAX_COMMENT_CHARS)));
+    }
+    authorize_ticket(&pool, &org, &ticket_id).await?;
+
+    let comment = comments::add_comment(&pool, &ticket_id, &user.user_id, &user.name, body)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    user_events::follow(&pool, &user.user_id, Subject::Ticket, &ticket_id).await;
+    let event = UserEvent::TicketComment { ticket_id: ticket_id.clone(), author: user.name.clone() };
+    user_events::publish(&pool, Subject::Ticket, &ticket_id, Some(&user.user_id), event).await;
+
+    if guest.is_some() {
+        crate::notifications::notify(
+            &pool,
+            CreateNotificationRequest {
+                kind: "guest_comment".to_string(),
+                title: format!("{} commented on {}", user.name, ticket_id),
+                body: Some(body.chars().take(280).collect()),
+                ticket_id: Some(ticket_id),
+                organization: Some(org.name),
+            },
+        )
+        .await;
+    }
+
+    Ok((StatusCode::CREATED, Json(comment)))
+}
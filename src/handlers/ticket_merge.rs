@@ -0,0 +1,573 @@
+//! Ticket merge and split
+//!
+//! Merge folds a duplicate ticket into a surviving one, moving its history,
+//! comments, relationships (blocking and typed), linked email threads and
+//! auto-links, drafts, imported external ids and agent runs. The duplicate is
+//! only cancelled once everything has moved; a merge that failed part way can
+//! be retried. Split carves child tickets out of a ticket's description
+//! sections, checklist items (or explicit items) and wires the parent as
+//! blocked by each child.
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+    response::{IntoResponse, Response},
+};
+use serde::Deserialize;
+use serde_json::json;
+use sqlx::SqlitePool;
+use std::sync::Arc;
+use tracing::{error, info, warn};
+
+use ticketing_system::{
+    checklists, drafts, email_auto_links, email_thread_tickets, imports, relationships, ticket_comments, tickets,
+    LinkThreadTicketRequest, Ticket,
+};
+
+use crate::mcp_wrapper::call_mcp_tool;
+
+use super::slices::slice_pipeline_defaults;
+use super::ticket_relationships::{set_blocked_by, Relation};
+
+// ============================================================================
+// Request Types
+// ============================================================================
+
+#[derive(Debug, Deserialize)]
+pub struct MergeTicketRequest {
+    /// Ticket to absorb; it is cancelled once merged
+    pub source_ticket_id: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SplitItem {
+    pub title: String,
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SplitTicketRequest {
+    /// Headings of description sections to split out (e.g. "## API changes" → "API changes")
+    #[serde(default)]
+    pub sections: Vec<String>,
+    /// Checklist items to turn into child tickets
+    #[serde(default)]
+    pub checklist_item_ids: Vec<i64>,
+    /// Additional child tickets not taken from the description
+    #[serde(default)]
+    pub items: Vec<SplitItem>,
+    /// Remove split sections and checklist items from the parent (default true)
+    pub remove_from_parent: Option<bool>,
+}
+
+// ============================================================================
+// Description Sections
+// ============================================================================
+
+/// A markdown heading section of a ticket description
+#[derive(Debug, Clone, PartialEq)]
+pub struct DescriptionSection {
+    /// 1 for `#`, 2 for `##`, ...
+    pub level: usize,
+    pub heading: String,
+    /// Everything up to the next section, including any deeper subsections
+    pub body: String,
+}
+
+/// The level and text of an ATX heading line (`## Title`)
+fn heading(line: &str) -> Option<(usize, &str)> {
+    let trimmed = line.trim_start();
+    let level = trimmed.chars().take_while(|c| *c == '#').count();
+    let rest = &trimmed[level..];
+    if !(1..=6).contains(&level) || !(rest.is_empty() || rest.starts_with(' ')) {
+        return None;
+    }
+    let text = rest.trim().trim_end_matches('#').trim();
+    (!text.is_empty()).then_some((level, text))
+}
+
+/// The level and text of every line that is a heading, `None` for other
+/// lines and anything inside a fenced code block
+fn line_headings(description: &str) -> Vec<Option<(usize, &str)>> {
+    let mut fence: Option<&str> = None;
+    description
+        .lines()
+        .map(|line| {
+            let trimmed = line.trim_start();
+            let marker = ["```", "~~~"].into_iter().find(|m| trimmed.starts_with(m));
+            match (fence, marker) {
+                (None, Some(marker)) => {
+                    fence = Some(marker);
+                    None
+                }
+                (Some(open), Some(marker)) if open == marker => {
+                    fence = None;
+                    None
+                }
+                (Some(_), _) => None,
+                (None, None) => heading(line),
+            }
+        })
+        .collect()
+}
+
+/// Split a markdown description into its sections at its top heading level;
+/// deeper headings stay in their section's body. Text before the first
+/// heading is returned separately as the preamble.
+pub fn parse_sections(description: &str) -> (String, Vec<DescriptionSection>) {
+    let mut preamble = Vec::new();
+    let mut sections: Vec<DescriptionSection> = Vec::new();
+
+    let headings = line_headings(description);
+    let top = headings.iter().flatten().map(|(level, _)| *level).min();
+    for (line, heading) in description.lines().zip(headings) {
+        if let Some((level, text)) = heading.filter(|(level, _)| Some(*level) == top) {
+            sections.push(DescriptionSection { level, heading: text.to_string(), body: String::new() });
+            continue;
+        }
+        match sections.last_mut() {
+            Some(section) => {
+                section.body.push_str(line);
+                section.body.push('\n');
+            }
+            None => preamble.push(line),
+        }
+    }
+
+    for section in &mut sections {
+        section.body = section.body.trim().to_string();
+    }
+    (preamble.join("\n").trim().to_string(), sections)
+}
+
+/// Reassemble a description from its preamble and remaining sections
+fn render_sections(preamble: &str, sections: &[DescriptionSection]) -> String {
+    let mut parts = Vec::new();
+    if !preamble.is_empty() {
+        parts.push(preamble.to_string());
+    }
+    for section in sections {
+        let heading = format!("{} {}", "#".repeat(section.level), section.heading);
+        if section.body.is_empty() {
+            parts.push(heading);
+        } else {
+            parts.push(format!("{}\n\n{}", heading, section.body));
+        }
+    }
+    parts.join("\n\n")
+}
+
+// ============================================================================
+// Helpers
+// ============================================================================
+
+/// Point the source's blocking relations at the target: the target is blocked
+/// by the source's blockers, and the source's dependents wait on the target
+async fn rewire_blocking(pool: &SqlitePool, source: &Ticket, target: &Ticket) -> anyhow::Result<()> {
+    let target_blockers: Vec<String> = target.blocked_by.clone().unwrap_or_default();
+    for blocker_id in source.blocked_by.iter().flatten() {
+        if blocker_id != &target.ticket_id && !target_blockers.contains(blocker_id) {
+            set_blocked_by(target, blocker_id, true).await?;
+        }
+    }
+
+    let all = tickets::list_tickets_by_organization(pool, &source.organization).await?;
+    for dependent in all.iter().filter(|t| {
+        t.ticket_id != target.ticket_id && t.blocked_by.as_ref().is_some_and(|b| b.contains(&source.ticket_id))
+    }) {
+        if !dependent.blocked_by.as_ref().is_some_and(|b| b.contains(&target.ticket_id)) {
+            set_blocked_by(dependent, &target.ticket_id, true).await?;
+        }
+        set_blocked_by(dependent, &source.ticket_id, false).await?;
+    }
+    if target_blockers.contains(&source.ticket_id) {
+        set_blocked_by(target, &source.ticket_id, false).await?;
+    }
+    Ok(())
+}
+
+/// Move the source's typed relationships (both directions) to the target,
+/// dropping any that would relate the target to itself. Returns how many moved.
+async fn rewire_relationships(pool: &SqlitePool, source: &Ticket, target: &Ticket) -> anyhow::Result<usize> {
+    let mut moved = 0;
+    for relationship in relationships::list_for_ticket(pool, &source.ticket_id).await? {
+        // Blocking lives in `blocked_by`, see `rewire_blocking`
+        if Relation::parse(&relationship.relation) == Some(Relation::Blocks) {
+            continue;
+        }
+        let (from, to) = if relationship.ticket_id == source.ticket_id {
+            (target.ticket_id.as_str(), relationship.target_ticket_id.as_str())
+        } else {
+            (relationship.ticket_id.as_str(), target.ticket_id.as_str())
+        };
+        if from != to {
+            relationships::add_relationship(pool, from, &relationship.relation, to).await?;
+            moved += 1;
+        }
+        relationships::remove_relationship(
+            pool,
+            &relationship.ticket_id,
+            &relationship.relation,
+            &relationship.target_ticket_id,
+        )
+        .await?;
+    }
+    Ok(moved)
+}
+
+async fn load_ticket(pool: &SqlitePool, ticket_id: &str) -> Result<Ticket, Response> {
+    match tickets::get_ticket_by_id(pool, ticket_id).await {
+        Ok(Some(ticket)) => Ok(ticket),
+        Ok(None) => Err((
+            StatusCode::NOT_FOUND,
+            Json(json!({ "error": format!("Ticket not found: {}", ticket_id) }))
+        ).into_response()),
+        Err(e) => {
+            error!("Failed to get ticket {}: {:?}", ticket_id, e);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({ "error": format!("Failed to get ticket: {}", e) }))
+            ).into_response())
+        }
+    }
+}
+
+// ============================================================================
+// Handlers
+// ============================================================================
+
+/// POST /api/tickets/:ticket_id/merge
+pub async fn merge_ticket(
+    State(pool): State<Arc<SqlitePool>>,
+    Path(ticket_id): Path<String>,
+    Json(request): Json<MergeTicketRequest>,
+) -> Response {
+    if request.source_ticket_id == ticket_id {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(json!({ "error": "Cannot merge a ticket into itself" }))
+        ).into_response();
+    }
+
+    let target = match load_ticket(&pool, &ticket_id).await {
+        Ok(t) => t,
+        Err(resp) => return resp,
+    };
+    let source = match load_ticket(&pool, &request.source_ticket_id).await {
+        Ok(t) => t,
+        Err(resp) => return resp,
+    };
+    if source.organization != target.organization {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(json!({ "error": "Tickets belong to different organizations" }))
+        ).into_response();
+    }
+
+    // Everything pointing at the source moves to the target. Failures are
+    // collected; the source is only retired once nothing is left behind.
+    let mut errors: Vec<String> = Vec::new();
+    let mut record = |what: &str, result: anyhow::Result<u64>| -> u64 {
+        result.unwrap_or_else(|e| {
+            error!("Failed to move {} from {}: {:?}", what, source.ticket_id, e);
+            errors.push(format!("{}: {:#}", what, e));
+            0
+        })
+    };
+    let moved_history = record(
+        "history",
+        ticketing_system::ticket_history::reassign_history(&pool, &source.ticket_id, &target.ticket_id)
+            .await
+            .map_err(Into::into),
+    );
+    let moved_runs = record(
+        "agent runs",
+        ticketing_system::agent_runs::reassign_agent_runs(&pool, &source.ticket_id, &target.ticket_id)
+            .await
+            .map_err(Into::into),
+    );
+    let moved_comments = record(
+        "comments",
+        ticket_comments::reassign_comments(&pool, &source.ticket_id, &target.ticket_id).await.map_err(Into::into),
+    );
+    let moved_drafts = record(
+        "drafts",
+        drafts::reassign_drafts(&pool, &source.ticket_id, &target.ticket_id).await.map_err(Into::into),
+    );
+    let moved_auto_links = record(
+        "email auto-links",
+        email_auto_links::reassign_links(&pool, &source.ticket_id, &target.ticket_id).await.map_err(Into::into),
+    );
+    let moved_external_ids = record(
+        "external ids",
+        imports::reassign_external_ids(&pool, &source.ticket_id, &target.ticket_id).await.map_err(Into::into),
+    );
+    let moved_threads = record("email threads", move_threads(&pool, &source, &target).await);
+    let moved_relationships = record(
+        "relationships",
+        rewire_relationships(&pool, &source, &target).await.map(|n| n as u64),
+    );
+    record("blocking relationships", rewire_blocking(&pool, &source, &target).await.map(|_| 0));
+
+    let moved = json!({
+        "history": moved_history,
+        "agent_runs": moved_runs,
+        "comments": moved_comments,
+        "drafts": moved_drafts,
+        "email_auto_links": moved_auto_links,
+        "external_ids": moved_external_ids,
+        "email_threads": moved_threads,
+        "relationships": moved_relationships,
+    });
+    if !errors.is_empty() {
+        return (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(json!({
+                "error": format!("Merge of {} into {} is incomplete; retry it to move the rest", source.ticket_id, target.ticket_id),
+                "errors": errors,
+                "moved": moved,
+            }))
+        ).into_response();
+    }
+
+    // Retire the source ticket
+    let args = json!({
+        "organization": source.organization,
+        "epic_id": source.epic_id,
+        "slice_id": source.slice_id,
+        "ticket_id": source.ticket_id,
+        "new_status": "cancelled"
+    });
+    if let Err(e) = call_mcp_tool("update_ticket_status", Some(args)).await {
+        error!("Failed to cancel merged ticket {}: {:?}", source.ticket_id, e);
+    }
+    let args = json!({
+        "organization": source.organization,
+        "epic_id": source.epic_id,
+        "slice_id": source.slice_id,
+        "ticket_id": source.ticket_id,
+        "notes": format!("Merged into {}", target.ticket_id)
+    });
+    if let Err(e) = call_mcp_tool("update_ticket_notes", Some(args)).await {
+        error!("Failed to annotate merged ticket {}: {:?}", source.ticket_id, e);
+    }
+
+    // Record on both tickets so either side's history explains the merge
+    for history_ticket_id in [&target.ticket_id, &source.ticket_id] {
+        if let Err(e) = ticketing_system::ticket_history::log_ticket_merged(
+            &pool,
+            history_ticket_id,
+            &source.ticket_id,
+            &target.ticket_id,
+        ).await {
+            warn!("Failed to log merge for {}: {:?}", history_ticket_id, e);
+        }
+    }
+
+    info!("Merged ticket {} into {} (moved: {})", source.ticket_id, target.ticket_id, moved);
+
+    (StatusCode::OK, Json(json!({
+        "ticket_id": target.ticket_id,
+        "merged_ticket_id": source.ticket_id,
+        "moved": moved,
+    }))).into_response()
+}
+
+/// Relink the source's email threads to the target
+async fn move_threads(pool: &SqlitePool, source: &Ticket, target: &Ticket) -> anyhow::Result<u64> {
+    let mut moved = 0;
+    for link in email_thread_tickets::get_threads_for_ticket(pool, &source.ticket_id).await? {
+        let req = LinkThreadTicketRequest {
+            thread_id: link.thread_id.clone(),
+            ticket_id: target.ticket_id.clone(),
+            epic_id: Some(target.epic_id.clone()),
+            slice_id: Some(target.slice_id.clone()),
+        };
+        email_thread_tickets::link_thread_to_ticket(pool, &req).await?;
+        email_thread_tickets::unlink_thread_from_ticket(pool, &link.thread_id, &source.ticket_id).await?;
+        moved += 1;
+    }
+    Ok(moved)
+}
+
+/// POST /api/tickets/:ticket_id/split
+pub async fn split_ticket(
+    State(pool): State<Arc<SqlitePool>>,
+    Path(ticket_id): Path<String>,
+    Json(request): Json<SplitTicketRequest>,
+) -> Response {
+    if request.sections.is_empty() && request.checklist_item_ids.is_empty() && request.items.is_empty() {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(json!({ "error": "Nothing to split: provide sections, checklist_item_ids or items" }))
+        ).into_response();
+    }
+
+    let parent = match load_ticket(&pool, &ticket_id).await {
+        Ok(t) => t,
+        Err(resp) => return resp,
+    };
+
+    let (preamble, sections) = parse_sections(parent.description.as_deref().unwrap_or(""));
+
+    // Resolve requested sections (case-insensitive heading match)
+    let mut children: Vec<(String, Option<String>)> = Vec::new();
+    let mut split_headings = Vec::new();
+    for wanted in &request.sections {
+        match sections.iter().find(|s| s.heading.eq_ignore_ascii_case(wanted.trim())) {
+            Some(section) => {
+                let body = (!section.body.is_empty()).then(|| section.body.clone());
+                children.push((section.heading.clone(), body));
+                split_headings.push(section.heading.clone());
+            }
+            None => {
+                return (
+                    StatusCode::BAD_REQUEST,
+                    Json(json!({ "error": format!("Description has no section '{}'", wanted) }))
+                ).into_response();
+            }
+        }
+    }
+
+    for item_id in &request.checklist_item_ids {
+        match checklists::get_item(&pool, &parent.ticket_id, *item_id).await {
+            Ok(Some(item)) => children.push((item.text, None)),
+            Ok(None) => {
+                return (
+                    StatusCode::BAD_REQUEST,
+                    Json(json!({ "error": format!("Checklist item not found: {}", item_id) }))
+                ).into_response();
+            }
+            Err(e) => {
+                error!("Failed to get checklist item {}: {:?}", item_id, e);
+                return (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(json!({ "error": format!("Failed to get checklist item: {}", e) }))
+                ).into_response();
+            }
+        }
+    }
+    children.extend(request.items.into_iter().map(|item| (item.title, item.description)));
+
+    let defaults = slice_pipeline_defaults(&pool, &parent.organization, &parent.epic_id, &parent.slice_id).await;
+    let specs: Vec<serde_json::Value> = children
+        .iter()
+        .enumerate()
+        .map(|(i, (title, description))| {
+            let mut spec = json!({
+                "ref": format!("split-{}", i),
+                "title": title,
+                "ticket_type": "milestone",
+                "pipeline_template_id": defaults.template_id,
+            });
+            if let Some(description) = description {
+                spec["description"] = json!(description);
+            }
+            if let Some(labels) = &parent.labels {
+                spec["labels"] = json!(labels);
+            }
+            spec
+        })
+        .collect();
+
+    let args = json!({
+        "organization": parent.organization,
+        "epic_id": parent.epic_id,
+        "slice_id": parent.slice_id,
+        "tickets": specs
+    });
+
+    let child_ids: Vec<String> = match call_mcp_tool("create_slice_tickets", Some(args)).await {
+        Ok(result) => result
+            .get("tickets")
+            .and_then(|t| t.as_array())
+            .map(|created| {
+                created
+                    .iter()
+                    .filter_map(|t| t.get("ticket").and_then(|t| t.get("ticket_id")).and_then(|id| id.as_str()))
+                    .map(|id| id.to_string())
+                    .collect()
+            })
+            .unwrap_or_default(),
+        Err(e) => {
+            error!("Failed to create split tickets: {:?}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({ "error": format!("Failed to create tickets: {}", e) }))
+            ).into_response();
+        }
+    };
+
+    // Parent can't finish until its children do
+    let mut errors = Vec::new();
+    for child_id in &child_ids {
+        if let Err(e) = set_blocked_by(&parent, child_id, true).await {
+            error!("Failed to block {} on {}: {:?}", parent.ticket_id, child_id, e);
+            errors.push(format!("blocked_by {}: {:#}", child_id, e));
+        }
+    }
+
+    let remove_from_parent = request.remove_from_parent.unwrap_or(true);
+    if remove_from_parent {
+        for item_id in &request.checklist_item_ids {
+            if let Err(e) = checklists::delete_item(&pool, &parent.ticket_id, *item_id).await {
+                error!("Failed to remove checklist item {} from {}: {:?}", item_id, parent.ticket_id, e);
+                errors.push(format!("checklist item {}: {}", item_id, e));
+            }
+        }
+    }
+
+    if remove_from_parent && !split_headings.is_empty() {
+        let remaining: Vec<DescriptionSection> = sections
+            .into_iter()
+            .filter(|s| !split_headings.contains(&s.heading))
+            .collect();
+        let description = render_sections(&preamble, &remaining);
+        if let Err(e) = tickets::update_ticket_description(&pool, &parent.ticket_id, Some(&description)).await {
+            error!("Failed to update description of {}: {:?}", parent.ticket_id, e);
+            errors.push(format!("description: {}", e));
+        }
+    }
+
+    if let Err(e) = ticketing_system::ticket_history::log_ticket_split(&pool, &parent.ticket_id, &child_ids).await {
+        warn!("Failed to log split for {}: {:?}", parent.ticket_id, e);
+    }
+
+    info!("Split ticket {} into {:?}", parent.ticket_id, child_ids);
+    (StatusCode::CREATED, Json(json!({
+        "ticket_id": parent.ticket_id,
+        "child_ticket_ids": child_ids,
+        "errors": errors,
+    }))).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_and_renders_sections() {
+        let description = "Intro line\n\n## API changes\nAdd endpoint\n\n## UI\nNew page\n";
+        let (preamble, sections) = parse_sections(description);
+        assert_eq!(preamble, "Intro line");
+        assert_eq!(sections.len(), 2);
+        assert_eq!(
+            sections[0],
+            DescriptionSection { level: 2, heading: "API changes".into(), body: "Add endpoint".into() }
+        );
+
+        let rendered = render_sections(&preamble, &sections[1..]);
+        assert_eq!(rendered, "Intro line\n\n## UI\n\nNew page");
+    }
+
+    #[test]
+    fn keeps_heading_levels_and_code_fences() {
+        let description = "# API\n### Errors\nReturn 404\n```sh\n# not a heading\n```\n# UI\n#hashtag\n";
+        let (_, sections) = parse_sections(description);
+        assert_eq!(sections.iter().map(|s| s.heading.as_str()).collect::<Vec<_>>(), ["API", "UI"]);
+        assert_eq!(sections[0].body, "### Errors\nReturn 404\n```sh\n# not a heading\n```");
+        assert_eq!(sections[1].body, "#hashtag");
+        assert_eq!(render_sections("", &sections[1..]), "# UI\n\n#hashtag");
+    }
+}
@@ -0,0 +1,391 @@
+//! Typed ticket relationships
+//!
+//! Relations are stored once, from the ticket that holds them: `A blocks B`,
+//! `A duplicates B`, `A relates-to B`, `A parent-of B`. Listing a ticket's
+//! relationships returns both directions with the other ticket resolved to a
+//! summary. Blocking and parent chains must stay acyclic.
+//!
+//! Blocking has a single source of truth: the blocked ticket's `blocked_by`
+//! list, which the MCP tools, plans, merges and agent context already use. So
+//! `A blocks B` is read from and written to B's `blocked_by`, never to the
+//! relationships table; `migrate_blocks_relations` moves `blocks` rows stored
+//! there before onto the blocked tickets.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use axum::{
+    extract::{Extension, Path, State},
+    http::StatusCode,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+use serde_json::json;
+use ticketing_system::{relationships, tickets, Ticket};
+
+use crate::mcp_wrapper::call_mcp_tool;
+use crate::tenancy::Organization;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Relation {
+    Blocks,
+    Duplicates,
+    RelatesTo,
+    ParentOf,
+}
+
+impl Relation {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Relation::Blocks => "blocks",
+            Relation::Duplicates => "duplicates",
+            Relation::RelatesTo => "relates-to",
+            Relation::ParentOf => "parent-of",
+        }
+    }
+
+    /// Accepts kebab or snake case (`relates_to`)
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().replace('_', "-").as_str() {
+            "blocks" => Some(Relation::Blocks),
+            "duplicates" => Some(Relation::Duplicates),
+            "relates-to" => Some(Relation::RelatesTo),
+            "parent-of" => Some(Relation::ParentOf),
+            _ => None,
+        }
+    }
+
+    /// How the relation reads from the other ticket's side
+    fn inverse_label(&self) -> &'static str {
+        match self {
+            Relation::Blocks => "blocked-by",
+            Relation::Duplicates => "duplicated-by",
+            Relation::RelatesTo => "relates-to",
+            Relation::ParentOf => "child-of",
+        }
+    }
+
+    /// Relations whose chains must not loop back on themselves
+    fn is_acyclic(&self) -> bool {
+        matches!(self, Relation::Blocks | Relation::ParentOf)
+    }
+}
+
+/// Whether adding `from -> to` to `edges` would close a loop, i.e. `to` already reaches `from`
+pub fn would_create_cycle(edges: &[(String, String)], from: &str, to: &str) -> bool {
+    let mut next: HashMap<&str, Vec<&str>> = HashMap::new();
+    for (source, target) in edges {
+        next.entry(source.as_str()).or_default().push(target.as_str());
+    }
+
+    let mut seen = HashSet::new();
+    let mut stack = vec![to];
+    while let Some(ticket_id) = stack.pop() {
+        if ticket_id == from {
+            return true;
+        }
+        if seen.insert(ticket_id) {
+            stack.extend(next.get(ticket_id).into_iter().flatten());
+        }
+    }
+    false
+}
+
+/// `(blocker, blocked)` pairs from the tickets' `blocked_by` lists
+pub fn blocking_edges(tickets: &[Ticket]) -> Vec<(String, String)> {
+    tickets
+        .iter()
+        .flat_map(|ticket| {
+            ticket
+                .blocked_by
+                .iter()
+                .flatten()
+                .map(|blocker| (blocker.clone(), ticket.ticket_id.clone()))
+        })
+        .collect()
+}
+
+fn is_blocked_by(ticket: &Ticket, blocker_id: &str) -> bool {
+    ticket.blocked_by.as_ref().is_some_and(|b| b.iter().any(|id| id == blocker_id))
+}
+
+/// Add or remove `blocker_id` on a ticket's `blocked_by`, through the MCP tool that owns it
+pub(crate) async fn set_blocked_by(ticket: &Ticket, blocker_id: &str, blocked: bool) -> anyhow::Result<()> {
+    let args = json!({
+        "organization": ticket.organization,
+        "epic_id": ticket.epic_id,
+        "slice_id": ticket.slice_id,
+        "ticket_id": ticket.ticket_id,
+        "relationship_type": "blocked_by",
+        "target_ticket_id": blocker_id
+    });
+    let tool = if blocked { "add_ticket_relationship" } else { "remove_ticket_relationship" };
+    call_mcp_tool(tool, Some(args)).await?;
+    Ok(())
+}
+
+/// Move `blocks` rows stored in the relationships table onto the blocked
+/// tickets' `blocked_by`. Run at startup; rows that fail to move stay for the
+/// next start.
+pub async fn migrate_blocks_relations(pool: &SqlitePool) {
+    let edges = match relationships::list_edges(pool, Relation::Blocks.as_str()).await {
+        Ok(edges) => edges,
+        Err(e) => {
+            tracing::error!("Failed to list blocks relations to migrate: {:?}", e);
+            return;
+        }
+    };
+    let mut migrated = 0;
+    for (blocker_id, blocked_id) in edges {
+        match tickets::get_ticket_by_id(pool, &blocked_id).await {
+            Ok(Some(ticket)) if !is_blocked_by(&ticket, &blocker_id) => {
+                if let Err(e) = set_blocked_by(&ticket, &blocker_id, true).await {
+                    tracing::warn!("Failed to migrate {} blocks {}: {:?}", blocker_id, blocked_id, e);
+                    continue;
+                }
+            }
+            Ok(_) => {}
+            Err(e) => {
+                tracing::warn!("Failed to load {} to migrate its blockers: {:?}", blocked_id, e);
+                continue;
+            }
+        }
+        match relationships::remove_relationship(pool, &blocker_id, Relation::Blocks.as_str(), &blocked_id).await {
+            Ok(_) => migrated += 1,
+            Err(e) => tracing::warn!("Failed to remove migrated relation {} blocks {}: {:?}", blocker_id, blocked_id, e),
+        }
+    }
+    if migrated > 0 {
+        tracing::info!("Moved {} blocks relation(s) onto blocked_by", migrated);
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct TicketSummary {
+    pub ticket_id: String,
+    pub title: String,
+    pub status: String,
+    pub organization: String,
+    pub epic_id: String,
+    pub slice_id: String,
+}
+
+impl From<Ticket> for TicketSummary {
+    fn from(ticket: Ticket) -> Self {
+        Self {
+            ticket_id: ticket.ticket_id,
+            title: ticket.title,
+            status: ticket.status,
+            organization: ticket.organization,
+            epic_id: ticket.epic_id,
+            slice_id: ticket.slice_id,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct RelationshipView {
+    pub relation: Relation,
+    /// `outgoing` when this ticket holds the relation, `incoming` otherwise
+    pub direction: &'static str,
+    /// The relation read from this ticket's side, e.g. `blocked-by`
+    pub label: &'static str,
+    pub ticket: TicketSummary,
+}
+
+impl RelationshipView {
+    fn new(relation: Relation, outgoing: bool, other: Ticket) -> Self {
+        Self {
+            relation,
+            direction: if outgoing { "outgoing" } else { "incoming" },
+            label: if outgoing { relation.as_str() } else { relation.inverse_label() },
+            ticket: other.into(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct RelationshipsResponse {
+    pub ticket_id: String,
+    pub relationships: Vec<RelationshipView>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AddRelationshipRequest {
+    pub relation: String,
+    pub target_ticket_id: String,
+}
+
+async fn load_ticket(pool: &SqlitePool, org: &Organization, ticket_id: &str) -> Result<Ticket, (StatusCode, String)> {
+    let ticket = tickets::get_ticket_by_id(pool, ticket_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or((StatusCode::NOT_FOUND, format!("Ticket {} not found", ticket_id)))?;
+    org.authorize(&ticket.organization)?;
+    Ok(ticket)
+}
+
+fn parse_relation(relation: &str) -> Result<Relation, (StatusCode, String)> {
+    Relation::parse(relation).ok_or_else(|| {
+        (
+            StatusCode::BAD_REQUEST,
+            format!("Unknown relation '{}'; expected blocks, duplicates, relates-to or parent-of", relation),
+        )
+    })
+}
+
+/// GET /api/tickets/:ticket_id/relationships
+pub async fn list_ticket_relationships(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(org): Extension<Organization>,
+    Path(ticket_id): Path<String>,
+) -> Result<Json<RelationshipsResponse>, (StatusCode, String)> {
+    let ticket = load_ticket(&pool, &org, &ticket_id).await?;
+    let org_tickets = tickets::list_tickets_by_organization(&pool, &ticket.organization)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let stored = relationships::list_for_ticket(&pool, &ticket_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    // Blocking both ways from `blocked_by`; blockers in other organizations are left out
+    let blockers = ticket.blocked_by.clone().unwrap_or_default();
+    let mut views = Vec::new();
+    for other in org_tickets {
+        if blockers.contains(&other.ticket_id) {
+            views.push(RelationshipView::new(Relation::Blocks, false, other.clone()));
+        }
+        if is_blocked_by(&other, &ticket_id) {
+            views.push(RelationshipView::new(Relation::Blocks, true, other));
+        }
+    }
+
+    for relationship in stored {
+        let Some(relation) = Relation::parse(&relationship.relation) else {
+            tracing::warn!("Skipping unknown relation '{}' on {}", relationship.relation, ticket_id);
+            continue;
+        };
+        // Not yet migrated onto `blocked_by`, which is listed above
+        if relation == Relation::Blocks {
+            continue;
+        }
+        let outgoing = relationship.ticket_id == ticket_id;
+        let other_id = if outgoing { &relationship.target_ticket_id } else { &relationship.ticket_id };
+        let other = match tickets::get_ticket_by_id(&pool, other_id).await {
+            Ok(Some(other)) if org.authorize(&other.organization).is_ok() => other,
+            Ok(_) => continue,
+            Err(e) => return Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
+        };
+        views.push(RelationshipView::new(relation, outgoing, other));
+    }
+
+    Ok(Json(RelationshipsResponse { ticket_id, relationships: views }))
+}
+
+/// POST /api/tickets/:ticket_id/relationships
+pub async fn add_ticket_relationship(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(org): Extension<Organization>,
+    Path(ticket_id): Path<String>,
+    Json(req): Json<AddRelationshipRequest>,
+) -> Result<(StatusCode, Json<RelationshipView>), (StatusCode, String)> {
+    let relation = parse_relation(&req.relation)?;
+    if req.target_ticket_id == ticket_id {
+        return Err((StatusCode::BAD_REQUEST, "A ticket cannot be related to itself".to_string()));
+    }
+    let ticket = load_ticket(&pool, &org, &ticket_id).await?;
+    let target = load_ticket(&pool, &org, &req.target_ticket_id).await?;
+
+    if relation.is_acyclic() {
+        let edges = match relation {
+            Relation::Blocks => {
+                let org_tickets = tickets::list_tickets_by_organization(&pool, &ticket.organization)
+                    .await
+                    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+                blocking_edges(&org_tickets)
+            }
+            _ => relationships::list_edges(&pool, relation.as_str())
+                .await
+                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?,
+        };
+        if would_create_cycle(&edges, &ticket_id, &target.ticket_id) {
+            return Err((
+                StatusCode::CONFLICT,
+                format!(
+                    "{} {} {} would create a cycle",
+                    ticket_id,
+                    relation.as_str(),
+                    target.ticket_id
+                ),
+            ));
+        }
+    }
+
+    match relation {
+        Relation::Blocks if is_blocked_by(&target, &ticket_id) => {}
+        Relation::Blocks => set_blocked_by(&target, &ticket_id, true)
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?,
+        _ => {
+            relationships::add_relationship(&pool, &ticket_id, relation.as_str(), &target.ticket_id)
+                .await
+                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        }
+    }
+    tracing::info!("Added relationship {} {} {}", ticket_id, relation.as_str(), target.ticket_id);
+
+    Ok((StatusCode::CREATED, Json(RelationshipView::new(relation, true, target))))
+}
+
+/// DELETE /api/tickets/:ticket_id/relationships/:relation/:target_ticket_id
+pub async fn remove_ticket_relationship(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(org): Extension<Organization>,
+    Path((ticket_id, relation, target_ticket_id)): Path<(String, String, String)>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let relation = parse_relation(&relation)?;
+    load_ticket(&pool, &org, &ticket_id).await?;
+
+    let removed = match relation {
+        Relation::Blocks => {
+            let target = load_ticket(&pool, &org, &target_ticket_id).await?;
+            let blocked = is_blocked_by(&target, &ticket_id);
+            if blocked {
+                set_blocked_by(&target, &ticket_id, false)
+                    .await
+                    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+            }
+            blocked
+        }
+        _ => relationships::remove_relationship(&pool, &ticket_id, relation.as_str(), &target_ticket_id)
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?,
+    };
+    if !removed {
+        return Err((StatusCode::NOT_FOUND, "Relationship not found".to_string()));
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn edge(a: &str, b: &str) -> (String, String) {
+        (a.to_string(), b.to_string())
+    }
+
+    #[test]
+    fn parses_relations_and_detects_cycles() {
+        assert_eq!(Relation::parse("relates_to"), Some(Relation::RelatesTo));
+        assert_eq!(Relation::parse("blocked_by"), None);
+
+        let edges = vec![edge("a", "b"), edge("b", "c")];
+        assert!(would_create_cycle(&edges, "c", "a"));
+        assert!(!would_create_cycle(&edges, "a", "c"));
+        assert!(!would_create_cycle(&edges, "d", "a"));
+    }
+}
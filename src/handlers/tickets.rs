@@ -1,6 +1,6 @@
 use axum::{
-    extract::{Path, Query, State},
-    http::{StatusCode, HeaderMap},
+    extract::{Extension, Path, Query, State},
+    http::{HeaderMap, StatusCode},
     Json,
     response::{IntoResponse, Response},
 };
@@ -8,30 +8,63 @@ use serde::Deserialize;
 use serde_json::json;
 use sqlx::SqlitePool;
 use std::sync::Arc;
-use tracing::{error, info};
+use tracing::{error, info, warn};
+
+use ticketing_system::models::{Pipeline, PipelineStepStatus};
+use ticketing_system::User;
 
 use crate::{
+    board::{self, WipEnforcement, WipViolation},
+    deadlines::DueDateFilter,
+    ndjson,
     models::{CreateTicketRequest, UpdateTicketRequest},
     mcp_wrapper::call_mcp_tool,
+    tenancy::Organization,
+    user_events::{self, Subject, UserEvent},
 };
 
-use super::get_organization;
+use super::checklists::attach_checklist_progress;
+use super::slices::{slice_board_settings, slice_pipeline_defaults};
 
 #[derive(Debug, Deserialize)]
 pub struct TicketQuery {
     pub slice_id: Option<String>,
+    pub due_before: Option<chrono::NaiveDate>,
+    pub due_after: Option<chrono::NaiveDate>,
+    pub overdue: Option<bool>,
+}
+
+/// Ticket list as JSON, or one ticket per line when the client accepts NDJSON
+pub(super) fn ticket_list_response(headers: &HeaderMap, mut response: serde_json::Value) -> Response {
+    if !ndjson::wants_ndjson(headers) {
+        return (StatusCode::OK, Json(response)).into_response();
+    }
+    let tickets = match &mut response {
+        serde_json::Value::Array(items) => std::mem::take(items),
+        serde_json::Value::Object(obj) => match obj.get_mut("tickets").and_then(|t| t.as_array_mut()) {
+            Some(items) => std::mem::take(items),
+            None => Vec::new(),
+        },
+        _ => Vec::new(),
+    };
+    ndjson::rows(tickets)
 }
 
 // List all tickets for an organization
 pub async fn list_all_tickets(
     State(pool): State<Arc<SqlitePool>>,
+    Extension(org): Extension<Organization>,
+    Query(due): Query<DueDateFilter>,
     headers: HeaderMap,
 ) -> Response {
-    let organization = get_organization(&headers);
+    let organization = org.name;
 
     match ticketing_system::tickets::list_tickets_by_organization(&pool, &organization).await {
         Ok(tickets) => {
-            (StatusCode::OK, Json(tickets)).into_response()
+            let mut response = json!(tickets);
+            due.apply(&mut response, chrono::Utc::now().date_naive());
+            attach_checklist_progress(&pool, &mut response).await;
+            ticket_list_response(&headers, response)
         }
         Err(e) => {
             error!("Failed to list all tickets: {:?}", e);
@@ -45,12 +78,18 @@ pub async fn list_all_tickets(
 
 // List tickets for an epic or a specific slice
 pub async fn list_tickets(
-    State(_pool): State<Arc<SqlitePool>>,
-    headers: HeaderMap,
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(org): Extension<Organization>,
     Path(epic_id): Path<String>,
     Query(params): Query<TicketQuery>,
+    headers: HeaderMap,
 ) -> Response {
-    let organization = get_organization(&headers);
+    let organization = org.name;
+    let due = DueDateFilter {
+        due_before: params.due_before,
+        due_after: params.due_after,
+        overdue: params.overdue,
+    };
     let args = if let Some(slice_id) = params.slice_id {
         json!({
             "organization": organization,
@@ -62,8 +101,10 @@ pub async fn list_tickets(
     };
 
     match call_mcp_tool("list_tickets", Some(args)).await {
-        Ok(result) => {
-            (StatusCode::OK, Json(result)).into_response()
+        Ok(mut result) => {
+            due.apply(&mut result, chrono::Utc::now().date_naive());
+            attach_checklist_progress(&pool, &mut result).await;
+            ticket_list_response(&headers, result)
         }
         Err(e) => {
             error!("Failed to list tickets: {:?}", e);
@@ -78,24 +119,32 @@ pub async fn list_tickets(
 // Convenience function for listing tickets specifically in a slice (used by route)
 pub async fn list_slice_tickets(
     State(pool): State<Arc<SqlitePool>>,
-    headers: HeaderMap,
+    org: Extension<Organization>,
     Path((epic_id, slice_id)): Path<(String, String)>,
+    Query(due): Query<DueDateFilter>,
+    headers: HeaderMap,
 ) -> Response {
     list_tickets(
         State(pool),
-        headers,
+        org,
         Path(epic_id),
-        Query(TicketQuery { slice_id: Some(slice_id) })
+        Query(TicketQuery {
+            slice_id: Some(slice_id),
+            due_before: due.due_before,
+            due_after: due.due_after,
+            overdue: due.overdue,
+        }),
+        headers,
     ).await
 }
 
 // Get ticket with full path (epic_id, slice_id, ticket_id)
 pub async fn get_ticket_nested(
     State(_pool): State<Arc<SqlitePool>>,
-    headers: HeaderMap,
+    Extension(org): Extension<Organization>,
     Path((epic_id, slice_id, ticket_id)): Path<(String, String, String)>,
 ) -> Response {
-    let organization = get_organization(&headers);
+    let organization = org.name;
     let args = json!({
         "organization": organization,
         "epic_id": epic_id,
@@ -125,12 +174,14 @@ pub async fn get_ticket_nested(
 }
 
 pub async fn create_ticket(
-    State(_pool): State<Arc<SqlitePool>>,
-    headers: HeaderMap,
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(user): Extension<User>,
+    Extension(org): Extension<Organization>,
     Path((epic_id, slice_id)): Path<(String, String)>,
     Json(request): Json<CreateTicketRequest>,
 ) -> Response {
-    let organization = get_organization(&headers);
+    let organization = org.name;
+    let defaults = slice_pipeline_defaults(&pool, &organization, &epic_id, &slice_id).await;
     let ref_handle = format!("api-{}", uuid::Uuid::new_v4().to_string().split('-').next().unwrap_or("0"));
     let args = json!({
         "organization": organization,
@@ -140,19 +191,40 @@ pub async fn create_ticket(
             "ref": ref_handle,
             "title": request.title,
             "ticket_type": "milestone",
-            "pipeline_template_id": "human-task",
+            "pipeline_template_id": defaults.template_id,
         }]
     });
 
     match call_mcp_tool("create_slice_tickets", Some(args)).await {
         Ok(result) => {
             // Extract first ticket from batch result for single-item response
-            let ticket = result.get("tickets")
+            let mut ticket = result.get("tickets")
                 .and_then(|t| t.get(0))
                 .and_then(|t| t.get("ticket"))
                 .cloned()
                 .unwrap_or(result);
             info!("Created ticket: {:?}", ticket);
+
+            if let Some(due_date) = request.due_date {
+                let due_date = due_date.to_string();
+                match ticket.get("ticket_id").and_then(|id| id.as_str()) {
+                    Some(ticket_id) => match ticketing_system::tickets::update_ticket_due_date(&pool, ticket_id, Some(&due_date)).await {
+                        Ok(()) => ticket["due_date"] = json!(due_date),
+                        Err(e) => error!("Failed to set due date on ticket {}: {:?}", ticket_id, e),
+                    },
+                    None => error!("Created ticket has no ticket_id, due date not set"),
+                }
+            }
+
+            if let Some(ticket_id) = ticket.get("ticket_id").and_then(|id| id.as_str()) {
+                user_events::follow(&pool, &user.user_id, Subject::Ticket, ticket_id).await;
+            }
+
+            if defaults.auto_run {
+                if let Some(ticket_id) = ticket.get("ticket_id").and_then(|id| id.as_str()) {
+                    auto_run_pipeline(&pool, ticket_id).await;
+                }
+            }
             (StatusCode::CREATED, Json(ticket)).into_response()
         }
         Err(e) => {
@@ -165,17 +237,77 @@ pub async fn create_ticket(
     }
 }
 
+/// Start the first step of a newly created ticket's pipeline (slice auto-run).
+/// Failures are logged; the ticket itself was already created.
+pub(crate) async fn auto_run_pipeline(pool: &SqlitePool, ticket_id: &str) {
+    let first_step_id = match ticketing_system::tickets::get_ticket_by_id(pool, ticket_id).await {
+        Ok(Some(ticket)) => ticket
+            .pipeline
+            .and_then(|p| p.steps.first().map(|s| s.step_id.clone())),
+        Ok(None) => None,
+        Err(e) => {
+            error!("Failed to load ticket {} for auto-run: {:?}", ticket_id, e);
+            return;
+        }
+    };
+
+    let Some(step_id) = first_step_id else {
+        return;
+    };
+
+    match crate::pipeline_automation::start_step_execution(pool, ticket_id, &step_id).await {
+        Ok(result) => {
+            info!("Auto-ran pipeline for ticket {}: {:?}", ticket_id, result);
+            crate::shadow_pipelines::start_shadow_runs(pool, ticket_id).await;
+        }
+        Err(e) => error!("Failed to auto-run pipeline for ticket {}: {:?}", ticket_id, e),
+    }
+}
+
+/// WIP limit the slice would break if the ticket moved to `status`. Lookup
+/// failures skip the check rather than blocking status changes.
+async fn check_wip_limit(
+    pool: &SqlitePool,
+    organization: &str,
+    epic_id: &str,
+    slice_id: &str,
+    ticket_id: &str,
+    status: &str,
+) -> Option<WipViolation> {
+    let settings = slice_board_settings(pool, organization, epic_id, slice_id).await;
+    if !settings.wip_limits.contains_key(status) {
+        return None;
+    }
+    let tickets = match ticketing_system::tickets::list_tickets(pool, organization, epic_id, slice_id).await {
+        Ok(tickets) => tickets,
+        Err(e) => {
+            warn!("Failed to list tickets for WIP check in {}/{}: {:?}", epic_id, slice_id, e);
+            return None;
+        }
+    };
+    let tickets: Vec<serde_json::Value> = tickets.iter().filter_map(|t| serde_json::to_value(t).ok()).collect();
+    board::check_transition(&settings, &tickets, ticket_id, status)
+}
+
 // Update ticket with full path (epic_id, slice_id, ticket_id)
 pub async fn update_ticket_nested(
-    State(_pool): State<Arc<SqlitePool>>,
-    headers: HeaderMap,
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(user): Extension<User>,
+    Extension(org): Extension<Organization>,
     Path((epic_id, slice_id, ticket_id)): Path<(String, String, String)>,
     Json(request): Json<UpdateTicketRequest>,
 ) -> Response {
-    let organization = get_organization(&headers);
+    let organization = org.name;
 
     // Determine which update operation to use based on what's being updated
     if let Some(status) = request.status {
+        let wip_violation = check_wip_limit(&pool, &organization, &epic_id, &slice_id, &ticket_id, &status).await;
+        if let Some(violation) = wip_violation.as_ref().filter(|v| v.enforcement == WipEnforcement::Block) {
+            return (
+                StatusCode::CONFLICT,
+                Json(json!({ "error": violation.message(), "wip_violation": violation }))
+            ).into_response();
+        }
         let args = json!({
             "organization": organization,
             "epic_id": epic_id,
@@ -185,8 +317,14 @@ pub async fn update_ticket_nested(
         });
 
         match call_mcp_tool("update_ticket_status", Some(args)).await {
-            Ok(result) => {
+            Ok(mut result) => {
                 info!("Updated ticket status: {:?}", result);
+                user_events::follow(&pool, &user.user_id, Subject::Ticket, &ticket_id).await;
+                let event = UserEvent::TicketStatus { ticket_id: ticket_id.clone(), organization, status };
+                user_events::publish(&pool, Subject::Ticket, &ticket_id, Some(&user.user_id), event).await;
+                if let (Some(violation), Some(obj)) = (wip_violation, result.as_object_mut()) {
+                    obj.insert("wip_warning".to_string(), json!({ "message": violation.message(), "violation": violation }));
+                }
                 (StatusCode::OK, Json(result)).into_response()
             }
             Err(e) => {
@@ -230,20 +368,21 @@ pub async fn update_ticket_nested(
 // Delete ticket with full path (epic_id, slice_id, ticket_id)
 pub async fn delete_ticket_nested(
     State(_pool): State<Arc<SqlitePool>>,
-    headers: HeaderMap,
+    Extension(org): Extension<Organization>,
     Path((epic_id, slice_id, ticket_id)): Path<(String, String, String)>,
 ) -> Response {
-    let organization = get_organization(&headers);
+    let organization = org.name;
     let args = json!({
         "organization": organization,
         "epic_id": epic_id,
         "slice_id": slice_id,
-        "ticket_id": ticket_id
+        "ticket_id": &ticket_id
     });
 
     match call_mcp_tool("delete_ticket", Some(args)).await {
         Ok(result) => {
             info!("Deleted ticket: {:?}", result);
+            crate::pipeline_read_model::invalidate(&ticket_id);
             (StatusCode::OK, Json(result)).into_response()
         }
         Err(e) => {
@@ -263,70 +402,6 @@ pub async fn delete_ticket_nested(
     }
 }
 
-// Add relationship with full path
-pub async fn add_relationship_nested(
-    State(_pool): State<Arc<SqlitePool>>,
-    headers: HeaderMap,
-    Path((epic_id, slice_id, ticket_id)): Path<(String, String, String)>,
-    Json(request): Json<serde_json::Value>,
-) -> Response {
-    let organization = get_organization(&headers);
-    let args = json!({
-        "organization": organization,
-        "epic_id": epic_id,
-        "slice_id": slice_id,
-        "ticket_id": ticket_id,
-        "relationship_type": request["relationship_type"],
-        "target_ticket_id": request["target_ticket_id"]
-    });
-
-    match call_mcp_tool("add_ticket_relationship", Some(args)).await {
-        Ok(result) => {
-            info!("Added ticket relationship: {:?}", result);
-            (StatusCode::CREATED, Json(result)).into_response()
-        }
-        Err(e) => {
-            error!("Failed to add relationship: {:?}", e);
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(json!({ "error": format!("Failed to add relationship: {}", e) }))
-            ).into_response()
-        }
-    }
-}
-
-// Remove relationship with full path
-pub async fn remove_relationship_nested(
-    State(_pool): State<Arc<SqlitePool>>,
-    headers: HeaderMap,
-    Path((epic_id, slice_id, ticket_id)): Path<(String, String, String)>,
-    Json(request): Json<serde_json::Value>,
-) -> Response {
-    let organization = get_organization(&headers);
-    let args = json!({
-        "organization": organization,
-        "epic_id": epic_id,
-        "slice_id": slice_id,
-        "ticket_id": ticket_id,
-        "relationship_type": request["relationship_type"],
-        "target_ticket_id": request["target_ticket_id"]
-    });
-
-    match call_mcp_tool("remove_ticket_relationship", Some(args)).await {
-        Ok(result) => {
-            info!("Removed ticket relationship: {:?}", result);
-            (StatusCode::OK, Json(result)).into_response()
-        }
-        Err(e) => {
-            error!("Failed to remove relationship: {:?}", e);
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(json!({ "error": format!("Failed to remove relationship: {}", e) }))
-            ).into_response()
-        }
-    }
-}
-
 // Get ticket by ID only (uses index lookup - ticket_id is globally unique)
 pub async fn get_ticket_by_id(
     State(_pool): State<Arc<SqlitePool>>,
@@ -412,3 +487,250 @@ pub async fn update_ticket_guidance(
         }
     }
 }
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateDueDateRequest {
+    /// YYYY-MM-DD, or null to clear
+    pub due_date: Option<chrono::NaiveDate>,
+}
+
+// Set or clear a ticket's due date by ID
+pub async fn update_ticket_due_date(
+    State(pool): State<Arc<SqlitePool>>,
+    Path(ticket_id): Path<String>,
+    Json(request): Json<UpdateDueDateRequest>,
+) -> Response {
+    let due_date = request.due_date.map(|d| d.to_string());
+    if let Err(e) = ticketing_system::tickets::update_ticket_due_date(&pool, &ticket_id, due_date.as_deref()).await {
+        error!("Failed to update ticket due date: {:?}", e);
+        return if e.to_string().contains("not found") {
+            (StatusCode::NOT_FOUND, Json(json!({ "error": "Ticket not found" }))).into_response()
+        } else {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({ "error": format!("Failed to update due date: {}", e) }))
+            ).into_response()
+        };
+    }
+
+    match ticketing_system::tickets::get_ticket_by_id(&pool, &ticket_id).await {
+        Ok(Some(ticket)) => {
+            info!("Updated due date for {}: {:?}", ticket_id, due_date);
+            (StatusCode::OK, Json(ticket)).into_response()
+        }
+        Ok(None) => (StatusCode::NOT_FOUND, Json(json!({ "error": "Ticket not found" }))).into_response(),
+        Err(e) => {
+            error!("Failed to fetch updated ticket: {:?}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({ "error": format!("Failed to fetch ticket: {}", e) }))
+            ).into_response()
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CloneTicketRequest {
+    /// Title for the clone, defaults to the source title
+    pub title: Option<String>,
+    /// Target epic/slice, defaults to the source ticket's location. A slice
+    /// belongs to one epic, so moving to another epic needs its `slice_id`.
+    pub epic_id: Option<String>,
+    pub slice_id: Option<String>,
+    pub copy_description: Option<bool>,
+    pub copy_guidance: Option<bool>,
+    pub copy_labels: Option<bool>,
+    pub copy_pipeline: Option<bool>,
+    pub copy_relationships: Option<bool>,
+}
+
+/// Copy a pipeline with every step reset to queued and run state cleared
+fn reset_pipeline(pipeline: &Pipeline) -> Pipeline {
+    let mut pipeline = pipeline.clone();
+    pipeline.status = None;
+    for step in pipeline.steps.iter_mut() {
+        step.status = PipelineStepStatus::Queued;
+        step.outputs = None;
+        step.agent_run_id = None;
+    }
+    pipeline
+}
+
+/// Make a clone blocked by the source's blockers and a blocker of its dependents
+async fn copy_blocking(pool: &SqlitePool, source: &ticketing_system::Ticket, clone_id: &str) -> anyhow::Result<()> {
+    let clone = ticketing_system::tickets::get_ticket_by_id(pool, clone_id)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("Clone {} not found", clone_id))?;
+    for blocker_id in source.blocked_by.iter().flatten() {
+        super::set_blocked_by(&clone, blocker_id, true).await?;
+    }
+    let org_tickets = ticketing_system::tickets::list_tickets_by_organization(pool, &source.organization).await?;
+    let dependents = org_tickets
+        .iter()
+        .filter(|t| t.blocked_by.as_ref().is_some_and(|b| b.contains(&source.ticket_id)));
+    for dependent in dependents {
+        super::set_blocked_by(dependent, clone_id, true).await?;
+    }
+    Ok(())
+}
+
+// Clone a ticket, optionally into a different epic/slice. Anything that
+// failed to copy onto the created clone is listed in its `copy_errors`.
+pub async fn clone_ticket(
+    State(pool): State<Arc<SqlitePool>>,
+    Path(ticket_id): Path<String>,
+    Json(request): Json<CloneTicketRequest>,
+) -> Response {
+    let source = match ticketing_system::tickets::get_ticket_by_id(&pool, &ticket_id).await {
+        Ok(Some(ticket)) => ticket,
+        Ok(None) => {
+            return (
+                StatusCode::NOT_FOUND,
+                Json(json!({ "error": "Ticket not found" }))
+            ).into_response();
+        }
+        Err(e) => {
+            error!("Failed to get ticket for clone: {:?}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({ "error": format!("Failed to get ticket: {}", e) }))
+            ).into_response();
+        }
+    };
+
+    let epic_id = request.epic_id.clone().unwrap_or_else(|| source.epic_id.clone());
+    if epic_id != source.epic_id && request.slice_id.is_none() {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(json!({ "error": "slice_id is required when cloning into another epic" }))
+        ).into_response();
+    }
+    let slice_id = request.slice_id.clone().unwrap_or_else(|| source.slice_id.clone());
+    let title = request.title.clone().unwrap_or_else(|| source.title.clone());
+
+    let mut spec = json!({
+        "ref": format!("clone-{}", uuid::Uuid::new_v4().to_string().split('-').next().unwrap_or("0")),
+        "title": title,
+        "ticket_type": "milestone",
+    });
+    if request.copy_description.unwrap_or(true) {
+        if let Some(description) = &source.description {
+            spec["description"] = json!(description);
+        }
+    }
+    if request.copy_labels.unwrap_or(true) {
+        if let Some(labels) = &source.labels {
+            spec["labels"] = json!(labels);
+        }
+    }
+    // Pipeline is copied from the source below; only fall back to the default template
+    let copy_pipeline = request.copy_pipeline.unwrap_or(true) && source.pipeline.is_some();
+    if !copy_pipeline {
+        let defaults = slice_pipeline_defaults(&pool, &source.organization, &epic_id, &slice_id).await;
+        spec["pipeline_template_id"] = json!(defaults.template_id);
+    }
+
+    let args = json!({
+        "organization": source.organization,
+        "epic_id": epic_id,
+        "slice_id": slice_id,
+        "tickets": [spec]
+    });
+
+    let new_ticket_id = match call_mcp_tool("create_slice_tickets", Some(args)).await {
+        Ok(result) => {
+            match result.get("tickets")
+                .and_then(|t| t.get(0))
+                .and_then(|t| t.get("ticket"))
+                .and_then(|t| t.get("ticket_id"))
+                .and_then(|id| id.as_str())
+            {
+                Some(id) => id.to_string(),
+                None => {
+                    error!("Clone create returned no ticket id: {:?}", result);
+                    return (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        Json(json!({ "error": "Failed to create cloned ticket" }))
+                    ).into_response();
+                }
+            }
+        }
+        Err(e) => {
+            error!("Failed to create cloned ticket: {:?}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({ "error": format!("Failed to create ticket: {}", e) }))
+            ).into_response();
+        }
+    };
+
+    // The clone exists from here on; what fails to copy is reported with it
+    let mut copy_errors = Vec::new();
+
+    if request.copy_guidance.unwrap_or(true) {
+        if let Some(guidance) = &source.guidance {
+            if let Err(e) = ticketing_system::tickets::update_ticket_guidance(&pool, &new_ticket_id, Some(guidance)).await {
+                error!("Failed to copy guidance to {}: {:?}", new_ticket_id, e);
+                copy_errors.push(format!("guidance: {}", e));
+            }
+        }
+    }
+
+    if copy_pipeline {
+        if let Some(pipeline) = &source.pipeline {
+            let pipeline = reset_pipeline(pipeline);
+            if let Err(e) = ticketing_system::tickets::update_ticket_pipeline(&pool, &new_ticket_id, Some(&pipeline)).await {
+                error!("Failed to copy pipeline to {}: {:?}", new_ticket_id, e);
+                copy_errors.push(format!("pipeline: {}", e));
+            }
+        }
+    }
+
+    if request.copy_relationships.unwrap_or(false) {
+        match ticketing_system::relationships::list_for_ticket(&pool, &ticket_id).await {
+            Ok(stored) => {
+                // Every relation type, in the same direction as on the source
+                for relationship in stored {
+                    let (from, to) = if relationship.ticket_id == ticket_id {
+                        (new_ticket_id.as_str(), relationship.target_ticket_id.as_str())
+                    } else {
+                        (relationship.ticket_id.as_str(), new_ticket_id.as_str())
+                    };
+                    if let Err(e) = ticketing_system::relationships::add_relationship(&pool, from, &relationship.relation, to).await {
+                        error!("Failed to copy relationship {} {} {}: {:?}", from, relationship.relation, to, e);
+                        copy_errors.push(format!("relationship {} {} {}: {}", from, relationship.relation, to, e));
+                    }
+                }
+            }
+            Err(e) => {
+                error!("Failed to list relationships of {}: {:?}", ticket_id, e);
+                copy_errors.push(format!("relationships: {}", e));
+            }
+        }
+        // Blocking lives in `blocked_by`: blocked by the same tickets, and blocking the same ones
+        if let Err(e) = copy_blocking(&pool, &source, &new_ticket_id).await {
+            error!("Failed to copy blockers of {}: {}", ticket_id, e);
+            copy_errors.push(format!("blocked_by: {}", e));
+        }
+    }
+
+    match ticketing_system::tickets::get_ticket_by_id(&pool, &new_ticket_id).await {
+        Ok(Some(ticket)) => {
+            info!("Cloned ticket {} -> {}", ticket_id, new_ticket_id);
+            let mut body = json!(ticket);
+            body["copy_errors"] = json!(copy_errors);
+            (StatusCode::CREATED, Json(body)).into_response()
+        }
+        Ok(None) => (
+            StatusCode::NOT_FOUND,
+            Json(json!({ "error": "Cloned ticket not found" }))
+        ).into_response(),
+        Err(e) => {
+            error!("Failed to fetch cloned ticket: {:?}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({ "error": format!("Failed to fetch ticket: {}", e) }))
+            ).into_response()
+        }
+    }
+}
@@ -0,0 +1,51 @@
+//! Tool audit trail of agent runs, see `crate::agents::tool_audit`
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use serde::Deserialize;
+use std::sync::Arc;
+use ticketing_system::{tool_audit, SqlitePool, ToolAuditEntry, ToolUsageStats};
+
+use crate::tenancy::Organization;
+
+const DEFAULT_STATS_DAYS: i64 = 7;
+const MAX_STATS_DAYS: i64 = 90;
+
+#[derive(Debug, Deserialize)]
+pub struct ToolStatsQuery {
+    pub days: Option<i64>,
+    pub agent_type: Option<String>,
+}
+
+/// GET /api/agent-runs/:session_id/tools
+///
+/// The run's tool calls in the order they were made
+pub async fn list_agent_run_tools(
+    Path(session_id): Path<String>,
+    State(db): State<Arc<SqlitePool>>,
+) -> Result<Json<Vec<ToolAuditEntry>>, (StatusCode, String)> {
+    let calls = tool_audit::list_for_session(&db, &session_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?;
+    Ok(Json(calls))
+}
+
+/// GET /api/tool-audit/stats
+///
+/// Calls, errors and durations per tool across the organization's runs over
+/// the last `days` days
+pub async fn get_tool_usage_stats(
+    State(db): State<Arc<SqlitePool>>,
+    Extension(org): Extension<Organization>,
+    Query(params): Query<ToolStatsQuery>,
+) -> Result<Json<Vec<ToolUsageStats>>, (StatusCode, String)> {
+    let days = params.days.unwrap_or(DEFAULT_STATS_DAYS).clamp(1, MAX_STATS_DAYS);
+    let since = (chrono::Utc::now() - chrono::Duration::days(days)).to_rfc3339();
+    let stats = tool_audit::usage_stats(&db, &org.name, &since, params.agent_type.as_deref())
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?;
+    Ok(Json(stats))
+}
@@ -0,0 +1,336 @@
+//! Bulk transcript ingestion for external voice bots streaming live captions
+//!
+//! A bot sends captions in batches to
+//! `POST /api/transcripts/:session_id/entries/batch`. Each entry carries the
+//! bot's own `entry_id` and a `sequence` number. Resending an entry that was
+//! already stored is a no-op, so a bot can retry a batch it isn't sure
+//! landed. Each batch is stored in sequence order, all or nothing, and one
+//! batch per session is ingested at a time. Live batches must continue past
+//! the session's last sequence; captions missed while a bot was disconnected
+//! go in as a `backfill` batch, which may fill gaps below it and may target
+//! an ended session. Live and backfill entries have separate per-session
+//! rate limits, so a large backfill can't hold up live captions.
+
+use axum::{
+    extract::{Extension, Path, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use sqlx::SqlitePool;
+
+use ticketing_system::{IngestTranscriptEntry, TranscriptEntry, User};
+
+/// Most entries in one batch
+const MAX_BATCH_ENTRIES: usize = 500;
+const MAX_ENTRY_ID_CHARS: usize = 128;
+const RATE_WINDOW: Duration = Duration::from_secs(60);
+/// Live entries a session may ingest per window
+const LIVE_ENTRIES_PER_WINDOW: usize = 600;
+/// Backfill entries a session may ingest per window
+const BACKFILL_ENTRIES_PER_WINDOW: usize = 5000;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct BatchTranscriptEntry {
+    /// The bot's id for the entry; resending it is a no-op
+    pub entry_id: String,
+    /// Position in the session; entries are stored in this order
+    pub sequence: i64,
+    pub user_id: String,
+    pub username: String,
+    pub text: String,
+    /// When the words were spoken; defaults to now
+    pub timestamp: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BatchTranscriptEntriesRequest {
+    pub entries: Vec<BatchTranscriptEntry>,
+    /// Entries caught up after a gap rather than streamed live
+    #[serde(default)]
+    pub backfill: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BatchTranscriptEntriesResponse {
+    pub session_id: String,
+    /// Entries stored by this batch, in sequence order
+    pub entries: Vec<TranscriptEntry>,
+    /// Entry ids that were already stored and skipped
+    pub duplicates: Vec<String>,
+    /// Highest sequence stored for the session
+    pub last_sequence: Option<i64>,
+}
+
+/// Check a batch and put it in sequence order
+fn check_batch(mut entries: Vec<BatchTranscriptEntry>) -> Result<Vec<BatchTranscriptEntry>, String> {
+    if entries.is_empty() || entries.len() > MAX_BATCH_ENTRIES {
+        return Err(format!("entries must contain between 1 and {} entries", MAX_BATCH_ENTRIES));
+    }
+    for entry in &entries {
+        let id_len = entry.entry_id.trim().chars().count();
+        if id_len == 0 || id_len > MAX_ENTRY_ID_CHARS {
+            return Err(format!("entry_id must be 1 to {} characters", MAX_ENTRY_ID_CHARS));
+        }
+        if entry.sequence < 0 {
+            return Err(format!("Entry {}: sequence cannot be negative", entry.entry_id));
+        }
+        if entry.text.trim().is_empty() || entry.username.trim().is_empty() {
+            return Err(format!("Entry {}: text and username are required", entry.entry_id));
+        }
+    }
+    entries.sort_by_key(|e| e.sequence);
+    let mut ids = HashSet::new();
+    for (i, entry) in entries.iter().enumerate() {
+        if !ids.insert(entry.entry_id.as_str()) {
+            return Err(format!("Entry {} appears more than once", entry.entry_id));
+        }
+        if i > 0 && entries[i - 1].sequence == entry.sequence {
+            return Err(format!("Entries {} and {} share sequence {}", entries[i - 1].entry_id, entry.entry_id, entry.sequence));
+        }
+    }
+    Ok(entries)
+}
+
+// ============================================================================
+// Per-session Limits
+// ============================================================================
+
+#[derive(Debug, PartialEq)]
+enum IngestRefusal {
+    /// Another batch for the session is being stored
+    Busy,
+    RateLimited { retry_after: Duration },
+}
+
+#[derive(Debug)]
+struct SessionIngest {
+    window_start: Instant,
+    live: usize,
+    backfill: usize,
+    busy: bool,
+}
+
+#[derive(Default)]
+struct IngestLimits {
+    sessions: Mutex<HashMap<String, SessionIngest>>,
+}
+
+/// Marks a session's batch as in flight until dropped
+struct IngestGuard<'a> {
+    limits: &'a IngestLimits,
+    session_id: String,
+}
+
+impl Drop for IngestGuard<'_> {
+    fn drop(&mut self) {
+        if let Some(session) = self.limits.sessions.lock().unwrap().get_mut(&self.session_id) {
+            session.busy = false;
+        }
+    }
+}
+
+impl IngestLimits {
+    /// Take the session's ingest slot, held until the guard is dropped
+    fn begin(&self, session_id: &str, now: Instant) -> Result<IngestGuard<'_>, IngestRefusal> {
+        let mut sessions = self.sessions.lock().unwrap();
+        sessions.retain(|_, s| s.busy || now.duration_since(s.window_start) < RATE_WINDOW);
+        let session = sessions.entry(session_id.to_string()).or_insert(SessionIngest {
+            window_start: now,
+            live: 0,
+            backfill: 0,
+            busy: false,
+        });
+        if session.busy {
+            return Err(IngestRefusal::Busy);
+        }
+        session.busy = true;
+        Ok(IngestGuard { limits: self, session_id: session_id.to_string() })
+    }
+}
+
+impl IngestGuard<'_> {
+    /// Count `count` entries against the session's live or backfill limit
+    fn charge(&self, count: usize, backfill: bool, now: Instant) -> Result<(), IngestRefusal> {
+        let mut sessions = self.limits.sessions.lock().unwrap();
+        let Some(session) = sessions.get_mut(&self.session_id) else {
+            return Ok(());
+        };
+        if now.duration_since(session.window_start) >= RATE_WINDOW {
+            session.window_start = now;
+            session.live = 0;
+            session.backfill = 0;
+        }
+        let (used, limit) = if backfill {
+            (&mut session.backfill, BACKFILL_ENTRIES_PER_WINDOW)
+        } else {
+            (&mut session.live, LIVE_ENTRIES_PER_WINDOW)
+        };
+        if *used + count > limit {
+            let retry_after = RATE_WINDOW.saturating_sub(now.duration_since(session.window_start));
+            return Err(IngestRefusal::RateLimited { retry_after });
+        }
+        *used += count;
+        Ok(())
+    }
+}
+
+lazy_static::lazy_static! {
+    static ref INGEST_LIMITS: IngestLimits = IngestLimits::default();
+}
+
+// ============================================================================
+// Handler
+// ============================================================================
+
+fn error(status: StatusCode, message: String) -> Response {
+    (status, message).into_response()
+}
+
+/// POST /api/transcripts/:session_id/entries/batch
+/// Store a batch of captions from a voice bot, skipping entries it already sent
+pub async fn add_entries_batch(
+    Path(session_id): Path<String>,
+    State(db): State<Arc<SqlitePool>>,
+    Extension(user): Extension<User>,
+    Json(req): Json<BatchTranscriptEntriesRequest>,
+) -> Response {
+    let entries = match check_batch(req.entries) {
+        Ok(entries) => entries,
+        Err(e) => return error(StatusCode::BAD_REQUEST, e),
+    };
+
+    let session = match ticketing_system::transcripts::get_session(&db, &session_id).await {
+        Ok(Some(session)) => session,
+        Ok(None) => return error(StatusCode::NOT_FOUND, "Session not found".to_string()),
+        Err(e) => return error(StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)),
+    };
+    if !session.is_active && !req.backfill {
+        return error(StatusCode::CONFLICT, "Session has ended; send missed entries as a backfill".to_string());
+    }
+
+    let now = Instant::now();
+    let guard = match INGEST_LIMITS.begin(&session_id, now) {
+        Ok(guard) => guard,
+        Err(_) => {
+            return error(StatusCode::CONFLICT, "Another batch for this session is being stored; retry shortly".to_string());
+        }
+    };
+
+    let ids: Vec<String> = entries.iter().map(|e| e.entry_id.clone()).collect();
+    let existing = match ticketing_system::transcripts::existing_external_ids(&db, &session_id, &ids).await {
+        Ok(existing) => existing,
+        Err(e) => return error(StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)),
+    };
+    let (duplicates, new_entries): (Vec<_>, Vec<_>) =
+        entries.into_iter().partition(|e| existing.contains(&e.entry_id));
+    let duplicates: Vec<String> = duplicates.into_iter().map(|e| e.entry_id).collect();
+
+    if let Err(IngestRefusal::RateLimited { retry_after }) = guard.charge(new_entries.len(), req.backfill, now) {
+        return (
+            StatusCode::TOO_MANY_REQUESTS,
+            [(header::RETRY_AFTER, retry_after.as_secs().max(1).to_string())],
+            "Too many transcript entries for this session; retry later".to_string(),
+        )
+            .into_response();
+    }
+
+    // Read under the session's ingest slot, so no other batch can move it on
+    let last_sequence = match ticketing_system::transcripts::last_sequence(&db, &session_id).await {
+        Ok(last) => last,
+        Err(e) => return error(StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)),
+    };
+    if !req.backfill {
+        if let (Some(first), Some(last)) = (new_entries.first(), last_sequence) {
+            if first.sequence <= last {
+                return error(
+                    StatusCode::CONFLICT,
+                    format!(
+                        "Entry {} has sequence {} but the session is at {}; send it as a backfill",
+                        first.entry_id, first.sequence, last
+                    ),
+                );
+            }
+        }
+    }
+
+    let now = chrono::Utc::now().to_rfc3339();
+    let ingest: Vec<IngestTranscriptEntry> = new_entries
+        .into_iter()
+        .map(|e| IngestTranscriptEntry {
+            external_id: e.entry_id,
+            sequence: e.sequence,
+            user_id: e.user_id,
+            username: e.username,
+            text: e.text,
+            timestamp: e.timestamp.unwrap_or_else(|| now.clone()),
+        })
+        .collect();
+    let stored = if ingest.is_empty() {
+        Vec::new()
+    } else {
+        match ticketing_system::transcripts::add_ingested_entries(&db, &session_id, &ingest).await {
+            Ok(stored) => stored,
+            Err(e) => return error(StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)),
+        }
+    };
+    tracing::info!(
+        "{} stored {} transcript entries in session {} ({} duplicates{})",
+        user.user_id,
+        stored.len(),
+        session_id,
+        duplicates.len(),
+        if req.backfill { ", backfill" } else { "" }
+    );
+
+    let last_sequence = ingest.iter().map(|e| e.sequence).chain(last_sequence).max();
+    Json(BatchTranscriptEntriesResponse { session_id, entries: stored, duplicates, last_sequence }).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(id: &str, sequence: i64) -> BatchTranscriptEntry {
+        BatchTranscriptEntry {
+            entry_id: id.to_string(),
+            sequence,
+            user_id: "u1".to_string(),
+            username: "Ana".to_string(),
+            text: "hello".to_string(),
+            timestamp: None,
+        }
+    }
+
+    #[test]
+    fn orders_batches_and_limits_sessions() {
+        let sorted = check_batch(vec![entry("c", 3), entry("a", 1), entry("b", 2)]).unwrap();
+        let ids: Vec<&str> = sorted.iter().map(|e| e.entry_id.as_str()).collect();
+        assert_eq!(ids, vec!["a", "b", "c"]);
+        assert!(check_batch(vec![entry("a", 1), entry("a", 2)]).is_err());
+        assert!(check_batch(vec![entry("a", 1), entry("b", 1)]).is_err());
+        assert!(check_batch(vec![entry("a", -1)]).is_err());
+        assert!(check_batch(Vec::new()).is_err());
+
+        let limits = IngestLimits::default();
+        let start = Instant::now();
+        let guard = limits.begin("s1", start).unwrap();
+        assert_eq!(limits.begin("s1", start).err(), Some(IngestRefusal::Busy));
+        assert!(limits.begin("s2", start).is_ok());
+        assert!(guard.charge(10, false, start).is_ok());
+        drop(guard);
+
+        let later = start + Duration::from_secs(20);
+        let guard = limits.begin("s1", later).unwrap();
+        assert_eq!(
+            guard.charge(LIVE_ENTRIES_PER_WINDOW, false, later),
+            Err(IngestRefusal::RateLimited { retry_after: Duration::from_secs(40) })
+        );
+        assert!(guard.charge(LIVE_ENTRIES_PER_WINDOW, true, later).is_ok());
+        assert!(guard.charge(LIVE_ENTRIES_PER_WINDOW, false, start + RATE_WINDOW).is_ok());
+    }
+}
@@ -1,5 +1,5 @@
 use axum::{
-    extract::{Path, Query, State},
+    extract::{Extension, Path, Query, State},
     response::sse::{Event, KeepAlive, Sse},
     http::StatusCode,
     Json,
@@ -12,10 +12,12 @@ use sqlx::SqlitePool;
 use serde::{Deserialize, Serialize};
 
 use ticketing_system::{
-    TranscriptSession, TranscriptEntry,
-    CreateTranscriptSessionRequest, CreateTranscriptEntryRequest,
+    TranscriptSession, TranscriptEntry, TranscriptEntryEdit,
+    CreateTranscriptSessionRequest, CreateTranscriptEntryRequest, User,
 };
 
+use super::meeting_transcription::MERGED_TRANSCRIPT_SPEAKER;
+
 #[derive(Debug, Serialize)]
 pub struct TranscriptSessionsResponse {
     pub sessions: Vec<TranscriptSession>,
@@ -32,6 +34,21 @@ pub struct ListSessionsQuery {
     pub active_only: Option<bool>,
 }
 
+/// Correction to a single transcript entry. Omitted fields are left as they
+/// are; `username` (with an optional `user_id`) reassigns the speaker, except
+/// on a merged meeting transcript whose segments are labelled in its text.
+#[derive(Debug, Deserialize)]
+pub struct UpdateTranscriptEntryRequest {
+    pub text: Option<String>,
+    pub username: Option<String>,
+    pub user_id: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TranscriptEditsResponse {
+    pub edits: Vec<TranscriptEntryEdit>,
+}
+
 /// SSE event for transcript streaming
 #[derive(Debug, Serialize)]
 #[serde(tag = "type")]
@@ -118,6 +135,93 @@ pub async fn add_entry(
     Ok(Json(entry))
 }
 
+/// PATCH /api/transcripts/:session_id/entries/:entry_id
+/// Correct an entry's text or reassign its speaker. Every changed field is
+/// recorded in the session's edit log with its previous value.
+pub async fn update_entry(
+    Path((session_id, entry_id)): Path<(String, i64)>,
+    State(db): State<Arc<SqlitePool>>,
+    Extension(user): Extension<User>,
+    Json(req): Json<UpdateTranscriptEntryRequest>,
+) -> Result<Json<TranscriptEntry>, (StatusCode, String)> {
+    if req.text.is_none() && req.username.is_none() && req.user_id.is_none() {
+        return Err((StatusCode::BAD_REQUEST, "Nothing to update".to_string()));
+    }
+    if req.text.as_deref().is_some_and(|t| t.trim().is_empty()) {
+        return Err((StatusCode::BAD_REQUEST, "Text cannot be empty".to_string()));
+    }
+    if req.username.as_deref().is_some_and(|u| u.trim().is_empty()) {
+        return Err((StatusCode::BAD_REQUEST, "Speaker cannot be empty".to_string()));
+    }
+    if req.username.as_deref() == Some(MERGED_TRANSCRIPT_SPEAKER) {
+        return Err((StatusCode::BAD_REQUEST, format!("'{}' is reserved", MERGED_TRANSCRIPT_SPEAKER)));
+    }
+
+    let entry = ticketing_system::transcripts::get_entry(&db, &session_id, entry_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?
+        .ok_or_else(|| (StatusCode::NOT_FOUND, "Entry not found".to_string()))?;
+    // A finalized meeting transcript is one entry whose text holds every
+    // segment's `[Speaker]:` label, so there is no single speaker to reassign
+    if entry.username == MERGED_TRANSCRIPT_SPEAKER && (req.username.is_some() || req.user_id.is_some()) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "Merged meeting transcripts have no single speaker; correct the [Speaker]: labels in the text instead"
+                .to_string(),
+        ));
+    }
+
+    let changes: Vec<(&str, &str, &str)> = [
+        ("text", entry.text.as_str(), req.text.as_deref()),
+        ("username", entry.username.as_str(), req.username.as_deref()),
+        ("user_id", entry.user_id.as_str(), req.user_id.as_deref()),
+    ]
+    .into_iter()
+    .filter_map(|(field, old, new)| new.filter(|new| *new != old).map(|new| (field, old, new)))
+    .collect();
+
+    if changes.is_empty() {
+        return Ok(Json(entry));
+    }
+
+    let updated = ticketing_system::transcripts::update_entry(
+        &db,
+        &session_id,
+        entry_id,
+        req.text.as_deref(),
+        req.username.as_deref(),
+        req.user_id.as_deref(),
+    )
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?
+    .ok_or_else(|| (StatusCode::NOT_FOUND, "Entry not found".to_string()))?;
+
+    for (field, old, new) in changes {
+        if let Err(e) = ticketing_system::transcripts::record_entry_edit(
+            &db, &session_id, entry_id, &user.user_id, field, old, new,
+        )
+        .await
+        {
+            tracing::error!("Failed to record edit of transcript entry {} ({}): {}", entry_id, field, e);
+        }
+    }
+
+    Ok(Json(updated))
+}
+
+/// GET /api/transcripts/:session_id/edits
+/// Edit log for a session's entries, oldest first
+pub async fn list_entry_edits(
+    Path(session_id): Path<String>,
+    State(db): State<Arc<SqlitePool>>,
+) -> Result<Json<TranscriptEditsResponse>, (StatusCode, String)> {
+    let edits = ticketing_system::transcripts::list_entry_edits(&db, &session_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?;
+
+    Ok(Json(TranscriptEditsResponse { edits }))
+}
+
 /// GET /api/transcripts/:session_id/stream
 /// SSE endpoint for live transcript updates
 pub async fn stream_session(
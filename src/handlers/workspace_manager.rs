@@ -1,50 +1,155 @@
-use axum::{extract::State, Json};
+use axum::{
+    extract::{Extension, Path, Query, State},
+    http::StatusCode,
+    Json,
+};
 use std::sync::Arc;
 use std::path::PathBuf;
 use std::collections::HashMap;
 use sqlx::SqlitePool;
 use serde::Deserialize;
 
+use ticketing_system::{CreatePinnedContextRequest, PinnedContext, User};
+
 use crate::agents::AgentType;
+use crate::tenancy::Organization;
 use super::chat_stream::{self, ChatConfig, SseStream};
+use super::conversation_attachments::attach_to_message;
+
+/// Approximate token budget for pinned context injected into new sessions
+const PINNED_CONTEXT_TOKEN_BUDGET: usize = 4000;
+
+/// Rough chars-per-token ratio used for budgeting
+pub(super) const CHARS_PER_TOKEN: usize = 4;
 
 #[derive(Debug, Deserialize)]
 pub struct WorkspaceManagerRequest {
     pub message: String,
-    /// Accepted from frontend but not used server-side (agent works cross-org)
-    #[allow(dead_code)]
+    /// Used to select the organization's pinned context for new sessions
     pub organization: Option<String>,
     pub session_id: Option<String>,
     pub conversation_id: Option<String>,
+    /// Conversation attachments to show the agent with this message
+    #[serde(default)]
+    pub attachment_ids: Vec<String>,
+    /// Also open a new session with the user's bookmarked messages
+    #[serde(default)]
+    pub include_bookmarks: bool,
+}
+
+/// Read by the MCP server's cross-organization tools (`search_tickets_across_organizations`,
+/// `search_epics_across_organizations`), which search only the organizations listed.
+/// Always set, so a session never falls back to the server's default.
+const ORGANIZATIONS_ENV: &str = "AGENTIC_ORGANIZATIONS";
+
+/// Organizations a session in `organization` may search: the user's
+/// memberships, or just `organization` when there are none to list
+fn search_scope(accessible: &[String], organization: &str) -> Vec<String> {
+    if accessible.is_empty() {
+        vec![organization.to_string()]
+    } else {
+        accessible.to_vec()
+    }
 }
 
-fn config() -> ChatConfig {
+/// The cross-organization search tools are scoped to the user's memberships,
+/// which are passed to the MCP server through the agent's environment, as is
+/// the organization's own Anthropic key when it has one.
+pub(super) async fn config(db: &SqlitePool, org: &Organization, organization: &str) -> ChatConfig {
+    let mut prompt_vars = HashMap::new();
+    let mut env = crate::llm_keys::anthropic_env(db, organization).await;
+    prompt_vars.insert("current_organization".to_string(), organization.to_string());
+    let organizations = search_scope(org.accessible(), organization);
+    prompt_vars.insert("organizations".to_string(), organizations.join(", "));
+    env.insert(ORGANIZATIONS_ENV.to_string(), organizations.join(","));
+
     ChatConfig {
         agent_type: AgentType::WorkspaceManager,
         prompt_name: "workspace-manager",
         working_dir: PathBuf::from("/Users/jarvisgpt/projects"),
-        prompt_vars: HashMap::new(),
+        prompt_vars,
+        env,
+        add_dirs: Vec::new(),
+        organization: Some(organization.to_string()),
+    }
+}
+
+/// Prepend the organization's pinned context, upcoming deadlines and epic
+/// critical paths to the first message of a session, with the bookmarks of
+/// `bookmarks_of` when given.
+/// Newest pins win; older pins are dropped once the token budget is used up.
+async fn inject_pinned_context(db: &SqlitePool, organization: &str, bookmarks_of: Option<&str>, message: &str) -> String {
+    let pins = ticketing_system::pinned_context::list_pins(db, organization)
+        .await
+        .unwrap_or_else(|e| {
+            tracing::warn!("Failed to load pinned context for {}: {}", organization, e);
+            Vec::new()
+        });
+
+    let mut remaining = PINNED_CONTEXT_TOKEN_BUDGET * CHARS_PER_TOKEN;
+    let mut parts = vec!["[Pinned Context]".to_string()];
+    for pin in &pins {
+        let section = format!("\n## {}\n{}", pin.title, pin.content);
+        if section.len() > remaining {
+            tracing::info!("Pinned context budget reached for {}, skipping remaining pins", organization);
+            break;
+        }
+        remaining -= section.len();
+        parts.push(section);
+    }
+    if parts.len() == 1 {
+        parts.clear();
+    }
+
+    if let Some(deadlines) = crate::deadlines::deadlines_context(db, organization).await {
+        parts.push(deadlines);
+    }
+    if let Some(paths) = super::critical_path::critical_path_context(db, organization).await {
+        parts.push(paths);
     }
+    if let Some(user_id) = bookmarks_of {
+        parts.extend(super::message_bookmarks::bookmarks_context(db, user_id, organization).await);
+    }
+
+    if parts.is_empty() {
+        return message.to_string();
+    }
+
+    parts.push("---".to_string());
+    parts.push(String::new());
+    parts.push(message.to_string());
+    parts.join("\n")
 }
 
 /// POST /api/workspace-manager/chat
 pub async fn workspace_manager_chat(
     State(db): State<Arc<SqlitePool>>,
+    Extension(org): Extension<Organization>,
+    Extension(user): Extension<User>,
     Json(req): Json<WorkspaceManagerRequest>,
-) -> SseStream {
+) -> Result<SseStream, (StatusCode, String)> {
     tracing::info!("=== WORKSPACE_MANAGER_CHAT START ===");
-    chat_stream::chat(
+    let organization = org.clone().resolve(req.organization.clone())?;
+    crate::quotas::check_agent_run(&db, &organization).await?;
+    let (message, attachment_dir) =
+        attach_to_message(&db, &org, req.conversation_id.as_deref(), &req.attachment_ids, req.message).await?;
+    let bookmarks_of = req.include_bookmarks.then_some(user.user_id.as_str());
+    let injected_message = inject_pinned_context(&db, &organization, bookmarks_of, &message).await;
+    let mut config = config(&db, &org, &organization).await;
+    config.add_dirs.extend(attachment_dir);
+    Ok(chat_stream::chat(
         db,
-        req.message,
+        injected_message,
         req.session_id,
         req.conversation_id,
-        config(),
-    )
+        config,
+    ))
 }
 
 /// POST /api/workspace-manager/resume
 pub async fn workspace_manager_resume(
     State(db): State<Arc<SqlitePool>>,
+    Extension(org): Extension<Organization>,
     Json(req): Json<WorkspaceManagerRequest>,
 ) -> SseStream {
     tracing::info!("=== WORKSPACE_MANAGER_RESUME START ===");
@@ -52,11 +157,192 @@ pub async fn workspace_manager_resume(
         Some(id) => id,
         None => return chat_stream::create_error_sse("session_id is required for resume".to_string()),
     };
+    let organization = match org.clone().resolve(req.organization.clone()) {
+        Ok(organization) => organization,
+        Err((_, message)) => return chat_stream::create_error_sse(message),
+    };
+    if let Err(exceeded) = crate::quotas::check_agent_run(&db, &organization).await {
+        return chat_stream::create_error_sse(exceeded.message());
+    }
+    let (message, attachment_dir) =
+        match attach_to_message(&db, &org, req.conversation_id.as_deref(), &req.attachment_ids, req.message).await {
+            Ok(attached) => attached,
+            Err((_, message)) => return chat_stream::create_error_sse(message),
+        };
+    let mut config = config(&db, &org, &organization).await;
+    config.add_dirs.extend(attachment_dir);
+    // A thread past its history budget continues in a fresh session from its memory
+    if let Some(conversation_id) = req.conversation_id.as_deref() {
+        if let Some(prompt) = crate::conversation_memory::compacted_resume(&db, conversation_id, &message).await {
+            let injected_message = inject_pinned_context(&db, &organization, None, &prompt).await;
+            return chat_stream::chat(db, injected_message, None, req.conversation_id, config);
+        }
+    }
     chat_stream::resume(
         db,
-        req.message,
+        message,
         session_id,
         req.conversation_id,
-        config(),
+        config,
+    )
+}
+
+/// First message and config for regenerating a workspace manager reply from
+/// an edited message in one of the organization's conversations (see
+/// `update_message`)
+pub(super) async fn regeneration(
+    db: &SqlitePool,
+    org: &Organization,
+    organization: &str,
+    message: &str,
+) -> (String, ChatConfig) {
+    let injected_message = inject_pinned_context(db, organization, None, message).await;
+    (injected_message, config(db, org, organization).await)
+}
+
+// ============================================================================
+// Pinned Context
+// ============================================================================
+
+#[derive(Debug, Deserialize)]
+pub struct PinnedContextQuery {
+    pub organization: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PinContextRequest {
+    pub organization: Option<String>,
+    pub title: Option<String>,
+    /// Free-form content to pin
+    pub content: Option<String>,
+    /// Pin a summary of a prior conversation
+    pub conversation_id: Option<String>,
+    /// Pin the output of an agent run (research artifact, plan, ...)
+    pub session_id: Option<String>,
+}
+
+/// Summarize a conversation for pinning: the last assistant reply carries the
+/// conclusions of a planning session. Only conversations of the organization
+/// pinned to, or from before tenancy, may be pinned.
+async fn summarize_conversation(
+    db: &SqlitePool,
+    organization: &str,
+    conversation_id: &str,
+) -> Result<(String, String), (StatusCode, String)> {
+    let conv = ticketing_system::conversations::get_conversation(db, conversation_id, false)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .filter(|c| c.organization.as_deref().is_none_or(|o| o == organization))
+        .ok_or((StatusCode::NOT_FOUND, "Conversation not found".to_string()))?;
+
+    let messages = ticketing_system::conversations::list_messages(db, conversation_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let summary = messages
+        .iter()
+        .rev()
+        .find(|m| m.role == "assistant" && !m.content.trim().is_empty())
+        .map(|m| m.content.clone())
+        .ok_or((StatusCode::BAD_REQUEST, "Conversation has no assistant output to pin".to_string()))?;
+
+    let title = conv.title.unwrap_or_else(|| format!("Conversation {}", conversation_id));
+    Ok((title, summary))
+}
+
+/// GET /api/workspace-manager/context
+pub async fn list_pinned_context(
+    State(db): State<Arc<SqlitePool>>,
+    Extension(org): Extension<Organization>,
+    Query(params): Query<PinnedContextQuery>,
+) -> Result<Json<Vec<PinnedContext>>, (StatusCode, String)> {
+    let organization = org.resolve(params.organization)?;
+    let pins = ticketing_system::pinned_context::list_pins(&db, &organization)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(Json(pins))
+}
+
+/// POST /api/workspace-manager/context
+pub async fn pin_context(
+    State(db): State<Arc<SqlitePool>>,
+    Extension(org): Extension<Organization>,
+    Json(req): Json<PinContextRequest>,
+) -> Result<(StatusCode, Json<PinnedContext>), (StatusCode, String)> {
+    let organization = org.resolve(req.organization.clone())?;
+
+    let (source_type, source_id, default_title, content) = if let Some(content) = req.content {
+        ("note", None, "Pinned note".to_string(), content)
+    } else if let Some(conversation_id) = req.conversation_id {
+        let (title, summary) = summarize_conversation(&db, &organization, &conversation_id).await?;
+        ("conversation", Some(conversation_id), title, summary)
+    } else if let Some(session_id) = req.session_id {
+        let run = ticketing_system::agent_runs::get_agent_run(&db, &session_id)
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+            .ok_or((StatusCode::NOT_FOUND, "Agent run not found".to_string()))?;
+        // Runs belong to their ticket's organization
+        let ticket = ticketing_system::tickets::get_ticket_by_id(&db, &run.ticket_id)
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        if ticket.is_none_or(|t| t.organization != organization) {
+            return Err((StatusCode::NOT_FOUND, "Agent run not found".to_string()));
+        }
+        let output = run
+            .output_summary
+            .ok_or((StatusCode::BAD_REQUEST, "Agent run has no output to pin".to_string()))?;
+        ("agent_run", Some(session_id), format!("{} output", run.agent_type), output)
+    } else {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "One of content, conversation_id or session_id is required".to_string(),
+        ));
+    };
+
+    let pin = ticketing_system::pinned_context::create_pin(
+        &db,
+        CreatePinnedContextRequest {
+            organization,
+            title: req.title.unwrap_or(default_title),
+            content,
+            source_type: source_type.to_string(),
+            source_id,
+        },
     )
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok((StatusCode::CREATED, Json(pin)))
+}
+
+/// DELETE /api/workspace-manager/context/:pin_id
+pub async fn unpin_context(
+    State(db): State<Arc<SqlitePool>>,
+    Extension(org): Extension<Organization>,
+    Path(pin_id): Path<String>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let pin = ticketing_system::pinned_context::get_pin(&db, &pin_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or((StatusCode::NOT_FOUND, "Pin not found".to_string()))?;
+    org.authorize(&pin.organization)?;
+
+    ticketing_system::pinned_context::delete_pin(&db, &pin_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scopes_search_to_memberships_or_the_session() {
+        let memberships = vec!["acme".to_string(), "globex".to_string()];
+        assert_eq!(search_scope(&memberships, "acme"), memberships);
+        assert_eq!(search_scope(&[], "acme"), vec!["acme".to_string()]);
+    }
 }
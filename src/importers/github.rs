@@ -0,0 +1,191 @@
+//! GitHub Issues: REST API issue objects, `gh issue list --json` output, or CSV
+
+use anyhow::{anyhow, Context, Result};
+use serde_json::Value;
+
+use super::{
+    csv_field, non_empty, read_csv, split_list, ImportCredentials, ImportedComment, ImportedIssue, ImportedProject,
+    MAX_API_ISSUES,
+};
+
+const API_URL: &str = "https://api.github.com";
+const PAGE_SIZE: usize = 100;
+
+/// "closed" issues closed as not planned are cancellations, not completions
+fn status_of(issue: &Value) -> String {
+    let state = issue.get("state").and_then(|s| s.as_str()).unwrap_or("open").to_lowercase();
+    let reason = issue
+        .get("state_reason")
+        .or_else(|| issue.get("stateReason"))
+        .and_then(|r| r.as_str())
+        .unwrap_or("")
+        .to_lowercase();
+    if state == "closed" && reason == "not_planned" {
+        "not planned".to_string()
+    } else {
+        state
+    }
+}
+
+fn author_of(value: &Value) -> String {
+    value
+        .get("user")
+        .or_else(|| value.get("author"))
+        .and_then(|u| u.get("login"))
+        .and_then(|l| l.as_str())
+        .unwrap_or("unknown")
+        .to_string()
+}
+
+fn parse_comment(comment: &Value) -> Option<ImportedComment> {
+    Some(ImportedComment {
+        author: author_of(comment),
+        body: comment.get("body")?.as_str()?.to_string(),
+        created_at: comment
+            .get("created_at")
+            .or_else(|| comment.get("createdAt"))
+            .and_then(|d| d.as_str())
+            .map(|s| s.to_string()),
+    })
+}
+
+/// Parse one issue; pull requests (which the issues API also returns) are skipped
+fn parse_issue(issue: &Value) -> Option<ImportedIssue> {
+    if issue.get("pull_request").is_some() {
+        return None;
+    }
+    let number = issue.get("number")?.as_u64()?;
+    Some(ImportedIssue {
+        external_id: issue
+            .get("node_id")
+            .or_else(|| issue.get("id"))
+            .map(|id| id.as_str().map(|s| s.to_string()).unwrap_or_else(|| id.to_string()))
+            .unwrap_or_else(|| number.to_string()),
+        key: format!("#{}", number),
+        title: issue.get("title").and_then(|t| t.as_str()).unwrap_or_default().to_string(),
+        description: non_empty(issue.get("body").and_then(|b| b.as_str()).map(|s| s.to_string())),
+        status: status_of(issue),
+        labels: issue
+            .get("labels")
+            .and_then(|l| l.as_array())
+            .map(|labels| {
+                labels
+                    .iter()
+                    .filter_map(|l| l.as_str().or_else(|| l.get("name").and_then(|n| n.as_str())))
+                    .map(|s| s.to_string())
+                    .collect()
+            })
+            .unwrap_or_default(),
+        assignee: issue
+            .get("assignees")
+            .and_then(|a| a.as_array())
+            .and_then(|a| a.first())
+            .and_then(|a| a.get("login"))
+            .and_then(|l| l.as_str())
+            .map(|s| s.to_string()),
+        // `gh` embeds comment objects; the REST API only gives a count (fetched separately)
+        comments: issue
+            .get("comments")
+            .and_then(|c| c.as_array())
+            .map(|c| c.iter().filter_map(parse_comment).collect())
+            .unwrap_or_default(),
+        url: issue
+            .get("html_url")
+            .or_else(|| issue.get("url"))
+            .and_then(|u| u.as_str())
+            .map(|s| s.to_string()),
+    })
+}
+
+/// "owner/repo" from an issue's `repository_url`, if present
+fn repo_of(issue: &Value) -> Option<String> {
+    let url = issue.get("repository_url")?.as_str()?;
+    let mut parts = url.rsplit('/');
+    let repo = parts.next()?;
+    let owner = parts.next()?;
+    Some(format!("{}/{}", owner, repo))
+}
+
+fn single_project(key: String, issues: Vec<ImportedIssue>) -> Vec<ImportedProject> {
+    if issues.is_empty() {
+        return Vec::new();
+    }
+    vec![ImportedProject { name: key.clone(), key, issues }]
+}
+
+pub fn parse_json(data: &str) -> Result<Vec<ImportedProject>> {
+    let root: Value = serde_json::from_str(data).context("Invalid GitHub JSON")?;
+    let items = root.as_array().ok_or_else(|| anyhow!("Expected an array of GitHub issues"))?;
+    let repo = items.iter().find_map(repo_of).unwrap_or_else(|| "github".to_string());
+    Ok(single_project(repo, items.iter().filter_map(parse_issue).collect()))
+}
+
+pub fn parse_csv(data: &str) -> Result<Vec<ImportedProject>> {
+    let mut issues = Vec::new();
+    for row in read_csv(data)? {
+        let Some(number) = csv_field(&row, "number") else {
+            continue;
+        };
+        issues.push(ImportedIssue {
+            external_id: csv_field(&row, "id").unwrap_or_else(|| number.clone()),
+            key: format!("#{}", number.trim_start_matches('#')),
+            title: csv_field(&row, "title").unwrap_or_default(),
+            description: csv_field(&row, "body"),
+            status: csv_field(&row, "state").unwrap_or_else(|| "open".to_string()),
+            labels: csv_field(&row, "labels").map(|l| split_list(&l)).unwrap_or_default(),
+            assignee: csv_field(&row, "assignee"),
+            comments: Vec::new(),
+            url: csv_field(&row, "url"),
+        });
+    }
+    Ok(single_project("github".to_string(), issues))
+}
+
+async fn get_json(client: &reqwest::Client, token: &str, url: &str) -> Result<Value> {
+    let response = client
+        .get(url)
+        .bearer_auth(token)
+        .header("Accept", "application/vnd.github+json")
+        .header("User-Agent", "agentic-flowstate")
+        .send()
+        .await
+        .context("GitHub request failed")?;
+    if !response.status().is_success() {
+        return Err(anyhow!("GitHub API error: {}", response.text().await.unwrap_or_default()));
+    }
+    response.json().await.context("Failed to parse GitHub response")
+}
+
+pub async fn fetch(credentials: &ImportCredentials) -> Result<Vec<ImportedProject>> {
+    if credentials.project.split('/').count() != 2 {
+        return Err(anyhow!("GitHub project must be \"owner/repo\""));
+    }
+    let client = reqwest::Client::new();
+    let mut issues = Vec::new();
+    for page in 1.. {
+        let url = format!(
+            "{}/repos/{}/issues?state=all&per_page={}&page={}",
+            API_URL, credentials.project, PAGE_SIZE, page
+        );
+        let items = get_json(&client, &credentials.token, &url).await?;
+        let items = items.as_array().cloned().unwrap_or_default();
+        for item in &items {
+            let Some(mut issue) = parse_issue(item) else {
+                continue;
+            };
+            let has_comments = item.get("comments").and_then(|c| c.as_u64()).unwrap_or(0) > 0;
+            if let (true, Some(comments_url)) = (has_comments, item.get("comments_url").and_then(|u| u.as_str())) {
+                let comments = get_json(&client, &credentials.token, comments_url).await?;
+                issue.comments = comments
+                    .as_array()
+                    .map(|c| c.iter().filter_map(parse_comment).collect())
+                    .unwrap_or_default();
+            }
+            issues.push(issue);
+        }
+        if items.len() < PAGE_SIZE || issues.len() >= MAX_API_ISSUES {
+            break;
+        }
+    }
+    Ok(single_project(credentials.project.clone(), issues))
+}
@@ -0,0 +1,243 @@
+//! Jira: search API JSON (`/rest/api/2|3/search`) or the CSV export
+
+use anyhow::{anyhow, Context, Result};
+use serde_json::Value;
+
+use super::{
+    check_site_url, csv_field, csv_fields, group_by_project, non_empty, read_csv, ImportCredentials, ImportedComment,
+    ImportedIssue, ImportedProject, MAX_API_ISSUES,
+};
+
+const PAGE_SIZE: usize = 100;
+const FIELDS: &str = "summary,description,status,labels,assignee,project,comment";
+
+/// Plain text from a field that is a string (API v2) or Atlassian Document Format (v3)
+fn text_of(value: &Value) -> Option<String> {
+    fn collect(node: &Value, out: &mut String) {
+        if let Some(text) = node.get("text").and_then(|t| t.as_str()) {
+            out.push_str(text);
+        }
+        if let Some(children) = node.get("content").and_then(|c| c.as_array()) {
+            for child in children {
+                collect(child, out);
+            }
+            if node.get("type").and_then(|t| t.as_str()) == Some("paragraph") {
+                out.push('\n');
+            }
+        }
+    }
+    match value {
+        Value::String(s) => non_empty(Some(s.clone())),
+        Value::Object(_) => {
+            let mut out = String::new();
+            collect(value, &mut out);
+            non_empty(Some(out.trim().to_string()))
+        }
+        _ => None,
+    }
+}
+
+fn parse_issue(issue: &Value) -> Option<(String, String, ImportedIssue)> {
+    let fields = issue.get("fields")?;
+    let key = issue.get("key")?.as_str()?.to_string();
+    let project = fields.get("project");
+    let project_key = project
+        .and_then(|p| p.get("key"))
+        .and_then(|k| k.as_str())
+        .map(|s| s.to_string())
+        .unwrap_or_else(|| key.split('-').next().unwrap_or(&key).to_string());
+    let project_name = project
+        .and_then(|p| p.get("name"))
+        .and_then(|n| n.as_str())
+        .unwrap_or(&project_key)
+        .to_string();
+
+    let comments = fields
+        .get("comment")
+        .and_then(|c| c.get("comments"))
+        .and_then(|c| c.as_array())
+        .map(|comments| {
+            comments
+                .iter()
+                .filter_map(|c| {
+                    Some(ImportedComment {
+                        author: c
+                            .get("author")
+                            .and_then(|a| a.get("displayName"))
+                            .and_then(|n| n.as_str())
+                            .unwrap_or("unknown")
+                            .to_string(),
+                        body: text_of(c.get("body")?)?,
+                        created_at: c.get("created").and_then(|d| d.as_str()).map(|s| s.to_string()),
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let imported = ImportedIssue {
+        external_id: issue.get("id").and_then(|id| id.as_str()).unwrap_or(&key).to_string(),
+        title: fields.get("summary").and_then(|s| s.as_str()).unwrap_or(&key).to_string(),
+        description: fields.get("description").and_then(text_of),
+        status: fields
+            .get("status")
+            .and_then(|s| s.get("name"))
+            .and_then(|n| n.as_str())
+            .unwrap_or("Open")
+            .to_string(),
+        labels: fields
+            .get("labels")
+            .and_then(|l| l.as_array())
+            .map(|l| l.iter().filter_map(|v| v.as_str().map(|s| s.to_string())).collect())
+            .unwrap_or_default(),
+        assignee: fields
+            .get("assignee")
+            .and_then(|a| a.get("displayName"))
+            .and_then(|n| n.as_str())
+            .map(|s| s.to_string()),
+        comments,
+        url: issue.get("self").and_then(|s| s.as_str()).map(|s| s.to_string()),
+        key,
+    };
+    Some((project_key, project_name, imported))
+}
+
+/// A JQL string literal for `value`, quoted and escaped
+fn jql_string(value: &str) -> String {
+    let escaped = value.replace('\\', "\\\\").replace('"', "\\\"");
+    format!("\"{}\"", escaped)
+}
+
+fn issues_array(root: &Value) -> Result<&Vec<Value>> {
+    root.as_array()
+        .or_else(|| root.get("issues").and_then(|i| i.as_array()))
+        .ok_or_else(|| anyhow!("Expected an array of issues or an object with an \"issues\" array"))
+}
+
+pub fn parse_json(data: &str) -> Result<Vec<ImportedProject>> {
+    let root: Value = serde_json::from_str(data).context("Invalid Jira JSON")?;
+    Ok(group_by_project(issues_array(&root)?.iter().filter_map(parse_issue).collect()))
+}
+
+pub fn parse_csv(data: &str) -> Result<Vec<ImportedProject>> {
+    let mut issues = Vec::new();
+    for row in read_csv(data)? {
+        let Some(key) = csv_field(&row, "Issue key") else {
+            continue;
+        };
+        let project_key = csv_field(&row, "Project key").unwrap_or_else(|| key.split('-').next().unwrap_or(&key).to_string());
+        let project_name = csv_field(&row, "Project name").unwrap_or_else(|| project_key.clone());
+        // Jira exports comments as "date;author;body"
+        let comments = csv_fields(&row, "Comment")
+            .into_iter()
+            .map(|c| {
+                let mut parts = c.splitn(3, ';');
+                match (parts.next(), parts.next(), parts.next()) {
+                    (Some(date), Some(author), Some(body)) => ImportedComment {
+                        author: author.to_string(),
+                        body: body.to_string(),
+                        created_at: Some(date.to_string()),
+                    },
+                    _ => ImportedComment { author: "unknown".to_string(), body: c.clone(), created_at: None },
+                }
+            })
+            .collect();
+
+        issues.push((
+            project_key,
+            project_name,
+            ImportedIssue {
+                external_id: csv_field(&row, "Issue id").unwrap_or_else(|| key.clone()),
+                title: csv_field(&row, "Summary").unwrap_or_else(|| key.clone()),
+                description: csv_field(&row, "Description"),
+                status: csv_field(&row, "Status").unwrap_or_else(|| "Open".to_string()),
+                labels: csv_fields(&row, "Labels"),
+                assignee: csv_field(&row, "Assignee"),
+                comments,
+                url: None,
+                key,
+            },
+        ));
+    }
+    Ok(group_by_project(issues))
+}
+
+pub async fn fetch(credentials: &ImportCredentials) -> Result<Vec<ImportedProject>> {
+    let base_url = credentials.base_url.as_deref().ok_or_else(|| anyhow!("Jira import needs base_url"))?;
+    let base_url = check_site_url(base_url).await?;
+    let base_url = base_url.as_str().trim_end_matches('/');
+    let email = credentials.email.as_deref().ok_or_else(|| anyhow!("Jira import needs email"))?;
+
+    // A redirect could lead anywhere, including hosts `check_site_url` rejects
+    let client = reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .context("Failed to build Jira client")?;
+    let jql = format!("project = {} ORDER BY created ASC", jql_string(&credentials.project));
+    let mut issues = Vec::new();
+    loop {
+        let response = client
+            .get(format!("{}/rest/api/2/search", base_url))
+            .basic_auth(email, Some(&credentials.token))
+            .query(&[
+                ("jql", jql.as_str()),
+                ("fields", FIELDS),
+                ("startAt", &issues.len().to_string()),
+                ("maxResults", &PAGE_SIZE.to_string()),
+            ])
+            .send()
+            .await
+            .context("Jira request failed")?;
+        if !response.status().is_success() {
+            return Err(anyhow!("Jira API error: {}", response.text().await.unwrap_or_default()));
+        }
+        let page: Value = response.json().await.context("Failed to parse Jira response")?;
+        let page_issues = issues_array(&page)?;
+        let total = page.get("total").and_then(|t| t.as_u64()).unwrap_or(0) as usize;
+        issues.extend(page_issues.iter().filter_map(parse_issue));
+        if page_issues.is_empty() || issues.len() >= total.min(MAX_API_ISSUES) {
+            break;
+        }
+    }
+    Ok(group_by_project(issues))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_csv_with_repeated_columns() {
+        let csv = "Summary,Issue key,Issue id,Status,Project key,Labels,Labels,Comment\n\
+                   Fix login,PROJ-1,10001,In Progress,PROJ,auth,bug,2024-01-02;Ana;Looks good\n\
+                   Add docs,PROJ-2,10002,Done,PROJ,,,\n";
+        let projects = parse_csv(csv).unwrap();
+        assert_eq!(projects.len(), 1);
+        let issues = &projects[0].issues;
+        assert_eq!(issues.len(), 2);
+        assert_eq!(issues[0].labels, vec!["auth", "bug"]);
+        assert_eq!(issues[0].comments[0].author, "Ana");
+        assert!(issues[1].labels.is_empty());
+    }
+
+    #[test]
+    fn escapes_jql_strings() {
+        assert_eq!(jql_string("PROJ"), "\"PROJ\"");
+        assert_eq!(jql_string("X\" OR project != \"Y"), "\"X\\\" OR project != \\\"Y\"");
+        assert_eq!(jql_string("a\\b"), "\"a\\\\b\"");
+    }
+
+    #[test]
+    fn parses_search_api_issues() {
+        let json = r#"{"issues": [{"id": "10001", "key": "PROJ-1", "self": "https://acme.atlassian.net/rest/api/2/issue/10001",
+            "fields": {"summary": "Fix login", "status": {"name": "In Progress"}, "labels": ["auth"],
+                "project": {"key": "PROJ", "name": "Project"},
+                "description": {"type": "doc", "content": [{"type": "paragraph", "content": [{"type": "text", "text": "Broken"}]}]}}}]}"#;
+        let projects = parse_json(json).unwrap();
+        assert_eq!(projects[0].name, "Project");
+        let issue = &projects[0].issues[0];
+        assert_eq!(issue.external_id, "10001");
+        assert_eq!(issue.status, "In Progress");
+        assert_eq!(issue.description.as_deref(), Some("Broken"));
+    }
+}
@@ -0,0 +1,145 @@
+//! Linear: GraphQL issue nodes (API or a saved query result) or the CSV export
+
+use anyhow::{anyhow, Context, Result};
+use serde_json::{json, Value};
+
+use super::{
+    csv_field, group_by_project, non_empty, read_csv, split_list, ImportCredentials, ImportedComment, ImportedIssue,
+    ImportedProject, MAX_API_ISSUES,
+};
+
+const API_URL: &str = "https://api.linear.app/graphql";
+const PAGE_SIZE: usize = 100;
+
+const ISSUES_QUERY: &str = r#"
+query Issues($team: String!, $first: Int!, $after: String) {
+  issues(filter: { team: { key: { eq: $team } } }, first: $first, after: $after) {
+    nodes {
+      id identifier title description url
+      state { name }
+      labels { nodes { name } }
+      assignee { name }
+      team { key name }
+      comments { nodes { body createdAt user { name } } }
+    }
+    pageInfo { hasNextPage endCursor }
+  }
+}"#;
+
+fn str_at<'a>(value: &'a Value, path: &[&str]) -> Option<&'a str> {
+    path.iter().try_fold(value, |v, key| v.get(key))?.as_str()
+}
+
+fn parse_node(node: &Value) -> Option<(String, String, ImportedIssue)> {
+    let identifier = node.get("identifier")?.as_str()?.to_string();
+    let team_key = str_at(node, &["team", "key"])
+        .map(|s| s.to_string())
+        .unwrap_or_else(|| identifier.split('-').next().unwrap_or(&identifier).to_string());
+    let team_name = str_at(node, &["team", "name"]).unwrap_or(&team_key).to_string();
+
+    let names = |path: &[&str]| -> Vec<Value> {
+        path.iter()
+            .try_fold(node, |v, key| v.get(key))
+            .and_then(|n| n.as_array())
+            .cloned()
+            .unwrap_or_default()
+    };
+
+    let issue = ImportedIssue {
+        external_id: node.get("id").and_then(|id| id.as_str()).unwrap_or(&identifier).to_string(),
+        title: node.get("title").and_then(|t| t.as_str()).unwrap_or(&identifier).to_string(),
+        description: non_empty(node.get("description").and_then(|d| d.as_str()).map(|s| s.to_string())),
+        status: str_at(node, &["state", "name"]).unwrap_or("Todo").to_string(),
+        labels: names(&["labels", "nodes"])
+            .iter()
+            .filter_map(|l| l.get("name").and_then(|n| n.as_str()).map(|s| s.to_string()))
+            .collect(),
+        assignee: str_at(node, &["assignee", "name"]).map(|s| s.to_string()),
+        comments: names(&["comments", "nodes"])
+            .iter()
+            .filter_map(|c| {
+                Some(ImportedComment {
+                    author: str_at(c, &["user", "name"]).unwrap_or("unknown").to_string(),
+                    body: c.get("body")?.as_str()?.to_string(),
+                    created_at: c.get("createdAt").and_then(|d| d.as_str()).map(|s| s.to_string()),
+                })
+            })
+            .collect(),
+        url: node.get("url").and_then(|u| u.as_str()).map(|s| s.to_string()),
+        key: identifier,
+    };
+    Some((team_key, team_name, issue))
+}
+
+/// Accepts a bare array of nodes, `{ "nodes": [...] }`, or a full GraphQL response
+fn nodes_array(root: &Value) -> Result<&Vec<Value>> {
+    root.as_array()
+        .or_else(|| root.get("nodes").and_then(|n| n.as_array()))
+        .or_else(|| root.pointer("/data/issues/nodes").and_then(|n| n.as_array()))
+        .ok_or_else(|| anyhow!("Expected an array of Linear issues"))
+}
+
+pub fn parse_json(data: &str) -> Result<Vec<ImportedProject>> {
+    let root: Value = serde_json::from_str(data).context("Invalid Linear JSON")?;
+    Ok(group_by_project(nodes_array(&root)?.iter().filter_map(parse_node).collect()))
+}
+
+pub fn parse_csv(data: &str) -> Result<Vec<ImportedProject>> {
+    let mut issues = Vec::new();
+    for row in read_csv(data)? {
+        let Some(identifier) = csv_field(&row, "ID") else {
+            continue;
+        };
+        let team = csv_field(&row, "Team")
+            .unwrap_or_else(|| identifier.split('-').next().unwrap_or(&identifier).to_string());
+        issues.push((
+            team.clone(),
+            team,
+            ImportedIssue {
+                external_id: csv_field(&row, "UUID").unwrap_or_else(|| identifier.clone()),
+                title: csv_field(&row, "Title").unwrap_or_else(|| identifier.clone()),
+                description: csv_field(&row, "Description"),
+                status: csv_field(&row, "Status").unwrap_or_else(|| "Todo".to_string()),
+                labels: csv_field(&row, "Labels").map(|l| split_list(&l)).unwrap_or_default(),
+                assignee: csv_field(&row, "Assignee"),
+                comments: Vec::new(),
+                url: None,
+                key: identifier,
+            },
+        ));
+    }
+    Ok(group_by_project(issues))
+}
+
+pub async fn fetch(credentials: &ImportCredentials) -> Result<Vec<ImportedProject>> {
+    let client = reqwest::Client::new();
+    let mut issues = Vec::new();
+    let mut cursor: Option<String> = None;
+    loop {
+        let response = client
+            .post(API_URL)
+            .header("Authorization", &credentials.token)
+            .json(&json!({
+                "query": ISSUES_QUERY,
+                "variables": { "team": credentials.project, "first": PAGE_SIZE, "after": cursor },
+            }))
+            .send()
+            .await
+            .context("Linear request failed")?;
+        if !response.status().is_success() {
+            return Err(anyhow!("Linear API error: {}", response.text().await.unwrap_or_default()));
+        }
+        let page: Value = response.json().await.context("Failed to parse Linear response")?;
+        if let Some(errors) = page.get("errors") {
+            return Err(anyhow!("Linear API error: {}", errors));
+        }
+        issues.extend(nodes_array(&page)?.iter().filter_map(parse_node));
+
+        let has_next = page.pointer("/data/issues/pageInfo/hasNextPage").and_then(|v| v.as_bool()).unwrap_or(false);
+        cursor = page.pointer("/data/issues/pageInfo/endCursor").and_then(|v| v.as_str()).map(|s| s.to_string());
+        if !has_next || cursor.is_none() || issues.len() >= MAX_API_ISSUES {
+            break;
+        }
+    }
+    Ok(group_by_project(issues))
+}
@@ -0,0 +1,292 @@
+//! Ticket import from Jira, Linear and GitHub Issues
+//!
+//! Each source is parsed (from an export or fetched via its API) into a common
+//! [`ImportedProject`] shape; `handlers::import` maps projects to epics and
+//! issues to tickets, either as a dry-run report or for real.
+
+mod github;
+mod jira;
+mod linear;
+
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::str::FromStr;
+
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+
+/// Max issues fetched from a source API in one import
+pub const MAX_API_ISSUES: usize = 2000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportSource {
+    Jira,
+    Linear,
+    Github,
+}
+
+impl ImportSource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ImportSource::Jira => "jira",
+            ImportSource::Linear => "linear",
+            ImportSource::Github => "github",
+        }
+    }
+}
+
+impl FromStr for ImportSource {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "jira" => Ok(ImportSource::Jira),
+            "linear" => Ok(ImportSource::Linear),
+            "github" => Ok(ImportSource::Github),
+            other => Err(anyhow!("Unknown import source '{}' (expected jira, linear or github)", other)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    Json,
+    Csv,
+}
+
+/// API access for fetching directly from the source instead of an export
+#[derive(Debug, Clone, Deserialize)]
+pub struct ImportCredentials {
+    pub token: String,
+    /// Jira account email (basic auth)
+    pub email: Option<String>,
+    /// Jira site, e.g. https://acme.atlassian.net; must be https on a public host
+    pub base_url: Option<String>,
+    /// Jira project key, Linear team key, or GitHub "owner/repo"
+    pub project: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ImportedComment {
+    pub author: String,
+    pub body: String,
+    pub created_at: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ImportedIssue {
+    /// Stable id in the source system, used to skip re-imports
+    pub external_id: String,
+    /// Human-facing key, e.g. PROJ-12, ENG-7, #42
+    pub key: String,
+    pub title: String,
+    pub description: Option<String>,
+    /// Status name as the source reports it
+    pub status: String,
+    pub labels: Vec<String>,
+    pub assignee: Option<String>,
+    pub comments: Vec<ImportedComment>,
+    pub url: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ImportedProject {
+    pub key: String,
+    pub name: String,
+    pub issues: Vec<ImportedIssue>,
+}
+
+/// Parse an exported JSON or CSV payload
+pub fn parse_export(source: ImportSource, format: ExportFormat, data: &str) -> Result<Vec<ImportedProject>> {
+    match (source, format) {
+        (ImportSource::Jira, ExportFormat::Json) => jira::parse_json(data),
+        (ImportSource::Jira, ExportFormat::Csv) => jira::parse_csv(data),
+        (ImportSource::Linear, ExportFormat::Json) => linear::parse_json(data),
+        (ImportSource::Linear, ExportFormat::Csv) => linear::parse_csv(data),
+        (ImportSource::Github, ExportFormat::Json) => github::parse_json(data),
+        (ImportSource::Github, ExportFormat::Csv) => github::parse_csv(data),
+    }
+}
+
+/// Fetch issues straight from the source's API
+pub async fn fetch(source: ImportSource, credentials: &ImportCredentials) -> Result<Vec<ImportedProject>> {
+    match source {
+        ImportSource::Jira => jira::fetch(credentials).await,
+        ImportSource::Linear => linear::fetch(credentials).await,
+        ImportSource::Github => github::fetch(credentials).await,
+    }
+}
+
+/// Map a source status name onto a ticket status. `overrides` (keyed by
+/// lowercase source status) wins over the built-in mapping.
+pub fn map_status(raw: &str, overrides: &HashMap<String, String>) -> Option<String> {
+    let normalized = raw.trim().to_lowercase();
+    if let Some(status) = overrides.get(&normalized) {
+        return Some(status.clone());
+    }
+    let status = match normalized.as_str() {
+        "to do" | "todo" | "open" | "backlog" | "new" | "triage" | "unstarted" | "selected for development" => "open",
+        "in progress" | "in review" | "started" | "review" | "doing" | "in development" => "in_progress",
+        "blocked" | "on hold" | "waiting" => "blocked",
+        "done" | "closed" | "resolved" | "completed" | "complete" | "fixed" => "completed",
+        "canceled" | "cancelled" | "won't do" | "wont do" | "won't fix" | "duplicate" | "not planned" => "cancelled",
+        _ => return None,
+    };
+    Some(status.to_string())
+}
+
+/// Epic id for an imported project, e.g. `jira-proj`
+pub fn epic_id_for(source: ImportSource, project_key: &str) -> String {
+    let slug: String = project_key
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect();
+    let slug = slug.split('-').filter(|s| !s.is_empty()).collect::<Vec<_>>().join("-");
+    format!("{}-{}", source.as_str(), slug)
+}
+
+/// Group issues by project key, preserving first-seen order
+fn group_by_project(issues: Vec<(String, String, ImportedIssue)>) -> Vec<ImportedProject> {
+    let mut projects: Vec<ImportedProject> = Vec::new();
+    for (key, name, issue) in issues {
+        match projects.iter_mut().find(|p| p.key == key) {
+            Some(project) => project.issues.push(issue),
+            None => projects.push(ImportedProject { key, name, issues: vec![issue] }),
+        }
+    }
+    projects
+}
+
+/// Read a CSV export into header-indexed rows. Repeated headers (Jira exports
+/// one "Labels"/"Comment" column per value) keep all their values in order.
+fn read_csv(data: &str) -> Result<Vec<HashMap<String, Vec<String>>>> {
+    let mut reader = csv::ReaderBuilder::new().flexible(true).from_reader(data.as_bytes());
+    let headers: Vec<String> = reader.headers()?.iter().map(|h| h.trim().to_string()).collect();
+
+    let mut rows = Vec::new();
+    for record in reader.records() {
+        let record = record?;
+        let mut row: HashMap<String, Vec<String>> = HashMap::new();
+        for (header, value) in headers.iter().zip(record.iter()) {
+            if !value.trim().is_empty() {
+                row.entry(header.clone()).or_default().push(value.trim().to_string());
+            }
+        }
+        rows.push(row);
+    }
+    Ok(rows)
+}
+
+fn csv_field(row: &HashMap<String, Vec<String>>, header: &str) -> Option<String> {
+    row.get(header).and_then(|v| v.first()).cloned()
+}
+
+fn csv_fields(row: &HashMap<String, Vec<String>>, header: &str) -> Vec<String> {
+    row.get(header).cloned().unwrap_or_default()
+}
+
+/// Split a delimited cell ("a, b" or "a;b") into trimmed values
+fn split_list(value: &str) -> Vec<String> {
+    value
+        .split([',', ';'])
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .collect()
+}
+
+fn non_empty(value: Option<String>) -> Option<String> {
+    value.filter(|v| !v.trim().is_empty())
+}
+
+/// Addresses only reachable from inside the server's network
+fn is_private_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            ip.is_private()
+                || ip.is_loopback()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                // Carrier-grade NAT, 100.64.0.0/10
+                || (a == 100 && (b & 0xc0) == 64)
+        }
+        IpAddr::V6(ip) => {
+            let first = ip.segments()[0];
+            ip.is_loopback()
+                || ip.is_unspecified()
+                // Unique local fc00::/7 and link-local fe80::/10
+                || (first & 0xfe00) == 0xfc00
+                || (first & 0xffc0) == 0xfe80
+                || ip.to_ipv4_mapped().is_some_and(|v4| is_private_ip(IpAddr::V4(v4)))
+        }
+    }
+}
+
+/// Check a site URL given with the credentials before the server requests
+/// it: https only, on a host that resolves to public addresses only
+pub(crate) async fn check_site_url(raw: &str) -> Result<reqwest::Url> {
+    let url = reqwest::Url::parse(raw).context("Invalid base_url")?;
+    if url.scheme() != "https" {
+        return Err(anyhow!("base_url must use https"));
+    }
+    let host = url.host_str().ok_or_else(|| anyhow!("base_url has no host"))?;
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    let port = url.port_or_known_default().unwrap_or(443);
+    let addresses: Vec<_> = tokio::net::lookup_host((host, port))
+        .await
+        .with_context(|| format!("Could not resolve {}", host))?
+        .collect();
+    if addresses.is_empty() || addresses.iter().any(|a| is_private_ip(a.ip())) {
+        return Err(anyhow!("base_url must be a public host"));
+    }
+    Ok(url)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn maps_common_statuses_and_overrides() {
+        let none = HashMap::new();
+        assert_eq!(map_status("In Progress", &none).as_deref(), Some("in_progress"));
+        assert_eq!(map_status("Won't Do", &none).as_deref(), Some("cancelled"));
+        assert_eq!(map_status("QA", &none), None);
+
+        let overrides = HashMap::from([("qa".to_string(), "blocked".to_string())]);
+        assert_eq!(map_status("QA", &overrides).as_deref(), Some("blocked"));
+    }
+
+    #[test]
+    fn flags_private_addresses() {
+        let private = [
+            "127.0.0.1", "10.1.2.3", "172.16.0.1", "192.168.1.1", "169.254.169.254", "100.64.0.1", "0.0.0.0",
+            "::1", "fd00::1", "fe80::1", "::ffff:10.0.0.1",
+        ];
+        for ip in private {
+            assert!(is_private_ip(ip.parse().unwrap()), "{}", ip);
+        }
+        for ip in ["104.192.136.1", "8.8.8.8", "2606:4700::1111"] {
+            assert!(!is_private_ip(ip.parse().unwrap()), "{}", ip);
+        }
+    }
+
+    #[tokio::test]
+    async fn rejects_insecure_and_private_site_urls() {
+        assert!(check_site_url("http://acme.atlassian.net").await.is_err());
+        assert!(check_site_url("not a url").await.is_err());
+        assert!(check_site_url("https://127.0.0.1").await.is_err());
+        assert!(check_site_url("https://[::1]:8443").await.is_err());
+        assert!(check_site_url("https://169.254.169.254/latest").await.is_err());
+    }
+
+    #[test]
+    fn epic_ids_are_slugged() {
+        assert_eq!(epic_id_for(ImportSource::Github, "acme/Web App"), "github-acme-web-app");
+        assert_eq!(epic_id_for(ImportSource::Jira, "PROJ"), "jira-proj");
+    }
+}
@@ -0,0 +1,197 @@
+//! Organization invitations
+//!
+//! An invitation is emailed as a link carrying a signed token,
+//! `<invitation_id>.<signature>`, where the signature is an HMAC-SHA256 over
+//! the invitation's id, organization, email, role and expiry, keyed by
+//! `INVITATION_SIGNING_KEY` (base64, at least 32 bytes). A token is only
+//! honored while it matches the stored invitation, so changing any of those
+//! fields, revoking the invitation or accepting it once invalidates the link.
+//!
+//! `~/.agentic-flowstate/invitations.json` configures the email:
+//! `{"email_from": "...", "accept_url": "https://app.example.com/invitations"}`.
+//! The token is appended to `accept_url` as `?token=`. Without the file,
+//! invitations are still created and the link is returned to the inviter.
+
+use anyhow::{anyhow, Context, Result};
+use base64::{
+    engine::general_purpose::{STANDARD as BASE64, URL_SAFE_NO_PAD},
+    Engine,
+};
+use hmac::{Hmac, Mac};
+use serde::Deserialize;
+use sha2::Sha256;
+use ticketing_system::{OrgInvitation, SqlitePool};
+
+const KEY_ENV: &str = "INVITATION_SIGNING_KEY";
+
+/// Days an invitation stays valid
+pub const INVITATION_VALID_DAYS: i64 = 7;
+
+pub const ROLE_ADMIN: &str = "admin";
+pub const ROLE_MEMBER: &str = "member";
+pub const ROLES: &[&str] = &[ROLE_ADMIN, ROLE_MEMBER];
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct InvitationConfig {
+    /// Sender of invitation emails; invitations aren't emailed without it
+    pub email_from: Option<String>,
+    /// Page of the web app that accepts invitations
+    pub accept_url: Option<String>,
+}
+
+/// Load the invitation config, falling back to defaults when none is configured
+pub fn load_config() -> Result<InvitationConfig> {
+    let path = dirs::home_dir()
+        .context("Could not determine home directory")?
+        .join(".agentic-flowstate")
+        .join("invitations.json");
+    if !path.exists() {
+        return Ok(InvitationConfig::default());
+    }
+    let content = std::fs::read_to_string(&path).context("Failed to read invitations config")?;
+    serde_json::from_str(&content).context("Failed to parse invitations config")
+}
+
+/// HMAC key from a base64 environment variable of at least 32 bytes. Also
+/// used for the signed approval links of `email_approvals`.
+pub(crate) fn signing_key_from(var: &str) -> Result<Vec<u8>> {
+    let encoded = std::env::var(var).with_context(|| format!("{} is not set", var))?;
+    let key = BASE64
+        .decode(encoded.trim())
+        .with_context(|| format!("{} is not valid base64", var))?;
+    if key.len() < 32 {
+        return Err(anyhow!("{} must decode to at least 32 bytes", var));
+    }
+    Ok(key)
+}
+
+fn signing_key() -> Result<Vec<u8>> {
+    signing_key_from(KEY_ENV)
+}
+
+/// Fields a token signs, in order
+fn signed_fields(invitation: &OrgInvitation) -> [&str; 5] {
+    [
+        &invitation.invitation_id,
+        &invitation.organization,
+        &invitation.email,
+        &invitation.role,
+        &invitation.expires_at,
+    ]
+}
+
+fn mac(key: &[u8], fields: &[&str]) -> Hmac<Sha256> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts any key length");
+    for field in fields {
+        mac.update(field.as_bytes());
+        mac.update(b"\n");
+    }
+    mac
+}
+
+/// `fields[0]` is the record's id, which leads the token
+pub(crate) fn sign_fields(key: &[u8], fields: &[&str]) -> String {
+    let signature = mac(key, fields).finalize().into_bytes();
+    format!("{}.{}", fields[0], URL_SAFE_NO_PAD.encode(signature))
+}
+
+pub(crate) fn verify_fields(key: &[u8], token: &str, fields: &[&str]) -> bool {
+    let Some((id, signature)) = token.split_once('.') else {
+        return false;
+    };
+    let Ok(signature) = URL_SAFE_NO_PAD.decode(signature) else {
+        return false;
+    };
+    id == fields[0] && mac(key, fields).verify_slice(&signature).is_ok()
+}
+
+/// Signed token for an invitation
+pub fn sign(invitation: &OrgInvitation) -> Result<String> {
+    Ok(sign_fields(&signing_key()?, &signed_fields(invitation)))
+}
+
+/// Invitation id a token claims to be for, before its signature is checked
+pub fn token_invitation_id(token: &str) -> Option<&str> {
+    token.split_once('.').map(|(id, _)| id).filter(|id| !id.is_empty())
+}
+
+/// Whether a token was signed for this invitation as it is stored now
+pub fn verify(token: &str, invitation: &OrgInvitation) -> Result<bool> {
+    Ok(verify_fields(&signing_key()?, token, &signed_fields(invitation)))
+}
+
+/// Why an invitation can no longer be accepted, if it can't
+pub fn unusable_reason(invitation: &OrgInvitation) -> Option<&'static str> {
+    if invitation.revoked_at.is_some() {
+        return Some("Invitation was revoked");
+    }
+    if invitation.accepted_by.is_some() {
+        return Some("Invitation was already used");
+    }
+    let expired = chrono::DateTime::parse_from_rfc3339(&invitation.expires_at)
+        .map(|t| t < chrono::Utc::now())
+        .unwrap_or(true);
+    expired.then_some("Invitation has expired")
+}
+
+/// Link the invitee opens, when an accept page is configured
+pub fn accept_link(config: &InvitationConfig, token: &str) -> Option<String> {
+    let base = config.accept_url.as_deref()?.trim_end_matches('/');
+    let separator = if base.contains('?') { '&' } else { '?' };
+    Some(format!("{}{}token={}", base, separator, token))
+}
+
+/// Email the invitation. Returns the SES message id, or `None` when no sender is configured.
+pub async fn send_invitation_email(
+    db: &SqlitePool,
+    config: &InvitationConfig,
+    invitation: &OrgInvitation,
+    inviter_name: &str,
+    token: &str,
+) -> std::result::Result<Option<String>, (axum::http::StatusCode, String)> {
+    let Some(from) = &config.email_from else {
+        return Ok(None);
+    };
+    let link = accept_link(config, token).unwrap_or_else(|| format!("Invitation token: {}", token));
+    let body = format!(
+        "{} invited you to join {} as {}.\n\nAccept the invitation:\n{}\n\nThis invitation expires on {}.",
+        inviter_name, invitation.organization, invitation.role, link, invitation.expires_at
+    );
+    let req = crate::handlers::SendEmailRequest {
+        to: vec![invitation.email.clone()],
+        cc: Vec::new(),
+        bcc: Vec::new(),
+        subject: format!("You're invited to join {}", invitation.organization),
+        body_text: Some(body),
+        body_html: None,
+        from: from.clone(),
+        reply_to: None,
+    };
+    crate::handlers::deliver_email(db, &invitation.organization, &req).await.map(Some)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokens_only_verify_for_the_fields_they_signed() {
+        let key = [7u8; 32];
+        let fields = ["inv-1", "acme", "dana@example.com", "member", "2026-01-01T00:00:00Z"];
+        let token = sign_fields(&key, &fields);
+        assert_eq!(token_invitation_id(&token), Some("inv-1"));
+        assert!(verify_fields(&key, &token, &fields));
+
+        let promoted = ["inv-1", "acme", "dana@example.com", "admin", "2026-01-01T00:00:00Z"];
+        assert!(!verify_fields(&key, &token, &promoted));
+        assert!(!verify_fields(&[8u8; 32], &token, &fields));
+        assert!(!verify_fields(&key, "inv-1.not-a-signature", &fields));
+    }
+
+    #[test]
+    fn builds_accept_links() {
+        let config = InvitationConfig { email_from: None, accept_url: Some("https://app.example.com/join/".to_string()) };
+        assert_eq!(accept_link(&config, "t.s").as_deref(), Some("https://app.example.com/join?token=t.s"));
+        assert_eq!(accept_link(&InvitationConfig::default(), "t.s"), None);
+    }
+}
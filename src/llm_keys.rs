@@ -0,0 +1,128 @@
+//! Organization-provided LLM keys
+//!
+//! An organization can bring its own Anthropic key, used by every Claude agent
+//! run, chat session and notes extraction done for it, and its own OpenAI key,
+//! used for Whisper transcription, so each team's usage is billed to its own
+//! account. Keys are encrypted like other secrets (see `crate::secrets`). An
+//! organization without a key, or whose key can't be decrypted, falls back to
+//! the process-level `ANTHROPIC_API_KEY` / `OPENAI_KEY`.
+
+use std::collections::HashMap;
+
+use ticketing_system::{llm_keys, SqlitePool};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LlmProvider {
+    Anthropic,
+    OpenAi,
+}
+
+impl LlmProvider {
+    pub const ALL: [LlmProvider; 2] = [LlmProvider::Anthropic, LlmProvider::OpenAi];
+
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value {
+            "anthropic" => Ok(LlmProvider::Anthropic),
+            "openai" => Ok(LlmProvider::OpenAi),
+            other => Err(format!("Unknown provider '{}', expected anthropic or openai", other)),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LlmProvider::Anthropic => "anthropic",
+            LlmProvider::OpenAi => "openai",
+        }
+    }
+
+    /// Process environment variable holding the shared key
+    pub fn env_var(&self) -> &'static str {
+        match self {
+            LlmProvider::Anthropic => "ANTHROPIC_API_KEY",
+            LlmProvider::OpenAi => "OPENAI_KEY",
+        }
+    }
+
+    /// Prefix keys issued by the provider start with, to catch pasting the wrong one
+    fn key_prefix(&self) -> &'static str {
+        match self {
+            LlmProvider::Anthropic => "sk-ant-",
+            LlmProvider::OpenAi => "sk-",
+        }
+    }
+
+    pub fn validate_key(&self, key: &str) -> Result<(), String> {
+        let key = key.trim();
+        if key.is_empty() || key.chars().any(char::is_whitespace) {
+            return Err("API key must be a single non-empty token".to_string());
+        }
+        if !key.starts_with(self.key_prefix()) {
+            return Err(format!("{} keys start with '{}'", self.as_str(), self.key_prefix()));
+        }
+        if *self == LlmProvider::OpenAi && key.starts_with(LlmProvider::Anthropic.key_prefix()) {
+            return Err("This is an Anthropic key".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Last characters of a key, enough to tell keys apart in listings
+pub fn key_hint(key: &str) -> String {
+    let chars: Vec<char> = key.trim().chars().collect();
+    let tail: String = chars[chars.len().saturating_sub(4)..].iter().collect();
+    format!("…{}", tail)
+}
+
+/// The organization's own key for a provider, if it has a usable one
+pub async fn org_key(db: &SqlitePool, organization: &str, provider: LlmProvider) -> Option<String> {
+    let stored = match llm_keys::get_key(db, organization, provider.as_str()).await {
+        Ok(stored) => stored?,
+        Err(e) => {
+            tracing::warn!("Failed to load {} key for {}: {}", provider.as_str(), organization, e);
+            return None;
+        }
+    };
+    match crate::secrets::decrypt(&stored.encrypted_value) {
+        Ok(key) => Some(key),
+        Err(e) => {
+            tracing::warn!(
+                "Ignoring {} key of {}, using the shared key: {}",
+                provider.as_str(), organization, e
+            );
+            None
+        }
+    }
+}
+
+/// Key to use for an organization: its own, else the process-level one
+pub async fn api_key(db: &SqlitePool, organization: &str, provider: LlmProvider) -> Option<String> {
+    match org_key(db, organization, provider).await {
+        Some(key) => Some(key),
+        None => std::env::var(provider.env_var()).ok().filter(|k| !k.trim().is_empty()),
+    }
+}
+
+/// Environment giving a Claude process the organization's Anthropic key.
+/// Empty when it has none, so the process inherits the shared key.
+pub async fn anthropic_env(db: &SqlitePool, organization: &str) -> HashMap<String, String> {
+    org_key(db, organization, LlmProvider::Anthropic)
+        .await
+        .map(|key| HashMap::from([(LlmProvider::Anthropic.env_var().to_string(), key)]))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validates_keys_per_provider() {
+        assert!(LlmProvider::Anthropic.validate_key("sk-ant-api03-abc").is_ok());
+        assert!(LlmProvider::Anthropic.validate_key("sk-proj-abc").is_err());
+        assert!(LlmProvider::OpenAi.validate_key("sk-proj-abc").is_ok());
+        assert!(LlmProvider::OpenAi.validate_key("sk-ant-api03-abc").is_err());
+        assert!(LlmProvider::OpenAi.validate_key("sk-a b").is_err());
+        assert_eq!(key_hint("sk-ant-api03-wxyz"), "…wxyz");
+        assert_eq!(key_hint("ab"), "…ab");
+    }
+}
@@ -0,0 +1,154 @@
+//! Log setup and request correlation
+//!
+//! Every request runs inside a `request` span carrying its request id, taken
+//! from an incoming `X-Request-Id` header or generated, and echoed back on the
+//! response. Background work started by a request is spawned inside an
+//! `agent_run` span (with `session_id` and `ticket_id`) or a `pipeline` span
+//! (with `ticket_id`), nested under the request span, so every line an agent
+//! run logs can be traced back to its run and the request that started it.
+//!
+//! Output is plain text by default. `{"format": "json"}` in
+//! `~/.agentic-flowstate/logging.json`, or `LOG_FORMAT=json`, switches to one
+//! JSON object per line with the span fields included.
+
+use std::future::Future;
+
+use anyhow::{Context, Result};
+use axum::{
+    extract::Request,
+    http::{HeaderName, HeaderValue},
+    middleware::Next,
+    response::Response,
+};
+use serde::Deserialize;
+use tracing::{Instrument, Span};
+use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+
+pub const REQUEST_ID_HEADER: HeaderName = HeaderName::from_static("x-request-id");
+
+/// Longest incoming request id that is kept rather than replaced
+const MAX_REQUEST_ID_LEN: usize = 128;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct LoggingConfig {
+    pub format: LogFormat,
+}
+
+/// Load the logging config, falling back to defaults when none is configured.
+/// `LOG_FORMAT` overrides the file.
+pub fn load_config() -> Result<LoggingConfig> {
+    let path = dirs::home_dir()
+        .context("Could not determine home directory")?
+        .join(".agentic-flowstate")
+        .join("logging.json");
+    let mut config = if path.exists() {
+        let content = std::fs::read_to_string(&path).context("Failed to read logging config")?;
+        serde_json::from_str(&content).context("Failed to parse logging config")?
+    } else {
+        LoggingConfig::default()
+    };
+    if let Ok(format) = std::env::var("LOG_FORMAT") {
+        config.format = match format.to_lowercase().as_str() {
+            "json" => LogFormat::Json,
+            "text" => LogFormat::Text,
+            other => anyhow::bail!("Unknown LOG_FORMAT '{}', expected text or json", other),
+        };
+    }
+    Ok(config)
+}
+
+/// Install the global subscriber
+pub fn init(config: &LoggingConfig) {
+    let filter = tracing_subscriber::EnvFilter::try_from_default_env()
+        .unwrap_or_else(|_| "agentic_api=debug,tower_http=info".into());
+    let registry = tracing_subscriber::registry().with(filter);
+    match config.format {
+        LogFormat::Text => registry.with(tracing_subscriber::fmt::layer()).init(),
+        LogFormat::Json => registry
+            .with(tracing_subscriber::fmt::layer().json().with_current_span(true).with_span_list(true))
+            .init(),
+    }
+}
+
+/// Incoming request id if it is short and printable, so it can't break log lines
+fn accept_request_id(value: &HeaderValue) -> Option<String> {
+    let id = value.to_str().ok()?.trim();
+    let valid = !id.is_empty()
+        && id.len() <= MAX_REQUEST_ID_LEN
+        && id.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | ':'));
+    valid.then(|| id.to_string())
+}
+
+/// Request id of the current request, available to handlers as an extension
+#[derive(Debug, Clone)]
+pub struct RequestId(pub String);
+
+/// Middleware running each request in a span tagged with its request id
+pub async fn request_id(mut request: Request, next: Next) -> Response {
+    let id = request
+        .headers()
+        .get(&REQUEST_ID_HEADER)
+        .and_then(accept_request_id)
+        .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+    let span = tracing::info_span!(
+        "request",
+        request_id = %id,
+        method = %request.method(),
+        path = %request.uri().path(),
+    );
+    request.extensions_mut().insert(RequestId(id.clone()));
+
+    let mut response = next.run(request).instrument(span).await;
+    if let Ok(value) = HeaderValue::from_str(&id) {
+        response.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
+    response
+}
+
+/// Span for work done on behalf of one agent run. A `ticket_id` not known yet
+/// can be filled in later with `Span::record`.
+pub fn agent_run_span(session_id: &str, ticket_id: Option<&str>) -> Span {
+    let span = tracing::info_span!("agent_run", session_id = %session_id, ticket_id = tracing::field::Empty);
+    if let Some(ticket_id) = ticket_id {
+        span.record("ticket_id", ticket_id);
+    }
+    span
+}
+
+/// Span for pipeline automation on a ticket outside any one agent run
+pub fn pipeline_span(ticket_id: &str) -> Span {
+    tracing::info_span!("pipeline", ticket_id = %ticket_id)
+}
+
+/// `tokio::spawn` inside `span`. Spans are created as children of the
+/// caller's current span, so the task stays tied to the request that started it.
+pub fn spawn_in_span<F>(span: Span, future: F) -> tokio::task::JoinHandle<F::Output>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    tokio::spawn(future.instrument(span))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_only_safe_incoming_request_ids() {
+        let accept = |v: &str| accept_request_id(&HeaderValue::from_str(v).unwrap());
+        assert_eq!(accept(" req-42.a:b_c ").as_deref(), Some("req-42.a:b_c"));
+        assert_eq!(accept("has space"), None);
+        assert_eq!(accept(""), None);
+        assert_eq!(accept(&"x".repeat(MAX_REQUEST_ID_LEN + 1)), None);
+    }
+}
@@ -0,0 +1,352 @@
+//! Login activity, anomaly alerts and lockout
+//!
+//! Every login attempt is recorded with the client's IP, user agent and a
+//! coarse device label ("Firefox on macOS"); users see their own with
+//! `GET /api/auth/activity`. A successful login from a device the user
+//! hasn't signed in from before, and a run of failed attempts, alert the
+//! account owner, and only them, on their user event stream. The stream only
+//! reaches open sessions, so alerts are also emailed to the owner's address
+//! when `alert_email` is configured. Enough failures in a row lock the account
+//! for a while.
+//!
+//! `~/.agentic-flowstate/login-security.json` sets the policy for the
+//! instance, e.g.
+//! `{"lockout": {"max_failures": 5, "window_mins": 15, "lockout_mins": 30}, "notify_failures": 3}`.
+//! `"lockout": null` turns lockout off, `"notify_failures": null` failure
+//! alerts. Behind a reverse proxy, `"trust_forwarded_for": true` takes the
+//! client IP from `X-Forwarded-For` instead of the connection.
+//! `"alert_email": {"from": "security@example.com", "organization": "acme"}`
+//! emails alerts from that sender, counted against the organization's email
+//! quota and suppressions.
+
+use std::net::SocketAddr;
+
+use anyhow::{Context, Result};
+use axum::http::{header, HeaderMap};
+use chrono::{DateTime, Duration, Utc};
+use serde::Deserialize;
+use ticketing_system::{login_attempts, CreateLoginAttemptRequest, LoginAttempt, SqlitePool};
+
+use crate::user_events::{send_to_user, UserEvent};
+
+pub const OUTCOME_SUCCESS: &str = "success";
+pub const OUTCOME_FAILURE: &str = "failure";
+/// Rejected without checking the password because the account was locked
+pub const OUTCOME_LOCKED: &str = "locked";
+
+/// Attempts looked at to count failures in a row
+const RECENT_ATTEMPTS: i64 = 50;
+
+const MAX_USER_AGENT_CHARS: usize = 512;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct LockoutPolicy {
+    /// Failures in a row, within `window_mins` of each other, that lock the account
+    pub max_failures: usize,
+    pub window_mins: i64,
+    pub lockout_mins: i64,
+}
+
+impl Default for LockoutPolicy {
+    fn default() -> Self {
+        Self { max_failures: 5, window_mins: 15, lockout_mins: 15 }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct AlertEmail {
+    pub from: String,
+    /// Organization whose email quota and suppressions the alerts count against
+    pub organization: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct LoginSecurityConfig {
+    pub lockout: Option<LockoutPolicy>,
+    /// Failures in a row that raise a notification
+    pub notify_failures: Option<usize>,
+    pub notify_new_device: bool,
+    pub trust_forwarded_for: bool,
+    /// Also email alerts to the account owner; off without it
+    pub alert_email: Option<AlertEmail>,
+}
+
+impl Default for LoginSecurityConfig {
+    fn default() -> Self {
+        Self {
+            lockout: Some(LockoutPolicy::default()),
+            notify_failures: Some(3),
+            notify_new_device: true,
+            trust_forwarded_for: false,
+            alert_email: None,
+        }
+    }
+}
+
+pub fn load_config() -> Result<LoginSecurityConfig> {
+    let path = dirs::home_dir()
+        .context("Could not determine home directory")?
+        .join(".agentic-flowstate")
+        .join("login-security.json");
+    if !path.exists() {
+        return Ok(LoginSecurityConfig::default());
+    }
+    let content = std::fs::read_to_string(&path).context("Failed to read login security config")?;
+    serde_json::from_str(&content).context("Failed to parse login security config")
+}
+
+/// Where a login attempt came from
+#[derive(Debug, Clone, Default)]
+pub struct ClientInfo {
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+    pub device: Option<String>,
+}
+
+impl ClientInfo {
+    pub fn from_request(headers: &HeaderMap, peer: SocketAddr, config: &LoginSecurityConfig) -> Self {
+        let forwarded = config
+            .trust_forwarded_for
+            .then(|| headers.get("x-forwarded-for").and_then(|v| v.to_str().ok()))
+            .flatten()
+            .and_then(|v| v.split(',').next())
+            .map(|ip| ip.trim().to_string())
+            .filter(|ip| !ip.is_empty());
+        let user_agent = headers
+            .get(header::USER_AGENT)
+            .and_then(|v| v.to_str().ok())
+            .map(|ua| ua.chars().take(MAX_USER_AGENT_CHARS).collect::<String>())
+            .filter(|ua| !ua.trim().is_empty());
+        Self {
+            ip: Some(forwarded.unwrap_or_else(|| peer.ip().to_string())),
+            device: user_agent.as_deref().map(device_label),
+            user_agent,
+        }
+    }
+}
+
+/// A coarse "browser on OS" label for a user agent, stable across browser updates
+pub fn device_label(user_agent: &str) -> String {
+    let browser = [
+        ("Edg/", "Edge"),
+        ("OPR/", "Opera"),
+        ("Firefox/", "Firefox"),
+        ("Chrome/", "Chrome"),
+        ("Safari/", "Safari"),
+    ]
+    .iter()
+    .find(|(marker, _)| user_agent.contains(marker))
+    .map(|(_, name)| name.to_string())
+    .unwrap_or_else(|| user_agent.split(['/', ' ']).next().unwrap_or_default().to_string());
+    // iOS and Android agents also say "Mac OS X" and "Linux"
+    let os = [
+        ("iPhone", "iOS"),
+        ("iPad", "iOS"),
+        ("Android", "Android"),
+        ("Windows", "Windows"),
+        ("Mac OS X", "macOS"),
+        ("CrOS", "ChromeOS"),
+        ("Linux", "Linux"),
+    ]
+    .iter()
+    .find(|(marker, _)| user_agent.contains(marker))
+    .map(|(_, name)| *name);
+    match (browser.is_empty(), os) {
+        (true, _) => "Unknown device".to_string(),
+        (false, Some(os)) => format!("{} on {}", browser, os),
+        (false, None) => browser,
+    }
+}
+
+/// When the account unlocks, given its failures in a row (newest first)
+pub fn locked_until(failures: &[DateTime<Utc>], policy: &LockoutPolicy, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    let newest = *failures.first()?;
+    let window_start = newest - Duration::minutes(policy.window_mins);
+    let in_window = failures.iter().take_while(|t| **t >= window_start).count();
+    if policy.max_failures == 0 || in_window < policy.max_failures {
+        return None;
+    }
+    Some(newest + Duration::minutes(policy.lockout_mins)).filter(|until| *until > now)
+}
+
+/// Times of the failures since the user's last successful login, newest first
+fn failures_in_a_row(attempts: &[LoginAttempt]) -> Vec<DateTime<Utc>> {
+    attempts
+        .iter()
+        .take_while(|a| a.outcome != OUTCOME_SUCCESS)
+        .filter(|a| a.outcome == OUTCOME_FAILURE)
+        .filter_map(|a| DateTime::parse_from_rfc3339(&a.created_at).ok())
+        .map(|t| t.with_timezone(&Utc))
+        .collect()
+}
+
+async fn recent_failures(db: &SqlitePool, user_id: &str) -> Result<Vec<DateTime<Utc>>> {
+    let attempts = login_attempts::list_attempts(db, user_id, RECENT_ATTEMPTS).await?;
+    Ok(failures_in_a_row(&attempts))
+}
+
+/// When the user's account unlocks, if it's locked
+pub async fn check_lockout(
+    db: &SqlitePool,
+    user_id: &str,
+    config: &LoginSecurityConfig,
+) -> Result<Option<DateTime<Utc>>> {
+    let Some(policy) = &config.lockout else {
+        return Ok(None);
+    };
+    Ok(locked_until(&recent_failures(db, user_id).await?, policy, Utc::now()))
+}
+
+async fn record(db: &SqlitePool, user_id: &str, outcome: &str, client: &ClientInfo) {
+    let req = CreateLoginAttemptRequest {
+        user_id: user_id.to_string(),
+        outcome: outcome.to_string(),
+        ip: client.ip.clone(),
+        user_agent: client.user_agent.clone(),
+        device: client.device.clone(),
+    };
+    if let Err(e) = login_attempts::record_attempt(db, &req).await {
+        tracing::warn!("Failed to record login attempt for {}: {}", user_id, e);
+    }
+}
+
+fn client_summary(client: &ClientInfo) -> String {
+    format!(
+        "{} from {}",
+        client.device.as_deref().unwrap_or("Unknown device"),
+        client.ip.as_deref().unwrap_or("an unknown address")
+    )
+}
+
+fn alert_subject(kind: &str) -> &'static str {
+    match kind {
+        "login_new_device" => "New sign-in to your account",
+        "login_locked" => "Your account has been locked",
+        _ => "Failed sign-ins to your account",
+    }
+}
+
+/// Email an alert to the account's address, if it has one. Failures are logged.
+async fn email_alert(db: &SqlitePool, config: &AlertEmail, user_id: &str, kind: &str, message: String) {
+    let email = match ticketing_system::auth::get_user_by_id(db, user_id).await {
+        Ok(Some(user)) => user.email.filter(|e| !e.trim().is_empty()),
+        Ok(None) => None,
+        Err(e) => {
+            tracing::warn!("Failed to load {} to email a login alert: {}", user_id, e);
+            None
+        }
+    };
+    let Some(email) = email else {
+        return;
+    };
+    let req = crate::handlers::SendEmailRequest {
+        to: vec![email],
+        cc: Vec::new(),
+        bcc: Vec::new(),
+        subject: alert_subject(kind).to_string(),
+        body_text: Some(message),
+        body_html: None,
+        from: config.from.clone(),
+        reply_to: None,
+    };
+    if let Err((_, e)) = crate::handlers::deliver_email(db, &config.organization, &req).await {
+        tracing::warn!("Failed to email {} alert to {}: {}", kind, user_id, e);
+    }
+}
+
+/// Alert the account owner about sign-in activity on their account, on their
+/// event stream and, when configured, by email sent in the background
+fn alert(db: &SqlitePool, config: &LoginSecurityConfig, user_id: &str, kind: &str, message: String, client: &ClientInfo) {
+    if let Some(email) = config.alert_email.clone() {
+        let (db, user_id, kind, message) = (db.clone(), user_id.to_string(), kind.to_string(), message.clone());
+        tokio::spawn(async move { email_alert(&db, &email, &user_id, &kind, message).await });
+    }
+    send_to_user(
+        user_id,
+        UserEvent::LoginAlert {
+            kind: kind.to_string(),
+            message,
+            device: client.device.clone(),
+            ip: client.ip.clone(),
+        },
+    );
+}
+
+/// Record a successful login, alerting when it's from a new device
+pub async fn record_success(db: &SqlitePool, user_id: &str, client: &ClientInfo, config: &LoginSecurityConfig) {
+    // Checked before recording, so this login doesn't count as having seen the device
+    let new_device = match login_attempts::known_devices(db, user_id).await {
+        // A first login ever isn't an anomaly
+        Ok(known) => !known.is_empty() && client.device.as_ref().is_some_and(|d| !known.contains(d)),
+        Err(e) => {
+            tracing::warn!("Failed to load known devices of {}: {}", user_id, e);
+            false
+        }
+    };
+    record(db, user_id, OUTCOME_SUCCESS, client).await;
+    if new_device && config.notify_new_device {
+        tracing::info!("Login for {} from a new device: {}", user_id, client_summary(client));
+        let message = format!(
+            "New sign-in to your account: {}. If this wasn't you, change your password.",
+            client_summary(client)
+        );
+        alert(db, config, user_id, "login_new_device", message, client);
+    }
+}
+
+/// Record a failed login, alerting once the failures in a row reach the
+/// alert threshold and again when they lock the account
+pub async fn record_failure(db: &SqlitePool, user_id: &str, client: &ClientInfo, config: &LoginSecurityConfig) {
+    record(db, user_id, OUTCOME_FAILURE, client).await;
+    let failures = match recent_failures(db, user_id).await {
+        Ok(failures) => failures,
+        Err(e) => {
+            tracing::warn!("Failed to count login failures of {}: {}", user_id, e);
+            return;
+        }
+    };
+    let locked = config.lockout.as_ref().and_then(|policy| {
+        (failures.len() == policy.max_failures).then(|| locked_until(&failures, policy, Utc::now())).flatten()
+    });
+    let (kind, summary) = if let Some(until) = locked {
+        tracing::warn!("Locked {} out until {} after {} failed logins", user_id, until, failures.len());
+        ("login_locked", format!("Your account is locked until {}", until.format("%H:%M UTC")))
+    } else if config.notify_failures == Some(failures.len()) {
+        ("login_failures", format!("{} failed sign-ins to your account", failures.len()))
+    } else {
+        return;
+    };
+    let message = format!("{}. Latest attempt: {}.", summary, client_summary(client));
+    alert(db, config, user_id, kind, message, client);
+}
+
+/// Record a login rejected because the account is locked; doesn't extend the lockout
+pub async fn record_locked(db: &SqlitePool, user_id: &str, client: &ClientInfo) {
+    record(db, user_id, OUTCOME_LOCKED, client).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn labels_devices_and_locks_after_repeated_failures() {
+        let mac_firefox = "Mozilla/5.0 (Macintosh; Intel Mac OS X 14.5; rv:128.0) Gecko/20100101 Firefox/128.0";
+        let iphone_safari = "Mozilla/5.0 (iPhone; CPU iPhone OS 17_5 like Mac OS X) AppleWebKit/605.1.15 \
+                             (KHTML, like Gecko) Version/17.5 Mobile/15E148 Safari/604.1";
+        assert_eq!(device_label(mac_firefox), "Firefox on macOS");
+        assert_eq!(device_label(iphone_safari), "Safari on iOS");
+        assert_eq!(device_label("curl/8.5.0"), "curl");
+
+        let policy = LockoutPolicy { max_failures: 3, window_mins: 15, lockout_mins: 30 };
+        let now = Utc::now();
+        let ago = |mins: i64| now - Duration::minutes(mins);
+        assert_eq!(locked_until(&[ago(1), ago(5), ago(10)], &policy, now), Some(ago(1) + Duration::minutes(30)));
+        // Spread over more than the window
+        assert_eq!(locked_until(&[ago(1), ago(5), ago(20)], &policy, now), None);
+        // Lockout already over
+        assert_eq!(locked_until(&[ago(40), ago(41), ago(42)], &policy, now), None);
+        assert_eq!(locked_until(&[], &policy, now), None);
+    }
+}
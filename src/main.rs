@@ -3,12 +3,64 @@ mod models;
 mod mcp_wrapper;
 mod agents;
 mod email_fetcher;
+mod email_bounces;
+mod email_digest;
+mod notifications;
+mod secrets;
+mod analytics;
+mod conversation_retention;
+mod research_corpus;
+mod embeddings;
+mod estimates;
+mod importers;
+mod meeting_followups;
 pub mod pipeline_automation;
 mod seed_templates;
 mod auth_middleware;
+mod tenancy;
+mod user_events;
+mod cors;
+mod reload;
+mod maintenance;
+mod deadlines;
+mod publish;
+mod database;
+mod redaction;
+mod quotas;
+mod context_packs;
+mod guest_access;
+mod email_steps;
+mod pipeline_status;
+mod etags;
+mod board;
+mod ndjson;
+mod logging;
+mod email_suppressions;
+mod invitations;
+mod llm_keys;
+mod pipeline_read_model;
+mod email_health;
+mod shadow_pipelines;
+mod plan_carryover;
+mod epic_pipelines;
+mod email_approvals;
+mod email_replies;
+mod saved_views;
+mod email_style;
+mod critical_path;
+mod storage_crypto;
+mod step_library;
+mod co_notes;
+mod email_auto_link;
+mod runners;
+mod runner_worker;
+mod conversation_memory;
+mod email_tickets;
+mod login_security;
+mod template_sharing;
 
 use axum::{
-    routing::{delete, get, patch, post},
+    routing::{delete, get, patch, post, put},
     Router,
     extract::DefaultBodyLimit,
 };
@@ -16,19 +68,17 @@ use std::sync::Arc;
 use tower_http::cors::{CorsLayer, AllowOrigin};
 use http::{header, Method};
 use tower_cookies::CookieManagerLayer;
-use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 use tokio::signal;
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     // Initialize tracing
-    tracing_subscriber::registry()
-        .with(
-            tracing_subscriber::EnvFilter::try_from_default_env()
-                .unwrap_or_else(|_| "agentic_api=debug,tower_http=info".into()),
-        )
-        .with(tracing_subscriber::fmt::layer())
-        .init();
+    logging::init(&logging::load_config()?);
+
+    // `agentic_api runner` executes pipeline steps for another server instead of serving
+    if std::env::args().nth(1).as_deref() == Some("runner") {
+        return runner_worker::run().await;
+    }
 
     tracing::info!("Starting Agentic API Server...");
 
@@ -36,10 +86,43 @@ async fn main() -> anyhow::Result<()> {
     mcp_wrapper::init_mcp_handler().await?;
     tracing::info!("MCP handler initialized");
 
+    // Sensitive columns are encrypted at rest once a storage key is configured
+    match storage_crypto::init()? {
+        Some(key_id) => tracing::info!("Storage encryption enabled with key {}", key_id),
+        None => tracing::warn!(
+            "STORAGE_ENCRYPTION_KEY is not set; email bodies, transcripts and messages are stored unencrypted"
+        ),
+    }
+
     // Initialize SQLite database pool
-    let db_pool = Arc::new(ticketing_system::init_db().await?);
+    let db_config = database::load_config()?;
+    let db_pool = Arc::new(database::connect(&db_config).await?);
     tracing::info!("SQLite database pool initialized");
 
+    // A damaged database stays readable but refuses writes until someone looks at it
+    match database::check_integrity(&db_pool, db_config.integrity_check).await {
+        Ok(problems) if problems.is_empty() => {}
+        Ok(problems) => {
+            for problem in &problems {
+                tracing::error!("Database integrity problem: {}", problem);
+            }
+            maintenance::set(
+                true,
+                Some("Database integrity check failed; the API is read-only until it is repaired.".to_string()),
+                "integrity-check",
+            );
+        }
+        Err(e) => {
+            tracing::error!("Database integrity check failed to run: {:?}", e);
+        }
+    }
+    database::start_wal_checkpoint_job(db_pool.clone(), &db_config);
+
+    // Users of an instance from before memberships keep their organizations
+    if let Err(e) = tenancy::bootstrap_memberships(&db_pool).await {
+        tracing::error!("Failed to bootstrap organization memberships: {:?}", e);
+    }
+
     // Mark any interrupted agent checkpoints from previous run
     match ticketing_system::checkpoints::mark_all_running_as_interrupted(&db_pool).await {
         Ok(count) if count > 0 => {
@@ -84,19 +167,68 @@ async fn main() -> anyhow::Result<()> {
         tracing::warn!("Failed to seed pipeline templates: {:?}", e);
     }
 
-    // Start email fetcher background task
+    // Blocking lives in blocked_by; move any blocks relations stored as rows onto it
+    handlers::migrate_blocks_relations(&db_pool).await;
+
+    // Start email fetcher background task (accounts can be reloaded later)
     match email_fetcher::load_email_accounts() {
         Ok(accounts) if !accounts.is_empty() => {
             tracing::info!("Starting email fetcher for {} account(s)", accounts.len());
-            email_fetcher::start_email_fetcher(db_pool.clone(), accounts);
+            email_fetcher::set_email_accounts(accounts).await;
         }
         Ok(_) => {
-            tracing::info!("No email accounts configured, email fetcher disabled");
+            tracing::info!("No email accounts configured, email fetcher idle");
         }
         Err(e) => {
             tracing::warn!("Failed to load email accounts: {:?}", e);
         }
     }
+    email_fetcher::start_email_fetcher(db_pool.clone());
+
+    // PII redaction rules for agent context (reloadable)
+    if let Err(e) = redaction::load_config() {
+        tracing::warn!("Failed to load redaction rules, using defaults: {:?}", e);
+    }
+
+    // Monthly usage quotas per organization (reloadable)
+    if let Err(e) = quotas::load_config() {
+        tracing::warn!("Failed to load usage quotas, leaving usage unlimited: {:?}", e);
+    }
+
+    // CORS allowlist (reloadable)
+    if let Err(e) = cors::load_allowed_origins() {
+        tracing::warn!("Failed to load CORS origins, using defaults: {:?}", e);
+    }
+
+    // Reload configuration on SIGHUP
+    reload::start_sighup_listener();
+
+    // Expire stale presence heartbeats
+    handlers::start_presence_sweeper();
+
+    // Pipeline analytics rollups (backfill + hourly refresh)
+    analytics::start_analytics_rollup_job(db_pool.clone());
+
+    // Embedding index for semantic search (no-op without a provider)
+    embeddings::start_embedding_indexer(db_pool.clone());
+
+    // Purge old conversations per ~/.agentic-flowstate/conversation-retention.json
+    conversation_retention::start_conversation_retention_job(db_pool.clone());
+
+    // Fold old messages of long conversations into their memory (hourly)
+    conversation_memory::start_memory_rollup_job(db_pool.clone());
+
+    // Due-soon / overdue ticket reminders
+    deadlines::start_deadline_reminder_job(db_pool.clone());
+
+    // Daily email digest per ~/.agentic-flowstate/email-digest.json
+    email_digest::start_email_digest_job(db_pool.clone());
+
+    // Morning daily plan snapshot and carry-over of unfinished items
+    plan_carryover::start_plan_carryover_job(db_pool.clone());
+
+    // Advance epic pipelines as their slices' ticket pipelines finish
+    epic_pipelines::start_epic_pipeline_job(db_pool.clone());
 
     // Clone db_pool for shutdown handler before building router (which moves db_pool)
     let shutdown_db = db_pool.clone();
@@ -108,6 +240,9 @@ async fn main() -> anyhow::Result<()> {
             let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(6 * 60 * 60));
             loop {
                 interval.tick().await;
+                if maintenance::is_enabled() {
+                    continue;
+                }
                 match ticketing_system::auth::cleanup_expired_sessions(&cleanup_pool).await {
                     Ok(count) if count > 0 => {
                         tracing::info!("Cleaned up {} expired session(s)", count);
@@ -127,27 +262,83 @@ async fn main() -> anyhow::Result<()> {
         .route("/api/auth/login", post(handlers::auth::login))
         .route("/api/auth/logout", post(handlers::auth::logout))
         .route("/api/auth/me", get(handlers::auth::me))
+        .route("/api/auth/guest-invitations/:token/accept", post(handlers::auth::accept_guest_invitation))
+        .route("/api/auth/invitations/:token", get(handlers::auth::get_org_invitation))
+        .route("/api/auth/invitations/:token/accept", post(handlers::auth::accept_org_invitation))
+        .route("/api/email-approvals/:token", get(handlers::get_email_approval))
+        .route("/api/email-approvals/:token/approve", post(handlers::approve_by_email))
+        .route("/api/email-approvals/:token/reject", post(handlers::reject_by_email))
+        .route("/api/runners/queue", get(handlers::claim_runner_job))
+        .route("/api/runners/runs/:run_id/events", post(handlers::report_runner_events))
         .route("/health", get(|| async { "OK" }));
 
     // Protected routes (require valid session)
     let protected_routes = Router::new()
         // Epic routes
         .route("/api/epics", get(handlers::list_epics).post(handlers::create_epic))
-        .route("/api/epics/:epic_id", get(handlers::get_epic).delete(handlers::delete_epic))
+        .route("/api/epics/order", put(handlers::reorder_epics))
+        .route("/api/epics/:epic_id",
+            get(handlers::get_epic)
+            .patch(handlers::update_epic_metadata)
+            .delete(handlers::delete_epic))
+        .route("/api/epics/:epic_id/critical-path", get(handlers::get_epic_critical_path))
+
+        // Epic pipeline routes
+        .route("/api/epics/:epic_id/pipelines",
+            get(handlers::list_epic_pipelines)
+            .post(handlers::create_epic_pipeline))
+        .route("/api/epic-pipelines/:epic_pipeline_id", get(handlers::get_epic_pipeline))
+        .route("/api/epic-pipelines/:epic_pipeline_id/cancel", post(handlers::cancel_epic_pipeline))
 
         // Slice routes
         .route("/api/epics/:epic_id/slices",
             get(handlers::list_slices)
             .post(handlers::create_slice))
+        .route("/api/epics/:epic_id/slices/order", put(handlers::reorder_slices))
         .route("/api/epics/:epic_id/slices/:slice_id",
             get(handlers::get_slice)
             .delete(handlers::delete_slice))
+        .route("/api/epics/:epic_id/slices/:slice_id/settings",
+            get(handlers::get_slice_settings)
+            .put(handlers::update_slice_settings))
+        .route("/api/epics/:epic_id/slices/:slice_id/board",
+            get(handlers::get_slice_board))
+        .route("/api/epics/:epic_id/slices/:slice_id/duplicates", get(handlers::get_slice_duplicates))
 
         // Ticket routes
         .route("/api/tickets", get(handlers::list_all_tickets))
+        .route("/api/views", get(handlers::list_views).post(handlers::create_view))
+        .route("/api/views/:view_id",
+            get(handlers::get_view)
+            .put(handlers::update_view)
+            .delete(handlers::delete_view))
+        .route("/api/views/:view_id/tickets", get(handlers::list_view_tickets))
         .route("/api/tickets/:ticket_id", get(handlers::get_ticket_by_id))
         .route("/api/tickets/:ticket_id/guidance", patch(handlers::update_ticket_guidance))
+        .route("/api/tickets/:ticket_id/due-date", put(handlers::update_ticket_due_date))
+        .route("/api/tickets/:ticket_id/clone", post(handlers::clone_ticket))
+        .route("/api/tickets/:ticket_id/merge", post(handlers::merge_ticket))
+        .route("/api/tickets/:ticket_id/similar", get(handlers::get_similar_tickets))
+        .route("/api/tickets/:ticket_id/split", post(handlers::split_ticket))
+        .route("/api/tickets/:ticket_id/relationships",
+            get(handlers::list_ticket_relationships)
+            .post(handlers::add_ticket_relationship))
+        .route("/api/tickets/:ticket_id/relationships/:relation/:target_ticket_id",
+            delete(handlers::remove_ticket_relationship))
+        .route("/api/tickets/:ticket_id/checklist",
+            get(handlers::list_checklist)
+            .post(handlers::create_checklist_item))
+        .route("/api/tickets/:ticket_id/checklist/:item_id",
+            patch(handlers::update_checklist_item)
+            .delete(handlers::delete_checklist_item))
+        .route("/api/tickets/:ticket_id/checklist/:item_id/toggle",
+            post(handlers::toggle_checklist_item))
         .route("/api/tickets/:ticket_id/history", get(handlers::get_ticket_history_by_id))
+        .route("/api/tickets/:ticket_id/comments",
+            get(handlers::list_ticket_comments)
+            .post(handlers::add_ticket_comment))
+        .route("/api/tickets/:ticket_id/estimate", post(handlers::estimate_ticket))
+        .route("/api/tickets/:ticket_id/pins", get(handlers::list_ticket_pins))
         .route("/api/epics/:epic_id/tickets", get(handlers::list_tickets))
         .route("/api/epics/:epic_id/slices/:slice_id/tickets",
             get(handlers::list_slice_tickets)
@@ -157,9 +348,6 @@ async fn main() -> anyhow::Result<()> {
             get(handlers::get_ticket_nested)
             .patch(handlers::update_ticket_nested)
             .delete(handlers::delete_ticket_nested))
-        .route("/api/epics/:epic_id/slices/:slice_id/tickets/:ticket_id/relationships",
-            post(handlers::add_relationship_nested)
-            .delete(handlers::remove_relationship_nested))
         .route("/api/epics/:epic_id/slices/:slice_id/tickets/:ticket_id/history",
             get(handlers::get_ticket_history))
 
@@ -173,19 +361,142 @@ async fn main() -> anyhow::Result<()> {
             get(handlers::get_active_agent_run))
         .route("/api/agent-runs/:session_id",
             get(handlers::get_agent_run))
+        .route("/api/agent-runs/:session_id/context",
+            get(handlers::get_agent_run_context))
+        .route("/api/agent-runs/:session_id/tools",
+            get(handlers::list_agent_run_tools))
         .route("/api/agent-runs/:session_id/stream",
             get(handlers::reconnect_agent_stream))
+        .route("/api/agent-runs/:session_id/events",
+            get(handlers::poll_agent_run_events))
         .route("/api/agent-runs/:session_id/message",
             post(handlers::send_message_to_agent))
+        .route("/api/agent-runs/:session_id/approve-tool",
+            post(handlers::approve_tool_use))
+        .route("/api/agent-runs/:session_id/export",
+            get(handlers::export_agent_run))
+        .route("/api/agent-runs/:session_id/feedback",
+            get(handlers::list_agent_feedback)
+            .post(handlers::submit_agent_feedback))
+        .route("/api/agent-runs/:session_id/pin",
+            post(handlers::pin_agent_output)
+            .delete(handlers::unpin_agent_output))
+        .route("/api/agent-feedback/stats", get(handlers::get_agent_feedback_stats))
+        .route("/api/tool-audit/stats", get(handlers::get_tool_usage_stats))
+
+        // Account routes
+        .route("/api/auth/activity", get(handlers::auth::activity))
+
+        // Notification routes
+        .route("/api/notifications", get(handlers::list_notifications))
+        .route("/api/notifications/:id/read", post(handlers::mark_notification_read))
+
+        // Presence routes
+        .route("/api/presence", get(handlers::get_presence))
+        .route("/api/presence/heartbeat", post(handlers::presence_heartbeat))
+        .route("/api/presence/leave", post(handlers::presence_leave))
+        .route("/api/presence/stream", get(handlers::stream_presence))
+
+        // Analytics routes
+        .route("/api/analytics/pipelines", get(handlers::get_pipeline_analytics))
+        .route("/api/analytics/refresh", post(handlers::refresh_pipeline_analytics))
+
+        // Secret routes (values are write-only)
+        .route("/api/secrets",
+            get(handlers::list_secrets)
+            .post(handlers::put_secret))
+        .route("/api/secrets/:name", delete(handlers::delete_secret))
+        .route("/api/llm-keys", get(handlers::list_llm_keys))
+        .route("/api/llm-keys/:provider",
+            put(handlers::put_llm_key)
+            .delete(handlers::delete_llm_key))
+
+        // Research corpus routes
+        .route("/api/research-docs",
+            get(handlers::list_research_docs)
+            .post(handlers::create_research_doc))
+        .route("/api/research-docs/:doc_id",
+            get(handlers::get_research_doc)
+            .delete(handlers::delete_research_doc))
+
+        // Admin routes
+        .route("/api/admin/reload", post(handlers::reload_config))
+        .route("/api/admin/conversation-retention", post(handlers::run_conversation_retention))
+        .route("/api/admin/storage-encryption/rotate", post(handlers::rotate_storage_key))
+        .route("/api/admin/maintenance",
+            get(handlers::get_maintenance)
+            .put(handlers::set_maintenance))
+        .route("/api/admin/stats", get(handlers::get_admin_stats))
+
+        // Guest collaborator routes
+        .route("/api/guests", get(handlers::list_guests))
+        .route("/api/guests/invitations", post(handlers::invite_guest))
+        .route("/api/guests/:user_id/shares", put(handlers::update_guest_shares))
+        .route("/api/guests/:user_id", delete(handlers::revoke_guest))
+        .route("/api/guest/tickets", get(handlers::list_shared_tickets))
+
+        // Context pack routes
+        .route("/api/context-packs",
+            get(handlers::list_context_packs)
+            .post(handlers::create_context_pack))
+        .route("/api/context-packs/:pack_id",
+            get(handlers::get_context_pack)
+            .put(handlers::update_context_pack)
+            .delete(handlers::delete_context_pack))
+
+        // Semantic search routes
+        .route("/api/semantic-search", get(handlers::semantic_search))
+
+        // Import routes
+        .route("/api/import/:source", post(handlers::import_tickets))
+
+        // Plan routes
+        .route("/api/plans/apply", post(handlers::apply_plan))
+
+        // Organization settings routes
+        .route("/api/org-settings",
+            get(handlers::get_org_settings)
+            .put(handlers::update_org_settings))
+        .route("/api/organizations/:org/usage", get(handlers::get_organization_usage))
+        .route("/api/organizations/:org/invitations",
+            get(handlers::list_org_invitations)
+            .post(handlers::create_org_invitation))
+        .route("/api/organizations/:org/invitations/:invitation_id", delete(handlers::revoke_org_invitation))
 
         // Email routes
         .route("/api/emails", get(handlers::list_emails))
         .route("/api/emails/send", post(handlers::send_email))
         .route("/api/emails/stats", get(handlers::get_email_stats))
+        .route("/api/emails/digest", post(handlers::generate_email_digest))
+        .route("/api/emails/digests", get(handlers::list_email_digests))
         .route("/api/emails/:id",
             get(handlers::get_email)
             .patch(handlers::update_email)
             .delete(handlers::delete_email))
+        .route("/api/emails/:id/suggested-replies", get(handlers::get_suggested_replies))
+        .route("/api/emails/:id/suggested-replies/:kind/accept", post(handlers::accept_suggested_reply))
+        .route("/api/emails/:id/create-ticket", post(handlers::create_ticket_from_email))
+        .route("/api/email-accounts/:mailbox/grants", get(handlers::list_email_grants))
+        .route("/api/email-accounts/:mailbox/health", get(handlers::get_email_account_health))
+        .route("/api/email-accounts/:mailbox/grants/:user_id",
+            put(handlers::set_email_grant)
+            .delete(handlers::delete_email_grant))
+
+        // Email template routes
+        .route("/api/email-templates",
+            get(handlers::list_email_templates)
+            .post(handlers::create_email_template))
+        .route("/api/email-templates/:template_id",
+            get(handlers::get_email_template)
+            .put(handlers::update_email_template)
+            .delete(handlers::delete_email_template))
+        .route("/api/email-templates/:template_id/render", post(handlers::render_email_template))
+
+        // Email suppression routes
+        .route("/api/email-suppressions",
+            get(handlers::list_email_suppressions)
+            .post(handlers::add_email_suppression))
+        .route("/api/email-suppressions/:address", delete(handlers::remove_email_suppression))
 
         // Draft routes
         .route("/api/drafts",
@@ -199,6 +510,12 @@ async fn main() -> anyhow::Result<()> {
             post(handlers::update_draft_status))
         .route("/api/drafts/:id/send",
             post(handlers::send_draft))
+        .route("/api/drafts/generate-variants",
+            post(handlers::generate_draft_variants))
+        .route("/api/drafts/variants/:group_id",
+            get(handlers::list_draft_variants))
+        .route("/api/drafts/variants/:group_id/select",
+            post(handlers::select_draft_variant))
 
         // Email thread-ticket linking routes
         .route("/api/email-threads/:thread_id/tickets",
@@ -206,6 +523,10 @@ async fn main() -> anyhow::Result<()> {
             .post(handlers::link_thread_to_ticket))
         .route("/api/email-threads/:thread_id/tickets/:ticket_id",
             delete(handlers::unlink_thread_from_ticket))
+        .route("/api/email-threads/:thread_id/auto-links",
+            get(handlers::list_thread_auto_links))
+        .route("/api/email-threads/:thread_id/auto-links/:link_id/undo",
+            post(handlers::undo_thread_auto_link))
 
         // Transcript routes
         .route("/api/transcripts",
@@ -217,6 +538,12 @@ async fn main() -> anyhow::Result<()> {
             post(handlers::end_session))
         .route("/api/transcripts/:session_id/entries",
             post(handlers::add_entry))
+        .route("/api/transcripts/:session_id/entries/batch",
+            post(handlers::add_entries_batch))
+        .route("/api/transcripts/:session_id/entries/:entry_id",
+            patch(handlers::update_entry))
+        .route("/api/transcripts/:session_id/edits",
+            get(handlers::list_entry_edits))
         .route("/api/transcripts/:session_id/stream",
             get(handlers::stream_session))
 
@@ -225,6 +552,11 @@ async fn main() -> anyhow::Result<()> {
             post(handlers::workspace_manager_chat))
         .route("/api/workspace-manager/resume",
             post(handlers::workspace_manager_resume))
+        .route("/api/workspace-manager/context",
+            get(handlers::list_pinned_context)
+            .post(handlers::pin_context))
+        .route("/api/workspace-manager/context/:pin_id",
+            delete(handlers::unpin_context))
 
         // Life Planner routes
         .route("/api/life-planner/chat",
@@ -237,6 +569,9 @@ async fn main() -> anyhow::Result<()> {
             get(handlers::list_project_workload))
         .route("/api/project-workload/pull",
             post(handlers::pull_project_ticket))
+        .route("/api/project-workload/capacity",
+            get(handlers::get_workload_capacity)
+            .put(handlers::update_workload_capacity))
         .route("/api/project-workload/toggle",
             post(handlers::toggle_project_workload))
         .route("/api/project-workload/:id",
@@ -245,6 +580,8 @@ async fn main() -> anyhow::Result<()> {
         // Daily Plan routes
         .route("/api/daily-plan",
             get(handlers::get_daily_plan))
+        .route("/api/daily-plan/history",
+            get(handlers::get_daily_plan_history))
         .route("/api/daily-plan/toggle",
             post(handlers::toggle_daily_plan_item))
         .route("/api/daily-plan/items",
@@ -262,15 +599,44 @@ async fn main() -> anyhow::Result<()> {
             .post(handlers::create_conversation))
         .route("/api/conversations/subscribe",
             get(handlers::subscribe_conversations))
+        .route("/api/conversations/search",
+            get(handlers::search_conversations))
         .route("/api/conversations/:id",
             get(handlers::get_conversation)
             .patch(handlers::update_conversation)
             .delete(handlers::delete_conversation))
+        .route("/api/conversations/:id/archive",
+            post(handlers::archive_conversation))
+        .route("/api/conversations/:id/unarchive",
+            post(handlers::unarchive_conversation))
+        .route("/api/conversations/:id/memory",
+            get(handlers::get_conversation_memory)
+            .delete(handlers::reset_conversation_memory))
         .route("/api/conversations/:id/messages",
             get(handlers::list_messages)
             .post(handlers::add_message))
+        .route("/api/conversations/:id/messages/search",
+            get(handlers::search_conversation_messages))
         .route("/api/conversations/:conv_id/messages/:message_id",
             patch(handlers::update_message))
+        .route("/api/conversations/:conv_id/messages/:message_id/reactions",
+            get(handlers::list_message_reactions)
+            .post(handlers::add_message_reaction))
+        .route("/api/conversations/:conv_id/messages/:message_id/reactions/:emoji",
+            delete(handlers::remove_message_reaction))
+        .route("/api/conversations/:conv_id/messages/:message_id/bookmark",
+            put(handlers::bookmark_message)
+            .delete(handlers::unbookmark_message))
+        .route("/api/bookmarks", get(handlers::list_bookmarks))
+        .route("/api/conversations/:id/stream",
+            get(handlers::reconnect_conversation_stream))
+        .route("/api/conversations/:id/recover",
+            post(handlers::recover_conversation))
+        .route("/api/conversations/:id/attachments",
+            get(handlers::list_conversation_attachments)
+            .post(handlers::upload_conversation_attachment))
+        .route("/api/conversations/:id/attachments/:attachment_id",
+            delete(handlers::delete_conversation_attachment))
 
         // Pipeline template routes
         .route("/api/pipeline-templates",
@@ -279,14 +645,47 @@ async fn main() -> anyhow::Result<()> {
         .route("/api/pipeline-templates/:template_id",
             get(handlers::get_template)
             .delete(handlers::delete_template))
+        .route("/api/pipeline-templates/:template_id/export",
+            get(handlers::export_template))
+        .route("/api/pipeline-templates/:template_id/shadow-runs",
+            get(handlers::list_template_shadow_runs))
+        .route("/api/pipeline-templates/:template_id/sharing",
+            put(handlers::set_template_sharing))
+        .route("/api/pipeline-templates/:template_id/overrides",
+            get(handlers::get_template_overrides)
+            .put(handlers::put_template_overrides)
+            .delete(handlers::delete_template_overrides))
+
+        // Step library routes
+        .route("/api/step-library",
+            get(handlers::list_library_steps)
+            .post(handlers::create_library_step))
+        .route("/api/step-library/:library_step_id",
+            get(handlers::get_library_step)
+            .put(handlers::update_library_step)
+            .delete(handlers::delete_library_step))
+        .route("/api/step-library/:library_step_id/versions",
+            get(handlers::list_library_step_versions))
+
+        // Pipeline overview
+        .route("/api/pipelines/active", get(handlers::list_active_pipelines))
+
+        // Remote runners (runner endpoints are public, token-authenticated)
+        .route("/api/runners", get(handlers::list_runners))
 
         // Ticket pipeline routes
+        .route("/api/tickets/:ticket_id/pipeline/status",
+            get(handlers::get_ticket_pipeline_status))
         .route("/api/tickets/:ticket_id/pipeline",
             get(handlers::get_ticket_pipeline)
             .post(handlers::set_ticket_pipeline)
             .delete(handlers::delete_ticket_pipeline))
         .route("/api/tickets/:ticket_id/pipeline/run",
             post(handlers::run_pipeline))
+        .route("/api/tickets/:ticket_id/pipeline/attach-and-run",
+            post(handlers::attach_and_run_pipeline))
+        .route("/api/tickets/:ticket_id/shadow-runs",
+            get(handlers::list_ticket_shadow_runs))
 
         // Pipeline step operations
         .route("/api/tickets/:ticket_id/pipeline/steps/:step_id/start",
@@ -297,8 +696,12 @@ async fn main() -> anyhow::Result<()> {
             post(handlers::fail_step))
         .route("/api/tickets/:ticket_id/pipeline/steps/:step_id/approve",
             post(handlers::approve_step))
+        .route("/api/tickets/:ticket_id/pipeline/steps/:step_id/comments",
+            post(handlers::add_step_comment))
         .route("/api/tickets/:ticket_id/pipeline/steps/:step_id/reject",
             post(handlers::reject_step))
+        .route("/api/tickets/:ticket_id/pipeline/steps/:step_id/skip",
+            post(handlers::skip_step))
         .route("/api/tickets/:ticket_id/pipeline/steps/:step_id/retry",
             post(handlers::retry_step))
         .route("/api/tickets/:ticket_id/pipeline/steps/:step_id/agent-run",
@@ -306,11 +709,14 @@ async fn main() -> anyhow::Result<()> {
 
         // Data events SSE (live updates)
         .route("/api/data/subscribe", get(handlers::subscribe_data))
+        .route("/api/users/me/subscribe", get(handlers::subscribe_user_events))
 
         // Meeting routes
         .route("/api/meetings",
             get(handlers::list_meetings)
             .post(handlers::create_meeting))
+        .route("/api/meetings/search",
+            get(handlers::search_meetings))
         .route("/api/meetings/signaling",
             get(handlers::signaling_websocket))
         .route("/api/meetings/:room_id",
@@ -327,23 +733,39 @@ async fn main() -> anyhow::Result<()> {
             post(handlers::upload_meeting_audio))
         .route("/api/meetings/:room_id/finalize-transcript",
             post(handlers::finalize_meeting_transcript))
+        .route("/api/meetings/:room_id/notes",
+            get(handlers::get_co_notes)
+            .patch(handlers::patch_co_notes))
+        .route("/api/meetings/:room_id/notes/stream",
+            get(handlers::stream_co_notes))
+        .route("/api/meetings/:room_id/extract-notes",
+            post(handlers::reextract_meeting_notes))
+        .route("/api/meetings/:room_id/transcription-status",
+            get(handlers::get_transcription_status))
+        .route("/api/meetings/:room_id/transcription-status/stream",
+            get(handlers::stream_transcription_status))
         .route("/api/meetings/:room_id/favorite",
             post(handlers::toggle_meeting_favorite))
-
+        .route("/api/meetings/:room_id/push-to-daily-plan",
+            post(handlers::push_meeting_to_daily_plan))
+        .route("/api/meetings/:room_id/follow-up-pipeline",
+            put(handlers::set_meeting_follow_up_pipeline)
+            .delete(handlers::clear_meeting_follow_up_pipeline))
+
+        .layer(axum::middleware::from_fn(etags::conditional_get))
+        .layer(axum::middleware::from_fn_with_state(db_pool.clone(), tenancy::authorize_path_resources))
+        .layer(axum::middleware::from_fn_with_state(db_pool.clone(), tenancy::resolve_organization))
         .layer(axum::middleware::from_fn_with_state(db_pool.clone(), auth_middleware::require_auth));
 
     let app = public_routes
         .merge(protected_routes)
         .with_state(db_pool)
+        .layer(axum::middleware::from_fn(maintenance::reject_mutations))
         .layer(DefaultBodyLimit::max(2 * 1024 * 1024 * 1024)) // 2GB - never lose a session due to size limits
         .layer(CookieManagerLayer::new())
         .layer(
             CorsLayer::new()
-                .allow_origin(AllowOrigin::list([
-                    "http://localhost:3000".parse().unwrap(),
-                    "http://100.119.87.128:3000".parse().unwrap(),
-                    "https://jarviss-mac-mini-1.tail3da916.ts.net".parse().unwrap(),
-                ]))
+                .allow_origin(AllowOrigin::predicate(|origin, _| cors::is_allowed_origin(origin)))
                 .allow_credentials(true)
                 .allow_methods([
                     Method::GET,
@@ -358,13 +780,19 @@ async fn main() -> anyhow::Result<()> {
                     header::ACCEPT,
                     header::AUTHORIZATION,
                     header::COOKIE,
+                    header::IF_NONE_MATCH,
                     header::HeaderName::from_static("x-organization"),
+                    logging::REQUEST_ID_HEADER,
                 ])
                 .expose_headers([
                     header::SET_COOKIE,
                     header::CONTENT_TYPE,
+                    header::ETAG,
+                    header::HeaderName::from_static("x-forked-conversation"),
+                    logging::REQUEST_ID_HEADER,
                 ]),
-        );
+        )
+        .layer(axum::middleware::from_fn(logging::request_id));
 
     // Start the server - bind to 0.0.0.0 to allow access from other devices (mobile via Tailscale)
     let addr = "0.0.0.0:8001";
@@ -372,7 +800,7 @@ async fn main() -> anyhow::Result<()> {
     tracing::info!("Server running on http://{}", addr);
 
     // Run server with graceful shutdown
-    axum::serve(listener, app)
+    axum::serve(listener, app.into_make_service_with_connect_info::<std::net::SocketAddr>())
         .with_graceful_shutdown(shutdown_signal(shutdown_db))
         .await?;
 
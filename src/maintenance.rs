@@ -0,0 +1,92 @@
+//! Maintenance (read-only) mode
+//!
+//! While enabled, mutating requests are rejected with 503 so the operator can
+//! back up or migrate the SQLite file with the frontend still up. Agent runs
+//! already in flight finish normally; background jobs skip their passes and
+//! pipelines hold their next auto step for approval instead of starting it.
+//! The flag is in memory only, so a restart always comes back writable.
+
+use std::sync::RwLock;
+
+use axum::{
+    extract::Request,
+    http::{header, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::Serialize;
+use serde_json::json;
+
+const DEFAULT_MESSAGE: &str = "The API is in maintenance mode and is read-only. Please try again shortly.";
+
+/// Seconds clients are told to wait before retrying a rejected mutation
+const RETRY_AFTER_SECS: &str = "60";
+
+/// Mutations still allowed while read-only, so an admin can sign in and turn the mode off again
+const EXEMPT_PATHS: &[&str] = &["/api/admin/maintenance", "/api/auth/login", "/api/auth/logout"];
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct MaintenanceStatus {
+    pub enabled: bool,
+    pub message: Option<String>,
+    pub since: Option<String>,
+    pub enabled_by: Option<String>,
+}
+
+lazy_static::lazy_static! {
+    static ref STATUS: RwLock<MaintenanceStatus> = RwLock::new(MaintenanceStatus::default());
+}
+
+pub fn is_enabled() -> bool {
+    STATUS.read().unwrap().enabled
+}
+
+pub fn status() -> MaintenanceStatus {
+    STATUS.read().unwrap().clone()
+}
+
+/// Turn maintenance mode on or off. Re-enabling keeps the original `since`.
+pub fn set(enabled: bool, message: Option<String>, user: &str) -> MaintenanceStatus {
+    let mut status = STATUS.write().unwrap();
+    if enabled {
+        if !status.enabled {
+            status.since = Some(chrono::Utc::now().to_rfc3339());
+            status.enabled_by = Some(user.to_string());
+        }
+        status.enabled = true;
+        status.message = message.filter(|m| !m.trim().is_empty());
+        tracing::warn!("Maintenance mode enabled by {}", user);
+    } else {
+        if status.enabled {
+            tracing::warn!("Maintenance mode disabled by {}", user);
+        }
+        *status = MaintenanceStatus::default();
+    }
+    status.clone()
+}
+
+fn is_mutation(method: &Method) -> bool {
+    !matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS)
+}
+
+/// Middleware rejecting mutations with 503 while maintenance mode is on
+pub async fn reject_mutations(request: Request, next: Next) -> Response {
+    if !is_mutation(request.method()) || EXEMPT_PATHS.contains(&request.uri().path()) {
+        return next.run(request).await;
+    }
+    let status = STATUS.read().unwrap().clone();
+    if !status.enabled {
+        return next.run(request).await;
+    }
+    (
+        StatusCode::SERVICE_UNAVAILABLE,
+        [(header::RETRY_AFTER, RETRY_AFTER_SECS)],
+        Json(json!({
+            "error": status.message.as_deref().unwrap_or(DEFAULT_MESSAGE),
+            "maintenance": true,
+            "since": status.since,
+        })),
+    )
+        .into_response()
+}
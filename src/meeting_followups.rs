@@ -0,0 +1,221 @@
+//! Meeting follow-ups → daily plan and pipelines
+//!
+//! The meeting-notes agent ends its notes with a `**Follow-ups**` list, each
+//! item tagged `[today]`, `[tomorrow]` or `[later]`. Dated follow-ups can be
+//! pushed to the daily plan as one-off items, automatically after extraction
+//! (when the organization enables it) or on demand.
+//!
+//! A meeting can also designate a follow-up ticket and pipeline template.
+//! Once its notes are extracted, the template is attached to that ticket and
+//! run, with the notes as inputs of the first step.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use anyhow::Result;
+use axum::{extract::{Path, State}, Json};
+use chrono::{Duration, NaiveDate};
+use serde::Serialize;
+use ticketing_system::{daily_plan, pipelines, CreateDailyPlanDateItemRequest, DailyPlanDateItem, SqlitePool};
+
+use crate::handlers::{attach_and_run_pipeline, AttachAndRunRequest};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FollowUpDue {
+    Today,
+    Tomorrow,
+    Later,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FollowUp {
+    pub title: String,
+    pub due: FollowUpDue,
+}
+
+impl FollowUp {
+    /// Plan date relative to `today`; `None` for undated follow-ups
+    pub fn date(&self, today: NaiveDate) -> Option<NaiveDate> {
+        match self.due {
+            FollowUpDue::Today => Some(today),
+            FollowUpDue::Tomorrow => Some(today + Duration::days(1)),
+            FollowUpDue::Later => None,
+        }
+    }
+}
+
+/// Parse the `**Follow-ups**` section of extracted meeting notes
+pub fn parse_follow_ups(notes: &str) -> Vec<FollowUp> {
+    let Some(start) = notes.find("**Follow-ups**") else {
+        return Vec::new();
+    };
+    notes[start..]
+        .lines()
+        .skip(1)
+        .map(str::trim)
+        .take_while(|line| !line.starts_with("**"))
+        .filter_map(|line| {
+            let item = line.strip_prefix("- ").or_else(|| line.strip_prefix("* "))?.trim();
+            let (due, title) = match item.split_once(']') {
+                Some((tag, rest)) if tag.starts_with('[') => {
+                    let due = match tag[1..].trim().to_lowercase().as_str() {
+                        "today" => FollowUpDue::Today,
+                        "tomorrow" => FollowUpDue::Tomorrow,
+                        _ => FollowUpDue::Later,
+                    };
+                    (due, rest.trim())
+                }
+                _ => (FollowUpDue::Later, item),
+            };
+            (!title.is_empty()).then(|| FollowUp { title: title.to_string(), due })
+        })
+        .collect()
+}
+
+/// Create daily-plan items for follow-ups, skipping any already on that day's plan.
+/// Undated follow-ups go on `undated_date` if given and are skipped otherwise.
+pub async fn push_to_daily_plan(
+    db: &SqlitePool,
+    follow_ups: &[FollowUp],
+    meeting_title: Option<&str>,
+    today: NaiveDate,
+    undated_date: Option<NaiveDate>,
+) -> Result<Vec<DailyPlanDateItem>> {
+    let mut created = Vec::new();
+    for follow_up in follow_ups {
+        let Some(date) = follow_up.date(today).or(undated_date) else {
+            continue;
+        };
+        let date = date.format("%Y-%m-%d").to_string();
+        let existing = daily_plan::list_date_items(db, &date).await?;
+        if existing.iter().any(|item| item.title.eq_ignore_ascii_case(&follow_up.title)) {
+            continue;
+        }
+        let item = daily_plan::create_date_item(
+            db,
+            CreateDailyPlanDateItemRequest {
+                date,
+                title: follow_up.title.clone(),
+                time: None,
+                notes: meeting_title.map(|t| format!("Follow-up from meeting: {}", t)),
+                carried_over_from: None,
+            },
+        )
+        .await?;
+        created.push(item);
+    }
+    Ok(created)
+}
+
+/// After notes extraction: push today/tomorrow follow-ups if the meeting's
+/// organization has the toggle on. Failures are logged, never propagated.
+pub async fn auto_push(db: &SqlitePool, organization: &str, notes: &str, meeting_title: Option<&str>) {
+    let settings = crate::handlers::load_org_settings(db, organization).await;
+    if !settings.meeting_followups_to_daily_plan {
+        return;
+    }
+    let follow_ups = parse_follow_ups(notes);
+    if follow_ups.is_empty() {
+        return;
+    }
+    let today = chrono::Utc::now().date_naive();
+    match push_to_daily_plan(db, &follow_ups, meeting_title, today, None).await {
+        Ok(created) if !created.is_empty() => {
+            tracing::info!("Added {} meeting follow-ups to the daily plan", created.len())
+        }
+        Ok(_) => {}
+        Err(e) => tracing::warn!("Failed to add meeting follow-ups to the daily plan: {:?}", e),
+    }
+}
+
+/// Step inputs are templated, so `{{` in meeting text must not read as a placeholder
+fn literal_input(text: &str) -> serde_json::Value {
+    serde_json::Value::String(text.replace("{{", "{ {"))
+}
+
+/// Inputs given to the first step of a meeting's follow-up pipeline
+fn follow_up_inputs(room_id: &str, notes: &str, meeting_title: Option<&str>) -> serde_json::Value {
+    serde_json::json!({
+        "meeting_id": room_id,
+        "meeting_title": literal_input(meeting_title.unwrap_or_default()),
+        "meeting_notes": literal_input(notes),
+    })
+}
+
+/// After notes extraction: attach and run the meeting's follow-up pipeline,
+/// if it has one. Failures are logged, never propagated.
+pub async fn run_follow_up_pipeline(db: &SqlitePool, room_id: &str, notes: &str, meeting_title: Option<&str>) {
+    let meeting = match ticketing_system::meetings::get_meeting(db, room_id).await {
+        Ok(Some(meeting)) => meeting,
+        Ok(None) => return,
+        Err(e) => {
+            tracing::warn!("Failed to load meeting {} for its follow-up pipeline: {:?}", room_id, e);
+            return;
+        }
+    };
+    let (Some(ticket_id), Some(template_id)) = (meeting.follow_up_ticket_id, meeting.follow_up_template_id) else {
+        return;
+    };
+    let first_step_id = match pipelines::get_template(db, &template_id).await {
+        Ok(Some(template)) => match template.steps.first() {
+            Some(step) => step.step_id.clone(),
+            None => return,
+        },
+        Ok(None) => {
+            tracing::warn!("Follow-up pipeline template {} for meeting {} no longer exists", template_id, room_id);
+            return;
+        }
+        Err(e) => {
+            tracing::warn!("Failed to load follow-up pipeline template {}: {:?}", template_id, e);
+            return;
+        }
+    };
+
+    let request = AttachAndRunRequest {
+        template_id: template_id.clone(),
+        step_inputs: Some(HashMap::from([(first_step_id, follow_up_inputs(room_id, notes, meeting_title))])),
+    };
+    let response = attach_and_run_pipeline(State(Arc::new(db.clone())), Path(ticket_id.clone()), Json(request)).await;
+    if response.status().is_success() {
+        tracing::info!("Started follow-up pipeline {} on ticket {} for meeting {}", template_id, ticket_id, room_id);
+    } else {
+        let body = axum::body::to_bytes(response.into_body(), 64 * 1024).await.unwrap_or_default();
+        tracing::warn!(
+            "Failed to start follow-up pipeline on ticket {} for meeting {}: {}",
+            ticket_id,
+            room_id,
+            String::from_utf8_lossy(&body)
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_tagged_follow_ups() {
+        let notes = "**Issue 1: Pricing**\n- Vendor quote is high\n\n**Follow-ups**\n\
+                     - [today] Send revised quote to Dana\n\
+                     - [Tomorrow] Book venue walkthrough\n\
+                     - Review Q3 budget\n";
+        assert_eq!(
+            parse_follow_ups(notes),
+            vec![
+                FollowUp { title: "Send revised quote to Dana".to_string(), due: FollowUpDue::Today },
+                FollowUp { title: "Book venue walkthrough".to_string(), due: FollowUpDue::Tomorrow },
+                FollowUp { title: "Review Q3 budget".to_string(), due: FollowUpDue::Later },
+            ]
+        );
+        assert!(parse_follow_ups("**Issue 1: Pricing**\n- [today] not a follow-up").is_empty());
+    }
+
+    #[test]
+    fn meeting_text_never_reads_as_input_placeholders() {
+        let inputs = follow_up_inputs("room-1", "Use {{ticket.title}} in the reply", None);
+        assert_eq!(inputs["meeting_notes"], "Use { {ticket.title}} in the reply");
+        assert_eq!(inputs["meeting_title"], "");
+        assert!(crate::pipeline_automation::input_placeholders(&inputs).is_empty());
+    }
+}
@@ -19,6 +19,7 @@ pub struct CreateSliceRequest {
 #[derive(Debug, Deserialize)]
 pub struct CreateTicketRequest {
     pub title: String,
+    pub due_date: Option<chrono::NaiveDate>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -0,0 +1,153 @@
+//! NDJSON list responses
+//!
+//! Large lists (all of an organization's tickets, a mailbox, an agent run's
+//! events) can be requested with `Accept: application/x-ndjson`. Rows are then
+//! written one JSON document per line as the body is sent, instead of being
+//! serialized into a single array first, and paged sources are read from the
+//! database one page at a time. A failure after the response has started is
+//! reported as a final `{"error": ...}` line, since the status is already sent.
+
+use axum::{
+    body::{Body, Bytes},
+    http::{header, HeaderMap, HeaderValue},
+    response::{IntoResponse, Response},
+};
+use futures::Stream;
+use serde::Serialize;
+use std::future::Future;
+
+pub const CONTENT_TYPE: &str = "application/x-ndjson";
+
+/// Rows read per database round trip when streaming a paged source
+pub const PAGE_SIZE: i64 = 500;
+
+/// Whether the client asked for NDJSON
+pub fn wants_ndjson(headers: &HeaderMap) -> bool {
+    headers
+        .get_all(header::ACCEPT)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .any(|media| media.split(';').next().is_some_and(|m| m.trim().eq_ignore_ascii_case(CONTENT_TYPE)))
+}
+
+/// One row as a line
+fn line<T: Serialize>(row: &T) -> Bytes {
+    let mut bytes = serde_json::to_vec(row).unwrap_or_else(|e| error_json(&e.to_string()));
+    bytes.push(b'\n');
+    Bytes::from(bytes)
+}
+
+fn error_json(error: &str) -> Vec<u8> {
+    serde_json::to_vec(&serde_json::json!({ "error": error })).unwrap_or_default()
+}
+
+fn stream_response<S>(lines: S) -> Response
+where
+    S: Stream<Item = Result<Bytes, std::convert::Infallible>> + Send + 'static,
+{
+    (
+        [(header::CONTENT_TYPE, HeaderValue::from_static(CONTENT_TYPE))],
+        Body::from_stream(lines),
+    )
+        .into_response()
+}
+
+/// Stream rows that are already loaded, serializing each only as it is sent
+pub fn rows<T>(rows: Vec<T>) -> Response
+where
+    T: Serialize + Send + 'static,
+{
+    stream_response(futures::stream::iter(rows.into_iter().map(|row| Ok(line(&row)))))
+}
+
+/// One page read from a paged source
+pub struct Page<T> {
+    /// Rows to send, after any filtering
+    pub rows: Vec<T>,
+    /// Rows read from the source before filtering
+    pub read: i64,
+}
+
+impl<T> From<Vec<T>> for Page<T> {
+    fn from(rows: Vec<T>) -> Self {
+        let read = rows.len() as i64;
+        Self { rows, read }
+    }
+}
+
+/// Stream a paged source. `fetch(offset, limit)` is called until a page reads
+/// fewer than `limit` rows; at most `max_rows` source rows are read when set.
+pub fn pages<T, F, Fut>(max_rows: Option<i64>, mut fetch: F) -> Response
+where
+    T: Serialize + Send + 'static,
+    F: FnMut(i64, i64) -> Fut + Send + 'static,
+    Fut: Future<Output = Result<Page<T>, String>> + Send,
+{
+    let lines = async_stream::stream! {
+        let mut offset = 0;
+        loop {
+            let limit = match max_rows {
+                Some(max) if max - offset <= 0 => break,
+                Some(max) => PAGE_SIZE.min(max - offset),
+                None => PAGE_SIZE,
+            };
+            let page = match fetch(offset, limit).await {
+                Ok(page) => page,
+                Err(e) => {
+                    tracing::error!("NDJSON stream failed at row {}: {}", offset, e);
+                    let mut error = error_json(&e);
+                    error.push(b'\n');
+                    yield Ok(Bytes::from(error));
+                    break;
+                }
+            };
+            let read = page.read;
+            for row in page.rows {
+                yield Ok(line(&row));
+            }
+            if read < limit {
+                break;
+            }
+            offset += read;
+        }
+    };
+    stream_response(lines)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_ndjson_among_accepted_types() {
+        let mut headers = HeaderMap::new();
+        assert!(!wants_ndjson(&headers));
+        headers.insert(header::ACCEPT, HeaderValue::from_static("application/json"));
+        assert!(!wants_ndjson(&headers));
+        headers.insert(header::ACCEPT, HeaderValue::from_static("text/html, Application/X-NDJSON; q=0.9"));
+        assert!(wants_ndjson(&headers));
+    }
+
+    #[tokio::test]
+    async fn pages_until_a_short_page_and_reports_errors_inline() {
+        let response = pages(Some(1200), |offset, limit| async move {
+            Ok::<_, String>(Page::from((offset..offset + limit).collect::<Vec<i64>>()))
+        });
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let lines: Vec<&str> = std::str::from_utf8(&body).unwrap().lines().collect();
+        assert_eq!(lines.len(), 1200);
+        assert_eq!(lines[1199], "1199");
+
+        let response = pages(None, |offset, _| async move {
+            if offset == 0 {
+                Ok(Page::from(vec![1; PAGE_SIZE as usize]))
+            } else {
+                Err("database is locked".to_string())
+            }
+        });
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let last = std::str::from_utf8(&body).unwrap().lines().last().unwrap().to_string();
+        assert_eq!(last, r#"{"error":"database is locked"}"#);
+    }
+}
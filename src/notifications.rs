@@ -0,0 +1,14 @@
+//! In-app notifications
+//!
+//! Background jobs call `notify` to surface events (bounces, reminders, ...)
+//! to the user. Failures are logged and never propagated to the caller.
+
+use ticketing_system::{notifications, CreateNotificationRequest, SqlitePool};
+
+/// Persist a notification, logging instead of failing
+pub async fn notify(db: &SqlitePool, req: CreateNotificationRequest) {
+    let kind = req.kind.clone();
+    if let Err(e) = notifications::create_notification(db, req).await {
+        tracing::warn!("Failed to create {} notification: {}", kind, e);
+    }
+}
@@ -9,20 +9,444 @@
 //! - Marks manual steps as awaiting_approval
 //! - Updates ticket status on pipeline completion
 
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::time::Duration;
+
 use anyhow::Result;
+use regex::Regex;
 use sqlx::SqlitePool;
-use tracing::{error, info, warn};
+use tracing::{error, info, warn, Instrument};
 
 use ticketing_system::{
-    models::{ExecutionType, PipelineStepStatus, Ticket},
+    models::{ExecutionType, Pipeline, PipelineStep, PipelineStepStatus, RetryPolicy, Ticket},
     pipelines, tickets,
 };
 
-use crate::agents::{AgentExecutor, AgentType, TicketContext, resolve_working_dir};
+use crate::agents::{AgentRunStatus, AgentType, TicketContext, resolve_working_dir};
+use crate::agents::prompts::prompt_version;
+use crate::analytics;
+use crate::email_style::EmailStyle;
+use crate::agents::failure::{self, FailureReason};
+use crate::pipeline_status::save_pipeline;
+use crate::runners::StepRunner;
 
 /// Maximum depth of chained auto-steps to prevent infinite loops
 const MAX_AUTO_CHAIN_DEPTH: u32 = 10;
 
+/// Upper bound on `retry.max_attempts` accepted in a template step
+pub const MAX_RETRY_ATTEMPTS: u32 = 5;
+
+// ============================================================================
+// Step Conditions
+// ============================================================================
+
+/// Condition gating whether a queued step runs, evaluated against the
+/// previous step's output summary. Steps whose condition is not met are skipped.
+///
+/// Syntax: `always`, `never`, `output_contains: <text>`, `output_not_contains: <text>`
+/// (text matching is case-insensitive).
+#[derive(Debug, Clone, PartialEq)]
+pub enum StepCondition {
+    Always,
+    Never,
+    OutputContains(String),
+    OutputNotContains(String),
+}
+
+impl FromStr for StepCondition {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let s = s.trim();
+        match s {
+            "always" => return Ok(StepCondition::Always),
+            "never" => return Ok(StepCondition::Never),
+            _ => {}
+        }
+
+        let (op, arg) = s
+            .split_once(':')
+            .ok_or_else(|| format!("Invalid condition '{}'", s))?;
+        let arg = arg.trim().trim_matches('"').to_string();
+        if arg.is_empty() {
+            return Err(format!("Condition '{}' is missing its text argument", op.trim()));
+        }
+
+        match op.trim() {
+            "output_contains" => Ok(StepCondition::OutputContains(arg)),
+            "output_not_contains" => Ok(StepCondition::OutputNotContains(arg)),
+            other => Err(format!("Unknown condition '{}'", other)),
+        }
+    }
+}
+
+impl StepCondition {
+    pub fn is_met(&self, previous_output: Option<&str>) -> bool {
+        let output = previous_output.unwrap_or("").to_lowercase();
+        match self {
+            StepCondition::Always => true,
+            StepCondition::Never => false,
+            StepCondition::OutputContains(text) => output.contains(&text.to_lowercase()),
+            StepCondition::OutputNotContains(text) => !output.contains(&text.to_lowercase()),
+        }
+    }
+}
+
+/// Index of the first step from `from_idx` onward that wasn't skipped, by its
+/// condition or by hand ahead of time
+fn first_unskipped<'a>(statuses: impl IntoIterator<Item = &'a PipelineStepStatus>, from_idx: usize) -> Option<usize> {
+    statuses
+        .into_iter()
+        .enumerate()
+        .skip(from_idx)
+        .find(|(_, status)| **status != PipelineStepStatus::Skipped)
+        .map(|(idx, _)| idx)
+}
+
+/// Skip queued steps from `from_idx` onward whose condition is not met, passing
+/// over steps that were already skipped.
+///
+/// Returns the index of the first step that should run, or `None` if every
+/// remaining step was skipped. Unparseable conditions are treated as met.
+pub(crate) fn skip_unmet_steps(
+    pipeline: &mut Pipeline,
+    from_idx: usize,
+    previous_output: Option<&str>,
+) -> Option<usize> {
+    for idx in from_idx..pipeline.steps.len() {
+        let step = &pipeline.steps[idx];
+        let condition = match (&step.status, &step.condition) {
+            (PipelineStepStatus::Skipped, _) => continue,
+            (PipelineStepStatus::Queued, Some(condition)) => condition.clone(),
+            _ => return Some(idx),
+        };
+
+        match condition.parse::<StepCondition>() {
+            Ok(parsed) if !parsed.is_met(previous_output) => {
+                let step_id = step.step_id.clone();
+                info!("Skipping pipeline step {}: condition '{}' not met", step_id, condition);
+                pipelines::skip_step(
+                    pipeline,
+                    &step_id,
+                    Some(serde_json::json!({ "skipped": format!("Condition not met: {}", condition) })),
+                );
+            }
+            Ok(_) => return Some(idx),
+            Err(e) => {
+                warn!("Ignoring invalid condition on step {}: {}", step.step_id, e);
+                return Some(idx);
+            }
+        }
+    }
+    None
+}
+
+/// Delay before re-running a failed auto step
+fn retry_delay(policy: &RetryPolicy) -> Duration {
+    Duration::from_secs(policy.backoff_secs.unwrap_or(0))
+}
+
+/// Attempts a rate-limited step gets, with or without a retry policy
+const RATE_LIMIT_ATTEMPTS: u32 = 3;
+
+/// Minimum wait before a rate-limited step runs again, multiplied by the attempt
+const RATE_LIMIT_BACKOFF_SECS: u64 = 60;
+
+/// Delay before re-running an auto step that failed for `reason`, or None when
+/// it should not run again. Rate limits are always retried with a growing
+/// backoff; other transient failures only under the step's retry policy;
+/// permission errors, context overflows and impossible tasks never.
+fn retry_after(reason: FailureReason, policy: Option<&RetryPolicy>, attempt: u32) -> Option<Duration> {
+    let max_attempts = policy.map(|p| p.max_attempts.clamp(1, MAX_RETRY_ATTEMPTS)).unwrap_or(1);
+    let delay = policy.map(retry_delay).unwrap_or_default();
+    retry_decision(reason, max_attempts, delay, attempt)
+}
+
+fn retry_decision(reason: FailureReason, max_attempts: u32, delay: Duration, attempt: u32) -> Option<Duration> {
+    match reason {
+        FailureReason::RateLimit => (attempt < max_attempts.max(RATE_LIMIT_ATTEMPTS))
+            .then(|| delay.max(Duration::from_secs(RATE_LIMIT_BACKOFF_SECS * u64::from(attempt)))),
+        reason if reason.is_transient() => (attempt < max_attempts).then_some(delay),
+        _ => None,
+    }
+}
+
+// ============================================================================
+// Step Input Templating
+// ============================================================================
+
+/// Placeholder roots accepted in step inputs:
+/// - `{{ticket.<field>}}` — any ticket field, e.g. `ticket.title`
+/// - `{{previous.outputs.<key>}}` — the last completed step before this one
+/// - `{{steps.<step_id>.outputs.<key>}}` — a specific earlier step
+/// - `{{custom_field.<name>}}` — a ticket custom field
+pub const INPUT_PLACEHOLDER_ROOTS: &[&str] = &["ticket", "previous", "steps", "custom_field"];
+
+lazy_static::lazy_static! {
+    static ref INPUT_PLACEHOLDER: Regex = Regex::new(r"\{\{\s*([A-Za-z0-9_.\-]+)\s*\}\}").unwrap();
+}
+
+/// Placeholder paths (e.g. `ticket.title`) referenced anywhere in a value
+pub fn input_placeholders(value: &serde_json::Value) -> Vec<String> {
+    match value {
+        serde_json::Value::String(s) => INPUT_PLACEHOLDER
+            .captures_iter(s)
+            .map(|c| c[1].to_string())
+            .collect(),
+        serde_json::Value::Array(items) => items.iter().flat_map(input_placeholders).collect(),
+        serde_json::Value::Object(map) => map.values().flat_map(input_placeholders).collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Check a placeholder path when a template is saved. `earlier_steps` are the
+/// ids of steps before the one whose inputs reference it.
+pub fn check_input_placeholder(path: &str, earlier_steps: &[&str]) -> std::result::Result<(), String> {
+    let segments: Vec<&str> = path.split('.').collect();
+    match segments.as_slice() {
+        ["ticket", _, ..] | ["previous", _, ..] | ["custom_field", _, ..] => Ok(()),
+        ["steps", step_id, _, ..] if earlier_steps.contains(step_id) => Ok(()),
+        ["steps", step_id, ..] => Err(format!("'{{{{{}}}}}' must reference an earlier step, not '{}'", path, step_id)),
+        _ => Err(format!(
+            "Unknown placeholder '{{{{{}}}}}' (expected one of {})",
+            path,
+            INPUT_PLACEHOLDER_ROOTS.join(", ")
+        )),
+    }
+}
+
+/// Walk a dotted path into a JSON value; numeric segments index arrays
+fn lookup_path<'a>(root: &'a serde_json::Value, path: &[&str]) -> Option<&'a serde_json::Value> {
+    path.iter().try_fold(root, |value, segment| match value {
+        serde_json::Value::Array(items) => items.get(segment.parse::<usize>().ok()?),
+        _ => value.get(segment),
+    })
+}
+
+/// Resolve one placeholder path against the ticket and the steps before `step_idx`
+fn resolve_placeholder(
+    path: &str,
+    ticket: &serde_json::Value,
+    pipeline: &Pipeline,
+    step_idx: usize,
+) -> Option<serde_json::Value> {
+    let segments: Vec<&str> = path.split('.').collect();
+    let earlier = &pipeline.steps[..step_idx.min(pipeline.steps.len())];
+    let value = match segments.as_slice() {
+        ["ticket", rest @ ..] => lookup_path(ticket, rest)?.clone(),
+        ["custom_field", rest @ ..] if !rest.is_empty() => lookup_path(ticket.get("custom_fields")?, rest)?.clone(),
+        ["previous", rest @ ..] => {
+            let step = earlier.iter().rev().find(|s| s.status == PipelineStepStatus::Completed)?;
+            lookup_path(&serde_json::to_value(step).ok()?, rest)?.clone()
+        }
+        ["steps", step_id, rest @ ..] => {
+            let step = earlier.iter().find(|s| s.step_id == *step_id)?;
+            lookup_path(&serde_json::to_value(step).ok()?, rest)?.clone()
+        }
+        _ => return None,
+    };
+    (!value.is_null()).then_some(value)
+}
+
+/// Substitute placeholders in a value. A string that is exactly one placeholder
+/// takes the resolved JSON value as-is; otherwise values are spliced in as text.
+/// Unresolved placeholders become empty and their paths are pushed to `unresolved`.
+fn interpolate_value(
+    value: &serde_json::Value,
+    resolve: &dyn Fn(&str) -> Option<serde_json::Value>,
+    unresolved: &mut Vec<String>,
+) -> serde_json::Value {
+    match value {
+        serde_json::Value::String(s) => {
+            if let Some(caps) = INPUT_PLACEHOLDER.captures(s) {
+                if caps[0].len() == s.trim().len() {
+                    return resolve(&caps[1]).unwrap_or_else(|| {
+                        unresolved.push(caps[1].to_string());
+                        serde_json::Value::String(String::new())
+                    });
+                }
+            }
+            let replaced = INPUT_PLACEHOLDER.replace_all(s, |caps: &regex::Captures| match resolve(&caps[1]) {
+                Some(serde_json::Value::String(text)) => text,
+                Some(other) => other.to_string(),
+                None => {
+                    unresolved.push(caps[1].to_string());
+                    String::new()
+                }
+            });
+            serde_json::Value::String(replaced.into_owned())
+        }
+        serde_json::Value::Array(items) => {
+            serde_json::Value::Array(items.iter().map(|v| interpolate_value(v, resolve, unresolved)).collect())
+        }
+        serde_json::Value::Object(map) => serde_json::Value::Object(
+            map.iter()
+                .map(|(k, v)| (k.clone(), interpolate_value(v, resolve, unresolved)))
+                .collect(),
+        ),
+        other => other.clone(),
+    }
+}
+
+/// Resolve the inputs of the step at `step_idx` at the moment it starts.
+/// Unresolvable placeholders are logged and left empty rather than failing the step.
+pub fn resolve_step_inputs(ticket: &Ticket, pipeline: &Pipeline, step_idx: usize) -> HashMap<String, serde_json::Value> {
+    let Some(inputs) = pipeline.steps.get(step_idx).and_then(|s| s.inputs.as_ref()) else {
+        return HashMap::new();
+    };
+    let ticket_json = serde_json::to_value(ticket).unwrap_or_default();
+    let resolve = |path: &str| resolve_placeholder(path, &ticket_json, pipeline, step_idx);
+
+    let mut unresolved = Vec::new();
+    let resolved = inputs
+        .iter()
+        .map(|(key, value)| (key.clone(), interpolate_value(value, &resolve, &mut unresolved)))
+        .collect();
+    if !unresolved.is_empty() {
+        warn!(
+            "Unresolved input placeholders on step {} of ticket {}: {}",
+            pipeline.steps[step_idx].step_id,
+            ticket.ticket_id,
+            unresolved.join(", ")
+        );
+    }
+    resolved
+}
+
+/// Resolve the inputs of a step by id; empty if the ticket has no such step
+pub fn step_inputs_for(ticket: &Ticket, step_id: &str) -> HashMap<String, serde_json::Value> {
+    ticket
+        .pipeline
+        .as_ref()
+        .and_then(|p| {
+            let idx = p.steps.iter().position(|s| s.step_id == step_id)?;
+            Some(resolve_step_inputs(ticket, p, idx))
+        })
+        .unwrap_or_default()
+}
+
+/// Render resolved inputs for the agent prompt (`- key: value`, sorted by key)
+pub fn render_step_inputs(inputs: &HashMap<String, serde_json::Value>) -> Option<String> {
+    if inputs.is_empty() {
+        return None;
+    }
+    let mut lines: Vec<String> = inputs
+        .iter()
+        .map(|(key, value)| match value {
+            serde_json::Value::String(s) => format!("- {}: {}", key, s),
+            other => format!("- {}: {}", key, other),
+        })
+        .collect();
+    lines.sort();
+    Some(lines.join("\n"))
+}
+
+/// Individual inputs as prompt variables (`{{INPUT_<KEY>}}`)
+pub fn step_input_vars(inputs: &HashMap<String, serde_json::Value>) -> HashMap<String, String> {
+    inputs
+        .iter()
+        .map(|(key, value)| {
+            let text = match value {
+                serde_json::Value::String(s) => s.clone(),
+                other => other.to_string(),
+            };
+            (format!("input_{}", key.to_lowercase()), text)
+        })
+        .collect()
+}
+
+// ============================================================================
+// Step Fan-in
+// ============================================================================
+
+/// Most source steps a single step may combine with `input_from`
+pub const MAX_INPUT_FROM: usize = 8;
+
+/// Check a step's `input_from` list when a template is saved
+pub fn check_input_from(input_from: &[String], earlier_steps: &[&str]) -> Vec<String> {
+    let mut problems = Vec::new();
+    if input_from.len() > MAX_INPUT_FROM {
+        problems.push(format!("input_from can name at most {} steps", MAX_INPUT_FROM));
+    }
+    for (idx, source) in input_from.iter().enumerate() {
+        if input_from[..idx].contains(source) {
+            problems.push(format!("input_from lists '{}' twice", source));
+        } else if !earlier_steps.contains(&source.as_str()) {
+            problems.push(format!("input_from must reference earlier steps, not '{}'", source));
+        }
+    }
+    problems
+}
+
+/// Combined output of the steps named in a step's `input_from`, one section
+/// per source step in the listed order. `None` when the step doesn't declare
+/// any, in which case it gets the preceding step's summary as before.
+pub fn fan_in_context(pipeline: &Pipeline, step_idx: usize) -> Option<String> {
+    let sources = pipeline.steps.get(step_idx)?.input_from.as_ref().filter(|s| !s.is_empty())?;
+    let earlier = &pipeline.steps[..step_idx];
+    let sections: Vec<String> = sources
+        .iter()
+        .map(|source| {
+            let step = earlier.iter().find(|s| s.step_id == *source);
+            let summary = step
+                .and_then(|s| s.outputs.as_ref())
+                .and_then(|o| o.get("summary"))
+                .and_then(|v| v.as_str());
+            fan_in_section(source, step.map(|s| &s.status), summary)
+        })
+        .collect();
+    Some(sections.join("\n\n"))
+}
+
+/// `fan_in_context` of a step by id; None if the ticket has no such step
+pub fn step_fan_in(ticket: &Ticket, step_id: &str) -> Option<String> {
+    let pipeline = ticket.pipeline.as_ref()?;
+    let idx = pipeline.steps.iter().position(|s| s.step_id == step_id)?;
+    fan_in_context(pipeline, idx)
+}
+
+fn fan_in_section(source: &str, status: Option<&PipelineStepStatus>, summary: Option<&str>) -> String {
+    let body = match (status, summary) {
+        (_, Some(summary)) => summary,
+        (Some(PipelineStepStatus::Skipped), None) => "(step was skipped)",
+        _ => "(no output)",
+    };
+    format!("## Output of step `{}`\n\n{}", source, body)
+}
+
+// ============================================================================
+// Step Review Comments
+// ============================================================================
+
+/// `StepComment::kind` of a note left by a reviewer
+pub const STEP_COMMENT_NOTE: &str = "note";
+/// `StepComment::kind` of the feedback given when rejecting a step
+pub const STEP_COMMENT_REJECTION: &str = "rejection";
+
+/// Review comments left on a step, for the agent that (re)runs it
+pub fn step_comments_context(step: &PipelineStep) -> Option<String> {
+    if step.comments.is_empty() {
+        return None;
+    }
+    let lines: Vec<String> = step
+        .comments
+        .iter()
+        .map(|c| {
+            let kind = if c.kind == STEP_COMMENT_REJECTION { " (rejected the step)" } else { "" };
+            format!("- {}{} at {}: {}", c.author_name, kind, c.created_at, c.body)
+        })
+        .collect();
+    Some(format!(
+        "## Reviewer comments on this step
+
+Address these from earlier reviews:
+
+{}",
+        lines.join("\n")
+    ))
+}
+
 /// Result of advancing a pipeline after a step completes
 #[derive(Debug)]
 pub enum PipelineAdvanceResult {
@@ -47,14 +471,14 @@ pub enum PipelineAdvanceResult {
 /// 1. Re-reads the ticket pipeline from DB (fresh state)
 /// 2. If success: calls `pipelines::complete_step()`, saves to DB
 /// 3. If failure: calls `pipelines::fail_step()`, saves to DB, returns early
-/// 4. Checks `pipeline.is_complete()` → updates ticket status to "completed" if done
+/// 4. Checks `pipeline.is_complete()` (saving moves the ticket status, see `pipeline_status`)
 /// 5. Finds next step → if auto, spawns background agent; if manual, marks awaiting approval
 pub async fn advance_pipeline_after_step(
     pool: &SqlitePool,
     ticket_id: &str,
     step_id: &str,
     success: bool,
-    outputs: Option<serde_json::Value>,
+    mut outputs: Option<serde_json::Value>,
 ) -> Result<PipelineAdvanceResult> {
     // Re-read ticket to get fresh pipeline state
     let ticket = match tickets::get_ticket_by_id(pool, ticket_id).await? {
@@ -75,41 +499,47 @@ pub async fn advance_pipeline_after_step(
 
     if !success {
         // Mark step as failed
+        let agent_type = pipeline.steps[step_idx].agent_type.clone();
         pipelines::fail_step(&mut pipeline, step_id, outputs);
-        tickets::update_ticket_pipeline(pool, ticket_id, Some(&pipeline)).await?;
+        save_pipeline(pool, ticket_id, &pipeline).await?;
+        analytics::record_step_event(pool, ticket_id, step_id, &agent_type, analytics::STEP_EVENT_FAILED).await;
         info!("Pipeline step {} failed for ticket {}", step_id, ticket_id);
         return Ok(PipelineAdvanceResult::PipelineDone { completed: false });
     }
 
-    // Mark step as completed
+    // Mark step as completed, saving an email step's draft for a later send step
+    crate::email_steps::capture_draft(pool, &ticket, step_id, &mut outputs).await;
     pipelines::complete_step(&mut pipeline, step_id, outputs);
-    tickets::update_ticket_pipeline(pool, ticket_id, Some(&pipeline)).await?;
+    save_pipeline(pool, ticket_id, &pipeline).await?;
     info!("Pipeline step {} completed for ticket {}", step_id, ticket_id);
 
     // Check if pipeline is complete
     if pipeline.is_complete() {
         if !pipeline.has_failed() {
-            info!("Pipeline completed successfully for ticket {}, updating status to 'completed'", ticket_id);
-            if let Err(e) = tickets::update_ticket_status(
-                pool,
-                &ticket.organization,
-                &ticket.epic_id,
-                &ticket.slice_id,
-                ticket_id,
-                "completed",
-            ).await {
-                error!("Failed to update ticket status to completed: {}", e);
-            }
+            info!("Pipeline completed successfully for ticket {}", ticket_id);
             return Ok(PipelineAdvanceResult::PipelineDone { completed: true });
         }
         return Ok(PipelineAdvanceResult::PipelineDone { completed: false });
     }
 
-    // Find next step
-    let next_idx = step_idx + 1;
-    if next_idx >= pipeline.steps.len() {
-        return Ok(PipelineAdvanceResult::NoNextStep);
-    }
+    // Find next step, skipping any whose condition is not met
+    let previous_output = pipeline.steps[step_idx]
+        .outputs
+        .as_ref()
+        .and_then(|o| o.get("summary"))
+        .and_then(|v| v.as_str())
+        .map(|s| s.to_string());
+    let next_idx = match skip_unmet_steps(&mut pipeline, step_idx + 1, previous_output.as_deref()) {
+        Some(idx) => idx,
+        None => {
+            // Every remaining step was skipped
+            save_pipeline(pool, ticket_id, &pipeline).await?;
+            if pipeline.is_complete() && !pipeline.has_failed() {
+                return Ok(PipelineAdvanceResult::PipelineDone { completed: true });
+            }
+            return Ok(PipelineAdvanceResult::NoNextStep);
+        }
+    };
 
     let next_step = &pipeline.steps[next_idx];
     if next_step.status != PipelineStepStatus::Queued {
@@ -121,6 +551,7 @@ pub async fn advance_pipeline_after_step(
     match next_step.execution_type {
         ExecutionType::Auto => {
             // Spawn agent for auto step (background, non-streaming)
+            let ticket = Ticket { pipeline: Some(pipeline), ..ticket };
             match spawn_agent_for_step(pool, &ticket, next_idx, 0).await? {
                 PipelineProgressResult::AgentSpawned { step_id, session_id } => {
                     Ok(PipelineAdvanceResult::NextAutoStepSpawned { step_id, session_id })
@@ -131,7 +562,7 @@ pub async fn advance_pipeline_after_step(
         ExecutionType::Manual => {
             // Mark as awaiting approval
             pipelines::await_approval(&mut pipeline, &next_step_id);
-            tickets::update_ticket_pipeline(pool, ticket_id, Some(&pipeline)).await?;
+            save_pipeline(pool, ticket_id, &pipeline).await?;
             info!("Pipeline step {} marked as awaiting approval for ticket {}", next_step_id, ticket_id);
             Ok(PipelineAdvanceResult::NextStepAwaitingApproval { step_id: next_step_id })
         }
@@ -186,7 +617,7 @@ pub async fn process_next_step(
 
     // Check if pipeline is already complete or failed
     if pipeline.is_complete() {
-        return handle_pipeline_completion(pool, &ticket).await;
+        return Ok(handle_pipeline_completion(&ticket));
     }
 
     // Find the current step index
@@ -200,12 +631,11 @@ pub async fn process_next_step(
         None => return Ok(PipelineProgressResult::NoNextStep),
     };
 
-    // Get the next step (if any)
-    let next_idx = current_idx + 1;
-    if next_idx >= pipeline.steps.len() {
+    // Get the next step (if any), passing over steps skipped ahead of time
+    let Some(next_idx) = first_unskipped(pipeline.steps.iter().map(|s| &s.status), current_idx + 1) else {
         // No more steps - check completion
-        return handle_pipeline_completion(pool, &ticket).await;
-    }
+        return Ok(handle_pipeline_completion(&ticket));
+    };
 
     let next_step = &pipeline.steps[next_idx];
 
@@ -241,7 +671,7 @@ async fn mark_step_awaiting_approval(
 
     pipelines::await_approval(&mut pipeline, &step_id);
 
-    tickets::update_ticket_pipeline(pool, &ticket.ticket_id, Some(&pipeline)).await?;
+    save_pipeline(pool, &ticket.ticket_id, &pipeline).await?;
 
     info!(
         "Pipeline step {} marked as awaiting approval for ticket {}",
@@ -279,19 +709,25 @@ async fn spawn_agent_for_step(
                     "error": format!("Unknown agent type: {}", agent_type_str)
                 })),
             );
-            tickets::update_ticket_pipeline(pool, &ticket.ticket_id, Some(&pipeline)).await?;
+            save_pipeline(pool, &ticket.ticket_id, &pipeline).await?;
             return Ok(PipelineProgressResult::PipelineFailed {
                 reason: format!("Unknown agent type: {}", agent_type_str),
             });
         }
     };
 
+    // Over quota: hold the step until someone approves it (after a reset or a raised limit)
+    if let Err(exceeded) = crate::quotas::check_agent_run(pool, &ticket.organization).await {
+        warn!("Holding auto step {} on ticket {}: {}", step_id, ticket.ticket_id, exceeded.message());
+        return mark_step_awaiting_approval(pool, ticket, step_idx).await;
+    }
+
     // Generate session ID for the agent run
     let session_id = uuid::Uuid::new_v4().to_string();
 
     // Mark step as started
     pipelines::start_step(&mut pipeline, &step_id, &session_id);
-    tickets::update_ticket_pipeline(pool, &ticket.ticket_id, Some(&pipeline)).await?;
+    save_pipeline(pool, &ticket.ticket_id, &pipeline).await?;
 
     info!(
         "Starting auto step {} with agent {} for ticket {} (session: {})",
@@ -306,6 +742,7 @@ async fn spawn_agent_for_step(
         ticket_id: ticket.ticket_id.clone(),
         agent_type: agent_type_str.clone(),
         input_message: ticket.description.clone().unwrap_or_default(),
+        prompt_version: prompt_version(&agent_type_str),
     };
     ticketing_system::agent_runs::create_agent_run(pool, create_req).await?;
 
@@ -320,7 +757,8 @@ async fn spawn_agent_for_step(
     let step_id_clone = step_id.clone();
     let session_id_clone = session_id.clone();
 
-    tokio::spawn(async move {
+    let span = crate::logging::agent_run_span(&session_id, Some(&ticket_id));
+    crate::logging::spawn_in_span(span, async move {
         let result = execute_agent_for_step(
             &pool_clone,
             &ticket_id,
@@ -378,16 +816,14 @@ async fn execute_agent_for_step(
             .ok_or_else(|| anyhow::anyhow!("Ticket not found: {}", ticket_id))?;
         if let Some(pipeline) = &ticket.pipeline {
             if let Some(current_idx) = pipeline.steps.iter().position(|s| s.step_id == initial_step_id) {
-                if current_idx > 0 {
-                    pipeline.steps[current_idx - 1]
-                        .outputs
+                // Walk back past any steps skipped by their condition
+                pipeline.steps[..current_idx].iter().rev().find_map(|s| {
+                    s.outputs
                         .as_ref()
                         .and_then(|o| o.get("summary"))
                         .and_then(|v| v.as_str())
                         .map(|s| s.to_string())
-                } else {
-                    None
-                }
+                })
             } else {
                 None
             }
@@ -406,7 +842,54 @@ async fn execute_agent_for_step(
             break;
         }
 
-        let executor = AgentExecutor::new(working_dir.clone());
+        let current_ticket = tickets::get_ticket_by_id(pool, ticket_id).await?;
+        let research = match &current_ticket {
+            Some(t) => crate::research_corpus::research_context(pool, &current_agent_type, t).await,
+            None => None,
+        };
+        // Inputs are resolved as the step starts, against the latest ticket and step outputs
+        let step_inputs = current_ticket
+            .as_ref()
+            .map(|t| step_inputs_for(t, &current_step_id))
+            .unwrap_or_default();
+
+        let env = crate::secrets::agent_env(pool, organization, &current_agent_type).await;
+        let step_env = match &current_ticket {
+            Some(t) => crate::secrets::step_env(pool, t, &current_step_id).await,
+            None => Ok(Default::default()),
+        };
+        let (step_env, step_env_error) = match step_env {
+            Ok(step_env) => (step_env, None),
+            Err(e) => (Default::default(), Some(e)),
+        };
+
+        // Publish config from the template, if any; such steps run in the ticket's worktree
+        let publish_config = current_ticket
+            .as_ref()
+            .and_then(|t| t.pipeline.as_ref())
+            .and_then(|p| p.steps.iter().find(|s| s.step_id == current_step_id))
+            .and_then(|s| s.publish.clone())
+            .filter(|_| crate::publish::supports_publish(current_agent_type.as_str()));
+        let (step_dir, worktree_error) = match &publish_config {
+            Some(config) => match crate::publish::ticket_worktree(&working_dir, ticket_id, config).await {
+                Ok(dir) => (dir, None),
+                Err(e) => (working_dir.clone(), Some(format!("Failed to prepare the ticket's worktree: {:#}", e))),
+            },
+            None => (working_dir.clone(), None),
+        };
+        let step_env_error = step_env_error.or(worktree_error);
+        let mut prompt_vars = step_input_vars(&step_inputs);
+        prompt_vars.extend(EmailStyle::from_inputs(&step_inputs).prompt_vars());
+        // Runs here, or queued for a remote runner that takes this agent type
+        let runner = StepRunner::for_step(
+            &current_session_id,
+            &current_step_id,
+            &current_agent_type,
+            step_dir.clone(),
+            env,
+            step_env,
+            prompt_vars,
+        );
 
         let context = TicketContext {
             epic_id: epic_id.to_string(),
@@ -414,13 +897,72 @@ async fn execute_agent_for_step(
             ticket_id: ticket_id.to_string(),
             title: title.to_string(),
             intent: intent.to_string(),
+            checklist: crate::handlers::checklist_context(pool, ticket_id).await,
+            research,
+            step_inputs: render_step_inputs(&step_inputs),
+            estimation: crate::estimates::estimation_context(pool, &current_agent_type, ticket_id).await,
+            email_templates: crate::handlers::email_templates_context(pool, &current_agent_type, organization).await,
+            pinned_outputs: crate::handlers::pinned_outputs_context(pool, ticket_id).await,
+        };
+
+        // Outputs of the steps this one fans in from, if it declares any
+        let fan_in = current_ticket.as_ref().and_then(|t| step_fan_in(t, &current_step_id));
+
+        // Retry policy from the template, if any
+        let current_step = current_ticket
+            .and_then(|t| t.pipeline)
+            .and_then(|p| p.steps.into_iter().find(|s| s.step_id == current_step_id));
+        let context_pack = match current_step.as_ref().and_then(|s| s.context_pack_id.as_deref()) {
+            Some(pack_id) => crate::context_packs::pack_context(pool, organization, pack_id).await,
+            None => None,
         };
+        let review_comments = current_step.as_ref().and_then(step_comments_context);
+        let retry_policy = current_step.and_then(|s| s.retry);
 
         // Execute agent (no streaming for automated runs)
-        // Pass previous step output for chaining (e.g., research output → synthesis agent)
-        let result = executor
-            .execute(current_agent_type.clone(), context, previous_step_output.clone(), None, None, None)
-            .await;
+        // Pass previous step output for chaining (e.g., research output → synthesis agent),
+        // or the combined outputs of its `input_from` steps, after the step's context pack
+        // and any review comments left on the step
+        let step_previous = [context_pack, review_comments, fan_in.or_else(|| previous_step_output.clone())]
+            .into_iter()
+            .flatten()
+            .reduce(|acc, part| format!("{}\n\n{}", acc, part));
+        let mut attempt = 1;
+        let result = loop {
+            let result = match &step_env_error {
+                // Running without a variable the step asked for could reach the wrong
+                // environment, and a publishing step outside its worktree would change
+                // the shared checkout
+                Some(e) => break Err(anyhow::anyhow!(e.clone())),
+                None => {
+                    // Chained steps run under their own session id
+                    let span = tracing::info_span!("step", step_id = %current_step_id, session_id = %current_session_id);
+                    runner
+                        .execute(current_agent_type.clone(), context.clone(), step_previous.clone())
+                        .instrument(span)
+                        .await
+                }
+            };
+            let reason = match &result {
+                Err(e) => failure::classify(&format!("{:#}", e)),
+                Ok(run) if run.status == AgentRunStatus::Failed => run.failure_reason.unwrap_or(FailureReason::Unknown),
+                Ok(_) => break result,
+            };
+            match retry_after(reason, retry_policy.as_ref(), attempt) {
+                Some(delay) => {
+                    warn!(
+                        "Auto step {} failed on attempt {} for ticket {} ({}); retrying in {}s",
+                        current_step_id, attempt, ticket_id, reason.as_str(), delay.as_secs()
+                    );
+                    analytics::record_step_event(
+                        pool, ticket_id, &current_step_id, current_agent_type.as_str(), analytics::STEP_EVENT_RETRIED,
+                    ).await;
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                None => break result,
+            }
+        };
 
         // Get current pipeline state
         let ticket = tickets::get_ticket_by_id(pool, ticket_id)
@@ -434,6 +976,8 @@ async fn execute_agent_for_step(
 
         match result {
             Ok(agent_run) => {
+                crate::quotas::record_agent_run(pool, organization, &agent_run.usage).await;
+
                 // Update agent run record
                 let db_run = ticketing_system::AgentRun {
                     session_id: current_session_id.clone(),
@@ -448,16 +992,85 @@ async fn execute_agent_for_step(
                     output_summary: agent_run.output_summary.clone(),
                 };
                 ticketing_system::agent_runs::update_agent_run(pool, &db_run).await?;
+                if let Some(snapshot) = &agent_run.context_snapshot {
+                    crate::agents::snapshot::store_snapshot(pool, &current_session_id, snapshot).await;
+                }
+                crate::agents::tool_audit::store_tool_calls(pool, &current_session_id, &agent_run.tool_calls).await;
+                if let Some(reason) = agent_run.failure_reason {
+                    ticketing_system::agent_runs::set_failure_reason(pool, &current_session_id, reason.as_str()).await?;
+                }
+
+                // The agent ran but failed, and retrying didn't help - the pipeline halts
+                if agent_run.status == AgentRunStatus::Failed {
+                    let reason = agent_run.failure_reason.unwrap_or(FailureReason::Unknown);
+                    let error = agent_run.output_summary.clone().unwrap_or_else(|| "Agent run failed".to_string());
+                    pipelines::fail_step(
+                        &mut pipeline,
+                        &current_step_id,
+                        Some(serde_json::json!({ "error": error, "failure_reason": reason })),
+                    );
+                    save_pipeline(pool, ticket_id, &pipeline).await?;
+                    analytics::record_step_event(
+                        pool, ticket_id, &current_step_id, current_agent_type.as_str(), analytics::STEP_EVENT_FAILED,
+                    ).await;
+                    error!(
+                        "Auto step {} failed for ticket {} ({}): {}",
+                        current_step_id, ticket_id, reason.as_str(), error
+                    );
+                    if let Err(e) = ticketing_system::ticket_history::log_agent_run_completed(
+                        pool,
+                        ticket_id,
+                        &current_session_id,
+                        current_agent_type.as_str(),
+                        "failed",
+                    )
+                    .await
+                    {
+                        warn!("Failed to log agent run to history: {}", e);
+                    }
+                    break;
+                }
+
+                if let Some(output) = &agent_run.output_summary {
+                    crate::research_corpus::capture_agent_output(
+                        pool, ticket_id, current_agent_type.as_str(), &current_session_id, output,
+                    ).await;
+                    crate::estimates::capture_agent_output(
+                        pool, ticket_id, current_agent_type.as_str(), &current_session_id, output,
+                    ).await;
+                }
 
                 // Capture output for next step in chain
                 previous_step_output = agent_run.output_summary.clone();
 
                 // Create outputs JSON from agent run
-                let outputs = agent_run.output_summary.map(|s| serde_json::json!({ "summary": s }));
+                let mut outputs = agent_run.output_summary.map(|s| serde_json::json!({ "summary": s }));
+
+                // Commit the step's changes and open a PR, if the template asks for it
+                if let Some(config) = &publish_config {
+                    match crate::publish::publish_step_changes(pool, &ticket, &current_step_id, &step_dir, config).await {
+                        Ok(Some(published)) => {
+                            info!(
+                                "Published step {} for ticket {} on {} ({})",
+                                current_step_id, ticket_id, published.branch,
+                                published.pr_url.as_deref().unwrap_or("no PR")
+                            );
+                            published.merge_into(outputs.get_or_insert_with(|| serde_json::json!({})));
+                        }
+                        Ok(None) => info!("Step {} for ticket {} left no changes to publish", current_step_id, ticket_id),
+                        Err(e) => {
+                            warn!("Failed to publish step {} for ticket {}: {:#}", current_step_id, ticket_id, e);
+                            if let Some(map) = outputs.get_or_insert_with(|| serde_json::json!({})).as_object_mut() {
+                                map.insert("publish_error".to_string(), serde_json::json!(format!("{:#}", e)));
+                            }
+                        }
+                    }
+                }
 
-                // Mark step as completed
+                // Mark step as completed, saving an email step's draft for a later send step
+                crate::email_steps::capture_draft(pool, &ticket, &current_step_id, &mut outputs).await;
                 pipelines::complete_step(&mut pipeline, &current_step_id, outputs);
-                tickets::update_ticket_pipeline(pool, ticket_id, Some(&pipeline)).await?;
+                save_pipeline(pool, ticket_id, &pipeline).await?;
 
                 info!(
                     "Auto step {} completed successfully for ticket {}",
@@ -492,30 +1105,21 @@ async fn execute_agent_for_step(
                 if pipeline.is_complete() {
                     // Handle pipeline completion
                     if !pipeline.has_failed() {
-                        info!(
-                            "Pipeline completed successfully for ticket {}, updating status to 'completed'",
-                            ticket_id
-                        );
-                        if let Err(e) = tickets::update_ticket_status(
-                            pool,
-                            &ticket.organization,
-                            epic_id,
-                            slice_id,
-                            ticket_id,
-                            "completed",
-                        )
-                        .await
-                        {
-                            error!("Failed to update ticket status to completed: {}", e);
-                        }
+                        info!("Pipeline completed successfully for ticket {}", ticket_id);
                     }
                     break;
                 }
 
-                // Get next step
-                let next_idx = current_idx + 1;
-                if next_idx >= pipeline.steps.len() {
-                    break;
+                // Get next step, skipping any whose condition is not met
+                let next_idx = match skip_unmet_steps(&mut pipeline, current_idx + 1, previous_step_output.as_deref()) {
+                    Some(idx) => idx,
+                    None => {
+                        save_pipeline(pool, ticket_id, &pipeline).await?;
+                        break;
+                    }
+                };
+                if next_idx > current_idx + 1 {
+                    save_pipeline(pool, ticket_id, &pipeline).await?;
                 }
 
                 let next_step = &pipeline.steps[next_idx];
@@ -530,6 +1134,25 @@ async fn execute_agent_for_step(
                 let next_agent_type_str = next_step.agent_type.clone();
                 let next_execution_type = next_step.execution_type.clone();
 
+                // Read-only maintenance: let this run finish but don't start another
+                if matches!(next_execution_type, ExecutionType::Auto) && crate::maintenance::is_enabled() {
+                    pipelines::await_approval(&mut pipeline, &next_step_id);
+                    save_pipeline(pool, ticket_id, &pipeline).await?;
+                    warn!(
+                        "Maintenance mode: holding auto step {} for approval on ticket {}",
+                        next_step_id, ticket_id
+                    );
+                    break;
+                }
+                if matches!(next_execution_type, ExecutionType::Auto) {
+                    if let Err(exceeded) = crate::quotas::check_agent_run(pool, organization).await {
+                        pipelines::await_approval(&mut pipeline, &next_step_id);
+                        save_pipeline(pool, ticket_id, &pipeline).await?;
+                        warn!("Holding auto step {} on ticket {}: {}", next_step_id, ticket_id, exceeded.message());
+                        break;
+                    }
+                }
+
                 match next_execution_type {
                     ExecutionType::Auto => {
                         // Set up for next iteration — re-resolve working_dir for new agent type
@@ -547,7 +1170,7 @@ async fn execute_agent_for_step(
                                         "error": format!("Unknown agent type: {}", next_agent_type_str)
                                     })),
                                 );
-                                tickets::update_ticket_pipeline(pool, ticket_id, Some(&pipeline)).await?;
+                                save_pipeline(pool, ticket_id, &pipeline).await?;
                                 break;
                             }
                         };
@@ -566,7 +1189,7 @@ async fn execute_agent_for_step(
                         let mut pipeline = ticket.pipeline.unwrap();
 
                         pipelines::start_step(&mut pipeline, &current_step_id, &current_session_id);
-                        tickets::update_ticket_pipeline(pool, ticket_id, Some(&pipeline)).await?;
+                        save_pipeline(pool, ticket_id, &pipeline).await?;
 
                         // Create agent run record
                         let create_req = ticketing_system::CreateAgentRunRequest {
@@ -576,6 +1199,7 @@ async fn execute_agent_for_step(
                             ticket_id: ticket_id.to_string(),
                             agent_type: current_agent_type.as_str().to_string(),
                             input_message: intent.to_string(),
+                            prompt_version: prompt_version(current_agent_type.as_str()),
                         };
                         ticketing_system::agent_runs::create_agent_run(pool, create_req).await?;
 
@@ -590,7 +1214,7 @@ async fn execute_agent_for_step(
                     ExecutionType::Manual => {
                         // Mark as awaiting approval and stop the loop
                         pipelines::await_approval(&mut pipeline, &next_step_id);
-                        tickets::update_ticket_pipeline(pool, ticket_id, Some(&pipeline)).await?;
+                        save_pipeline(pool, ticket_id, &pipeline).await?;
                         info!(
                             "Pipeline step {} marked as awaiting approval for ticket {}",
                             next_step_id, ticket_id
@@ -615,18 +1239,22 @@ async fn execute_agent_for_step(
                     output_summary: Some(format!("Agent failed: {}", e)),
                 };
                 ticketing_system::agent_runs::update_agent_run(pool, &db_run).await?;
+                let reason = failure::record_failure(pool, &current_session_id, &format!("{:#}", e)).await;
 
                 // Mark step as failed
                 pipelines::fail_step(
                     &mut pipeline,
                     &current_step_id,
-                    Some(serde_json::json!({ "error": e.to_string() })),
+                    Some(serde_json::json!({ "error": e.to_string(), "failure_reason": reason })),
                 );
-                tickets::update_ticket_pipeline(pool, ticket_id, Some(&pipeline)).await?;
+                save_pipeline(pool, ticket_id, &pipeline).await?;
+                analytics::record_step_event(
+                    pool, ticket_id, &current_step_id, current_agent_type.as_str(), analytics::STEP_EVENT_FAILED,
+                ).await;
 
                 error!(
-                    "Auto step {} failed for ticket {}: {}",
-                    current_step_id, ticket_id, e
+                    "Auto step {} failed for ticket {} ({}): {}",
+                    current_step_id, ticket_id, reason.as_str(), e
                 );
 
                 // Log to ticket history
@@ -651,52 +1279,24 @@ async fn execute_agent_for_step(
     Ok(())
 }
 
-/// Handle pipeline completion - update ticket status
-async fn handle_pipeline_completion(
-    pool: &SqlitePool,
-    ticket: &Ticket,
-) -> Result<PipelineProgressResult> {
+/// Result for a pipeline with no steps left. The ticket status was already
+/// moved when the final step was saved (see `pipeline_status`).
+fn handle_pipeline_completion(ticket: &Ticket) -> PipelineProgressResult {
     let pipeline = ticket.pipeline.as_ref().unwrap();
 
     if pipeline.has_failed() {
-        info!(
-            "Pipeline failed for ticket {}, setting status to 'pipeline_failed'",
-            ticket.ticket_id
-        );
-        // Optionally update ticket status to indicate pipeline failure
-        // We don't change to "completed" since it failed
-        return Ok(PipelineProgressResult::PipelineFailed {
+        info!("Pipeline failed for ticket {}", ticket.ticket_id);
+        return PipelineProgressResult::PipelineFailed {
             reason: "One or more steps failed".to_string(),
-        });
+        };
     }
 
-    if pipeline.is_complete() && !pipeline.has_failed() {
-        info!(
-            "Pipeline completed successfully for ticket {}, updating status to 'completed'",
-            ticket.ticket_id
-        );
-
-        // Update ticket status to completed
-        if let Err(e) = tickets::update_ticket_status(
-            pool,
-            &ticket.organization,
-            &ticket.epic_id,
-            &ticket.slice_id,
-            &ticket.ticket_id,
-            "completed",
-        )
-        .await
-        {
-            error!(
-                "Failed to update ticket status to completed: {}",
-                e
-            );
-        }
-
-        return Ok(PipelineProgressResult::PipelineCompleted);
+    if pipeline.is_complete() {
+        info!("Pipeline completed successfully for ticket {}", ticket.ticket_id);
+        return PipelineProgressResult::PipelineCompleted;
     }
 
-    Ok(PipelineProgressResult::NoNextStep)
+    PipelineProgressResult::NoNextStep
 }
 
 /// Start executing a specific step (used for both first step and approved manual steps).
@@ -747,3 +1347,95 @@ pub async fn start_step_execution(
     }
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn interpolates_inputs_and_keeps_whole_placeholder_types() {
+        let values = json!({ "ticket.title": "Renew contract", "previous.outputs.count": 3 });
+        let resolve = |path: &str| values.get(path).cloned();
+        let mut unresolved = Vec::new();
+
+        assert_eq!(
+            interpolate_value(&json!("Re: {{ ticket.title }}"), &resolve, &mut unresolved),
+            json!("Re: Renew contract")
+        );
+        assert_eq!(interpolate_value(&json!("{{previous.outputs.count}}"), &resolve, &mut unresolved), json!(3));
+        assert_eq!(interpolate_value(&json!(["{{custom_field.customer}}"]), &resolve, &mut unresolved), json!([""]));
+        assert_eq!(unresolved, vec!["custom_field.customer"]);
+    }
+
+    #[test]
+    fn parses_and_evaluates_step_conditions() {
+        let contains: StepCondition = "output_contains: \"Needs Review\"".parse().unwrap();
+        assert_eq!(contains, StepCondition::OutputContains("Needs Review".to_string()));
+        assert!(contains.is_met(Some("this needs review soon")));
+        assert!(!contains.is_met(Some("approved")));
+        assert!(!contains.is_met(None));
+
+        let not_contains: StepCondition = " output_not_contains:blocked ".parse().unwrap();
+        assert!(not_contains.is_met(None));
+        assert!(!not_contains.is_met(Some("BLOCKED on legal")));
+
+        assert!("always".parse::<StepCondition>().unwrap().is_met(None));
+        assert!(!"never".parse::<StepCondition>().unwrap().is_met(Some("anything")));
+        assert!("output_contains:".parse::<StepCondition>().is_err());
+        assert!("sometimes".parse::<StepCondition>().is_err());
+        assert!("output_matches: x".parse::<StepCondition>().is_err());
+    }
+
+    #[test]
+    fn checks_placeholder_roots_and_step_order() {
+        assert!(check_input_placeholder("ticket.title", &[]).is_ok());
+        assert!(check_input_placeholder("steps.research.outputs.summary", &["research"]).is_ok());
+        assert!(check_input_placeholder("steps.review.outputs.summary", &["research"]).is_err());
+        assert!(check_input_placeholder("ticket", &[]).is_err());
+        assert!(check_input_placeholder("env.HOME", &[]).is_err());
+    }
+
+    #[test]
+    fn checks_input_from_sources() {
+        let sources = |ids: &[&str]| ids.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        assert!(check_input_from(&sources(&["research", "pricing"]), &["research", "pricing"]).is_empty());
+        assert_eq!(check_input_from(&sources(&["research", "research"]), &["research"]).len(), 1);
+        assert_eq!(check_input_from(&sources(&["synthesis"]), &["research"]).len(), 1);
+    }
+
+    #[test]
+    fn renders_fan_in_sections() {
+        use PipelineStepStatus::*;
+        assert_eq!(
+            fan_in_section("research", Some(&Completed), Some("Three vendors")),
+            "## Output of step `research`\n\nThree vendors"
+        );
+        assert!(fan_in_section("pricing", Some(&Skipped), None).ends_with("(step was skipped)"));
+        assert!(fan_in_section("pricing", Some(&Failed), None).ends_with("(no output)"));
+        assert!(fan_in_section("missing", None, None).ends_with("(no output)"));
+    }
+
+    #[test]
+    fn passes_over_skipped_steps() {
+        use PipelineStepStatus::*;
+        let statuses = [Completed, Skipped, Skipped, Queued, Skipped];
+        assert_eq!(first_unskipped(&statuses, 1), Some(3));
+        assert_eq!(first_unskipped(&statuses, 0), Some(0));
+        assert_eq!(first_unskipped(&statuses, 4), None);
+        assert_eq!(first_unskipped(&statuses, 5), None);
+    }
+
+    #[test]
+    fn retries_only_failures_a_retry_can_fix() {
+        let backoff = Duration::from_secs(5);
+        assert_eq!(retry_decision(FailureReason::RateLimit, 1, Duration::ZERO, 1), Some(Duration::from_secs(60)));
+        assert_eq!(retry_decision(FailureReason::RateLimit, 1, Duration::ZERO, 2), Some(Duration::from_secs(120)));
+        assert_eq!(retry_decision(FailureReason::RateLimit, 1, Duration::ZERO, 3), None);
+        assert_eq!(retry_decision(FailureReason::CliCrash, 3, backoff, 2), Some(backoff));
+        assert_eq!(retry_decision(FailureReason::CliCrash, 3, backoff, 3), None);
+        assert_eq!(retry_decision(FailureReason::Unknown, 1, backoff, 1), None);
+        assert_eq!(retry_decision(FailureReason::PermissionDenied, 5, backoff, 1), None);
+        assert_eq!(retry_decision(FailureReason::ContextOverflow, 5, backoff, 1), None);
+    }
+}
@@ -0,0 +1,218 @@
+//! Pipeline read model
+//!
+//! Step transitions used to load the whole ticket, deserializing its pipeline
+//! JSON, and then write the whole pipeline back. This keeps, per ticket, the
+//! step statuses and the last pipeline JSON written or read, so
+//! `save_pipeline` can tell phase changes from the cache and write only the
+//! steps that changed.
+//!
+//! Entries are written through on every save and dropped when a pipeline is
+//! replaced another way. The MCP server writes tickets from its own process,
+//! so the cache can be stale: status views expire after `ENTRY_TTL`, and step
+//! updates are compare-and-set against the stored steps, falling back to a
+//! whole-pipeline write when any of them changed underneath.
+
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use serde::Serialize;
+use serde_json::Value;
+use ticketing_system::{
+    models::{Pipeline, PipelineStepStatus},
+    tickets, SqlitePool,
+};
+
+use crate::pipeline_status::{phase, PipelinePhase};
+
+/// How long a status view is served without reading the ticket again
+const ENTRY_TTL: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StepStatusView {
+    pub step_id: String,
+    pub status: PipelineStepStatus,
+}
+
+/// Step statuses of a ticket's pipeline, without step inputs or outputs
+#[derive(Debug, Clone, Serialize)]
+pub struct PipelineStatusView {
+    pub ticket_id: String,
+    /// Empty when the ticket has no pipeline
+    pub steps: Vec<StepStatusView>,
+    pub phase: Option<PipelinePhase>,
+}
+
+impl PipelineStatusView {
+    fn new(ticket_id: &str, pipeline: Option<&Pipeline>) -> Self {
+        Self {
+            ticket_id: ticket_id.to_string(),
+            steps: pipeline
+                .map(|p| {
+                    p.steps
+                        .iter()
+                        .map(|s| StepStatusView { step_id: s.step_id.clone(), status: s.status.clone() })
+                        .collect()
+                })
+                .unwrap_or_default(),
+            phase: pipeline.and_then(phase),
+        }
+    }
+
+    pub fn has_pipeline(&self) -> bool {
+        !self.steps.is_empty()
+    }
+}
+
+/// A pipeline's JSON split into the pipeline without its steps, and each step
+#[derive(Debug, Clone, PartialEq)]
+struct Snapshot {
+    head: Value,
+    steps: Vec<Value>,
+}
+
+impl Snapshot {
+    fn of(pipeline: &Pipeline) -> Option<Self> {
+        let mut head = serde_json::to_value(pipeline).ok()?;
+        let steps = match head.as_object_mut()?.remove("steps")? {
+            Value::Array(steps) => steps,
+            _ => return None,
+        };
+        Some(Self { head, steps })
+    }
+}
+
+/// Indices of the steps that differ, or `None` when more than step contents
+/// changed (steps added, removed or reordered, or pipeline-level fields) and
+/// the whole pipeline has to be written.
+fn changed_steps(before: &Snapshot, after: &Snapshot) -> Option<Vec<usize>> {
+    let step_id = |step: &Value| step.get("step_id").cloned();
+    if before.head != after.head
+        || before.steps.len() != after.steps.len()
+        || before.steps.iter().zip(&after.steps).any(|(b, a)| step_id(b) != step_id(a))
+    {
+        return None;
+    }
+    Some(
+        before
+            .steps
+            .iter()
+            .zip(&after.steps)
+            .enumerate()
+            .filter(|(_, (b, a))| b != a)
+            .map(|(idx, _)| idx)
+            .collect(),
+    )
+}
+
+struct Entry {
+    view: Arc<PipelineStatusView>,
+    /// `None` when the ticket has no pipeline
+    snapshot: Option<Snapshot>,
+    stored_at: Instant,
+}
+
+lazy_static::lazy_static! {
+    static ref ENTRIES: RwLock<HashMap<String, Entry>> = RwLock::new(HashMap::new());
+}
+
+/// Write through a pipeline just read or saved for a ticket
+pub fn record(ticket_id: &str, pipeline: Option<&Pipeline>) {
+    let entry = Entry {
+        view: Arc::new(PipelineStatusView::new(ticket_id, pipeline)),
+        snapshot: pipeline.and_then(Snapshot::of),
+        stored_at: Instant::now(),
+    };
+    ENTRIES.write().unwrap().insert(ticket_id.to_string(), entry);
+}
+
+/// Drop a ticket's entry after its pipeline was replaced or the ticket deleted
+pub fn invalidate(ticket_id: &str) {
+    ENTRIES.write().unwrap().remove(ticket_id);
+}
+
+fn fresh_view(ticket_id: &str) -> Option<Arc<PipelineStatusView>> {
+    let entries = ENTRIES.read().unwrap();
+    entries
+        .get(ticket_id)
+        .filter(|e| e.stored_at.elapsed() < ENTRY_TTL)
+        .map(|e| e.view.clone())
+}
+
+/// Step statuses of a ticket's pipeline, or `None` when the ticket doesn't exist
+pub async fn status_view(pool: &SqlitePool, ticket_id: &str) -> Result<Option<Arc<PipelineStatusView>>> {
+    if let Some(view) = fresh_view(ticket_id) {
+        return Ok(Some(view));
+    }
+    let Some(ticket) = tickets::get_ticket_by_id(pool, ticket_id).await? else {
+        invalidate(ticket_id);
+        return Ok(None);
+    };
+    record(ticket_id, ticket.pipeline.as_ref());
+    Ok(fresh_view(ticket_id))
+}
+
+/// Save a ticket's pipeline, updating only the steps that changed since it was
+/// cached, and only while the stored steps still match the cache. Falls back to
+/// rewriting the whole pipeline, which is also how a save that changes nothing
+/// is written, since the cache can't tell whether the stored row still matches.
+pub async fn write_pipeline(pool: &SqlitePool, ticket_id: &str, pipeline: &Pipeline) -> Result<()> {
+    let after = Snapshot::of(pipeline);
+    let updates = {
+        let entries = ENTRIES.read().unwrap();
+        entries.get(ticket_id).and_then(|e| {
+            let before = e.snapshot.as_ref()?;
+            let changed = changed_steps(before, after.as_ref()?)?;
+            let updates: Vec<_> = changed
+                .into_iter()
+                .map(|idx| (idx, before.steps[idx].clone(), &pipeline.steps[idx]))
+                .collect();
+            Some(updates).filter(|u| !u.is_empty())
+        })
+    };
+
+    let updated = match updates {
+        Some(updates) => {
+            let steps: Vec<_> = updates.iter().map(|(idx, expected, step)| (*idx, expected, *step)).collect();
+            // Only applied while every stored step still equals its cached value
+            tickets::update_pipeline_steps(pool, ticket_id, &steps).await?
+        }
+        None => false,
+    };
+    if !updated {
+        tickets::update_ticket_pipeline(pool, ticket_id, Some(pipeline)).await?;
+    }
+    record(ticket_id, Some(pipeline));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn snapshot(head: Value, steps: Vec<Value>) -> Snapshot {
+        Snapshot { head, steps }
+    }
+
+    #[test]
+    fn diffs_steps_only_when_the_shape_is_unchanged() {
+        let head = json!({"template_id": "t1"});
+        let before = snapshot(
+            head.clone(),
+            vec![json!({"step_id": "a", "status": "completed"}), json!({"step_id": "b", "status": "queued"})],
+        );
+        let started = snapshot(
+            head.clone(),
+            vec![json!({"step_id": "a", "status": "completed"}), json!({"step_id": "b", "status": "running"})],
+        );
+        assert_eq!(changed_steps(&before, &started), Some(vec![1]));
+        assert_eq!(changed_steps(&before, &before), Some(vec![]));
+
+        let reordered = snapshot(head, vec![before.steps[1].clone(), before.steps[0].clone()]);
+        assert_eq!(changed_steps(&before, &reordered), None);
+        let retemplated = snapshot(json!({"template_id": "t2"}), before.steps.clone());
+        assert_eq!(changed_steps(&before, &retemplated), None);
+    }
+}
@@ -0,0 +1,197 @@
+//! Ticket status transitions driven by pipeline progress
+//!
+//! Every pipeline save goes through `save_pipeline`, which moves the ticket's
+//! status when the pipeline enters a new phase: started, awaiting approval,
+//! completed or failed. The status for each phase comes from the template's
+//! `status_transitions` (copied onto the pipeline when attached), falling back
+//! to `DEFAULT_TRANSITIONS`; an empty string turns a transition off. Status is
+//! only written on a phase change, so manual status edits between pipeline
+//! events are left alone. The previous phase comes from the pipeline read
+//! model, so the ticket itself is only loaded when the phase changes.
+
+use anyhow::Result;
+use serde::Serialize;
+use ticketing_system::{
+    models::{Pipeline, PipelineStepStatus, StatusTransitions},
+    tickets, SqlitePool,
+};
+use tracing::{info, warn};
+
+use crate::pipeline_read_model::{self, PipelineStatusView};
+use crate::user_events::{self, Subject, UserEvent};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PipelinePhase {
+    Started,
+    AwaitingApproval,
+    Completed,
+    Failed,
+}
+
+/// Status per phase when the template doesn't set one
+const DEFAULT_TRANSITIONS: [(PipelinePhase, &str); 4] = [
+    (PipelinePhase::Started, "in_progress"),
+    (PipelinePhase::AwaitingApproval, "in_review"),
+    (PipelinePhase::Completed, "completed"),
+    (PipelinePhase::Failed, "blocked"),
+];
+
+/// Longest status a template may configure
+pub const MAX_STATUS_LEN: usize = 32;
+
+/// The phase a pipeline is in, or `None` before any step has started
+pub fn phase(pipeline: &Pipeline) -> Option<PipelinePhase> {
+    if pipeline.has_failed() {
+        return Some(PipelinePhase::Failed);
+    }
+    if pipeline.is_complete() {
+        return Some(PipelinePhase::Completed);
+    }
+    if pipeline.steps.iter().any(|s| s.status == PipelineStepStatus::AwaitingApproval) {
+        return Some(PipelinePhase::AwaitingApproval);
+    }
+    pipeline
+        .steps
+        .iter()
+        .any(|s| s.status != PipelineStepStatus::Queued)
+        .then_some(PipelinePhase::Started)
+}
+
+/// Ticket status for entering `phase`, or `None` when the transition is turned off
+pub fn status_for(transitions: Option<&StatusTransitions>, phase: PipelinePhase) -> Option<String> {
+    let configured = transitions.and_then(|t| match phase {
+        PipelinePhase::Started => t.started.clone(),
+        PipelinePhase::AwaitingApproval => t.awaiting_approval.clone(),
+        PipelinePhase::Completed => t.completed.clone(),
+        PipelinePhase::Failed => t.failed.clone(),
+    });
+    let status = configured.unwrap_or_else(|| {
+        DEFAULT_TRANSITIONS
+            .iter()
+            .find(|(p, _)| *p == phase)
+            .map(|(_, status)| status.to_string())
+            .unwrap_or_default()
+    });
+    (!status.is_empty()).then_some(status)
+}
+
+/// Problems with a template's configured statuses
+pub fn validate_transitions(transitions: &StatusTransitions) -> Vec<String> {
+    [
+        ("started", &transitions.started),
+        ("awaiting_approval", &transitions.awaiting_approval),
+        ("completed", &transitions.completed),
+        ("failed", &transitions.failed),
+    ]
+    .into_iter()
+    .filter_map(|(name, status)| {
+        let status = status.as_deref()?;
+        let valid = status.len() <= MAX_STATUS_LEN && status.chars().all(|c| c.is_ascii_lowercase() || c == '_');
+        (!valid).then(|| format!("status_transitions.{}: '{}' is not a valid status", name, status))
+    })
+    .collect()
+}
+
+/// Update the ticket's status if the pipeline moved into a new phase since `before`
+async fn apply_transition(pool: &SqlitePool, before: &PipelineStatusView, pipeline: &Pipeline) {
+    let Some(new_phase) = phase(pipeline) else { return };
+    if before.phase == Some(new_phase) {
+        return;
+    }
+    let Some(status) = status_for(pipeline.status_transitions.as_ref(), new_phase) else { return };
+    let before = match tickets::get_ticket_by_id(pool, &before.ticket_id).await {
+        Ok(Some(ticket)) => ticket,
+        Ok(None) => return,
+        Err(e) => {
+            warn!("Failed to load ticket {} for its status transition: {}", before.ticket_id, e);
+            return;
+        }
+    };
+    if before.status == status {
+        return;
+    }
+
+    match tickets::update_ticket_status(
+        pool,
+        &before.organization,
+        &before.epic_id,
+        &before.slice_id,
+        &before.ticket_id,
+        &status,
+    )
+    .await
+    {
+        Ok(_) => {
+            info!("Pipeline {:?} on ticket {}: status → {}", new_phase, before.ticket_id, status);
+            let event = UserEvent::TicketStatus {
+                ticket_id: before.ticket_id.clone(),
+                organization: before.organization.clone(),
+                status,
+            };
+            user_events::publish(pool, Subject::Ticket, &before.ticket_id, None, event).await;
+        }
+        Err(e) => warn!("Failed to update status of ticket {} to {}: {}", before.ticket_id, status, e),
+    }
+}
+
+/// Tell the ticket's followers about steps that started waiting for approval,
+/// and email the approvers listed on those steps
+async fn notify_awaiting_approval(pool: &SqlitePool, before: &PipelineStatusView, pipeline: &Pipeline) {
+    let was_awaiting = |step_id: &str| {
+        before
+            .steps
+            .iter()
+            .any(|s| s.step_id == step_id && s.status == PipelineStepStatus::AwaitingApproval)
+    };
+    for step in &pipeline.steps {
+        if step.status == PipelineStepStatus::AwaitingApproval && !was_awaiting(&step.step_id) {
+            let event = UserEvent::StepAwaitingApproval {
+                ticket_id: before.ticket_id.clone(),
+                step_id: step.step_id.clone(),
+            };
+            user_events::publish(pool, Subject::Ticket, &before.ticket_id, None, event).await;
+            if step.approval_emails.as_ref().is_some_and(|approvers| !approvers.is_empty()) {
+                let (pool, ticket_id, step) = (pool.clone(), before.ticket_id.clone(), step.clone());
+                tokio::spawn(async move {
+                    crate::email_approvals::send_approval_emails(&pool, &ticket_id, &step).await;
+                });
+            }
+        }
+    }
+}
+
+/// Save a ticket's pipeline and apply any status transition it triggers
+pub async fn save_pipeline(pool: &SqlitePool, ticket_id: &str, pipeline: &Pipeline) -> Result<()> {
+    let before = pipeline_read_model::status_view(pool, ticket_id).await?;
+    pipeline_read_model::write_pipeline(pool, ticket_id, pipeline).await?;
+    if let Some(before) = before {
+        notify_awaiting_approval(pool, &before, pipeline).await;
+        apply_transition(pool, &before, pipeline).await;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn maps_phases_with_overrides() {
+        assert_eq!(status_for(None, PipelinePhase::Started).as_deref(), Some("in_progress"));
+        assert_eq!(status_for(None, PipelinePhase::Failed).as_deref(), Some("blocked"));
+
+        let transitions = StatusTransitions {
+            started: Some("doing".to_string()),
+            awaiting_approval: Some(String::new()),
+            ..Default::default()
+        };
+        assert_eq!(status_for(Some(&transitions), PipelinePhase::Started).as_deref(), Some("doing"));
+        assert_eq!(status_for(Some(&transitions), PipelinePhase::AwaitingApproval), None);
+        assert_eq!(status_for(Some(&transitions), PipelinePhase::Completed).as_deref(), Some("completed"));
+
+        assert!(validate_transitions(&transitions).is_empty());
+        let invalid = StatusTransitions { failed: Some("On Hold".to_string()), ..Default::default() };
+        assert_eq!(validate_transitions(&invalid).len(), 1);
+    }
+}
@@ -0,0 +1,125 @@
+//! Daily plan history and carry-over
+//!
+//! Each morning, once `CARRY_OVER_HOUR` (UTC) has passed, a job stores
+//! yesterday's plan as it ended, so past days can be browsed without being
+//! rebuilt from today's recurring items, and copies the unchecked one-off items
+//! of the last plan before today (yesterday's, unless days were skipped) onto
+//! today's plan, marked with the date they were carried over from. Recurring
+//! items are on every day's plan already and aren't copied. Items already on
+//! today's plan (by title) are skipped, so the job can run again after a
+//! restart without duplicating anything.
+
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use anyhow::Result;
+use chrono::{Duration, NaiveDate, Timelike, Utc};
+use ticketing_system::{daily_plan, CreateDailyPlanDateItemRequest, DailyPlanDateItem, SqlitePool};
+
+/// How often the job checks whether the morning run is due
+const CHECK_INTERVAL_SECS: u64 = 15 * 60;
+
+/// Hour (UTC) after which yesterday's unfinished items move to today
+const CARRY_OVER_HOUR: u32 = 4;
+
+const DATE_FORMAT: &str = "%Y-%m-%d";
+
+fn date_key(date: NaiveDate) -> String {
+    date.format(DATE_FORMAT).to_string()
+}
+
+/// Whether an item is carried over: it wasn't checked off and no item with
+/// its title (ignoring case) is on the target plan or carried already
+fn should_carry(item_id: &str, title: &str, checked: &HashSet<String>, titles: &mut HashSet<String>) -> bool {
+    !checked.contains(item_id) && titles.insert(title.to_lowercase())
+}
+
+/// Store a day's plan as it stands, replacing any earlier snapshot of it
+pub async fn snapshot_plan(db: &SqlitePool, date: NaiveDate) -> Result<()> {
+    let date = date_key(date);
+    let plan = daily_plan::get_plan_for_date(db, &date).await?;
+    daily_plan::save_plan_snapshot(db, &date, &plan).await
+}
+
+/// Copy the one-off items left unchecked on `from` onto `to`
+pub async fn carry_over(db: &SqlitePool, from: NaiveDate, to: NaiveDate) -> Result<Vec<DailyPlanDateItem>> {
+    let (from, to) = (date_key(from), date_key(to));
+    let checked: HashSet<String> = daily_plan::checked_item_ids(db, &from).await?.into_iter().collect();
+    let mut titles: HashSet<String> = daily_plan::list_date_items(db, &to)
+        .await?
+        .into_iter()
+        .map(|item| item.title.to_lowercase())
+        .collect();
+
+    let mut created = Vec::new();
+    for item in daily_plan::list_date_items(db, &from).await? {
+        if !should_carry(&item.item_id, &item.title, &checked, &mut titles) {
+            continue;
+        }
+        let carried = daily_plan::create_date_item(
+            db,
+            CreateDailyPlanDateItemRequest {
+                date: to.clone(),
+                title: item.title,
+                time: item.time,
+                notes: item.notes,
+                carried_over_from: Some(from.clone()),
+            },
+        )
+        .await?;
+        created.push(carried);
+    }
+    Ok(created)
+}
+
+/// Snapshot yesterday's plan and carry the last plan's unfinished items over
+/// to today
+async fn run_morning(db: &SqlitePool, today: NaiveDate) -> Result<usize> {
+    let yesterday = today - Duration::days(1);
+    snapshot_plan(db, yesterday).await?;
+    let Some(last) = daily_plan::last_date_with_items(db, &date_key(today)).await? else {
+        return Ok(0);
+    };
+    let last = NaiveDate::parse_from_str(&last, DATE_FORMAT)?;
+    Ok(carry_over(db, last, today).await?.len())
+}
+
+/// Start the daily carry-over job
+pub fn start_plan_carryover_job(db: Arc<SqlitePool>) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(std::time::Duration::from_secs(CHECK_INTERVAL_SECS));
+        let mut last_run: Option<NaiveDate> = None;
+        loop {
+            interval.tick().await;
+            let now = Utc::now();
+            let today = now.date_naive();
+            if now.hour() < CARRY_OVER_HOUR || last_run == Some(today) || crate::maintenance::is_enabled() {
+                continue;
+            }
+            match run_morning(&db, today).await {
+                Ok(carried) => {
+                    last_run = Some(today);
+                    if carried > 0 {
+                        tracing::info!("Carried {} unfinished daily plan items over to {}", carried, today);
+                    }
+                }
+                Err(e) => tracing::error!("Daily plan carry-over failed: {:?}", e),
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn carries_unchecked_items_once_per_title() {
+        let checked = HashSet::from(["done".to_string()]);
+        let mut titles = HashSet::from(["call bank".to_string()]);
+        assert!(!should_carry("done", "Finish report", &checked, &mut titles));
+        assert!(!should_carry("a", "Call Bank", &checked, &mut titles));
+        assert!(should_carry("b", "Book flights", &checked, &mut titles));
+        assert!(!should_carry("c", "book flights", &checked, &mut titles));
+    }
+}
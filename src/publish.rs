@@ -0,0 +1,280 @@
+//! Publishing execution-step changes
+//!
+//! A pipeline template step can carry a `publish` config. An execution agent
+//! step with one runs in the ticket's own `git worktree` of its working
+//! directory's repository, on a branch named after the ticket (see
+//! `ticket_worktree`), so it never sees or sweeps up what other tickets and
+//! agents leave in the shared checkout, which is never switched to another
+//! branch. When the step completes, the worktree's changes are committed,
+//! pushed, and (optionally) opened as a GitHub pull request. The branch,
+//! commit and PR URL go into the step outputs and the PR URL into the ticket's
+//! `pr_url` field.
+//!
+//! Worktrees live in `~/.agentic-flowstate/worktrees/<repo>/<ticket>` and are
+//! reused by later publishing steps of the same ticket.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, bail, Context, Result};
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+use tokio::process::Command;
+use ticketing_system::{models::PublishConfig, tickets, SqlitePool, Ticket};
+
+use crate::agents::AgentType;
+
+const API_URL: &str = "https://api.github.com";
+
+/// Secret (or environment variable) holding the token used to open PRs
+const TOKEN_NAME: &str = "GITHUB_TOKEN";
+
+/// Custom field holding the ticket's latest PR URL
+pub const PR_URL_FIELD: &str = "pr_url";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PublishedChanges {
+    pub branch: String,
+    pub commit: String,
+    pub pr_url: Option<String>,
+}
+
+impl PublishedChanges {
+    /// Merge into a step's outputs object
+    pub fn merge_into(&self, outputs: &mut Value) {
+        if let Value::Object(map) = outputs {
+            map.insert("branch".to_string(), json!(self.branch));
+            map.insert("commit".to_string(), json!(self.commit));
+            if let Some(pr_url) = &self.pr_url {
+                map.insert(PR_URL_FIELD.to_string(), json!(pr_url));
+            }
+        }
+    }
+}
+
+/// Only execution agents change code worth committing
+pub fn supports_publish(agent_type: &str) -> bool {
+    agent_type == AgentType::Execution.as_str()
+}
+
+/// `ticket/<ticket-id>`, restricted to characters git accepts in ref names
+pub fn branch_name(ticket_id: &str) -> String {
+    let id: String = ticket_id
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '-' })
+        .collect();
+    format!("ticket/{}", id.trim_matches('-'))
+}
+
+/// `(owner, repo)` from an HTTPS or SSH GitHub remote URL
+fn parse_github_remote(url: &str) -> Option<(String, String)> {
+    let url = url.trim();
+    let path = url
+        .strip_prefix("git@github.com:")
+        .or_else(|| url.strip_prefix("ssh://git@github.com/"))
+        .or_else(|| url.strip_prefix("https://github.com/"))
+        .or_else(|| url.strip_prefix("http://github.com/"))?;
+    let (owner, repo) = path.trim_end_matches('/').split_once('/')?;
+    let repo = repo.trim_end_matches(".git");
+    if owner.is_empty() || repo.is_empty() || repo.contains('/') {
+        return None;
+    }
+    Some((owner.to_string(), repo.to_string()))
+}
+
+/// Directory of a ticket's worktree of the repository at `repo_root`
+fn worktree_dir(worktrees_root: &Path, repo_root: &Path, ticket_id: &str) -> PathBuf {
+    let repo = repo_root.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_else(|| "repo".to_string());
+    let branch = branch_name(ticket_id);
+    worktrees_root.join(repo).join(branch.trim_start_matches("ticket/"))
+}
+
+async fn git(dir: &Path, args: &[&str]) -> Result<String> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .await
+        .with_context(|| format!("Failed to run git {}", args.join(" ")))?;
+    if !output.status.success() {
+        bail!("git {} failed: {}", args.join(" "), String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+async fn github_token(db: &SqlitePool, organization: &str) -> Option<String> {
+    crate::secrets::agent_env(db, organization, &AgentType::Execution)
+        .await
+        .remove(TOKEN_NAME)
+        .or_else(|| std::env::var(TOKEN_NAME).ok())
+        .filter(|t| !t.trim().is_empty())
+}
+
+/// Open a PR for `branch`, or return the URL of the one already open for it
+async fn open_pull_request(
+    token: &str,
+    owner: &str,
+    repo: &str,
+    branch: &str,
+    base: &str,
+    ticket: &Ticket,
+    draft: bool,
+) -> Result<String> {
+    let client = reqwest::Client::new();
+    let pulls_url = format!("{}/repos/{}/{}/pulls", API_URL, owner, repo);
+
+    let existing: Value = client
+        .get(&pulls_url)
+        .query(&[("head", format!("{}:{}", owner, branch)), ("state", "open".to_string())])
+        .bearer_auth(token)
+        .header("Accept", "application/vnd.github+json")
+        .header("User-Agent", "agentic-flowstate")
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    if let Some(url) = existing.get(0).and_then(|pr| pr.get("html_url")).and_then(|u| u.as_str()) {
+        return Ok(url.to_string());
+    }
+
+    let body = format!(
+        "Automated changes for ticket `{}`.\n\n{}",
+        ticket.ticket_id,
+        ticket.description.as_deref().unwrap_or_default()
+    );
+    let created: Value = client
+        .post(&pulls_url)
+        .bearer_auth(token)
+        .header("Accept", "application/vnd.github+json")
+        .header("User-Agent", "agentic-flowstate")
+        .json(&json!({
+            "title": format!("{}: {}", ticket.ticket_id, ticket.title),
+            "head": branch,
+            "base": base,
+            "body": body,
+            "draft": draft,
+        }))
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    created
+        .get("html_url")
+        .and_then(|u| u.as_str())
+        .map(|u| u.to_string())
+        .ok_or_else(|| anyhow!("GitHub response had no html_url"))
+}
+
+/// The directory a publishing step of the ticket runs in: the ticket's
+/// worktree of the repository `working_dir` is in, at the same subdirectory.
+/// Created on first use, on the ticket branch, which starts from the
+/// configured base branch or the shared checkout's current commit.
+pub async fn ticket_worktree(working_dir: &Path, ticket_id: &str, config: &PublishConfig) -> Result<PathBuf> {
+    let repo_root = PathBuf::from(git(working_dir, &["rev-parse", "--show-toplevel"]).await?);
+    let subdir = working_dir.strip_prefix(&repo_root).unwrap_or(Path::new("")).to_path_buf();
+    let worktrees_root = dirs::home_dir()
+        .context("Could not determine home directory")?
+        .join(".agentic-flowstate")
+        .join("worktrees");
+    let dir = worktree_dir(&worktrees_root, &repo_root, ticket_id);
+
+    if !dir.exists() {
+        if let Some(parent) = dir.parent() {
+            tokio::fs::create_dir_all(parent).await.context("Failed to create worktrees directory")?;
+        }
+        let dir_arg = dir.to_string_lossy().to_string();
+        let branch = branch_name(ticket_id);
+        let branch_ref = format!("refs/heads/{}", branch);
+        // Later steps on the same ticket add commits to the same branch
+        if git(&repo_root, &["rev-parse", "--verify", "--quiet", &branch_ref]).await.is_ok() {
+            git(&repo_root, &["worktree", "add", &dir_arg, &branch]).await?;
+        } else {
+            let start = config.base_branch.as_deref().unwrap_or("HEAD");
+            git(&repo_root, &["worktree", "add", "-b", &branch, &dir_arg, start]).await?;
+        }
+    }
+    Ok(dir.join(subdir))
+}
+
+/// Branch a PR targets: the configured one, else the remote's default branch
+async fn base_branch(dir: &Path, config: &PublishConfig) -> Result<String> {
+    if let Some(base) = &config.base_branch {
+        return Ok(base.clone());
+    }
+    let head = git(dir, &["symbolic-ref", "--short", "refs/remotes/origin/HEAD"])
+        .await
+        .context("Could not determine the remote's default branch; set publish.base_branch")?;
+    Ok(head.strip_prefix("origin/").unwrap_or(&head).to_string())
+}
+
+/// Commit the changes in the ticket's worktree (see `ticket_worktree`), push,
+/// and open a PR.
+///
+/// Returns `None` when there is nothing to commit.
+pub async fn publish_step_changes(
+    db: &SqlitePool,
+    ticket: &Ticket,
+    step_id: &str,
+    worktree: &Path,
+    config: &PublishConfig,
+) -> Result<Option<PublishedChanges>> {
+    if git(worktree, &["status", "--porcelain"]).await?.is_empty() {
+        return Ok(None);
+    }
+
+    let branch = branch_name(&ticket.ticket_id);
+    let current = git(worktree, &["rev-parse", "--abbrev-ref", "HEAD"]).await?;
+    if current != branch {
+        bail!("{} is on '{}', not the ticket branch {}", worktree.display(), current, branch);
+    }
+
+    git(worktree, &["add", "-A"]).await?;
+    let message = format!("{}: {}\n\nPipeline step: {}", ticket.ticket_id, ticket.title, step_id);
+    git(worktree, &["commit", "-m", &message]).await?;
+    let commit = git(worktree, &["rev-parse", "HEAD"]).await?;
+
+    let pr_url = if config.open_pr {
+        git(worktree, &["push", "-u", "origin", &branch]).await?;
+        let base = base_branch(worktree, config).await?;
+        let remote = git(worktree, &["remote", "get-url", "origin"]).await?;
+        let (owner, repo) =
+            parse_github_remote(&remote).ok_or_else(|| anyhow!("Remote '{}' is not a GitHub repository", remote))?;
+        let token = github_token(db, &ticket.organization)
+            .await
+            .ok_or_else(|| anyhow!("No {} secret configured for {}", TOKEN_NAME, ticket.organization))?;
+        let url = open_pull_request(&token, &owner, &repo, &branch, &base, ticket, config.draft).await?;
+
+        let mut fields = Map::new();
+        fields.insert(PR_URL_FIELD.to_string(), json!(url));
+        if let Err(e) = tickets::update_custom_fields(db, &ticket.ticket_id, &fields).await {
+            tracing::warn!("Failed to record PR URL on {}: {}", ticket.ticket_id, e);
+        }
+        Some(url)
+    } else {
+        None
+    };
+
+    Ok(Some(PublishedChanges { branch, commit, pr_url }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_branches_and_parses_remotes() {
+        assert_eq!(branch_name("tkt_123"), "ticket/tkt_123");
+        assert_eq!(branch_name("a b/c"), "ticket/a-b-c");
+
+        let expected = Some(("acme".to_string(), "api".to_string()));
+        assert_eq!(parse_github_remote("git@github.com:acme/api.git"), expected);
+        assert_eq!(parse_github_remote("https://github.com/acme/api"), expected);
+        assert_eq!(parse_github_remote("https://gitlab.com/acme/api.git"), None);
+
+        assert_eq!(
+            worktree_dir(Path::new("/w"), Path::new("/src/api"), "a b/c"),
+            PathBuf::from("/w/api/a-b-c")
+        );
+    }
+}
@@ -0,0 +1,463 @@
+//! Monthly usage quotas per organization
+//!
+//! Limits come from `~/.agentic-flowstate/quotas.json`: a `default` set plus
+//! per-organization overrides, every limit optional (absent means unlimited):
+//!
+//! ```json
+//! { "default": { "agent_runs": 500 },
+//!   "organizations": { "acme": { "cost_usd": 200.0, "emails_sent": 1000 } } }
+//! ```
+//!
+//! Usage goes into a ledger as agent runs finish, emails are sent and meeting
+//! audio is transcribed, and is checked before an agent is spawned, an email
+//! goes out or a transcription starts. Periods are UTC calendar months.
+//! Exhausted count quotas (agent runs, emails, transcription minutes) answer
+//! 429; exhausted spend quotas (tokens, cost, transcription cost) answer 402.
+//!
+//! Transcription is priced at `transcription_usd_per_minute` (Whisper's list
+//! price by default) and tracked per meeting as well. When a transcription
+//! takes an organization past one of the `warn_at` fractions of its
+//! transcription budget, a notification says so; a transcription that
+//! started under budget always finishes.
+
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+use anyhow::{Context, Result};
+use axum::http::StatusCode;
+use chrono::{DateTime, Datelike, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use ticketing_system::{usage, SqlitePool};
+
+use ticketing_system::{CreateNotificationRequest, MeetingTranscriptionUsage};
+
+use crate::agents::RunUsage;
+
+/// OpenAI's price for `whisper-1`
+const DEFAULT_TRANSCRIPTION_USD_PER_MINUTE: f64 = 0.006;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Metric {
+    AgentRuns,
+    Tokens,
+    CostUsd,
+    EmailsSent,
+    TranscriptionMinutes,
+    TranscriptionCostUsd,
+}
+
+impl Metric {
+    pub const ALL: [Metric; 6] = [
+        Metric::AgentRuns,
+        Metric::Tokens,
+        Metric::CostUsd,
+        Metric::EmailsSent,
+        Metric::TranscriptionMinutes,
+        Metric::TranscriptionCostUsd,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Metric::AgentRuns => "agent_runs",
+            Metric::Tokens => "tokens",
+            Metric::CostUsd => "cost_usd",
+            Metric::EmailsSent => "emails_sent",
+            Metric::TranscriptionMinutes => "transcription_minutes",
+            Metric::TranscriptionCostUsd => "transcription_cost_usd",
+        }
+    }
+
+    fn exceeded_status(&self) -> StatusCode {
+        match self {
+            Metric::AgentRuns | Metric::EmailsSent | Metric::TranscriptionMinutes => StatusCode::TOO_MANY_REQUESTS,
+            Metric::Tokens | Metric::CostUsd | Metric::TranscriptionCostUsd => StatusCode::PAYMENT_REQUIRED,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct QuotaLimits {
+    pub agent_runs: Option<u64>,
+    pub tokens: Option<u64>,
+    pub cost_usd: Option<f64>,
+    pub emails_sent: Option<u64>,
+    pub transcription_minutes: Option<f64>,
+    pub transcription_cost_usd: Option<f64>,
+}
+
+impl QuotaLimits {
+    pub fn get(&self, metric: Metric) -> Option<f64> {
+        match metric {
+            Metric::AgentRuns => self.agent_runs.map(|n| n as f64),
+            Metric::Tokens => self.tokens.map(|n| n as f64),
+            Metric::CostUsd => self.cost_usd,
+            Metric::EmailsSent => self.emails_sent.map(|n| n as f64),
+            Metric::TranscriptionMinutes => self.transcription_minutes,
+            Metric::TranscriptionCostUsd => self.transcription_cost_usd,
+        }
+    }
+
+    /// These limits with any set in `overrides` replacing them
+    fn with_overrides(&self, overrides: &QuotaLimits) -> QuotaLimits {
+        QuotaLimits {
+            agent_runs: overrides.agent_runs.or(self.agent_runs),
+            tokens: overrides.tokens.or(self.tokens),
+            cost_usd: overrides.cost_usd.or(self.cost_usd),
+            emails_sent: overrides.emails_sent.or(self.emails_sent),
+            transcription_minutes: overrides.transcription_minutes.or(self.transcription_minutes),
+            transcription_cost_usd: overrides.transcription_cost_usd.or(self.transcription_cost_usd),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct QuotaConfig {
+    pub default: QuotaLimits,
+    pub organizations: HashMap<String, QuotaLimits>,
+    /// Fractions of a transcription budget that raise a warning when crossed
+    pub warn_at: Vec<f64>,
+    pub transcription_usd_per_minute: f64,
+}
+
+impl Default for QuotaConfig {
+    fn default() -> Self {
+        Self {
+            default: QuotaLimits::default(),
+            organizations: HashMap::new(),
+            warn_at: vec![0.5, 0.8, 0.9],
+            transcription_usd_per_minute: DEFAULT_TRANSCRIPTION_USD_PER_MINUTE,
+        }
+    }
+}
+
+impl QuotaConfig {
+    pub fn limits_for(&self, organization: &str) -> QuotaLimits {
+        match self.organizations.get(organization) {
+            Some(overrides) => self.default.with_overrides(overrides),
+            None => self.default.clone(),
+        }
+    }
+}
+
+lazy_static::lazy_static! {
+    static ref QUOTAS: RwLock<QuotaConfig> = RwLock::new(QuotaConfig::default());
+}
+
+/// Load quotas from the config file (unlimited when absent).
+/// Returns the number of organizations with overrides; the old quotas stay on error.
+pub fn load_config() -> Result<usize> {
+    let path = dirs::home_dir()
+        .context("Could not determine home directory")?
+        .join(".agentic-flowstate")
+        .join("quotas.json");
+    let config: QuotaConfig = if path.exists() {
+        let content = std::fs::read_to_string(&path).context("Failed to read quota config")?;
+        serde_json::from_str(&content).context("Failed to parse quota config")?
+    } else {
+        QuotaConfig::default()
+    };
+    let count = config.organizations.len();
+    *QUOTAS.write().unwrap() = config;
+    Ok(count)
+}
+
+pub fn limits_for(organization: &str) -> QuotaLimits {
+    QUOTAS.read().unwrap().limits_for(organization)
+}
+
+/// Start of the month containing `now` and start of the next one
+pub fn current_period(now: DateTime<Utc>) -> (DateTime<Utc>, DateTime<Utc>) {
+    let start = Utc.with_ymd_and_hms(now.year(), now.month(), 1, 0, 0, 0).unwrap();
+    let (year, month) = if now.month() == 12 { (now.year() + 1, 1) } else { (now.year(), now.month() + 1) };
+    let end = Utc.with_ymd_and_hms(year, month, 1, 0, 0, 0).unwrap();
+    (start, end)
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MetricUsage {
+    pub metric: Metric,
+    pub used: f64,
+    /// `None` when unlimited
+    pub limit: Option<f64>,
+    pub remaining: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UsageReport {
+    pub organization: String,
+    pub period_start: String,
+    pub period_end: String,
+    pub metrics: Vec<MetricUsage>,
+}
+
+/// Consumption against limits for the current month
+pub async fn usage_report(db: &SqlitePool, organization: &str) -> Result<UsageReport> {
+    let (start, end) = current_period(Utc::now());
+    let totals = usage::totals_since(db, organization, &start.to_rfc3339()).await?;
+    let limits = limits_for(organization);
+    let metrics = Metric::ALL
+        .into_iter()
+        .map(|metric| {
+            let used = totals.get(metric.as_str()).copied().unwrap_or(0.0);
+            let limit = limits.get(metric);
+            MetricUsage { metric, used, limit, remaining: limit.map(|l| (l - used).max(0.0)) }
+        })
+        .collect();
+    Ok(UsageReport {
+        organization: organization.to_string(),
+        period_start: start.to_rfc3339(),
+        period_end: end.to_rfc3339(),
+        metrics,
+    })
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OrganizationTranscription {
+    pub organization: String,
+    pub minutes: f64,
+    pub cost_usd: f64,
+    pub minutes_limit: Option<f64>,
+    pub cost_limit_usd: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TranscriptionStats {
+    pub period_start: String,
+    pub period_end: String,
+    pub usd_per_minute: f64,
+    /// Most spent first
+    pub organizations: Vec<OrganizationTranscription>,
+    /// Meetings transcribed this month, most spent first
+    pub meetings: Vec<MeetingTranscriptionUsage>,
+}
+
+/// Transcription minutes and spend this month, across organizations
+pub async fn transcription_stats(db: &SqlitePool) -> Result<TranscriptionStats> {
+    let (start, end) = current_period(Utc::now());
+    let totals = usage::totals_by_organization_since(db, &start.to_rfc3339()).await?;
+    let mut organizations: Vec<OrganizationTranscription> = totals
+        .into_iter()
+        .filter_map(|(organization, totals)| {
+            let minutes = totals.get(Metric::TranscriptionMinutes.as_str()).copied()?;
+            let limits = limits_for(&organization);
+            Some(OrganizationTranscription {
+                minutes,
+                cost_usd: totals.get(Metric::TranscriptionCostUsd.as_str()).copied().unwrap_or(0.0),
+                minutes_limit: limits.get(Metric::TranscriptionMinutes),
+                cost_limit_usd: limits.get(Metric::TranscriptionCostUsd),
+                organization,
+            })
+        })
+        .collect();
+    organizations.sort_by(|a, b| b.cost_usd.total_cmp(&a.cost_usd));
+    let mut meetings = ticketing_system::meetings::list_transcription_usage(db, &start.to_rfc3339()).await?;
+    meetings.sort_by(|a, b| b.cost_usd.total_cmp(&a.cost_usd));
+    Ok(TranscriptionStats {
+        period_start: start.to_rfc3339(),
+        period_end: end.to_rfc3339(),
+        usd_per_minute: QUOTAS.read().unwrap().transcription_usd_per_minute,
+        organizations,
+        meetings,
+    })
+}
+
+#[derive(Debug, Clone)]
+pub struct QuotaExceeded {
+    pub organization: String,
+    pub metric: Metric,
+    pub used: f64,
+    pub limit: f64,
+    pub resets_at: String,
+}
+
+impl QuotaExceeded {
+    pub fn status(&self) -> StatusCode {
+        self.metric.exceeded_status()
+    }
+
+    pub fn message(&self) -> String {
+        format!(
+            "Monthly {} quota exhausted for organization '{}' ({} of {}); resets at {}",
+            self.metric.as_str(),
+            self.organization,
+            self.used,
+            self.limit,
+            self.resets_at
+        )
+    }
+}
+
+impl From<QuotaExceeded> for (StatusCode, String) {
+    fn from(exceeded: QuotaExceeded) -> Self {
+        (exceeded.status(), exceeded.message())
+    }
+}
+
+/// Fail when any of `metrics` has reached its limit. Ledger errors are logged
+/// and let the request through rather than blocking work.
+async fn check(db: &SqlitePool, organization: &str, metrics: &[Metric]) -> Result<(), QuotaExceeded> {
+    let limits = limits_for(organization);
+    if metrics.iter().all(|m| limits.get(*m).is_none()) {
+        return Ok(());
+    }
+    let (start, end) = current_period(Utc::now());
+    let totals = match usage::totals_since(db, organization, &start.to_rfc3339()).await {
+        Ok(totals) => totals,
+        Err(e) => {
+            tracing::warn!("Failed to load usage for {}, skipping quota check: {}", organization, e);
+            return Ok(());
+        }
+    };
+    for &metric in metrics {
+        let Some(limit) = limits.get(metric) else { continue };
+        let used = totals.get(metric.as_str()).copied().unwrap_or(0.0);
+        if used >= limit {
+            return Err(QuotaExceeded {
+                organization: organization.to_string(),
+                metric,
+                used,
+                limit,
+                resets_at: end.to_rfc3339(),
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Check before spawning an agent run
+pub async fn check_agent_run(db: &SqlitePool, organization: &str) -> Result<(), QuotaExceeded> {
+    check(db, organization, &[Metric::AgentRuns, Metric::Tokens, Metric::CostUsd]).await
+}
+
+/// Check before sending an email
+pub async fn check_email(db: &SqlitePool, organization: &str) -> Result<(), QuotaExceeded> {
+    check(db, organization, &[Metric::EmailsSent]).await
+}
+
+async fn record(db: &SqlitePool, organization: &str, metric: Metric, amount: f64) {
+    if amount <= 0.0 {
+        return;
+    }
+    if let Err(e) = usage::record_usage(db, organization, metric.as_str(), amount).await {
+        tracing::warn!("Failed to record {} usage for {}: {}", metric.as_str(), organization, e);
+    }
+}
+
+/// Check before transcribing meeting audio
+pub async fn check_transcription(db: &SqlitePool, organization: &str) -> Result<(), QuotaExceeded> {
+    check(db, organization, &[Metric::TranscriptionMinutes, Metric::TranscriptionCostUsd]).await
+}
+
+/// The highest of `thresholds` (fractions of `limit`) that usage went past
+/// going from `before` to `after`
+fn crossed_threshold(before: f64, after: f64, limit: f64, thresholds: &[f64]) -> Option<f64> {
+    thresholds
+        .iter()
+        .copied()
+        .filter(|t| before < t * limit && after >= t * limit)
+        .max_by(f64::total_cmp)
+}
+
+/// Record transcribed meeting audio, on the meeting and in the ledger, and
+/// warn when it takes the organization past a budget threshold
+pub async fn record_transcription(db: &SqlitePool, organization: &str, room_id: &str, seconds: f64) {
+    if seconds <= 0.0 {
+        return;
+    }
+    let (limits, warn_at, usd_per_minute) = {
+        let config = QUOTAS.read().unwrap();
+        (config.limits_for(organization), config.warn_at.clone(), config.transcription_usd_per_minute)
+    };
+    let minutes = seconds / 60.0;
+    let cost_usd = minutes * usd_per_minute;
+    if let Err(e) = ticketing_system::meetings::add_transcription_usage(db, room_id, minutes, cost_usd).await {
+        tracing::warn!("Failed to record transcription usage of meeting {}: {}", room_id, e);
+    }
+
+    let budgeted = [(Metric::TranscriptionMinutes, minutes), (Metric::TranscriptionCostUsd, cost_usd)]
+        .into_iter()
+        .filter_map(|(metric, amount)| Some((metric, amount, limits.get(metric)?)))
+        .collect::<Vec<_>>();
+    let totals = if budgeted.is_empty() {
+        None
+    } else {
+        let (start, _) = current_period(Utc::now());
+        match usage::totals_since(db, organization, &start.to_rfc3339()).await {
+            Ok(totals) => Some(totals),
+            Err(e) => {
+                tracing::warn!("Failed to load usage for {}, skipping budget warnings: {}", organization, e);
+                None
+            }
+        }
+    };
+    record(db, organization, Metric::TranscriptionMinutes, minutes).await;
+    record(db, organization, Metric::TranscriptionCostUsd, cost_usd).await;
+
+    let Some(totals) = totals else { return };
+    for (metric, amount, limit) in budgeted {
+        let before = totals.get(metric.as_str()).copied().unwrap_or(0.0);
+        let Some(threshold) = crossed_threshold(before, before + amount, limit, &warn_at) else {
+            continue;
+        };
+        let title = format!(
+            "{} has used {:.0}% of its monthly {} budget",
+            organization,
+            threshold * 100.0,
+            metric.as_str()
+        );
+        tracing::warn!("{}", title);
+        crate::notifications::notify(
+            db,
+            CreateNotificationRequest {
+                kind: "transcription_budget".to_string(),
+                title,
+                body: Some(format!("{:.2} of {:.2} used this month", before + amount, limit)),
+                ticket_id: None,
+                organization: Some(organization.to_string()),
+            },
+        )
+        .await;
+    }
+}
+
+/// Record a finished agent run and what it consumed
+pub async fn record_agent_run(db: &SqlitePool, organization: &str, run: &RunUsage) {
+    record(db, organization, Metric::AgentRuns, 1.0).await;
+    record(db, organization, Metric::Tokens, run.tokens() as f64).await;
+    record(db, organization, Metric::CostUsd, run.cost_usd).await;
+}
+
+pub async fn record_email_sent(db: &SqlitePool, organization: &str) {
+    record(db, organization, Metric::EmailsSent, 1.0).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merges_overrides_and_computes_periods() {
+        let config: QuotaConfig = serde_json::from_str(
+            r#"{"default": {"agent_runs": 500, "tokens": 1000000},
+                "organizations": {"acme": {"tokens": 5000, "cost_usd": 20.5}}}"#,
+        )
+        .unwrap();
+        let acme = config.limits_for("acme");
+        assert_eq!(acme.get(Metric::AgentRuns), Some(500.0));
+        assert_eq!(acme.get(Metric::Tokens), Some(5000.0));
+        assert_eq!(acme.get(Metric::CostUsd), Some(20.5));
+        assert_eq!(config.limits_for("other").get(Metric::CostUsd), None);
+
+        let (start, end) = current_period(Utc.with_ymd_and_hms(2026, 12, 15, 8, 30, 0).unwrap());
+        assert_eq!(start, Utc.with_ymd_and_hms(2026, 12, 1, 0, 0, 0).unwrap());
+        assert_eq!(end, Utc.with_ymd_and_hms(2027, 1, 1, 0, 0, 0).unwrap());
+
+        let thresholds = [0.5, 0.8, 0.9];
+        assert_eq!(crossed_threshold(40.0, 85.0, 100.0, &thresholds), Some(0.8));
+        assert_eq!(crossed_threshold(80.0, 85.0, 100.0, &thresholds), None);
+        assert_eq!(crossed_threshold(10.0, 20.0, 100.0, &thresholds), None);
+    }
+}
@@ -0,0 +1,231 @@
+//! PII redaction for agent input
+//!
+//! Email bodies and meeting transcripts are scrubbed of email addresses, phone
+//! numbers, card numbers and any custom patterns before they are injected into
+//! an agent prompt. Rules come from `~/.agentic-flowstate/redaction.json` and
+//! reload with the rest of the configuration. Only counts per rule are kept
+//! for auditing, never the redacted values: in the run's context snapshot for
+//! agent runs, and through `store_audit` for the email, meeting and memory
+//! agents, which have no snapshot.
+
+use std::collections::BTreeMap;
+use std::sync::RwLock;
+
+use anyhow::{Context, Result};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use ticketing_system::{redaction_audits, CreateRedactionAuditRequest, SqlitePool};
+
+use crate::agents::AgentType;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CustomPattern {
+    pub name: String,
+    pub pattern: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct RedactionConfig {
+    pub enabled: bool,
+    pub emails: bool,
+    pub phone_numbers: bool,
+    pub credit_cards: bool,
+    pub custom: Vec<CustomPattern>,
+}
+
+impl Default for RedactionConfig {
+    fn default() -> Self {
+        Self { enabled: true, emails: true, phone_numbers: true, credit_cards: true, custom: Vec::new() }
+    }
+}
+
+/// How many matches each rule replaced
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RedactionAudit {
+    pub counts: BTreeMap<String, usize>,
+}
+
+impl RedactionAudit {
+    pub fn is_empty(&self) -> bool {
+        self.counts.is_empty()
+    }
+
+    pub fn merge(&mut self, other: RedactionAudit) {
+        for (rule, count) in other.counts {
+            *self.counts.entry(rule).or_default() += count;
+        }
+    }
+}
+
+struct Rule {
+    name: String,
+    regex: Regex,
+    /// Extra check on a match (Luhn for card numbers)
+    accept: fn(&str) -> bool,
+}
+
+struct Redactor {
+    rules: Vec<Rule>,
+}
+
+fn any_match(_: &str) -> bool {
+    true
+}
+
+/// Luhn checksum, so order numbers and IDs aren't mistaken for card numbers
+fn is_card_number(candidate: &str) -> bool {
+    let digits: Vec<u32> = candidate.chars().filter_map(|c| c.to_digit(10)).collect();
+    if !(13..=19).contains(&digits.len()) {
+        return false;
+    }
+    let sum: u32 = digits
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(i, &d)| if i % 2 == 1 { if d * 2 > 9 { d * 2 - 9 } else { d * 2 } } else { d })
+        .sum();
+    sum.is_multiple_of(10)
+}
+
+impl Redactor {
+    fn build(config: &RedactionConfig) -> Result<Self> {
+        let mut rules = Vec::new();
+        if !config.enabled {
+            return Ok(Self { rules });
+        }
+        // Cards before phones: a card number contains phone-shaped digit runs
+        if config.credit_cards {
+            rules.push(Rule {
+                name: "credit_card".to_string(),
+                regex: Regex::new(r"\b\d(?:[ -]?\d){12,18}\b")?,
+                accept: is_card_number,
+            });
+        }
+        if config.emails {
+            rules.push(Rule {
+                name: "email".to_string(),
+                regex: Regex::new(r"[A-Za-z0-9._%+-]+@[A-Za-z0-9.-]+\.[A-Za-z]{2,}")?,
+                accept: any_match,
+            });
+        }
+        if config.phone_numbers {
+            rules.push(Rule {
+                name: "phone".to_string(),
+                regex: Regex::new(r"(?:\+\d{1,3}[\s.-]?)?(?:\(\d{2,4}\)|\b\d{2,4})[\s.-]?\d{3}[\s.-]?\d{3,4}\b")?,
+                accept: any_match,
+            });
+        }
+        for custom in &config.custom {
+            rules.push(Rule {
+                name: custom.name.clone(),
+                regex: Regex::new(&custom.pattern)
+                    .with_context(|| format!("Invalid redaction pattern '{}'", custom.name))?,
+                accept: any_match,
+            });
+        }
+        Ok(Self { rules })
+    }
+
+    fn redact(&self, text: &str) -> (String, RedactionAudit) {
+        let mut audit = RedactionAudit::default();
+        let mut text = text.to_string();
+        for rule in &self.rules {
+            let placeholder = format!("[REDACTED_{}]", rule.name.to_uppercase());
+            let mut count = 0;
+            text = rule
+                .regex
+                .replace_all(&text, |caps: &regex::Captures| {
+                    let matched = &caps[0];
+                    if (rule.accept)(matched) {
+                        count += 1;
+                        placeholder.clone()
+                    } else {
+                        matched.to_string()
+                    }
+                })
+                .into_owned();
+            if count > 0 {
+                audit.counts.insert(rule.name.clone(), count);
+            }
+        }
+        (text, audit)
+    }
+}
+
+lazy_static::lazy_static! {
+    static ref REDACTOR: RwLock<Redactor> = RwLock::new(
+        Redactor::build(&RedactionConfig::default()).expect("default redaction rules compile")
+    );
+}
+
+/// Load redaction rules from the config file (defaults when absent).
+/// Returns the number of active rules; the old rules stay on error.
+pub fn load_config() -> Result<usize> {
+    let path = dirs::home_dir()
+        .context("Could not determine home directory")?
+        .join(".agentic-flowstate")
+        .join("redaction.json");
+    let config = if path.exists() {
+        let content = std::fs::read_to_string(&path).context("Failed to read redaction config")?;
+        serde_json::from_str(&content).context("Failed to parse redaction config")?
+    } else {
+        RedactionConfig::default()
+    };
+    let redactor = Redactor::build(&config)?;
+    let count = redactor.rules.len();
+    *REDACTOR.write().unwrap() = redactor;
+    Ok(count)
+}
+
+/// Redact PII from text bound for an agent prompt
+pub fn redact(text: &str) -> (String, RedactionAudit) {
+    REDACTOR.read().unwrap().redact(text)
+}
+
+/// Store what was redacted from the input of an agent run without a context
+/// snapshot. `subject` names the input, e.g. `email:42`. Stored even when
+/// nothing matched, as a record that the input was scanned; failures are
+/// logged and never fail the run.
+pub async fn store_audit(
+    db: &SqlitePool,
+    organization: Option<&str>,
+    agent_type: &AgentType,
+    subject: &str,
+    audit: &RedactionAudit,
+) {
+    let request = CreateRedactionAuditRequest {
+        organization: organization.map(str::to_string),
+        agent_type: agent_type.as_str().to_string(),
+        subject: subject.to_string(),
+        counts: audit.counts.clone(),
+    };
+    if let Err(e) = redaction_audits::create_audit(db, &request).await {
+        tracing::warn!("Failed to store redaction audit for {}: {}", subject, e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn redacts_default_and_custom_patterns() {
+        let config = RedactionConfig {
+            custom: vec![CustomPattern { name: "employee_id".to_string(), pattern: r"EMP-\d{5}".to_string() }],
+            ..Default::default()
+        };
+        let redactor = Redactor::build(&config).unwrap();
+        let (text, audit) = redactor.redact(
+            "Reach jane.doe@example.com or +1 415-555-0134. Card 4111 1111 1111 1111, order 1234567890123, badge EMP-00042.",
+        );
+        assert_eq!(
+            text,
+            "Reach [REDACTED_EMAIL] or [REDACTED_PHONE]. Card [REDACTED_CREDIT_CARD], order 1234567890123, badge [REDACTED_EMPLOYEE_ID]."
+        );
+        assert_eq!(audit.counts.len(), 4);
+
+        let disabled = Redactor::build(&RedactionConfig { enabled: false, ..Default::default() }).unwrap();
+        assert!(disabled.redact("jane@example.com").1.is_empty());
+    }
+}
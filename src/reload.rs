@@ -0,0 +1,91 @@
+//! Runtime configuration reload
+//!
+//! Triggered by SIGHUP or `POST /api/admin/reload`. Reloads agent configuration
+//! (agents.json), the CORS allowlist, email accounts, PII redaction rules and
+//! usage quotas, and checks that every agent's prompt template is readable
+//! (prompts are read from disk on each run, so edits already apply to the next
+//! run). Running agents and pipelines keep the configuration they started with.
+
+use serde::Serialize;
+
+use crate::agents::{prompts::prompt_version, AgentType, AgentsConfig};
+use crate::{cors, email_fetcher, quotas, redaction};
+
+#[derive(Debug, Default, Serialize)]
+pub struct ReloadReport {
+    pub agents: Option<usize>,
+    pub cors_origins: Option<usize>,
+    pub email_accounts: Option<usize>,
+    pub redaction_rules: Option<usize>,
+    /// Organizations with quota overrides
+    pub quota_overrides: Option<usize>,
+    pub prompts: usize,
+    /// One entry per component that failed to reload (its previous config is kept)
+    pub errors: Vec<String>,
+}
+
+/// Reload every reloadable component, keeping the old config for any that fail
+pub async fn reload_all() -> ReloadReport {
+    let mut report = ReloadReport::default();
+
+    match AgentsConfig::reload() {
+        Ok(count) => report.agents = Some(count),
+        Err(e) => report.errors.push(format!("agents: {:#}", e)),
+    }
+
+    match cors::load_allowed_origins() {
+        Ok(count) => report.cors_origins = Some(count),
+        Err(e) => report.errors.push(format!("cors: {:#}", e)),
+    }
+
+    match email_fetcher::load_email_accounts() {
+        Ok(accounts) => {
+            report.email_accounts = Some(accounts.len());
+            email_fetcher::set_email_accounts(accounts).await;
+        }
+        Err(e) => report.errors.push(format!("email accounts: {:#}", e)),
+    }
+
+    match redaction::load_config() {
+        Ok(count) => report.redaction_rules = Some(count),
+        Err(e) => report.errors.push(format!("redaction: {:#}", e)),
+    }
+
+    match quotas::load_config() {
+        Ok(count) => report.quota_overrides = Some(count),
+        Err(e) => report.errors.push(format!("quotas: {:#}", e)),
+    }
+
+    for agent_type in AgentType::all() {
+        if prompt_version(agent_type.as_str()).is_some() {
+            report.prompts += 1;
+        } else {
+            report.errors.push(format!("prompts: missing template for '{}'", agent_type.as_str()));
+        }
+    }
+
+    if report.errors.is_empty() {
+        tracing::info!("Configuration reloaded: {:?}", report);
+    } else {
+        tracing::warn!("Configuration reloaded with errors: {:?}", report);
+    }
+    report
+}
+
+/// Reload configuration whenever the process receives SIGHUP
+pub fn start_sighup_listener() {
+    #[cfg(unix)]
+    tokio::spawn(async {
+        let mut hangup = match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup()) {
+            Ok(signal) => signal,
+            Err(e) => {
+                tracing::error!("Failed to install SIGHUP handler: {}", e);
+                return;
+            }
+        };
+        while hangup.recv().await.is_some() {
+            tracing::info!("Received SIGHUP, reloading configuration...");
+            reload_all().await;
+        }
+    });
+}
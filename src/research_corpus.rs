@@ -0,0 +1,169 @@
+//! Research corpus
+//!
+//! Research agent findings are kept as structured documents (tagged by
+//! organization, ticket, epic and topic) instead of living only in the previous
+//! step's output string. Documents are deduplicated by a hash of their
+//! normalized content, and synthesis/planning agents get the epic's corpus
+//! injected into their prompt, most similar to the ticket first when semantic
+//! search is configured.
+
+use anyhow::Result;
+use std::collections::HashMap;
+use ticketing_system::{research_docs, CreateResearchDocRequest, ResearchDoc, ResearchDocQuery, SqlitePool, Ticket};
+
+use crate::agents::AgentType;
+
+/// Max characters of corpus text injected into a single prompt
+const CONTEXT_BUDGET_CHARS: usize = 40_000;
+
+/// Max documents considered for prompt injection
+const CONTEXT_MAX_DOCS: usize = 10;
+
+/// Newest documents of the epic ranked for prompt injection
+const CONTEXT_CANDIDATE_DOCS: i64 = 50;
+
+/// Agents whose completed output is captured into the corpus
+fn is_research_agent(agent_type: &str) -> bool {
+    matches!(agent_type, "exa-research" | "research-synthesis")
+}
+
+/// Agents that receive the epic's corpus as prompt context
+fn consumes_corpus(agent_type: &AgentType) -> bool {
+    matches!(
+        agent_type,
+        AgentType::ResearchSynthesis | AgentType::Planning | AgentType::TicketPlanner | AgentType::DocDrafter
+    )
+}
+
+/// Hash of content with case and whitespace differences removed, used for dedup
+pub fn content_hash(content: &str) -> String {
+    let normalized = content.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase();
+    // FNV-1a: stable across builds and processes
+    let hash = normalized.bytes().fold(0xcbf29ce484222325u64, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    });
+    format!("{:016x}", hash)
+}
+
+/// Store a document unless identical content already exists in the organization.
+/// Returns the stored (or existing) document and whether it was newly created.
+pub async fn store_doc(db: &SqlitePool, mut req: CreateResearchDocRequest) -> Result<(ResearchDoc, bool)> {
+    req.content_hash = content_hash(&req.content);
+    if let Some(existing) = research_docs::find_by_hash(db, &req.organization, &req.content_hash).await? {
+        return Ok((existing, false));
+    }
+    Ok((research_docs::create_doc(db, &req).await?, true))
+}
+
+/// Capture a completed research agent run into the corpus. No-op for other agents.
+pub async fn capture_agent_output(db: &SqlitePool, ticket_id: &str, agent_type: &str, session_id: &str, output: &str) {
+    if !is_research_agent(agent_type) || output.trim().is_empty() {
+        return;
+    }
+    let ticket = match ticketing_system::tickets::get_ticket_by_id(db, ticket_id).await {
+        Ok(Some(ticket)) => ticket,
+        Ok(None) => return,
+        Err(e) => {
+            tracing::warn!("Failed to load ticket {} for research capture: {}", ticket_id, e);
+            return;
+        }
+    };
+
+    let req = CreateResearchDocRequest {
+        organization: ticket.organization.clone(),
+        ticket_id: Some(ticket_id.to_string()),
+        epic_id: Some(ticket.epic_id.clone()),
+        topic: None,
+        title: format!("{} ({})", ticket.title, agent_type),
+        content: output.to_string(),
+        content_hash: String::new(),
+        tags: vec![agent_type.to_string()],
+        source_session_id: Some(session_id.to_string()),
+    };
+    match store_doc(db, req).await {
+        Ok((doc, true)) => tracing::info!("Stored research doc {} from session {}", doc.doc_id, session_id),
+        Ok((doc, false)) => tracing::debug!("Research output from {} duplicates doc {}", session_id, doc.doc_id),
+        Err(e) => tracing::warn!("Failed to store research output from {}: {}", session_id, e),
+    }
+}
+
+/// Order docs by similarity score, best first. Docs without a score keep
+/// their order after the scored ones.
+fn order_by_score<T>(mut docs: Vec<T>, doc_id: impl Fn(&T) -> &str, scores: &HashMap<String, f32>) -> Vec<T> {
+    // Stable sort, so unscored docs stay newest first
+    docs.sort_by(|a, b| {
+        let score = |doc: &T| scores.get(doc_id(doc)).copied().unwrap_or(f32::NEG_INFINITY);
+        score(b).total_cmp(&score(a))
+    });
+    docs
+}
+
+/// Similarity of the organization's research docs to the ticket, by doc id.
+/// Empty when semantic search isn't configured or fails.
+async fn doc_scores(db: &SqlitePool, ticket: &Ticket) -> HashMap<String, f32> {
+    let Some(client) = crate::embeddings::client() else {
+        return HashMap::new();
+    };
+    let query = format!("{}\n\n{}", ticket.title, ticket.description.clone().unwrap_or_default());
+    let sources = [crate::embeddings::SOURCE_RESEARCH_DOC];
+    match crate::embeddings::search(db, client, &query, &ticket.organization, &sources, CONTEXT_CANDIDATE_DOCS as usize).await {
+        Ok(hits) => hits.into_iter().map(|hit| (hit.source_id, hit.score)).collect(),
+        Err(e) => {
+            tracing::warn!("Failed to rank research docs for ticket {}: {}", ticket.ticket_id, e);
+            HashMap::new()
+        }
+    }
+}
+
+/// Render the epic's research corpus for an agent prompt, most similar to the
+/// ticket first (newest first without semantic search), within the context
+/// budget. `None` if the agent doesn't use the corpus or it's empty.
+pub async fn research_context(db: &SqlitePool, agent_type: &AgentType, ticket: &Ticket) -> Option<String> {
+    if !consumes_corpus(agent_type) {
+        return None;
+    }
+    let query = ResearchDocQuery {
+        organization: Some(ticket.organization.clone()),
+        epic_id: Some(ticket.epic_id.clone()),
+        limit: Some(CONTEXT_CANDIDATE_DOCS),
+        ..Default::default()
+    };
+    let docs = research_docs::search_docs(db, &query).await.ok()?;
+    let docs = order_by_score(docs, |doc: &ResearchDoc| doc.doc_id.as_str(), &doc_scores(db, ticket).await);
+
+    let mut remaining = CONTEXT_BUDGET_CHARS;
+    let mut sections = Vec::new();
+    for doc in docs.into_iter().take(CONTEXT_MAX_DOCS) {
+        let section = format!("### {} ({})\n{}", doc.title, doc.doc_id, doc.content);
+        if section.len() > remaining {
+            break;
+        }
+        remaining -= section.len();
+        sections.push(section);
+    }
+
+    if sections.is_empty() {
+        None
+    } else {
+        Some(sections.join("\n\n---\n\n"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn content_hash_ignores_case_and_whitespace() {
+        assert_eq!(content_hash("Vendor A  is\ncheaper"), content_hash("vendor a is cheaper "));
+        assert_ne!(content_hash("vendor a"), content_hash("vendor b"));
+    }
+
+    #[test]
+    fn orders_docs_by_similarity_then_recency() {
+        let newest_first = vec!["d1", "d2", "d3", "d4"];
+        let scores = HashMap::from([("d3".to_string(), 0.9), ("d2".to_string(), 0.4)]);
+        assert_eq!(order_by_score(newest_first.clone(), |d| d, &scores), vec!["d3", "d2", "d1", "d4"]);
+        assert_eq!(order_by_score(newest_first.clone(), |d| d, &HashMap::new()), newest_first);
+    }
+}
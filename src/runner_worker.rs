@@ -0,0 +1,169 @@
+//! `agentic_api runner`: execute pipeline steps for a remote API server
+//!
+//! Polls the server's queue (see `crate::runners`), runs each job with the
+//! local agent CLI and sends its events back as they come, then the outcome.
+//! Configured by env:
+//!
+//! - `FLOWSTATE_URL`: the API server, default `http://localhost:8001`
+//! - `FLOWSTATE_RUNNER_TOKEN`: the runner's token (its `token_env` on the server)
+//! - `FLOWSTATE_RUNNER_WORKDIR`: run every job here instead of the server's working directory
+//!
+//! Agents use the tools installed on this machine; the in-process MCP tools
+//! of API-key backends need the server's database and aren't available.
+
+use std::path::PathBuf;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use reqwest::StatusCode;
+use serde_json::Value;
+use tokio::sync::mpsc;
+use tracing::{error, info, warn};
+
+use crate::agents::AgentExecutor;
+use crate::runners::{RemoteJob, RemoteRun, RunOutcome};
+use crate::secrets::StepEnv;
+
+const DEFAULT_URL: &str = "http://localhost:8001";
+const POLL_WAIT_SECS: u64 = 30;
+const RETRY_DELAY: Duration = Duration::from_secs(5);
+
+/// Events are sent at least this often; an empty batch keeps the claim alive
+const FLUSH_INTERVAL: Duration = Duration::from_secs(2);
+const MAX_BATCH: usize = 200;
+
+struct Server {
+    client: reqwest::Client,
+    url: String,
+    token: String,
+}
+
+impl Server {
+    async fn claim(&self) -> Result<Option<RemoteJob>> {
+        let response = self
+            .client
+            .get(format!("{}/api/runners/queue", self.url))
+            .query(&[("wait", POLL_WAIT_SECS)])
+            .bearer_auth(&self.token)
+            .timeout(Duration::from_secs(POLL_WAIT_SECS + 30))
+            .send()
+            .await?
+            .error_for_status()?;
+        if response.status() == StatusCode::NO_CONTENT {
+            return Ok(None);
+        }
+        Ok(Some(response.json().await.context("Unreadable job from server")?))
+    }
+
+    /// Send events, and the outcome if given. `false` when the server no
+    /// longer holds the run for us.
+    async fn report(&self, run_id: &str, events: Vec<Value>, outcome: Option<&RunOutcome>) -> Result<bool> {
+        let response = self
+            .client
+            .post(format!("{}/api/runners/runs/{}/events", self.url, run_id))
+            .bearer_auth(&self.token)
+            .json(&serde_json::json!({ "events": events, "outcome": outcome }))
+            .send()
+            .await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(false);
+        }
+        response.error_for_status()?;
+        Ok(true)
+    }
+
+    /// Like `report`, retrying until the server answers: a lost outcome
+    /// would leave the step waiting until its lease runs out
+    async fn report_with_retry(&self, run_id: &str, events: Vec<Value>, outcome: Option<&RunOutcome>) -> bool {
+        loop {
+            match self.report(run_id, events.clone(), outcome).await {
+                Ok(held) => return held,
+                Err(e) => {
+                    warn!("Failed to report run {}: {:#}; retrying", run_id, e);
+                    tokio::time::sleep(RETRY_DELAY).await;
+                }
+            }
+        }
+    }
+}
+
+pub async fn run() -> Result<()> {
+    let server = Server {
+        client: reqwest::Client::new(),
+        url: std::env::var("FLOWSTATE_URL").unwrap_or_else(|_| DEFAULT_URL.to_string()).trim_end_matches('/').to_string(),
+        token: std::env::var("FLOWSTATE_RUNNER_TOKEN").context("FLOWSTATE_RUNNER_TOKEN is not set")?,
+    };
+    let workdir = std::env::var("FLOWSTATE_RUNNER_WORKDIR").ok().map(PathBuf::from);
+    info!("Runner polling {} for work", server.url);
+
+    loop {
+        match server.claim().await {
+            Ok(Some(job)) => run_job(&server, job, workdir.clone()).await,
+            Ok(None) => {}
+            Err(e) => {
+                warn!("Failed to poll {} for work: {:#}", server.url, e);
+                tokio::time::sleep(RETRY_DELAY).await;
+            }
+        }
+    }
+}
+
+async fn run_job(server: &Server, job: RemoteJob, workdir: Option<PathBuf>) {
+    let run_id = job.run_id.clone();
+    info!("Running step {} of ticket {} (run {})", job.step_id, job.ticket_id, run_id);
+
+    let executor = AgentExecutor::new(workdir.unwrap_or(job.working_dir))
+        .with_env(job.env)
+        .with_step_env(StepEnv { plain: job.step_env, secret: Default::default() })
+        .with_prompt_vars(job.prompt_vars);
+    let (tx, mut rx) = mpsc::channel(256);
+    let execution = executor.execute(job.agent_type, job.context, job.previous_output, None, None, Some(tx));
+    tokio::pin!(execution);
+
+    let mut batch: Vec<Value> = Vec::new();
+    let mut flush = tokio::time::interval(FLUSH_INTERVAL);
+    let result = loop {
+        tokio::select! {
+            result = &mut execution => break result,
+            Some(event) = rx.recv() => {
+                if let Ok(event) = serde_json::to_value(&event) {
+                    batch.push(event);
+                }
+                if batch.len() < MAX_BATCH {
+                    continue;
+                }
+            }
+            _ = flush.tick() => {}
+        }
+        match server.report(&run_id, std::mem::take(&mut batch), None).await {
+            Ok(true) => {}
+            Ok(false) => {
+                // Dropping the execution stops the agent
+                warn!("Server dropped run {}; abandoning it", run_id);
+                return;
+            }
+            Err(e) => warn!("Failed to send events of run {}: {:#}", run_id, e),
+        }
+    };
+
+    while let Ok(event) = rx.try_recv() {
+        if let Ok(event) = serde_json::to_value(&event) {
+            batch.push(event);
+        }
+    }
+    let outcome = match result {
+        Ok(run) => RunOutcome::Completed { run: RemoteRun::from_agent_run(run) },
+        Err(e) => {
+            error!("Run {} failed: {:#}", run_id, e);
+            RunOutcome::Failed { error: format!("{:#}", e) }
+        }
+    };
+    for chunk in batch.chunks(MAX_BATCH).map(<[Value]>::to_vec) {
+        server.report_with_retry(&run_id, chunk, None).await;
+    }
+    if server.report_with_retry(&run_id, Vec::new(), Some(&outcome)).await {
+        info!("Finished run {}", run_id);
+    } else {
+        warn!("Server dropped run {} before its outcome arrived", run_id);
+    }
+}
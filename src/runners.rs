@@ -0,0 +1,471 @@
+//! Remote runners: pipeline steps executed on other machines
+//!
+//! A runner is a worker process (`agentic_api runner`, see `runner_worker`)
+//! on another machine, e.g. a build box for execution agents while the API
+//! server stays small. `~/.agentic-flowstate/runners.json` names each runner,
+//! the env var holding its token and the agent types it takes:
+//!
+//! ```json
+//! {"runners": [{"name": "build-box", "token_env": "RUNNER_BUILD_BOX_TOKEN", "agent_types": ["execution"]}]}
+//! ```
+//!
+//! An auto step whose agent type a runner takes is queued instead of run
+//! here. Runners claim work with `GET /api/runners/queue` and send the run's
+//! events, then its outcome, with `POST /api/runners/runs/:run_id/events`;
+//! the step then completes or fails as if it had run locally. A step no
+//! runner claims within `claim_timeout_secs`, or whose runner goes quiet for
+//! `lease_secs`, fails (and is retried under the step's retry policy). The
+//! queue lives in memory: after a restart, runners get 404 for their runs and
+//! drop them, as interrupted local runs are.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use tokio::sync::{oneshot, Mutex, Notify};
+use tracing::{info, warn};
+
+use crate::agents::{
+    AgentExecutor, AgentRun, AgentRunStatus, AgentType, ContextSnapshot, EmailOutput, FailureReason, RunUsage,
+    TicketContext, ToolCall,
+};
+use crate::secrets::StepEnv;
+
+const DEFAULT_CLAIM_TIMEOUT_SECS: u64 = 600;
+const DEFAULT_LEASE_SECS: u64 = 120;
+
+/// How often a waiting step checks whether its job expired
+const EXPIRY_CHECK_INTERVAL: Duration = Duration::from_secs(15);
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct RunnerConfig {
+    pub name: String,
+    /// Env var holding the token the runner authenticates with
+    pub token_env: String,
+    /// Agent types the runner executes, e.g. `execution`
+    pub agent_types: Vec<String>,
+}
+
+impl RunnerConfig {
+    /// A runner without a token set can't connect, so it takes no work
+    fn token(&self) -> Option<String> {
+        std::env::var(&self.token_env).ok().filter(|t| !t.is_empty())
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct RunnersConfig {
+    pub runners: Vec<RunnerConfig>,
+    pub claim_timeout_secs: u64,
+    pub lease_secs: u64,
+}
+
+impl Default for RunnersConfig {
+    fn default() -> Self {
+        Self {
+            runners: Vec::new(),
+            claim_timeout_secs: DEFAULT_CLAIM_TIMEOUT_SECS,
+            lease_secs: DEFAULT_LEASE_SECS,
+        }
+    }
+}
+
+impl RunnersConfig {
+    /// Whether steps of this agent type are sent to a runner
+    pub fn is_remote(&self, agent_type: &str) -> bool {
+        self.runners
+            .iter()
+            .any(|r| r.agent_types.iter().any(|t| t == agent_type) && r.token().is_some())
+    }
+
+    /// The runner a bearer token belongs to
+    pub fn runner_for_token(&self, token: &str) -> Option<&RunnerConfig> {
+        self.runners
+            .iter()
+            .find(|r| r.token().is_some_and(|expected| tokens_match(&expected, token)))
+    }
+}
+
+/// Compare tokens in constant time. Both are hashed first, so neither the
+/// first differing byte nor the lengths show in the timing.
+fn tokens_match(expected: &str, given: &str) -> bool {
+    let (expected, given) = (Sha256::digest(expected.as_bytes()), Sha256::digest(given.as_bytes()));
+    expected.iter().zip(given.iter()).fold(0u8, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+/// Load the runners config; without one every step runs locally
+pub fn load_config() -> Result<RunnersConfig> {
+    let path = dirs::home_dir()
+        .context("Could not determine home directory")?
+        .join(".agentic-flowstate")
+        .join("runners.json");
+    if !path.exists() {
+        return Ok(RunnersConfig::default());
+    }
+    let content = std::fs::read_to_string(&path).context("Failed to read runners config")?;
+    serde_json::from_str(&content).context("Failed to parse runners config")
+}
+
+/// What a runner needs to execute a step, as `GET /api/runners/queue` returns it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RemoteJob {
+    /// The agent run's session id
+    pub run_id: String,
+    pub ticket_id: String,
+    pub step_id: String,
+    pub agent_type: AgentType,
+    /// Working directory on the API server; runners can map it to their own checkout
+    pub working_dir: PathBuf,
+    pub context: TicketContext,
+    pub previous_output: Option<String>,
+    /// Organization secrets and secret step env, redacted from the run's output
+    pub env: HashMap<String, String>,
+    pub step_env: HashMap<String, String>,
+    pub prompt_vars: HashMap<String, String>,
+}
+
+/// The finished run, as a runner reports it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RemoteRun {
+    pub status: AgentRunStatus,
+    pub started_at: String,
+    #[serde(default)]
+    pub completed_at: Option<String>,
+    pub input_message: String,
+    #[serde(default)]
+    pub output_summary: Option<String>,
+    #[serde(default)]
+    pub email_output: Option<EmailOutput>,
+    #[serde(default)]
+    pub failure_reason: Option<FailureReason>,
+    #[serde(default)]
+    pub context_snapshot: Option<ContextSnapshot>,
+    #[serde(default)]
+    pub usage: RunUsage,
+    #[serde(default)]
+    pub tool_calls: Vec<ToolCall>,
+}
+
+impl RemoteRun {
+    pub fn from_agent_run(run: AgentRun) -> Self {
+        Self {
+            status: run.status,
+            started_at: run.started_at,
+            completed_at: run.completed_at,
+            input_message: run.input_message,
+            output_summary: run.output_summary,
+            email_output: run.email_output,
+            failure_reason: run.failure_reason,
+            context_snapshot: run.context_snapshot,
+            usage: run.usage,
+            tool_calls: run.tool_calls,
+        }
+    }
+
+    fn into_agent_run(self, job: &RemoteJob) -> AgentRun {
+        AgentRun {
+            session_id: job.run_id.clone(),
+            ticket_id: job.context.ticket_id.clone(),
+            epic_id: job.context.epic_id.clone(),
+            slice_id: job.context.slice_id.clone(),
+            agent_type: job.agent_type.as_str().to_string(),
+            status: self.status,
+            started_at: self.started_at,
+            completed_at: self.completed_at,
+            input_message: self.input_message,
+            output_summary: self.output_summary,
+            email_output: self.email_output,
+            failure_reason: self.failure_reason,
+            context_snapshot: self.context_snapshot,
+            usage: self.usage,
+            tool_calls: self.tool_calls,
+        }
+    }
+}
+
+/// How a remote run ended: the run, or the error that stopped it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "result", rename_all = "snake_case")]
+pub enum RunOutcome {
+    Completed { run: RemoteRun },
+    Failed { error: String },
+}
+
+struct QueuedRun {
+    job: RemoteJob,
+    queued_at: Instant,
+    /// Runner that claimed the job, and when it was last heard from
+    claimed: Option<(String, Instant)>,
+    next_event_index: i32,
+    done: oneshot::Sender<RunOutcome>,
+}
+
+/// A queued or claimed run, for `GET /api/runners`
+#[derive(Debug, Clone, Serialize)]
+pub struct RunSummary {
+    pub run_id: String,
+    pub ticket_id: String,
+    pub step_id: String,
+    pub agent_type: String,
+    pub runner: Option<String>,
+    pub waiting_secs: u64,
+}
+
+pub struct RunnerHub {
+    runs: Mutex<HashMap<String, QueuedRun>>,
+    /// When each runner last polled the queue
+    last_polled: Mutex<HashMap<String, chrono::DateTime<chrono::Utc>>>,
+    queued: Notify,
+}
+
+lazy_static::lazy_static! {
+    pub static ref RUNNERS: RunnerHub = RunnerHub::new();
+}
+
+impl RunnerHub {
+    fn new() -> Self {
+        Self { runs: Mutex::new(HashMap::new()), last_polled: Mutex::new(HashMap::new()), queued: Notify::new() }
+    }
+
+    async fn enqueue(&self, job: RemoteJob) -> oneshot::Receiver<RunOutcome> {
+        let (done, rx) = oneshot::channel();
+        let run_id = job.run_id.clone();
+        let queued = QueuedRun { job, queued_at: Instant::now(), claimed: None, next_event_index: 0, done };
+        self.runs.lock().await.insert(run_id, queued);
+        self.queued.notify_waiters();
+        rx
+    }
+
+    /// Claim the oldest unclaimed job the runner takes, waiting up to `wait` for one
+    pub async fn claim(&self, runner: &RunnerConfig, wait: Duration) -> Option<RemoteJob> {
+        self.last_polled.lock().await.insert(runner.name.clone(), chrono::Utc::now());
+        let deadline = tokio::time::Instant::now() + wait;
+        loop {
+            // Registered before looking, so a job queued meanwhile still wakes us
+            let queued = self.queued.notified();
+            {
+                let mut runs = self.runs.lock().await;
+                let next = runs
+                    .values_mut()
+                    .filter(|r| r.claimed.is_none() && runner.agent_types.iter().any(|t| t == r.job.agent_type.as_str()))
+                    .min_by_key(|r| r.queued_at);
+                if let Some(run) = next {
+                    run.claimed = Some((runner.name.clone(), Instant::now()));
+                    return Some(run.job.clone());
+                }
+            }
+            if tokio::time::timeout_at(deadline, queued).await.is_err() {
+                return None;
+            }
+        }
+    }
+
+    /// Take events from the runner holding `run_id`, returning the index of
+    /// the first; an outcome finishes the run. `None` when the run is
+    /// unknown, finished, expired or claimed by another runner.
+    pub async fn report(
+        &self,
+        run_id: &str,
+        runner: &str,
+        event_count: usize,
+        outcome: Option<RunOutcome>,
+    ) -> Option<i32> {
+        let mut runs = self.runs.lock().await;
+        let run = runs.get_mut(run_id)?;
+        match &mut run.claimed {
+            Some((claimed_by, last_seen)) if claimed_by == runner => *last_seen = Instant::now(),
+            _ => return None,
+        }
+        let first_index = run.next_event_index;
+        run.next_event_index += event_count as i32;
+        if let Some(outcome) = outcome {
+            if let Some(run) = runs.remove(run_id) {
+                let _ = run.done.send(outcome);
+            }
+        }
+        Some(first_index)
+    }
+
+    /// Drop the run if nobody claimed it in time or its runner went quiet,
+    /// returning why
+    async fn expire(&self, run_id: &str, config: &RunnersConfig) -> Option<String> {
+        let mut runs = self.runs.lock().await;
+        let run = runs.get(run_id)?;
+        let reason = match &run.claimed {
+            None if run.queued_at.elapsed().as_secs() >= config.claim_timeout_secs => {
+                format!("No runner claimed the step within {}s", config.claim_timeout_secs)
+            }
+            Some((runner, last_seen)) if last_seen.elapsed().as_secs() >= config.lease_secs => {
+                format!("Runner {} stopped reporting for {}s", runner, config.lease_secs)
+            }
+            _ => return None,
+        };
+        runs.remove(run_id);
+        Some(reason)
+    }
+
+    pub async fn runs(&self) -> Vec<RunSummary> {
+        let runs = self.runs.lock().await;
+        let mut summaries: Vec<(Instant, RunSummary)> = runs
+            .values()
+            .map(|r| {
+                (
+                    r.queued_at,
+                    RunSummary {
+                        run_id: r.job.run_id.clone(),
+                        ticket_id: r.job.ticket_id.clone(),
+                        step_id: r.job.step_id.clone(),
+                        agent_type: r.job.agent_type.as_str().to_string(),
+                        runner: r.claimed.as_ref().map(|(name, _)| name.clone()),
+                        waiting_secs: r.queued_at.elapsed().as_secs(),
+                    },
+                )
+            })
+            .collect();
+        summaries.sort_by_key(|(queued_at, _)| *queued_at);
+        summaries.into_iter().map(|(_, s)| s).collect()
+    }
+
+    pub async fn last_polled(&self, runner: &str) -> Option<chrono::DateTime<chrono::Utc>> {
+        self.last_polled.lock().await.get(runner).copied()
+    }
+}
+
+/// Queue a job for a runner and wait for its outcome
+async fn execute_remote(job: RemoteJob, config: &RunnersConfig) -> Result<AgentRun> {
+    let run_id = job.run_id.clone();
+    info!("Queued step {} of ticket {} for a {} runner", job.step_id, job.ticket_id, job.agent_type.as_str());
+    let mut done = RUNNERS.enqueue(job.clone()).await;
+    loop {
+        match tokio::time::timeout(EXPIRY_CHECK_INTERVAL, &mut done).await {
+            Ok(Ok(RunOutcome::Completed { run })) => return Ok(run.into_agent_run(&job)),
+            Ok(Ok(RunOutcome::Failed { error })) => anyhow::bail!(error),
+            Ok(Err(_)) => anyhow::bail!("Remote run {} was dropped", run_id),
+            Err(_) => {
+                if let Some(reason) = RUNNERS.expire(&run_id, config).await {
+                    warn!("Remote run {}: {}", run_id, reason);
+                    anyhow::bail!(reason);
+                }
+            }
+        }
+    }
+}
+
+/// Where a pipeline step's agent runs: here, or on a runner that takes its agent type
+pub enum StepRunner {
+    Local(AgentExecutor),
+    Remote {
+        run_id: String,
+        step_id: String,
+        working_dir: PathBuf,
+        env: HashMap<String, String>,
+        step_env: HashMap<String, String>,
+        prompt_vars: HashMap<String, String>,
+        config: RunnersConfig,
+    },
+}
+
+impl StepRunner {
+    pub fn for_step(
+        run_id: &str,
+        step_id: &str,
+        agent_type: &AgentType,
+        working_dir: PathBuf,
+        mut env: HashMap<String, String>,
+        step_env: StepEnv,
+        prompt_vars: HashMap<String, String>,
+    ) -> Self {
+        let config = load_config().unwrap_or_else(|e| {
+            warn!("Failed to load runners config, running steps locally: {:#}", e);
+            RunnersConfig::default()
+        });
+        if !config.is_remote(agent_type.as_str()) {
+            return StepRunner::Local(
+                AgentExecutor::new(working_dir)
+                    .with_env(env)
+                    .with_step_env(step_env)
+                    .with_prompt_vars(prompt_vars),
+            );
+        }
+        env.extend(step_env.secret);
+        StepRunner::Remote {
+            run_id: run_id.to_string(),
+            step_id: step_id.to_string(),
+            working_dir,
+            env,
+            step_env: step_env.plain,
+            prompt_vars,
+            config,
+        }
+    }
+
+    pub async fn execute(
+        &self,
+        agent_type: AgentType,
+        context: TicketContext,
+        previous_output: Option<String>,
+    ) -> Result<AgentRun> {
+        match self {
+            StepRunner::Local(executor) => executor.execute(agent_type, context, previous_output, None, None, None).await,
+            StepRunner::Remote { run_id, step_id, working_dir, env, step_env, prompt_vars, config } => {
+                let job = RemoteJob {
+                    run_id: run_id.clone(),
+                    ticket_id: context.ticket_id.clone(),
+                    step_id: step_id.clone(),
+                    agent_type,
+                    working_dir: working_dir.clone(),
+                    context,
+                    previous_output,
+                    env: env.clone(),
+                    step_env: step_env.clone(),
+                    prompt_vars: prompt_vars.clone(),
+                };
+                execute_remote(job, config).await
+            }
+        }
+    }
+}
+
+/// The events a runner sends, with the type each is stored under
+pub fn event_types(events: &[Value]) -> Result<Vec<&str>, usize> {
+    events
+        .iter()
+        .enumerate()
+        .map(|(idx, event)| event.get("type").and_then(Value::as_str).filter(|t| !t.is_empty()).ok_or(idx))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn routes_agent_types_to_runners_with_tokens() {
+        std::env::set_var("RUNNERS_TEST_BUILD_BOX_TOKEN", "s3cret");
+        let config: RunnersConfig = serde_json::from_value(json!({
+            "runners": [
+                { "name": "build-box", "token_env": "RUNNERS_TEST_BUILD_BOX_TOKEN", "agent_types": ["execution"] },
+                { "name": "offline", "token_env": "RUNNERS_TEST_UNSET_TOKEN", "agent_types": ["planning"] },
+            ],
+        }))
+        .unwrap();
+        assert_eq!(config.lease_secs, DEFAULT_LEASE_SECS);
+        assert!(config.is_remote("execution"));
+        assert!(!config.is_remote("planning"));
+        assert!(!config.is_remote("email"));
+        assert_eq!(config.runner_for_token("s3cret").map(|r| r.name.as_str()), Some("build-box"));
+        assert!(config.runner_for_token("").is_none());
+        assert!(config.runner_for_token("guess").is_none());
+        assert!(config.runner_for_token("s3cre").is_none());
+
+        let events = [json!({ "type": "text", "content": "hi" }), json!({ "content": "no type" })];
+        assert_eq!(event_types(&events[..1]), Ok(vec!["text"]));
+        assert_eq!(event_types(&events), Err(1));
+    }
+}
@@ -0,0 +1,207 @@
+//! Saved ticket list views
+//!
+//! A view is a named filter, sort and column layout for the ticket list,
+//! owned by one user in one organization. `GET /api/views/:id/tickets` runs
+//! the view on the server so a dashboard loads just the tickets it shows.
+//! Filters combine with AND: a ticket must have one of `statuses`, at least
+//! one of `labels`, and the `assignee` (`unassigned` for none), each only when
+//! set. Sort keys apply in order; tickets missing a sort field go last.
+
+use std::cmp::Ordering;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::deadlines::DueDateFilter;
+
+/// Assignee filter value matching tickets with no assignee
+pub const UNASSIGNED: &str = "unassigned";
+
+/// Fields a view can sort by
+pub const SORT_FIELDS: &[&str] = &["created_at", "updated_at", "due_date", "priority", "status", "title", "ticket_id"];
+
+const MAX_VIEW_NAME_CHARS: usize = 100;
+const MAX_COLUMNS: usize = 30;
+const MAX_SORT_KEYS: usize = 3;
+
+/// Ranks for the usual priorities, highest first; any other value sorts after them
+pub(crate) const PRIORITY_ORDER: &[&str] = &["urgent", "high", "medium", "low"];
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortDirection {
+    #[default]
+    Asc,
+    Desc,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SortKey {
+    pub field: String,
+    #[serde(default)]
+    pub direction: SortDirection,
+}
+
+/// What a view shows; stored as JSON on the view
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ViewDefinition {
+    #[serde(default)]
+    pub statuses: Vec<String>,
+    #[serde(default)]
+    pub labels: Vec<String>,
+    pub assignee: Option<String>,
+    #[serde(default)]
+    pub sort: Vec<SortKey>,
+    /// Ticket fields the list shows, in order; only the client reads these
+    #[serde(default)]
+    pub columns: Vec<String>,
+}
+
+/// Problems with a view before it is saved
+pub fn validate_view(name: &str, definition: &ViewDefinition) -> Vec<String> {
+    let mut problems = Vec::new();
+    let name_len = name.trim().chars().count();
+    if name_len == 0 || name_len > MAX_VIEW_NAME_CHARS {
+        problems.push(format!("name must be 1 to {} characters", MAX_VIEW_NAME_CHARS));
+    }
+    if definition.sort.len() > MAX_SORT_KEYS {
+        problems.push(format!("sort can have at most {} keys", MAX_SORT_KEYS));
+    }
+    for key in &definition.sort {
+        if !SORT_FIELDS.contains(&key.field.as_str()) {
+            problems.push(format!("sort: unknown field '{}', expected one of: {}", key.field, SORT_FIELDS.join(", ")));
+        }
+    }
+    if definition.columns.len() > MAX_COLUMNS {
+        problems.push(format!("columns can have at most {} entries", MAX_COLUMNS));
+    }
+    if definition.columns.iter().any(|c| c.trim().is_empty()) {
+        problems.push("columns must not be empty".to_string());
+    }
+    if definition.statuses.iter().chain(&definition.labels).any(|v| v.trim().is_empty()) {
+        problems.push("status and label filters must not be empty".to_string());
+    }
+    problems
+}
+
+fn field_str<'a>(ticket: &'a Value, field: &str) -> Option<&'a str> {
+    ticket.get(field).and_then(|v| v.as_str()).filter(|s| !s.trim().is_empty())
+}
+
+fn matches(definition: &ViewDefinition, ticket: &Value) -> bool {
+    if !definition.statuses.is_empty() {
+        let status = field_str(ticket, "status").unwrap_or_default();
+        if !definition.statuses.iter().any(|s| s == status) {
+            return false;
+        }
+    }
+    if !definition.labels.is_empty() {
+        let labels = ticket.get("labels").and_then(|l| l.as_array());
+        let has_label = labels.is_some_and(|labels| {
+            labels.iter().filter_map(|l| l.as_str()).any(|l| definition.labels.iter().any(|want| want.eq_ignore_ascii_case(l)))
+        });
+        if !has_label {
+            return false;
+        }
+    }
+    match definition.assignee.as_deref() {
+        None => true,
+        Some(UNASSIGNED) => field_str(ticket, "assignee").is_none(),
+        Some(assignee) => field_str(ticket, "assignee").is_some_and(|a| a.eq_ignore_ascii_case(assignee)),
+    }
+}
+
+/// Compare two present values of a sort field
+fn compare_field(field: &str, a: &Value, b: &Value) -> Ordering {
+    if field == "priority" {
+        let rank = |v: &Value| {
+            let p = v.as_str().unwrap_or_default().to_lowercase();
+            (PRIORITY_ORDER.iter().position(|o| *o == p).unwrap_or(PRIORITY_ORDER.len()), p)
+        };
+        return rank(a).cmp(&rank(b));
+    }
+    match (a.as_f64(), b.as_f64()) {
+        (Some(a), Some(b)) => a.partial_cmp(&b).unwrap_or(Ordering::Equal),
+        _ => a.as_str().unwrap_or_default().cmp(b.as_str().unwrap_or_default()),
+    }
+}
+
+/// A sort field's value, unless it's missing, null or empty
+fn sort_value<'a>(ticket: &'a Value, field: &str) -> Option<&'a Value> {
+    ticket.get(field).filter(|v| !v.is_null() && v.as_str() != Some(""))
+}
+
+fn compare(sort: &[SortKey], a: &Value, b: &Value) -> Ordering {
+    for key in sort {
+        let ordering = match (sort_value(a, &key.field), sort_value(b, &key.field)) {
+            (Some(a), Some(b)) => match key.direction {
+                SortDirection::Asc => compare_field(&key.field, a, b),
+                SortDirection::Desc => compare_field(&key.field, b, a),
+            },
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => Ordering::Equal,
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+    Ordering::Equal
+}
+
+/// Run a view over an organization's tickets, with an extra due date filter
+pub fn apply_view(definition: &ViewDefinition, due: &DueDateFilter, tickets: Vec<Value>) -> Vec<Value> {
+    let mut response = Value::Array(tickets.into_iter().filter(|t| matches(definition, t)).collect());
+    due.apply(&mut response, chrono::Utc::now().date_naive());
+    let Value::Array(mut tickets) = response else {
+        return Vec::new();
+    };
+    tickets.sort_by(|a, b| compare(&definition.sort, a, b));
+    tickets
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn filters_sorts_and_validates_views() {
+        let tickets = vec![
+            json!({"ticket_id": "T-1", "status": "todo", "labels": ["Bug"], "assignee": "ana", "priority": "low"}),
+            json!({"ticket_id": "T-2", "status": "in_progress", "labels": ["bug", "ui"], "assignee": null, "priority": "urgent"}),
+            json!({"ticket_id": "T-3", "status": "todo", "labels": [], "assignee": "ben", "priority": "high"}),
+            json!({"ticket_id": "T-4", "status": "todo", "labels": ["bug"], "assignee": "Ana"}),
+            json!({"ticket_id": "T-5", "status": "completed", "labels": ["bug"], "assignee": "ana", "priority": "high"}),
+        ];
+        let ids = |tickets: &[Value]| tickets.iter().map(|t| t["ticket_id"].as_str().unwrap().to_string()).collect::<Vec<_>>();
+
+        let view = ViewDefinition {
+            statuses: vec!["todo".to_string(), "in_progress".to_string()],
+            labels: vec!["bug".to_string()],
+            sort: vec![SortKey { field: "priority".to_string(), direction: SortDirection::Asc }],
+            ..Default::default()
+        };
+        let due = DueDateFilter::default();
+        assert_eq!(ids(&apply_view(&view, &due, tickets.clone())), vec!["T-2", "T-1", "T-4"]);
+
+        let view = ViewDefinition { assignee: Some(UNASSIGNED.to_string()), ..Default::default() };
+        assert_eq!(ids(&apply_view(&view, &due, tickets.clone())), vec!["T-2"]);
+
+        let view = ViewDefinition {
+            assignee: Some("ana".to_string()),
+            sort: vec![SortKey { field: "ticket_id".to_string(), direction: SortDirection::Desc }],
+            ..Default::default()
+        };
+        assert_eq!(ids(&apply_view(&view, &due, tickets)), vec!["T-5", "T-4", "T-1"]);
+
+        let view = ViewDefinition {
+            sort: vec![SortKey { field: "mood".to_string(), direction: SortDirection::Asc }],
+            columns: vec![" ".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(validate_view("Mine", &view).len(), 2);
+        assert_eq!(validate_view(" ", &ViewDefinition::default()).len(), 1);
+        assert!(validate_view("Mine", &ViewDefinition::default()).is_empty());
+    }
+}
@@ -0,0 +1,222 @@
+//! Per-organization secrets for agent runtimes
+//!
+//! Values are encrypted with AES-256-GCM before they reach the database, using
+//! the base64 key in `SECRETS_ENCRYPTION_KEY`. Plaintext only exists in memory
+//! while building an agent's environment; read APIs return metadata only.
+
+use std::collections::HashMap;
+
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use anyhow::{anyhow, Context, Result};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use regex::Regex;
+use ticketing_system::{secrets, SqlitePool, Ticket};
+
+use crate::agents::AgentType;
+
+const KEY_ENV: &str = "SECRETS_ENCRYPTION_KEY";
+const NONCE_LEN: usize = 12;
+
+/// Environment variables agents depend on that a secret must not shadow
+const RESERVED_NAMES: &[&str] = &["PATH", "HOME", "USER", "SHELL", "ANTHROPIC_API_KEY"];
+
+lazy_static::lazy_static! {
+    /// A step env value that is exactly `{{secret.NAME}}`
+    static ref SECRET_REF: Regex = Regex::new(r"^\{\{\s*secret\.([A-Za-z0-9_]+)\s*\}\}$").unwrap();
+}
+
+fn cipher() -> Result<Aes256Gcm> {
+    let encoded = std::env::var(KEY_ENV).with_context(|| format!("{} is not set", KEY_ENV))?;
+    let key = BASE64
+        .decode(encoded.trim())
+        .with_context(|| format!("{} is not valid base64", KEY_ENV))?;
+    if key.len() != 32 {
+        return Err(anyhow!("{} must decode to 32 bytes", KEY_ENV));
+    }
+    Ok(Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key)))
+}
+
+/// Encrypt a secret value; output is base64(nonce || ciphertext)
+pub fn encrypt(plaintext: &str) -> Result<String> {
+    let cipher = cipher()?;
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let ciphertext = cipher
+        .encrypt(&nonce, plaintext.as_bytes())
+        .map_err(|_| anyhow!("Failed to encrypt secret"))?;
+
+    let mut out = nonce.to_vec();
+    out.extend_from_slice(&ciphertext);
+    Ok(BASE64.encode(out))
+}
+
+/// Decrypt a value produced by `encrypt`
+pub fn decrypt(encoded: &str) -> Result<String> {
+    let cipher = cipher()?;
+    let bytes = BASE64.decode(encoded).context("Stored secret is not valid base64")?;
+    if bytes.len() <= NONCE_LEN {
+        return Err(anyhow!("Stored secret is truncated"));
+    }
+    let (nonce, ciphertext) = bytes.split_at(NONCE_LEN);
+    let plaintext = cipher
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| anyhow!("Failed to decrypt secret (wrong key?)"))?;
+    String::from_utf8(plaintext).context("Decrypted secret is not UTF-8")
+}
+
+/// Check a secret name is usable as an environment variable
+pub fn validate_name(name: &str) -> Result<(), String> {
+    let valid = !name.is_empty()
+        && !name.starts_with(|c: char| c.is_ascii_digit())
+        && name.chars().all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_');
+    if !valid {
+        return Err("Secret names must be UPPER_SNAKE_CASE environment variable names".to_string());
+    }
+    if RESERVED_NAMES.contains(&name) {
+        return Err(format!("{} is reserved and cannot be set as a secret", name));
+    }
+    Ok(())
+}
+
+/// Environment variables for an agent run: the organization's secrets that are
+/// scoped to `agent_type` (or to all agent types), and its own Anthropic key
+/// if it has one. Secrets that fail to decrypt are skipped and logged by name only.
+pub async fn agent_env(db: &SqlitePool, organization: &str, agent_type: &AgentType) -> HashMap<String, String> {
+    let mut env = crate::llm_keys::anthropic_env(db, organization).await;
+    let stored = match secrets::list_secrets(db, organization).await {
+        Ok(stored) => stored,
+        Err(e) => {
+            tracing::warn!("Failed to load secrets for organization {}: {}", organization, e);
+            return env;
+        }
+    };
+
+    for secret in stored {
+        if !scoped_to(&secret.agent_types, agent_type.as_str()) {
+            continue;
+        }
+        match decrypt(&secret.encrypted_value) {
+            Ok(value) => {
+                env.insert(secret.name, value);
+            }
+            Err(e) => tracing::warn!("Skipping secret {}: {}", secret.name, e),
+        }
+    }
+    env
+}
+
+/// Whether a secret scoped to `agent_types` may be given to an agent type;
+/// an empty scope means every agent type
+fn scoped_to(agent_types: &[String], agent_type: &str) -> bool {
+    agent_types.is_empty() || agent_types.iter().any(|t| t == agent_type)
+}
+
+/// Environment declared on a pipeline step, resolved for one run. Secret
+/// references are kept apart so only their values are redacted from output.
+#[derive(Debug, Default)]
+pub struct StepEnv {
+    pub plain: HashMap<String, String>,
+    pub secret: HashMap<String, String>,
+}
+
+/// Name of the secret a step env value references, if it is a reference
+fn secret_reference(value: &str) -> Option<&str> {
+    SECRET_REF.captures(value).and_then(|c| c.get(1)).map(|m| m.as_str())
+}
+
+/// Problems with a step's declared env, for template validation. Values are
+/// either literals or exactly `{{secret.NAME}}`.
+pub fn validate_step_env(env: &HashMap<String, String>) -> Vec<String> {
+    let mut problems = Vec::new();
+    for (name, value) in env {
+        if let Err(e) = validate_name(name) {
+            problems.push(format!("env '{}': {}", name, e));
+        }
+        if value.contains("{{") && secret_reference(value).is_none() {
+            problems.push(format!("env '{}': secret references must be the whole value, as {{{{secret.NAME}}}}", name));
+        }
+    }
+    problems
+}
+
+/// Resolve the env a pipeline step declares. Secret references may name the
+/// ticket organization's secrets scoped to the step's agent type, as
+/// `agent_env` does; a missing, out-of-scope or undecryptable one fails the
+/// step rather than running it without the variable.
+pub async fn step_env(db: &SqlitePool, ticket: &Ticket, step_id: &str) -> Result<StepEnv, String> {
+    let Some(step) = ticket
+        .pipeline
+        .as_ref()
+        .and_then(|p| p.steps.iter().find(|s| s.step_id == step_id))
+    else {
+        return Ok(StepEnv::default());
+    };
+    let declared = step.env.clone().unwrap_or_default();
+    if declared.is_empty() {
+        return Ok(StepEnv::default());
+    }
+
+    let mut env = StepEnv::default();
+    let mut stored = None;
+    for (name, value) in declared {
+        let Some(secret_name) = secret_reference(&value) else {
+            env.plain.insert(name, value);
+            continue;
+        };
+        if stored.is_none() {
+            stored = Some(
+                secrets::list_secrets(db, &ticket.organization)
+                    .await
+                    .map_err(|e| format!("Failed to load secrets for step env: {}", e))?,
+            );
+        }
+        let secret = stored
+            .iter()
+            .flatten()
+            .find(|s| s.name == secret_name)
+            .ok_or_else(|| format!("Step env {} references unknown secret {}", name, secret_name))?;
+        if !scoped_to(&secret.agent_types, &step.agent_type) {
+            return Err(format!(
+                "Step env {} references secret {}, which is not available to {} agents",
+                name, secret_name, step.agent_type
+            ));
+        }
+        let plaintext = decrypt(&secret.encrypted_value)
+            .map_err(|e| format!("Step env {} could not use secret {}: {}", name, secret_name, e))?;
+        env.secret.insert(name, plaintext);
+    }
+    Ok(env)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validates_step_env() {
+        assert_eq!(secret_reference("{{ secret.STAGING_TOKEN }}"), Some("STAGING_TOKEN"));
+        assert_eq!(secret_reference("Bearer {{secret.STAGING_TOKEN}}"), None);
+
+        let env: HashMap<String, String> = [
+            ("API_BASE_URL", "https://staging.example.com"),
+            ("API_TOKEN", "{{secret.STAGING_TOKEN}}"),
+            ("AUTH_HEADER", "Bearer {{secret.STAGING_TOKEN}}"),
+            ("feature_flag", "on"),
+            ("PATH", "/tmp"),
+        ]
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+        let mut problems = validate_step_env(&env);
+        problems.sort();
+        assert_eq!(problems.len(), 3);
+        assert!(problems[0].starts_with("env 'AUTH_HEADER'"));
+        assert!(problems[1].starts_with("env 'PATH'"));
+        assert!(problems[2].starts_with("env 'feature_flag'"));
+
+        let deploy_only = vec!["deploy".to_string()];
+        assert!(scoped_to(&deploy_only, "deploy"));
+        assert!(!scoped_to(&deploy_only, "research"));
+        assert!(scoped_to(&[], "research"));
+    }
+}
@@ -61,6 +61,8 @@ fn get_default_templates() -> Vec<CreatePipelineTemplateRequest> {
                     execution_type: ExecutionType::Auto,
                     name: Some("Research".to_string()),
                     default_inputs: None,
+                    condition: None,
+                    retry: None,
                 },
                 PipelineTemplateStep {
                     step_id: "plan".to_string(),
@@ -68,6 +70,8 @@ fn get_default_templates() -> Vec<CreatePipelineTemplateRequest> {
                     execution_type: ExecutionType::Manual,
                     name: Some("Review implementation plan".to_string()),
                     default_inputs: None,
+                    condition: None,
+                    retry: None,
                 },
                 PipelineTemplateStep {
                     step_id: "execute".to_string(),
@@ -75,6 +79,8 @@ fn get_default_templates() -> Vec<CreatePipelineTemplateRequest> {
                     execution_type: ExecutionType::Auto,
                     name: Some("Implement changes".to_string()),
                     default_inputs: None,
+                    condition: None,
+                    retry: None,
                 },
                 PipelineTemplateStep {
                     step_id: "evaluate".to_string(),
@@ -82,8 +88,13 @@ fn get_default_templates() -> Vec<CreatePipelineTemplateRequest> {
                     execution_type: ExecutionType::Auto,
                     name: Some("Verify implementation".to_string()),
                     default_inputs: None,
+                    condition: None,
+                    retry: None,
                 },
             ],
+            status_transitions: None,
+            shadow_of: None,
+            shared: false,
         },
         // Human task (no automation, manual completion)
         CreatePipelineTemplateRequest {
@@ -102,8 +113,13 @@ fn get_default_templates() -> Vec<CreatePipelineTemplateRequest> {
                     execution_type: ExecutionType::Manual,
                     name: Some("Complete task".to_string()),
                     default_inputs: None,
+                    condition: None,
+                    retry: None,
                 },
             ],
+            status_transitions: None,
+            shadow_of: None,
+            shared: false,
         },
         // Deep research with ticket creation
         CreatePipelineTemplateRequest {
@@ -122,6 +138,8 @@ fn get_default_templates() -> Vec<CreatePipelineTemplateRequest> {
                     execution_type: ExecutionType::Auto,
                     name: Some("Research".to_string()),
                     default_inputs: None,
+                    condition: None,
+                    retry: None,
                 },
                 PipelineTemplateStep {
                     step_id: "plan-tickets".to_string(),
@@ -129,6 +147,8 @@ fn get_default_templates() -> Vec<CreatePipelineTemplateRequest> {
                     execution_type: ExecutionType::Auto,
                     name: Some("Plan follow-up tickets".to_string()),
                     default_inputs: None,
+                    condition: None,
+                    retry: None,
                 },
                 PipelineTemplateStep {
                     step_id: "create-tickets".to_string(),
@@ -136,8 +156,13 @@ fn get_default_templates() -> Vec<CreatePipelineTemplateRequest> {
                     execution_type: ExecutionType::Manual,
                     name: Some("Create follow-up tickets".to_string()),
                     default_inputs: None,
+                    condition: None,
+                    retry: None,
                 },
             ],
+            status_transitions: None,
+            shadow_of: None,
+            shared: false,
         },
         // Research only: single research step, no follow-up tickets
         CreatePipelineTemplateRequest {
@@ -156,8 +181,13 @@ fn get_default_templates() -> Vec<CreatePipelineTemplateRequest> {
                     execution_type: ExecutionType::Auto,
                     name: Some("Research".to_string()),
                     default_inputs: None,
+                    condition: None,
+                    retry: None,
                 },
             ],
+            status_transitions: None,
+            shadow_of: None,
+            shared: false,
         },
         // Document drafting: research → draft (drafter does its own structured extraction)
         CreatePipelineTemplateRequest {
@@ -177,6 +207,8 @@ fn get_default_templates() -> Vec<CreatePipelineTemplateRequest> {
                     execution_type: ExecutionType::Auto,
                     name: Some("Research".to_string()),
                     default_inputs: None,
+                    condition: None,
+                    retry: None,
                 },
                 PipelineTemplateStep {
                     step_id: "draft".to_string(),
@@ -184,8 +216,13 @@ fn get_default_templates() -> Vec<CreatePipelineTemplateRequest> {
                     execution_type: ExecutionType::Manual,
                     name: Some("Draft document".to_string()),
                     default_inputs: None,
+                    condition: None,
+                    retry: None,
                 },
             ],
+            status_transitions: None,
+            shadow_of: None,
+            shared: false,
         },
     ]
 }
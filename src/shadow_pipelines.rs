@@ -0,0 +1,255 @@
+//! Shadow pipelines - dark-launching new templates
+//!
+//! A template with `shadow_of` set is a shadow of another template: whenever a
+//! ticket starts a pipeline attached from that template, the shadow runs
+//! alongside it in the background. Its steps are stored on a shadow run (see
+//! `ticketing_system::shadow_runs`) rather than on the ticket, so they never move
+//! the ticket's status, notify anyone or show up among the ticket's agent runs.
+//! Comparing shadow runs with the real pipelines lets a new workflow be judged
+//! on real tickets before the template is switched over. A template can only be
+//! shadowed by one of the same organization, and shadows only run on that
+//! organization's tickets.
+//!
+//! A shadow must not change anything the real pipeline works on. Human and
+//! send-email steps are skipped, as are agent steps that can write files, run
+//! commands or modify tickets (see `has_side_effects`) or that need step
+//! environment variables, and nothing is published. Manual agent steps run
+//! without approval, since nothing they produce takes effect.
+
+use anyhow::Result;
+use sqlx::SqlitePool;
+use tracing::{error, info, warn};
+
+use ticketing_system::{
+    models::{Pipeline, PipelineStep, Ticket},
+    pipelines, shadow_runs, tickets, CreateShadowRunRequest, ShadowRun,
+};
+
+use crate::agents::{resolve_working_dir, AgentExecutor, AgentType, TicketContext};
+use crate::pipeline_automation::{fan_in_context, render_step_inputs, resolve_step_inputs, skip_unmet_steps, step_input_vars};
+
+pub const SHADOW_RUNNING: &str = "running";
+pub const SHADOW_COMPLETED: &str = "completed";
+pub const SHADOW_FAILED: &str = "failed";
+/// Stopped before the end by maintenance mode or the organization's quota
+pub const SHADOW_HALTED: &str = "halted";
+
+/// Tools that change files or run commands in the working directory
+const WRITE_TOOLS: &[&str] = &["Write", "Edit", "MultiEdit", "NotebookEdit", "Bash"];
+
+/// MCP tool name prefixes that change tickets or other records
+const MUTATING_MCP_PREFIXES: &[&str] = &["create_", "update_", "delete_", "add_", "toggle_", "remove_", "set_"];
+
+/// Whether an agent with these tools could change what the real pipeline works
+/// on. MCP wildcards count as mutating, since they include every tool.
+fn has_side_effects(tools: &[&str]) -> bool {
+    tools.iter().any(|tool| {
+        if WRITE_TOOLS.contains(tool) {
+            return true;
+        }
+        match tool.strip_prefix("mcp__") {
+            Some(mcp) => {
+                let name = mcp.rsplit("__").next().unwrap_or(mcp);
+                name.ends_with('*') || MUTATING_MCP_PREFIXES.iter().any(|p| name.starts_with(p))
+            }
+            None => false,
+        }
+    })
+}
+
+/// Agent to run a shadow step with, or why the step is skipped
+fn shadow_agent(step: &PipelineStep) -> std::result::Result<AgentType, String> {
+    if step.agent_type == "human" || crate::email_steps::is_send_step(&step.agent_type) {
+        return Err("Human steps are not run in shadow pipelines".to_string());
+    }
+    if step.env.as_ref().is_some_and(|env| !env.is_empty()) {
+        return Err("Steps with environment variables are not run in shadow pipelines".to_string());
+    }
+    let agent_type: AgentType = serde_json::from_str(&format!("\"{}\"", step.agent_type))
+        .map_err(|_| format!("Unknown agent type: {}", step.agent_type))?;
+    if has_side_effects(&agent_type.allowed_tools()) {
+        return Err(format!("The {} agent can change the workspace or tickets", step.agent_type));
+    }
+    Ok(agent_type)
+}
+
+/// Start a shadow run of every template shadowing the one the ticket's pipeline
+/// was attached from. Called once the real pipeline has started; failures are
+/// logged and never affect it.
+pub async fn start_shadow_runs(pool: &SqlitePool, ticket_id: &str) {
+    let ticket = match tickets::get_ticket_by_id(pool, ticket_id).await {
+        Ok(Some(ticket)) => ticket,
+        Ok(None) => return,
+        Err(e) => {
+            error!("Failed to load ticket {} for shadow runs: {:?}", ticket_id, e);
+            return;
+        }
+    };
+    let Some(template_id) = ticket.pipeline.as_ref().and_then(|p| p.template_id.clone()) else {
+        return;
+    };
+    let shadows = match pipelines::list_shadow_templates(pool, &template_id).await {
+        Ok(shadows) => shadows,
+        Err(e) => {
+            error!("Failed to list shadows of template {}: {:?}", template_id, e);
+            return;
+        }
+    };
+
+    // Shadows only ever run on their own organization's tickets
+    for shadow in shadows.into_iter().filter(|s| s.organization.as_deref() == Some(ticket.organization.as_str())) {
+        let run = shadow_runs::create_shadow_run(
+            pool,
+            &CreateShadowRunRequest {
+                ticket_id: ticket_id.to_string(),
+                template_id: shadow.template_id.clone(),
+                shadow_of: template_id.clone(),
+            },
+        )
+        .await;
+        let run = match run {
+            Ok(run) => run,
+            Err(e) => {
+                error!("Failed to create shadow run of {} for ticket {}: {:?}", shadow.template_id, ticket_id, e);
+                continue;
+            }
+        };
+        info!(
+            "Starting shadow run {} of template {} for ticket {}",
+            run.shadow_run_id, run.template_id, ticket_id
+        );
+
+        let pool = pool.clone();
+        tokio::spawn(async move {
+            let (shadow_run_id, ticket_id) = (run.shadow_run_id.clone(), run.ticket_id.clone());
+            if let Err(e) = run_shadow(&pool, run).await {
+                error!("Shadow run {} failed for ticket {}: {:#}", shadow_run_id, ticket_id, e);
+            }
+        });
+    }
+}
+
+/// Run a shadow pipeline's steps in order until it finishes, fails or is halted
+async fn run_shadow(pool: &SqlitePool, run: ShadowRun) -> Result<()> {
+    let ticket = &tickets::get_ticket_by_id(pool, &run.ticket_id)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("Ticket not found: {}", run.ticket_id))?;
+    let mut pipeline = run.pipeline;
+    let mut previous_output: Option<String> = None;
+    let mut from_idx = 0;
+
+    let status = loop {
+        let Some(idx) = skip_unmet_steps(&mut pipeline, from_idx, previous_output.as_deref()) else {
+            break SHADOW_COMPLETED;
+        };
+        from_idx = idx + 1;
+        let step_id = pipeline.steps[idx].step_id.clone();
+
+        let agent_type = match shadow_agent(&pipeline.steps[idx]) {
+            Ok(agent_type) => agent_type,
+            Err(reason) => {
+                info!("Skipping shadow step {} on ticket {}: {}", step_id, ticket.ticket_id, reason);
+                pipelines::skip_step(&mut pipeline, &step_id, Some(serde_json::json!({ "skipped": reason })));
+                continue;
+            }
+        };
+        if crate::maintenance::is_enabled() {
+            warn!("Maintenance mode: halting shadow run {} before step {}", run.shadow_run_id, step_id);
+            break SHADOW_HALTED;
+        }
+        if let Err(exceeded) = crate::quotas::check_agent_run(pool, &ticket.organization).await {
+            warn!("Halting shadow run {} before step {}: {}", run.shadow_run_id, step_id, exceeded.message());
+            break SHADOW_HALTED;
+        }
+
+        pipelines::start_step(&mut pipeline, &step_id, &uuid::Uuid::new_v4().to_string());
+        shadow_runs::update_shadow_run(pool, &run.shadow_run_id, &pipeline, SHADOW_RUNNING).await?;
+
+        match execute_shadow_step(pool, ticket, &pipeline, idx, agent_type.clone(), previous_output.clone()).await {
+            Ok(agent_run) => {
+                crate::quotas::record_agent_run(pool, &ticket.organization, &agent_run.usage).await;
+                crate::agents::tool_audit::store_standalone_tool_calls(
+                    pool,
+                    Some(&agent_run.session_id),
+                    Some(&ticket.organization),
+                    &agent_type,
+                    &agent_run.tool_calls,
+                )
+                .await;
+                previous_output = agent_run.output_summary.clone();
+                let outputs = agent_run.output_summary.map(|s| serde_json::json!({ "summary": s }));
+                pipelines::complete_step(&mut pipeline, &step_id, outputs);
+                shadow_runs::update_shadow_run(pool, &run.shadow_run_id, &pipeline, SHADOW_RUNNING).await?;
+            }
+            Err(e) => {
+                warn!("Shadow step {} failed on ticket {}: {:#}", step_id, ticket.ticket_id, e);
+                pipelines::fail_step(&mut pipeline, &step_id, Some(serde_json::json!({ "error": format!("{:#}", e) })));
+                break SHADOW_FAILED;
+            }
+        }
+    };
+
+    shadow_runs::update_shadow_run(pool, &run.shadow_run_id, &pipeline, status).await?;
+    info!("Shadow run {} for ticket {} finished: {}", run.shadow_run_id, ticket.ticket_id, status);
+    Ok(())
+}
+
+/// Run one shadow step's agent with the context a real step would get
+async fn execute_shadow_step(
+    pool: &SqlitePool,
+    ticket: &Ticket,
+    pipeline: &Pipeline,
+    step_idx: usize,
+    agent_type: AgentType,
+    previous_output: Option<String>,
+) -> Result<crate::agents::AgentRun> {
+    let organization = &ticket.organization;
+    let step = &pipeline.steps[step_idx];
+    let step_inputs = resolve_step_inputs(ticket, pipeline, step_idx);
+
+    let working_dir = resolve_working_dir(pool, &agent_type, organization).await?;
+    let env = crate::secrets::agent_env(pool, organization, &agent_type).await;
+    let executor = AgentExecutor::new(working_dir)
+        .with_env(env)
+        .with_prompt_vars(step_input_vars(&step_inputs));
+
+    let context = TicketContext {
+        epic_id: ticket.epic_id.clone(),
+        slice_id: ticket.slice_id.clone(),
+        ticket_id: ticket.ticket_id.clone(),
+        title: ticket.title.clone(),
+        intent: ticket.description.clone().unwrap_or_default(),
+        checklist: crate::handlers::checklist_context(pool, &ticket.ticket_id).await,
+        research: crate::research_corpus::research_context(pool, &agent_type, ticket).await,
+        step_inputs: render_step_inputs(&step_inputs),
+        estimation: crate::estimates::estimation_context(pool, &agent_type, &ticket.ticket_id).await,
+        email_templates: crate::handlers::email_templates_context(pool, &agent_type, organization).await,
+        pinned_outputs: crate::handlers::pinned_outputs_context(pool, &ticket.ticket_id).await,
+    };
+
+    let context_pack = match step.context_pack_id.as_deref() {
+        Some(pack_id) => crate::context_packs::pack_context(pool, organization, pack_id).await,
+        None => None,
+    };
+    let step_previous = [context_pack, fan_in_context(pipeline, step_idx).or(previous_output)]
+        .into_iter()
+        .flatten()
+        .reduce(|acc, part| format!("{}\n\n{}", acc, part));
+
+    executor.execute(agent_type, context, step_previous, None, None, None).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flags_agents_that_can_change_things() {
+        assert!(!has_side_effects(&["Read", "Glob", "Grep", "WebSearch"]));
+        assert!(!has_side_effects(&["mcp__agentic-mcp__get_ticket", "mcp__agentic-mcp__list_tickets"]));
+        assert!(has_side_effects(&["Read", "Bash"]));
+        assert!(has_side_effects(&["mcp__agentic-mcp__update_ticket"]));
+        assert!(has_side_effects(&["mcp__agentic-mcp__*"]));
+        assert!(!has_side_effects(&[]));
+    }
+}
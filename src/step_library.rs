@@ -0,0 +1,181 @@
+//! Step library: reusable pipeline step definitions
+//!
+//! A library step is defined once per organization (agent type, execution
+//! type, the inputs it starts with and accepts, and its retry policy), under
+//! an id unique within that organization, and templates use it by reference
+//! instead of spelling the step out:
+//!
+//! ```yaml
+//! - step_id: review
+//!   library_step: { id: security-review, inputs: { focus: auth } }
+//! ```
+//!
+//! The library owns every field its definition covers; a template step adds
+//! the rest (condition, input_from, env, ...) and sets inputs through the
+//! reference. Templates store the expanded step, so attaching a pipeline
+//! works as before. Every edit makes a new version and re-expands the
+//! templates following the latest one; a reference with a `version` stays
+//! pinned to it.
+
+use std::collections::{HashMap, HashSet};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use ticketing_system::models::{ExecutionType, RetryPolicy};
+
+/// Step fields set from the library definition, replacing the template's
+const LIBRARY_FIELDS: &[&str] = &["agent_type", "execution_type", "name", "default_inputs", "retry"];
+
+const MAX_INPUTS: usize = 30;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InputSpec {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Every use of the step must give it a value, here or in the template
+    #[serde(default)]
+    pub required: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StepDefinition {
+    pub agent_type: String,
+    pub execution_type: ExecutionType,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Inputs (prompt variables) each use starts from
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub default_inputs: HashMap<String, Value>,
+    /// Inputs the step accepts; when empty any input may be set
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub inputs: Vec<InputSpec>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry: Option<RetryPolicy>,
+}
+
+fn is_input_name(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+impl StepDefinition {
+    fn declares(&self, input: &str) -> bool {
+        self.inputs.is_empty() || self.inputs.iter().any(|i| i.name == input)
+    }
+
+    /// Problems with the definition itself; the step fields are checked like
+    /// any template step's once expanded, see `sample_step`
+    pub fn validate(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        if self.inputs.len() > MAX_INPUTS {
+            problems.push(format!("a library step takes at most {} inputs", MAX_INPUTS));
+        }
+        let mut seen = HashSet::new();
+        for input in &self.inputs {
+            if !is_input_name(&input.name) {
+                problems.push(format!("input '{}': names may only use letters, digits and _", input.name));
+            }
+            if !seen.insert(input.name.as_str()) {
+                problems.push(format!("duplicate input '{}'", input.name));
+            }
+        }
+        let mut defaults: Vec<&String> = self.default_inputs.keys().filter(|k| !self.declares(k)).collect();
+        defaults.sort();
+        for key in defaults {
+            problems.push(format!("default_inputs: '{}' is not one of the step's inputs", key));
+        }
+        problems
+    }
+}
+
+/// Expand a template step using `definition`, with `overrides` from the
+/// step's reference on top of the library's default inputs. Returns the
+/// problems with the overrides; the step is expanded either way.
+pub fn expand_step(
+    step: &mut Map<String, Value>,
+    definition: &StepDefinition,
+    overrides: Option<&HashMap<String, Value>>,
+) -> Vec<String> {
+    let mut problems = Vec::new();
+    let mut inputs = definition.default_inputs.clone();
+    for (name, value) in overrides.into_iter().flatten() {
+        if !definition.declares(name) {
+            problems.push(format!("library step doesn't take input '{}'", name));
+        }
+        inputs.insert(name.clone(), value.clone());
+    }
+    for input in definition.inputs.iter().filter(|i| i.required) {
+        if inputs.get(&input.name).is_none_or(Value::is_null) {
+            problems.push(format!("missing required input '{}'", input.name));
+        }
+    }
+
+    for field in LIBRARY_FIELDS {
+        step.remove(*field);
+    }
+    if let Ok(Value::Object(fields)) = serde_json::to_value(definition) {
+        step.extend(fields.into_iter().filter(|(key, _)| LIBRARY_FIELDS.contains(&key.as_str())));
+    }
+    step.remove("default_inputs");
+    if !inputs.is_empty() {
+        step.insert("default_inputs".to_string(), serde_json::json!(inputs));
+    }
+    problems
+}
+
+/// The definition as a standalone template step, for validating it
+pub fn sample_step(library_step_id: &str, definition: &StepDefinition) -> Map<String, Value> {
+    let mut step = Map::new();
+    step.insert("step_id".to_string(), Value::String(library_step_id.to_string()));
+    expand_step(&mut step, definition, None);
+    step
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn expands_template_step_from_library() {
+        let definition = StepDefinition {
+            agent_type: "exa-research".to_string(),
+            execution_type: ExecutionType::Auto,
+            name: Some("Research with sources".to_string()),
+            default_inputs: HashMap::from([("depth".to_string(), json!("thorough"))]),
+            inputs: vec![
+                InputSpec { name: "depth".to_string(), description: None, required: false },
+                InputSpec { name: "topic".to_string(), description: None, required: true },
+            ],
+            retry: None,
+        };
+        assert!(definition.validate().is_empty());
+
+        let mut step = json!({
+            "step_id": "research",
+            "agent_type": "planning",
+            "retry": { "max_attempts": 2 },
+            "condition": "ticket.priority == high",
+            "library_step": { "id": "research-with-sources" },
+        });
+        let step = step.as_object_mut().unwrap();
+        let overrides = HashMap::from([("topic".to_string(), json!("{{ticket.title}}"))]);
+        assert!(expand_step(step, &definition, Some(&overrides)).is_empty());
+        assert_eq!(step["agent_type"], "exa-research");
+        assert_eq!(step["name"], "Research with sources");
+        assert_eq!(step["default_inputs"], json!({ "depth": "thorough", "topic": "{{ticket.title}}" }));
+        assert_eq!(step["condition"], "ticket.priority == high");
+        assert!(!step.contains_key("retry"));
+
+        let unknown = HashMap::from([("tone".to_string(), json!("formal"))]);
+        let problems = expand_step(step, &definition, Some(&unknown));
+        assert_eq!(problems, vec!["library step doesn't take input 'tone'", "missing required input 'topic'"]);
+
+        let bad = StepDefinition {
+            default_inputs: HashMap::from([("style".to_string(), json!("x"))]),
+            inputs: vec![InputSpec { name: "bad name".to_string(), description: None, required: false }],
+            ..definition
+        };
+        assert_eq!(bad.validate().len(), 2);
+    }
+}
@@ -0,0 +1,240 @@
+//! Encryption at rest for sensitive columns
+//!
+//! Email bodies, transcript entries and conversation messages are encrypted
+//! by the ticketing_system models with the `ColumnCipher` installed here at
+//! startup, and decrypted transparently when read. Copies this crate stores
+//! itself, the chunk text of the semantic search index and conversation
+//! memory, go through `seal` and `open` instead. The AES-256-GCM key is the
+//! base64 `STORAGE_ENCRYPTION_KEY`; without it nothing is encrypted. Values
+//! are stored as `enc:v1:<key id>:base64(nonce || ciphertext)`, and anything
+//! without that prefix is read as plaintext, so rows written before
+//! encryption was turned on stay readable.
+//!
+//! To rotate, make the new key `STORAGE_ENCRYPTION_KEY`, list the old one in
+//! `STORAGE_ENCRYPTION_OLD_KEYS` (comma separated), restart, then run
+//! `POST /api/admin/storage-encryption/rotate` (`flowstate admin
+//! rotate-storage-key`). That re-encrypts every value not already under the
+//! new key, plaintext included; afterwards the old key can be dropped. Run it
+//! once after first turning encryption on, too, so older index chunks and
+//! memories are sealed.
+//!
+//! Encrypted values can only be searched after they are read back: SQL
+//! matching on these columns (`LIKE`, full-text) no longer finds anything,
+//! and semantic search matches on the stored vectors alone.
+
+use std::sync::Arc;
+
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use anyhow::{anyhow, Context, Result};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use once_cell::sync::OnceCell;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use ticketing_system::crypto::ColumnCipher;
+use ticketing_system::SqlitePool;
+
+const KEY_ENV: &str = "STORAGE_ENCRYPTION_KEY";
+const OLD_KEYS_ENV: &str = "STORAGE_ENCRYPTION_OLD_KEYS";
+const PREFIX: &str = "enc:v1:";
+const NONCE_LEN: usize = 12;
+
+/// Columns this crate writes with `seal`, rotated alongside the models' columns
+const SEALED_COLUMNS: &[(&str, &str)] = &[("embedding_chunks", "content"), ("conversations", "memory")];
+
+static CIPHER: OnceCell<Arc<StorageCipher>> = OnceCell::new();
+
+struct KeyEntry {
+    id: String,
+    cipher: Aes256Gcm,
+}
+
+impl KeyEntry {
+    fn new(key: &[u8]) -> Result<Self> {
+        if key.len() != 32 {
+            return Err(anyhow!("Storage encryption keys must be 32 bytes"));
+        }
+        let id = Sha256::digest(key)[..4].iter().map(|b| format!("{:02x}", b)).collect();
+        Ok(Self { id, cipher: Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key)) })
+    }
+}
+
+/// The current key, which encrypts, and retired keys still accepted for reads
+pub struct StorageCipher {
+    current: KeyEntry,
+    retired: Vec<KeyEntry>,
+}
+
+fn decode_key(encoded: &str, name: &str) -> Result<Vec<u8>> {
+    BASE64.decode(encoded.trim()).with_context(|| format!("{} is not valid base64", name))
+}
+
+impl StorageCipher {
+    pub fn new(current: &[u8], retired: &[Vec<u8>]) -> Result<Self> {
+        Ok(Self {
+            current: KeyEntry::new(current)?,
+            retired: retired.iter().map(|key| KeyEntry::new(key)).collect::<Result<_>>()?,
+        })
+    }
+
+    /// The configured cipher, or `None` when no key is set
+    pub fn from_env() -> Result<Option<Self>> {
+        let Ok(current) = std::env::var(KEY_ENV) else {
+            return Ok(None);
+        };
+        let retired = std::env::var(OLD_KEYS_ENV)
+            .unwrap_or_default()
+            .split(',')
+            .filter(|k| !k.trim().is_empty())
+            .map(|k| decode_key(k, OLD_KEYS_ENV))
+            .collect::<Result<Vec<_>>>()?;
+        Self::new(&decode_key(&current, KEY_ENV)?, &retired).map(Some)
+    }
+
+    pub fn key_id(&self) -> &str {
+        &self.current.id
+    }
+
+    /// Whether a stored value is plaintext or under a retired key
+    pub fn needs_rotation(&self, stored: &str) -> bool {
+        stored
+            .strip_prefix(PREFIX)
+            .and_then(|rest| rest.split_once(':'))
+            .is_none_or(|(key_id, _)| key_id != self.current.id)
+    }
+}
+
+impl ColumnCipher for StorageCipher {
+    fn encrypt(&self, plaintext: &str) -> Result<String> {
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = self
+            .current
+            .cipher
+            .encrypt(&nonce, plaintext.as_bytes())
+            .map_err(|_| anyhow!("Failed to encrypt column value"))?;
+        let mut out = nonce.to_vec();
+        out.extend_from_slice(&ciphertext);
+        Ok(format!("{}{}:{}", PREFIX, self.current.id, BASE64.encode(out)))
+    }
+
+    fn decrypt(&self, stored: &str) -> Result<String> {
+        let Some(rest) = stored.strip_prefix(PREFIX) else {
+            return Ok(stored.to_string());
+        };
+        let (key_id, encoded) = rest.split_once(':').context("Encrypted value has no key id")?;
+        let key = std::iter::once(&self.current)
+            .chain(&self.retired)
+            .find(|k| k.id == key_id)
+            .with_context(|| format!("Value is encrypted with unknown key {}", key_id))?;
+        let bytes = BASE64.decode(encoded).context("Encrypted value is not valid base64")?;
+        if bytes.len() <= NONCE_LEN {
+            return Err(anyhow!("Encrypted value is truncated"));
+        }
+        let (nonce, ciphertext) = bytes.split_at(NONCE_LEN);
+        let plaintext = key
+            .cipher
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| anyhow!("Failed to decrypt value with key {}", key_id))?;
+        String::from_utf8(plaintext).context("Decrypted value is not UTF-8")
+    }
+}
+
+/// Install the configured cipher into the ticketing_system models. Returns
+/// its key id, or `None` when encryption is off.
+pub fn init() -> Result<Option<String>> {
+    let Some(cipher) = StorageCipher::from_env()? else {
+        return Ok(None);
+    };
+    let cipher = Arc::new(cipher);
+    let key_id = cipher.key_id().to_string();
+    ticketing_system::crypto::set_column_cipher(cipher.clone());
+    let _ = CIPHER.set(cipher);
+    Ok(Some(key_id))
+}
+
+/// Encrypt a value this crate stores itself. Returned as is when encryption is off.
+pub fn seal(plaintext: &str) -> Result<String> {
+    match CIPHER.get() {
+        Some(cipher) => cipher.encrypt(plaintext),
+        None => Ok(plaintext.to_string()),
+    }
+}
+
+/// Read a value stored with `seal`; plaintext passes through
+pub fn open(stored: &str) -> Result<String> {
+    match CIPHER.get() {
+        Some(cipher) => cipher.decrypt(stored),
+        None if stored.starts_with(PREFIX) => Err(anyhow!("{} is not set to read an encrypted value", KEY_ENV)),
+        None => Ok(stored.to_string()),
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct RotatedColumn {
+    pub table: String,
+    pub column: String,
+    pub rewritten: u64,
+    /// Values no configured key could decrypt, left as they were
+    pub failed: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RotationReport {
+    pub key_id: String,
+    pub columns: Vec<RotatedColumn>,
+}
+
+/// Re-encrypt every sensitive value not already under the current key
+pub async fn rotate(db: &SqlitePool) -> Result<RotationReport> {
+    let cipher = CIPHER.get().with_context(|| format!("{} is not set", KEY_ENV))?.clone();
+    let reencrypt = |stored: &str| {
+        if !cipher.needs_rotation(stored) {
+            return Ok(None);
+        }
+        let plaintext = cipher.decrypt(stored)?;
+        cipher.encrypt(&plaintext).map(Some)
+    };
+    let mut rewrites = ticketing_system::crypto::rewrite_encrypted_columns(db, &reencrypt).await?;
+    rewrites.extend(ticketing_system::crypto::rewrite_columns(db, SEALED_COLUMNS, &reencrypt).await?);
+
+    let columns: Vec<RotatedColumn> = rewrites
+        .into_iter()
+        .map(|r| RotatedColumn { table: r.table, column: r.column, rewritten: r.rewritten, failed: r.failed })
+        .collect();
+    for column in &columns {
+        tracing::info!(
+            "Re-encrypted {} value(s) in {}.{} under key {} ({} failed)",
+            column.rewritten,
+            column.table,
+            column.column,
+            cipher.key_id(),
+            column.failed
+        );
+    }
+    Ok(RotationReport { key_id: cipher.key_id().to_string(), columns })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encrypts_and_rotates_column_values() {
+        let old = StorageCipher::new(&[1u8; 32], &[]).unwrap();
+        let stored = old.encrypt("Quarterly numbers attached").unwrap();
+        assert!(stored.starts_with(&format!("{}{}:", PREFIX, old.key_id())));
+        assert_eq!(old.decrypt(&stored).unwrap(), "Quarterly numbers attached");
+        assert_eq!(old.decrypt("written before encryption").unwrap(), "written before encryption");
+        assert!(!old.needs_rotation(&stored));
+
+        let new = StorageCipher::new(&[2u8; 32], &[vec![1u8; 32]]).unwrap();
+        assert!(new.needs_rotation(&stored));
+        assert!(new.needs_rotation("written before encryption"));
+        assert_eq!(new.decrypt(&stored).unwrap(), "Quarterly numbers attached");
+        assert!(!new.needs_rotation(&new.encrypt("x").unwrap()));
+
+        let stranger = StorageCipher::new(&[3u8; 32], &[]).unwrap();
+        assert!(stranger.decrypt(&stored).is_err());
+        assert!(StorageCipher::new(&[1u8; 16], &[]).is_err());
+    }
+}
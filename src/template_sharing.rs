@@ -0,0 +1,180 @@
+//! Pipeline templates shared across organizations
+//!
+//! A template belongs to the organization that created it. Marking it shared
+//! (`PUT /api/pipeline-templates/:id/sharing`) lets every organization list
+//! and attach it; templates with no organization are global and always were.
+//! Listing templates for an organization merges its own with the shared and
+//! global ones, each tagged with where it comes from.
+//!
+//! An organization using a template it doesn't own can't edit it, but can
+//! override step inputs for itself (`/api/pipeline-templates/:id/overrides`,
+//! same shape as `step_inputs` when attaching). Overrides sit between the
+//! template's default inputs and the `step_inputs` of the attach request, and
+//! apply whenever a pipeline is attached from the template here: setting or
+//! running a ticket's pipeline and epic pipelines.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+use serde_json::Value;
+use ticketing_system::{models::Pipeline, pipelines, template_overrides, tickets, SqlitePool};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TemplateSource {
+    /// Belongs to the organization listing it
+    Organization,
+    /// Another organization's template, shared with everyone
+    Shared,
+    /// Has no organization
+    Global,
+}
+
+/// Where a template in a listing comes from
+#[derive(Debug, Clone, Serialize)]
+pub struct TemplateProvenance {
+    pub source: TemplateSource,
+    /// The organization that owns and edits the template
+    pub owner_organization: Option<String>,
+    /// The listing organization has step input overrides for it
+    pub has_overrides: bool,
+}
+
+/// Where a template comes from, seen from `organization`; `None` when it
+/// isn't available there
+pub fn template_source(owner: Option<&str>, shared: bool, organization: &str) -> Option<TemplateSource> {
+    match owner {
+        None => Some(TemplateSource::Global),
+        Some(owner) if owner == organization => Some(TemplateSource::Organization),
+        Some(_) if shared => Some(TemplateSource::Shared),
+        Some(_) => None,
+    }
+}
+
+/// Step inputs to attach with: `overrides` with the request's inputs on top,
+/// key by key within each step. `None` when there are none.
+pub fn merge_step_inputs(
+    overrides: Option<&HashMap<String, Value>>,
+    request: Option<&HashMap<String, Value>>,
+) -> Option<HashMap<String, Value>> {
+    let mut merged = overrides.cloned().unwrap_or_default();
+    for (step_id, inputs) in request.into_iter().flatten() {
+        match (merged.get_mut(step_id).and_then(Value::as_object_mut), inputs.as_object()) {
+            (Some(base), Some(inputs)) => base.extend(inputs.iter().map(|(k, v)| (k.clone(), v.clone()))),
+            _ => {
+                merged.insert(step_id.clone(), inputs.clone());
+            }
+        }
+    }
+    (!merged.is_empty()).then_some(merged)
+}
+
+lazy_static::lazy_static! {
+    /// Tickets a pipeline is being attached to and started on
+    static ref ATTACHING: Mutex<HashSet<String>> = Mutex::new(HashSet::new());
+}
+
+/// Held while a pipeline is attached to a ticket and its first step started
+pub struct AttachGuard {
+    ticket_id: String,
+}
+
+impl Drop for AttachGuard {
+    fn drop(&mut self) {
+        ATTACHING.lock().unwrap().remove(&self.ticket_id);
+    }
+}
+
+/// Take the ticket's attach lock, or `None` while another attach to it is in
+/// flight (by hand or from an epic pipeline)
+pub fn lock_attach(ticket_id: &str) -> Option<AttachGuard> {
+    ATTACHING
+        .lock()
+        .unwrap()
+        .insert(ticket_id.to_string())
+        .then(|| AttachGuard { ticket_id: ticket_id.to_string() })
+}
+
+/// Put back the pipeline a ticket had before an attach whose first step
+/// failed to start. The ticket's pipeline is only replaced if it is still the
+/// one read here, in one transaction, so a concurrent change is never
+/// overwritten. Returns whether it was restored.
+pub async fn restore_pipeline(db: &SqlitePool, ticket_id: &str, previous: Option<&Pipeline>) -> Result<bool> {
+    let current = tickets::get_ticket_by_id(db, ticket_id).await?.and_then(|t| t.pipeline);
+    Ok(tickets::replace_ticket_pipeline(db, ticket_id, current.as_ref(), previous).await?)
+}
+
+/// Fails as not found unless `organization` may attach the template
+fn check_attachable(template_id: &str, owner: Option<&str>, shared: bool, organization: &str) -> Result<()> {
+    match template_source(owner, shared, organization) {
+        Some(_) => Ok(()),
+        None => Err(anyhow!("Template {} not found", template_id)),
+    }
+}
+
+/// Attach a template to a ticket, applying the organization's overrides.
+/// Another organization's template that isn't shared is reported as not found.
+pub async fn attach_template(
+    db: &SqlitePool,
+    ticket_id: &str,
+    organization: &str,
+    template_id: &str,
+    step_inputs: Option<&HashMap<String, Value>>,
+) -> Result<Pipeline> {
+    let template = pipelines::get_template(db, template_id)
+        .await?
+        .ok_or_else(|| anyhow!("Template {} not found", template_id))?;
+    check_attachable(template_id, template.organization.as_deref(), template.shared, organization)?;
+    let overrides = template_overrides::get_overrides(db, template_id, organization).await?;
+    let step_inputs = merge_step_inputs(overrides.as_ref().map(|o| &o.step_inputs), step_inputs);
+    Ok(tickets::attach_pipeline_from_template(db, ticket_id, template_id, step_inputs.as_ref()).await?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn sources_and_merged_step_inputs() {
+        assert_eq!(template_source(None, false, "acme"), Some(TemplateSource::Global));
+        assert_eq!(template_source(Some("acme"), false, "acme"), Some(TemplateSource::Organization));
+        assert_eq!(template_source(Some("globex"), true, "acme"), Some(TemplateSource::Shared));
+        assert_eq!(template_source(Some("globex"), false, "acme"), None);
+
+        let overrides = HashMap::from([
+            ("review".to_string(), json!({"focus": "auth", "depth": "full"})),
+            ("build".to_string(), json!({"target": "release"})),
+        ]);
+        let request = HashMap::from([
+            ("review".to_string(), json!({"focus": "billing"})),
+            ("deploy".to_string(), json!({"env": "staging"})),
+        ]);
+        let merged = merge_step_inputs(Some(&overrides), Some(&request)).unwrap();
+        assert_eq!(merged["review"], json!({"focus": "billing", "depth": "full"}));
+        assert_eq!(merged["build"], json!({"target": "release"}));
+        assert_eq!(merged["deploy"], json!({"env": "staging"}));
+        assert_eq!(merge_step_inputs(None, None), None);
+    }
+
+    #[test]
+    fn foreign_unshared_templates_are_not_attachable() {
+        let err = check_attachable("tpl-1", Some("globex"), false, "acme").unwrap_err();
+        assert!(err.to_string().contains("not found"));
+        assert!(check_attachable("tpl-1", Some("globex"), true, "acme").is_ok());
+    }
+
+    #[test]
+    fn one_attach_per_ticket_at_a_time() {
+        let ticket_id = format!("test-attach-{}", uuid::Uuid::new_v4());
+        let guard = lock_attach(&ticket_id);
+        assert!(guard.is_some());
+        assert!(lock_attach(&ticket_id).is_none());
+        drop(guard);
+        assert!(lock_attach(&ticket_id).is_some());
+        assert!(check_attachable("tpl-1", Some("acme"), false, "acme").is_ok());
+        assert!(check_attachable("tpl-1", None, false, "acme").is_ok());
+    }
+}
@@ -0,0 +1,321 @@
+//! Tenancy middleware - resolves the request's organization
+//!
+//! Runs after `require_auth`. The organization comes from the `X-Organization`
+//! header, or the user's first organization when the header is absent, and is
+//! checked against the user's memberships before being added to request
+//! extensions as `Organization`. Users without any memberships may use no
+//! organization; they join one through an invitation. Guests are pinned to
+//! the organization that invited them.
+//!
+//! `authorize_path_resources` then checks organization-owned resources named
+//! in the path (tickets, agent runs, pipeline and email templates,
+//! conversations, research docs, context packs and epic pipelines) against the
+//! organization that owns them. Anything else a handler loads by id - ids in
+//! the request body or query, and resources owned by a user or mailbox rather
+//! than an organization - is for the handler to authorize, loading it through
+//! a helper that checks ownership (e.g. `load_conversation`) rather than a bare
+//! getter.
+//!
+//! Members are `admin`s or `member`s of an organization. Admins invite people
+//! to it, and admins of the default organization administer the instance
+//! (`/api/admin/*`, global pipeline templates).
+
+use std::sync::Arc;
+
+use anyhow::Result;
+use axum::{
+    extract::{MatchedPath, RawPathParams, Request, State},
+    http::StatusCode,
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde_json::json;
+
+use ticketing_system::{
+    agent_runs, context_packs, conversations, email_templates, epic_pipelines, guests, organizations, pipelines,
+    research_docs, tickets, GuestAccess, SqlitePool, User,
+};
+
+use crate::invitations::ROLE_ADMIN;
+use crate::template_sharing::template_source;
+
+pub const ORGANIZATION_HEADER: &str = "X-Organization";
+
+/// The first organization of a fresh instance, see `bootstrap_memberships`.
+/// Its admins administer the instance.
+const DEFAULT_ORGANIZATION: &str = "telemetryops";
+
+const MAX_ORGANIZATION_LEN: usize = 64;
+
+/// The organization a request acts on, resolved and authorized by `resolve_organization`
+#[derive(Debug, Clone)]
+pub struct Organization {
+    pub name: String,
+    /// Organizations the user belongs to
+    allowed: Vec<String>,
+    /// Organizations the user is an admin of
+    admin_of: Vec<String>,
+}
+
+impl Organization {
+    /// Check an organization given explicitly (query or body) against the user's memberships
+    pub fn authorize(&self, organization: &str) -> Result<(), (StatusCode, String)> {
+        if self.allowed.iter().any(|o| o == organization) {
+            return Ok(());
+        }
+        Err((StatusCode::FORBIDDEN, format!("You are not a member of organization '{}'", organization)))
+    }
+
+    /// Check the user is an admin of an organization
+    pub fn require_admin(&self, organization: &str) -> Result<(), (StatusCode, String)> {
+        self.authorize(organization)?;
+        if self.admin_of.iter().any(|o| o == organization) {
+            return Ok(());
+        }
+        Err((StatusCode::FORBIDDEN, format!("Only admins of organization '{}' may do this", organization)))
+    }
+
+    /// Check the user administers the instance
+    pub fn require_instance_admin(&self) -> Result<(), (StatusCode, String)> {
+        if self.admin_of.iter().any(|o| o == DEFAULT_ORGANIZATION) {
+            return Ok(());
+        }
+        Err((StatusCode::FORBIDDEN, "Only instance admins may do this".to_string()))
+    }
+
+    /// Organizations the user may act on
+    pub fn accessible(&self) -> &[String] {
+        &self.allowed
+    }
+
+    /// The organization named explicitly in a query or body, once authorized,
+    /// otherwise the request's organization
+    pub fn resolve(self, explicit: Option<String>) -> Result<String, (StatusCode, String)> {
+        match explicit {
+            Some(organization) => {
+                self.authorize(&organization)?;
+                Ok(organization)
+            }
+            None => Ok(self.name),
+        }
+    }
+}
+
+fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= MAX_ORGANIZATION_LEN
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+fn reject(status: StatusCode, message: String) -> Response {
+    (status, Json(json!({ "error": message }))).into_response()
+}
+
+/// Middleware resolving and authorizing the organization for authenticated requests.
+/// 400 for a malformed header, 403 for organizations the user doesn't belong to.
+pub async fn resolve_organization(
+    State(pool): State<Arc<SqlitePool>>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let Some(user) = request.extensions().get::<User>().cloned() else {
+        return reject(StatusCode::UNAUTHORIZED, "Authentication required".to_string());
+    };
+
+    if let Some(access) = request.extensions().get::<GuestAccess>() {
+        let name = access.organization.clone();
+        request.extensions_mut().insert(Organization { allowed: vec![name.clone()], admin_of: Vec::new(), name });
+        return next.run(request).await;
+    }
+
+    let memberships = match organizations::list_user_memberships(&pool, &user.user_id).await {
+        Ok(memberships) => memberships,
+        Err(e) => {
+            tracing::error!("Tenancy middleware error: {:?}", e);
+            return reject(StatusCode::INTERNAL_SERVER_ERROR, "Organization check failed".to_string());
+        }
+    };
+    if memberships.is_empty() {
+        return reject(
+            StatusCode::FORBIDDEN,
+            "You are not a member of any organization; ask an admin for an invitation".to_string(),
+        );
+    }
+
+    let name = match request.headers().get(ORGANIZATION_HEADER) {
+        Some(value) => match value.to_str().map(str::trim) {
+            Ok(name) if is_valid_name(name) => name.to_string(),
+            _ => return reject(StatusCode::BAD_REQUEST, "Malformed X-Organization header".to_string()),
+        },
+        None => memberships[0].organization.clone(),
+    };
+
+    let organization = Organization {
+        name,
+        admin_of: memberships.iter().filter(|m| m.role == ROLE_ADMIN).map(|m| m.organization.clone()).collect(),
+        allowed: memberships.into_iter().map(|m| m.organization).collect(),
+    };
+    if let Err((status, message)) = organization.authorize(&organization.name) {
+        return reject(status, message);
+    }
+
+    request.extensions_mut().insert(organization);
+    next.run(request).await
+}
+
+/// Who may act on a resource named in the path
+enum PathResourceOwner {
+    /// Members of the organization
+    Organization(String),
+    /// Instance admins
+    Instance,
+}
+
+/// The owner of a resource named in the path, `None` when anyone may act on
+/// it or it doesn't exist (the handler answers 404)
+async fn path_resource_owner(
+    pool: &SqlitePool,
+    route: &str,
+    param: &str,
+    value: &str,
+    organization: &Organization,
+    mutation: bool,
+) -> Result<Option<PathResourceOwner>> {
+    let owner = match param {
+        "ticket_id" | "target_ticket_id" => tickets::get_ticket_by_id(pool, value).await?.map(|t| t.organization),
+        "session_id" if route.starts_with("/api/agent-runs/") => {
+            let Some(run) = agent_runs::get_agent_run(pool, value).await? else {
+                return Ok(None);
+            };
+            tickets::get_ticket_by_id(pool, &run.ticket_id).await?.map(|t| t.organization)
+        }
+        "template_id" if route.starts_with("/api/pipeline-templates/") => {
+            let Some(template) = pipelines::get_template(pool, value).await? else {
+                return Ok(None);
+            };
+            // Shared and global templates may be read and used by everyone, only
+            // edited by the owner; instance admins edit the global ones
+            let editing = mutation && route == "/api/pipeline-templates/:template_id";
+            match template.organization {
+                None if editing => return Ok(Some(PathResourceOwner::Instance)),
+                None => None,
+                Some(owner) if editing => Some(owner),
+                Some(owner) => template_source(Some(&owner), template.shared, &organization.name)
+                    .is_none()
+                    .then_some(owner),
+            }
+        }
+        "template_id" if route.starts_with("/api/email-templates/") => {
+            email_templates::get_template(pool, value).await?.map(|t| t.organization)
+        }
+        // Conversations without an organization predate tenancy and stay open
+        "id" | "conv_id" if route.starts_with("/api/conversations/") => {
+            conversations::get_conversation(pool, value, false).await?.and_then(|c| c.organization)
+        }
+        "doc_id" => research_docs::get_doc(pool, value).await?.map(|d| d.organization),
+        "pack_id" => context_packs::get_pack(pool, value).await?.map(|p| p.organization),
+        "epic_pipeline_id" => epic_pipelines::get_epic_pipeline(pool, value).await?.map(|p| p.organization),
+        _ => None,
+    };
+    Ok(owner.map(PathResourceOwner::Organization))
+}
+
+/// Middleware authorizing the organization-owned resources named in the path
+/// (see the module doc) against the organization that owns them. 403 when the
+/// user isn't a member of it; resources that don't exist are left to the
+/// handler.
+pub async fn authorize_path_resources(
+    State(pool): State<Arc<SqlitePool>>,
+    matched: Option<MatchedPath>,
+    params: Option<RawPathParams>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let (Some(matched), Some(params)) = (matched, params) else {
+        return next.run(request).await;
+    };
+    let Some(organization) = request.extensions().get::<Organization>().cloned() else {
+        return reject(StatusCode::UNAUTHORIZED, "Authentication required".to_string());
+    };
+    let mutation = !matches!(*request.method(), axum::http::Method::GET | axum::http::Method::HEAD);
+    for (param, value) in &params {
+        let allowed = match path_resource_owner(&pool, matched.as_str(), param, value, &organization, mutation).await {
+            Ok(Some(PathResourceOwner::Organization(owner))) => organization.authorize(&owner),
+            Ok(Some(PathResourceOwner::Instance)) => organization.require_instance_admin(),
+            Ok(None) => Ok(()),
+            Err(e) => {
+                tracing::error!("Tenancy middleware error: {:?}", e);
+                return reject(StatusCode::INTERNAL_SERVER_ERROR, "Organization check failed".to_string());
+            }
+        };
+        if let Err((status, message)) = allowed {
+            return reject(status, message);
+        }
+    }
+    next.run(request).await
+}
+
+/// Give an instance without any memberships its first members. Users who
+/// predate tenancy could use every organization, so each becomes an admin of
+/// all existing ones and of the default organization, which makes them
+/// instance admins too.
+/// Does nothing once anyone is a member; later users join by invitation.
+pub async fn bootstrap_memberships(pool: &SqlitePool) -> Result<()> {
+    if organizations::count_members(pool).await? > 0 {
+        return Ok(());
+    }
+    let mut existing = organizations::list_organizations(pool).await?;
+    if !existing.iter().any(|o| o == DEFAULT_ORGANIZATION) {
+        existing.push(DEFAULT_ORGANIZATION.to_string());
+    }
+    for user in ticketing_system::users::list_users(pool).await? {
+        if guests::get_guest(pool, &user.user_id).await?.is_some() {
+            continue;
+        }
+        for organization in &existing {
+            organizations::add_member(pool, organization, &user.user_id, ROLE_ADMIN).await?;
+        }
+        tracing::info!("Bootstrapped {} as admin of {}", user.user_id, existing.join(", "));
+    }
+    Ok(())
+}
+
+/// Whether the user may act for something owned by `owner`: members of the
+/// organization may, and instance admins for things without an organization
+pub async fn has_standing(pool: &SqlitePool, user_id: &str, owner: Option<&str>) -> Result<bool> {
+    let memberships = organizations::list_user_memberships(pool, user_id).await?;
+    Ok(match owner {
+        Some(owner) => memberships.iter().any(|m| m.organization == owner),
+        None => memberships.iter().any(|m| m.organization == DEFAULT_ORGANIZATION && m.role == ROLE_ADMIN),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn authorizes_against_memberships() {
+        let member = Organization { name: "acme".to_string(), allowed: vec!["acme".to_string()], admin_of: vec![] };
+        assert!(member.authorize("acme").is_ok());
+        assert_eq!(member.authorize("globex").unwrap_err().0, StatusCode::FORBIDDEN);
+        assert_eq!(member.require_admin("acme").unwrap_err().0, StatusCode::FORBIDDEN);
+        assert!(member.require_instance_admin().is_err());
+
+        let admin = Organization {
+            name: DEFAULT_ORGANIZATION.to_string(),
+            allowed: vec![DEFAULT_ORGANIZATION.to_string()],
+            admin_of: vec![DEFAULT_ORGANIZATION.to_string()],
+        };
+        assert!(admin.require_admin(DEFAULT_ORGANIZATION).is_ok());
+        assert!(admin.require_instance_admin().is_ok());
+
+        let no_memberships = Organization { name: "acme".to_string(), allowed: vec![], admin_of: vec![] };
+        assert_eq!(no_memberships.authorize("acme").unwrap_err().0, StatusCode::FORBIDDEN);
+
+        assert!(is_valid_name("telemetry-ops_2"));
+        assert!(!is_valid_name("acme; drop"));
+        assert!(!is_valid_name(""));
+    }
+}
@@ -0,0 +1,184 @@
+//! Per-user event stream
+//!
+//! Users follow the tickets and conversations they take part in: creating or
+//! commenting on a ticket, acting on its pipeline steps, creating or posting
+//! in a conversation. Events about a ticket or conversation are published
+//! once with its followers resolved up front (minus whoever caused it), and
+//! each `GET /api/users/me/subscribe` connection only ever receives the
+//! events addressed to its user, unlike the organization-wide data stream.
+//! Events about the user's own account, like sign-in alerts, go straight to
+//! them with `send_to_user`.
+
+use std::sync::Arc;
+
+use serde::Serialize;
+use tokio::sync::broadcast::{self, error::RecvError};
+use ticketing_system::SqlitePool;
+
+use crate::deadlines::DeadlineEvent;
+
+/// What a user can follow
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Subject {
+    Ticket,
+    Conversation,
+}
+
+impl Subject {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Subject::Ticket => "ticket",
+            Subject::Conversation => "conversation",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum UserEvent {
+    /// A followed ticket's status changed
+    TicketStatus { ticket_id: String, organization: String, status: String },
+    /// Someone commented on a followed ticket
+    TicketComment { ticket_id: String, author: String },
+    /// A pipeline step on a followed ticket needs approval before it runs
+    StepAwaitingApproval { ticket_id: String, step_id: String },
+    /// An agent run on a followed ticket is waiting on a tool approval
+    ToolApprovalRequired { ticket_id: String, session_id: String, request_id: String, tool_name: String },
+    /// A followed ticket became due soon or overdue
+    Deadline { deadline: DeadlineEvent },
+    /// A message was added to a followed conversation
+    ConversationMessage { conversation_id: String, message_id: String, role: String },
+    /// Sign-in activity on the user's own account worth a look, see `crate::login_security`
+    LoginAlert { kind: String, message: String, device: Option<String>, ip: Option<String> },
+}
+
+/// An event and the users it is for
+#[derive(Debug)]
+struct Addressed {
+    recipients: Vec<String>,
+    event: UserEvent,
+}
+
+/// Fans events out to per-user subscriptions, filtering by recipient before
+/// anything reaches a connection
+pub struct UserEventBroadcaster {
+    tx: broadcast::Sender<Arc<Addressed>>,
+}
+
+impl UserEventBroadcaster {
+    fn new() -> Self {
+        Self { tx: broadcast::channel(256).0 }
+    }
+
+    fn send(&self, recipients: Vec<String>, event: UserEvent) {
+        if recipients.is_empty() {
+            return;
+        }
+        // No receivers just means nobody is connected
+        let _ = self.tx.send(Arc::new(Addressed { recipients, event }));
+    }
+
+    pub fn subscribe(&self, user_id: &str) -> UserSubscription {
+        UserSubscription { user_id: user_id.to_string(), rx: self.tx.subscribe() }
+    }
+}
+
+/// One user's view of the broadcaster
+pub struct UserSubscription {
+    user_id: String,
+    rx: broadcast::Receiver<Arc<Addressed>>,
+}
+
+impl UserSubscription {
+    /// Next event addressed to this user; `None` once the broadcaster is gone.
+    /// Events dropped while lagging are skipped.
+    pub async fn recv(&mut self) -> Option<UserEvent> {
+        loop {
+            match self.rx.recv().await {
+                Ok(addressed) if addressed.recipients.contains(&self.user_id) => return Some(addressed.event.clone()),
+                Ok(_) => {}
+                Err(RecvError::Lagged(skipped)) => {
+                    tracing::warn!("User event stream for {} lagged, skipped {} events", self.user_id, skipped);
+                }
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    }
+}
+
+lazy_static::lazy_static! {
+    pub static ref USER_EVENTS: UserEventBroadcaster = UserEventBroadcaster::new();
+}
+
+/// Send an event to one user only
+pub fn send_to_user(user_id: &str, event: UserEvent) {
+    USER_EVENTS.send(vec![user_id.to_string()], event);
+}
+
+/// Make `user_id` a follower. Failures are logged; following is best effort.
+pub async fn follow(db: &SqlitePool, user_id: &str, subject: Subject, subject_id: &str) {
+    if let Err(e) = ticketing_system::followers::follow(db, user_id, subject.as_str(), subject_id).await {
+        tracing::warn!("Failed to add {} as follower of {} {}: {}", user_id, subject.as_str(), subject_id, e);
+    }
+}
+
+/// Send an event to the followers of a ticket or conversation, except `actor`
+pub async fn publish(db: &SqlitePool, subject: Subject, subject_id: &str, actor: Option<&str>, event: UserEvent) {
+    let followers = match ticketing_system::followers::followers(db, subject.as_str(), subject_id).await {
+        Ok(followers) => followers,
+        Err(e) => {
+            tracing::warn!("Failed to load followers of {} {}: {}", subject.as_str(), subject_id, e);
+            return;
+        }
+    };
+    USER_EVENTS.send(recipients(followers, actor), event);
+}
+
+fn recipients(followers: Vec<String>, actor: Option<&str>) -> Vec<String> {
+    let mut recipients: Vec<String> = followers.into_iter().filter(|f| Some(f.as_str()) != actor).collect();
+    recipients.sort();
+    recipients.dedup();
+    recipients
+}
+
+/// A gated tool call is waiting on the run's session; tell the ticket's followers
+pub async fn tool_approval_required(db: &SqlitePool, session_id: &str, request_id: &str, tool_name: &str) {
+    let ticket_id = match ticketing_system::agent_runs::get_agent_run(db, session_id).await {
+        Ok(Some(run)) if !run.ticket_id.is_empty() => run.ticket_id,
+        _ => return,
+    };
+    let event = UserEvent::ToolApprovalRequired {
+        ticket_id: ticket_id.clone(),
+        session_id: session_id.to_string(),
+        request_id: request_id.to_string(),
+        tool_name: tool_name.to_string(),
+    };
+    publish(db, Subject::Ticket, &ticket_id, None, event).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn subscriptions_only_see_their_own_events() {
+        let broadcaster = UserEventBroadcaster::new();
+        let mut alice = broadcaster.subscribe("alice");
+        let mut bob = broadcaster.subscribe("bob");
+
+        let comment = |ticket_id: &str| UserEvent::TicketComment { ticket_id: ticket_id.to_string(), author: "carol".to_string() };
+        broadcaster.send(recipients(vec!["alice".to_string(), "carol".to_string()], Some("carol")), comment("T-1"));
+        broadcaster.send(vec!["bob".to_string()], comment("T-2"));
+
+        match alice.recv().await {
+            Some(UserEvent::TicketComment { ticket_id, .. }) => assert_eq!(ticket_id, "T-1"),
+            other => panic!("unexpected event {:?}", other),
+        }
+        match bob.recv().await {
+            Some(UserEvent::TicketComment { ticket_id, .. }) => assert_eq!(ticket_id, "T-2"),
+            other => panic!("unexpected event {:?}", other),
+        }
+        let nothing_else = tokio::time::timeout(std::time::Duration::from_millis(20), alice.recv()).await;
+        assert!(nothing_else.is_err());
+    }
+}